@@ -1,9 +1,18 @@
+use crate::{
+    helper::{EventOrdering, EventProcessors, EventRouting, RequestCosts, Warmups},
+    middleware::route_concurrency::RouteConcurrencyLimits,
+};
+use chrono::FixedOffset;
 use envconfig::Envconfig;
-use integrationos_domain::{cache::CacheConfig, environment::Environment};
+use integrationos_domain::{
+    cache::CacheConfig, environment::Environment, health::HealthThresholds,
+};
 use integrationos_domain::{database::DatabaseConfig, secrets::SecretsConfig};
+use integrationos_unified::{rate_limit::RateLimitConfig, unified::UnifiedCacheTTLs};
 use std::{
     fmt::{Display, Formatter, Result},
     net::SocketAddr,
+    time::Duration,
 };
 
 #[derive(Envconfig, Clone)]
@@ -22,18 +31,34 @@ pub struct ConnectionsConfig {
     pub access_key_whitelist_refresh_interval_secs: u64,
     #[envconfig(from = "CONNECTION_CACHE_TTL_SECS", default = "120")]
     pub connection_cache_ttl_secs: u64,
+    /// Serve the last-known cached connection, even if expired, when the database is unavailable
+    #[envconfig(from = "CONNECTION_STALE_FALLBACK_ENABLED", default = "false")]
+    pub connection_stale_fallback_enabled: bool,
+    /// Partitions the connection cache by tenant, each of them holding up to this many
+    /// connections. The cache is shared by all tenants when unset.
+    #[envconfig(from = "CONNECTION_CACHE_TENANT_CAPACITY")]
+    pub connection_cache_tenant_capacity: Option<u64>,
     #[envconfig(from = "ENGINEERING_ACCOUNT_ID", default = "engineering_account")]
     pub engineering_account_id: String,
     #[envconfig(from = "CONNECTION_DEFINITION_CACHE_TTL_SECS", default = "86400")]
     pub connection_definition_cache_ttl_secs: u64,
+    /// Header which, when present on an authorized read of a connection definition, bypasses
+    /// the cache like `Cache-Control: no-cache` does
+    #[envconfig(from = "CONNECTION_DEFINITION_CACHE_BYPASS_HEADER")]
+    pub connection_definition_cache_bypass_header: Option<String>,
     #[envconfig(from = "CONNECTION_OAUTH_DEFINITION_CACHE_TTL_SECS", default = "86400")]
     pub connection_oauth_definition_cache_ttl_secs: u64,
     #[envconfig(from = "CONNECTION_MODEL_SCHEMA_TTL_SECS", default = "86400")]
     pub connection_model_schema_cache_ttl_secs: u64,
     #[envconfig(from = "CONNECTION_MODEL_DEFINITION_CACHE_TTL_SECS", default = "86400")]
     pub connection_model_definition_cache_ttl_secs: u64,
-    #[envconfig(from = "SECRET_CACHE_TTL_SECS", default = "300")]
+    /// TTL of decrypted connection secrets, which must be shorter than the connection cache TTL
+    #[envconfig(from = "SECRET_CACHE_TTL_SECS", default = "60")]
     pub secret_cache_ttl_secs: u64,
+    /// TTL of the latest event schemas events are validated against. Registrations through this
+    /// instance invalidate them right away, those through other instances once they expire.
+    #[envconfig(from = "EVENT_SCHEMA_CACHE_TTL_SECS", default = "60")]
+    pub event_schema_cache_ttl_secs: u64,
     #[envconfig(
         from = "EVENT_ACCESS_PASSWORD",
         default = "32KFFT_i4UpkJmyPwY2TGzgHpxfXs7zS"
@@ -43,19 +68,100 @@ pub struct ConnectionsConfig {
     pub event_access_throughput: u64,
     #[envconfig(from = "EVENT_SAVE_BUFFER_SIZE", default = "2048")]
     pub event_save_buffer_size: usize,
+    /// Interval at which the number of events waiting in the save buffer is recorded
+    #[envconfig(from = "EVENT_BUFFER_DEPTH_SAMPLE_INTERVAL_SECS", default = "10")]
+    pub event_buffer_depth_sample_interval_secs: u64,
     #[envconfig(from = "EVENT_SAVE_TIMEOUT_SECS", default = "30")]
     pub event_save_timeout_secs: u64,
+    /// Buffers the events of each tenant separately, flushing them on the size and age
+    /// thresholds below, so that a tenant sending many events does not delay the others
+    #[envconfig(from = "EVENT_BUFFER_PER_TENANT", default = "false")]
+    pub event_buffer_per_tenant: bool,
+    #[envconfig(from = "TENANT_EVENT_BUFFER_SIZE", default = "256")]
+    pub tenant_event_buffer_size: usize,
+    /// Longest an event waits in the buffer of its tenant before being saved
+    #[envconfig(from = "TENANT_EVENT_BUFFER_MAX_AGE_MILLIS", default = "1000")]
+    pub tenant_event_buffer_max_age_millis: u64,
+    #[envconfig(from = "EVENT_SAVE_MAX_BATCH_SIZE", default = "1000")]
+    pub event_save_max_batch_size: usize,
+    #[envconfig(from = "EVENT_SAVE_MAX_BATCH_BYTES", default = "16777216")]
+    pub event_save_max_batch_bytes: usize,
+    /// Retries of a failed save of events, after which the events are moved to the dead letter
+    /// store. The wait before each retry doubles from `EVENT_SAVE_RETRY_BASE_DELAY_MILLIS`.
+    #[envconfig(from = "EVENT_SAVE_MAX_RETRIES", default = "3")]
+    pub event_save_max_retries: u32,
+    #[envconfig(from = "EVENT_SAVE_RETRY_BASE_DELAY_MILLIS", default = "100")]
+    pub event_save_retry_base_delay_millis: u64,
+    /// Dead letter events that arrived more than this many days ago are removed. They are kept
+    /// forever when unset.
+    #[envconfig(from = "DEAD_LETTER_RETENTION_DAYS")]
+    pub dead_letter_retention_days: Option<u64>,
+    /// An alert is raised when the dead letter events reach this number, none when unset
+    #[envconfig(from = "DEAD_LETTER_ALERT_THRESHOLD")]
+    pub dead_letter_alert_threshold: Option<u64>,
+    /// Interval at which dead letter events are swept and counted
+    #[envconfig(from = "DEAD_LETTER_CHECK_INTERVAL_SECS", default = "300")]
+    pub dead_letter_check_interval_secs: u64,
+    #[envconfig(from = "DEDICATED_EVENT_TENANTS", default = "")]
+    pub dedicated_event_tenants: EventRouting,
+    /// Filters and transforms applied to events before they are saved, see [`EventProcessors`]
+    #[envconfig(from = "EVENT_PROCESSORS", default = "[]")]
+    pub event_processors: EventProcessors,
+    /// Which events are persisted in the order they were sent, see [`EventOrdering`]
+    #[envconfig(from = "EVENT_ORDERING", default = "none")]
+    pub event_ordering: EventOrdering,
     #[envconfig(from = "METRIC_SAVE_CHANNEL_SIZE", default = "2048")]
     pub metric_save_channel_size: usize,
     #[envconfig(from = "METRIC_SYSTEM_ID", default = "IntegrationOS-Internal-System")]
     pub metric_system_id: String,
+    /// Daily and monthly metric buckets older than this many days are pruned, lifetime totals
+    /// are always kept. Buckets are kept forever when unset.
+    #[envconfig(from = "METRIC_RETENTION_DAYS")]
+    pub metric_retention_days: Option<u64>,
+    #[envconfig(from = "METRIC_PRUNE_INTERVAL_SECS", default = "3600")]
+    pub metric_prune_interval_secs: u64,
+    /// Metric records of single requests, looked up by correlation id, are deleted after this
+    /// many days
+    #[envconfig(from = "METRIC_RECORD_RETENTION_DAYS", default = "7")]
+    pub metric_record_retention_days: u64,
+    /// UTC offset of the business timezone, e.g. `+02:00`, whose days and months bound the
+    /// metric buckets. Raw timestamps are stored in UTC regardless.
+    #[envconfig(from = "METRIC_TIMEZONE", default = "+00:00")]
+    pub metric_timezone: FixedOffset,
+    /// Connections whose credentials expire within this window are notified
+    #[envconfig(from = "CREDENTIAL_EXPIRY_WINDOW_SECS", default = "604800")]
+    pub credential_expiry_window_secs: u64,
+    #[envconfig(from = "CREDENTIAL_EXPIRY_CHECK_INTERVAL_SECS", default = "3600")]
+    pub credential_expiry_check_interval_secs: u64,
     #[envconfig(from = "SEGMENT_WRITE_KEY")]
     pub segment_write_key: Option<String>,
+    /// Address `GET /metrics` is served on, apart from the API so that it can be bound to an
+    /// internal interface. Metrics aren't served when unset.
+    #[envconfig(from = "METRICS_ADDRESS")]
+    pub metrics_address: Option<SocketAddr>,
     // In the future, we will want to emit events for internal API actions
     #[envconfig(from = "EMIT_URL", default = "http://127.0.0.1:3000/emit/")]
     pub emit_url: String,
     #[envconfig(nested = true)]
     pub secrets_config: SecretsConfig,
+    /// Comma separated JSON pointers of the event body fields encrypted at rest, which are
+    /// decrypted when events are read
+    #[envconfig(from = "ENCRYPTED_EVENT_FIELDS")]
+    pub encrypted_event_fields: Option<String>,
+    /// Comma separated event types dropped at ingestion from startup, until the denylist is
+    /// replaced through the admin endpoint
+    #[envconfig(from = "EVENT_DENYLIST")]
+    pub event_denylist: Option<String>,
+    /// Comma separated common models the OpenAPI schema is generated for, all of them when
+    /// unset, along with the models they reference
+    #[envconfig(from = "OPENAPI_MODEL_ALLOWLIST")]
+    pub openapi_model_allowlist: Option<String>,
+    /// Comma separated common models left out of the OpenAPI schema
+    #[envconfig(from = "OPENAPI_MODEL_DENYLIST")]
+    pub openapi_model_denylist: Option<String>,
+    /// Version the OpenAPI schema is published under, as its `info.version`
+    #[envconfig(from = "OPENAPI_VERSION", default = "1.0.0")]
+    pub openapi_version: String,
     #[envconfig(
         from = "JWT_SECRET",
         default = "2thZ2UiOnsibmFtZSI6IlN0YXJ0dXBsa3NoamRma3NqZGhma3NqZGhma3NqZG5jhYtggfaP9ubmVjdGlvbnMiOjUwMDAwMCwibW9kdWxlcyI6NSwiZW5kcG9pbnRzIjo3b4e05e2-f050-401f-9822-44f43f71753c"
@@ -72,6 +178,90 @@ pub struct ConnectionsConfig {
     pub mock_llm: bool,
     #[envconfig(from = "HTTP_CLIENT_TIMEOUT_SECS", default = "30")]
     pub http_client_timeout_secs: u64,
+    #[envconfig(from = "MAX_DOWNSTREAM_RESPONSE_BYTES", default = "52428800")]
+    pub max_downstream_response_bytes: usize,
+    /// Largest passthrough request body buffered before being sent to the platform
+    #[envconfig(from = "PASSTHROUGH_MAX_BODY_BYTES", default = "2097152")]
+    pub passthrough_max_body_bytes: usize,
+    /// Largest passthrough request body streamed to the platform, for the model definitions
+    /// marked as streaming
+    #[envconfig(from = "PASSTHROUGH_MAX_STREAMING_BODY_BYTES", default = "1073741824")]
+    pub passthrough_max_streaming_body_bytes: usize,
+    /// Estimated size above which the responses of the read endpoints are streamed
+    #[envconfig(from = "READ_STREAMING_THRESHOLD_BYTES", default = "1048576")]
+    pub read_streaming_threshold_bytes: usize,
+    /// How long an idle connection to a platform is kept in the pool of the unified calls
+    #[envconfig(from = "DOWNSTREAM_POOL_IDLE_TIMEOUT_SECS", default = "90")]
+    pub downstream_pool_idle_timeout_secs: u64,
+    #[envconfig(from = "DOWNSTREAM_POOL_MAX_IDLE_PER_HOST", default = "32")]
+    pub downstream_pool_max_idle_per_host: usize,
+    /// Comma separated list of the inbound headers forwarded to the platform on unified calls,
+    /// all of them are forwarded when unset
+    #[envconfig(from = "UNIFIED_FORWARDED_HEADERS")]
+    pub unified_forwarded_headers: Option<String>,
+    /// Default retry policy of the unified calls, for the connection definitions without one.
+    /// A single attempt is made by default.
+    #[envconfig(from = "UNIFIED_RETRY_MAX_ATTEMPTS", default = "1")]
+    pub unified_retry_max_attempts: u32,
+    #[envconfig(from = "UNIFIED_RETRY_INITIAL_BACKOFF_MILLIS", default = "100")]
+    pub unified_retry_initial_backoff_millis: u64,
+    /// Comma separated list of the statuses of the platforms that are retried
+    #[envconfig(from = "UNIFIED_RETRY_STATUS_CODES", default = "429,502,503,504")]
+    pub unified_retry_status_codes: String,
+    /// Longest a unified call to a platform may take before being cancelled, unbounded when
+    /// unset
+    #[envconfig(from = "UNIFIED_CALL_TIMEOUT_MILLIS")]
+    pub unified_call_timeout_millis: Option<u64>,
+    /// Longest a platform may hold off the calls of a connection through `Retry-After`. Unified
+    /// calls asking to wait longer are answered with the 429 of the platform instead.
+    #[envconfig(from = "UNIFIED_MAX_RETRY_AFTER_SECS", default = "300")]
+    pub unified_max_retry_after_secs: u64,
+    /// OAuth access tokens expiring within this many seconds are refreshed before a unified call
+    #[envconfig(from = "OAUTH_REFRESH_SKEW_SECS", default = "60")]
+    pub oauth_refresh_skew_secs: u64,
+    /// Longest the PKCE code verifier of an authorize step is kept for its token exchange. Expired
+    /// verifiers are dropped from the store by a TTL index.
+    #[envconfig(from = "OAUTH_PKCE_VERIFIER_TTL_SECS", default = "600")]
+    pub oauth_pkce_verifier_ttl_secs: i64,
+    /// Requests per second accepted by the whole instance, across all tenants. Unlimited when
+    /// unset.
+    #[envconfig(from = "GLOBAL_RATE_LIMIT")]
+    pub global_rate_limit: Option<u64>,
+    /// Maximum number of middleware stages of a pipeline
+    #[envconfig(from = "PIPELINE_MAX_STAGES", default = "32")]
+    pub pipeline_max_stages: usize,
+    /// Maximum length of a chain of pipeline extractors depending on each other
+    #[envconfig(from = "PIPELINE_MAX_DEPTH", default = "8")]
+    pub pipeline_max_depth: usize,
+    /// Requests handled concurrently, the others wait in a queue of `REQUEST_QUEUE_SIZE` and are
+    /// shed once it is full. Unlimited when unset.
+    #[envconfig(from = "MAX_CONCURRENT_REQUESTS")]
+    pub max_concurrent_requests: Option<usize>,
+    #[envconfig(from = "REQUEST_QUEUE_SIZE", default = "1024")]
+    pub request_queue_size: usize,
+    #[envconfig(from = "REQUEST_QUEUE_TIMEOUT_MILLIS", default = "5000")]
+    pub request_queue_timeout_millis: u64,
+    /// Requests of a single client handled concurrently, on top of `MAX_CONCURRENT_REQUESTS`.
+    /// Unlimited when unset.
+    #[envconfig(from = "MAX_CONCURRENT_REQUESTS_PER_CLIENT")]
+    pub max_concurrent_requests_per_client: Option<usize>,
+    /// Requests of expensive routes handled concurrently, see [`RouteConcurrencyLimits`]
+    #[envconfig(from = "ROUTE_CONCURRENCY_LIMITS", default = "")]
+    pub route_concurrency_limits: RouteConcurrencyLimits,
+    /// Time given to each dependency checked by `/readyz` before it is reported as down
+    #[envconfig(from = "READINESS_CHECK_TIMEOUT_MILLIS", default = "1000")]
+    pub readiness_check_timeout_millis: u64,
+    /// Warmups, see [`Warmups`], run once the server started and before `/readyz` reports it
+    /// as ready
+    #[envconfig(from = "WARMUPS", default = "")]
+    pub warmups: Warmups,
+    /// Delay before a failed warmup is attempted again
+    #[envconfig(from = "WARMUP_RETRY_INTERVAL_MILLIS", default = "1000")]
+    pub warmup_retry_interval_millis: u64,
+    #[envconfig(from = "CONNECTION_HEALTH_FAILURE_THRESHOLD", default = "3")]
+    pub connection_health_failure_threshold: u32,
+    #[envconfig(from = "CONNECTION_HEALTH_SUCCESS_THRESHOLD", default = "2")]
+    pub connection_health_success_threshold: u32,
     #[envconfig(nested = true)]
     pub headers: Headers,
     #[envconfig(nested = true)]
@@ -80,10 +270,60 @@ pub struct ConnectionsConfig {
     pub cache_config: CacheConfig,
     #[envconfig(from = "RATE_LIMIT_ENABLED", default = "true")]
     pub rate_limit_enabled: bool,
+    #[envconfig(from = "CONNECTION_RATE_LIMIT_REFILL_SECS", default = "60")]
+    pub connection_rate_limit_refill_secs: u64,
+    /// Recent platform calls per connection the observability of connections is computed from
+    #[envconfig(from = "CONNECTION_CALL_STATS_WINDOW", default = "100")]
+    pub connection_call_stats_window: usize,
+    /// Consecutive failed platform calls of a connection, within the window below, after
+    /// which its calls are suspended for the cool down
+    #[envconfig(from = "CIRCUIT_BREAKER_FAILURE_THRESHOLD", default = "5")]
+    pub circuit_breaker_failure_threshold: u32,
+    #[envconfig(from = "CIRCUIT_BREAKER_WINDOW_SECS", default = "60")]
+    pub circuit_breaker_window_secs: u64,
+    #[envconfig(from = "CIRCUIT_BREAKER_COOL_DOWN_SECS", default = "30")]
+    pub circuit_breaker_cool_down_secs: u64,
+    /// Delay between two connection tests of a bulk connection validation
+    #[envconfig(from = "BULK_VALIDATION_INTERVAL_MILLIS", default = "200")]
+    pub bulk_validation_interval_millis: u64,
+    /// Delay between two rotations of a bulk credential rotation
+    #[envconfig(from = "BULK_ROTATION_INTERVAL_MILLIS", default = "200")]
+    pub bulk_rotation_interval_millis: u64,
+    #[envconfig(from = "REQUEST_COSTS", default = "")]
+    pub request_costs: RequestCosts,
+    #[envconfig(from = "MONTHLY_COST_BUDGET", default = "0")]
+    pub monthly_cost_budget: u64,
     #[envconfig(from = "ENVIRONMENT", default = "development")]
     pub environment: Environment,
 }
 
+impl ConnectionsConfig {
+    pub fn health_thresholds(&self) -> HealthThresholds {
+        HealthThresholds {
+            failures: self.connection_health_failure_threshold,
+            successes: self.connection_health_success_threshold,
+        }
+    }
+
+    pub fn cache_ttls(&self) -> UnifiedCacheTTLs {
+        UnifiedCacheTTLs {
+            connection_cache_ttl_secs: self.connection_cache_ttl_secs,
+            connection_definition_cache_ttl_secs: self.connection_definition_cache_ttl_secs,
+            connection_model_schema_cache_ttl_secs: self.connection_model_schema_cache_ttl_secs,
+            connection_model_definition_cache_ttl_secs: self
+                .connection_model_definition_cache_ttl_secs,
+            secret_cache_ttl_secs: self.secret_cache_ttl_secs,
+        }
+    }
+
+    pub fn rate_limit(&self) -> RateLimitConfig {
+        RateLimitConfig {
+            enabled: self.rate_limit_enabled,
+            window: Duration::from_secs(self.connection_rate_limit_refill_secs),
+        }
+    }
+}
+
 impl Display for ConnectionsConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         writeln!(f, "WORKER_THREADS: {:?}", self.worker_threads)?;
@@ -107,42 +347,312 @@ impl Display for ConnectionsConfig {
             self.event_access_throughput
         )?;
         writeln!(f, "EVENT_SAVE_BUFFER_SIZE: {}", self.event_save_buffer_size)?;
+        writeln!(
+            f,
+            "EVENT_BUFFER_DEPTH_SAMPLE_INTERVAL_SECS: {}",
+            self.event_buffer_depth_sample_interval_secs
+        )?;
         writeln!(
             f,
             "CONNECTION_CACHE_TTL_SECS: {}",
             self.connection_cache_ttl_secs
         )?;
+        writeln!(
+            f,
+            "CONNECTION_STALE_FALLBACK_ENABLED: {}",
+            self.connection_stale_fallback_enabled
+        )?;
+        writeln!(
+            f,
+            "CONNECTION_CACHE_TENANT_CAPACITY: {:?}",
+            self.connection_cache_tenant_capacity
+        )?;
         writeln!(
             f,
             "CONNECTION_DEFINITION_CACHE_TTL_SECS: {}",
             self.connection_definition_cache_ttl_secs
         )?;
+        writeln!(
+            f,
+            "CONNECTION_DEFINITION_CACHE_BYPASS_HEADER: {:?}",
+            self.connection_definition_cache_bypass_header
+        )?;
         writeln!(
             f,
             "CONNECTION_OAUTH_DEFINITION_CACHE_TTL_SECS: {}",
             self.connection_oauth_definition_cache_ttl_secs
         )?;
+        writeln!(
+            f,
+            "EVENT_SCHEMA_CACHE_TTL_SECS: {}",
+            self.event_schema_cache_ttl_secs
+        )?;
         writeln!(
             f,
             "EVENT_SAVE_TIMEOUT_SECS: {}",
             self.event_save_timeout_secs
         )?;
+        writeln!(
+            f,
+            "EVENT_BUFFER_PER_TENANT: {}",
+            self.event_buffer_per_tenant
+        )?;
+        writeln!(
+            f,
+            "TENANT_EVENT_BUFFER_SIZE: {}",
+            self.tenant_event_buffer_size
+        )?;
+        writeln!(
+            f,
+            "TENANT_EVENT_BUFFER_MAX_AGE_MILLIS: {}",
+            self.tenant_event_buffer_max_age_millis
+        )?;
+        writeln!(
+            f,
+            "EVENT_SAVE_MAX_BATCH_SIZE: {}",
+            self.event_save_max_batch_size
+        )?;
+        writeln!(
+            f,
+            "EVENT_SAVE_MAX_BATCH_BYTES: {}",
+            self.event_save_max_batch_bytes
+        )?;
+        writeln!(f, "EVENT_SAVE_MAX_RETRIES: {}", self.event_save_max_retries)?;
+        writeln!(
+            f,
+            "EVENT_SAVE_RETRY_BASE_DELAY_MILLIS: {}",
+            self.event_save_retry_base_delay_millis
+        )?;
+        writeln!(
+            f,
+            "DEAD_LETTER_RETENTION_DAYS: {:?}",
+            self.dead_letter_retention_days
+        )?;
+        writeln!(
+            f,
+            "DEAD_LETTER_ALERT_THRESHOLD: {:?}",
+            self.dead_letter_alert_threshold
+        )?;
+        writeln!(
+            f,
+            "DEAD_LETTER_CHECK_INTERVAL_SECS: {}",
+            self.dead_letter_check_interval_secs
+        )?;
+        writeln!(
+            f,
+            "DEDICATED_EVENT_TENANTS: {:?}",
+            self.dedicated_event_tenants
+        )?;
+        writeln!(f, "EVENT_PROCESSORS: {:?}", self.event_processors)?;
+        writeln!(f, "EVENT_ORDERING: {}", self.event_ordering)?;
         writeln!(
             f,
             "METRIC_SAVE_CHANNEL_SIZE: {}",
             self.metric_save_channel_size
         )?;
         writeln!(f, "METRIC_SYSTEM_ID: {}", self.metric_system_id)?;
+        writeln!(f, "METRIC_RETENTION_DAYS: {:?}", self.metric_retention_days)?;
+        writeln!(
+            f,
+            "METRIC_PRUNE_INTERVAL_SECS: {}",
+            self.metric_prune_interval_secs
+        )?;
+        writeln!(
+            f,
+            "METRIC_RECORD_RETENTION_DAYS: {}",
+            self.metric_record_retention_days
+        )?;
+        writeln!(f, "METRIC_TIMEZONE: {}", self.metric_timezone)?;
+        writeln!(
+            f,
+            "CREDENTIAL_EXPIRY_WINDOW_SECS: {}",
+            self.credential_expiry_window_secs
+        )?;
+        writeln!(
+            f,
+            "CREDENTIAL_EXPIRY_CHECK_INTERVAL_SECS: {}",
+            self.credential_expiry_check_interval_secs
+        )?;
         writeln!(f, "SEGMENT_WRITE_KEY: ***")?;
+        writeln!(f, "METRICS_ADDRESS: {:?}", self.metrics_address)?;
         writeln!(f, "EMIT_URL: {}", self.emit_url)?;
         writeln!(f, "JWT_SECRET: ***")?;
         write!(f, "{}", self.secrets_config)?;
+        writeln!(
+            f,
+            "ENCRYPTED_EVENT_FIELDS: {:?}",
+            self.encrypted_event_fields
+        )?;
+        writeln!(f, "EVENT_DENYLIST: {:?}", self.event_denylist)?;
+        writeln!(
+            f,
+            "OPENAPI_MODEL_ALLOWLIST: {:?}",
+            self.openapi_model_allowlist
+        )?;
+        writeln!(
+            f,
+            "OPENAPI_MODEL_DENYLIST: {:?}",
+            self.openapi_model_denylist
+        )?;
+        writeln!(f, "OPENAPI_VERSION: {}", self.openapi_version)?;
         writeln!(f, "API_VERSION: {}", self.api_version)?;
         writeln!(f, "MOCK_LLM: {}", self.mock_llm)?;
+        writeln!(
+            f,
+            "MAX_DOWNSTREAM_RESPONSE_BYTES: {}",
+            self.max_downstream_response_bytes
+        )?;
+        writeln!(
+            f,
+            "PASSTHROUGH_MAX_BODY_BYTES: {}",
+            self.passthrough_max_body_bytes
+        )?;
+        writeln!(
+            f,
+            "PASSTHROUGH_MAX_STREAMING_BODY_BYTES: {}",
+            self.passthrough_max_streaming_body_bytes
+        )?;
+        writeln!(
+            f,
+            "READ_STREAMING_THRESHOLD_BYTES: {}",
+            self.read_streaming_threshold_bytes
+        )?;
+        writeln!(
+            f,
+            "DOWNSTREAM_POOL_IDLE_TIMEOUT_SECS: {}",
+            self.downstream_pool_idle_timeout_secs
+        )?;
+        writeln!(
+            f,
+            "DOWNSTREAM_POOL_MAX_IDLE_PER_HOST: {}",
+            self.downstream_pool_max_idle_per_host
+        )?;
+        writeln!(
+            f,
+            "UNIFIED_FORWARDED_HEADERS: {:?}",
+            self.unified_forwarded_headers
+        )?;
+        writeln!(
+            f,
+            "UNIFIED_RETRY_MAX_ATTEMPTS: {}",
+            self.unified_retry_max_attempts
+        )?;
+        writeln!(
+            f,
+            "UNIFIED_RETRY_INITIAL_BACKOFF_MILLIS: {}",
+            self.unified_retry_initial_backoff_millis
+        )?;
+        writeln!(
+            f,
+            "UNIFIED_RETRY_STATUS_CODES: {}",
+            self.unified_retry_status_codes
+        )?;
+        writeln!(
+            f,
+            "UNIFIED_CALL_TIMEOUT_MILLIS: {:?}",
+            self.unified_call_timeout_millis
+        )?;
+        writeln!(
+            f,
+            "UNIFIED_MAX_RETRY_AFTER_SECS: {}",
+            self.unified_max_retry_after_secs
+        )?;
+        writeln!(
+            f,
+            "OAUTH_REFRESH_SKEW_SECS: {}",
+            self.oauth_refresh_skew_secs
+        )?;
+        writeln!(
+            f,
+            "OAUTH_PKCE_VERIFIER_TTL_SECS: {}",
+            self.oauth_pkce_verifier_ttl_secs
+        )?;
+        writeln!(f, "GLOBAL_RATE_LIMIT: {:?}", self.global_rate_limit)?;
+        writeln!(f, "PIPELINE_MAX_STAGES: {}", self.pipeline_max_stages)?;
+        writeln!(f, "PIPELINE_MAX_DEPTH: {}", self.pipeline_max_depth)?;
+        writeln!(
+            f,
+            "MAX_CONCURRENT_REQUESTS: {:?}",
+            self.max_concurrent_requests
+        )?;
+        writeln!(f, "REQUEST_QUEUE_SIZE: {}", self.request_queue_size)?;
+        writeln!(
+            f,
+            "REQUEST_QUEUE_TIMEOUT_MILLIS: {}",
+            self.request_queue_timeout_millis
+        )?;
+        writeln!(
+            f,
+            "MAX_CONCURRENT_REQUESTS_PER_CLIENT: {:?}",
+            self.max_concurrent_requests_per_client
+        )?;
+        writeln!(
+            f,
+            "ROUTE_CONCURRENCY_LIMITS: {:?}",
+            self.route_concurrency_limits
+        )?;
+        writeln!(
+            f,
+            "READINESS_CHECK_TIMEOUT_MILLIS: {}",
+            self.readiness_check_timeout_millis
+        )?;
+        writeln!(f, "WARMUPS: {:?}", self.warmups)?;
+        writeln!(
+            f,
+            "WARMUP_RETRY_INTERVAL_MILLIS: {}",
+            self.warmup_retry_interval_millis
+        )?;
+        writeln!(
+            f,
+            "CONNECTION_HEALTH_FAILURE_THRESHOLD: {}",
+            self.connection_health_failure_threshold
+        )?;
+        writeln!(
+            f,
+            "CONNECTION_HEALTH_SUCCESS_THRESHOLD: {}",
+            self.connection_health_success_threshold
+        )?;
         writeln!(f, "{}", self.headers)?;
         writeln!(f, "{}", self.db_config)?;
         writeln!(f, "{}", self.cache_config)?;
         writeln!(f, "RATE_LIMIT_ENABLED: {}", self.rate_limit_enabled)?;
+        writeln!(
+            f,
+            "CONNECTION_RATE_LIMIT_REFILL_SECS: {}",
+            self.connection_rate_limit_refill_secs
+        )?;
+        writeln!(
+            f,
+            "CONNECTION_CALL_STATS_WINDOW: {}",
+            self.connection_call_stats_window
+        )?;
+        writeln!(
+            f,
+            "CIRCUIT_BREAKER_FAILURE_THRESHOLD: {}",
+            self.circuit_breaker_failure_threshold
+        )?;
+        writeln!(
+            f,
+            "CIRCUIT_BREAKER_WINDOW_SECS: {}",
+            self.circuit_breaker_window_secs
+        )?;
+        writeln!(
+            f,
+            "CIRCUIT_BREAKER_COOL_DOWN_SECS: {}",
+            self.circuit_breaker_cool_down_secs
+        )?;
+        writeln!(
+            f,
+            "BULK_VALIDATION_INTERVAL_MILLIS: {}",
+            self.bulk_validation_interval_millis
+        )?;
+        writeln!(
+            f,
+            "BULK_ROTATION_INTERVAL_MILLIS: {}",
+            self.bulk_rotation_interval_millis
+        )?;
+        writeln!(f, "REQUEST_COSTS: {:?}", self.request_costs)?;
+        writeln!(f, "MONTHLY_COST_BUDGET: {}", self.monthly_cost_budget)?;
         writeln!(f, "ENVIRONMENT: {}", self.environment)
     }
 }
@@ -185,6 +695,23 @@ pub struct Headers {
         default = "x-integrationos-rate-limit-reset"
     )]
     pub rate_limit_reset: String,
+    #[envconfig(
+        from = "HEADER_STALE_CONNECTION",
+        default = "x-integrationos-stale-connection"
+    )]
+    pub stale_connection_header: String,
+    #[envconfig(
+        from = "HEADER_WEBHOOK_SIGNATURE",
+        default = "x-integrationos-webhook-signature"
+    )]
+    pub webhook_signature_header: String,
+    /// Id tying the events and metrics of a request together, generated when the caller
+    /// doesn't send one and returned in the response
+    #[envconfig(
+        from = "HEADER_CORRELATION_ID",
+        default = "x-integrationos-correlation-id"
+    )]
+    pub correlation_id_header: String,
 }
 
 impl Headers {
@@ -219,6 +746,64 @@ impl Display for Headers {
             "HEADER_RATE_LIMIT_REMAINING: {}",
             self.rate_limit_remaining
         )?;
-        writeln!(f, "HEADER_RATE_LIMIT_RESET: {}", self.rate_limit_reset)
+        writeln!(f, "HEADER_RATE_LIMIT_RESET: {}", self.rate_limit_reset)?;
+        writeln!(
+            f,
+            "HEADER_STALE_CONNECTION: {}",
+            self.stale_connection_header
+        )?;
+        writeln!(
+            f,
+            "HEADER_WEBHOOK_SIGNATURE: {}",
+            self.webhook_signature_header
+        )?;
+        writeln!(f, "HEADER_CORRELATION_ID: {}", self.correlation_id_header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::async_trait;
+    use integrationos_domain::{secret::Secret, IntegrationOSError, SecretExt};
+    use integrationos_unified::unified::UnifiedDestination;
+    use serde_json::Value;
+    use std::{collections::HashMap, sync::Arc};
+
+    struct SecretsClient;
+
+    #[async_trait]
+    impl SecretExt for SecretsClient {
+        async fn get(&self, _id: &str, _buildable_id: &str) -> Result<Secret, IntegrationOSError> {
+            unimplemented!()
+        }
+
+        async fn create(
+            &self,
+            _secret: &Value,
+            _buildable_id: &str,
+        ) -> Result<Secret, IntegrationOSError> {
+            unimplemented!()
+        }
+
+        async fn ping(&self) -> Result<(), IntegrationOSError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_config_builds_unified_destination() {
+        let config = ConnectionsConfig::init_from_hashmap(&HashMap::new())
+            .expect("Failed to load default config");
+
+        UnifiedDestination::new(
+            config.db_config.clone(),
+            config.cache_size,
+            Arc::new(SecretsClient),
+            config.cache_ttls(),
+            config.rate_limit(),
+        )
+        .await
+        .expect("Default cache TTLs should be accepted");
     }
 }
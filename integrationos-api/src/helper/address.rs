@@ -0,0 +1,157 @@
+use std::{
+    fmt::{Display, Formatter},
+    io::ErrorKind,
+    net::{IpAddr, SocketAddr},
+};
+use tokio::net::TcpListener;
+use tracing::warn;
+
+pub const ADDRESS_VAR: &str = "INTERNAL_SERVER_ADDRESS";
+/// Ports below this one can only be bound by privileged processes on unix
+const FIRST_UNPRIVILEGED_PORT: u16 = 1024;
+
+/// Why the server can't listen on its configured address, with what to do about it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressDiagnostic {
+    Malformed { address: String, reason: String },
+    InUse { address: SocketAddr },
+    Privileged { address: SocketAddr },
+    NotLocal { address: SocketAddr },
+    Other { address: SocketAddr, error: String },
+}
+
+impl Display for AddressDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed { address, reason } => write!(
+                f,
+                "{ADDRESS_VAR} `{address}` is not a valid socket address: {reason}. Expected an IP and a port, as in 0.0.0.0:3005"
+            ),
+            Self::InUse { address } => write!(
+                f,
+                "{address} is already in use by another process. Stop it or set {ADDRESS_VAR} to another port"
+            ),
+            Self::Privileged { address } => write!(
+                f,
+                "Port {} of {address} is privileged and this process may not bind it. Use a port of {FIRST_UNPRIVILEGED_PORT} or above, or grant the process CAP_NET_BIND_SERVICE",
+                address.port()
+            ),
+            Self::NotLocal { address } => write!(
+                f,
+                "{} of {address} is not an address of this host. Use one of its interfaces, or 0.0.0.0 to listen on all of them",
+                address.ip()
+            ),
+            Self::Other { address, error } => write!(f, "Could not listen on {address}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for AddressDiagnostic {}
+
+/// Parses the address the server listens on, telling what is wrong with it if it can't be
+pub fn parse_address(address: &str) -> Result<SocketAddr, AddressDiagnostic> {
+    if let Ok(address) = address.trim().parse() {
+        return Ok(address);
+    }
+
+    let malformed = |reason: &str| AddressDiagnostic::Malformed {
+        address: address.to_owned(),
+        reason: reason.to_owned(),
+    };
+    let Some((host, port)) = address.trim().rsplit_once(':') else {
+        return Err(malformed("the port is missing"));
+    };
+
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.parse::<IpAddr>().is_err() {
+        return Err(malformed(&format!(
+            "`{host}` is not an IP address, host names are not resolved"
+        )));
+    }
+    if port.parse::<u16>().is_err() {
+        return Err(malformed(&format!(
+            "`{port}` is not a port, ports range from 0 to 65535"
+        )));
+    }
+    Err(malformed(
+        "IPv6 addresses must be in brackets, as in [::1]:3005",
+    ))
+}
+
+/// Binds the listener of the server, telling why it can't be if so
+pub async fn bind_listener(address: SocketAddr) -> Result<TcpListener, AddressDiagnostic> {
+    if address.port() == 0 {
+        warn!("{ADDRESS_VAR} has port 0, the server listens on a random port");
+    } else if address.port() < FIRST_UNPRIVILEGED_PORT {
+        warn!(
+            "Port {} of {ADDRESS_VAR} is privileged, binding it requires elevated permissions",
+            address.port()
+        );
+    }
+
+    TcpListener::bind(address)
+        .await
+        .map_err(|e| match e.kind() {
+            ErrorKind::AddrInUse => AddressDiagnostic::InUse { address },
+            ErrorKind::PermissionDenied if address.port() < FIRST_UNPRIVILEGED_PORT => {
+                AddressDiagnostic::Privileged { address }
+            }
+            ErrorKind::AddrNotAvailable => AddressDiagnostic::NotLocal { address },
+            _ => AddressDiagnostic::Other {
+                address,
+                error: e.to_string(),
+            },
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_malformed_addresses_are_diagnosed() {
+        assert_eq!(
+            parse_address("0.0.0.0:3005"),
+            Ok(SocketAddr::from(([0, 0, 0, 0], 3005)))
+        );
+
+        let reason = |address| match parse_address(address) {
+            Err(AddressDiagnostic::Malformed { reason, .. }) => reason,
+            res => panic!("{address} was not diagnosed as malformed: {res:?}"),
+        };
+        assert_eq!(reason("0.0.0.0"), "the port is missing");
+        assert_eq!(
+            reason("localhost:3005"),
+            "`localhost` is not an IP address, host names are not resolved"
+        );
+        assert_eq!(
+            reason("0.0.0.0:70000"),
+            "`70000` is not a port, ports range from 0 to 65535"
+        );
+        assert_eq!(
+            reason("::1:3005"),
+            "IPv6 addresses must be in brackets, as in [::1]:3005"
+        );
+
+        assert_eq!(
+            parse_address("0.0.0.0").unwrap_err().to_string(),
+            "INTERNAL_SERVER_ADDRESS `0.0.0.0` is not a valid socket address: the port is missing. Expected an IP and a port, as in 0.0.0.0:3005"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_already_bound_port_is_diagnosed() {
+        let bound = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = bound.local_addr().unwrap();
+
+        let diagnostic = bind_listener(address).await.unwrap_err();
+        assert_eq!(diagnostic, AddressDiagnostic::InUse { address });
+        assert_eq!(
+            diagnostic.to_string(),
+            format!("{address} is already in use by another process. Stop it or set INTERNAL_SERVER_ADDRESS to another port")
+        );
+
+        drop(bound);
+        assert!(bind_listener(address).await.is_ok());
+    }
+}
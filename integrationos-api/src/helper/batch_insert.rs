@@ -0,0 +1,103 @@
+use serde::Serialize;
+use std::future::Future;
+use tracing::warn;
+
+/// Splits `docs` into sub-batches holding at most `max_count` documents and `max_bytes` of
+/// serialized bson each. A document larger than `max_bytes` on its own is put in a batch of
+/// its own.
+pub fn split_batch<T: Serialize>(docs: Vec<T>, max_count: usize, max_bytes: usize) -> Vec<Vec<T>> {
+    let max_count = max_count.max(1);
+    let mut batches = vec![];
+    let mut batch = vec![];
+    let mut batch_bytes = 0;
+
+    for doc in docs {
+        let bytes = bson::to_vec(&doc)
+            .map(|bytes| bytes.len())
+            .unwrap_or_else(|e| {
+                warn!("Could not compute bson size of document: {e}");
+                0
+            });
+
+        if !batch.is_empty() && (batch.len() == max_count || batch_bytes + bytes > max_bytes) {
+            batches.push(std::mem::take(&mut batch));
+            batch_bytes = 0;
+        }
+
+        batch.push(doc);
+        batch_bytes += bytes;
+    }
+
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+
+    batches
+}
+
+/// Inserts `docs` with one `insert` call per sub-batch, see [`split_batch`]. All sub-batches
+/// are attempted and the first error, if any, is returned.
+pub async fn insert_in_batches<T, F, Fut, E>(
+    docs: Vec<T>,
+    max_count: usize,
+    max_bytes: usize,
+    mut insert: F,
+) -> Result<(), E>
+where
+    T: Serialize,
+    F: FnMut(Vec<T>) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+{
+    let mut result = Ok(());
+    for batch in split_batch(docs, max_count, max_bytes) {
+        if let Err(e) = insert(batch).await {
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_insert_splits_into_sub_batches() {
+        let docs = (0..25).map(|i| json!({ "i": i })).collect::<Vec<_>>();
+        let inserted = Mutex::new(vec![]);
+
+        insert_in_batches(docs.clone(), 10, usize::MAX, |batch| {
+            inserted.lock().unwrap().push(batch);
+            async { Ok::<_, ()>(()) }
+        })
+        .await
+        .unwrap();
+
+        let inserted = inserted.into_inner().unwrap();
+        assert_eq!(
+            inserted.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![10, 10, 5]
+        );
+        assert_eq!(inserted.into_iter().flatten().collect::<Vec<_>>(), docs);
+    }
+
+    #[test]
+    fn test_split_batch_by_bytes() {
+        let doc = json!({ "body": "x".repeat(100) });
+        let size = bson::to_vec(&doc).unwrap().len();
+        let docs = vec![doc; 5];
+
+        let batches = split_batch(docs.clone(), 100, size * 2);
+        assert_eq!(
+            batches.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+
+        let batches = split_batch(docs, 100, size / 2);
+        assert_eq!(batches.len(), 5);
+    }
+}
@@ -0,0 +1,386 @@
+use futures::future::{join_all, pending};
+use integrationos_domain::{ApplicationError, IntegrationOSError};
+use std::{collections::HashMap, future::Future, hash::Hash, time::Duration};
+use tokio::{
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        oneshot,
+    },
+    task::JoinSet,
+    time::{sleep_until, timeout, Instant},
+};
+use tracing::{error, trace};
+
+/// Requests to flush a buffer immediately, made through the [`FlushTrigger`] created with them
+pub struct FlushRequests(Option<Receiver<oneshot::Sender<usize>>>);
+
+impl FlushRequests {
+    /// Requests of a buffer that is only flushed on its own thresholds
+    pub fn none() -> Self {
+        Self(None)
+    }
+
+    /// Next request, pending forever once every trigger is dropped
+    async fn next(&mut self) -> oneshot::Sender<usize> {
+        if let Some(requests) = self.0.as_mut() {
+            if let Some(reply) = requests.recv().await {
+                return reply;
+            }
+            self.0 = None;
+        }
+        pending().await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FlushTrigger(Sender<oneshot::Sender<usize>>);
+
+impl FlushTrigger {
+    /// Flushes the items buffered so far without waiting for the thresholds of the buffer.
+    /// Returns the number of items flushed once they are.
+    pub async fn flush(&self) -> Result<usize, IntegrationOSError> {
+        let stopped = || ApplicationError::service_unavailable("Buffer is not running", None);
+
+        let (reply, flushed) = oneshot::channel();
+        self.0.send(reply).await.map_err(|_| stopped())?;
+        flushed.await.map_err(|_| stopped())
+    }
+}
+
+/// Control channel of the manual flushes of a buffer
+pub fn flush_trigger() -> (FlushTrigger, FlushRequests) {
+    let (tx, rx) = mpsc::channel(16);
+    (FlushTrigger(tx), FlushRequests(Some(rx)))
+}
+
+/// Buffers the items received on `receiver` and hands them to `flush` once `capacity` items
+/// are buffered, or once no item was received for `wait`. Flushes run in tasks of their own so
+/// that receiving is not held up by them.
+///
+/// Each of `flush_requests` flushes the items sent so far right away, and is answered with the
+/// number of items flushed once the flush completed.
+///
+/// When `shutdown` resolves the channel is closed, the items already sent are still received
+/// and the last partial buffer is flushed. Returns once every flush has completed, which also
+/// happens when all the senders are dropped.
+pub async fn buffer_and_flush<T, F, Fut>(
+    mut receiver: Receiver<T>,
+    capacity: usize,
+    wait: Duration,
+    mut flush_requests: FlushRequests,
+    shutdown: impl Future<Output = ()>,
+    mut flush: F,
+) where
+    F: FnMut(Vec<T>) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let capacity = capacity.max(1);
+    let mut buffer = Vec::with_capacity(capacity);
+    let mut flushes = JoinSet::new();
+    let mut closed = false;
+    tokio::pin!(shutdown);
+
+    loop {
+        let res = tokio::select! {
+            res = timeout(wait, receiver.recv()) => res,
+            reply = flush_requests.next() => {
+                // Items sent before the request are flushed with it
+                while let Ok(item) = receiver.try_recv() {
+                    buffer.push(item);
+                }
+                let to_flush = std::mem::replace(&mut buffer, Vec::with_capacity(capacity));
+                let flushed = to_flush.len();
+                trace!("Flushing buffer of {flushed} items on request");
+                let flushing = (flushed > 0).then(|| flush(to_flush));
+                flushes.spawn(flush_and_reply(flushing.into_iter().collect(), flushed, reply));
+                continue;
+            }
+            _ = &mut shutdown, if !closed => {
+                trace!("Closing buffer with {} items", buffer.len());
+                receiver.close();
+                closed = true;
+                continue;
+            }
+        };
+        let is_timeout = match res {
+            Ok(Some(item)) => {
+                buffer.push(item);
+                false
+            }
+            Ok(None) => break,
+            Err(_) => {
+                trace!("Buffer timed out waiting for new items");
+                true
+            }
+        };
+
+        if buffer.len() >= capacity || (is_timeout && !buffer.is_empty()) {
+            let to_flush = std::mem::replace(&mut buffer, Vec::with_capacity(capacity));
+            flushes.spawn(flush(to_flush));
+        }
+        while let Some(res) = flushes.try_join_next() {
+            if let Err(e) = res {
+                error!("Buffer flush panicked: {e}");
+            }
+        }
+    }
+
+    if !buffer.is_empty() {
+        flushes.spawn(flush(buffer));
+    }
+    while let Some(res) = flushes.join_next().await {
+        if let Err(e) = res {
+            error!("Buffer flush panicked: {e}");
+        }
+    }
+}
+
+/// Like [`buffer_and_flush`], with a buffer per `key` of the items so that the items of a key
+/// are flushed independently of the other keys: once `capacity` of them are buffered, or once
+/// the oldest of them was buffered for `max_age`. Items of a key receiving a lot of them are
+/// not flushed along with, nor hold up, the items of the other keys. A flush request flushes
+/// the buffers of every key.
+pub async fn buffer_and_flush_by_key<T, K, F, Fut>(
+    mut receiver: Receiver<T>,
+    capacity: usize,
+    max_age: Duration,
+    key: impl Fn(&T) -> K,
+    mut flush_requests: FlushRequests,
+    shutdown: impl Future<Output = ()>,
+    mut flush: F,
+) where
+    K: Hash + Eq + Clone,
+    F: FnMut(Vec<T>) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let capacity = capacity.max(1);
+    let mut buffers = HashMap::<K, (Instant, Vec<T>)>::new();
+    let mut flushes = JoinSet::new();
+    let mut closed = false;
+    tokio::pin!(shutdown);
+
+    loop {
+        let deadline = buffers
+            .values()
+            .map(|(buffered_at, _)| *buffered_at + max_age)
+            .min();
+        tokio::select! {
+            res = receiver.recv() => {
+                let Some(item) = res else {
+                    break;
+                };
+                let key = key(&item);
+                let (_, buffer) = buffers
+                    .entry(key.clone())
+                    .or_insert_with(|| (Instant::now(), Vec::with_capacity(capacity)));
+                buffer.push(item);
+                if buffer.len() >= capacity {
+                    if let Some((_, to_flush)) = buffers.remove(&key) {
+                        flushes.spawn(flush(to_flush));
+                    }
+                }
+            }
+            _ = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                let now = Instant::now();
+                let expired = buffers
+                    .iter()
+                    .filter(|(_, (buffered_at, _))| *buffered_at + max_age <= now)
+                    .map(|(key, _)| key.clone())
+                    .collect::<Vec<_>>();
+                trace!("Flushing {} buffers that reached their maximum age", expired.len());
+                for key in expired {
+                    if let Some((_, to_flush)) = buffers.remove(&key) {
+                        flushes.spawn(flush(to_flush));
+                    }
+                }
+            }
+            reply = flush_requests.next() => {
+                // Items sent before the request are flushed with it
+                while let Ok(item) = receiver.try_recv() {
+                    buffers
+                        .entry(key(&item))
+                        .or_insert_with(|| (Instant::now(), vec![]))
+                        .1
+                        .push(item);
+                }
+                let to_flush = buffers.drain().map(|(_, (_, buffer))| buffer).collect::<Vec<_>>();
+                let flushed = to_flush.iter().map(Vec::len).sum();
+                trace!("Flushing {} buffers of {flushed} items on request", to_flush.len());
+                let flushing = to_flush.into_iter().map(&mut flush).collect();
+                flushes.spawn(flush_and_reply(flushing, flushed, reply));
+            }
+            _ = &mut shutdown, if !closed => {
+                trace!("Closing {} buffers", buffers.len());
+                receiver.close();
+                closed = true;
+            }
+        }
+        while let Some(res) = flushes.try_join_next() {
+            if let Err(e) = res {
+                error!("Buffer flush panicked: {e}");
+            }
+        }
+    }
+
+    for (_, (_, buffer)) in buffers.drain() {
+        flushes.spawn(flush(buffer));
+    }
+    while let Some(res) = flushes.join_next().await {
+        if let Err(e) = res {
+            error!("Buffer flush panicked: {e}");
+        }
+    }
+}
+
+async fn flush_and_reply<Fut: Future<Output = ()>>(
+    flushing: Vec<Fut>,
+    flushed: usize,
+    reply: oneshot::Sender<usize>,
+) {
+    join_all(flushing).await;
+    let _ = reply.send(flushed);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::{mpsc, oneshot};
+
+    #[tokio::test]
+    async fn test_partial_buffer_is_flushed_on_shutdown() {
+        let (tx, rx) = mpsc::channel(100);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let flushed = Arc::new(Mutex::new(vec![]));
+
+        let buffer = tokio::spawn({
+            let flushed = flushed.clone();
+            buffer_and_flush(
+                rx,
+                100,
+                Duration::from_secs(3600),
+                FlushRequests::none(),
+                async {
+                    let _ = shutdown_rx.await;
+                },
+                move |items| {
+                    let flushed = flushed.clone();
+                    async move {
+                        tokio::task::yield_now().await;
+                        flushed.lock().unwrap().extend(items);
+                    }
+                },
+            )
+        });
+
+        for i in 0..10 {
+            tx.send(i).await.unwrap();
+        }
+        assert!(flushed.lock().unwrap().is_empty());
+
+        shutdown_tx.send(()).unwrap();
+        buffer.await.unwrap();
+
+        // The sender is still alive, only the channel is closed
+        assert!(tx.send(10).await.is_err());
+        assert_eq!(*flushed.lock().unwrap(), (0..10).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_requested_flush_does_not_wait_for_the_thresholds() {
+        let (tx, rx) = mpsc::channel(100);
+        let (trigger, flush_requests) = flush_trigger();
+        let flushed = Arc::new(Mutex::new(vec![]));
+
+        let buffer = tokio::spawn({
+            let flushed = flushed.clone();
+            buffer_and_flush(
+                rx,
+                100,
+                Duration::from_secs(3600),
+                flush_requests,
+                pending(),
+                move |items| {
+                    let flushed = flushed.clone();
+                    async move {
+                        tokio::task::yield_now().await;
+                        flushed.lock().unwrap().extend(items);
+                    }
+                },
+            )
+        });
+
+        for i in 0..10 {
+            tx.send(i).await.unwrap();
+        }
+        // Items are flushed by the time the request is answered
+        assert_eq!(trigger.flush().await.unwrap(), 10);
+        assert_eq!(*flushed.lock().unwrap(), (0..10).collect::<Vec<_>>());
+        assert_eq!(trigger.flush().await.unwrap(), 0);
+
+        drop(tx);
+        buffer.await.unwrap();
+        assert!(trigger.flush().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_quiet_key_is_flushed_promptly_while_another_floods() {
+        let (tx, rx) = mpsc::channel(100);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let (saved_tx, mut saved_rx) = mpsc::unbounded_channel();
+
+        let buffer = tokio::spawn(buffer_and_flush_by_key(
+            rx,
+            100,
+            Duration::from_millis(20),
+            |(tenant, _): &(&'static str, usize)| *tenant,
+            FlushRequests::none(),
+            async {
+                let _ = shutdown_rx.await;
+            },
+            move |items: Vec<(&'static str, usize)>| {
+                let saved_tx = saved_tx.clone();
+                async move {
+                    // Saving the large batches of the flooding tenant is slow
+                    if items.iter().any(|(tenant, _)| *tenant == "loud") {
+                        tokio::time::sleep(Duration::from_millis(500)).await;
+                    }
+                    let _ = saved_tx.send(items);
+                }
+            },
+        ));
+
+        let flood = tokio::spawn({
+            let tx = tx.clone();
+            async move {
+                for i in 0..2000 {
+                    if tx.send(("loud", i)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        tokio::task::yield_now().await;
+
+        let sent_at = Instant::now();
+        tx.send(("quiet", 0)).await.unwrap();
+        let saved = tokio::time::timeout(Duration::from_millis(300), saved_rx.recv())
+            .await
+            .expect("The quiet tenant waited on the flooding one")
+            .unwrap();
+        assert_eq!(saved, vec![("quiet", 0)]);
+        assert!(sent_at.elapsed() < Duration::from_millis(300));
+
+        flood.await.unwrap();
+        shutdown_tx.send(()).unwrap();
+        buffer.await.unwrap();
+
+        let mut loud = 0;
+        while let Ok(items) = saved_rx.try_recv() {
+            assert!(items.len() <= 100);
+            assert!(items.iter().all(|(tenant, _)| *tenant == "loud"));
+            loud += items.len();
+        }
+        assert_eq!(loud, 2000);
+    }
+}
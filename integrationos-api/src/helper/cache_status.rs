@@ -0,0 +1,38 @@
+use http::{header::AGE, HeaderMap, HeaderName, HeaderValue};
+
+/// Header telling whether a response was served from a cache, `HIT` or `MISS`
+pub const CACHE_STATUS_HEADER: &str = "x-cache";
+
+/// Headers of a response built from a value read through a cache. Hits carry the `Age` of the
+/// cached entry in seconds, so that callers can decide whether to trust it, misses only their
+/// status.
+pub fn cache_status_headers(age: Option<chrono::Duration>) -> HeaderMap {
+    let status = HeaderName::from_static(CACHE_STATUS_HEADER);
+    let mut headers = HeaderMap::new();
+    match age {
+        Some(age) => {
+            headers.insert(status, HeaderValue::from_static("HIT"));
+            headers.insert(AGE, HeaderValue::from(age.num_seconds().max(0)));
+        }
+        None => {
+            headers.insert(status, HeaderValue::from_static("MISS"));
+        }
+    }
+    headers
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hits_carry_their_age_and_misses_their_status() {
+        let hit = cache_status_headers(Some(chrono::Duration::milliseconds(42_900)));
+        assert_eq!(hit[CACHE_STATUS_HEADER], "HIT");
+        assert_eq!(hit[AGE], "42");
+
+        let miss = cache_status_headers(None);
+        assert_eq!(miss[CACHE_STATUS_HEADER], "MISS");
+        assert!(miss.get(AGE).is_none());
+    }
+}
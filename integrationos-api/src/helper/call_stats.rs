@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Latency percentiles of the recent calls of a connection, in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyPercentiles {
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+/// Outcome of the recent calls of a connection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallStats {
+    pub calls: usize,
+    pub errors: usize,
+    pub error_rate: f64,
+    /// Unset until the connection is called
+    pub latency_millis: Option<LatencyPercentiles>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Call {
+    latency: Duration,
+    failed: bool,
+}
+
+/// Latency and outcome of the last `window` platform calls of every connection, keyed by
+/// connection id. Kept in memory, so the stats are those of the calls this instance made.
+#[derive(Clone)]
+pub struct ConnectionCallStats {
+    calls: Arc<Mutex<HashMap<String, VecDeque<Call>>>>,
+    window: usize,
+}
+
+impl ConnectionCallStats {
+    pub fn new(window: usize) -> Self {
+        Self {
+            calls: Arc::new(Mutex::new(HashMap::new())),
+            window: window.max(1),
+        }
+    }
+
+    pub fn record(&self, connection_id: &str, latency: Duration, failed: bool) {
+        let mut calls = self
+            .calls
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let calls = calls.entry(connection_id.to_owned()).or_default();
+
+        if calls.len() == self.window {
+            calls.pop_front();
+        }
+        calls.push_back(Call { latency, failed });
+    }
+
+    pub fn stats(&self, connection_id: &str) -> CallStats {
+        let calls = self
+            .calls
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(connection_id)
+            .cloned()
+            .unwrap_or_default();
+
+        let errors = calls.iter().filter(|call| call.failed).count();
+        let mut latencies = calls
+            .iter()
+            .map(|call| call.latency.as_millis() as u64)
+            .collect::<Vec<_>>();
+        latencies.sort_unstable();
+
+        CallStats {
+            calls: calls.len(),
+            errors,
+            error_rate: if calls.is_empty() {
+                0.0
+            } else {
+                errors as f64 / calls.len() as f64
+            },
+            latency_millis: latencies.last().map(|max| LatencyPercentiles {
+                p50: percentile(&latencies, 50),
+                p90: percentile(&latencies, 90),
+                p99: percentile(&latencies, 99),
+                max: *max,
+            }),
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted, non empty, `latencies`
+fn percentile(latencies: &[u64], percentile: usize) -> u64 {
+    let rank = (percentile * latencies.len()).div_ceil(100);
+    latencies[rank.saturating_sub(1)]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stats_of_recent_calls() {
+        let stats = ConnectionCallStats::new(10);
+        assert_eq!(
+            stats.stats("connection"),
+            CallStats {
+                calls: 0,
+                errors: 0,
+                error_rate: 0.0,
+                latency_millis: None,
+            }
+        );
+
+        // Only the last 10 calls are kept, the slow ones below are pushed out of the window
+        for _ in 0..5 {
+            stats.record("connection", Duration::from_secs(60), true);
+        }
+        for latency in 1..=10 {
+            stats.record(
+                "connection",
+                Duration::from_millis(latency * 10),
+                latency > 8,
+            );
+        }
+        stats.record("other", Duration::from_millis(1), false);
+
+        assert_eq!(
+            stats.stats("connection"),
+            CallStats {
+                calls: 10,
+                errors: 2,
+                error_rate: 0.2,
+                latency_millis: Some(LatencyPercentiles {
+                    p50: 50,
+                    p90: 90,
+                    p99: 100,
+                    max: 100,
+                }),
+            }
+        );
+        assert_eq!(stats.stats("other").calls, 1);
+    }
+}
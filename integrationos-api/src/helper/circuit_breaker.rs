@@ -0,0 +1,289 @@
+use super::{CIRCUIT_BREAKER_STATE_GAUGE, CONNECTION_LABEL};
+use chrono::{DateTime, Utc};
+use integrationos_domain::{ApplicationError, Clock, Connection, IntegrationOSError};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// State of the circuit breaker of a connection, reported as the value of
+/// [`CIRCUIT_BREAKER_STATE_GAUGE`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls go through to the platform
+    Closed,
+    /// A single call goes through to probe whether the platform recovered
+    HalfOpen,
+    /// Calls are refused without reaching the platform
+    Open,
+}
+
+impl CircuitState {
+    fn gauge(self) -> f64 {
+        match self {
+            CircuitState::Closed => 0.0,
+            CircuitState::HalfOpen => 1.0,
+            CircuitState::Open => 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Breaker {
+    Closed {
+        failures: u32,
+        first_failure: Option<DateTime<Utc>>,
+    },
+    Open {
+        until: DateTime<Utc>,
+    },
+    HalfOpen {
+        probe_started: DateTime<Utc>,
+    },
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Breaker::Closed {
+            failures: 0,
+            first_failure: None,
+        }
+    }
+}
+
+impl Breaker {
+    fn state(&self) -> CircuitState {
+        match self {
+            Breaker::Closed { .. } => CircuitState::Closed,
+            Breaker::Open { .. } => CircuitState::Open,
+            Breaker::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+}
+
+/// Circuit breakers of the calls to platforms, keyed by connection id. A connection whose
+/// calls failed `failure_threshold` times in a row within `failure_window` is cut off from its
+/// platform for `cool_down`, after which a single call probes whether the platform recovered.
+#[derive(Clone)]
+pub struct ConnectionCircuitBreaker {
+    breakers: Arc<Mutex<HashMap<String, Breaker>>>,
+    failure_threshold: u32,
+    failure_window: chrono::Duration,
+    cool_down: chrono::Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl ConnectionCircuitBreaker {
+    pub fn new(
+        failure_threshold: u32,
+        failure_window: Duration,
+        cool_down: Duration,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            breakers: Arc::new(Mutex::new(HashMap::new())),
+            failure_threshold: failure_threshold.max(1),
+            failure_window: chrono::Duration::from_std(failure_window).unwrap_or_default(),
+            cool_down: chrono::Duration::from_std(cool_down).unwrap_or_default(),
+            clock,
+        }
+    }
+
+    fn with_breaker<T>(
+        &self,
+        connection_id: &str,
+        connection_key: &str,
+        f: impl FnOnce(&mut Breaker, DateTime<Utc>) -> T,
+    ) -> T {
+        let now = self.clock.now();
+        let mut breakers = self
+            .breakers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let breaker = breakers.entry(connection_id.to_owned()).or_default();
+
+        let before = breaker.state();
+        let result = f(breaker, now);
+        let after = breaker.state();
+        if before != after {
+            metrics::gauge!(
+                CIRCUIT_BREAKER_STATE_GAUGE,
+                after.gauge(),
+                CONNECTION_LABEL => connection_key.to_owned()
+            );
+        }
+
+        result
+    }
+
+    /// Lets a call of the connection through to its platform, failing right away while the
+    /// breaker is open or a probe of the platform is in flight
+    pub fn acquire(&self, connection: &Connection) -> Result<(), IntegrationOSError> {
+        self.try_acquire(&connection.id.to_string(), connection.key.as_ref())
+    }
+
+    fn try_acquire(
+        &self,
+        connection_id: &str,
+        connection_key: &str,
+    ) -> Result<(), IntegrationOSError> {
+        let cool_down = self.cool_down;
+
+        self.with_breaker(connection_id, connection_key, |breaker, now| {
+            let retry_at = match breaker {
+                Breaker::Closed { .. } => return Ok(()),
+                Breaker::Open { until } if *until > now => *until,
+                // A probe that never completed, because its request was dropped, doesn't
+                // hold the breaker half open for good
+                Breaker::HalfOpen { probe_started } if *probe_started + cool_down > now => {
+                    *probe_started + cool_down
+                }
+                _ => {
+                    *breaker = Breaker::HalfOpen { probe_started: now };
+                    return Ok(());
+                }
+            };
+
+            Err(ApplicationError::service_unavailable(
+                &format!(
+                    "Calls to the platform of connection {} are suspended after repeated failures, retry in {}s",
+                    connection_key,
+                    (retry_at - now).num_seconds().max(1)
+                ),
+                None,
+            ))
+        })
+    }
+
+    /// Records the outcome of a call let through by [`Self::acquire`]
+    pub fn record(&self, connection: &Connection, failed: bool) {
+        self.record_call(&connection.id.to_string(), connection.key.as_ref(), failed)
+    }
+
+    fn record_call(&self, connection_id: &str, connection_key: &str, failed: bool) {
+        self.with_breaker(
+            connection_id,
+            connection_key,
+            |breaker, now| match breaker {
+                Breaker::Closed {
+                    failures,
+                    first_failure,
+                } if failed => {
+                    if first_failure.map_or(true, |first| first + self.failure_window < now) {
+                        *failures = 0;
+                        *first_failure = Some(now);
+                    }
+                    *failures += 1;
+
+                    if *failures >= self.failure_threshold {
+                        *breaker = Breaker::Open {
+                            until: now + self.cool_down,
+                        };
+                    }
+                }
+                Breaker::HalfOpen { .. } if failed => {
+                    *breaker = Breaker::Open {
+                        until: now + self.cool_down,
+                    };
+                }
+                Breaker::Closed { .. } | Breaker::HalfOpen { .. } => *breaker = Breaker::default(),
+                // Calls that started before the breaker opened don't change its cool down
+                Breaker::Open { .. } => {}
+            },
+        )
+    }
+
+    pub fn state(&self, connection: &Connection) -> CircuitState {
+        self.state_of(&connection.id.to_string(), connection.key.as_ref())
+    }
+
+    fn state_of(&self, connection_id: &str, connection_key: &str) -> CircuitState {
+        self.with_breaker(connection_id, connection_key, |breaker, _| breaker.state())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http::StatusCode;
+    use integrationos_domain::MockClock;
+
+    const ID: &str = "conn::connection";
+    const KEY: &str = "live::platform::connection";
+
+    #[test]
+    fn test_breaker_opens_then_recovers() {
+        let clock = MockClock::default();
+        let breaker = ConnectionCircuitBreaker::new(
+            3,
+            Duration::from_secs(60),
+            Duration::from_secs(30),
+            Arc::new(clock.clone()),
+        );
+
+        // Failures spread over more than the window don't open the breaker
+        for _ in 0..2 {
+            breaker.try_acquire(ID, KEY).unwrap();
+            breaker.record_call(ID, KEY, true);
+        }
+        clock.advance(chrono::Duration::seconds(61));
+        breaker.try_acquire(ID, KEY).unwrap();
+        breaker.record_call(ID, KEY, true);
+        assert_eq!(breaker.state_of(ID, KEY), CircuitState::Closed);
+
+        // Nor do failures broken by a success
+        breaker.record_call(ID, KEY, false);
+        breaker.record_call(ID, KEY, true);
+        breaker.record_call(ID, KEY, true);
+        assert_eq!(breaker.state_of(ID, KEY), CircuitState::Closed);
+
+        breaker.record_call(ID, KEY, true);
+        assert_eq!(breaker.state_of(ID, KEY), CircuitState::Open);
+        let err = breaker.try_acquire(ID, KEY).unwrap_err();
+        assert_eq!(StatusCode::from(&err), StatusCode::SERVICE_UNAVAILABLE);
+
+        // Once cooled down, a single probe goes through
+        clock.advance(chrono::Duration::seconds(30));
+        breaker.try_acquire(ID, KEY).unwrap();
+        assert_eq!(breaker.state_of(ID, KEY), CircuitState::HalfOpen);
+        assert!(breaker.try_acquire(ID, KEY).is_err());
+
+        // A failed probe opens the breaker again
+        breaker.record_call(ID, KEY, true);
+        assert_eq!(breaker.state_of(ID, KEY), CircuitState::Open);
+        clock.advance(chrono::Duration::seconds(29));
+        assert!(breaker.try_acquire(ID, KEY).is_err());
+
+        clock.advance(chrono::Duration::seconds(1));
+        breaker.try_acquire(ID, KEY).unwrap();
+        breaker.record_call(ID, KEY, false);
+        assert_eq!(breaker.state_of(ID, KEY), CircuitState::Closed);
+        breaker.try_acquire(ID, KEY).unwrap();
+        breaker.try_acquire(ID, KEY).unwrap();
+    }
+
+    #[test]
+    fn test_abandoned_probe_expires() {
+        let clock = MockClock::default();
+        let breaker = ConnectionCircuitBreaker::new(
+            1,
+            Duration::from_secs(60),
+            Duration::from_secs(30),
+            Arc::new(clock.clone()),
+        );
+
+        breaker.record_call(ID, KEY, true);
+        assert!(breaker.try_acquire(ID, KEY).is_err());
+        breaker.try_acquire("other", "other-key").unwrap();
+
+        clock.advance(chrono::Duration::seconds(30));
+        breaker.try_acquire(ID, KEY).unwrap();
+        clock.advance(chrono::Duration::seconds(10));
+        assert!(breaker.try_acquire(ID, KEY).is_err());
+
+        clock.advance(chrono::Duration::seconds(20));
+        breaker.try_acquire(ID, KEY).unwrap();
+        assert_eq!(breaker.state_of(ID, KEY), CircuitState::HalfOpen);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{
+    metrics::{bucket_keys, MONTHLY_KEY},
+    server::AppState,
+};
+use integrationos_domain::{
+    destination::Action, ApplicationError, IntegrationOSError, InternalError, Store,
+};
+use std::{collections::HashMap, str::FromStr};
+use tracing::error;
+
+pub const COST_KEY: &str = "cost";
+
+/// Cost of each kind of request, parsed from a comma separated list of `key=cost` pairs such
+/// as `unified::customers::getMany=5,unified::orders=3,passthrough=2`. Keys are matched case
+/// insensitively from the most to the least specific one, `default` overrides the cost of
+/// requests matching no key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestCosts {
+    costs: HashMap<String, u64>,
+    default: u64,
+}
+
+impl Default for RequestCosts {
+    fn default() -> Self {
+        Self {
+            costs: HashMap::new(),
+            default: 1,
+        }
+    }
+}
+
+impl FromStr for RequestCosts {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut costs = RequestCosts::default();
+
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (key, cost) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid request cost {pair}, expected key=cost"))?;
+            let cost = cost
+                .trim()
+                .parse::<u64>()
+                .map_err(|e| format!("Invalid request cost {pair}: {e}"))?;
+
+            match key.trim().to_lowercase().as_str() {
+                "default" => costs.default = cost,
+                key => {
+                    costs.costs.insert(key.to_string(), cost);
+                }
+            }
+        }
+
+        Ok(costs)
+    }
+}
+
+impl RequestCosts {
+    pub fn unified(&self, action: &Action) -> u64 {
+        let model = action.name().to_lowercase();
+        let crud = action
+            .action()
+            .map(|action| action.to_string().to_lowercase())
+            .unwrap_or_default();
+
+        self.lookup(&[
+            format!("unified::{model}::{crud}"),
+            format!("unified::{model}"),
+            "unified".to_string(),
+        ])
+    }
+
+    pub fn passthrough(&self) -> u64 {
+        self.lookup(&["passthrough".to_string()])
+    }
+
+    fn lookup(&self, keys: &[String]) -> u64 {
+        keys.iter()
+            .find_map(|key| self.costs.get(key))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+/// Fails with a 429 if spending `cost` on top of what was already `spent` exceeds `budget`.
+/// A budget of 0 means no budget is enforced.
+pub fn ensure_within_budget(spent: u64, cost: u64, budget: u64) -> Result<(), IntegrationOSError> {
+    if budget == 0 || spent.saturating_add(cost) <= budget {
+        Ok(())
+    } else {
+        Err(ApplicationError::too_many_requests(
+            &format!("Monthly request budget of {budget} exhausted, {spent} already spent"),
+            None,
+        ))
+    }
+}
+
+/// Checks the cost of a request against the monthly budget of the client, using the costs
+/// accumulated in the metrics store. Costs are recorded asynchronously with the metrics, so
+/// concurrent requests may slightly overshoot the budget.
+pub async fn check_cost_budget(
+    state: &AppState,
+    client_id: &str,
+    cost: u64,
+) -> Result<(), IntegrationOSError> {
+    let budget = state.config.monthly_cost_budget;
+    if budget == 0 {
+        return Ok(());
+    }
+
+    // Costs are bucketed by the months of the metric timezone
+    let (_, month) = bucket_keys(state.clock.now(), &state.config.metric_timezone);
+
+    let metrics = state
+        .app_stores
+        .db
+        .collection::<bson::Document>(&Store::Metrics.to_string());
+    let spent = metrics
+        .find_one(bson::doc! { "clientId": client_id }, None)
+        .await
+        .map_err(|e| {
+            error!("Could not fetch cost metrics: {e}");
+            InternalError::unknown("Could not fetch cost metrics", None)
+        })?
+        .and_then(|doc| {
+            doc.get_document(COST_KEY)
+                .and_then(|cost| cost.get_document(MONTHLY_KEY))
+                .and_then(|monthly| monthly.get_i64(&month))
+                .ok()
+        })
+        .unwrap_or_default();
+
+    ensure_within_budget(spent.max(0) as u64, cost, budget)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use integrationos_domain::connection_model_definition::CrudAction;
+
+    #[test]
+    fn test_high_cost_requests_deplete_budget_faster() {
+        let costs = RequestCosts::from_str("unified::customers::getMany=5, passthrough=1")
+            .expect("Failed to parse request costs");
+        let expensive = Action::Unified {
+            name: "Customers".into(),
+            action: CrudAction::GetMany,
+            id: None,
+        };
+
+        let requests_until_429 = |cost: u64| {
+            let mut spent = 0;
+            let mut count = 0;
+            while ensure_within_budget(spent, cost, 20).is_ok() {
+                spent += cost;
+                count += 1;
+            }
+            count
+        };
+
+        assert_eq!(costs.unified(&expensive), 5);
+        assert_eq!(costs.passthrough(), 1);
+        assert_eq!(requests_until_429(costs.unified(&expensive)), 4);
+        assert_eq!(requests_until_429(costs.passthrough()), 20);
+
+        let err = ensure_within_budget(20, 1, 20).expect_err("Budget should be exhausted");
+        assert_eq!(
+            http::StatusCode::from(&err),
+            http::StatusCode::TOO_MANY_REQUESTS
+        );
+        assert!(ensure_within_budget(1_000, 5, 0).is_ok());
+    }
+}
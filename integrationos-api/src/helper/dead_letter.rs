@@ -0,0 +1,115 @@
+use bson::{doc, Document};
+use chrono::{DateTime, Duration, Utc};
+use mongodb::Collection;
+use tracing::{error, info};
+
+/// Gauge of the events in the dead letter store, sampled on every sweep
+pub const DEAD_LETTER_EVENTS_GAUGE: &str = "dead_letter_events";
+/// Counter of the times the dead letter events crossed the alert threshold
+pub const DEAD_LETTER_ALERTS_COUNTER: &str = "dead_letter_alerts_total";
+
+/// Removes the dead letter events that arrived before `now - retention`
+pub async fn sweep_dead_letters(
+    dead_letters: &Collection<Document>,
+    retention: Duration,
+    now: DateTime<Utc>,
+) -> Result<u64, mongodb::error::Error> {
+    let cutoff = (now - retention).timestamp_millis();
+    let result = dead_letters
+        .delete_many(doc! { "arrivedAt": { "$lt": cutoff } }, None)
+        .await?;
+
+    Ok(result.deleted_count)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadLetterAlert {
+    /// The dead letter events reached the threshold
+    Raised { count: u64, threshold: u64 },
+    /// The dead letter events dropped back below the threshold
+    Cleared { count: u64, threshold: u64 },
+}
+
+/// Alerts when the number of dead letter events crosses `threshold`. An alert is raised once
+/// per crossing, not on every observation above the threshold.
+#[derive(Debug, Clone)]
+pub struct DeadLetterMonitor {
+    threshold: u64,
+    raised: bool,
+}
+
+impl DeadLetterMonitor {
+    pub fn new(threshold: u64) -> Self {
+        Self {
+            threshold,
+            raised: false,
+        }
+    }
+
+    pub fn observe(&mut self, count: u64) -> Option<DeadLetterAlert> {
+        let threshold = self.threshold;
+        match (self.raised, count >= threshold) {
+            (false, true) => {
+                self.raised = true;
+                Some(DeadLetterAlert::Raised { count, threshold })
+            }
+            (true, false) => {
+                self.raised = false;
+                Some(DeadLetterAlert::Cleared { count, threshold })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Records the number of dead letter events, and reports the alerts of `monitor`
+pub fn report_dead_letters(count: u64, monitor: Option<&mut DeadLetterMonitor>) {
+    metrics::gauge!(DEAD_LETTER_EVENTS_GAUGE, count as f64);
+
+    match monitor.and_then(|monitor| monitor.observe(count)) {
+        Some(DeadLetterAlert::Raised { count, threshold }) => {
+            metrics::counter!(DEAD_LETTER_ALERTS_COUNTER, 1);
+            error!("{count} events are in the dead letter store, the threshold is {threshold}");
+        }
+        Some(DeadLetterAlert::Cleared { count, threshold }) => {
+            info!("{count} events are in the dead letter store, back below the threshold of {threshold}");
+        }
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_alert_fires_when_dead_letters_cross_the_threshold() {
+        let mut monitor = DeadLetterMonitor::new(10);
+
+        assert_eq!(monitor.observe(0), None);
+        assert_eq!(monitor.observe(9), None);
+        assert_eq!(
+            monitor.observe(10),
+            Some(DeadLetterAlert::Raised {
+                count: 10,
+                threshold: 10
+            })
+        );
+        // Still above, the alert is not raised again
+        assert_eq!(monitor.observe(25), None);
+        assert_eq!(
+            monitor.observe(3),
+            Some(DeadLetterAlert::Cleared {
+                count: 3,
+                threshold: 10
+            })
+        );
+        assert_eq!(
+            monitor.observe(11),
+            Some(DeadLetterAlert::Raised {
+                count: 11,
+                threshold: 10
+            })
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use integrationos_domain::{ApplicationError, Event, IntegrationOSError, InternalError};
+use std::{future::Future, time::Duration};
+use tokio::sync::mpsc::{error::TrySendError, Sender, WeakSender};
+
+/// Counter of the events that could not be queued for saving, labelled with [`REASON_LABEL`],
+/// `full` when the buffer was full and `closed` when the server was shutting down
+pub const EVENTS_DROPPED_COUNTER: &str = "event_sends_dropped_total";
+pub const REASON_LABEL: &str = "reason";
+/// Gauge of the events queued for saving, sampled periodically. A depth staying close to the
+/// buffer size means events are about to be dropped.
+pub const EVENT_CHANNEL_DEPTH_GAUGE: &str = "event_channel_depth";
+
+/// Queues `event` for saving without waiting for room in the buffer, so that a saturated
+/// buffer fails the send instead of holding up the request
+pub fn send_event(event_tx: &Sender<Event>, event: Event) -> Result<(), IntegrationOSError> {
+    event_tx.try_send(event).map_err(|e| {
+        let reason = match e {
+            TrySendError::Full(_) => "full",
+            TrySendError::Closed(_) => "closed",
+        };
+        metrics::counter!(EVENTS_DROPPED_COUNTER, 1, REASON_LABEL => reason);
+
+        match e {
+            TrySendError::Full(_) => {
+                ApplicationError::service_unavailable("Event buffer is full", None)
+            }
+            TrySendError::Closed(_) => InternalError::io_err("Event buffer is closed", None),
+        }
+    })
+}
+
+/// Number of items queued in the channel of `tx`
+pub fn channel_depth<T>(tx: &Sender<T>) -> usize {
+    tx.max_capacity() - tx.capacity()
+}
+
+/// Records the depth of the channel of `tx` every `interval`, until `shutdown` resolves or
+/// every sender of the channel is dropped
+pub async fn sample_channel_depth<T>(
+    tx: WeakSender<T>,
+    interval: Duration,
+    shutdown: impl Future<Output = ()>,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    tokio::pin!(shutdown);
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = &mut shutdown => break,
+        }
+        let Some(tx) = tx.upgrade() else {
+            break;
+        };
+        metrics::gauge!(EVENT_CHANNEL_DEPTH_GAUGE, channel_depth(&tx) as f64);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http::{HeaderMap, StatusCode};
+    use integrationos_domain::{encrypted_access_key::EncryptedAccessKey, AccessKey};
+    use tokio::sync::mpsc;
+
+    const ACCESS_KEY: &str = "id_test_1_Q71YUIZydcgSwJQNOUCHhaTMqmIvslIafF5LluORJfJKydMGELHtYe_ydtBIrVuomEnOZ4jfZQgtkqWxtG-s7vhbyir4kNjLyHKyDyh1SDubBMlhSI7Mq-M5RVtwnwFqZiOeUkIgHJFgcGQn0Plb1AkAAAAAAAAAAAAAAAAAAAAAAMwWY_9_oDOV75noniBViOVmVPUQqzcW8G3P8nuUD6Q";
+    const PASSWORD: &[u8; 32] = b"32KFFT_i4UpkJmyPwY2TGzgHpxfXs7zS";
+
+    fn event() -> Event {
+        let encrypted_access_key = EncryptedAccessKey::parse(ACCESS_KEY).unwrap();
+        let access_key = AccessKey::parse(&encrypted_access_key, PASSWORD).unwrap();
+        Event::new(
+            &access_key,
+            &encrypted_access_key,
+            "event",
+            HeaderMap::new(),
+            "{}".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_send_fails_instead_of_waiting_when_buffer_is_full() {
+        let (tx, mut rx) = mpsc::channel(2);
+        send_event(&tx, event()).expect("Buffer has room");
+        send_event(&tx, event()).expect("Buffer has room");
+        assert_eq!(channel_depth(&tx), 2);
+
+        let full = send_event(&tx, event()).unwrap_err();
+        assert_eq!(StatusCode::from(full), StatusCode::SERVICE_UNAVAILABLE);
+
+        rx.recv().await.expect("Event was queued");
+        assert_eq!(channel_depth(&tx), 1);
+        send_event(&tx, event()).expect("Buffer has room again");
+
+        rx.close();
+        assert!(send_event(&tx, event()).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use integrationos_domain::Event;
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, RwLock},
+};
+
+/// Counter of the events dropped at ingestion because their type is denylisted, labelled with
+/// [`EVENT_TYPE_LABEL`]
+pub const EVENTS_DENIED_COUNTER: &str = "events_denied_total";
+pub const EVENT_TYPE_LABEL: &str = "event_type";
+
+/// Event types dropped at ingestion instead of being saved, e.g. to relieve the pipeline of a
+/// noisy event type during an incident. Kept in memory and replaced at runtime through the
+/// admin endpoint, so each instance has to be updated.
+#[derive(Debug, Clone, Default)]
+pub struct EventDenylist {
+    event_types: Arc<RwLock<BTreeSet<String>>>,
+}
+
+impl EventDenylist {
+    /// Denylist of the comma separated event types of `event_types`
+    pub fn parse(event_types: &str) -> Self {
+        let denylist = Self::default();
+        denylist.replace(
+            event_types
+                .split(',')
+                .map(str::trim)
+                .filter(|event_type| !event_type.is_empty())
+                .map(ToOwned::to_owned),
+        );
+        denylist
+    }
+
+    pub fn event_types(&self) -> BTreeSet<String> {
+        self.event_types
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Replaces the denied event types, the events ingested from then on are checked against
+    /// the new ones
+    pub fn replace(&self, event_types: impl IntoIterator<Item = String>) {
+        *self
+            .event_types
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = event_types.into_iter().collect();
+    }
+
+    pub fn is_denied(&self, event_type: &str) -> bool {
+        self.event_types
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .contains(event_type)
+    }
+
+    /// Whether `event` may be ingested, counting it as dropped when its type is denied
+    pub fn admits(&self, event: &Event) -> bool {
+        if self.is_denied(&event.name) {
+            metrics::counter!(EVENTS_DENIED_COUNTER, 1, EVENT_TYPE_LABEL => event.name.clone());
+            false
+        } else {
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_denied_event_types_follow_replacements() {
+        let denylist = EventDenylist::parse(" stripe::webhook-received, ,shopify::order ");
+        assert_eq!(
+            denylist.event_types(),
+            BTreeSet::from([
+                "shopify::order".to_owned(),
+                "stripe::webhook-received".to_owned()
+            ])
+        );
+        assert!(denylist.is_denied("stripe::webhook-received"));
+        assert!(!denylist.is_denied("stripe::request-succeeded"));
+
+        // Replacements are seen right away by every clone of the denylist
+        let shared = denylist.clone();
+        shared.replace(["stripe::request-succeeded".to_owned()]);
+        assert!(!denylist.is_denied("stripe::webhook-received"));
+        assert!(denylist.is_denied("stripe::request-succeeded"));
+
+        shared.replace([]);
+        assert!(denylist.event_types().is_empty());
+        assert!(EventDenylist::parse("").event_types().is_empty());
+    }
+}
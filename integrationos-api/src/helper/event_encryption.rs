@@ -0,0 +1,26 @@
+use super::EVENTS_LOST_COUNTER;
+use futures::future::join_all;
+use integrationos_domain::{field_encryption::FieldEncryption, Event};
+use tracing::error;
+
+/// Encrypts the configured fields of the `events` about to be saved. Events whose fields can't
+/// be encrypted are dropped, and counted in [`EVENTS_LOST_COUNTER`], rather than stored in
+/// plaintext.
+pub async fn encrypt_events(field_encryption: &FieldEncryption, events: Vec<Event>) -> Vec<Event> {
+    join_all(
+        events
+            .into_iter()
+            .map(|event| field_encryption.encrypt_event(event)),
+    )
+    .await
+    .into_iter()
+    .filter_map(|res| match res {
+        Ok(event) => Some(event),
+        Err(e) => {
+            error!("Could not encrypt the fields of an event, dropping it: {e}");
+            metrics::counter!(EVENTS_LOST_COUNTER, 1);
+            None
+        }
+    })
+    .collect()
+}
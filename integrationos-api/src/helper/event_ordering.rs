@@ -0,0 +1,164 @@
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt,
+};
+use integrationos_domain::Event;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use strum::{Display, EnumString};
+use tokio::sync::oneshot;
+
+/// Which saves of buffered events are ordered. Buffers are saved concurrently, so without
+/// ordering the events of a buffer may be persisted before those of an earlier buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "camelCase")]
+pub enum EventOrdering {
+    #[default]
+    None,
+    /// Events sharing a correlation id are persisted in the order they were sent, events
+    /// without one are not ordered
+    CorrelationId,
+    /// Events of a client are persisted in the order they were sent
+    ClientId,
+}
+
+impl EventOrdering {
+    /// Reference the event is ordered by, none if it is not ordered
+    pub fn reference<'a>(&self, event: &'a Event) -> Option<&'a str> {
+        match self {
+            EventOrdering::None => None,
+            EventOrdering::CorrelationId => event.correlation_id.as_deref(),
+            EventOrdering::ClientId => Some(&event.ownership.client_id),
+        }
+    }
+
+    /// Splits events by reference, keeping the order of the events of each reference
+    pub fn split(&self, events: Vec<Event>) -> Vec<(Option<String>, Vec<Event>)> {
+        if *self == EventOrdering::None {
+            return vec![(None, events)];
+        }
+
+        let mut references = HashMap::<Option<String>, usize>::new();
+        let mut groups = Vec::<(Option<String>, Vec<Event>)>::new();
+        for event in events {
+            let reference = self.reference(&event).map(str::to_owned);
+            let index = *references.entry(reference.clone()).or_insert_with(|| {
+                groups.push((reference, vec![]));
+                groups.len() - 1
+            });
+            groups[index].1.push(event);
+        }
+        groups
+    }
+}
+
+type Tail = Shared<oneshot::Receiver<()>>;
+
+/// Runs the futures scheduled for a key one after the other, in the order they were
+/// scheduled, while futures of different keys run concurrently
+#[derive(Clone, Default)]
+pub struct KeySequencer {
+    tails: Arc<Mutex<HashMap<String, (u64, Tail)>>>,
+    scheduled: Arc<AtomicU64>,
+}
+
+impl KeySequencer {
+    /// Schedules `future` to run once the futures scheduled before it for `key` have
+    /// completed. The order is the order of the calls, not the order the returned futures are
+    /// polled in.
+    pub fn schedule<F>(&self, key: String, future: F) -> BoxFuture<'static, F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send,
+    {
+        let (done, tail) = oneshot::channel();
+        let id = self.scheduled.fetch_add(1, Ordering::Relaxed);
+        let previous = self
+            .tails
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.clone(), (id, tail.shared()))
+            .map(|(_, tail)| tail);
+
+        let tails = self.tails.clone();
+        async move {
+            if let Some(previous) = previous {
+                // Also resolves if the previous future was dropped without completing
+                let _ = previous.await;
+            }
+            let output = future.await;
+            let _ = done.send(());
+
+            let mut tails = tails.lock().unwrap_or_else(|e| e.into_inner());
+            if tails.get(&key).is_some_and(|(tail, _)| *tail == id) {
+                tails.remove(&key);
+            }
+            output
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::helper::{buffer_and_flush, FlushRequests};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_saves_of_a_reference_persist_in_submission_order() {
+        let sequencer = KeySequencer::default();
+        let (tx, rx) = mpsc::channel(10);
+        let persisted = Arc::new(Mutex::new(vec![]));
+
+        // Every item is flushed on its own, the earlier flushes being the slower ones
+        let buffer = tokio::spawn({
+            let persisted = persisted.clone();
+            buffer_and_flush(
+                rx,
+                1,
+                Duration::from_secs(3600),
+                FlushRequests::none(),
+                std::future::pending(),
+                move |items: Vec<(&'static str, u64)>| {
+                    let (reference, i) = items[0];
+                    let persisted = persisted.clone();
+                    let save = async move {
+                        tokio::time::sleep(Duration::from_millis(50 - i * 10)).await;
+                        persisted.lock().unwrap().push((reference, i));
+                    };
+                    match reference {
+                        "unordered" => save.boxed(),
+                        reference => sequencer.schedule(reference.to_owned(), save),
+                    }
+                },
+            )
+        });
+
+        for i in 0..4 {
+            tx.send(("reference", i)).await.unwrap();
+        }
+        tx.send(("unordered", 4)).await.unwrap();
+        drop(tx);
+        buffer.await.unwrap();
+
+        let persisted = persisted.lock().unwrap().clone();
+        assert_eq!(
+            persisted
+                .iter()
+                .filter(|(reference, _)| *reference == "reference")
+                .map(|(_, i)| *i)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+        // Saves of other references are not held up by the ordered ones
+        assert_eq!(persisted[0], ("unordered", 4));
+    }
+}
@@ -0,0 +1,204 @@
+use integrationos_domain::Event;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+use std::{collections::BTreeMap, str::FromStr};
+
+/// Processors applied in order to the events of the API before they are buffered to be saved,
+/// parsed from a JSON array such as
+/// `[{"processor": "filter", "name": "^internal\\."}, {"processor": "map", "rename": {"/old": "/new"}}]`.
+/// Fields are JSON pointers into the body of the events, bodies that are not JSON are left as
+/// they are. Hashes of the events are the ones of the bodies they were received with.
+#[derive(Debug, Clone, Default)]
+pub struct EventProcessors(Vec<EventProcessor>);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "processor", rename_all = "camelCase")]
+pub enum EventProcessor {
+    /// Drops the events whose name matches the pattern
+    Filter {
+        #[serde(deserialize_with = "pattern")]
+        name: Regex,
+    },
+    /// Moves the field of every key to the field of its value
+    Map { rename: BTreeMap<String, String> },
+    /// Sets fields, e.g. `{"/tags/source": "api"}`, replacing the fields already set
+    Enrich { fields: BTreeMap<String, Value> },
+}
+
+fn pattern<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+impl FromStr for EventProcessors {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(s)
+            .map(Self)
+            .map_err(|e| format!("Invalid event processors: {e}"))
+    }
+}
+
+impl EventProcessors {
+    /// Runs the event through the processors, or returns `None` if one of them dropped it
+    pub fn apply(&self, mut event: Event) -> Option<Event> {
+        let mut body = None::<Value>;
+
+        for processor in &self.0 {
+            match processor {
+                EventProcessor::Filter { name } => {
+                    if name.is_match(&event.name) {
+                        return None;
+                    }
+                }
+                EventProcessor::Map { rename } => {
+                    let Some(body) = parsed(&mut body, &event.body) else {
+                        continue;
+                    };
+                    for (from, to) in rename {
+                        if let Some(field) = take(body, from) {
+                            set(body, to, field);
+                        }
+                    }
+                }
+                EventProcessor::Enrich { fields } => {
+                    let Some(body) = parsed(&mut body, &event.body) else {
+                        continue;
+                    };
+                    for (pointer, field) in fields {
+                        set(body, pointer, field.clone());
+                    }
+                }
+            }
+        }
+
+        if let Some(body) = body {
+            event.body = body.to_string();
+        }
+        Some(event)
+    }
+}
+
+/// Body of the event parsed on first use, `None` if it isn't JSON
+fn parsed<'a>(body: &'a mut Option<Value>, raw: &str) -> Option<&'a mut Value> {
+    if body.is_none() {
+        *body = Some(serde_json::from_str(raw).ok()?);
+    }
+    body.as_mut()
+}
+
+fn tokens(pointer: &str) -> Option<Vec<String>> {
+    let tokens = pointer
+        .strip_prefix('/')?
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect();
+    Some(tokens)
+}
+
+fn take(value: &mut Value, pointer: &str) -> Option<Value> {
+    let (last, parents) = tokens(pointer)?
+        .split_last()
+        .map(|(l, p)| (l.clone(), p.to_vec()))?;
+    let mut current = value;
+    for token in parents {
+        current = current.as_object_mut()?.get_mut(&token)?;
+    }
+    current.as_object_mut()?.remove(&last)
+}
+
+/// Sets the field at `pointer`, creating the objects leading to it
+fn set(value: &mut Value, pointer: &str, field: Value) {
+    let Some(tokens) = tokens(pointer) else {
+        return;
+    };
+    let Some((last, parents)) = tokens.split_last() else {
+        return;
+    };
+
+    let mut current = value;
+    for token in parents {
+        let Some(object) = current.as_object_mut() else {
+            return;
+        };
+        current = object
+            .entry(token.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+    if let Some(object) = current.as_object_mut() {
+        object.insert(last.clone(), field);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http::HeaderMap;
+    use integrationos_domain::{encrypted_access_key::EncryptedAccessKey, AccessKey};
+    use serde_json::json;
+
+    const ACCESS_KEY: &str = "id_test_1_Q71YUIZydcgSwJQNOUCHhaTMqmIvslIafF5LluORJfJKydMGELHtYe_ydtBIrVuomEnOZ4jfZQgtkqWxtG-s7vhbyir4kNjLyHKyDyh1SDubBMlhSI7Mq-M5RVtwnwFqZiOeUkIgHJFgcGQn0Plb1AkAAAAAAAAAAAAAAAAAAAAAAMwWY_9_oDOV75noniBViOVmVPUQqzcW8G3P8nuUD6Q";
+    const PASSWORD: &[u8; 32] = b"32KFFT_i4UpkJmyPwY2TGzgHpxfXs7zS";
+
+    fn event(name: &str, body: Value) -> Event {
+        let encrypted_access_key = EncryptedAccessKey::parse(ACCESS_KEY).unwrap();
+        let access_key = AccessKey::parse(&encrypted_access_key, PASSWORD).unwrap();
+        Event::new(
+            &access_key,
+            &encrypted_access_key,
+            name,
+            HeaderMap::new(),
+            body.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_filtered_events_are_dropped_and_others_reshaped() {
+        let processors = json!([
+            { "processor": "filter", "name": "^internal\\." },
+            { "processor": "map", "rename": { "/customer/mail": "/customer/email", "/missing": "/other" } },
+            { "processor": "enrich", "fields": { "/tags/source": "api" } },
+        ])
+        .to_string()
+        .parse::<EventProcessors>()
+        .expect("Failed to parse processors");
+
+        assert!(processors
+            .apply(event("internal.heartbeat", json!({})))
+            .is_none());
+
+        let event = processors
+            .apply(event(
+                "customer.created",
+                json!({ "customer": { "mail": "jane@example.com", "name": "Jane" } }),
+            ))
+            .expect("Event should be kept");
+        assert_eq!(
+            serde_json::from_str::<Value>(&event.body).unwrap(),
+            json!({
+                "customer": { "email": "jane@example.com", "name": "Jane" },
+                "tags": { "source": "api" },
+            })
+        );
+
+        // Bodies that are not JSON are kept as they are
+        let mut raw = self::event("customer.created", json!({}));
+        raw.body = "not json".to_string();
+        assert_eq!(processors.apply(raw).unwrap().body, "not json");
+
+        assert!("[{\"processor\": \"filter\", \"name\": \"(\"}]"
+            .parse::<EventProcessors>()
+            .is_err());
+        assert!(""
+            .parse::<EventProcessors>()
+            .unwrap()
+            .apply(event)
+            .is_some());
+    }
+}
@@ -0,0 +1,136 @@
+use integrationos_domain::{Event, Store};
+use std::{
+    collections::{BTreeMap, HashSet},
+    str::FromStr,
+};
+
+/// Tenants whose events are written to a dedicated collection, parsed from a comma separated
+/// list of client ids. Events of every other tenant share the default events collection.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventRouting {
+    dedicated: HashSet<String>,
+}
+
+impl FromStr for EventRouting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            dedicated: s
+                .split(',')
+                .map(str::trim)
+                .filter(|client_id| !client_id.is_empty())
+                .map(ToString::to_string)
+                .collect(),
+        })
+    }
+}
+
+impl EventRouting {
+    /// Name of the collection the event is written to
+    pub fn collection(&self, event: &Event) -> String {
+        self.collection_for(&event.ownership.client_id)
+    }
+
+    /// Name of the collection the events of the client are written to
+    pub fn collection_for(&self, client_id: &str) -> String {
+        if self.dedicated.contains(client_id) {
+            // Collection names can't hold characters such as `$`, keep the safe ones only
+            let client_id = client_id
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect::<String>();
+            format!("{}-{client_id}", Store::Events)
+        } else {
+            Store::Events.to_string()
+        }
+    }
+
+    /// Names of every collection events are written to, the shared one first
+    pub fn collections(&self) -> Vec<String> {
+        let mut collections = vec![Store::Events.to_string()];
+        collections.extend(
+            self.dedicated
+                .iter()
+                .map(|client_id| self.collection_for(client_id)),
+        );
+        collections
+    }
+
+    /// Groups a buffer of events by the collection each of them is written to
+    pub fn group(&self, events: Vec<Event>) -> BTreeMap<String, Vec<Event>> {
+        let mut groups = BTreeMap::<String, Vec<Event>>::new();
+        for event in events {
+            groups
+                .entry(self.collection(&event))
+                .or_default()
+                .push(event);
+        }
+        groups
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http::HeaderMap;
+    use integrationos_domain::{encrypted_access_key::EncryptedAccessKey, AccessKey};
+
+    const ACCESS_KEY: &str = "id_test_1_Q71YUIZydcgSwJQNOUCHhaTMqmIvslIafF5LluORJfJKydMGELHtYe_ydtBIrVuomEnOZ4jfZQgtkqWxtG-s7vhbyir4kNjLyHKyDyh1SDubBMlhSI7Mq-M5RVtwnwFqZiOeUkIgHJFgcGQn0Plb1AkAAAAAAAAAAAAAAAAAAAAAAMwWY_9_oDOV75noniBViOVmVPUQqzcW8G3P8nuUD6Q";
+    const PASSWORD: &[u8; 32] = b"32KFFT_i4UpkJmyPwY2TGzgHpxfXs7zS";
+
+    fn event(client_id: &str) -> Event {
+        let encrypted_access_key = EncryptedAccessKey::parse(ACCESS_KEY).unwrap();
+        let access_key = AccessKey::parse(&encrypted_access_key, PASSWORD).unwrap();
+        let mut event = Event::new(
+            &access_key,
+            &encrypted_access_key,
+            "event",
+            HeaderMap::new(),
+            "{}".to_string(),
+        );
+        event.ownership.client_id = client_id.to_string();
+        event
+    }
+
+    #[test]
+    fn test_dedicated_tenant_events_land_in_their_own_collection() {
+        let routing = "big-tenant, other$tenant".parse::<EventRouting>().unwrap();
+
+        let groups = routing.group(vec![
+            event("big-tenant"),
+            event("small-tenant"),
+            event("big-tenant"),
+            event("other$tenant"),
+            event("another-tenant"),
+        ]);
+
+        let shared = Store::Events.to_string();
+        let big_tenant = format!("{shared}-big-tenant");
+        let other_tenant = format!("{shared}-other_tenant");
+        assert_eq!(
+            groups.keys().collect::<Vec<_>>(),
+            [&shared, &big_tenant, &other_tenant]
+        );
+        assert_eq!(groups[&big_tenant].len(), 2);
+        assert!(groups[&big_tenant]
+            .iter()
+            .all(|event| event.ownership.client_id == "big-tenant"));
+        assert_eq!(groups[&shared].len(), 2);
+        assert_eq!(groups[&other_tenant].len(), 1);
+
+        let mut collections = routing.collections();
+        collections.sort();
+        assert_eq!(collections, [shared.clone(), big_tenant, other_tenant]);
+
+        let groups = EventRouting::default().group(vec![event("big-tenant")]);
+        assert_eq!(groups.keys().collect::<Vec<_>>(), [&shared]);
+        assert_eq!(EventRouting::default().collections(), [shared]);
+    }
+}
@@ -0,0 +1,270 @@
+use mongodb::error::{Error as MongoError, ErrorKind};
+use std::{future::Future, time::Duration};
+use tracing::{error, warn};
+
+/// Counter of the events whose save failed and was retried, counted on every retry
+pub const EVENT_SAVE_RETRIES_COUNTER: &str = "event_save_retries_total";
+/// Counter of the events moved to the dead letter store once their retries were exhausted
+pub const EVENTS_DEAD_LETTERED_COUNTER: &str = "events_dead_lettered_total";
+/// Counter of the events lost because the dead letter store could not take them either
+pub const EVENTS_LOST_COUNTER: &str = "events_lost_total";
+
+/// Code of the write errors of documents whose `_id` is already in the collection
+const DUPLICATE_KEY_CODE: i32 = 11000;
+
+/// How failed inserts are retried. The wait before each retry doubles from `base_delay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InsertRetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+impl InsertRetryPolicy {
+    /// Wait before the `retry`-th retry, counted from 1
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_delay.saturating_mul(factor)
+    }
+}
+
+/// Failure of an unordered insert
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsertFailure {
+    /// Indices of the documents that were not inserted, none if it is not known which ones
+    /// were, in which case they are all attempted again
+    pub failed: Option<Vec<usize>>,
+    pub message: String,
+}
+
+impl From<MongoError> for InsertFailure {
+    fn from(e: MongoError) -> Self {
+        let failed = match e.kind.as_ref() {
+            // Documents rejected for their `_id` were inserted by an earlier attempt whose
+            // response was lost, they are not inserted twice
+            ErrorKind::BulkWrite(failure) => failure.write_errors.as_ref().map(|errors| {
+                errors
+                    .iter()
+                    .filter(|error| error.code != DUPLICATE_KEY_CODE)
+                    .map(|error| error.index)
+                    .collect()
+            }),
+            _ => None,
+        };
+
+        Self {
+            failed,
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Inserts `docs`, retrying the documents that failed to be inserted as per `policy`. `insert`
+/// is expected to make unordered inserts, so that a failing document does not stop the
+/// following ones from being inserted. Returns the documents that could not be inserted once
+/// the retries are exhausted.
+pub async fn insert_with_retry<T, F, Fut>(
+    mut docs: Vec<T>,
+    policy: InsertRetryPolicy,
+    mut insert: F,
+) -> Result<(), (Vec<T>, InsertFailure)>
+where
+    T: Clone,
+    F: FnMut(Vec<T>) -> Fut,
+    Fut: Future<Output = Result<(), InsertFailure>>,
+{
+    let mut retry = 0;
+    loop {
+        let failure = match insert(docs.clone()).await {
+            Ok(()) => return Ok(()),
+            Err(failure) => failure,
+        };
+
+        if let Some(failed) = &failure.failed {
+            docs = docs
+                .into_iter()
+                .enumerate()
+                .filter_map(|(index, doc)| failed.contains(&index).then_some(doc))
+                .collect();
+            if docs.is_empty() {
+                return Ok(());
+            }
+        }
+
+        if retry == policy.max_retries {
+            return Err((docs, failure));
+        }
+        retry += 1;
+        metrics::counter!(EVENT_SAVE_RETRIES_COUNTER, docs.len() as u64);
+
+        let delay = policy.delay(retry);
+        warn!(
+            "Could not insert {} documents: {}, retrying in {delay:?}",
+            docs.len(),
+            failure.message
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Inserts `docs` as per [`insert_with_retry`], and hands the documents that could not be
+/// inserted to `dead_letter` so that they are not lost. Retries, dead lettered and lost
+/// documents are counted in [`EVENT_SAVE_RETRIES_COUNTER`], [`EVENTS_DEAD_LETTERED_COUNTER`]
+/// and [`EVENTS_LOST_COUNTER`].
+pub async fn insert_or_dead_letter<T, F, Fut, D, DFut>(
+    docs: Vec<T>,
+    policy: InsertRetryPolicy,
+    insert: F,
+    dead_letter: D,
+) -> Result<(), InsertFailure>
+where
+    T: Clone,
+    F: FnMut(Vec<T>) -> Fut,
+    Fut: Future<Output = Result<(), InsertFailure>>,
+    D: FnOnce(Vec<T>) -> DFut,
+    DFut: Future<Output = Result<(), InsertFailure>>,
+{
+    let Err((docs, failure)) = insert_with_retry(docs, policy, insert).await else {
+        return Ok(());
+    };
+
+    error!(
+        "Could not insert {} documents after {} retries: {}, moving them to the dead letter store",
+        docs.len(),
+        policy.max_retries,
+        failure.message
+    );
+    let count = docs.len() as u64;
+    let res = dead_letter(docs).await;
+    match res {
+        Ok(()) => metrics::counter!(EVENTS_DEAD_LETTERED_COUNTER, count),
+        Err(_) => metrics::counter!(EVENTS_LOST_COUNTER, count),
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::helper::prometheus_handle;
+    use metrics_exporter_prometheus::PrometheusHandle;
+    use std::sync::Mutex;
+
+    const POLICY: InsertRetryPolicy = InsertRetryPolicy {
+        max_retries: 2,
+        base_delay: Duration::from_millis(1),
+    };
+
+    #[tokio::test]
+    async fn test_batch_is_dead_lettered_once_retries_are_exhausted() {
+        let attempts = Mutex::new(vec![]);
+        let dead_letters = Mutex::new(vec![]);
+
+        insert_or_dead_letter(
+            vec![1, 2, 3],
+            POLICY,
+            |docs| {
+                attempts.lock().unwrap().push(docs);
+                async {
+                    Err(InsertFailure {
+                        failed: None,
+                        message: "Connection reset".to_string(),
+                    })
+                }
+            },
+            |docs| {
+                dead_letters.lock().unwrap().extend(docs);
+                async { Ok(()) }
+            },
+        )
+        .await
+        .expect("Batch is dead lettered");
+
+        assert_eq!(attempts.into_inner().unwrap(), vec![vec![1, 2, 3]; 3]);
+        assert_eq!(dead_letters.into_inner().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_only_documents_not_inserted_are_retried() {
+        let attempts = Mutex::new(vec![]);
+
+        insert_or_dead_letter(
+            vec![1, 2, 3, 4],
+            POLICY,
+            |docs| {
+                let mut attempts = attempts.lock().unwrap();
+                attempts.push(docs);
+                let result = match attempts.len() {
+                    1 => Err(InsertFailure {
+                        failed: Some(vec![1, 3]),
+                        message: "Write errors".to_string(),
+                    }),
+                    _ => Ok(()),
+                };
+                async { result }
+            },
+            |_| async { panic!("Batch was inserted") },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            attempts.into_inner().unwrap(),
+            vec![vec![1, 2, 3, 4], vec![2, 4]]
+        );
+    }
+
+    /// Value of the counter `name` in the metrics rendered by `handle`
+    fn counter(handle: &PrometheusHandle, name: &str) -> u64 {
+        handle
+            .render()
+            .lines()
+            .find_map(|line| line.strip_prefix(&format!("{name} ")))
+            .map_or(0, |value| value.parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_retries_and_dead_letters_are_counted() {
+        let handle = prometheus_handle().unwrap();
+        let retries = counter(&handle, EVENT_SAVE_RETRIES_COUNTER);
+        let dead_lettered = counter(&handle, EVENTS_DEAD_LETTERED_COUNTER);
+        let lost = counter(&handle, EVENTS_LOST_COUNTER);
+        let failure = |failed| InsertFailure {
+            failed,
+            message: "Write errors".to_string(),
+        };
+
+        // The first two documents fail every time, they are retried twice then dead lettered
+        insert_or_dead_letter(
+            vec![1, 2, 3],
+            POLICY,
+            |_| async { Err(failure(Some(vec![0, 1]))) },
+            |_| async { Ok(()) },
+        )
+        .await
+        .unwrap();
+        // Other tests count concurrently, the counters only go up by at least as much
+        assert!(counter(&handle, EVENT_SAVE_RETRIES_COUNTER) >= retries + 4);
+        assert!(counter(&handle, EVENTS_DEAD_LETTERED_COUNTER) >= dead_lettered + 2);
+
+        insert_or_dead_letter(
+            vec![1],
+            POLICY,
+            |_| async { Err(failure(None)) },
+            |_| async { Err(failure(None)) },
+        )
+        .await
+        .unwrap_err();
+        assert!(counter(&handle, EVENTS_LOST_COUNTER) > lost);
+    }
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        let policy = InsertRetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+    }
+}
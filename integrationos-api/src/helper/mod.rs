@@ -1,3 +1,39 @@
+pub mod address;
+pub mod batch_insert;
+pub mod buffer;
+pub mod cache_status;
+pub mod call_stats;
+pub mod circuit_breaker;
+pub mod cost;
+pub mod dead_letter;
+pub mod event_channel;
+pub mod event_denylist;
+pub mod event_encryption;
+pub mod event_ordering;
+pub mod event_processors;
+pub mod event_routing;
+pub mod insert_retry;
+pub mod pkce_verifiers;
+pub mod prometheus;
 pub mod shape_mongo_filter;
+pub mod warmup;
 
+pub use address::*;
+pub use batch_insert::*;
+pub use buffer::*;
+pub use cache_status::*;
+pub use call_stats::*;
+pub use circuit_breaker::*;
+pub use cost::*;
+pub use dead_letter::*;
+pub use event_channel::*;
+pub use event_denylist::*;
+pub use event_encryption::*;
+pub use event_ordering::*;
+pub use event_processors::*;
+pub use event_routing::*;
+pub use insert_retry::*;
+pub use pkce_verifiers::*;
+pub use prometheus::*;
 pub use shape_mongo_filter::*;
+pub use warmup::*;
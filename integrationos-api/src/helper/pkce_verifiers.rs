@@ -0,0 +1,86 @@
+use bson::doc;
+use chrono::{DateTime, Duration, Utc};
+use integrationos_domain::{IntegrationOSError, Store};
+use mongodb::{options::IndexOptions, Collection, Database, IndexModel};
+use serde::{Deserialize, Serialize};
+
+/// PKCE code verifiers issued on the authorize step of an OAuth flow, keyed by the state of the
+/// flow, until its token exchange. Stored in the control database so that the token exchange may
+/// reach any instance, and dropped by a TTL index once they expire. Expired verifiers the index
+/// has not dropped yet can't be taken either.
+#[derive(Clone)]
+pub struct PkceVerifiers {
+    verifiers: Collection<IssuedVerifier>,
+    ttl: Duration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IssuedVerifier {
+    #[serde(rename = "_id")]
+    state: String,
+    code_verifier: String,
+    expires_at: bson::DateTime,
+}
+
+impl PkceVerifiers {
+    /// Verifiers kept in `db`, whose collection is indexed for them to be dropped once expired
+    pub async fn new(db: &Database, ttl: Duration) -> Result<Self, IntegrationOSError> {
+        let verifiers = db.collection(&Store::PkceVerifiers.to_string());
+        verifiers
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "expiresAt": 1 })
+                    .options(
+                        IndexOptions::builder()
+                            .expire_after(std::time::Duration::ZERO)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await?;
+
+        Ok(Self { verifiers, ttl })
+    }
+
+    pub async fn insert(
+        &self,
+        state: String,
+        code_verifier: String,
+        now: DateTime<Utc>,
+    ) -> Result<(), IntegrationOSError> {
+        self.verifiers
+            .insert_one(
+                IssuedVerifier {
+                    state,
+                    code_verifier,
+                    expires_at: bson::DateTime::from_millis((now + self.ttl).timestamp_millis()),
+                },
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Verifier issued for `state`, which can't be taken again, by this instance or any other
+    pub async fn take(
+        &self,
+        state: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<String>, IntegrationOSError> {
+        let issued = self
+            .verifiers
+            .find_one_and_delete(
+                doc! {
+                    "_id": state,
+                    "expiresAt": { "$gt": bson::DateTime::from_millis(now.timestamp_millis()) },
+                },
+                None,
+            )
+            .await?;
+
+        Ok(issued.map(|issued| issued.code_verifier))
+    }
+}
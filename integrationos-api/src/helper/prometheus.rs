@@ -0,0 +1,123 @@
+use super::{
+    DEAD_LETTER_ALERTS_COUNTER, DEAD_LETTER_EVENTS_GAUGE, EVENTS_DEAD_LETTERED_COUNTER,
+    EVENTS_DENIED_COUNTER, EVENTS_DROPPED_COUNTER, EVENTS_LOST_COUNTER, EVENT_CHANNEL_DEPTH_GAUGE,
+    EVENT_SAVE_RETRIES_COUNTER,
+};
+use integrationos_domain::{IntegrationOSError, InternalError};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::sync::OnceLock;
+
+/// Counter of the requests served, labelled with [`METHOD_LABEL`] and [`STATUS_LABEL`]. Probes
+/// aren't counted.
+pub const HTTP_REQUESTS_COUNTER: &str = "http_requests_total";
+/// Histogram of the time taken to serve requests, labelled as [`HTTP_REQUESTS_COUNTER`]
+pub const HTTP_REQUEST_DURATION_HISTOGRAM: &str = "http_request_duration_seconds";
+/// Counter of the calls to platforms through the unified and passthrough APIs, labelled with
+/// [`PLATFORM_LABEL`], [`CONNECTION_LABEL`] and [`TYPE_LABEL`]
+pub const CONNECTION_CALLS_COUNTER: &str = "connection_calls_total";
+/// Gauge of the state of the circuit breaker of each connection, labelled with
+/// [`CONNECTION_LABEL`]: 0 when closed, 1 when half open and 2 when open
+pub const CIRCUIT_BREAKER_STATE_GAUGE: &str = "circuit_breaker_state";
+pub const METHOD_LABEL: &str = "method";
+pub const STATUS_LABEL: &str = "status";
+pub const PLATFORM_LABEL: &str = "platform";
+pub const CONNECTION_LABEL: &str = "connection";
+pub const TYPE_LABEL: &str = "type";
+
+/// Upper bounds, in seconds, of the buckets of [`HTTP_REQUEST_DURATION_HISTOGRAM`]
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+static HANDLE: OnceLock<Result<PrometheusHandle, String>> = OnceLock::new();
+
+/// Handle rendering the metrics of the process in the Prometheus text format. The recorder is
+/// installed by the first call, the following ones return the same handle.
+pub fn prometheus_handle() -> Result<PrometheusHandle, IntegrationOSError> {
+    HANDLE
+        .get_or_init(|| {
+            let handle = PrometheusBuilder::new()
+                .set_buckets_for_metric(
+                    Matcher::Full(HTTP_REQUEST_DURATION_HISTOGRAM.to_owned()),
+                    DURATION_BUCKETS,
+                )
+                .and_then(PrometheusBuilder::install_recorder)
+                .map_err(|e| e.to_string())?;
+            describe_metrics();
+
+            Ok(handle)
+        })
+        .clone()
+        .map_err(|e| {
+            InternalError::unknown(
+                &format!("Could not install the Prometheus recorder: {e}"),
+                None,
+            )
+        })
+}
+
+fn describe_metrics() {
+    metrics::describe_counter!(HTTP_REQUESTS_COUNTER, "number of requests served");
+    metrics::describe_histogram!(
+        HTTP_REQUEST_DURATION_HISTOGRAM,
+        metrics::Unit::Seconds,
+        "time taken to serve requests"
+    );
+    metrics::describe_counter!(
+        CONNECTION_CALLS_COUNTER,
+        "number of calls to platforms through the unified and passthrough APIs"
+    );
+    metrics::describe_gauge!(
+        CIRCUIT_BREAKER_STATE_GAUGE,
+        "state of the circuit breaker of the calls of a connection to its platform"
+    );
+    metrics::describe_counter!(
+        EVENTS_DROPPED_COUNTER,
+        "number of events dropped because the save buffer was full or closed"
+    );
+    metrics::describe_counter!(
+        EVENTS_DENIED_COUNTER,
+        "number of events dropped at ingestion because their type is denylisted"
+    );
+    metrics::describe_gauge!(
+        EVENT_CHANNEL_DEPTH_GAUGE,
+        "number of events waiting in the save buffer"
+    );
+    metrics::describe_counter!(
+        EVENT_SAVE_RETRIES_COUNTER,
+        "number of events whose save failed and was retried, counted on every retry"
+    );
+    metrics::describe_counter!(
+        EVENTS_DEAD_LETTERED_COUNTER,
+        "number of events moved to the dead letter store once their save retries were exhausted"
+    );
+    metrics::describe_counter!(
+        EVENTS_LOST_COUNTER,
+        "number of events that could be saved neither in their collection nor in the dead letter store"
+    );
+    metrics::describe_gauge!(
+        DEAD_LETTER_EVENTS_GAUGE,
+        "number of events that could not be saved, kept in the dead letter store"
+    );
+    metrics::describe_counter!(
+        DEAD_LETTER_ALERTS_COUNTER,
+        "number of times the dead letter events reached the alert threshold"
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_recorder_is_installed_once() {
+        let handle = prometheus_handle().unwrap();
+        let again = prometheus_handle().expect("The installed recorder should be reused");
+
+        metrics::counter!(HTTP_REQUESTS_COUNTER, 1, METHOD_LABEL => "GET", STATUS_LABEL => "200");
+        for rendered in [handle.render(), again.render()] {
+            assert!(rendered.contains("# TYPE http_requests_total counter"));
+            assert!(rendered.contains("http_requests_total{method=\"GET\",status=\"200\"}"));
+        }
+    }
+}
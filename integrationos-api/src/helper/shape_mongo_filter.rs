@@ -10,12 +10,16 @@ const ENVIRONMENT_STR: &str = "environment";
 const DUAL_ENVIRONMENT_HEADER: &str = "x-integrationos-show-all-environments";
 const LIMIT_STR: &str = "limit";
 const SKIP_STR: &str = "skip";
+const CURSOR_STR: &str = "cursor";
 
 #[derive(Debug, Clone)]
 pub struct MongoQuery {
     pub filter: Document,
     pub skip: u64,
     pub limit: u64,
+    /// Cursor of a keyset paginated read, empty for its first page. Reads without one are
+    /// paginated by `skip`.
+    pub cursor: Option<String>,
 }
 
 pub fn shape_mongo_filter(
@@ -26,6 +30,7 @@ pub fn shape_mongo_filter(
     let mut filter = doc! {};
     let mut skip = 0;
     let mut limit = 20;
+    let mut cursor = None;
 
     if let Some(q) = query {
         for (key, value) in q.0.iter() {
@@ -33,6 +38,8 @@ pub fn shape_mongo_filter(
                 limit = value.parse().unwrap_or(20);
             } else if key == SKIP_STR {
                 skip = value.parse().unwrap_or(0);
+            } else if key == CURSOR_STR {
+                cursor = Some(value.clone());
             } else {
                 match value.as_str() {
                     "true" => filter.insert(key, true),
@@ -68,6 +75,7 @@ pub fn shape_mongo_filter(
         filter,
         limit,
         skip,
+        cursor,
     }
 }
 
@@ -75,7 +83,7 @@ pub fn shape_mongo_filter(
 mod test {
     use super::shape_mongo_filter;
     use crate::helper::shape_mongo_filter::{
-        MongoQuery, DELETED_STR, DUAL_ENVIRONMENT_HEADER, ENVIRONMENT_STR, LIMIT_STR,
+        MongoQuery, CURSOR_STR, DELETED_STR, DUAL_ENVIRONMENT_HEADER, ENVIRONMENT_STR, LIMIT_STR,
         OWNERSHIP_STR, SKIP_STR,
     };
     use axum::extract::Query;
@@ -85,9 +93,10 @@ mod test {
         {
             connection_definition::{ConnectionDefinitionType, Paths},
             environment::Environment,
-            event_access::EventAccess,
+            event_access::{EventAccess, EventAccessScope},
             ownership::Ownership,
             record_metadata::RecordMetadata,
+            ConnectionMode,
         },
     };
     use std::{collections::BTreeMap, sync::Arc};
@@ -106,12 +115,14 @@ mod test {
             filter: mut doc,
             skip,
             limit,
+            cursor,
         } = shape_mongo_filter(Some(Query(params.clone())), None, None);
         assert_eq!(doc.get_str(OWNERSHIP_STR).unwrap(), "foo");
         assert_eq!(doc.get_str(ENVIRONMENT_STR).unwrap(), "bar");
         assert!(!doc.get_bool(DELETED_STR).unwrap());
         assert_eq!(limit, 10);
         assert_eq!(skip, 10);
+        assert_eq!(cursor, None);
 
         doc.insert(DELETED_STR, true);
         assert!(doc.get_bool(DELETED_STR).unwrap());
@@ -128,6 +139,7 @@ mod test {
             paths: Paths::default(),
             access_key: "access_key".to_string(),
             environment: Environment::Test,
+            scopes: EventAccessScope::all(),
             record_metadata: RecordMetadata::default(),
             throughput: 1000,
         });
@@ -161,6 +173,7 @@ mod test {
             paths: Paths::default(),
             access_key: "access_key".to_string(),
             environment: Environment::Test,
+            scopes: EventAccessScope::all(),
             record_metadata: RecordMetadata::default(),
             throughput: 1000,
         });
@@ -173,4 +186,30 @@ mod test {
 
         assert!(!doc.contains_key(ENVIRONMENT_STR));
     }
+
+    #[test]
+    fn filtering_by_connection_mode() {
+        let params = BTreeMap::from([(
+            "connectionMode".to_string(),
+            ConnectionMode::Sandbox.to_string(),
+        )]);
+
+        let MongoQuery { filter: doc, .. } = shape_mongo_filter(Some(Query(params)), None, None);
+
+        assert_eq!(doc.get_str("connectionMode").unwrap(), "sandbox");
+    }
+
+    #[test]
+    fn reading_by_cursor() {
+        let params = BTreeMap::from([
+            (CURSOR_STR.to_string(), "abc".to_string()),
+            ("name".to_string(), "foo".to_string()),
+        ]);
+
+        let MongoQuery { filter, cursor, .. } = shape_mongo_filter(Some(Query(params)), None, None);
+
+        assert_eq!(cursor.as_deref(), Some("abc"));
+        assert!(!filter.contains_key(CURSOR_STR));
+        assert_eq!(filter.get_str("name").unwrap(), "foo");
+    }
 }
@@ -0,0 +1,194 @@
+use crate::{
+    logic::health::{check_dependencies, Status},
+    server::AppState,
+};
+use bson::doc;
+use integrationos_domain::{IntegrationOSError, InternalError};
+use std::{
+    future::Future,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use strum::{Display, EnumString};
+use tracing::{info, warn};
+
+/// Step run once the instance started, `/readyz` reports it as not ready until every
+/// configured warmup completed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "camelCase")]
+pub enum Warmup {
+    /// Loads the connection definitions into their cache
+    ConnectionDefinitions,
+    /// Waits until the dependencies checked by `/readyz` are up
+    Dependencies,
+}
+
+/// Warmups of the instance, parsed from a comma separated list, run in that order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Warmups(Vec<Warmup>);
+
+impl FromStr for Warmups {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|warmup| !warmup.is_empty())
+            .map(|warmup| {
+                warmup
+                    .parse()
+                    .map_err(|_| format!("Invalid warmup {warmup}"))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// Whether the warmups of the instance completed, shared by the task running them and the
+/// readiness probe
+#[derive(Debug, Clone)]
+pub struct WarmupStatus {
+    done: Arc<AtomicBool>,
+}
+
+impl WarmupStatus {
+    /// Status of the `warmups` about to run, done right away when there are none
+    pub fn new(warmups: &Warmups) -> Self {
+        Self {
+            done: Arc::new(AtomicBool::new(warmups.0.is_empty())),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done.load(Ordering::Acquire)
+    }
+}
+
+/// Runs each warmup until it succeeds, waiting `retry_interval` between two attempts, then
+/// marks the warmups as done
+pub async fn warm_up<F, Fut>(
+    warmups: Warmups,
+    status: WarmupStatus,
+    retry_interval: Duration,
+    run: F,
+) where
+    F: Fn(Warmup) -> Fut,
+    Fut: Future<Output = Result<(), IntegrationOSError>>,
+{
+    for warmup in warmups.0 {
+        while let Err(e) = run(warmup).await {
+            warn!("Warmup {warmup} failed, retrying in {retry_interval:?}: {e}");
+            tokio::time::sleep(retry_interval).await;
+        }
+        info!("Warmup {warmup} completed");
+    }
+
+    status.done.store(true, Ordering::Release);
+}
+
+pub async fn run_warmup(state: &AppState, warmup: Warmup) -> Result<(), IntegrationOSError> {
+    match warmup {
+        Warmup::ConnectionDefinitions => {
+            let definitions = state
+                .app_stores
+                .connection_config
+                .get_many(Some(doc! { "deleted": false }), None, None, None, None)
+                .await?;
+            for definition in &definitions {
+                state
+                    .connection_definitions_cache
+                    .set(&definition.id, definition)
+                    .await?;
+            }
+            info!("Cached {} connection definitions", definitions.len());
+
+            Ok(())
+        }
+        Warmup::Dependencies => {
+            let checks = check_dependencies(state).await;
+            match checks
+                .iter()
+                .find(|(_, check)| check.status == Status::Down)
+            {
+                Some((name, check)) => Err(InternalError::connection_error(
+                    &format!(
+                        "{name} is down: {}",
+                        check.error.as_deref().unwrap_or_default()
+                    ),
+                    None,
+                )),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::AtomicU32;
+    use tokio::sync::Notify;
+
+    #[test]
+    fn test_warmups_are_parsed_in_order() {
+        assert_eq!(
+            " dependencies, connectionDefinitions ,".parse::<Warmups>(),
+            Ok(Warmups(vec![
+                Warmup::Dependencies,
+                Warmup::ConnectionDefinitions
+            ]))
+        );
+        assert_eq!("".parse::<Warmups>(), Ok(Warmups::default()));
+        assert!("indexes".parse::<Warmups>().is_err());
+        assert!(WarmupStatus::new(&Warmups::default()).is_done());
+    }
+
+    #[tokio::test]
+    async fn test_status_is_done_once_every_warmup_succeeded() {
+        let warmups = "dependencies,connectionDefinitions"
+            .parse::<Warmups>()
+            .unwrap();
+        let status = WarmupStatus::new(&warmups);
+        let attempts = Arc::new(AtomicU32::new(0));
+        let cached = Arc::new(Notify::new());
+
+        let task = tokio::spawn(warm_up(
+            warmups,
+            status.clone(),
+            Duration::from_millis(1),
+            {
+                let attempts = attempts.clone();
+                let cached = cached.clone();
+                move |warmup| {
+                    let attempts = attempts.clone();
+                    let cached = cached.clone();
+                    async move {
+                        match warmup {
+                            // Dependencies come up on the third attempt
+                            Warmup::Dependencies if attempts.fetch_add(1, Ordering::SeqCst) < 2 => {
+                                Err(InternalError::connection_error("Connection refused", None))
+                            }
+                            Warmup::Dependencies => Ok(()),
+                            Warmup::ConnectionDefinitions => {
+                                cached.notified().await;
+                                Ok(())
+                            }
+                        }
+                    }
+                }
+            },
+        ));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert!(!status.is_done());
+
+        cached.notify_one();
+        task.await.unwrap();
+        assert!(status.is_done());
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{router::ServerResponse, server::AppState};
+use axum::{extract::State, Json};
+use http::HeaderValue;
+use integrationos_domain::{ApplicationError, Id, IntegrationOSError};
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, sync::Arc};
+use strum::{Display, EnumString};
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+enum CacheName {
+    Connections,
+    ConnectionDefinitions,
+    OauthDefinitions,
+    EventAccess,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidateCacheRequest {
+    pub cache: String,
+    /// Key of the entry to invalidate, every entry of the cache is invalidated if unset. The
+    /// connection key for `connections`, the definition id for `connection-definitions` and
+    /// `oauth-definitions`, and the access key for `event-access`.
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidateCacheResponse {
+    pub cache: String,
+    pub invalidated: u64,
+}
+
+/// Invalidates entries of the local caches of this instance, so that changes made directly to
+/// the database are picked up before the entries expire
+pub async fn invalidate_cache(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<InvalidateCacheRequest>,
+) -> Result<Json<ServerResponse<InvalidateCacheResponse>>, IntegrationOSError> {
+    let cache = CacheName::from_str(&payload.cache).map_err(|_| {
+        ApplicationError::not_found(&format!("Unknown cache {}", payload.cache), None)
+    })?;
+
+    let invalidated = match (cache, payload.key.as_deref()) {
+        (CacheName::Connections, Some(key)) => {
+            state
+                .connections_cache
+                .invalidate_if(|(_, connection_key)| connection_key == key)
+                .await
+        }
+        (CacheName::Connections, None) => state.connections_cache.invalidate_all().await,
+        (CacheName::ConnectionDefinitions, Some(key)) => {
+            state
+                .connection_definitions_cache
+                .invalidate(&parse_id(key)?)
+                .await
+        }
+        (CacheName::ConnectionDefinitions, None) => {
+            state.connection_definitions_cache.invalidate_all().await
+        }
+        (CacheName::OauthDefinitions, Some(key)) => {
+            state
+                .connection_oauth_definitions_cache
+                .invalidate(&parse_id(key)?)
+                .await
+        }
+        (CacheName::OauthDefinitions, None) => {
+            state
+                .connection_oauth_definitions_cache
+                .invalidate_all()
+                .await
+        }
+        (CacheName::EventAccess, Some(key)) => {
+            let key = HeaderValue::from_str(key)
+                .map_err(|_| ApplicationError::bad_request("Invalid access key", None))?;
+            state.event_access_cache.invalidate(&key).await
+        }
+        (CacheName::EventAccess, None) => state.event_access_cache.invalidate_all().await,
+    };
+    info!("Invalidated {invalidated} entries of the {cache} cache");
+
+    Ok(Json(ServerResponse::new(
+        "cacheInvalidation",
+        InvalidateCacheResponse {
+            cache: cache.to_string(),
+            invalidated,
+        },
+    )))
+}
+
+fn parse_id(key: &str) -> Result<Id, IntegrationOSError> {
+    Id::from_str(key).map_err(|_| ApplicationError::bad_request(&format!("Invalid id {key}"), None))
+}
@@ -1,4 +1,4 @@
-use super::{create, delete, read, update, HookExt, PublicExt, RequestExt};
+use super::{create, delete, import, read, update, HookExt, PublicExt, RequestExt};
 use crate::server::{AppState, AppStores};
 use axum::{
     extract::{Json, Path, State},
@@ -26,6 +26,7 @@ pub fn get_router() -> Router<Arc<AppState>> {
             "/",
             post(create::<CreateRequest, CommonModel>).get(read::<CreateRequest, CommonModel>),
         )
+        .route("/import", post(import::<CreateRequest, CommonModel>))
         .route(
             "/:id",
             patch(update::<CreateRequest, CommonModel>)
@@ -1,5 +1,6 @@
 use super::{delete, read, PublicExt, RequestExt};
 use crate::{
+    helper::{send_event, CallStats},
     logic::event_access::{
         generate_event_access, get_client_throughput, CreateEventAccessPayloadWithOwnership,
     },
@@ -16,20 +17,26 @@ use chrono::Utc;
 use http::HeaderMap;
 use integrationos_domain::{
     algebra::MongoStore,
-    connection_definition::ConnectionDefinition,
-    domain::connection::SanitizedConnection,
+    connection_definition::{ConnectionDefinition, DownstreamRetryPolicy},
+    connection_model_definition::{CrudAction, TimeoutConfig},
+    domain::connection::{expiry::CredentialExpiry, SanitizedConnection},
+    encrypted_access_key::EncryptedAccessKey,
+    encrypted_data::PASSWORD_LENGTH,
     event_access::EventAccess,
     id::{prefix::IdPrefix, Id},
+    input_validation::validate_inputs,
     record_metadata::RecordMetadata,
     settings::Settings,
-    ApplicationError, Connection, IntegrationOSError, InternalError, Throughput,
+    AccessKey, ApplicationError, Connection, ConnectionMode, Event, IntegrationOSError,
+    InternalError, OAuth, Operation, OperationKind, OperationTracker, Throughput, Unit,
 };
+use integrationos_unified::rate_limit::RateLimitState;
 use mongodb::bson::doc;
 use mongodb::bson::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::{collections::HashMap, sync::Arc};
-use tracing::error;
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
+use tracing::{error, info};
 use validator::Validate;
 
 pub fn get_router() -> Router<Arc<AppState>> {
@@ -38,6 +45,13 @@ pub fn get_router() -> Router<Arc<AppState>> {
         .route("/", get(read::<CreateConnectionPayload, Connection>))
         .route("/:id", patch(update_connection))
         .route("/:id", axum_delete(delete_connection))
+        .route(
+            "/reauthorization-required",
+            get(get_reauthorization_required),
+        )
+        .route("/:id/rate-limit", get(get_connection_rate_limit))
+        .route("/:id/observability", get(get_connection_observability))
+        .route("/:id/openapi", get(super::openapi::get_connection_openapi))
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Validate)]
@@ -48,6 +62,36 @@ pub struct CreateConnectionPayload {
     pub group: String,
     pub auth_form_data: HashMap<String, String>,
     pub active: bool,
+    #[serde(default)]
+    pub mode: ConnectionMode,
+    /// Key the platform signs its webhooks with, kept in the secrets service
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+    /// Unix timestamp, in milliseconds, at which the credentials of the connection expire
+    #[serde(default)]
+    pub credentials_expire_at: Option<i64>,
+}
+
+/// Rejects auth form data violating the input rules of the connection definition, listing the
+/// violations in the meta of the error
+fn validate_auth_form_data(
+    connection_config: &ConnectionDefinition,
+    auth_form_data: &HashMap<String, String>,
+) -> Result<Unit, IntegrationOSError> {
+    let violations = validate_inputs(&connection_config.input_rules, auth_form_data);
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    let messages = violations
+        .iter()
+        .map(|violation| violation.message.as_str())
+        .collect::<Vec<_>>()
+        .join("; ");
+    Err(
+        ApplicationError::bad_request(&format!("Invalid auth form data: {messages}"), None)
+            .set_meta(&json!({ "violations": violations })),
+    )
 }
 
 async fn test_connection(
@@ -105,6 +149,127 @@ async fn test_connection(
     Ok(())
 }
 
+/// Outcome of testing a single connection during a bulk validation
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionValidation {
+    pub id: Id,
+    pub key: Arc<str>,
+    pub platform: Arc<str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkValidationReport {
+    pub total: usize,
+    pub healthy: Vec<ConnectionValidation>,
+    pub unhealthy: Vec<ConnectionValidation>,
+}
+
+/// Tests `connections` one at a time, waiting `interval` between two tests so that platforms
+/// are not flooded with test requests
+async fn validate_connections<F, Fut>(
+    connections: Vec<Connection>,
+    interval: Duration,
+    mut validate: F,
+) -> BulkValidationReport
+where
+    F: FnMut(Connection) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut ticker = tokio::time::interval(interval.max(Duration::from_millis(1)));
+    let mut report = BulkValidationReport {
+        total: connections.len(),
+        ..Default::default()
+    };
+
+    for connection in connections {
+        ticker.tick().await;
+
+        let mut validation = ConnectionValidation {
+            id: connection.id,
+            key: connection.key.clone(),
+            platform: connection.platform.clone(),
+            error: None,
+        };
+
+        match validate(connection).await {
+            Ok(()) => report.healthy.push(validation),
+            Err(e) => {
+                validation.error = Some(e.to_string());
+                report.unhealthy.push(validation);
+            }
+        }
+    }
+
+    report
+}
+
+/// Runs the test of the connection definition of an existing connection against its stored
+/// credentials, and records the outcome in the health of the connection
+async fn revalidate_connection(state: &AppState, mut connection: Connection) -> Result<()> {
+    let result = async {
+        let connection_config = state
+            .app_stores
+            .connection_config
+            .get_one_by_id(&connection.connection_definition_id.to_string())
+            .await?
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Connection definition {} not found",
+                    connection.connection_definition_id
+                )
+            })?;
+
+        let secret = state
+            .secrets_client
+            .get(&connection.secrets_service_id, &connection.ownership.id)
+            .await?
+            .as_value()?;
+
+        test_connection(state, &connection_config, &secret).await
+    }
+    .await;
+
+    if connection
+        .health
+        .record(result.is_ok(), &state.config.health_thresholds())
+    {
+        tracing::info!(
+            "Connection {} is now {:?} after bulk validation",
+            connection.id,
+            connection.health.status
+        );
+    }
+
+    match bson::to_bson(&connection.health) {
+        Ok(health) => {
+            if let Err(e) = state
+                .app_stores
+                .connection
+                .update_one(
+                    &connection.id.to_string(),
+                    doc! { "$set": { "health": health } },
+                )
+                .await
+            {
+                error!(
+                    "Could not record health of connection {}: {e}",
+                    connection.id
+                );
+            }
+        }
+        Err(e) => error!(
+            "Could not serialize health of connection {}: {e}",
+            connection.id
+        ),
+    }
+
+    result
+}
+
 impl PublicExt<Connection> for CreateConnectionPayload {
     fn public(input: Connection) -> Value {
         SanitizedConnection {
@@ -116,6 +281,7 @@ impl PublicExt<Connection> for CreateConnectionPayload {
             key: input.key,
             group: input.group,
             environment: input.environment,
+            mode: input.mode,
             platform: input.platform,
             secrets_service_id: input.secrets_service_id,
             event_access_id: input.event_access_id,
@@ -123,6 +289,7 @@ impl PublicExt<Connection> for CreateConnectionPayload {
             throughput: input.throughput,
             ownership: input.ownership,
             oauth: input.oauth,
+            health: input.health,
             record_metadata: input.record_metadata,
         }
         .to_value()
@@ -177,6 +344,8 @@ pub async fn create_connection(
         }
     };
 
+    validate_auth_form_data(&connection_config, &payload.auth_form_data)?;
+
     let key = format!(
         "{}::{}::{}",
         access.environment,
@@ -198,6 +367,7 @@ pub async fn create_connection(
             paths: connection_config.paths.clone(),
             ownership: access.ownership.clone(),
             throughput: Some(throughput),
+            scopes: None,
         },
     )
     .map_err(|e| {
@@ -245,6 +415,13 @@ pub async fn create_connection(
             e
         })?;
 
+    let webhook_secret = match payload.webhook_secret {
+        Some(webhook_secret) => {
+            Some(create_webhook_secret(&state, webhook_secret, &access.ownership.id).await?)
+        }
+        None => None,
+    };
+
     let connection = Connection {
         id: Id::new(IdPrefix::Connection, Utc::now()),
         platform_version: connection_config.clone().platform_version,
@@ -255,6 +432,7 @@ pub async fn create_connection(
         group: payload.group,
         platform: connection_config.platform.into(),
         environment: event_access.environment,
+        mode: payload.mode,
         secrets_service_id: secret_result.id(),
         event_access_id: event_access.id,
         access_key: event_access.access_key,
@@ -265,6 +443,10 @@ pub async fn create_connection(
         },
         ownership: event_access.ownership,
         oauth: None,
+        health: Default::default(),
+        webhook_secret,
+        credential_expiry: CredentialExpiry::new(payload.credentials_expire_at),
+        credentials_rotated_at: None,
         record_metadata: RecordMetadata::default(),
     };
 
@@ -288,6 +470,7 @@ pub async fn create_connection(
         key: connection.key,
         group: connection.group,
         environment: connection.environment,
+        mode: connection.mode,
         platform: connection.platform,
         secrets_service_id: connection.secrets_service_id,
         event_access_id: connection.event_access_id,
@@ -295,6 +478,7 @@ pub async fn create_connection(
         throughput: connection.throughput,
         ownership: connection.ownership,
         oauth: connection.oauth,
+        health: connection.health,
         record_metadata: connection.record_metadata,
     }))
 }
@@ -308,6 +492,335 @@ pub struct UpdateConnectionPayload {
     pub throughput: Option<Throughput>,
     pub auth_form_data: Option<HashMap<String, String>>,
     pub active: Option<bool>,
+    pub mode: Option<ConnectionMode>,
+    pub webhook_secret: Option<String>,
+    /// Expiry of renewed credentials, an upcoming expiry is notified again
+    pub credentials_expire_at: Option<i64>,
+}
+
+/// Event named `name` emitted on behalf of `connection`, through its own access key
+pub(crate) fn connection_event(
+    state: &AppState,
+    connection: &Connection,
+    name: &str,
+    headers: HeaderMap,
+    payload: String,
+) -> Result<Event, IntegrationOSError> {
+    let encrypted_access_key = EncryptedAccessKey::parse(&connection.access_key).map_err(|e| {
+        error!(
+            "Could not parse access key of connection {}: {e}",
+            connection.id
+        );
+        InternalError::invalid_argument("Could not parse access key of connection", None)
+    })?;
+    let password: [u8; PASSWORD_LENGTH] = state
+        .config
+        .event_access_password
+        .as_bytes()
+        .try_into()
+        .map_err(|e| {
+            error!("event_access_password is not 32 bytes in length: {e}");
+            InternalError::decryption_error("event_access_password is not 32 bytes in length", None)
+        })?;
+    let access_key = AccessKey::parse(&encrypted_access_key, &password).map_err(|e| {
+        error!("Could not decrypt access key: {e}");
+        InternalError::decryption_error("Could not decrypt access key", None)
+    })?;
+
+    Ok(
+        Event::new(&access_key, &encrypted_access_key, name, headers, payload)
+            .with_connection_mode(connection.mode),
+    )
+}
+
+/// Emits a `{platform}::credentials-expiring` event for every connection whose credentials
+/// expire within `window`. The notification is claimed in the database before the event is
+/// sent, so that it is only sent once even with several instances sweeping.
+pub async fn notify_expiring_credentials(
+    state: &AppState,
+    window: chrono::Duration,
+) -> Result<usize, IntegrationOSError> {
+    let now = state.clock.now();
+    let connections = state
+        .app_stores
+        .connection
+        .get_many(
+            Some(doc! {
+                "credentialExpiry.expiresAt": { "$lte": (now + window).timestamp_millis() },
+                "credentialExpiry.notifiedAt": null,
+                "deleted": false,
+            }),
+            None,
+            None,
+            None,
+            None,
+        )
+        .await?;
+
+    let mut notified = 0;
+    for mut connection in connections {
+        if !connection.credential_expiry.notify(now, window) {
+            continue;
+        }
+
+        let claimed = state
+            .app_stores
+            .connection
+            .collection
+            .update_one(
+                doc! {
+                    "_id": connection.id.to_string(),
+                    "credentialExpiry.notifiedAt": null,
+                },
+                doc! {
+                    "$set": { "credentialExpiry.notifiedAt": connection.credential_expiry.notified_at }
+                },
+                None,
+            )
+            .await?;
+        if claimed.modified_count == 0 {
+            continue;
+        }
+
+        let payload = json!({
+            "connectionId": connection.id,
+            "key": connection.key,
+            "platform": connection.platform,
+            "expiresAt": connection.credential_expiry.expires_at,
+        });
+        let name = format!("{}::credentials-expiring", connection.platform);
+        let event = connection_event(
+            state,
+            &connection,
+            &name,
+            HeaderMap::new(),
+            payload.to_string(),
+        )?;
+        if !state.event_denylist.admits(&event) {
+            continue;
+        }
+        if let Err(e) = send_event(&state.event_tx, event) {
+            error!("Could not send credentials expiry event: {e}");
+            continue;
+        }
+
+        info!(
+            "Credentials of connection {} expire soon, notified",
+            connection.id
+        );
+        notified += 1;
+    }
+
+    Ok(notified)
+}
+
+async fn create_webhook_secret(
+    state: &AppState,
+    webhook_secret: String,
+    buildable_id: &str,
+) -> Result<String, IntegrationOSError> {
+    let secret = state
+        .secrets_client
+        .create(&Value::String(webhook_secret), buildable_id)
+        .await
+        .map_err(|e| {
+            error!("Error creating webhook secret for connection: {:?}", e);
+
+            e
+        })?;
+
+    Ok(secret.id())
+}
+
+/// Connection `id`, if it belongs to the owner and environment of `event_access`
+async fn get_accessible_connection(
+    state: &AppState,
+    event_access: &EventAccess,
+    id: &str,
+) -> Result<Connection, IntegrationOSError> {
+    let Some(connection) = state
+        .app_stores
+        .connection
+        .get_one_by_id(id)
+        .await
+        .map_err(|e| {
+            error!("Error fetching connection {id}: {:?}", e);
+            e
+        })?
+    else {
+        return Err(ApplicationError::not_found(
+            &format!("Connection with id {id} not found"),
+            None,
+        ));
+    };
+
+    if connection.ownership != event_access.ownership
+        || connection.environment != event_access.environment
+    {
+        return Err(ApplicationError::forbidden(
+            "You do not have permission to view this connection",
+            None,
+        ));
+    }
+
+    Ok(connection)
+}
+
+pub async fn get_connection_rate_limit(
+    Extension(event_access): Extension<Arc<EventAccess>>,
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ServerResponse<RateLimitState>>, IntegrationOSError> {
+    let connection = get_accessible_connection(&state, &event_access, &id).await?;
+
+    Ok(Json(ServerResponse::new(
+        "rateLimit",
+        state.extractor_caller.rate_limiter.state(&connection),
+    )))
+}
+
+/// Timeout of the unified calls of an action, unset when the calls are never timed out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionTimeout {
+    pub connection_model_definition_id: Id,
+    pub model_name: String,
+    pub action_name: CrudAction,
+    pub timeout: Option<TimeoutConfig>,
+}
+
+/// How the platform calls of a connection are timed out and retried, and how its recent calls
+/// went
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionObservability {
+    pub connection_id: Id,
+    pub retry_policy: DownstreamRetryPolicy,
+    pub timeouts: Vec<ActionTimeout>,
+    pub recent_calls: CallStats,
+}
+
+pub async fn get_connection_observability(
+    Extension(event_access): Extension<Arc<EventAccess>>,
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ServerResponse<ConnectionObservability>>, IntegrationOSError> {
+    let connection = get_accessible_connection(&state, &event_access, &id).await?;
+
+    let connection_definition_id = connection.connection_definition_id.to_string();
+    let Some(connection_definition) = state
+        .app_stores
+        .connection_config
+        .get_one_by_id(&connection_definition_id)
+        .await?
+    else {
+        return Err(ApplicationError::not_found(
+            &format!("Connection definition {connection_definition_id} not found"),
+            None,
+        ));
+    };
+
+    let timeouts = state
+        .app_stores
+        .model_config
+        .get_many(
+            Some(doc! {
+                "connectionDefinitionId": &connection_definition_id,
+                "supported": true,
+            }),
+            None,
+            Some(doc! { "modelName": 1, "actionName": 1 }),
+            None,
+            None,
+        )
+        .await?
+        .into_iter()
+        .map(|definition| ActionTimeout {
+            connection_model_definition_id: definition.id,
+            model_name: definition.model_name,
+            action_name: definition.action_name,
+            timeout: definition.timeout,
+        })
+        .collect();
+
+    Ok(Json(ServerResponse::new(
+        "connectionObservability",
+        ConnectionObservability {
+            connection_id: connection.id,
+            retry_policy: state
+                .extractor_caller
+                .retry_policy_for(&connection_definition)
+                .clone(),
+            timeouts,
+            recent_calls: state
+                .connection_call_stats
+                .stats(&connection.id.to_string()),
+        },
+    )))
+}
+
+/// Lists the OAuth connections of the caller that lack scopes now required by their OAuth
+/// definition, flagged with `reauthorizationRequired` and the `missingScopes`
+pub async fn get_reauthorization_required(
+    Extension(event_access): Extension<Arc<EventAccess>>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ServerResponse<Vec<Value>>>, IntegrationOSError> {
+    let connections = state
+        .app_stores
+        .connection
+        .get_many(
+            Some(doc! {
+                "ownership.buildableId": event_access.ownership.id.as_ref(),
+                "environment": event_access.environment.to_string(),
+                "oauth.enabled": { "$exists": true },
+                "deleted": false,
+            }),
+            None,
+            None,
+            None,
+            None,
+        )
+        .await?;
+
+    let mut definitions = HashMap::new();
+    let mut flagged = vec![];
+    for connection in connections {
+        let Some(
+            oauth @ OAuth::Enabled {
+                connection_oauth_definition_id,
+                ..
+            },
+        ) = &connection.oauth
+        else {
+            continue;
+        };
+
+        if !definitions.contains_key(connection_oauth_definition_id) {
+            let definition = state
+                .app_stores
+                .oauth_config
+                .get_one_by_id(&connection_oauth_definition_id.to_string())
+                .await?;
+            definitions.insert(*connection_oauth_definition_id, definition);
+        }
+        let Some(Some(definition)) = definitions.get(connection_oauth_definition_id) else {
+            continue;
+        };
+
+        let missing_scopes = oauth.missing_scopes(&definition.frontend);
+        if missing_scopes.is_empty() {
+            continue;
+        }
+
+        let mut value = CreateConnectionPayload::public(connection);
+        if let Value::Object(object) = &mut value {
+            object.insert("reauthorizationRequired".to_string(), json!(true));
+            object.insert("missingScopes".to_string(), json!(missing_scopes));
+        }
+        flagged.push(value);
+    }
+
+    Ok(Json(ServerResponse::new("connections", flagged)))
 }
 
 pub async fn update_connection(
@@ -357,7 +870,7 @@ pub async fn update_connection(
     }
 
     if let Some(auth_form_data) = req.auth_form_data {
-        let auth_form_data_value = serde_json::to_value(auth_form_data).map_err(|e| {
+        let auth_form_data_value = serde_json::to_value(&auth_form_data).map_err(|e| {
             error!(
                 "Error serializing auth form data for connection update: {:?}",
                 e
@@ -392,6 +905,8 @@ pub async fn update_connection(
             }
         };
 
+        validate_auth_form_data(&connection_config, &auth_form_data)?;
+
         test_connection(&state, &connection_config, &auth_form_data_value)
             .await
             .map_err(|e| {
@@ -417,6 +932,19 @@ pub async fn update_connection(
         connection.record_metadata.active = active;
     }
 
+    if let Some(mode) = req.mode {
+        connection.mode = mode;
+    }
+
+    if let Some(webhook_secret) = req.webhook_secret {
+        connection.webhook_secret =
+            Some(create_webhook_secret(&state, webhook_secret, &event_access.ownership.id).await?);
+    }
+
+    if let Some(credentials_expire_at) = req.credentials_expire_at {
+        connection.credential_expiry = CredentialExpiry::new(Some(credentials_expire_at));
+    }
+
     let Ok(document) = bson::to_document(&connection) else {
         error!("Could not serialize connection into document");
 
@@ -455,6 +983,53 @@ pub async fn update_connection(
     }
 }
 
+/// Starts testing every connection in the background and returns the operation tracking it,
+/// whose result is a [`BulkValidationReport`] once finished
+pub async fn validate_all_connections(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ServerResponse<Operation>>, IntegrationOSError> {
+    let operation = OperationTracker::new(state.app_stores.operations.clone())
+        .start(
+            OperationKind::BulkConnectionValidation,
+            |progress| async move {
+                let connections = state
+                    .app_stores
+                    .connection
+                    .get_many(Some(doc! { "deleted": false }), None, None, None, None)
+                    .await?;
+
+                let total = connections.len();
+                let mut done = 0;
+                let report = validate_connections(
+                    connections,
+                    Duration::from_millis(state.config.bulk_validation_interval_millis),
+                    |connection| {
+                        done += 1;
+                        let fraction = done as f64 / total as f64;
+                        let state = state.clone();
+                        let progress = progress.clone();
+                        async move {
+                            let result = revalidate_connection(&state, connection).await;
+                            if let Err(e) = progress.report(fraction).await {
+                                error!("Could not report bulk validation progress: {e}");
+                            }
+                            result
+                        }
+                    },
+                )
+                .await;
+
+                serde_json::to_value(report).map_err(|e| {
+                    error!("Could not serialize bulk validation report: {e}");
+                    InternalError::serialize_error("Could not serialize validation report", None)
+                })
+            },
+        )
+        .await?;
+
+    Ok(Json(ServerResponse::new("operation", operation)))
+}
+
 pub async fn delete_connection(
     Extension(access): Extension<Arc<EventAccess>>,
     Path(id): Path<String>,
@@ -504,3 +1079,80 @@ pub async fn delete_connection(
         }),
     )))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use integrationos_domain::{environment::Environment, ownership::Ownership, ConnectionType};
+
+    fn connection(key: &str) -> Connection {
+        Connection {
+            id: Id::now(IdPrefix::Connection),
+            platform_version: "platformVersion".to_string(),
+            connection_definition_id: Id::now(IdPrefix::ConnectionDefinition),
+            r#type: ConnectionType::Api {},
+            name: key.to_string(),
+            key: key.into(),
+            group: "group".to_string(),
+            environment: Environment::Test,
+            mode: Default::default(),
+            platform: "platform".into(),
+            secrets_service_id: "secrets_service_id".to_string(),
+            event_access_id: Id::now(IdPrefix::EventAccess),
+            access_key: "accessKey".to_string(),
+            settings: Settings::default(),
+            throughput: Throughput {
+                key: "throughputKey".to_string(),
+                limit: 100,
+            },
+            ownership: Ownership::default(),
+            oauth: None,
+            health: Default::default(),
+            webhook_secret: None,
+            credential_expiry: Default::default(),
+            credentials_rotated_at: None,
+            record_metadata: RecordMetadata::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bulk_validation_reports_every_connection() {
+        let connections = vec![connection("a"), connection("broken"), connection("c")];
+        let mut validated = vec![];
+
+        let report = validate_connections(
+            connections.clone(),
+            Duration::from_millis(1),
+            |connection| {
+                validated.push(connection.key.clone());
+                async move {
+                    if connection.key.as_ref() == "broken" {
+                        bail!("Test connections failed: 401 Unauthorized");
+                    }
+                    Ok(())
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(
+            validated,
+            vec!["a".into(), "broken".into(), "c".into()] as Vec<Arc<str>>
+        );
+        assert_eq!(report.total, 3);
+        assert_eq!(
+            report
+                .healthy
+                .iter()
+                .map(|validation| validation.id)
+                .collect::<Vec<_>>(),
+            vec![connections[0].id, connections[2].id]
+        );
+        assert_eq!(report.unhealthy.len(), 1);
+        assert_eq!(report.unhealthy[0].id, connections[1].id);
+        assert_eq!(
+            report.unhealthy[0].error.as_deref(),
+            Some("Test connections failed: 401 Unauthorized")
+        );
+    }
+}
@@ -1,30 +1,46 @@
-use super::{create, delete, read, update, HookExt, PublicExt, RequestExt};
+use super::{
+    connection::connection_event, create, delete, import, read, read_common, update, HookExt,
+    PublicExt, ReadResponse, RequestExt, SuccessResponse,
+};
 use crate::{
+    helper::{cache_status_headers, send_event},
     router::ServerResponse,
     server::{AppState, AppStores},
 };
 use axum::{
-    extract::{Path, State},
-    routing::{patch, post},
-    Json, Router,
+    extract::{Path, Query, State},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use http::{
+    header::{ACCEPT_LANGUAGE, CACHE_CONTROL},
+    HeaderMap,
 };
 use integrationos_domain::{
     algebra::MongoStore,
     api_model_config::AuthMethod,
     connection_definition::{
         AuthSecret, ConnectionDefinition, ConnectionDefinitionType, ConnectionForm,
-        ConnectionStatus, FormDataItem, Frontend, Paths, PublicConnectionDetails, Spec,
+        ConnectionStatus, CredentialRotation, DownstreamRetryPolicy, FormDataItem, Frontend,
+        LocalizedFormDataItem, Paths, PublicConnectionDetails, Spec,
     },
     connection_model_definition::{ConnectionModelDefinition, CrudAction},
+    event_access::EventAccess,
     id::{prefix::IdPrefix, Id},
+    input_validation::InputValidationRule,
     record_metadata::RecordMetadata,
     settings::Settings,
-    ApplicationError, IntegrationOSError,
+    ApplicationError, IntegrationOSError, InternalError,
 };
-use mongodb::bson::doc;
+use integrationos_unified::shadow::diff_values;
+use mongodb::bson::{doc, Document};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tracing::error;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+use tracing::{error, info};
 
 pub fn get_router() -> Router<Arc<AppState>> {
     Router::new()
@@ -33,13 +49,242 @@ pub fn get_router() -> Router<Arc<AppState>> {
             post(create::<CreateRequest, ConnectionDefinition>)
                 .get(read::<CreateRequest, ConnectionDefinition>),
         )
+        .route(
+            "/import",
+            post(import::<CreateRequest, ConnectionDefinition>),
+        )
         .route(
             "/:id",
-            patch(update::<CreateRequest, ConnectionDefinition>)
+            get(read_one)
+                .patch(update_definition)
                 .delete(delete::<CreateRequest, ConnectionDefinition>),
         )
+        .route("/:id/derive", post(derive))
+}
+
+/// Creates a definition using the definition `id` as its template, the body being the fields
+/// overridden, see [`ConnectionDefinition::derive`]
+pub async fn derive(
+    Path(id): Path<Id>,
+    State(state): State<Arc<AppState>>,
+    Json(overrides): Json<Value>,
+) -> Result<Json<ServerResponse<ConnectionDefinition>>, IntegrationOSError> {
+    if !overrides.is_object() {
+        return Err(ApplicationError::bad_request(
+            "Overrides must be an object",
+            None,
+        ));
+    }
+
+    let store = &state.app_stores.connection_config;
+    let template = store
+        .get_one(doc! { "_id": id.to_string(), "deleted": false })
+        .await?
+        .ok_or_else(|| {
+            ApplicationError::not_found(
+                &format!("Connection definition with id {id} not found"),
+                None,
+            )
+        })?;
+
+    let derived = template.derive(Id::now(IdPrefix::ConnectionDefinition), &overrides)?;
+    store.create_one(&derived).await?;
+
+    Ok(Json(ServerResponse::new("connection_definition", derived)))
+}
+
+/// Summary of the changes made to a definition, sent to the connections depending on it
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionDefinitionChanges {
+    pub connection_definition_id: Id,
+    /// JSON paths of the changed fields of the definition, e.g. `$.frontend.spec.description`
+    pub changed_fields: Vec<String>,
+    /// Authentication fields the connections of the definition now have to provide
+    pub added_auth_secrets: Vec<String>,
+    pub removed_auth_secrets: Vec<String>,
+}
+
+impl ConnectionDefinitionChanges {
+    /// Changes from `previous` to `updated`, none when they only differ by their metadata
+    pub fn new(previous: &ConnectionDefinition, updated: &ConnectionDefinition) -> Option<Self> {
+        let (Ok(before), Ok(after)) = (
+            serde_json::to_value(previous),
+            serde_json::to_value(updated),
+        ) else {
+            return None;
+        };
+        let metadata = ["$.createdAt", "$.updatedAt", "$.updated", "$.changeLog"];
+        let mut changed_fields = diff_values(&before, &after)
+            .into_iter()
+            .filter(|path| !metadata.iter().any(|field| path.starts_with(field)))
+            .collect::<Vec<_>>();
+        changed_fields.sort();
+
+        let secrets = |definition: &ConnectionDefinition| {
+            definition
+                .auth_secrets
+                .iter()
+                .map(|secret| secret.name.clone())
+                .collect::<BTreeSet<_>>()
+        };
+        let (before, after) = (secrets(previous), secrets(updated));
+
+        (!changed_fields.is_empty()).then(|| Self {
+            connection_definition_id: updated.id,
+            changed_fields,
+            added_auth_secrets: after.difference(&before).cloned().collect(),
+            removed_auth_secrets: before.difference(&after).cloned().collect(),
+        })
+    }
+}
+
+/// Updates a definition, then notifies the connections of the definition of its changes with
+/// a `{platform}::connection-definition-changed` event, which integrators subscribe to with a
+/// pipeline on that event
+pub async fn update_definition(
+    access: Option<Extension<Arc<EventAccess>>>,
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateRequest>,
+) -> Result<Json<ServerResponse<SuccessResponse>>, IntegrationOSError> {
+    let previous = state
+        .app_stores
+        .connection_config
+        .get_one_by_id(&id)
+        .await?;
+    let changes = previous.and_then(|previous| {
+        ConnectionDefinitionChanges::new(&previous, &payload.update(previous.clone()))
+    });
+
+    let res = update::<CreateRequest, ConnectionDefinition>(
+        access,
+        Path(id),
+        State(state.clone()),
+        Json(payload),
+    )
+    .await?;
+
+    if let Some(changes) = changes {
+        // The definition is updated already, a failed notification doesn't fail the update
+        match notify_definition_changes(&state, &changes).await {
+            Ok(notified) => info!(
+                "Notified {notified} connections of the changes of connection definition {}",
+                changes.connection_definition_id
+            ),
+            Err(e) => error!(
+                "Could not notify the connections of connection definition {}: {e}",
+                changes.connection_definition_id
+            ),
+        }
+    }
+
+    Ok(res)
+}
+
+/// Emits a `{platform}::connection-definition-changed` event carrying `changes` for every
+/// connection of the changed definition, and returns how many were sent
+pub async fn notify_definition_changes(
+    state: &AppState,
+    changes: &ConnectionDefinitionChanges,
+) -> Result<usize, IntegrationOSError> {
+    let connections = state
+        .app_stores
+        .connection
+        .get_many(
+            Some(doc! {
+                "connectionDefinitionId": changes.connection_definition_id.to_string(),
+                "deleted": false,
+            }),
+            None,
+            None,
+            None,
+            None,
+        )
+        .await?;
+    let payload = serde_json::to_string(changes)
+        .map_err(|e| InternalError::serialize_error(&e.to_string(), None))?;
+
+    let mut notified = 0;
+    for connection in connections {
+        let name = format!("{}::connection-definition-changed", connection.platform);
+        let event =
+            match connection_event(state, &connection, &name, HeaderMap::new(), payload.clone()) {
+                Ok(event) => event,
+                Err(e) => {
+                    error!("Could not notify connection {}: {e}", connection.id);
+                    continue;
+                }
+            };
+        if !state.event_denylist.admits(&event) {
+            continue;
+        }
+        if let Err(e) = send_event(&state.event_tx, event) {
+            error!("Could not send connection definition change event: {e}");
+            continue;
+        }
+
+        notified += 1;
+    }
+
+    Ok(notified)
 }
 
+/// Whether a read asks for the definition to be read from the database rather than the cache
+fn bypasses_cache(headers: &HeaderMap, bypass_header: Option<&str>) -> bool {
+    let no_cache = headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-cache"));
+
+    no_cache || bypass_header.is_some_and(|name| headers.contains_key(name))
+}
+
+/// Reads a definition through the connection definitions cache. As this route is only served
+/// to authorized callers, they may bypass the cache, which refreshes the cached entry. The
+/// response tells whether the definition was served from the cache, and how old it was.
+pub async fn read_one(
+    headers: HeaderMap,
+    Path(id): Path<Id>,
+    State(state): State<Arc<AppState>>,
+) -> Result<(HeaderMap, Json<ServerResponse<ConnectionDefinition>>), IntegrationOSError> {
+    let store = state.app_stores.connection_config.clone();
+    let filter = doc! { "_id": id.to_string(), "deleted": false };
+    let bypass_header = state
+        .config
+        .connection_definition_cache_bypass_header
+        .as_deref();
+
+    let (definition, age) = if bypasses_cache(&headers, bypass_header) {
+        let definition = state
+            .connection_definitions_cache
+            .refresh_with_filter(&id, store, filter)
+            .await?;
+        (definition, None)
+    } else {
+        let cached = state
+            .connection_definitions_cache
+            .lookup_with_filter(&id, store, filter)
+            .await?;
+        (cached.definition, cached.age)
+    };
+
+    Ok((
+        cache_status_headers(age),
+        Json(ServerResponse::new("read", definition)),
+    ))
+}
+
+/// Query parameters restricting the listed definitions to the ones having a supported
+/// connection model definition for an action and/or a common model
+const SUPPORTED_ACTION_QUERY: &str = "supportedAction";
+const SUPPORTED_MODEL_QUERY: &str = "supportedModel";
+/// Query parameter of the locale the labels of the listed definitions are served in, taking
+/// precedence over the `Accept-Language` header
+const LOCALE_QUERY: &str = "locale";
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 #[serde(rename_all = "camelCase")]
@@ -65,6 +310,17 @@ pub struct CreateRequest {
     pub paths: Paths,
     pub test_connection: Option<Id>,
     pub active: bool,
+    #[serde(default)]
+    pub default_query_params: BTreeMap<String, String>,
+    #[serde(default)]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub retry_policy: Option<DownstreamRetryPolicy>,
+    #[serde(default)]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub input_rules: Vec<InputValidationRule>,
+    #[serde(default)]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub credential_rotation: Option<CredentialRotation>,
 }
 
 impl HookExt<ConnectionDefinition> for CreateRequest {}
@@ -77,6 +333,9 @@ pub struct AuthenticationItem {
     pub label: String,
     pub r#type: String,
     pub placeholder: String,
+    #[serde(default)]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub localized: BTreeMap<String, LocalizedFormDataItem>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -292,6 +551,125 @@ pub async fn public_get_connection_details(
     )))
 }
 
+/// Filter of the connection model definitions supporting `action` on `model`, `None` when
+/// neither is requested
+fn supported_model_definitions_filter(
+    action: Option<&str>,
+    model: Option<&str>,
+) -> Result<Option<Document>, IntegrationOSError> {
+    if action.is_none() && model.is_none() {
+        return Ok(None);
+    }
+
+    let mut filter = doc! {
+        "supported": true,
+        "deleted": false,
+    };
+    if let Some(action) = action {
+        let action = serde_json::from_value::<CrudAction>(Value::String(action.to_string()))
+            .map_err(|_| {
+                ApplicationError::bad_request(&format!("Unknown action {action}"), None)
+            })?;
+        filter.insert("actionName", action.to_string());
+    }
+    if let Some(model) = model {
+        filter.insert(
+            "mapping.commonModelName",
+            doc! {
+                "$regex": format!("^{}$", regex::escape(model)),
+                "$options": "i"
+            },
+        );
+    }
+
+    Ok(Some(filter))
+}
+
+/// Locales the labels of definitions are asked for in by order of preference, either the one
+/// of the `locale` query parameter or the ones of the `Accept-Language` header sorted by
+/// quality. Wildcards and rejected locales are ignored.
+fn requested_locales(headers: &HeaderMap, locale: Option<String>) -> Vec<String> {
+    if let Some(locale) = locale.filter(|locale| !locale.trim().is_empty()) {
+        return vec![locale.trim().to_string()];
+    }
+
+    let mut locales = headers
+        .get_all(ACCEPT_LANGUAGE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let locale = parts
+                .next()
+                .filter(|locale| !locale.is_empty() && *locale != "*")?;
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .map_or(Some(1.0), |quality| quality.parse::<f32>().ok())?;
+
+            (quality > 0.0).then(|| (locale.to_string(), quality))
+        })
+        .collect::<Vec<_>>();
+    locales.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+    locales.into_iter().map(|(locale, _)| locale).collect()
+}
+
+/// Lists connection definitions, optionally only the ones supporting the action and/or the
+/// common model given in the query. Their labels are served in the requested locale, see
+/// [`ConnectionDefinition::localize`].
+pub async fn read_supported(
+    headers: HeaderMap,
+    access: Option<Extension<Arc<EventAccess>>>,
+    query: Option<Query<BTreeMap<String, String>>>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ServerResponse<ReadResponse<Value>>>, IntegrationOSError> {
+    let mut query = query.map(|Query(query)| query).unwrap_or_default();
+    let action = query.remove(SUPPORTED_ACTION_QUERY);
+    let model = query.remove(SUPPORTED_MODEL_QUERY);
+    let locales = requested_locales(&headers, query.remove(LOCALE_QUERY));
+
+    let extra_filter =
+        match supported_model_definitions_filter(action.as_deref(), model.as_deref())? {
+            Some(filter) => {
+                let ids = state
+                    .app_stores
+                    .model_config
+                    .collection
+                    .distinct("connectionDefinitionId", filter, None)
+                    .await
+                    .map_err(|e| {
+                        error!("Error reading supported connection model definitions: {e}");
+                        IntegrationOSError::from(e)
+                    })?;
+                doc! { "_id": { "$in": ids } }
+            }
+            None => doc! {},
+        };
+
+    let Json(mut res) = read_common::<CreateRequest, ConnectionDefinition>(
+        headers,
+        access,
+        Some(Query(query)),
+        State(state),
+        true,
+        extra_filter,
+    )
+    .await?;
+
+    if !locales.is_empty() {
+        for row in res.args.rows.iter_mut() {
+            if let Ok(mut definition) = serde_json::from_value::<ConnectionDefinition>(row.clone())
+            {
+                definition.localize(&locales);
+                *row = serde_json::to_value(definition).unwrap_or_default();
+            }
+        }
+    }
+
+    Ok(Json(res))
+}
+
 impl RequestExt for CreateRequest {
     type Output = ConnectionDefinition;
 
@@ -312,6 +690,7 @@ impl RequestExt for CreateRequest {
                 r#type: item.r#type.clone(),
                 label: item.label.clone(),
                 placeholder: item.placeholder.clone(),
+                localized: item.localized.clone(),
             })
             .collect();
 
@@ -319,6 +698,7 @@ impl RequestExt for CreateRequest {
             name: "Connect".to_string(),
             description: "Securely connect your account".to_string(),
             form_data: connection_form_items,
+            localized: BTreeMap::new(),
         };
 
         let key = format!("api::{}::{}", self.platform, self.platform_version);
@@ -352,6 +732,11 @@ impl RequestExt for CreateRequest {
             paths: self.paths.clone(),
             settings: self.settings.clone(),
             hidden: false,
+            default_query_params: self.default_query_params.clone(),
+            retry_policy: self.retry_policy.clone(),
+            input_rules: self.input_rules.clone(),
+            template_id: None,
+            credential_rotation: self.credential_rotation.clone(),
             record_metadata: RecordMetadata::default(),
         };
 
@@ -372,6 +757,14 @@ impl RequestExt for CreateRequest {
         record.test_connection = self.test_connection;
         record.platform.clone_from(&self.platform);
         record.multi_env = self.multi_env;
+        record
+            .default_query_params
+            .clone_from(&self.default_query_params);
+        record.retry_policy.clone_from(&self.retry_policy);
+        record.input_rules.clone_from(&self.input_rules);
+        record
+            .credential_rotation
+            .clone_from(&self.credential_rotation);
         record.record_metadata.active = self.active;
         record
     }
@@ -380,3 +773,132 @@ impl RequestExt for CreateRequest {
         stores.connection_config
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http::HeaderValue;
+
+    #[test]
+    fn test_cache_is_bypassed_by_no_cache_or_configured_header() {
+        let mut headers = HeaderMap::new();
+        assert!(!bypasses_cache(&headers, Some("x-bypass-cache")));
+
+        headers.insert(
+            CACHE_CONTROL,
+            HeaderValue::from_static("max-age=0, No-Cache"),
+        );
+        assert!(bypasses_cache(&headers, None));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-bypass-cache", HeaderValue::from_static("1"));
+        assert!(!bypasses_cache(&headers, None));
+        assert!(bypasses_cache(&headers, Some("x-bypass-cache")));
+    }
+
+    #[test]
+    fn test_supported_filter_matches_requested_action_and_model() {
+        assert_eq!(
+            supported_model_definitions_filter(None, None).unwrap(),
+            None
+        );
+
+        let filter = supported_model_definitions_filter(Some("create"), Some("Invoice.v2"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(filter.get_str("actionName").unwrap(), "create");
+        assert!(filter.get_bool("supported").unwrap());
+        assert_eq!(
+            filter
+                .get_document("mapping.commonModelName")
+                .unwrap()
+                .get_str("$regex")
+                .unwrap(),
+            "^Invoice\\.v2$"
+        );
+
+        let filter = supported_model_definitions_filter(Some("getMany"), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(filter.get_str("actionName").unwrap(), "getMany");
+        assert!(!filter.contains_key("mapping.commonModelName"));
+
+        assert!(supported_model_definitions_filter(Some("invoice"), None).is_err());
+    }
+
+    #[test]
+    fn test_changes_summarize_the_updated_fields() {
+        let request = CreateRequest {
+            id: None,
+            platform: "stripe".to_string(),
+            platform_version: "v1".to_string(),
+            status: ConnectionStatus::default(),
+            r#type: ConnectionDefinitionType::Api,
+            name: "Stripe".to_string(),
+            description: "Payments".to_string(),
+            category: "Payments".to_string(),
+            image: "https://stripe.com/logo.png".to_string(),
+            tags: vec![],
+            helper_link: None,
+            authentication: vec![],
+            auth_method: None,
+            multi_env: false,
+            settings: Settings::default(),
+            paths: Paths::default(),
+            test_connection: None,
+            active: true,
+            default_query_params: BTreeMap::new(),
+            retry_policy: None,
+            input_rules: vec![],
+            credential_rotation: None,
+        };
+        let previous = request.from().unwrap();
+
+        let mut unchanged = request.update(previous.clone());
+        unchanged.record_metadata.updated_at += 1;
+        assert_eq!(
+            ConnectionDefinitionChanges::new(&previous, &unchanged),
+            None
+        );
+
+        let mut updated = CreateRequest {
+            description: "Payments and billing".to_string(),
+            active: false,
+            ..request
+        }
+        .update(previous.clone());
+        updated.auth_secrets.push(AuthSecret {
+            name: "STRIPE_SECRET_KEY".to_string(),
+        });
+
+        let changes = ConnectionDefinitionChanges::new(&previous, &updated).unwrap();
+        assert_eq!(changes.connection_definition_id, previous.id);
+        assert_eq!(
+            changes.changed_fields,
+            [
+                "$.active",
+                "$.authSecrets[0]",
+                "$.frontend.spec.description"
+            ]
+        );
+        assert_eq!(changes.added_auth_secrets, ["STRIPE_SECRET_KEY"]);
+        assert!(changes.removed_auth_secrets.is_empty());
+    }
+
+    #[test]
+    fn test_requested_locales_are_sorted_by_quality() {
+        let mut headers = HeaderMap::new();
+        assert!(requested_locales(&headers, None).is_empty());
+
+        headers.insert(
+            ACCEPT_LANGUAGE,
+            HeaderValue::from_static("en;q=0.5, fr-CA, *;q=0.1, de;q=0, fr;q=0.8"),
+        );
+        assert_eq!(requested_locales(&headers, None), ["fr-CA", "fr", "en"]);
+        assert_eq!(requested_locales(&headers, Some("es".to_string())), ["es"]);
+        assert_eq!(
+            requested_locales(&headers, Some(" ".to_string())),
+            ["fr-CA", "fr", "en"]
+        );
+    }
+}
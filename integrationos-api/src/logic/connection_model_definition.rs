@@ -1,4 +1,4 @@
-use super::{create, delete, read, update, HookExt, PublicExt, RequestExt};
+use super::{create, delete, import, read, update, HookExt, PublicExt, RequestExt};
 use crate::{
     router::ServerResponse,
     server::{AppState, AppStores},
@@ -17,8 +17,9 @@ use integrationos_domain::{
         ApiModelConfig, AuthMethod, ModelPaths, ResponseBody, SamplesInput, SchemasInput,
     },
     connection_model_definition::{
-        ConnectionModelDefinition, CrudAction, CrudMapping, ExtractorConfig, PlatformInfo,
-        TestConnection, TestConnectionState,
+        ConnectionModelDefinition, CrudAction, CrudMapping, ErrorFormat, ExtractorConfig,
+        PaginationLimits, PlatformInfo, ShadowConfig, TestConnection, TestConnectionState,
+        TimeoutConfig,
     },
     event_access::EventAccess,
     id::{prefix::IdPrefix, Id},
@@ -41,6 +42,10 @@ pub fn get_router() -> Router<Arc<AppState>> {
             post(create::<CreateRequest, ConnectionModelDefinition>)
                 .get(read::<CreateRequest, ConnectionModelDefinition>),
         )
+        .route(
+            "/import",
+            post(import::<CreateRequest, ConnectionModelDefinition>),
+        )
         .route(
             "/:id",
             patch(update::<CreateRequest, ConnectionModelDefinition>)
@@ -199,11 +204,16 @@ pub async fn test_connection_model_definition(
 
     let status_code = model_execution_result.status();
 
-    let response_body = model_execution_result.text().await.map_err(|e| {
-        error!("Could not get text from test connection failure: {e}");
+    let response_body = state
+        .extractor_caller
+        .read_response(model_execution_result)
+        .await
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .map_err(|e| {
+            error!("Could not get text from test connection failure: {e}");
 
-        InternalError::unknown("Could not get text from test connection", None)
-    })?;
+            e
+        })?;
 
     let status = match status_code {
         status if status.is_success() => TestConnection {
@@ -307,6 +317,19 @@ pub struct CreateRequest {
     pub paths: Option<ModelPaths>,
     pub supported: Option<bool>,
     pub active: Option<bool>,
+    #[serde(default)]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub shadow: Option<ShadowConfig>,
+    pub streaming: Option<bool>,
+    #[serde(default)]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub timeout: Option<TimeoutConfig>,
+    #[serde(default)]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub error_format: Option<ErrorFormat>,
+    #[serde(default)]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub pagination_limits: Option<PaginationLimits>,
 }
 
 impl HookExt<ConnectionModelDefinition> for CreateRequest {}
@@ -359,6 +382,11 @@ impl RequestExt for CreateRequest {
             mapping: self.mapping.clone(),
             record_metadata: Default::default(),
             supported: self.supported.unwrap_or(false),
+            shadow: self.shadow.clone(),
+            streaming: self.streaming.unwrap_or(false),
+            timeout: self.timeout,
+            error_format: self.error_format.clone(),
+            pagination_limits: self.pagination_limits,
         };
         record.record_metadata.version = self.version.clone();
         Some(record)
@@ -400,6 +428,14 @@ impl RequestExt for CreateRequest {
         });
         record.mapping.clone_from(&self.mapping);
         record.extractor_config.clone_from(&self.extractor_config);
+        record.shadow.clone_from(&self.shadow);
+        record.timeout = self.timeout;
+        record.error_format.clone_from(&self.error_format);
+        record.pagination_limits = self.pagination_limits;
+
+        if let Some(streaming) = self.streaming {
+            record.streaming = streaming;
+        }
         record.record_metadata.version = self.version.clone();
 
         if let Some(supported) = self.supported {
@@ -1,4 +1,4 @@
-use super::{create, delete, read, update, HookExt, PublicExt, ReadResponse, RequestExt};
+use super::{create, delete, import, read, update, HookExt, PublicExt, ReadResponse, RequestExt};
 use crate::{
     helper::shape_mongo_filter,
     router::ServerResponse,
@@ -13,8 +13,10 @@ use futures::try_join;
 use integrationos_domain::{
     algebra::MongoStore,
     connection_model_schema::{
-        ConnectionModelSchema, Mappings, PublicConnectionModelSchema, SchemaPaths,
+        ConnectionModelSchema, Mappings, PublicConnectionModelSchema, SchemaDriftPolicy,
+        SchemaPaths,
     },
+    diff::SchemaDiff,
     event_access::EventAccess,
     id::{prefix::IdPrefix, Id},
     json_schema::JsonSchema,
@@ -33,11 +35,16 @@ pub fn get_router() -> Router<Arc<AppState>> {
             post(create::<CreateRequest, ConnectionModelSchema>)
                 .get(read::<CreateRequest, ConnectionModelSchema>),
         )
+        .route(
+            "/import",
+            post(import::<CreateRequest, ConnectionModelSchema>),
+        )
         .route(
             "/:id",
             patch(update::<CreateRequest, ConnectionModelSchema>)
                 .delete(delete::<CreateRequest, ConnectionModelSchema>),
         )
+        .route("/:id/diff", post(diff_connection_model_schema))
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -93,6 +100,7 @@ where
             skip: query.skip,
             limit: query.limit,
             total,
+            next_cursor: None,
         },
         Err(e) => {
             error!("Error reading from store: {e}");
@@ -111,6 +119,34 @@ impl RequestExt for PublicGetConnectionModelSchema {
     }
 }
 
+/// Compares a proposed schema against the stored one without updating it
+pub async fn diff_connection_model_schema(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Json(proposed): Json<CreateRequest>,
+) -> Result<Json<ServerResponse<SchemaDiff>>, IntegrationOSError> {
+    let Some(stored) = state
+        .app_stores
+        .model_schema
+        .get_one_by_id(&id)
+        .await
+        .map_err(|e| {
+            error!("Error reading from connection model schema store: {e}");
+            e
+        })?
+    else {
+        return Err(ApplicationError::not_found(
+            &format!("Connection model schema with id {id} not found"),
+            None,
+        ));
+    };
+
+    Ok(Json(ServerResponse::new(
+        "connection_model_schema_diff",
+        SchemaDiff::new(&stored.schema, &proposed.schema),
+    )))
+}
+
 pub async fn get_platform_models(
     Path(platform_name): Path<String>,
     State(state): State<Arc<AppState>>,
@@ -163,6 +199,9 @@ pub struct CreateRequest {
     pub paths: Option<SchemaPaths>,
     #[cfg_attr(feature = "dummy", dummy(default))]
     pub mapping: Option<Mappings>,
+    #[serde(default)]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub drift_policy: SchemaDriftPolicy,
 }
 
 impl HookExt<ConnectionModelSchema> for CreateRequest {}
@@ -193,6 +232,7 @@ impl RequestExt for CreateRequest {
             mapping: self.mapping.clone(),
             sample: self.sample.clone(),
             paths: self.paths.clone(),
+            drift_policy: self.drift_policy,
             record_metadata: Default::default(),
         })
     }
@@ -210,6 +250,7 @@ impl RequestExt for CreateRequest {
         record.sample = self.sample.clone();
         record.paths.clone_from(&self.paths);
         record.mapping.clone_from(&self.mapping);
+        record.drift_policy = self.drift_policy;
         record
     }
 
@@ -1,4 +1,4 @@
-use super::{create, delete, read, update, HookExt, PublicExt, RequestExt};
+use super::{create, delete, import, read, update, HookExt, PublicExt, RequestExt};
 use crate::server::{AppState, AppStores};
 use axum::{
     routing::{patch, post},
@@ -9,7 +9,7 @@ use integrationos_domain::{
     algebra::MongoStore,
     api_model_config::{ApiModelConfig, Compute, Function, Lang},
     connection_oauth_definition::{
-        ComputeRequest, ConnectionOAuthDefinition, Frontend, OAuthApiConfig, OAuthCompute,
+        ComputeRequest, ConnectionOAuthDefinition, Frontend, OAuthApiConfig, OAuthCompute, Pkce,
     },
     id::{prefix::IdPrefix, Id},
     record_metadata::RecordMetadata,
@@ -25,6 +25,10 @@ pub fn get_router() -> Router<Arc<AppState>> {
             post(create::<CreateRequest, ConnectionOAuthDefinition>)
                 .get(read::<CreateRequest, ConnectionOAuthDefinition>),
         )
+        .route(
+            "/import",
+            post(import::<CreateRequest, ConnectionOAuthDefinition>),
+        )
         .route(
             "/:id",
             patch(update::<CreateRequest, ConnectionOAuthDefinition>)
@@ -48,6 +52,8 @@ pub struct CreateRequest {
     pub init: RequestParams,
     pub refresh: RequestParams,
     pub is_full_template_enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pkce: Option<Pkce>,
 }
 
 impl HookExt<ConnectionOAuthDefinition> for CreateRequest {}
@@ -127,6 +133,7 @@ impl RequestExt for CreateRequest {
                 scopes: self.scopes.clone(),
                 separator: self.separator.clone(),
             },
+            pkce: self.pkce,
             record_metadata: Default::default(),
             hooks: Default::default(),
         })
@@ -141,6 +148,7 @@ impl RequestExt for CreateRequest {
             refresh: self.refresh.configuration.clone(),
         };
         record.is_full_template_enabled = self.is_full_template_enabled;
+        record.pkce = self.pkce;
         record.compute = OAuthCompute {
             init: ComputeRequest {
                 computation: self
@@ -206,6 +214,8 @@ pub struct FrontendOauthConnectionDefinition {
     pub id: String,
     pub connection_platform: String,
     pub frontend: Frontend,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pkce: Option<Pkce>,
     #[serde(flatten)]
     pub record_metadata: RecordMetadata,
 }
@@ -0,0 +1,79 @@
+use crate::{metrics::MetricRecord, router::ServerResponse, server::AppState};
+use axum::{
+    extract::{Path, State},
+    routing::get,
+    Extension, Json, Router,
+};
+use bson::doc;
+use futures::TryStreamExt;
+use integrationos_domain::{event_access::EventAccess, Event, IntegrationOSError, PublicEvent};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+pub fn get_router() -> Router<Arc<AppState>> {
+    Router::new().route("/:id", get(get_correlation))
+}
+
+/// Events and metrics of the requests sharing a correlation id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorrelationResponse {
+    pub correlation_id: String,
+    pub events: Vec<PublicEvent>,
+    pub metrics: Vec<MetricRecord>,
+}
+
+pub async fn get_correlation(
+    Extension(access): Extension<Arc<EventAccess>>,
+    Path(correlation_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ServerResponse<CorrelationResponse>>, IntegrationOSError> {
+    let collection = state
+        .config
+        .dedicated_event_tenants
+        .collection_for(&access.ownership.client_id);
+    let mut events = state
+        .app_stores
+        .db
+        .collection::<Event>(&collection)
+        .find(
+            doc! {
+                "correlationId": &correlation_id,
+                "ownership.buildableId": access.ownership.id.as_ref(),
+            },
+            None,
+        )
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    if let Some(field_encryption) = &state.event_field_encryption {
+        for event in events.iter_mut() {
+            event.body = field_encryption.decrypt(&event.body).await?;
+        }
+    }
+
+    let metrics = state
+        .app_stores
+        .metric_records
+        .get_many(
+            Some(doc! {
+                "correlationId": &correlation_id,
+                "clientId": &access.ownership.client_id,
+            }),
+            None,
+            Some(doc! { "createdAt": 1 }),
+            None,
+            None,
+        )
+        .await?;
+
+    Ok(Json(ServerResponse::new(
+        "correlation",
+        CorrelationResponse {
+            correlation_id,
+            events: events.into_iter().map(Event::to_public).collect(),
+            metrics,
+        },
+    )))
+}
@@ -0,0 +1,411 @@
+use crate::{router::ServerResponse, server::AppState};
+use axum::{extract::State, Json};
+use bson::doc;
+use http::{HeaderMap, HeaderValue};
+use integrationos_domain::{
+    connection_definition::CredentialRotation,
+    connection_model_definition::ConnectionModelDefinition, id::Id, secret::Secret,
+    ApplicationError, Connection, IntegrationOSError, InternalError, Operation, OperationKind,
+    OperationTracker, SecretExt,
+};
+use integrationos_unified::unified::UnifiedDestination;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tracing::{error, info};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotateCredentialsRequest {
+    pub connection_definition_id: Id,
+    /// Connections to rotate, every connection of the definition if unset
+    #[serde(default)]
+    pub connection_ids: Option<Vec<Id>>,
+}
+
+/// Outcome of rotating the credentials of a single connection
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionRotation {
+    pub id: Id,
+    pub key: Arc<str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkRotationReport {
+    pub total: usize,
+    pub rotated: Vec<ConnectionRotation>,
+    pub failed: Vec<ConnectionRotation>,
+}
+
+/// Starts rotating the credentials of the connections of a definition through the key-rotation
+/// endpoint of its platform, and returns the operation tracking it. Connections are rotated
+/// one at a time, a failed rotation leaving the credentials of the connection untouched.
+pub async fn rotate_credentials(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<RotateCredentialsRequest>,
+) -> Result<Json<ServerResponse<Operation>>, IntegrationOSError> {
+    let connection_definition_id = req.connection_definition_id.to_string();
+    let Some(connection_definition) = state
+        .app_stores
+        .connection_config
+        .get_one_by_id(&connection_definition_id)
+        .await?
+    else {
+        return Err(ApplicationError::not_found(
+            &format!("Connection definition {connection_definition_id} not found"),
+            None,
+        ));
+    };
+    let Some(rotation) = connection_definition.credential_rotation else {
+        return Err(ApplicationError::bad_request(
+            &format!("Connection definition {connection_definition_id} can't rotate credentials"),
+            None,
+        ));
+    };
+
+    let model_definition_id = rotation.connection_model_definition_id.to_string();
+    let Some(model_definition) = state
+        .app_stores
+        .model_config
+        .get_one_by_id(&model_definition_id)
+        .await?
+    else {
+        return Err(ApplicationError::not_found(
+            &format!("Connection model definition {model_definition_id} not found"),
+            None,
+        ));
+    };
+
+    let mut filter = doc! {
+        "connectionDefinitionId": &connection_definition_id,
+        "deleted": false,
+    };
+    if let Some(ids) = &req.connection_ids {
+        let ids = ids.iter().map(Id::to_string).collect::<Vec<_>>();
+        filter.insert("_id", doc! { "$in": ids });
+    }
+
+    let operation = OperationTracker::new(state.app_stores.operations.clone())
+        .start(
+            OperationKind::BulkCredentialRotation,
+            move |progress| async move {
+                let connections = state
+                    .app_stores
+                    .connection
+                    .get_many(Some(filter), None, None, None, None)
+                    .await?;
+
+                let interval = Duration::from_millis(state.config.bulk_rotation_interval_millis);
+                let mut ticker = tokio::time::interval(interval.max(Duration::from_millis(1)));
+                let mut report = BulkRotationReport {
+                    total: connections.len(),
+                    ..Default::default()
+                };
+
+                for (done, connection) in connections.into_iter().enumerate() {
+                    ticker.tick().await;
+
+                    let mut outcome = ConnectionRotation {
+                        id: connection.id,
+                        key: connection.key.clone(),
+                        error: None,
+                    };
+                    match rotate_connection_credentials(
+                        &state,
+                        &connection,
+                        &rotation,
+                        &model_definition,
+                    )
+                    .await
+                    {
+                        Ok(()) => report.rotated.push(outcome),
+                        Err(e) => {
+                            error!(
+                                "Could not rotate credentials of connection {}: {e}",
+                                connection.id
+                            );
+                            outcome.error = Some(e.to_string());
+                            report.failed.push(outcome);
+                        }
+                    }
+
+                    if let Err(e) = progress
+                        .report((done + 1) as f64 / report.total as f64)
+                        .await
+                    {
+                        error!("Could not report credential rotation progress: {e}");
+                    }
+                }
+
+                serde_json::to_value(report).map_err(|e| {
+                    error!("Could not serialize credential rotation report: {e}");
+                    InternalError::serialize_error("Could not serialize rotation report", None)
+                })
+            },
+        )
+        .await?;
+
+    Ok(Json(ServerResponse::new("operation", operation)))
+}
+
+/// Rotates the credentials of `connection` and points it at the secret holding them
+async fn rotate_connection_credentials(
+    state: &AppState,
+    connection: &Connection,
+    rotation: &CredentialRotation,
+    model_definition: &ConnectionModelDefinition,
+) -> Result<(), IntegrationOSError> {
+    let rotated = rotate_secret(
+        &state.extractor_caller,
+        state.secrets_client.as_ref(),
+        &connection.secrets_service_id,
+        &connection.ownership.id,
+        rotation,
+        model_definition,
+    )
+    .await?;
+
+    let mut record_metadata = connection.record_metadata.clone();
+    record_metadata.mark_updated("system");
+    let mut update = bson::to_document(&record_metadata)
+        .map_err(|e| InternalError::serialize_error(&e.to_string(), None))?;
+    update.insert("secretsServiceId", rotated.id());
+    update.insert("credentialsRotatedAt", record_metadata.updated_at);
+    state
+        .app_stores
+        .connection
+        .update_one(&connection.id.to_string(), doc! { "$set": update })
+        .await?;
+
+    // The cached connection still points at the previous secret, which the platform may have
+    // revoked already
+    if let Ok(key) = HeaderValue::from_str(&connection.key) {
+        state
+            .connections_cache
+            .remove((connection.ownership.id.clone(), key))
+            .await?;
+    }
+    info!("Rotated credentials of connection {}", connection.id);
+
+    Ok(())
+}
+
+/// Calls the key-rotation endpoint of the platform with the current secret, and stores the
+/// rotated credentials in a new secret
+async fn rotate_secret(
+    caller: &UnifiedDestination,
+    secrets_client: &(dyn SecretExt + Sync + Send),
+    secrets_service_id: &str,
+    buildable_id: &str,
+    rotation: &CredentialRotation,
+    model_definition: &ConnectionModelDefinition,
+) -> Result<Secret, IntegrationOSError> {
+    let secret = secrets_client
+        .get(secrets_service_id, buildable_id)
+        .await?
+        .as_value()?;
+
+    let response = caller
+        .execute_model_definition(
+            model_definition,
+            HeaderMap::new(),
+            &HashMap::new(),
+            &secret,
+            None,
+        )
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(ApplicationError::failed_dependency(
+            &format!("Key rotation endpoint answered with {status}"),
+            None,
+        ));
+    }
+    let response = response.json::<Value>().await.map_err(|e| {
+        InternalError::deserialize_error(&format!("Invalid rotation response: {e}"), None)
+    })?;
+
+    secrets_client
+        .create(&rotation.rotate(&secret, &response)?, buildable_id)
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::async_trait;
+    use integrationos_domain::{
+        api_model_config::{ApiModelConfig, AuthMethod, SamplesInput, SchemasInput},
+        connection_model_definition::{CrudAction, PlatformInfo, TestConnection},
+        database::DatabaseConfig,
+        id::prefix::IdPrefix,
+        secret::SecretVersion,
+    };
+    use integrationos_unified::{rate_limit::RateLimitConfig, unified::UnifiedCacheTTLs};
+    use mockito::{Matcher, Server};
+    use serde_json::json;
+    use std::{collections::BTreeMap, sync::Mutex};
+
+    /// Serves the secret `{"API_KEY": "old", "ACCOUNT_ID": "account"}`, and keeps the secrets
+    /// created
+    #[derive(Default)]
+    struct SecretsClient {
+        created: Mutex<Vec<Value>>,
+    }
+
+    #[async_trait]
+    impl SecretExt for SecretsClient {
+        async fn get(&self, _id: &str, buildable_id: &str) -> Result<Secret, IntegrationOSError> {
+            Ok(Secret::new(
+                json!({ "API_KEY": "old", "ACCOUNT_ID": "account" }).to_string(),
+                Some(SecretVersion::V2),
+                buildable_id.to_string(),
+                None,
+            ))
+        }
+
+        async fn create(
+            &self,
+            secret: &Value,
+            buildable_id: &str,
+        ) -> Result<Secret, IntegrationOSError> {
+            self.created.lock().unwrap().push(secret.clone());
+            Ok(Secret::new(
+                secret.to_string(),
+                Some(SecretVersion::V2),
+                buildable_id.to_string(),
+                None,
+            ))
+        }
+
+        async fn ping(&self) -> Result<(), IntegrationOSError> {
+            Ok(())
+        }
+    }
+
+    fn rotation_definition(base_url: String) -> ConnectionModelDefinition {
+        ConnectionModelDefinition {
+            id: Id::now(IdPrefix::ConnectionModelDefinition),
+            platform_version: "v1".to_string(),
+            connection_platform: "platform".to_string(),
+            connection_definition_id: Id::now(IdPrefix::ConnectionDefinition),
+            title: "Rotate API key".to_string(),
+            name: "Rotate API key".to_string(),
+            key: "api::platform::v1::key::custom::rotate_key".to_string(),
+            model_name: "Key".to_string(),
+            platform_info: PlatformInfo::Api(ApiModelConfig {
+                base_url,
+                path: "keys/rotate".to_string(),
+                auth_method: AuthMethod::BearerToken {
+                    value: "{{API_KEY}}".to_string(),
+                },
+                headers: None,
+                content: None,
+                query_params: None,
+                schemas: SchemasInput {
+                    headers: None,
+                    query_params: None,
+                    path_params: None,
+                    body: None,
+                },
+                samples: SamplesInput {
+                    headers: None,
+                    query_params: None,
+                    path_params: None,
+                    body: None,
+                },
+                responses: vec![],
+                paths: None,
+            }),
+            action: http::Method::POST,
+            action_name: CrudAction::Custom,
+            extractor_config: None,
+            test_connection_status: TestConnection::default(),
+            test_connection_payload: None,
+            record_metadata: Default::default(),
+            is_default_crud_mapping: None,
+            mapping: None,
+            supported: true,
+            shadow: None,
+            streaming: false,
+            timeout: None,
+            error_format: None,
+            pagination_limits: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rotated_credential_is_stored() {
+        let secrets_client = Arc::new(SecretsClient::default());
+        let caller = UnifiedDestination::new(
+            DatabaseConfig::default(),
+            100,
+            secrets_client.clone(),
+            UnifiedCacheTTLs {
+                connection_cache_ttl_secs: 60,
+                connection_definition_cache_ttl_secs: 60,
+                connection_model_definition_cache_ttl_secs: 60,
+                connection_model_schema_cache_ttl_secs: 60,
+                secret_cache_ttl_secs: 30,
+            },
+            RateLimitConfig::default(),
+        )
+        .await
+        .expect("Failed to create unified destination");
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/keys/rotate")
+            .match_header("authorization", "Bearer old")
+            .with_status(200)
+            .with_body(r#"{"data": {"key": "new"}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let model_definition = rotation_definition(server.url());
+        let rotation = CredentialRotation {
+            connection_model_definition_id: model_definition.id,
+            credentials: BTreeMap::from([("API_KEY".to_string(), "/data/key".to_string())]),
+        };
+
+        let secret = rotate_secret(
+            &caller,
+            secrets_client.as_ref(),
+            "secret",
+            "buildable",
+            &rotation,
+            &model_definition,
+        )
+        .await
+        .expect("Failed to rotate credentials");
+        mock.assert_async().await;
+
+        let rotated = json!({ "API_KEY": "new", "ACCOUNT_ID": "account" });
+        assert_eq!(secret.as_value().unwrap(), rotated);
+        assert_eq!(*secrets_client.created.lock().unwrap(), vec![rotated]);
+
+        // Nothing is stored when the platform refuses to rotate
+        server
+            .mock("POST", "/keys/rotate")
+            .match_header("authorization", Matcher::Any)
+            .with_status(403)
+            .create_async()
+            .await;
+        assert!(rotate_secret(
+            &caller,
+            secrets_client.as_ref(),
+            "secret",
+            "buildable",
+            &rotation,
+            &model_definition,
+        )
+        .await
+        .is_err());
+        assert_eq!(secrets_client.created.lock().unwrap().len(), 1);
+    }
+}
@@ -16,7 +16,7 @@ use integrationos_domain::{
     algebra::MongoStore,
     connection_definition::{ConnectionDefinitionType, Paths},
     environment::Environment,
-    event_access::EventAccess,
+    event_access::{EventAccess, EventAccessScope},
     event_type::EventType,
     id::{prefix::IdPrefix, Id},
     ownership::Ownership,
@@ -52,6 +52,10 @@ pub struct CreateEventAccessRequest {
     pub namespace: Option<String>,
     pub connection_type: ConnectionDefinitionType,
     pub paths: Paths,
+    /// Scopes of the event access, all of them when unset. An empty list is rejected, as keys
+    /// holding no scope are read as predating scopes and granted them all.
+    #[serde(default)]
+    pub scopes: Option<Vec<EventAccessScope>>,
 }
 
 impl RequestExt for CreateEventAccessRequest {
@@ -76,6 +80,8 @@ pub struct CreateEventAccessPayloadWithOwnership {
     pub paths: Paths,
     pub ownership: Ownership,
     pub throughput: Option<u64>,
+    #[serde(default)]
+    pub scopes: Option<Vec<EventAccessScope>>,
 }
 
 impl CreateEventAccessPayloadWithOwnership {
@@ -84,6 +90,17 @@ impl CreateEventAccessPayloadWithOwnership {
     }
 }
 
+/// Rejects explicitly empty scopes, which the key would carry as every scope
+fn validate_scopes(scopes: &Option<Vec<EventAccessScope>>) -> Result<(), IntegrationOSError> {
+    match scopes {
+        Some(scopes) if scopes.is_empty() => Err(ApplicationError::bad_request(
+            "Invalid payload: scopes must not be empty",
+            None,
+        )),
+        _ => Ok(()),
+    }
+}
+
 pub fn generate_event_access(
     config: ConnectionsConfig,
     payload: CreateEventAccessPayloadWithOwnership,
@@ -92,6 +109,7 @@ pub fn generate_event_access(
         .namespace
         .unwrap_or_else(|| DEFAULT_NAMESPACE.to_string());
     let group = payload.group.unwrap_or_else(|| DEFAULT_GROUP.to_string());
+    let scopes = payload.scopes.unwrap_or_else(EventAccessScope::all);
 
     let access_key = AccessKey {
         prefix: AccessKeyPrefix {
@@ -118,6 +136,7 @@ pub fn generate_event_access(
                     .unwrap_or("$.body.id".to_string()),
             ),
             parent_access_key: None,
+            scopes: scopes.iter().map(ToString::to_string).collect(),
         },
     };
 
@@ -143,6 +162,7 @@ pub fn generate_event_access(
         paths: payload.paths,
         access_key: encoded_access_key.to_string(),
         environment: payload.environment,
+        scopes,
         record_metadata: RecordMetadata::default(),
         throughput: payload.throughput.unwrap_or(config.event_access_throughput),
     })
@@ -182,6 +202,8 @@ pub async fn create_event_access_for_new_user(
         ));
     }
 
+    validate_scopes(&req.scopes)?;
+
     let throughput = get_client_throughput(&req.ownership.id, &state).await?;
 
     let req = CreateEventAccessPayloadWithOwnership {
@@ -221,6 +243,8 @@ pub async fn create_event_access(
         ));
     }
 
+    validate_scopes(&payload.scopes)?;
+
     let throughput = get_client_throughput(&access.ownership.id, &state).await?;
 
     let event_access_payload = CreateEventAccessPayloadWithOwnership {
@@ -233,6 +257,7 @@ pub async fn create_event_access(
         paths: payload.paths.clone(),
         ownership: access.ownership.clone(),
         throughput: Some(throughput),
+        scopes: payload.scopes.clone(),
     };
 
     let event_access =
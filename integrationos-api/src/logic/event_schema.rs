@@ -0,0 +1,169 @@
+use crate::{router::ServerResponse, server::AppState};
+use axum::{
+    extract::{Path, State},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use bson::doc;
+use integrationos_domain::{
+    event_access::EventAccess,
+    event_schema::{latest_event_schema, EventSchema, PayloadSchema, SchemaCompatibility},
+    ApplicationError, Event, IntegrationOSError, InternalError,
+};
+use mongodb::{options::IndexOptions, Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+/// Registrations of a version racing with others for the same event type before giving up
+const MAX_REGISTRATION_ATTEMPTS: usize = 3;
+
+pub fn get_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", post(register_event_schema))
+        .route("/:event_type", get(get_event_schema_versions))
+}
+
+/// Ensures a version of the schema of an event type is registered once per owner, concurrent
+/// registrations of the same version failing on a duplicate key instead
+pub async fn index_event_schemas(
+    schemas: &Collection<EventSchema>,
+) -> Result<(), mongodb::error::Error> {
+    schemas
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "ownership.buildableId": 1, "eventType": 1, "version": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            None,
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterEventSchemaRequest {
+    pub event_type: String,
+    pub schema: PayloadSchema,
+    /// Policy of the new version, the one of the previous version if unset
+    pub compatibility: Option<SchemaCompatibility>,
+}
+
+/// Registers a new version of the schema of an event type of the owner of the access key. A
+/// version breaking the compatibility policy with the previous one is rejected. Registrations
+/// racing for the same version are checked again against the one that won.
+pub async fn register_event_schema(
+    Extension(access): Extension<Arc<EventAccess>>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RegisterEventSchemaRequest>,
+) -> Result<Json<ServerResponse<EventSchema>>, IntegrationOSError> {
+    let store = &state.app_stores.event_schema;
+    let ownership_id = access.ownership.id.as_ref();
+
+    let mut attempt = 1;
+    let schema = loop {
+        let previous = latest_event_schema(store, ownership_id, &payload.event_type).await?;
+
+        let compatibility = payload.compatibility.unwrap_or_else(|| {
+            previous
+                .as_ref()
+                .map(|previous| previous.compatibility)
+                .unwrap_or_default()
+        });
+        if let Some(previous) = &previous {
+            let issues = compatibility.check(&previous.schema, &payload.schema);
+            if !issues.is_empty() {
+                return Err(ApplicationError::conflict(
+                    &format!(
+                        "Schema is not {compatibility} compatible with version {}: {}",
+                        previous.version,
+                        issues.join(", ")
+                    ),
+                    None,
+                ));
+            }
+        }
+
+        let schema = EventSchema::new(
+            access.ownership.clone(),
+            payload.event_type.clone(),
+            payload.schema.clone(),
+            compatibility,
+            previous.as_ref(),
+        );
+        match store.create_one(&schema).await {
+            Ok(_) => break schema,
+            Err(IntegrationOSError::Internal(InternalError::UniqueFieldViolation { .. }))
+                if attempt < MAX_REGISTRATION_ATTEMPTS =>
+            {
+                warn!(
+                    "Version {} of the schema of {} was registered concurrently, retrying",
+                    schema.version, schema.event_type
+                );
+                attempt += 1;
+            }
+            Err(e) => {
+                error!("Error creating event schema: {e}");
+                return Err(e);
+            }
+        }
+    };
+    state
+        .event_schemas_cache
+        .invalidate(ownership_id, &schema.event_type)
+        .await;
+    info!(
+        "Registered version {} of the schema of {}",
+        schema.version, schema.event_type
+    );
+
+    Ok(Json(ServerResponse::new("eventSchema", schema)))
+}
+
+/// Versions of the schema of an event type of the owner of the access key, the latest first
+pub async fn get_event_schema_versions(
+    Extension(access): Extension<Arc<EventAccess>>,
+    Path(event_type): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ServerResponse<Vec<EventSchema>>>, IntegrationOSError> {
+    let versions = state
+        .app_stores
+        .event_schema
+        .get_many(
+            Some(doc! {
+                "ownership.buildableId": access.ownership.id.as_ref(),
+                "eventType": &event_type,
+                "deleted": false,
+            }),
+            None,
+            Some(doc! { "version": -1 }),
+            None,
+            None,
+        )
+        .await?;
+
+    Ok(Json(ServerResponse::new("eventSchemas", versions)))
+}
+
+/// Rejects an event whose payload does not satisfy the latest schema registered by its owner
+/// for its name. Events of types without a schema are accepted as they are.
+pub async fn validate_event_payload(
+    state: &AppState,
+    event: &Event,
+) -> Result<(), IntegrationOSError> {
+    let schema = state
+        .event_schemas_cache
+        .get_or_insert_latest(
+            &state.app_stores.event_schema,
+            &event.ownership.id,
+            &event.name,
+        )
+        .await?;
+
+    match schema {
+        Some(schema) => schema.validate_event(event),
+        None => Ok(()),
+    }
+}
@@ -1,27 +1,297 @@
-use super::{read_without_count, PublicExt, RequestExt};
-use crate::server::{AppState, AppStores};
-use axum::{routing::get, Router};
-use bson::doc;
-use integrationos_domain::{algebra::MongoStore, Event};
+use super::{read_from_store, PublicExt, ReadJson, RequestExt};
+use crate::{
+    router::ServerResponse,
+    server::{AppState, AppStores},
+};
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Extension, Json, Router,
+};
+use bson::{doc, Document};
+use http::HeaderMap;
+use integrationos_domain::{
+    algebra::MongoStore,
+    event_access::EventAccess,
+    field_encryption::FieldEncryption,
+    migration::{backfill_event_document, CURRENT_EVENT_VERSION},
+    ApplicationError, Event, IntegrationOSError, Operation, OperationKind, OperationTracker,
+};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+use tracing::{error, info};
 
 pub fn get_router() -> Router<Arc<AppState>> {
-    Router::new().route("/", get(read_without_count::<CreateEventRequest, Event>))
+    Router::new().route("/", get(read))
+}
+
+/// Reads events from the collection of the tenant, decrypting the fields of their bodies that
+/// are encrypted at rest
+async fn read(
+    headers: HeaderMap,
+    access: Option<Extension<Arc<EventAccess>>>,
+    query: Option<Query<BTreeMap<String, String>>>,
+    State(state): State<Arc<AppState>>,
+) -> Result<ReadJson<Value>, IntegrationOSError> {
+    let store = match &access {
+        Some(Extension(access)) => event_store(
+            &state,
+            &state
+                .config
+                .dedicated_event_tenants
+                .collection_for(&access.ownership.client_id),
+        ),
+        None => CreateEventRequest::get_store(state.app_stores.clone()),
+    };
+    let Json(mut res) = read_from_store::<CreateEventRequest, Document>(
+        headers,
+        access,
+        query,
+        store,
+        false,
+        doc! {},
+    )
+    .await?;
+
+    if let Some(field_encryption) = &state.event_field_encryption {
+        for row in res.args.rows.iter_mut() {
+            decrypt_body(field_encryption, row).await?;
+        }
+    }
+
+    Ok(ReadJson::new(
+        res,
+        state.config.read_streaming_threshold_bytes,
+    ))
+}
+
+/// Events of the collection, read as documents to handle every stored shape
+fn event_store(state: &AppState, collection: &str) -> MongoStore<Document> {
+    MongoStore {
+        collection: state.app_stores.db.collection(collection),
+    }
+}
+
+async fn decrypt_body(
+    field_encryption: &FieldEncryption,
+    event: &mut Value,
+) -> Result<(), IntegrationOSError> {
+    if let Some(Value::String(body)) = event.get_mut("body") {
+        *body = field_encryption.decrypt(body).await?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackfillEventsRequest {
+    /// Values given to the top-level fields missing from stored events
+    #[serde(default)]
+    pub defaults: BTreeMap<String, Value>,
+    #[serde(default = "default_backfill_batch_size")]
+    pub batch_size: u64,
+}
+
+fn default_backfill_batch_size() -> u64 {
+    500
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlushEventBufferResponse {
+    pub flushed: usize,
+}
+
+/// Saves the events buffered by this instance right away instead of on the size and timeout
+/// thresholds of the buffer, and returns how many were saved once they are
+pub async fn flush_event_buffer(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ServerResponse<FlushEventBufferResponse>>, IntegrationOSError> {
+    let flushed = state.event_buffer_flush.flush().await.map_err(|e| {
+        error!("Could not flush the event buffer: {e}");
+        e
+    })?;
+    info!("Flushed {flushed} buffered events on request");
+
+    Ok(Json(ServerResponse::new(
+        "eventBuffer",
+        FlushEventBufferResponse { flushed },
+    )))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventDenylistPayload {
+    pub event_types: BTreeSet<String>,
+}
+
+/// Event types this instance drops at ingestion
+pub async fn get_event_denylist(
+    State(state): State<Arc<AppState>>,
+) -> Json<ServerResponse<EventDenylistPayload>> {
+    Json(ServerResponse::new(
+        "eventDenylist",
+        EventDenylistPayload {
+            event_types: state.event_denylist.event_types(),
+        },
+    ))
+}
+
+/// Replaces the event types this instance drops at ingestion, taking effect for the next
+/// events ingested. An empty list ingests every event again.
+pub async fn update_event_denylist(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<EventDenylistPayload>,
+) -> Result<Json<ServerResponse<EventDenylistPayload>>, IntegrationOSError> {
+    if payload
+        .event_types
+        .iter()
+        .any(|event_type| event_type.trim().is_empty())
+    {
+        return Err(ApplicationError::bad_request(
+            "Event types must not be empty",
+            None,
+        ));
+    }
+
+    state.event_denylist.replace(payload.event_types);
+    let event_types = state.event_denylist.event_types();
+    info!("Event denylist updated to {event_types:?}");
+
+    Ok(Json(ServerResponse::new(
+        "eventDenylist",
+        EventDenylistPayload { event_types },
+    )))
+}
+
+/// Starts migrating the stored events to the current shape in the background, filling the
+/// fields missing from them with the requested defaults, and returns the operation tracking
+/// it. The events of every collection are migrated, the ones of the dedicated tenants
+/// included. Only the events needing it are selected, so that running the backfill again
+/// after an interruption resumes where it stopped.
+pub async fn backfill_events(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<BackfillEventsRequest>,
+) -> Result<Json<ServerResponse<Operation>>, IntegrationOSError> {
+    if req.batch_size == 0 {
+        return Err(ApplicationError::bad_request(
+            "Batch size must be greater than 0",
+            None,
+        ));
+    }
+
+    let defaults = bson::to_document(&req.defaults).map_err(|e| {
+        error!("Could not convert backfill defaults: {e}");
+        ApplicationError::bad_request("Invalid backfill defaults", None)
+    })?;
+
+    let operation = OperationTracker::new(state.app_stores.operations.clone())
+        .start(OperationKind::EventBackfill, move |progress| async move {
+            let stores = state
+                .config
+                .dedicated_event_tenants
+                .collections()
+                .iter()
+                .map(|collection| event_store(&state, collection))
+                .collect::<Vec<_>>();
+            let filter = backfill_filter(&defaults);
+            let mut total = 0;
+            for store in &stores {
+                total += store.count(filter.clone(), None).await?;
+            }
+
+            let mut backfilled = 0_u64;
+            for store in &stores {
+                let mut last_id = None;
+                loop {
+                    let mut batch_filter = filter.clone();
+                    if let Some(last_id) = &last_id {
+                        batch_filter =
+                            doc! { "$and": [batch_filter, { "_id": { "$gt": last_id } }] };
+                    }
+
+                    let batch = store
+                        .get_many(
+                            Some(batch_filter),
+                            None,
+                            Some(doc! { "_id": 1 }),
+                            Some(req.batch_size),
+                            None,
+                        )
+                        .await?;
+                    let Some(last) = batch.last() else {
+                        break;
+                    };
+                    last_id = last.get("_id").cloned();
+
+                    for document in &batch {
+                        let fields = backfill_event_document(document, &defaults);
+                        let Some(id) = document.get_str("_id").ok() else {
+                            continue;
+                        };
+                        if !fields.is_empty() {
+                            store.update_one(id, doc! { "$set": fields }).await?;
+                        }
+                    }
+
+                    backfilled += batch.len() as u64;
+                    if let Err(e) = progress
+                        .report(backfilled as f64 / total.max(backfilled) as f64)
+                        .await
+                    {
+                        error!("Could not report event backfill progress: {e}");
+                    }
+                    info!("Backfilled {backfilled} of {total} events");
+                }
+            }
+
+            Ok(json!({ "backfilled": backfilled }))
+        })
+        .await?;
+
+    Ok(Json(ServerResponse::new("operation", operation)))
+}
+
+/// Selects the events behind the current version or missing any of the `defaults`
+fn backfill_filter(defaults: &Document) -> Document {
+    let mut conditions = vec![
+        doc! { "schemaVersion": { "$exists": false } },
+        doc! { "schemaVersion": { "$lt": CURRENT_EVENT_VERSION as i64 } },
+    ];
+    conditions.extend(
+        defaults
+            .keys()
+            .map(|field| doc! { field: { "$exists": false } }),
+    );
+
+    doc! { "$or": conditions }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct CreateEventRequest;
 
-impl PublicExt<Event> for CreateEventRequest {
-    fn public(input: Event) -> serde_json::Value {
-        serde_json::to_value(input.to_public()).unwrap_or_default()
+impl PublicExt<Document> for CreateEventRequest {
+    fn public(input: Document) -> serde_json::Value {
+        match Event::from_document(input) {
+            Ok(event) => serde_json::to_value(event.to_public()).unwrap_or_default(),
+            Err(e) => {
+                error!("Could not read stored event: {e}");
+                serde_json::Value::Null
+            }
+        }
     }
 }
+
 impl RequestExt for CreateEventRequest {
-    type Output = Event;
+    type Output = Document;
 
     fn get_store(stores: AppStores) -> MongoStore<Self::Output> {
-        stores.event
+        MongoStore {
+            collection: stores.event.collection.clone_with_type(),
+        }
     }
 }
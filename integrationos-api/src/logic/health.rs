@@ -0,0 +1,163 @@
+use crate::server::AppState;
+use axum::{extract::State, Json};
+use bson::doc;
+use http::StatusCode;
+use integrationos_domain::IntegrationOSError;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, future::Future, sync::Arc, time::Duration};
+use tokio::{join, time::timeout};
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Status {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyStatus {
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthResponse {
+    pub status: Status,
+    /// Status of each dependency, empty for liveness
+    pub checks: BTreeMap<String, DependencyStatus>,
+}
+
+impl HealthResponse {
+    fn new(checks: BTreeMap<String, DependencyStatus>) -> Self {
+        let status = if checks.values().all(|check| check.status == Status::Up) {
+            Status::Up
+        } else {
+            Status::Down
+        };
+        Self { status, checks }
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self.status {
+            Status::Up => StatusCode::OK,
+            Status::Down => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+/// Liveness probe, answering as long as the process can handle requests
+pub async fn healthz() -> Json<HealthResponse> {
+    Json(HealthResponse::new(BTreeMap::new()))
+}
+
+/// Readiness probe, checking that the warmups completed and that MongoDB and the secrets
+/// client can be reached
+pub async fn readyz(State(state): State<Arc<AppState>>) -> (StatusCode, Json<HealthResponse>) {
+    // Dependencies aren't checked until then, the instance isn't ready whatever their status
+    if !state.warmup.is_done() {
+        let response = HealthResponse::new(BTreeMap::from([(
+            "warmup".to_string(),
+            DependencyStatus {
+                status: Status::Down,
+                error: Some("Warmups have not completed".to_string()),
+            },
+        )]));
+        return (response.status_code(), Json(response));
+    }
+
+    let mut checks = check_dependencies(&state).await;
+    for (name, check) in &checks {
+        if let Some(error) = &check.error {
+            warn!("Readiness check of {name} failed: {error}");
+        }
+    }
+    checks.insert(
+        "warmup".to_string(),
+        DependencyStatus {
+            status: Status::Up,
+            error: None,
+        },
+    );
+
+    let response = HealthResponse::new(checks);
+    (response.status_code(), Json(response))
+}
+
+/// Status of MongoDB and the secrets client, each given `READINESS_CHECK_TIMEOUT_MILLIS` to
+/// answer
+pub async fn check_dependencies(state: &AppState) -> BTreeMap<String, DependencyStatus> {
+    let wait = Duration::from_millis(state.config.readiness_check_timeout_millis);
+    let (mongo, secrets) = join!(
+        check(wait, async {
+            state
+                .app_stores
+                .db
+                .run_command(doc! { "ping": 1 }, None)
+                .await
+                .map(|_| ())
+                .map_err(IntegrationOSError::from)
+        }),
+        check(wait, state.secrets_client.ping()),
+    );
+
+    BTreeMap::from([
+        ("mongo".to_string(), mongo),
+        ("secrets".to_string(), secrets),
+    ])
+}
+
+async fn check(
+    wait: Duration,
+    ping: impl Future<Output = Result<(), IntegrationOSError>>,
+) -> DependencyStatus {
+    let error = match timeout(wait, ping).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("No response within {}ms", wait.as_millis())),
+    };
+    DependencyStatus {
+        status: if error.is_none() {
+            Status::Up
+        } else {
+            Status::Down
+        },
+        error,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use integrationos_domain::InternalError;
+
+    #[tokio::test]
+    async fn test_failing_or_slow_dependency_is_down() {
+        let wait = Duration::from_millis(10);
+        let up = check(wait, async { Ok(()) }).await;
+        let failing = check(wait, async {
+            Err(InternalError::connection_error("Connection refused", None))
+        })
+        .await;
+        let slow = check(wait, std::future::pending()).await;
+
+        assert_eq!(up.status, Status::Up);
+        assert_eq!(failing.status, Status::Down);
+        assert!(failing.error.unwrap().contains("Connection refused"));
+        assert_eq!(slow.status, Status::Down);
+        assert_eq!(slow.error.as_deref(), Some("No response within 10ms"));
+
+        let response = HealthResponse::new(BTreeMap::from([
+            ("mongo".to_string(), up),
+            ("secrets".to_string(), slow),
+        ]));
+        assert_eq!(response.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            HealthResponse::new(BTreeMap::new()).status_code(),
+            StatusCode::OK
+        );
+    }
+}
@@ -1,17 +1,20 @@
 use super::ReadResponse;
 use crate::{
-    metrics::{DAILY_KEY, MONTHLY_KEY, PLATFORMS_KEY, TOTAL_KEY},
+    metrics::{DAILY_KEY, MODES_KEY, MONTHLY_KEY, PLATFORMS_KEY, TOTAL_KEY},
     router::ServerResponse,
     server::AppState,
 };
 use axum::{
     extract::{Path, Query, State},
+    response::IntoResponse,
     routing::get,
     Extension, Json, Router,
 };
 use bson::Document;
+use http::header::CONTENT_TYPE;
 use integrationos_domain::{
-    event_access::EventAccess, ApplicationError, IntegrationOSError, InternalError, Store,
+    event_access::EventAccess, ApplicationError, ConnectionMode, IntegrationOSError, InternalError,
+    Store,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -24,6 +27,14 @@ pub fn get_router() -> Router<Arc<AppState>> {
         .route("/total", get(get_full_record))
 }
 
+/// Metrics of the process in the Prometheus text format
+pub async fn get_prometheus_metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.prometheus.render(),
+    )
+}
+
 #[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum Granularity {
@@ -49,6 +60,8 @@ pub struct QueryParams {
     metric_type: Option<MetricType>,
     #[serde(default)]
     platform: Option<String>,
+    #[serde(default)]
+    mode: Option<ConnectionMode>,
     #[serde(flatten)]
     granularity: Option<Granularity>,
 }
@@ -94,6 +107,7 @@ pub async fn get_full_record(
             total: 1,
             skip: 0,
             limit: 1,
+            next_cursor: None,
         },
     )))
 }
@@ -140,6 +154,21 @@ pub async fn get_metrics(
         )));
     };
 
+    let doc = if let Some(mode) = &query_params.mode {
+        let Ok(doc) = doc
+            .get_document(MODES_KEY)
+            .and_then(|d| d.get_document(mode.as_ref()))
+        else {
+            return Ok(Json(ServerResponse::new(
+                "metrics",
+                MetricResponse { count: 0 },
+            )));
+        };
+        doc
+    } else {
+        doc
+    };
+
     let doc = if let Some(platform) = &query_params.platform {
         let Ok(doc) = doc
             .get_document(PLATFORMS_KEY)
@@ -4,12 +4,17 @@ use crate::{
     server::{AppState, AppStores},
 };
 use axum::{
+    body::{Body, Bytes},
     extract::{Path, Query, State},
+    response::{IntoResponse, Response},
     Extension, Json,
 };
-use bson::doc;
-use http::{HeaderMap, HeaderValue};
-use integrationos_cache::local::connection_cache::ConnectionCacheArcStrHeaderKey;
+use bson::{doc, Document};
+use futures::{stream, StreamExt};
+use http::{header::CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue};
+use integrationos_cache::local::connection_cache::{
+    CachedConnection, ConnectionCacheArcStrHeaderKey,
+};
 use integrationos_domain::{
     algebra::MongoStore, event_access::EventAccess, ApplicationError, Connection,
     IntegrationOSError, InternalError, OAuth, Store, Unit,
@@ -20,7 +25,9 @@ use serde_json::Value;
 use std::{collections::BTreeMap, fmt::Debug, future::Future, sync::Arc};
 use tokio::try_join;
 use tracing::error;
+use uuid::Uuid;
 
+pub mod cache;
 pub mod common_enum;
 pub mod common_model;
 pub mod connection;
@@ -28,20 +35,27 @@ pub mod connection_definition;
 pub mod connection_model_definition;
 pub mod connection_model_schema;
 pub mod connection_oauth_definition;
+pub mod correlation;
+pub mod credential_rotation;
 pub mod event_access;
+pub mod event_schema;
 pub mod events;
+pub mod health;
 pub mod metrics;
 pub mod oauth;
 pub mod openapi;
+pub mod operation;
 pub mod passthrough;
 pub mod pipeline;
 pub mod platform;
 pub mod platform_page;
 pub mod schema_generator;
 pub mod secrets;
+pub mod storage;
 pub mod transactions;
 pub mod unified;
 pub mod utils;
+pub mod webhook;
 
 const INTEGRATION_OS_PASSTHROUGH_HEADER: &str = "x-integrationos-passthrough";
 
@@ -151,12 +165,236 @@ where
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Default)]
-pub struct ReadResponse<T> {
-    pub rows: Vec<T>,
-    pub total: u64,
-    pub skip: u64,
-    pub limit: u64,
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportRequest<T> {
+    pub items: Vec<T>,
+    /// Stops the import at the first item that fails, leaving the remaining ones untouched
+    #[serde(default)]
+    pub stop_on_first_error: bool,
+}
+
+/// Outcome of importing a single item, identified by its position in the request
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportItemResult {
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Items that were not attempted because the import stopped at an earlier failure
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResponse {
+    pub results: Vec<ImportItemResult>,
+    pub summary: ImportSummary,
+}
+
+/// Imports `items` one at a time, recording the id or the error of each of them
+async fn import_items<T, F, Fut>(
+    items: Vec<T>,
+    stop_on_first_error: bool,
+    mut import: F,
+) -> ImportResponse
+where
+    F: FnMut(T) -> Fut,
+    Fut: Future<Output = Result<Option<String>, IntegrationOSError>>,
+{
+    let mut response = ImportResponse {
+        summary: ImportSummary {
+            total: items.len(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    for (index, item) in items.into_iter().enumerate() {
+        let result = match import(item).await {
+            Ok(id) => {
+                response.summary.succeeded += 1;
+                ImportItemResult {
+                    index,
+                    id,
+                    error: None,
+                }
+            }
+            Err(e) => {
+                response.summary.failed += 1;
+                ImportItemResult {
+                    index,
+                    id: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        };
+
+        let failed = result.error.is_some();
+        response.results.push(result);
+        if failed && stop_on_first_error {
+            break;
+        }
+    }
+
+    response.summary.skipped =
+        response.summary.total - response.summary.succeeded - response.summary.failed;
+    response
+}
+
+/// Creates every item of the request, reporting the outcome of each of them instead of
+/// failing the whole import on the first invalid item
+pub async fn import<T, U>(
+    access: Option<Extension<Arc<EventAccess>>>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ImportRequest<T>>,
+) -> Result<Json<ServerResponse<ImportResponse>>, IntegrationOSError>
+where
+    T: RequestExt<Output = U> + HookExt<U> + 'static,
+    U: Serialize + DeserializeOwned + Unpin + Sync + Send + Debug + 'static,
+{
+    let access = access.map(|Extension(e)| e);
+    let store = &T::get_store(state.app_stores.clone());
+    let stores = &state.app_stores;
+
+    let response = import_items(payload.items, payload.stop_on_first_error, |item| {
+        let access = access.clone();
+        async move {
+            let output = access
+                .map(|e| item.access(e))
+                .unwrap_or_else(|| item.from())
+                .ok_or_else(|| {
+                    ApplicationError::bad_request("Could not generate output from payload", None)
+                })?;
+
+            store.create_one(&output).await.map_err(|e| {
+                error!("Error importing object: {e}");
+                e
+            })?;
+
+            T::after_create_hook(&output, stores)
+                .await
+                .map_err(|e| {
+                    error!("Error running after create hook: {:?}", e);
+                })
+                .ok();
+
+            Ok(serde_json::to_value(&output).ok().and_then(|value| {
+                value
+                    .get("_id")
+                    .and_then(Value::as_str)
+                    .map(ToString::to_string)
+            }))
+        }
+    })
+    .await;
+
+    Ok(Json(ServerResponse::new("import", response)))
+}
+
+pub use integrationos_domain::algebra::ReadResponse;
+
+/// Number of rows serialized to estimate the size of a [`ReadJson`]
+const READ_SIZE_SAMPLE: usize = 16;
+
+/// Response of the read endpoints. Responses estimated to be larger than the streaming
+/// threshold are sent as a chunked JSON document, one row per chunk, instead of being
+/// serialized in a single buffer. Smaller ones are sent buffered, with their length.
+pub struct ReadJson<T: Serialize> {
+    response: ServerResponse<ReadResponse<T>>,
+    streaming_threshold: usize,
+}
+
+impl<T: Serialize> ReadJson<T> {
+    pub fn new(response: ServerResponse<ReadResponse<T>>, streaming_threshold: usize) -> Self {
+        Self {
+            response,
+            streaming_threshold,
+        }
+    }
+
+    /// Extrapolates the size of the rows from the size of the first ones
+    fn estimated_size(&self) -> usize {
+        let rows = &self.response.args.rows;
+        let sample = rows.iter().take(READ_SIZE_SAMPLE);
+        let sample_len = sample.len();
+        if sample_len == 0 {
+            return 0;
+        }
+
+        let sample_size = sample
+            .map(|row| {
+                serde_json::to_vec(row)
+                    .map(|row| row.len())
+                    .unwrap_or_default()
+            })
+            .sum::<usize>();
+        sample_size / sample_len * rows.len()
+    }
+}
+
+impl<T: Serialize + Send + 'static> ReadJson<T> {
+    fn stream(self) -> Response {
+        let ServerResponse {
+            response_type,
+            args,
+        } = self.response;
+        let next_cursor = args
+            .next_cursor
+            .as_ref()
+            .map(|cursor| serde_json::to_string(cursor).map(|c| format!(r#""nextCursor":{c},"#)))
+            .transpose();
+        let head = serde_json::to_string(&response_type)
+            .and_then(|response_type| Ok((response_type, next_cursor?)))
+            .map(|(response_type, next_cursor)| {
+                format!(
+                    r#"{{"type":{response_type},"total":{},"skip":{},"limit":{},{}"rows":["#,
+                    args.total,
+                    args.skip,
+                    args.limit,
+                    next_cursor.unwrap_or_default()
+                )
+            });
+
+        let rows = args.rows.into_iter().enumerate().map(|(i, row)| {
+            serde_json::to_vec(&row).map(|row| match i {
+                0 => row,
+                _ => [b",".as_slice(), &row].concat(),
+            })
+        });
+        let chunks = stream::iter(
+            std::iter::once(head.map(String::into_bytes))
+                .chain(rows)
+                .chain(std::iter::once(Ok(b"]}".to_vec()))),
+        )
+        .map(|chunk| chunk.map(Bytes::from));
+
+        (
+            [(CONTENT_TYPE, "application/json")],
+            Body::from_stream(chunks),
+        )
+            .into_response()
+    }
+}
+
+impl<T: Serialize + Send + 'static> IntoResponse for ReadJson<T> {
+    fn into_response(self) -> Response {
+        if self.estimated_size() > self.streaming_threshold {
+            self.stream()
+        } else {
+            Json(self.response).into_response()
+        }
+    }
 }
 
 pub async fn read<T, U>(
@@ -164,12 +402,15 @@ pub async fn read<T, U>(
     access: Option<Extension<Arc<EventAccess>>>,
     query: Option<Query<BTreeMap<String, String>>>,
     State(state): State<Arc<AppState>>,
-) -> Result<Json<ServerResponse<ReadResponse<Value>>>, IntegrationOSError>
+) -> Result<ReadJson<Value>, IntegrationOSError>
 where
     T: RequestExt<Output = U> + PublicExt<U> + 'static,
     U: Serialize + DeserializeOwned + Unpin + Sync + Send + Debug + 'static,
 {
-    read_common::<T, U>(headers, access, query, State(state), true).await
+    let threshold = state.config.read_streaming_threshold_bytes;
+    let Json(res) =
+        read_common::<T, U>(headers, access, query, State(state), true, doc! {}).await?;
+    Ok(ReadJson::new(res, threshold))
 }
 
 pub async fn read_without_count<T, U>(
@@ -177,12 +418,15 @@ pub async fn read_without_count<T, U>(
     access: Option<Extension<Arc<EventAccess>>>,
     query: Option<Query<BTreeMap<String, String>>>,
     State(state): State<Arc<AppState>>,
-) -> Result<Json<ServerResponse<ReadResponse<Value>>>, IntegrationOSError>
+) -> Result<ReadJson<Value>, IntegrationOSError>
 where
     T: RequestExt<Output = U> + PublicExt<U> + 'static,
     U: Serialize + DeserializeOwned + Unpin + Sync + Send + Debug + 'static,
 {
-    read_common::<T, U>(headers, access, query, State(state), false).await
+    let threshold = state.config.read_streaming_threshold_bytes;
+    let Json(res) =
+        read_common::<T, U>(headers, access, query, State(state), false, doc! {}).await?;
+    Ok(ReadJson::new(res, threshold))
 }
 
 #[derive(Serialize, Deserialize)]
@@ -316,14 +560,16 @@ struct SparseConnection {
     secrets_service_id: String,
 }
 
+/// Looks up the connection for `connection_key`, along with whether it is a stale copy served
+/// from the cache because the database was unavailable
 async fn get_connection(
     access: &EventAccess,
     connection_key: &HeaderValue,
     stores: &AppStores,
     cache: &ConnectionCacheArcStrHeaderKey,
-) -> Result<Arc<Connection>, IntegrationOSError> {
-    let connection = cache
-        .get_or_insert_with_filter(
+) -> Result<(Arc<Connection>, bool), IntegrationOSError> {
+    let CachedConnection { connection, stale } = cache
+        .get_or_insert_with_filter_or_stale(
             (access.ownership.id.clone(), connection_key.clone()),
             stores.connection.clone(),
             doc! {
@@ -336,6 +582,11 @@ async fn get_connection(
         )
         .await?;
 
+    // The latest secret can't be fetched either while the database is unavailable
+    if stale {
+        return Ok((Arc::new(connection), stale));
+    }
+
     // If Oauth is enabled, fetching the latest secret (due to refresh, cache can't be used)
     if let Some(OAuth::Enabled { .. }) = connection.oauth {
         let collection = stores
@@ -366,23 +617,70 @@ async fn get_connection(
         updated_connection.oauth = Some(sparse_connection.oauth);
         updated_connection.secrets_service_id = sparse_connection.secrets_service_id;
 
-        return Ok(Arc::new(updated_connection));
+        return Ok((Arc::new(updated_connection), stale));
     }
-    Ok(Arc::new(connection))
+    Ok((Arc::new(connection), stale))
 }
 
+/// Flags a response as built from a connection served from a stale cache entry
+fn flag_stale_connection(headers: &mut HeaderMap, header: &str) {
+    if let Ok(header_name) = HeaderName::try_from(header) {
+        headers.insert(header_name, HeaderValue::from_static("true"));
+    }
+}
+
+/// Correlation id of a request, the one sent by the caller in `header` or a new one
+fn correlation_id(headers: &HeaderMap, header: &str) -> String {
+    headers
+        .get(header)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .map(ToOwned::to_owned)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+/// Returns the correlation id of a request in its response
+fn stamp_correlation_id(headers: &mut HeaderMap, header: &str, correlation_id: &str) {
+    if let (Ok(header_name), Ok(value)) = (
+        HeaderName::try_from(header),
+        HeaderValue::from_str(correlation_id),
+    ) {
+        headers.insert(header_name, value);
+    }
+}
+
+/// Reads the records matching the query, along with `extra_filter` which can't be expressed
+/// through query parameters
 pub async fn read_common<T, U>(
     headers: HeaderMap,
     access: Option<Extension<Arc<EventAccess>>>,
     query: Option<Query<BTreeMap<String, String>>>,
     State(state): State<Arc<AppState>>,
     count: bool,
+    extra_filter: Document,
 ) -> Result<Json<ServerResponse<ReadResponse<Value>>>, IntegrationOSError>
 where
     T: RequestExt<Output = U> + PublicExt<U> + 'static,
     U: Serialize + DeserializeOwned + Unpin + Sync + Send + Debug + 'static,
 {
-    let query = shape_mongo_filter(
+    let store = T::get_store(state.app_stores.clone());
+    read_from_store::<T, U>(headers, access, query, store, count, extra_filter).await
+}
+
+/// Same as [`read_common`], reading from `store` rather than the store of `T`
+pub async fn read_from_store<T, U>(
+    headers: HeaderMap,
+    access: Option<Extension<Arc<EventAccess>>>,
+    query: Option<Query<BTreeMap<String, String>>>,
+    store: MongoStore<U>,
+    count: bool,
+    extra_filter: Document,
+) -> Result<Json<ServerResponse<ReadResponse<Value>>>, IntegrationOSError>
+where
+    T: PublicExt<U> + 'static,
+    U: Serialize + DeserializeOwned + Unpin + Sync + Send + Debug + 'static,
+{
+    let mut query = shape_mongo_filter(
         query,
         access.map(|e| {
             let Extension(e) = e;
@@ -390,8 +688,37 @@ where
         }),
         Some(headers),
     );
+    query.filter.extend(extra_filter);
+
+    if let Some(cursor) = query.cursor.as_deref() {
+        let cursor = Some(cursor).filter(|cursor| !cursor.is_empty());
+        let sort = doc! { "createdAt": -1 };
+        let page = if count {
+            store
+                .paginate(query.filter, sort, query.limit, cursor)
+                .await
+        } else {
+            store
+                .paginate_without_count(query.filter, sort, query.limit, cursor)
+                .await
+        };
 
-    let store = T::get_store(state.app_stores.clone());
+        let res = match page {
+            Ok(page) => ReadResponse {
+                rows: page.rows.into_iter().map(T::public).collect(),
+                total: page.total,
+                skip: page.skip,
+                limit: page.limit,
+                next_cursor: page.next_cursor,
+            },
+            Err(e) => {
+                error!("Error reading from store: {e}");
+                return Err(e);
+            }
+        };
+
+        return Ok(Json(ServerResponse::new("read", res)));
+    }
 
     let filter = query.filter.clone();
     let total = async {
@@ -416,6 +743,7 @@ where
             skip: query.skip,
             limit: query.limit,
             total,
+            next_cursor: None,
         },
         Err(e) => {
             error!("Error reading from store: {e}");
@@ -425,3 +753,100 @@ where
 
     Ok(Json(ServerResponse::new("read", res)))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::body::HttpBody;
+
+    async fn import_item(item: i32) -> Result<Option<String>, IntegrationOSError> {
+        if item < 0 {
+            Err(ApplicationError::bad_request(
+                &format!("Item {item} is negative"),
+                None,
+            ))
+        } else {
+            Ok(Some(format!("item::{item}")))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_reports_every_failure_with_its_reason() {
+        let items = vec![1, -2, 3, -4, -5, 6];
+
+        let response = import_items(items.clone(), false, import_item).await;
+
+        assert_eq!(
+            response.summary,
+            ImportSummary {
+                total: 6,
+                succeeded: 3,
+                failed: 3,
+                skipped: 0,
+            }
+        );
+        let failures = response
+            .results
+            .iter()
+            .filter_map(|result| result.error.as_ref().map(|error| (result.index, error)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            failures.iter().map(|(index, _)| *index).collect::<Vec<_>>(),
+            [1, 3, 4]
+        );
+        for ((_, error), item) in failures.iter().zip([-2, -4, -5]) {
+            assert!(error.contains(&format!("Item {item} is negative")));
+        }
+        assert_eq!(response.results[5].id.as_deref(), Some("item::6"));
+        assert!(response
+            .results
+            .iter()
+            .all(|r| r.id.is_some() != r.error.is_some()));
+
+        let response = import_items(items, true, import_item).await;
+
+        assert_eq!(
+            response.summary,
+            ImportSummary {
+                total: 6,
+                succeeded: 1,
+                failed: 1,
+                skipped: 4,
+            }
+        );
+        assert_eq!(response.results.len(), 2);
+        assert_eq!(response.results[1].index, 1);
+    }
+
+    fn read_response(rows: usize) -> ServerResponse<ReadResponse<Value>> {
+        ServerResponse::new(
+            "read",
+            ReadResponse {
+                rows: (0..rows)
+                    .map(|i| serde_json::json!({ "id": i, "name": "record" }))
+                    .collect(),
+                total: rows as u64,
+                skip: 0,
+                limit: rows as u64,
+                next_cursor: Some("cursor".to_string()),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_small_reads_are_buffered_and_large_reads_streamed() {
+        // Bodies of known size are sent with their length, the other ones are chunked
+        let small = ReadJson::new(read_response(10), 1024).into_response();
+        assert!(small.body().size_hint().exact().is_some());
+
+        let large = ReadJson::new(read_response(1000), 1024).into_response();
+        assert!(large.body().size_hint().exact().is_none());
+        assert_eq!(large.headers()[CONTENT_TYPE], "application/json");
+
+        let body = axum::body::to_bytes(large.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let streamed: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(streamed, serde_json::to_value(read_response(1000)).unwrap());
+    }
+}
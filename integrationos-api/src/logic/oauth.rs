@@ -12,7 +12,8 @@ use integrationos_domain::{
     api_model_config::ContentType,
     connection_definition::ConnectionDefinition,
     connection_oauth_definition::{
-        Computation, ConnectionOAuthDefinition, OAuthResponse, PlatformSecret, Settings,
+        Computation, ConnectionOAuthDefinition, OAuthResponse, PkceChallenge, PlatformSecret,
+        Settings,
     },
     event_access::EventAccess,
     id::{prefix::IdPrefix, Id},
@@ -30,9 +31,12 @@ use std::{
     sync::Arc,
 };
 use tracing::{debug, error};
+use uuid::Uuid;
 
 pub fn get_router() -> Router<Arc<AppState>> {
-    Router::new().route("/:platform", post(oauth_handler))
+    Router::new()
+        .route("/:platform", post(oauth_handler))
+        .route("/:platform/authorize", post(authorize_handler))
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -47,6 +51,9 @@ struct OAuthRequest {
     label: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     payload: Option<Value>,
+    /// State of the authorize step, required by definitions using PKCE
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    state: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -56,6 +63,8 @@ struct OAuthPayload {
     client_id: String,
     client_secret: String,
     metadata: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code_verifier: Option<String>,
 }
 
 impl OAuthPayload {
@@ -64,6 +73,52 @@ impl OAuthPayload {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthorizeResponse {
+    /// To send as the state of the authorization request, and back on the token exchange
+    state: String,
+    #[serde(flatten)]
+    challenge: PkceChallenge,
+}
+
+/// Issues the PKCE code challenge of the authorization request of a platform. The verifier is
+/// kept under the returned state until the token exchange of the flow.
+async fn authorize_handler(
+    state: State<Arc<AppState>>,
+    Path(platform): Path<String>,
+) -> Result<Json<AuthorizeResponse>, IntegrationOSError> {
+    let conn_oauth_definition = get_conn_oauth_definition(&state, &platform).await?;
+    let method = conn_oauth_definition
+        .code_challenge_method()
+        .ok_or_else(|| {
+            ApplicationError::bad_request(
+                &format!("PKCE is not enabled for the OAuth definition of {platform}"),
+                None,
+            )
+        })?;
+
+    let challenge = PkceChallenge::new(method);
+    let oauth_state = Uuid::new_v4().simple().to_string();
+    state
+        .pkce_verifiers
+        .insert(
+            oauth_state.clone(),
+            challenge.code_verifier.clone(),
+            state.clock.now(),
+        )
+        .await
+        .map_err(|e| {
+            error!("Could not store PKCE verifier: {e}");
+            e
+        })?;
+
+    Ok(Json(AuthorizeResponse {
+        state: oauth_state,
+        challenge,
+    }))
+}
+
 async fn oauth_handler(
     state: State<Arc<AppState>>,
     Extension(user_event_access): Extension<Arc<EventAccess>>,
@@ -109,10 +164,32 @@ async fn oauth_handler(
         e
     })?;
 
+    let code_verifier = match conn_oauth_definition.code_challenge_method() {
+        Some(_) => {
+            let code_verifier = match payload.state.as_deref() {
+                Some(oauth_state) => {
+                    state
+                        .pkce_verifiers
+                        .take(oauth_state, state.clock.now())
+                        .await?
+                }
+                None => None,
+            };
+            Some(code_verifier.ok_or_else(|| {
+                ApplicationError::bad_request(
+                    "OAuth state is missing, expired or was already used",
+                    None,
+                )
+            })?)
+        }
+        None => None,
+    };
+
     let mut oauth_payload = OAuthPayload {
         metadata: payload.payload.clone().unwrap_or(Value::Null),
         client_id: payload.client_id,
         client_secret: secret.client_secret,
+        code_verifier,
     };
 
     if let Some(metadata) = oauth_payload.metadata.as_object_mut() {
@@ -207,6 +284,7 @@ async fn oauth_handler(
         paths: conn_definition.paths.clone(),
         ownership: user_event_access.ownership.clone(),
         throughput: Some(throughput),
+        scopes: None,
     }
     .as_event_access(&state.config)
     .map_err(|e| {
@@ -233,6 +311,7 @@ async fn oauth_handler(
         key: key.clone().into(),
         group: payload.group,
         environment: user_event_access.environment,
+        mode: Default::default(),
         platform: platform.into(),
         secrets_service_id: secret.id(),
         event_access_id: event_access.id,
@@ -254,7 +333,12 @@ async fn oauth_handler(
                     .unwrap_or_else(chrono::Utc::now)
                     .timestamp(),
             ),
+            scopes: Some(conn_oauth_definition.frontend.scopes()),
         }),
+        health: Default::default(),
+        webhook_secret: None,
+        credential_expiry: Default::default(),
+        credentials_rotated_at: None,
         record_metadata: Default::default(),
     };
 
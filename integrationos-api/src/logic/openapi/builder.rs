@@ -2,6 +2,7 @@ use convert_case::{Case, Casing};
 use indexmap::IndexMap;
 use integrationos_domain::{common_model::CommonModel, connection_model_definition::CrudAction};
 use openapiv3::*;
+use std::collections::HashSet;
 use strum::IntoEnumIterator;
 use tracing::debug;
 
@@ -21,15 +22,27 @@ const X_INTEGRATIONOS_CONNECTION_KEY: &str = "X-INTEGRATIONOS-CONNECTION-KEY";
 const X_INTEGRATIONOS_ENABLE_PASSTHROUGH: &str = "X-INTEGRATIONOS-ENABLE-PASSTHROUGH";
 const X_INTEGRATIONOS_PASSTHROUGH_FORWARD: &str = "X-INTEGRATIONOS-PASSTHROUGH-FORWARD";
 
-pub fn generate_path_item(common_model: &CommonModel) -> IndexMap<String, ReferenceOr<PathItem>> {
+/// Generates the paths of a common model, with only the operations of `actions`. Paths left
+/// without any operation are omitted.
+pub fn generate_path_item(
+    common_model: &CommonModel,
+    actions: &HashSet<CrudAction>,
+) -> IndexMap<String, ReferenceOr<PathItem>> {
     IndexMap::from_iter(
-        items(common_model)
-            .iter()
-            .map(|item| (item.path.to_string(), ReferenceOr::Item(item.item.clone())))
+        items(common_model, actions)
+            .into_iter()
+            .filter(|item| item.item.iter().next().is_some())
+            .map(|item| (item.path, ReferenceOr::Item(item.item)))
             .collect::<Vec<(String, ReferenceOr<PathItem>)>>(),
     )
 }
 
+pub fn all_actions() -> HashSet<CrudAction> {
+    CrudAction::iter()
+        .filter(|action| action != &CrudAction::Custom)
+        .collect()
+}
+
 pub fn generate_openapi_schema(
     path: Vec<IndexMap<String, ReferenceOr<PathItem>>>,
     schemas: IndexMap<String, ReferenceOr<Schema>>,
@@ -71,14 +84,20 @@ pub fn generate_openapi_schema(
     })
 }
 
-fn items(common_model: &CommonModel) -> [PathItemAction; 3] {
+fn items(common_model: &CommonModel, actions: &HashSet<CrudAction>) -> [PathItemAction; 3] {
+    let operation_for = |action: CrudAction| {
+        actions
+            .contains(&action)
+            .then(|| operation(&action, common_model))
+    };
+
     [
         PathItemAction {
             path: format!("/{}/{{id}}", common_model.name.to_case(Case::Kebab)),
             item: PathItem {
-                get: Some(operation(&CrudAction::GetOne, common_model)),
-                delete: Some(operation(&CrudAction::Delete, common_model)),
-                patch: Some(operation(&CrudAction::Update, common_model)),
+                get: operation_for(CrudAction::GetOne),
+                delete: operation_for(CrudAction::Delete),
+                patch: operation_for(CrudAction::Update),
                 parameters: header(),
                 ..Default::default()
             },
@@ -87,8 +106,8 @@ fn items(common_model: &CommonModel) -> [PathItemAction; 3] {
             path: format!("/{}", common_model.name.to_case(Case::Kebab)),
             item: PathItem {
                 description: Some(CrudAction::GetMany.description().into()),
-                get: Some(operation(&CrudAction::GetMany, common_model)),
-                post: Some(operation(&CrudAction::Create, common_model)),
+                get: operation_for(CrudAction::GetMany),
+                post: operation_for(CrudAction::Create),
                 parameters: header(),
                 ..Default::default()
             },
@@ -97,7 +116,7 @@ fn items(common_model: &CommonModel) -> [PathItemAction; 3] {
             path: format!("/{}/count", common_model.name.to_case(Case::Kebab)),
             item: PathItem {
                 description: Some(CrudAction::GetCount.description().into()),
-                get: Some(operation(&CrudAction::GetCount, common_model)),
+                get: operation_for(CrudAction::GetCount),
                 parameters: header(),
                 ..Default::default()
             },
@@ -1,22 +1,36 @@
 mod builder;
 
-use crate::server::AppState;
-use axum::extract::{Json, State};
-use bson::doc;
-use builder::{generate_openapi_schema, generate_path_item};
+use crate::{router::ServerResponse, server::AppState};
+use axum::{
+    extract::{Json, Path, Query, State},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use bson::{doc, Document};
+use builder::{all_actions, generate_openapi_schema, generate_path_item};
+use chrono::{DateTime, Utc};
 use convert_case::{Case, Casing};
 use futures::{Stream, StreamExt, TryStreamExt};
+use http::{
+    header::{ETAG, IF_NONE_MATCH, LAST_MODIFIED},
+    HeaderMap, HeaderValue, StatusCode,
+};
 use indexmap::IndexMap;
 use integrationos_domain::{
     algebra::{MongoStore, TimedExt},
     common_model::{CommonEnum, CommonModel},
-    IntegrationOSError, InternalError,
+    connection_model_definition::{ConnectionModelDefinition, CrudAction, CrudMapping},
+    event_access::EventAccess,
+    ApplicationError, Id, IntegrationOSError, InternalError, Operation, OperationKind,
+    OperationTracker,
 };
 use mongodb::error::Error as MongoError;
 use openapiv3::*;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::{
-    collections::HashSet,
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap, HashSet},
+    hash::{Hash, Hasher},
     pin::Pin,
     sync::{Arc, RwLock},
 };
@@ -26,9 +40,71 @@ use tracing::{debug, error, info};
 #[derive(Clone, Default, Debug)]
 pub struct OpenAPIData {
     state: Arc<RwLock<CachedSchema>>,
+    /// Schemas of the connection definitions, see [`get_connection_openapi`]
+    connections: Arc<RwLock<HashMap<Id, CachedConnectionSchema>>>,
+    /// Versions of the models of the last generated schema, kept across refreshes so that
+    /// the models changed by a generation can be told apart, see [`track_models`]
+    models: Arc<RwLock<BTreeMap<String, ModelVersion>>>,
+    model_filter: OpenApiModelFilter,
+    /// `info.version` of the generated schemas, the one of the builder when unset
+    version: Option<String>,
+}
+
+/// Common models the schema is generated for. Models left out are not read at all, the ones
+/// generated still bring the models and enums they reference along.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct OpenApiModelFilter {
+    /// Every model is allowed when unset
+    allowed: Option<BTreeSet<String>>,
+    denied: BTreeSet<String>,
+}
+
+impl OpenApiModelFilter {
+    /// Filter of the comma separated model names of `allowlist` and `denylist`
+    pub fn parse(allowlist: Option<&str>, denylist: Option<&str>) -> Self {
+        let names = |list: &str| {
+            list.split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(ToOwned::to_owned)
+                .collect::<BTreeSet<_>>()
+        };
+
+        Self {
+            allowed: allowlist.map(names),
+            denied: denylist.map(names).unwrap_or_default(),
+        }
+    }
+
+    /// Query of the primary common models passing the filter
+    fn query(&self) -> Document {
+        let mut names = Document::new();
+        if let Some(allowed) = &self.allowed {
+            names.insert("$in", allowed.iter().collect::<Vec<_>>());
+        }
+        if !self.denied.is_empty() {
+            names.insert("$nin", self.denied.iter().collect::<Vec<_>>());
+        }
+
+        let mut query = doc! { "primary": true };
+        if !names.is_empty() {
+            query.insert("name", names);
+        }
+        query
+    }
 }
 
 impl OpenAPIData {
+    pub fn with_model_filter(mut self, model_filter: OpenApiModelFilter) -> Self {
+        self.model_filter = model_filter;
+        self
+    }
+
+    pub fn with_version(mut self, version: String) -> Self {
+        self.version = Some(version);
+        self
+    }
+
     pub fn get(&self) -> Result<CachedSchema, anyhow::Error> {
         self.state.read().map(|state| state.clone()).map_err(|e| {
             anyhow::Error::msg(format!("Could not get openapi schema from cache: {e}"))
@@ -46,6 +122,56 @@ impl OpenAPIData {
         self.set(CachedSchema::default())
     }
 
+    /// Schema of a connection definition, if it was generated from the definitions identified
+    /// by `fingerprint`
+    fn get_connection(
+        &self,
+        connection_definition_id: &Id,
+        fingerprint: &[(Id, i64)],
+    ) -> Result<Option<Arc<OpenAPI>>, anyhow::Error> {
+        self.connections
+            .read()
+            .map(|connections| {
+                connections
+                    .get(connection_definition_id)
+                    .filter(|cached| cached.fingerprint == fingerprint)
+                    .map(|cached| cached.schema.clone())
+            })
+            .map_err(|e| {
+                anyhow::Error::msg(format!("Could not get connection schema from cache: {e}"))
+            })
+    }
+
+    fn set_connection(
+        &self,
+        connection_definition_id: Id,
+        value: CachedConnectionSchema,
+    ) -> Result<(), anyhow::Error> {
+        self.connections
+            .write()
+            .map(|mut connections| {
+                connections.insert(connection_definition_id, value);
+            })
+            .map_err(|e| {
+                anyhow::Error::msg(format!("Could not set connection schema in cache: {e}"))
+            })
+    }
+
+    fn get_models(&self) -> Result<BTreeMap<String, ModelVersion>, anyhow::Error> {
+        self.models
+            .read()
+            .map(|models| models.clone())
+            .map_err(|e| anyhow::Error::msg(format!("Could not get openapi models: {e}")))
+    }
+
+    fn set_models(&self, value: BTreeMap<String, ModelVersion>) -> Result<(), anyhow::Error> {
+        self.models
+            .write()
+            .map(|mut models| *models = value)
+            .map_err(|e| anyhow::Error::msg(format!("Could not set openapi models: {e}")))
+    }
+
+    /// Generates the schema of the common models passing the filter of the data
     pub fn spawn_openapi_generation(
         &self,
         cm_store: MongoStore<CommonModel>,
@@ -60,6 +186,76 @@ pub struct CachedSchema {
     schema: Vec<u8>,
     is_generating: bool,
     error: Option<String>,
+    /// Set once the schema is generated
+    version: Option<SchemaVersion>,
+}
+
+/// Version of a generated schema, returned in the `ETag` and `Last-Modified` of its responses
+/// so that clients can fetch it again only once it changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SchemaVersion {
+    /// Quoted hash of the serialized schema
+    etag: String,
+    /// Unix timestamp in milliseconds of the last change of its models
+    last_modified: i64,
+}
+
+impl SchemaVersion {
+    fn of(schema: &[u8], models: &BTreeMap<String, ModelVersion>, now: i64) -> Self {
+        let mut hasher = DefaultHasher::new();
+        schema.hash(&mut hasher);
+
+        Self {
+            etag: format!("\"{:x}\"", hasher.finish()),
+            last_modified: models
+                .values()
+                .map(|model| model.last_modified)
+                .max()
+                .unwrap_or(now),
+        }
+    }
+
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Ok(etag) = HeaderValue::from_str(&self.etag) {
+            headers.insert(ETAG, etag);
+        }
+        if let Some(last_modified) =
+            DateTime::from_timestamp_millis(self.last_modified).and_then(|at| {
+                HeaderValue::from_str(&at.format("%a, %d %b %Y %H:%M:%S GMT").to_string()).ok()
+            })
+        {
+            headers.insert(LAST_MODIFIED, last_modified);
+        }
+        headers
+    }
+
+    /// Whether the `If-None-Match` of `headers` holds the tag of the version, weakly or not
+    fn is_matched_by(&self, headers: &HeaderMap) -> bool {
+        headers
+            .get_all(IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .any(|etag| etag == "*" || etag.trim_start_matches("W/") == self.etag)
+    }
+}
+
+/// Content of a model of the schema, and the last time, as a Unix timestamp in milliseconds,
+/// it changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ModelVersion {
+    digest: u64,
+    last_modified: i64,
+}
+
+#[derive(Debug, Clone)]
+struct CachedConnectionSchema {
+    /// Ids and update times of the definitions the schema was generated from, so that the
+    /// schema is regenerated once any of them changes
+    fingerprint: Vec<(Id, i64)>,
+    schema: Arc<OpenAPI>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,63 +266,163 @@ pub enum OpenApiSchema {
     Error(String),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenApiChangesQuery {
+    /// Unix timestamp in milliseconds
+    pub since: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangedModel {
+    pub last_modified: i64,
+    pub schema: ReferenceOr<Schema>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenApiChanges {
+    pub since: i64,
+    pub models: BTreeMap<String, ChangedModel>,
+}
+
 struct PathWithSchema {
     path: IndexMap<String, ReferenceOr<PathItem>>,
     schema: IndexMap<String, ReferenceOr<Schema>>,
+    /// Update time of the common models each schema was generated from
+    updated_at: HashMap<String, i64>,
+    /// Schemas of `schema` generated from common enums
+    enums: HashSet<String>,
 }
 
 struct PathIter {
     paths: Vec<IndexMap<String, ReferenceOr<PathItem>>>,
     components: IndexMap<String, ReferenceOr<Schema>>,
+    updated_at: HashMap<String, i64>,
 }
 
 impl PathIter {
     /// Takes a list of paths and components, merges the components, collects
-    /// all the paths and returns a PathIter
+    /// all the paths and returns a PathIter. Enums no path or model refers to are dropped.
     fn from_paths(paths: Vec<PathWithSchema>) -> Self {
         let mut components = IndexMap::new();
+        let mut updated_at = HashMap::<String, i64>::new();
+        let mut enums = HashSet::new();
 
         for path in &paths {
             components.extend(path.schema.clone());
+            enums.extend(path.enums.iter().cloned());
+            for (name, time) in &path.updated_at {
+                let latest = updated_at.entry(name.clone()).or_insert(*time);
+                *latest = (*latest).max(*time);
+            }
         }
 
         let paths = paths
             .into_iter()
             .map(|path| path.path)
             .collect::<Vec<IndexMap<String, ReferenceOr<PathItem>>>>();
+        prune_unreferenced_enums(&paths, &mut components, &enums);
 
-        Self { paths, components }
+        Self {
+            paths,
+            components,
+            updated_at,
+        }
     }
 }
 
+/// Drops the `enums` of `components` which neither `paths` nor the other components refer to,
+/// e.g. enums of fields which declare their options inline
+fn prune_unreferenced_enums(
+    paths: &[IndexMap<String, ReferenceOr<PathItem>>],
+    components: &mut IndexMap<String, ReferenceOr<Schema>>,
+    enums: &HashSet<String>,
+) {
+    fn collect_references(value: &Value, references: &mut HashSet<String>) {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object {
+                    match value {
+                        Value::String(reference) if key == "$ref" => {
+                            references.insert(reference.clone());
+                        }
+                        value => collect_references(value, references),
+                    }
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    collect_references(value, references);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut references = HashSet::new();
+    for path in paths {
+        collect_references(
+            &serde_json::to_value(path).unwrap_or_default(),
+            &mut references,
+        );
+    }
+    for (name, schema) in components.iter() {
+        if !enums.contains(name) {
+            collect_references(
+                &serde_json::to_value(schema).unwrap_or_default(),
+                &mut references,
+            );
+        }
+    }
+
+    components.retain(|name, _| {
+        !enums.contains(name) || references.contains(&format!("#/components/schemas/{name}"))
+    });
+}
+
 type StreamResult = Pin<Box<dyn Stream<Item = Result<CommonModel, MongoError>> + Send>>;
 
 pub async fn refresh_openapi(
     state: State<Arc<AppState>>,
-) -> Result<Json<OpenApiSchema>, IntegrationOSError> {
+) -> Result<Json<ServerResponse<Operation>>, IntegrationOSError> {
     state.openapi_data.clone().clear().map_err(|e| {
         error!("Could not clear openapi schema from cache: {:?}", e);
         InternalError::io_err("Could not clear openapi schema", None)
     })?;
 
-    spawn_openapi_generation(
-        state.app_stores.common_model.clone(),
-        state.app_stores.common_enum.clone(),
-        state.openapi_data.clone(),
-    );
+    let cm_store = state.app_stores.common_model.clone();
+    let ce_store = state.app_stores.common_enum.clone();
+    let openapi_data = state.openapi_data.clone();
+
+    let operation = OperationTracker::new(state.app_stores.operations.clone())
+        .start(OperationKind::OpenApiGeneration, |_| async move {
+            spawn_openapi_generation(cm_store, ce_store, openapi_data.clone())
+                .await
+                .map_err(|e| InternalError::unknown(&e.to_string(), None))?
+                .map_err(|e| InternalError::unknown(&e.to_string(), None))?;
+
+            let schema = openapi_data
+                .get()
+                .map_err(|e| InternalError::io_err(&e.to_string(), None))?;
+
+            match schema.error {
+                Some(error) => Err(InternalError::unknown(&error, None)),
+                None => Ok(Value::Null),
+            }
+        })
+        .await?;
 
-    Ok(Json(OpenApiSchema::Accepted(
-        "OpenAPI schema is being regenerated".to_string(),
-    )))
+    Ok(Json(ServerResponse::new("operation", operation)))
 }
 
 pub async fn get_openapi_yaml(
     state: State<Arc<AppState>>,
 ) -> Result<Json<Vec<u8>>, IntegrationOSError> {
-    let spec = get_openapi(state).await;
+    let spec = read_openapi(&state);
 
     match spec {
-        Ok(Json(spec)) => {
+        Ok(spec) => {
             let try_yaml: serde_yaml::Value = serde_yaml::to_value(spec).map_err(|e| {
                 error!("Could not serialize openapi schema to yaml: {:?}", e);
 
@@ -145,20 +441,74 @@ pub async fn get_openapi_yaml(
     }
 }
 
+/// Models of the schema that changed after `since`, so that clients holding an earlier schema
+/// don't have to fetch the whole of it again. Paths are left out, they only change with the
+/// models they operate on.
+pub async fn get_openapi_changes(
+    state: State<Arc<AppState>>,
+    Query(query): Query<OpenApiChangesQuery>,
+) -> Result<Json<OpenApiChanges>, IntegrationOSError> {
+    let openapi = match read_openapi(&state)? {
+        OpenApiSchema::OpenAPI(openapi) => openapi,
+        OpenApiSchema::Accepted(message) | OpenApiSchema::Error(message) => {
+            return Err(ApplicationError::service_unavailable(&message, None))
+        }
+    };
+
+    let models = state.openapi_data.get_models().map_err(|e| {
+        error!("Could not get openapi models from cache: {:?}", e);
+
+        InternalError::io_err("Could not get openapi models", None)
+    })?;
+
+    Ok(Json(OpenApiChanges {
+        since: query.since,
+        models: changed_since(&openapi, &models, query.since),
+    }))
+}
+
+/// Schema of the common models, along with its `ETag` and `Last-Modified`. Requests whose
+/// `If-None-Match` holds the tag of the current schema are answered with 304 Not Modified.
 pub async fn get_openapi(
     state: State<Arc<AppState>>,
-) -> Result<Json<OpenApiSchema>, IntegrationOSError> {
-    let schema = state.openapi_data.get().map_err(|e| {
+    headers: HeaderMap,
+) -> Result<Response, IntegrationOSError> {
+    let schema = cached_openapi(&state)?;
+
+    match &schema.version {
+        Some(version) if version.is_matched_by(&headers) => {
+            Ok((StatusCode::NOT_MODIFIED, version.headers()).into_response())
+        }
+        Some(version) => {
+            let headers = version.headers();
+            Ok((headers, Json(parse_openapi(&state, schema)?)).into_response())
+        }
+        None => Ok(Json(parse_openapi(&state, schema)?).into_response()),
+    }
+}
+
+fn cached_openapi(state: &AppState) -> Result<CachedSchema, IntegrationOSError> {
+    state.openapi_data.get().map_err(|e| {
         error!("Could not get openapi schema from cache: {:?}", e);
 
         InternalError::io_err("Could not get openapi schema", None)
-    })?;
+    })
+}
+
+fn read_openapi(state: &AppState) -> Result<OpenApiSchema, IntegrationOSError> {
+    parse_openapi(state, cached_openapi(state)?)
+}
 
+/// Schema of `schema`, generating it again if its generation failed
+fn parse_openapi(
+    state: &AppState,
+    schema: CachedSchema,
+) -> Result<OpenApiSchema, IntegrationOSError> {
     if schema.is_generating {
         info!("OpenAPI schema is being generated");
-        return Ok(Json(OpenApiSchema::Accepted(
+        return Ok(OpenApiSchema::Accepted(
             "You're early, the schema is being generated".to_string(),
-        )));
+        ));
     }
 
     if let Some(error) = &schema.error {
@@ -180,7 +530,71 @@ pub async fn get_openapi(
         InternalError::io_err("Could not deserialize openapi schema", None)
     })?;
 
-    Ok(Json(OpenApiSchema::OpenAPI(openapi)))
+    Ok(OpenApiSchema::OpenAPI(openapi))
+}
+
+/// Versions of the models of a newly generated schema. Models keep their previous version
+/// unless their content changed, in which case they are modified at `now`. On the first
+/// generation there is nothing to compare with, models are then modified at the update time of
+/// the common models they were generated from.
+fn track_models(
+    previous: &BTreeMap<String, ModelVersion>,
+    components: &IndexMap<String, ReferenceOr<Schema>>,
+    updated_at: &HashMap<String, i64>,
+    now: i64,
+) -> BTreeMap<String, ModelVersion> {
+    components
+        .iter()
+        .map(|(name, schema)| {
+            let digest = digest(schema);
+            let last_modified = match previous.get(name) {
+                Some(version) if version.digest == digest => version.last_modified,
+                Some(_) => now,
+                None if previous.is_empty() => updated_at.get(name).copied().unwrap_or(now),
+                None => now,
+            };
+            (
+                name.clone(),
+                ModelVersion {
+                    digest,
+                    last_modified,
+                },
+            )
+        })
+        .collect()
+}
+
+fn digest(schema: &ReferenceOr<Schema>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_vec(schema)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Models of `openapi` modified after `since`
+fn changed_since(
+    openapi: &OpenAPI,
+    models: &BTreeMap<String, ModelVersion>,
+    since: i64,
+) -> BTreeMap<String, ChangedModel> {
+    openapi
+        .components
+        .iter()
+        .flat_map(|components| components.schemas.iter())
+        .filter_map(|(name, schema)| {
+            let version = models.get(name)?;
+            (version.last_modified > since).then(|| {
+                (
+                    name.clone(),
+                    ChangedModel {
+                        last_modified: version.last_modified,
+                        schema: schema.clone(),
+                    },
+                )
+            })
+        })
+        .collect()
 }
 
 fn spawn_openapi_generation(
@@ -191,7 +605,7 @@ fn spawn_openapi_generation(
     tokio::spawn(async move {
         let stream: StreamResult = cm_store
             .collection
-            .find(Some(doc! { "primary": true }), None)
+            .find(Some(state.model_filter.query()), None)
             .await
             .map_err(|e| {
                 error!("Could not fetch common model: {:?}", e);
@@ -203,6 +617,7 @@ fn spawn_openapi_generation(
             schema: Vec::new(),
             is_generating: true,
             error: None,
+            version: None,
         };
 
         info!("Setting openapi schema as generating in cache");
@@ -217,7 +632,7 @@ fn spawn_openapi_generation(
                 let ce_store = ce_store.clone();
                 match cm {
                     Ok(cm) => Some(
-                        generate_references_data(cm, cm_store, ce_store)
+                        generate_references_data(cm, &all_actions(), cm_store, ce_store)
                             .timed(|_, elapsed| {
                                 debug!("Common model processed in {:?}", elapsed);
                             })
@@ -238,7 +653,18 @@ fn spawn_openapi_generation(
             Ok(paths) => {
                 info!("Generating openapi schema");
                 let paths = PathIter::from_paths(paths);
-                let schema = generate_openapi_schema(paths.paths, paths.components);
+                let now = Utc::now().timestamp_millis();
+                let models = track_models(
+                    &state.get_models()?,
+                    &paths.components,
+                    &paths.updated_at,
+                    now,
+                );
+                state.set_models(models.clone())?;
+                let mut schema = generate_openapi_schema(paths.paths, paths.components);
+                if let Some(version) = &state.version {
+                    schema.info.version.clone_from(version);
+                }
 
                 info!("Deserializing openapi schema");
                 let schema = serde_json::to_vec(&schema).map_err(|e| {
@@ -254,6 +680,7 @@ fn spawn_openapi_generation(
                             error: Some(
                                 "Could not serialize openapi schema, retrying...".to_string(),
                             ),
+                            version: None,
                         })
                         .map_err(|e| {
                             error!("Could not set openapi schema in cache: {e}");
@@ -265,6 +692,7 @@ fn spawn_openapi_generation(
                 if let Ok(schema) = schema {
                     state
                         .set(CachedSchema {
+                            version: Some(SchemaVersion::of(&schema, &models, now)),
                             schema,
                             is_generating: false,
                             error: None,
@@ -283,6 +711,7 @@ fn spawn_openapi_generation(
                         schema: vec![],
                         is_generating: false,
                         error: Some(format!("Could not generate openapi schema: {err}")),
+                        version: None,
                     })
                     .map_err(|e| {
                         error!("Could not set openapi schema in cache: {e}");
@@ -295,13 +724,22 @@ fn spawn_openapi_generation(
 
 async fn generate_references_data(
     cm: CommonModel,
+    actions: &HashSet<CrudAction>,
     cm_store: MongoStore<CommonModel>,
     ce_store: MongoStore<CommonEnum>,
 ) -> Result<PathWithSchema, anyhow::Error> {
     let mut schema = IndexMap::new();
+    let mut updated_at = HashMap::new();
+    let mut enums = HashSet::new();
     let (child_cms, missing) = cm
         .fetch_all_children_common_models(cm_store.clone())
         .await?;
+    // Enums and missing children don't record when they change, they are given the latest
+    // update time of the models referencing them
+    let latest_update = child_cms
+        .values()
+        .map(|child_cm| child_cm.record_metadata.updated_at)
+        .fold(cm.record_metadata.updated_at, i64::max);
     // PERF: Use fetch_all_children_common_enums instead
     let mut enum_references = cm
         .get_enum_references()
@@ -320,6 +758,7 @@ async fn generate_references_data(
 
     // Add properties for children
     for (k, child_cm) in child_cms.into_iter() {
+        updated_at.insert(k.clone(), child_cm.record_metadata.updated_at);
         schema.insert(k, ReferenceOr::Item(child_cm.reference()));
         let references = child_cm
             .get_enum_references()
@@ -351,6 +790,8 @@ async fn generate_references_data(
         .await?;
 
     enum_references.into_iter().for_each(|ce| {
+        updated_at.insert(ce.name.clone(), latest_update);
+        enums.insert(ce.name.clone());
         schema.insert(
             ce.name.clone(),
             ReferenceOr::Item(Schema {
@@ -388,12 +829,387 @@ async fn generate_references_data(
                 ..Default::default()
             })),
         };
+        updated_at.insert(r#ref.clone(), latest_update);
         schema.insert(r#ref.clone(), ReferenceOr::Item(schema_item));
     }
 
     // Add properties for the common model itself
+    updated_at.insert(cm.name.clone(), cm.record_metadata.updated_at);
     schema.insert(cm.name.clone(), ReferenceOr::Item(cm.reference()));
 
-    let path = generate_path_item(&cm);
-    Ok(PathWithSchema { path, schema })
+    let path = generate_path_item(&cm, actions);
+    Ok(PathWithSchema {
+        path,
+        schema,
+        updated_at,
+        enums,
+    })
+}
+
+/// Serves the schema of the unified operations supported by the definition of a connection.
+/// The schema is generated on first request, and again whenever a definition is added,
+/// changed or removed.
+pub async fn get_connection_openapi(
+    Extension(access): Extension<Arc<EventAccess>>,
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<OpenAPI>, IntegrationOSError> {
+    let Some(connection) = state.app_stores.connection.get_one_by_id(&id).await? else {
+        return Err(ApplicationError::not_found(
+            &format!("Connection with id {id} not found"),
+            None,
+        ));
+    };
+
+    if connection.ownership != access.ownership || connection.environment != access.environment {
+        return Err(ApplicationError::forbidden(
+            "You do not have permission to view this connection",
+            None,
+        ));
+    }
+
+    let mut definitions = state
+        .app_stores
+        .model_config
+        .get_many(
+            Some(doc! {
+                "connectionDefinitionId": connection.connection_definition_id.to_string(),
+                "supported": true,
+                "deleted": false,
+            }),
+            None,
+            None,
+            None,
+            None,
+        )
+        .await?;
+    definitions.sort_by_key(|definition| definition.id);
+
+    let fingerprint = definitions
+        .iter()
+        .map(|definition| (definition.id, definition.record_metadata.updated_at))
+        .collect::<Vec<_>>();
+
+    let cached = state
+        .openapi_data
+        .get_connection(&connection.connection_definition_id, &fingerprint)
+        .map_err(|e| {
+            error!(
+                "Could not get connection openapi schema from cache: {:?}",
+                e
+            );
+            InternalError::io_err("Could not get connection openapi schema", None)
+        })?;
+    if let Some(schema) = cached {
+        return Ok(Json(schema.as_ref().clone()));
+    }
+
+    let schema = Arc::new(
+        generate_connection_openapi(
+            &connection.platform,
+            &definitions,
+            &state.app_stores.common_model,
+            &state.app_stores.common_enum,
+        )
+        .await
+        .map_err(|e| {
+            error!("Could not generate connection openapi schema: {e}");
+            InternalError::unknown("Could not generate connection openapi schema", None)
+        })?,
+    );
+
+    state
+        .openapi_data
+        .set_connection(
+            connection.connection_definition_id,
+            CachedConnectionSchema {
+                fingerprint,
+                schema: schema.clone(),
+            },
+        )
+        .map_err(|e| {
+            error!("Could not set connection openapi schema in cache: {:?}", e);
+            InternalError::io_err("Could not set connection openapi schema", None)
+        })?;
+
+    Ok(Json(schema.as_ref().clone()))
+}
+
+async fn generate_connection_openapi(
+    platform: &str,
+    definitions: &[ConnectionModelDefinition],
+    cm_store: &MongoStore<CommonModel>,
+    ce_store: &MongoStore<CommonEnum>,
+) -> Result<OpenAPI, anyhow::Error> {
+    let mut paths = Vec::new();
+    let mappings = definitions
+        .iter()
+        .filter_map(|definition| definition.mapping.as_ref());
+
+    for (name, actions) in supported_actions(mappings) {
+        let Some(cm) = cm_store
+            .get_one(doc! { "name": &name, "deleted": false })
+            .await?
+        else {
+            debug!("Common model {name} of platform {platform} not found, skipping");
+            continue;
+        };
+
+        paths.push(
+            generate_references_data(cm, &actions, cm_store.clone(), ce_store.clone()).await?,
+        );
+    }
+
+    let paths = PathIter::from_paths(paths);
+    let mut schema = generate_openapi_schema(paths.paths, paths.components);
+    schema.info.title = format!("{} {}", platform.to_case(Case::Title), schema.info.title);
+
+    Ok(*schema)
+}
+
+/// Groups the actions of the mappings by the common model they map to
+fn supported_actions<'a>(
+    mappings: impl IntoIterator<Item = &'a CrudMapping>,
+) -> BTreeMap<String, HashSet<CrudAction>> {
+    mappings
+        .into_iter()
+        .filter(|mapping| mapping.action != CrudAction::Custom)
+        .fold(BTreeMap::new(), |mut actions, mapping| {
+            actions
+                .entry(mapping.common_model_name.clone())
+                .or_default()
+                .insert(mapping.action.clone());
+            actions
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mapping(common_model_name: &str, action: CrudAction) -> CrudMapping {
+        CrudMapping {
+            action,
+            common_model_name: common_model_name.to_string(),
+            from_common_model: None,
+            to_common_model: None,
+        }
+    }
+
+    #[test]
+    fn test_connection_schema_only_holds_operations_of_its_definitions() {
+        let mappings = [
+            mapping("contacts", CrudAction::GetMany),
+            mapping("contacts", CrudAction::Create),
+            mapping("contacts", CrudAction::Custom),
+        ];
+        let actions = supported_actions(&mappings);
+        assert_eq!(actions.keys().collect::<Vec<_>>(), vec!["contacts"]);
+
+        let contacts = CommonModel {
+            name: "contacts".to_string(),
+            ..Default::default()
+        };
+        let deals = CommonModel {
+            name: "deals".to_string(),
+            ..Default::default()
+        };
+        let paths = actions
+            .iter()
+            .map(|(name, actions)| {
+                let cm = [&contacts, &deals]
+                    .into_iter()
+                    .find(|cm| &cm.name == name)
+                    .unwrap();
+                generate_path_item(cm, actions)
+            })
+            .collect();
+        let schema = generate_openapi_schema(paths, IndexMap::new());
+
+        let operations = schema
+            .paths
+            .iter()
+            .flat_map(|(path, item)| {
+                item.as_item()
+                    .unwrap()
+                    .iter()
+                    .map(move |(method, _)| format!("{method} {path}"))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(operations, vec!["get /contacts", "post /contacts"]);
+    }
+
+    fn component(title: &str) -> ReferenceOr<Schema> {
+        ReferenceOr::Item(Schema {
+            schema_data: SchemaData {
+                title: Some(title.to_string()),
+                ..Default::default()
+            },
+            schema_kind: SchemaKind::Type(Type::Object(Default::default())),
+        })
+    }
+
+    #[test]
+    fn test_models_keep_their_version_until_their_content_changes() {
+        let components = IndexMap::from([
+            ("Contacts".to_string(), component("Contacts")),
+            ("Deals".to_string(), component("Deals")),
+        ]);
+        let updated_at = HashMap::from([("Contacts".to_string(), 10), ("Deals".to_string(), 20)]);
+
+        // The first generation takes the update time of the common models
+        let first = track_models(&BTreeMap::new(), &components, &updated_at, 100);
+        assert_eq!(first["Contacts"].last_modified, 10);
+        assert_eq!(first["Deals"].last_modified, 20);
+
+        let components = IndexMap::from([
+            ("Contacts".to_string(), component("Contacts")),
+            ("Deals".to_string(), component("Changed deals")),
+            ("Leads".to_string(), component("Leads")),
+        ]);
+        let second = track_models(&first, &components, &updated_at, 200);
+        assert_eq!(second["Contacts"], first["Contacts"]);
+        assert_eq!(second["Deals"].last_modified, 200);
+        assert_eq!(second["Leads"].last_modified, 200);
+    }
+
+    #[test]
+    fn test_only_models_changed_after_the_timestamp_are_returned() {
+        let components = IndexMap::from([
+            ("Contacts".to_string(), component("Contacts")),
+            ("Deals".to_string(), component("Deals")),
+        ]);
+        let updated_at = HashMap::from([("Contacts".to_string(), 10), ("Deals".to_string(), 20)]);
+        let models = track_models(&BTreeMap::new(), &components, &updated_at, 100);
+        let openapi = generate_openapi_schema(vec![], components);
+
+        let changes = changed_since(&openapi, &models, 10);
+        assert_eq!(changes.keys().collect::<Vec<_>>(), vec!["Deals"]);
+        assert_eq!(changes["Deals"].last_modified, 20);
+        assert!(changed_since(&openapi, &models, 20).is_empty());
+        assert_eq!(changed_since(&openapi, &models, 0).len(), 2);
+    }
+
+    #[test]
+    fn test_model_filter_selects_the_primary_models_to_generate() {
+        assert_eq!(
+            OpenApiModelFilter::parse(None, None).query(),
+            doc! { "primary": true }
+        );
+        assert_eq!(
+            OpenApiModelFilter::parse(Some(" Contacts, Deals,"), Some("Deals")).query(),
+            doc! {
+                "primary": true,
+                "name": { "$in": ["Contacts", "Deals"], "$nin": ["Deals"] }
+            }
+        );
+        assert_eq!(
+            OpenApiModelFilter::parse(None, Some("Leads")).query(),
+            doc! { "primary": true, "name": { "$nin": ["Leads"] } }
+        );
+    }
+
+    #[test]
+    fn test_filtered_schema_only_holds_the_models_and_enums_it_refers_to() {
+        let filter = OpenApiModelFilter::parse(Some("Contacts,Deals"), None);
+        let models = ["Contacts", "Deals", "Leads"]
+            .into_iter()
+            .filter(|name| {
+                filter
+                    .allowed
+                    .as_ref()
+                    .is_some_and(|allowed| allowed.contains(*name))
+            })
+            .map(|name| CommonModel {
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        let mut contacts = Schema {
+            schema_data: Default::default(),
+            schema_kind: SchemaKind::Type(Type::Object(ObjectType {
+                properties: IndexMap::from([(
+                    "stage".to_string(),
+                    ReferenceOr::ref_("#/components/schemas/Stage"),
+                )]),
+                ..Default::default()
+            })),
+        };
+        contacts.schema_data.title = Some("Contacts".to_string());
+        let paths = models
+            .iter()
+            .map(|cm| {
+                let schema = match cm.name.as_str() {
+                    "Contacts" => IndexMap::from([
+                        ("Contacts".to_string(), ReferenceOr::Item(contacts.clone())),
+                        ("Stage".to_string(), component("Stage")),
+                        ("Color".to_string(), component("Color")),
+                    ]),
+                    _ => IndexMap::from([
+                        (cm.name.clone(), component(&cm.name)),
+                        ("Color".to_string(), component("Color")),
+                    ]),
+                };
+                PathWithSchema {
+                    path: generate_path_item(cm, &all_actions()),
+                    updated_at: schema.keys().map(|name| (name.clone(), 10)).collect(),
+                    enums: HashSet::from(["Stage".to_string(), "Color".to_string()]),
+                    schema,
+                }
+            })
+            .collect();
+
+        let PathIter {
+            paths, components, ..
+        } = PathIter::from_paths(paths);
+        let openapi = generate_openapi_schema(paths, components);
+        let schemas = openapi
+            .components
+            .as_ref()
+            .unwrap()
+            .schemas
+            .keys()
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            schemas,
+            BTreeSet::from([
+                &"Contacts".to_string(),
+                &"Deals".to_string(),
+                &"Stage".to_string()
+            ])
+        );
+        assert!(openapi
+            .paths
+            .paths
+            .keys()
+            .all(|path| !path.contains("leads")));
+    }
+
+    #[test]
+    fn test_schema_version_is_matched_by_its_tag() {
+        let models = BTreeMap::from([(
+            "Contacts".to_string(),
+            ModelVersion {
+                digest: 0,
+                last_modified: 1_700_000_000_000,
+            },
+        )]);
+        let version = SchemaVersion::of(b"{}", &models, 1_800_000_000_000);
+        assert_eq!(version, SchemaVersion::of(b"{}", &models, 0));
+        assert_ne!(version.etag, SchemaVersion::of(b"[]", &models, 0).etag);
+
+        let headers = version.headers();
+        assert_eq!(headers[ETAG], version.etag.as_str());
+        assert_eq!(headers[LAST_MODIFIED], "Tue, 14 Nov 2023 22:13:20 GMT");
+
+        let if_none_match = |value: &str| {
+            HeaderMap::from_iter([(IF_NONE_MATCH, HeaderValue::from_str(value).unwrap())])
+        };
+        assert!(version.is_matched_by(&if_none_match(&version.etag)));
+        assert!(version.is_matched_by(&if_none_match(&format!("\"other\", W/{}", version.etag))));
+        assert!(version.is_matched_by(&if_none_match("*")));
+        assert!(!version.is_matched_by(&if_none_match("\"other\"")));
+        assert!(!version.is_matched_by(&HeaderMap::new()));
+    }
 }
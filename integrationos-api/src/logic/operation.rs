@@ -0,0 +1,33 @@
+use crate::{router::ServerResponse, server::AppState};
+use axum::{
+    extract::{Path, State},
+    routing::get,
+    Json, Router,
+};
+use integrationos_domain::{
+    id::Id, ApplicationError, IntegrationOSError, Operation, OperationTracker,
+};
+use std::sync::Arc;
+use tracing::error;
+
+pub fn get_router() -> Router<Arc<AppState>> {
+    Router::new().route("/:id", get(get_operation))
+}
+
+pub async fn get_operation(
+    Path(id): Path<Id>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ServerResponse<Operation>>, IntegrationOSError> {
+    let operation = OperationTracker::new(state.app_stores.operations.clone())
+        .get(&id)
+        .await
+        .map_err(|e| {
+            error!("Error reading operation {id}: {e}");
+            e
+        })?
+        .ok_or_else(|| {
+            ApplicationError::not_found(&format!("Operation with id {id} not found"), None)
+        })?;
+
+    Ok(Json(ServerResponse::new("operation", operation)))
+}
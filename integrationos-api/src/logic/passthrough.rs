@@ -1,15 +1,18 @@
-use super::{get_connection, INTEGRATION_OS_PASSTHROUGH_HEADER};
-use crate::{metrics::Metric, server::AppState};
+use super::{
+    correlation_id, flag_stale_connection, get_connection, stamp_correlation_id,
+    INTEGRATION_OS_PASSTHROUGH_HEADER,
+};
+use crate::{helper::check_cost_budget, metrics::Metric, server::AppState};
 use axum::{
+    body::Body,
     extract::{Query, State},
     response::IntoResponse,
     routing::get,
     Extension, Router,
 };
-use http::{header::CONTENT_LENGTH, HeaderMap, HeaderName, Method, Uri};
-use hyper::body::Bytes;
+use http::{header::CONTENT_LENGTH, HeaderMap, HeaderName, Method, StatusCode, Uri};
 use integrationos_domain::{
-    ApplicationError, InternalError,
+    ApplicationError, TimedExt,
     {
         destination::{Action, Destination},
         event_access::EventAccess,
@@ -35,7 +38,7 @@ pub async fn passthrough_request(
     query_params: Option<Query<HashMap<String, String>>>,
     uri: Uri,
     method: Method,
-    body: Bytes,
+    body: Body,
 ) -> impl IntoResponse {
     let Some(connection_key_header) = headers.get(&state.config.headers.connection_header) else {
         return Err(ApplicationError::bad_request(
@@ -44,7 +47,7 @@ pub async fn passthrough_request(
         ));
     };
 
-    let connection = get_connection(
+    let (connection, stale) = get_connection(
         user_event_access.as_ref(),
         connection_key_header,
         &state.app_stores,
@@ -52,6 +55,10 @@ pub async fn passthrough_request(
     )
     .await?;
 
+    let cost = state.config.request_costs.passthrough();
+    check_cost_budget(&state, &connection.ownership.client_id, cost).await?;
+    state.connection_circuit_breaker.acquire(&connection)?;
+
     let destination = Destination {
         platform: connection.platform.clone(),
         action: Action::Passthrough {
@@ -63,18 +70,35 @@ pub async fn passthrough_request(
 
     let Query(query_params) = query_params.unwrap_or_default();
 
+    let correlation_id = correlation_id(&headers, &state.config.headers.correlation_id_header);
     headers.remove(&state.config.headers.auth_header);
     headers.remove(&state.config.headers.connection_header);
+    headers.remove(&state.config.headers.correlation_id_header);
 
     let model_execution_result = state
         .extractor_caller
-        .send_to_destination(
+        .send_to_destination_stream(
             Some(connection.clone()),
             &destination,
             headers,
             query_params,
-            Some(body.to_vec()),
+            body.into_data_stream(),
         )
+        .timed(|res, elapsed| {
+            let failed = res
+                .as_ref()
+                .map_or(true, |res| res.status().is_server_error());
+            state
+                .connection_call_stats
+                .record(&connection.id.to_string(), elapsed, failed);
+            state.connection_circuit_breaker.record(
+                &connection,
+                res.as_ref().map_or_else(
+                    |e| StatusCode::from(e).is_server_error(),
+                    |res| res.status().is_server_error(),
+                ),
+            );
+        })
         .await
         .map_err(|e| {
             error!("Failed to execute connection model definition in passthrough endpoint. ID: {}, Error: {}", connection.id, e);
@@ -100,21 +124,36 @@ pub async fn passthrough_request(
             }
         });
 
+    if stale {
+        flag_stale_connection(&mut headers, &state.config.headers.stale_connection_header);
+    }
+    stamp_correlation_id(
+        &mut headers,
+        &state.config.headers.correlation_id_header,
+        &correlation_id,
+    );
+
     let status = model_execution_result.status();
 
-    let metric = Metric::passthrough(connection);
+    let metric = Metric::passthrough(connection)
+        .with_cost(cost)
+        .with_correlation_id(correlation_id);
     if let Err(e) = state.metric_tx.send(metric).await {
         error!("Could not send metric to receiver: {e}");
     }
 
-    let bytes = model_execution_result.bytes().await.map_err(|e| {
-        error!(
-            "Error retrieving bytes from response in passthrough endpoint: {:?}",
-            e
-        );
+    let bytes = state
+        .extractor_caller
+        .read_response(model_execution_result)
+        .await
+        .map_err(|e| {
+            error!(
+                "Error retrieving bytes from response in passthrough endpoint: {:?}",
+                e
+            );
 
-        InternalError::script_error("Error retrieving bytes from response", None)
-    })?;
+            e
+        })?;
 
     Ok((status, headers, bytes))
 }
@@ -1,9 +1,17 @@
-use super::{create, delete, read, update, HookExt, PublicExt, RequestExt};
-use crate::server::{AppState, AppStores};
-use axum::{routing::post, Router};
+use super::{create, delete, read, update, HookExt, PublicExt, RequestExt, SuccessResponse};
+use crate::{
+    router::ServerResponse,
+    server::{AppState, AppStores},
+};
+use axum::{
+    extract::{Path, State},
+    routing::post,
+    Extension, Json, Router,
+};
 use bson::doc;
 use integrationos_domain::{
     algebra::MongoStore,
+    complexity::PipelineLimits,
     configuration::pipeline::PipelineConfig,
     destination::Destination,
     event_access::EventAccess,
@@ -12,25 +20,58 @@ use integrationos_domain::{
     record_metadata::RecordMetadata,
     signature::Signature,
     source::Source,
-    Pipeline,
+    ApplicationError, IntegrationOSError, Pipeline,
 };
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::sync::Arc;
 
 pub fn get_router() -> Router<Arc<AppState>> {
     Router::new()
         .route(
             "/:id",
-            post(update::<CreatePipelineRequest, Pipeline>)
-                .delete(delete::<CreatePipelineRequest, Pipeline>),
+            post(update_pipeline).delete(delete::<CreatePipelineRequest, Pipeline>),
         )
         .route(
             "/",
-            post(create::<CreatePipelineRequest, Pipeline>)
-                .get(read::<CreatePipelineRequest, Pipeline>),
+            post(create_pipeline).get(read::<CreatePipelineRequest, Pipeline>),
         )
 }
 
+/// Rejects pipelines too complex for the execution engine before they are saved
+fn check_complexity(
+    state: &AppState,
+    payload: &CreatePipelineRequest,
+) -> Result<(), IntegrationOSError> {
+    let limits = PipelineLimits {
+        max_stages: state.config.pipeline_max_stages,
+        max_depth: state.config.pipeline_max_depth,
+    };
+
+    limits
+        .check(&payload.middleware)
+        .map_err(|e| ApplicationError::bad_request(&e.to_string(), None))
+}
+
+async fn create_pipeline(
+    access: Option<Extension<Arc<EventAccess>>>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreatePipelineRequest>,
+) -> Result<Json<ServerResponse<Value>>, IntegrationOSError> {
+    check_complexity(&state, &payload)?;
+    create::<CreatePipelineRequest, Pipeline>(access, State(state), Json(payload)).await
+}
+
+async fn update_pipeline(
+    access: Option<Extension<Arc<EventAccess>>>,
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreatePipelineRequest>,
+) -> Result<Json<ServerResponse<SuccessResponse>>, IntegrationOSError> {
+    check_complexity(&state, &payload)?;
+    update::<CreatePipelineRequest, Pipeline>(access, Path(id), State(state), Json(payload)).await
+}
+
 #[derive(Serialize, Deserialize)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 pub struct CreatePipelineRequest {
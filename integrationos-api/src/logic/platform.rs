@@ -1,4 +1,4 @@
-use super::{create, delete, read, update, HookExt, PublicExt, RequestExt};
+use super::{create, delete, import, read, update, HookExt, PublicExt, RequestExt};
 use crate::server::{AppState, AppStores};
 use axum::{
     routing::{patch, post},
@@ -20,6 +20,7 @@ pub fn get_router() -> Router<Arc<AppState>> {
             "/",
             post(create::<CreateRequest, PlatformData>).get(read::<CreateRequest, PlatformData>),
         )
+        .route("/import", post(import::<CreateRequest, PlatformData>))
         .route(
             "/:id",
             patch(update::<CreateRequest, PlatformData>)
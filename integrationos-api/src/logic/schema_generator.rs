@@ -206,6 +206,7 @@ pub async fn get_common_models_projections(
         total: len as u64,
         skip: 0,
         limit: 0,
+        next_cursor: None,
     }))
 }
 
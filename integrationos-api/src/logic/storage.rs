@@ -0,0 +1,134 @@
+use crate::{router::ServerResponse, server::AppState};
+use axum::{
+    extract::{Path, State},
+    routing::{get, post},
+    Json, Router,
+};
+use bson::{doc, Bson, Document};
+use integrationos_domain::{ApplicationError, IntegrationOSError};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::error;
+
+pub fn get_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(get_storage_stats))
+        .route("/:collection/compact", post(compact_collection))
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionStats {
+    pub name: String,
+    pub count: i64,
+    /// Uncompressed size of the documents, in bytes
+    pub size: i64,
+    /// Size allocated on disk for the documents, in bytes
+    pub storage_size: i64,
+    pub total_index_size: i64,
+}
+
+impl CollectionStats {
+    fn from_coll_stats(name: String, stats: &Document) -> Self {
+        let number = |key: &str| match stats.get(key) {
+            Some(Bson::Int32(value)) => *value as i64,
+            Some(Bson::Int64(value)) => *value,
+            Some(Bson::Double(value)) => *value as i64,
+            _ => 0,
+        };
+
+        Self {
+            count: number("count"),
+            size: number("size"),
+            storage_size: number("storageSize"),
+            total_index_size: number("totalIndexSize"),
+            name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageStats {
+    pub collections: Vec<CollectionStats>,
+}
+
+pub async fn get_storage_stats(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ServerResponse<StorageStats>>, IntegrationOSError> {
+    let db = &state.app_stores.db;
+
+    let mut names = db.list_collection_names(None).await.map_err(|e| {
+        error!("Could not list collections: {e}");
+        e
+    })?;
+    names.sort();
+
+    let mut collections = Vec::with_capacity(names.len());
+    for name in names {
+        let stats = db
+            .run_command(doc! { "collStats": &name }, None)
+            .await
+            .map_err(|e| {
+                error!("Could not get stats of collection {name}: {e}");
+                e
+            })?;
+
+        collections.push(CollectionStats::from_coll_stats(name, &stats));
+    }
+
+    Ok(Json(ServerResponse::new(
+        "storage",
+        StorageStats { collections },
+    )))
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactResult {
+    pub name: String,
+    pub bytes_freed: i64,
+}
+
+/// Compacts a collection to release unused disk space. Compaction blocks some operations on
+/// the collection while it runs, so it is only triggered explicitly.
+pub async fn compact_collection(
+    Path(collection): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ServerResponse<CompactResult>>, IntegrationOSError> {
+    let db = &state.app_stores.db;
+
+    let exists = db
+        .list_collection_names(doc! { "name": &collection })
+        .await?
+        .contains(&collection);
+    if !exists {
+        return Err(ApplicationError::not_found(
+            &format!("Collection {collection} not found"),
+            None,
+        ));
+    }
+
+    let result = db
+        .run_command(doc! { "compact": &collection }, None)
+        .await
+        .map_err(|e| {
+            error!("Could not compact collection {collection}: {e}");
+            e
+        })?;
+
+    let bytes_freed = match result.get("bytesFreed") {
+        Some(Bson::Int32(value)) => *value as i64,
+        Some(Bson::Int64(value)) => *value,
+        Some(Bson::Double(value)) => *value as i64,
+        _ => 0,
+    };
+
+    Ok(Json(ServerResponse::new(
+        "compact",
+        CompactResult {
+            name: collection,
+            bytes_freed,
+        },
+    )))
+}
@@ -1,5 +1,13 @@
-use super::{get_connection, INTEGRATION_OS_PASSTHROUGH_HEADER};
-use crate::{config::Headers, metrics::Metric, server::AppState};
+use super::{
+    correlation_id, flag_stale_connection, get_connection, stamp_correlation_id,
+    INTEGRATION_OS_PASSTHROUGH_HEADER,
+};
+use crate::{
+    config::Headers,
+    helper::{check_cost_budget, send_event},
+    metrics::Metric,
+    server::AppState,
+};
 use axum::{
     extract::{Path, Query, State},
     response::{IntoResponse, Response},
@@ -8,11 +16,11 @@ use axum::{
 };
 use bson::doc;
 use convert_case::{Case, Casing};
-use http::{HeaderMap, HeaderName};
+use http::{HeaderMap, HeaderName, StatusCode};
 use integrationos_domain::{
     connection_model_definition::CrudAction, destination::Action,
     encrypted_access_key::EncryptedAccessKey, encrypted_data::PASSWORD_LENGTH,
-    event_access::EventAccess, AccessKey, ApplicationError, Event, InternalError,
+    event_access::EventAccess, AccessKey, ApplicationError, Event, InternalError, TimedExt,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -209,7 +217,7 @@ pub async fn process_request(
             None,
         ));
     };
-    let connection = get_connection(
+    let (connection, stale) = get_connection(
         access.as_ref(),
         connection_key_header,
         &state.app_stores,
@@ -230,6 +238,7 @@ pub async fn process_request(
         .unwrap_or_default();
 
     let access_key_header_value = headers.get(&state.config.headers.auth_header).cloned();
+    let correlation_id = correlation_id(&headers, &state.config.headers.correlation_id_header);
 
     remove_event_headers(&mut headers, &state.config.headers);
 
@@ -246,6 +255,10 @@ pub async fn process_request(
         connection.platform, connection.platform_version, model_name, action_name,
     );
 
+    let cost = state.config.request_costs.unified(&action);
+    check_cost_budget(&state, &connection.ownership.client_id, cost).await?;
+    state.connection_circuit_breaker.acquire(&connection)?;
+
     let mut response = state
         .extractor_caller
         .send_to_destination_unified(
@@ -257,6 +270,21 @@ pub async fn process_request(
             query_params,
             payload,
         )
+        .timed(|res, elapsed| {
+            let failed = res
+                .as_ref()
+                .map_or(true, |res| res.response.status().is_server_error());
+            state
+                .connection_call_stats
+                .record(&connection.id.to_string(), elapsed, failed);
+            state.connection_circuit_breaker.record(
+                &connection,
+                res.as_ref().map_or_else(
+                    |e| StatusCode::from(e).is_server_error(),
+                    |res| res.response.status().is_server_error(),
+                ),
+            );
+        })
         .await
         .map_err(|e| {
             error!(
@@ -278,6 +306,18 @@ pub async fn process_request(
         })
         .collect::<HeaderMap>();
 
+    if stale {
+        flag_stale_connection(
+            response.response.headers_mut(),
+            &state.config.headers.stale_connection_header,
+        );
+    }
+    stamp_correlation_id(
+        response.response.headers_mut(),
+        &state.config.headers.correlation_id_header,
+        &correlation_id,
+    );
+
     let (parts, body) = response.response.into_parts();
     let mut metadata = body.get(META).unwrap_or(&response.metadata).clone();
 
@@ -331,14 +371,20 @@ pub async fn process_request(
                 &name,
                 parts.headers.clone(),
                 body,
-            );
-            if let Err(e) = state.event_tx.send(event).await {
-                error!("Could not send event to receiver: {e}");
+            )
+            .with_connection_mode(connection.mode)
+            .with_correlation_id(&correlation_id);
+            if state.event_denylist.admits(&event) {
+                if let Err(e) = send_event(&state.event_tx, event) {
+                    error!("Could not send event to receiver: {e}");
+                }
             }
         }
     };
 
-    let metric = Metric::unified(connection.clone(), action);
+    let metric = Metric::unified(connection.clone(), action)
+        .with_cost(cost)
+        .with_correlation_id(correlation_id);
     if let Err(e) = state.metric_tx.send(metric).await {
         error!("Could not send metric to receiver: {e}");
     }
@@ -363,4 +409,5 @@ fn remove_event_headers(headers: &mut HeaderMap, headers_config: &Headers) {
     headers.remove(&headers_config.include_overflow_header);
     headers.remove(&headers_config.enable_passthrough_header);
     headers.remove(&headers_config.dynamic_platform_header);
+    headers.remove(&headers_config.correlation_id_header);
 }
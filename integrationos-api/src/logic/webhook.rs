@@ -0,0 +1,118 @@
+use crate::{
+    helper::send_event,
+    logic::{connection::connection_event, event_schema::validate_event_payload},
+    middleware::request_id::RequestId,
+    server::AppState,
+};
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    routing::post,
+    Extension, Json, Router,
+};
+use http::HeaderMap;
+use integrationos_domain::{
+    event_response::EventResponse, event_state::EventState, webhook::verify_webhook_signature,
+    ApplicationError, Connection, IntegrationOSError, InternalError,
+};
+use std::sync::Arc;
+use tracing::error;
+
+pub fn get_router() -> Router<Arc<AppState>> {
+    Router::new().route("/:id", post(receive_webhook))
+}
+
+/// Ingests a webhook sent by the platform of a connection as an event. The body must be
+/// signed with the webhook secret of the connection, anything else is rejected.
+pub async fn receive_webhook(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    request_id: Option<Extension<RequestId>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<EventResponse>, IntegrationOSError> {
+    let connection = match state.app_stores.connection.get_one_by_id(&id).await {
+        Ok(Some(connection)) if !connection.record_metadata.deleted => connection,
+        Ok(_) => {
+            return Err(ApplicationError::not_found(
+                &format!("Connection with id {id} not found"),
+                None,
+            ))
+        }
+        Err(e) => {
+            error!("Error fetching connection for webhook: {:?}", e);
+
+            return Err(e);
+        }
+    };
+
+    verify_webhook(&state, &connection, &headers, &body).await?;
+
+    let payload = String::from_utf8(body.to_vec())
+        .map_err(|_| ApplicationError::bad_request("Webhook body is not valid UTF-8", None))?;
+
+    let name = format!("{}::webhook-received", connection.platform);
+    let mut event = connection_event(&state, &connection, &name, headers, payload)?;
+    if let Some(Extension(RequestId(request_id))) = request_id {
+        event = event.with_correlation_id(request_id);
+    }
+    // Denylisted events are acknowledged as dropped, so that the platform doesn't retry them
+    if !state.event_denylist.admits(&event) {
+        return Ok(Json(EventResponse {
+            status: EventState::Dropped,
+            ..EventResponse::new(event)
+        }));
+    }
+    validate_event_payload(&state, &event).await?;
+
+    // A full buffer is answered with a 503, so that the platform retries the webhook later
+    send_event(&state.event_tx, event.clone()).map_err(|e| {
+        error!("Could not send event to receiver: {e}");
+        e
+    })?;
+
+    Ok(Json(EventResponse::new(event)))
+}
+
+async fn verify_webhook(
+    state: &AppState,
+    connection: &Connection,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(), IntegrationOSError> {
+    let Some(secret_id) = &connection.webhook_secret else {
+        return Err(ApplicationError::unauthorized(
+            "Connection does not accept webhooks",
+            None,
+        ));
+    };
+
+    let Some(signature) = headers
+        .get(&state.config.headers.webhook_signature_header)
+        .and_then(|signature| signature.to_str().ok())
+    else {
+        return Err(ApplicationError::unauthorized(
+            "Missing webhook signature",
+            None,
+        ));
+    };
+
+    let secret = state
+        .secrets_client
+        .get(secret_id, &connection.ownership.id)
+        .await
+        .map_err(|e| {
+            error!("Error fetching webhook secret for connection: {:?}", e);
+
+            e
+        })?
+        .as_value()?;
+    let Some(secret) = secret.as_str() else {
+        return Err(InternalError::invalid_argument(
+            "Webhook secret of connection is not a string",
+            None,
+        ));
+    };
+
+    verify_webhook_signature(secret, body, signature)
+}
@@ -1,12 +1,20 @@
 use anyhow::Result;
 use dotenvy::dotenv;
 use envconfig::Envconfig;
-use integrationos_api::{config::ConnectionsConfig, server::Server};
+use integrationos_api::{
+    config::ConnectionsConfig,
+    helper::{parse_address, ADDRESS_VAR},
+    server::Server,
+};
 use integrationos_domain::telemetry::{get_subscriber, init_subscriber};
 use tracing::info;
 
 fn main() -> Result<()> {
     dotenv().ok();
+    // The address fails to parse with a terse error otherwise
+    if let Ok(address) = std::env::var(ADDRESS_VAR) {
+        parse_address(&address)?;
+    }
     let config = ConnectionsConfig::init_from_env()?;
 
     let subscriber = get_subscriber("connections-api".into(), "info".into(), std::io::stdout);
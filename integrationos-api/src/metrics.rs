@@ -1,19 +1,43 @@
-use chrono::{DateTime, Datelike, Utc};
+use crate::helper::{
+    CONNECTION_CALLS_COUNTER, CONNECTION_LABEL, COST_KEY, PLATFORM_LABEL, TYPE_LABEL,
+};
+use bson::{doc, Bson, Document};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Utc};
+use futures::TryStreamExt;
 use http::HeaderValue;
 use integrationos_domain::{
     destination::Action, event_access::EventAccess, ownership::Ownership, Connection,
+    ConnectionMode,
+};
+use mongodb::{Collection, IndexModel};
+use segment::{
+    message::{Track, User},
+    AutoBatcher, Batcher, HttpClient,
 };
-use segment::message::{Track, User};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::sync::Arc;
+use tracing::{error, warn};
 
 pub const TOTAL_KEY: &str = "total";
 pub const DAILY_KEY: &str = "daily";
 pub const MONTHLY_KEY: &str = "monthly";
 pub const PLATFORMS_KEY: &str = "platforms";
+pub const MODES_KEY: &str = "modes";
 pub const CREATED_AT_KEY: &str = "createdAt";
 
+/// Keys of the daily and monthly buckets `date` falls in, bucket boundaries being the days of
+/// `timezone`
+pub fn bucket_keys(date: DateTime<Utc>, timezone: &FixedOffset) -> (String, String) {
+    let date = date.with_timezone(timezone);
+    let (year, month, day) = (date.year(), date.month(), date.day());
+
+    (
+        format!("{year}-{month:02}-{day:02}"),
+        format!("{year}-{month:02}"),
+    )
+}
+
 #[derive(Debug, Clone, strum::Display, Deserialize)]
 #[serde(rename_all = "lowercase")]
 #[strum(serialize_all = "lowercase")]
@@ -42,6 +66,25 @@ pub struct Metric {
     pub metric_type: MetricType,
     pub date: DateTime<Utc>,
     pub action: Option<Action>,
+    /// Cost charged to the budget of the client for this request
+    pub cost: u64,
+    /// Id shared with the events of the request, see [`MetricRecord`]
+    pub correlation_id: Option<String>,
+}
+
+/// Metric of a single request, kept besides the aggregated buckets when the request has a
+/// correlation id so that the metric can be looked up along with the events of the request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricRecord {
+    pub correlation_id: String,
+    pub client_id: String,
+    pub r#type: String,
+    pub platform: String,
+    pub common_model: Option<String>,
+    pub action: Option<String>,
+    pub cost: u64,
+    pub created_at: i64,
 }
 
 impl Metric {
@@ -50,6 +93,8 @@ impl Metric {
             metric_type: MetricType::Passthrough(connection),
             date: Utc::now(),
             action: None,
+            cost: 0,
+            correlation_id: None,
         }
     }
 
@@ -58,6 +103,8 @@ impl Metric {
             metric_type: MetricType::Unified(connection),
             date: Utc::now(),
             action: Some(action),
+            cost: 0,
+            correlation_id: None,
         }
     }
 
@@ -66,9 +113,38 @@ impl Metric {
             metric_type: MetricType::RateLimited(event_access, key),
             date: Utc::now(),
             action: None,
+            cost: 0,
+            correlation_id: None,
         }
     }
 
+    pub fn with_cost(mut self, cost: u64) -> Self {
+        self.cost = cost;
+        self
+    }
+
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    pub fn record(&self) -> Option<MetricRecord> {
+        Some(MetricRecord {
+            correlation_id: self.correlation_id.clone()?,
+            client_id: self.ownership().client_id.clone(),
+            r#type: self.metric_type.to_string(),
+            platform: self.platform().to_owned(),
+            common_model: self.action.as_ref().map(|a| a.name().to_string()),
+            action: self
+                .action
+                .as_ref()
+                .and_then(|a| a.action())
+                .map(ToString::to_string),
+            cost: self.cost,
+            created_at: self.date.timestamp_millis(),
+        })
+    }
+
     pub fn ownership(&self) -> &Ownership {
         use MetricType::*;
         match &self.metric_type {
@@ -78,6 +154,21 @@ impl Metric {
         }
     }
 
+    /// Counts the call to a platform the metric is of, rate limited requests made none
+    pub fn count_call(&self) {
+        use MetricType::*;
+        let (Passthrough(connection) | Unified(connection)) = &self.metric_type else {
+            return;
+        };
+        metrics::counter!(
+            CONNECTION_CALLS_COUNTER,
+            1,
+            PLATFORM_LABEL => connection.platform.to_string(),
+            CONNECTION_LABEL => connection.key.to_string(),
+            TYPE_LABEL => self.metric_type.to_string()
+        );
+    }
+
     fn platform(&self) -> &str {
         use MetricType::*;
         match &self.metric_type {
@@ -87,23 +178,55 @@ impl Metric {
         }
     }
 
-    pub fn update_doc(&self) -> bson::Document {
+    fn mode(&self) -> Option<ConnectionMode> {
+        use MetricType::*;
+        match &self.metric_type {
+            Passthrough(c) => Some(c.mode),
+            Unified(c) => Some(c.mode),
+            RateLimited(_, _) => None,
+        }
+    }
+
+    /// Update incrementing the buckets of the metric, which are the days and months of
+    /// `timezone`. The creation date is stored in UTC regardless.
+    pub fn update_doc(&self, timezone: &FixedOffset) -> bson::Document {
         let platform = self.platform();
         let metric_type = &self.metric_type;
-        let day = self.date.day();
-        let month = self.date.month();
-        let year = self.date.year();
-        let daily_key = format!("{year}-{month:02}-{day:02}");
-        let monthly_key = format!("{year}-{month:02}");
+        let (daily_key, monthly_key) = bucket_keys(self.date, timezone);
+
+        // Connection metrics are also counted per mode so sandbox traffic can be told apart
+        let mut prefixes = vec![metric_type.to_string()];
+        if let Some(mode) = self.mode() {
+            prefixes.push(format!("{metric_type}.{MODES_KEY}.{mode}"));
+        }
+
+        let mut inc = bson::Document::new();
+        for prefix in prefixes {
+            inc.insert(format!("{prefix}.{TOTAL_KEY}"), 1);
+            inc.insert(
+                format!("{prefix}.{PLATFORMS_KEY}.{platform}.{TOTAL_KEY}"),
+                1,
+            );
+            inc.insert(format!("{prefix}.{DAILY_KEY}.{daily_key}"), 1);
+            inc.insert(
+                format!("{prefix}.{PLATFORMS_KEY}.{platform}.{DAILY_KEY}.{daily_key}"),
+                1,
+            );
+            inc.insert(format!("{prefix}.{MONTHLY_KEY}.{monthly_key}"), 1);
+            inc.insert(
+                format!("{prefix}.{PLATFORMS_KEY}.{platform}.{MONTHLY_KEY}.{monthly_key}"),
+                1,
+            );
+        }
+
+        if self.cost > 0 {
+            let cost = self.cost as i64;
+            inc.insert(format!("{COST_KEY}.{TOTAL_KEY}"), cost);
+            inc.insert(format!("{COST_KEY}.{MONTHLY_KEY}.{monthly_key}"), cost);
+        }
+
         bson::doc! {
-            "$inc": {
-                format!("{metric_type}.{TOTAL_KEY}"): 1,
-                format!("{metric_type}.{PLATFORMS_KEY}.{platform}.{TOTAL_KEY}"): 1,
-                format!("{metric_type}.{DAILY_KEY}.{daily_key}"): 1,
-                format!("{metric_type}.{PLATFORMS_KEY}.{platform}.{DAILY_KEY}.{daily_key}"): 1,
-                format!("{metric_type}.{MONTHLY_KEY}.{monthly_key}"): 1,
-                format!("{metric_type}.{PLATFORMS_KEY}.{platform}.{MONTHLY_KEY}.{monthly_key}"): 1,
-            },
+            "$inc": inc,
             "$setOnInsert": {
                 CREATED_AT_KEY: self.date.timestamp_millis()
             }
@@ -126,6 +249,7 @@ impl Metric {
                 properties: json!({
                     "connectionDefinitionId": conn.id.to_string(),
                     "environment": conn.environment,
+                    "mode": conn.mode,
                     "key": &conn.key,
                     "platform": self.platform(),
                     "platformVersion": &conn.platform_version,
@@ -148,6 +272,7 @@ impl Metric {
                 properties: json!({
                     "connectionDefinitionId": conn.id.to_string(),
                     "environment": conn.environment,
+                    "mode": conn.mode,
                     "key": &conn.key,
                     "platform": self.platform(),
                     "platformVersion": &conn.platform_version,
@@ -177,3 +302,264 @@ impl Metric {
         }
     }
 }
+
+/// Tracks metrics in Segment. Without a write key nothing is built, neither the client nor the
+/// messages of the metrics.
+pub struct SegmentTracker {
+    batcher: Option<AutoBatcher>,
+}
+
+impl SegmentTracker {
+    pub fn new(write_key: Option<&str>) -> Self {
+        Self {
+            batcher: write_key.map(|key| {
+                AutoBatcher::new(HttpClient::default(), Batcher::new(None), key.to_string())
+            }),
+        }
+    }
+
+    fn message(&self, metric: &Metric) -> Option<Track> {
+        self.batcher.as_ref().map(|_| metric.segment_track())
+    }
+
+    pub async fn track(&mut self, metric: &Metric) {
+        let Some(msg) = self.message(metric) else {
+            return;
+        };
+        if let Some(batcher) = &mut self.batcher {
+            if let Err(e) = batcher.push(msg).await {
+                warn!("Tracking msg is too large: {e}");
+            }
+        }
+    }
+
+    pub async fn flush(&mut self) {
+        if let Some(batcher) = &mut self.batcher {
+            if let Err(e) = batcher.flush().await {
+                warn!("Tracking flush is too large: {e}");
+            }
+        }
+    }
+}
+
+/// Dotted paths of the daily buckets of `metric` older than `daily_cutoff` and of the monthly
+/// buckets older than `monthly_cutoff`, totals are never part of them
+pub fn expired_buckets(metric: &Document, daily_cutoff: &str, monthly_cutoff: &str) -> Vec<String> {
+    fn walk(
+        document: &Document,
+        path: &str,
+        daily_cutoff: &str,
+        monthly_cutoff: &str,
+        expired: &mut Vec<String>,
+    ) {
+        for (key, value) in document {
+            let Bson::Document(child) = value else {
+                continue;
+            };
+            let child_path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{path}.{key}")
+            };
+
+            let cutoff = match key.as_str() {
+                DAILY_KEY => Some(daily_cutoff),
+                MONTHLY_KEY => Some(monthly_cutoff),
+                _ => None,
+            };
+            match cutoff {
+                Some(cutoff) => expired.extend(
+                    child
+                        .keys()
+                        .filter(|bucket| bucket.as_str() < cutoff)
+                        .map(|bucket| format!("{child_path}.{bucket}")),
+                ),
+                None => walk(child, &child_path, daily_cutoff, monthly_cutoff, expired),
+            }
+        }
+    }
+
+    let mut expired = vec![];
+    walk(metric, "", daily_cutoff, monthly_cutoff, &mut expired);
+    expired
+}
+
+/// Removes the daily and monthly buckets older than `retention` from every metric document,
+/// buckets being in `timezone`. A monthly bucket is only removed once the whole month is past
+/// the retention.
+pub async fn prune_metrics(
+    metrics: &Collection<Document>,
+    retention: Duration,
+    now: DateTime<Utc>,
+    timezone: &FixedOffset,
+) -> Result<u64, mongodb::error::Error> {
+    let (daily_cutoff, monthly_cutoff) = bucket_keys(now - retention, timezone);
+
+    let mut pruned = 0;
+    let mut cursor = metrics.find(None, None).await?;
+    while let Some(metric) = cursor.try_next().await? {
+        let expired = expired_buckets(&metric, &daily_cutoff, &monthly_cutoff);
+        if expired.is_empty() {
+            continue;
+        }
+        let Some(id) = metric.get("_id") else {
+            continue;
+        };
+
+        let unset = expired
+            .into_iter()
+            .map(|path| (path, Bson::String(String::new())))
+            .collect::<Document>();
+        match metrics
+            .update_one(doc! { "_id": id }, doc! { "$unset": unset }, None)
+            .await
+        {
+            Ok(_) => pruned += 1,
+            Err(e) => error!("Could not prune metric buckets: {e}"),
+        }
+    }
+
+    Ok(pruned)
+}
+
+/// Indexes the metric records by the correlation id and client they are read by
+pub async fn index_metric_records(
+    records: &Collection<MetricRecord>,
+) -> Result<(), mongodb::error::Error> {
+    records
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "correlationId": 1, "clientId": 1 })
+                .build(),
+            None,
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Deletes the metric records created more than `retention` before `now`, returning how many
+/// were deleted
+pub async fn prune_metric_records(
+    records: &Collection<MetricRecord>,
+    retention: Duration,
+    now: DateTime<Utc>,
+) -> Result<u64, mongodb::error::Error> {
+    let cutoff = (now - retention).timestamp_millis();
+    let result = records
+        .delete_many(doc! { "createdAt": { "$lt": cutoff } }, None)
+        .await?;
+
+    Ok(result.deleted_count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+    use integrationos_domain::{
+        connection_definition::{ConnectionDefinitionType, Paths},
+        environment::Environment,
+        event_access::EventAccessScope,
+        id::{prefix::IdPrefix, Id},
+        record_metadata::RecordMetadata,
+    };
+
+    #[test]
+    fn test_segment_messages_are_only_built_with_write_key() {
+        let metric = Metric::rate_limited(
+            Arc::new(EventAccess {
+                id: Id::now(IdPrefix::EventAccess),
+                name: "name".to_string(),
+                key: "key".to_string(),
+                namespace: "default".to_string(),
+                platform: "stripe".to_string(),
+                r#type: ConnectionDefinitionType::Api,
+                group: "group".to_string(),
+                ownership: Ownership::new("client".to_string()),
+                paths: Paths::default(),
+                access_key: "access_key".to_string(),
+                environment: Environment::Test,
+                scopes: EventAccessScope::all(),
+                record_metadata: RecordMetadata::default(),
+                throughput: 1000,
+            }),
+            None,
+        );
+
+        assert!(SegmentTracker::new(None).message(&metric).is_none());
+        let msg = SegmentTracker::new(Some("write-key"))
+            .message(&metric)
+            .expect("Message should be built with a write key");
+        assert_eq!(msg.event, metric.metric_type.event_name());
+    }
+
+    #[test]
+    fn test_buckets_align_to_days_of_timezone() {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let paris = "+02:00".parse::<FixedOffset>().unwrap();
+        let new_york = "-05:00".parse::<FixedOffset>().unwrap();
+
+        // Late evening in UTC on the last day of the month
+        let date = Utc.with_ymd_and_hms(2024, 3, 31, 23, 30, 0).unwrap();
+        assert_eq!(
+            bucket_keys(date, &utc),
+            ("2024-03-31".to_string(), "2024-03".to_string())
+        );
+        assert_eq!(
+            bucket_keys(date, &paris),
+            ("2024-04-01".to_string(), "2024-04".to_string())
+        );
+
+        // Midnight in Paris and New York, the first instants of their April 1st
+        let paris_midnight = Utc.with_ymd_and_hms(2024, 3, 31, 22, 0, 0).unwrap();
+        assert_eq!(bucket_keys(paris_midnight, &paris).0, "2024-04-01");
+        assert_eq!(
+            bucket_keys(paris_midnight - Duration::seconds(1), &paris).0,
+            "2024-03-31"
+        );
+        let new_york_midnight = Utc.with_ymd_and_hms(2024, 4, 1, 5, 0, 0).unwrap();
+        assert_eq!(bucket_keys(new_york_midnight, &new_york).0, "2024-04-01");
+        assert_eq!(
+            bucket_keys(new_york_midnight - Duration::seconds(1), &new_york).0,
+            "2024-03-31"
+        );
+    }
+
+    #[test]
+    fn test_old_buckets_are_pruned_while_totals_remain() {
+        let metric = doc! {
+            "clientId": "client",
+            "unified": {
+                "total": 6,
+                "daily": { "2024-01-30": 1, "2024-02-01": 2, "2024-03-10": 3 },
+                "monthly": { "2024-01": 1, "2024-02": 2, "2024-03": 3 },
+                "platforms": {
+                    "stripe": {
+                        "total": 6,
+                        "daily": { "2024-01-30": 1, "2024-03-10": 5 },
+                        "monthly": { "2024-01": 1, "2024-03": 5 },
+                    },
+                },
+            },
+            "cost": { "total": 40, "monthly": { "2024-01": 10, "2024-03": 30 } },
+            "createdAt": 1706572800000_i64,
+        };
+
+        let mut expired = expired_buckets(&metric, "2024-02-01", "2024-02");
+        expired.sort();
+
+        assert_eq!(
+            expired,
+            [
+                "cost.monthly.2024-01",
+                "unified.daily.2024-01-30",
+                "unified.monthly.2024-01",
+                "unified.platforms.stripe.daily.2024-01-30",
+                "unified.platforms.stripe.monthly.2024-01",
+            ]
+        );
+        assert!(expired.iter().all(|path| !path.ends_with(TOTAL_KEY)));
+        assert!(expired_buckets(&metric, "2024-01-01", "2024-01").is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{metrics::Metric, server::AppState};
+use crate::{metrics::Metric, middleware::request_id::RequestId, server::AppState};
 use anyhow::{Context, Result};
 use axum::{
     body::Body,
@@ -108,13 +108,14 @@ pub async fn rate_limit(
         .await;
 
     if count >= throughput {
-        let _ = state
-            .metric_tx
-            .send(Metric::rate_limited(
-                event_access.clone(),
-                req.headers().get(&state.key_header_name).cloned(),
-            ))
-            .await;
+        let mut metric = Metric::rate_limited(
+            event_access.clone(),
+            req.headers().get(&state.key_header_name).cloned(),
+        );
+        if let Some(RequestId(request_id)) = req.extensions().get::<RequestId>() {
+            metric = metric.with_correlation_id(request_id);
+        }
+        let _ = state.metric_tx.send(metric).await;
         let mut res =
             ApplicationError::too_many_requests("Rate limit exceeded", None).into_response();
 
@@ -0,0 +1,123 @@
+use crate::helper::TokenBucket;
+use axum::{
+    body::Body,
+    extract::State,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http::{header::RETRY_AFTER, HeaderValue, Request};
+use integrationos_domain::{ApplicationError, Clock};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::warn;
+
+/// Single token bucket shared by every request of the instance, refilled at `limit` tokens
+/// per second. It is a safety valve on top of the limits of each tenant.
+pub struct GlobalRateLimiter {
+    bucket: Mutex<TokenBucket>,
+    limit: u64,
+    clock: Arc<dyn Clock>,
+}
+
+impl GlobalRateLimiter {
+    pub fn new(limit: u64, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            bucket: Mutex::new(TokenBucket::new(limit, Duration::from_secs(1), clock.now())),
+            limit,
+            clock,
+        }
+    }
+
+    /// Takes a token for a request, or returns how long to wait for the next one
+    pub fn acquire(&self) -> Result<(), Duration> {
+        let now = self.clock.now();
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if bucket.try_acquire(self.limit, now) {
+            Ok(())
+        } else {
+            Err(bucket.retry_after(self.limit, now))
+        }
+    }
+}
+
+pub async fn global_rate_limit(
+    State(limiter): State<Arc<GlobalRateLimiter>>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, Response> {
+    if let Err(retry_after) = limiter.acquire() {
+        warn!("Global rate limit exceeded, rejecting request");
+        let mut res =
+            ApplicationError::too_many_requests("Global rate limit exceeded", None).into_response();
+        // Retry-After is expressed in whole seconds, never advertise an immediate retry
+        let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        res.headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(secs));
+        return Err(res);
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{middleware::from_fn_with_state, routing::get, Router};
+    use http::StatusCode;
+    use integrationos_domain::MockClock;
+
+    #[tokio::test]
+    async fn test_aggregate_traffic_past_global_limit_is_rejected() {
+        let clock = MockClock::default();
+        let limiter = Arc::new(GlobalRateLimiter::new(3, Arc::new(clock.clone())));
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(from_fn_with_state(limiter, global_rate_limit));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind listener");
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        // Traffic of several tenants counts against the same limit
+        let client = reqwest::Client::new();
+        let mut statuses = vec![];
+        for tenant in ["a", "b", "c", "a", "b"] {
+            let res = client
+                .get(&url)
+                .header("x-tenant", tenant)
+                .send()
+                .await
+                .expect("Failed to send request");
+            statuses.push(res.status());
+            if res.status() == StatusCode::TOO_MANY_REQUESTS {
+                assert_eq!(res.headers()[RETRY_AFTER], "1");
+            }
+        }
+        assert_eq!(
+            statuses,
+            [
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::TOO_MANY_REQUESTS,
+            ]
+        );
+
+        clock.advance(chrono::Duration::seconds(1));
+        let res = client
+            .get(&url)
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
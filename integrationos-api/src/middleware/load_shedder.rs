@@ -0,0 +1,110 @@
+use axum::{body::Body, extract::State, middleware::Next, response::Response};
+use http::Request;
+use integrationos_domain::{ApplicationError, IntegrationOSError};
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::timeout,
+};
+use tracing::warn;
+
+/// Limits the number of requests handled concurrently. Requests above the limit wait in a
+/// bounded queue for up to `max_wait` and are only shed once the queue is full or the wait
+/// times out.
+#[derive(Debug, Clone)]
+pub struct RequestQueue {
+    permits: Arc<Semaphore>,
+    queue: Arc<Semaphore>,
+    max_wait: Duration,
+}
+
+impl RequestQueue {
+    pub fn new(max_concurrency: usize, max_queue_size: usize, max_wait: Duration) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_concurrency)),
+            queue: Arc::new(Semaphore::new(max_queue_size)),
+            max_wait,
+        }
+    }
+
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, IntegrationOSError> {
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+
+        let Ok(_slot) = self.queue.try_acquire() else {
+            warn!("Request queue is full, shedding request");
+            return Err(ApplicationError::service_unavailable(
+                "Server is overloaded, please try again later",
+                None,
+            ));
+        };
+
+        match timeout(self.max_wait, self.permits.clone().acquire_owned()).await {
+            Ok(Ok(permit)) => Ok(permit),
+            Ok(Err(_)) | Err(_) => {
+                warn!("Request waited too long in queue, shedding request");
+                Err(ApplicationError::service_unavailable(
+                    "Server is overloaded, please try again later",
+                    None,
+                ))
+            }
+        }
+    }
+}
+
+pub async fn load_shed(
+    State(queue): State<Arc<RequestQueue>>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, IntegrationOSError> {
+    let _permit = queue.acquire().await?;
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http::StatusCode;
+
+    #[tokio::test]
+    async fn test_short_burst_is_queued() {
+        let queue = RequestQueue::new(1, 2, Duration::from_millis(500));
+        let permit = queue.acquire().await.expect("Failed to acquire permit");
+
+        let waiting = (0..2)
+            .map(|_| {
+                let queue = queue.clone();
+                tokio::spawn(async move { queue.acquire().await.map(drop) })
+            })
+            .collect::<Vec<_>>();
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(permit);
+
+        for handle in waiting {
+            assert!(handle.await.expect("Task panicked").is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sustained_overload_is_shed() {
+        let queue = RequestQueue::new(1, 1, Duration::from_millis(50));
+        let _permit = queue.acquire().await.expect("Failed to acquire permit");
+
+        let waiting = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.acquire().await.map(drop) })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let full = queue.acquire().await.expect_err("Queue should be full");
+        assert_eq!(StatusCode::from(full), StatusCode::SERVICE_UNAVAILABLE);
+
+        let timed_out = waiting
+            .await
+            .expect("Task panicked")
+            .expect_err("Queued request should time out");
+        assert_eq!(StatusCode::from(timed_out), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
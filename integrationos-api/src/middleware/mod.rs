@@ -1,7 +1,13 @@
 pub mod blocker;
 pub mod extractor;
+pub mod global_rate_limit;
 pub mod header_auth;
 pub mod jwt_auth;
+pub mod load_shedder;
+pub mod request_id;
+pub mod request_metrics;
+pub mod route_concurrency;
+pub mod tenant_concurrency;
 
 pub use header_auth::header_auth;
 pub use jwt_auth::jwt_auth;
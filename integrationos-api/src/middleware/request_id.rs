@@ -0,0 +1,153 @@
+use axum::{body::Body, extract::State, middleware::Next, response::Response};
+use http::{header::CONTENT_LENGTH, HeaderName, HeaderValue, Request};
+use integrationos_domain::IntegrationOSError;
+use std::sync::Arc;
+use tracing::{info_span, Instrument};
+use uuid::Uuid;
+
+/// Header the request id is also read from and returned in, for callers that don't know of
+/// the correlation header
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Id of a request, the correlation id sent by the caller or a new one. Handlers read it from
+/// the extensions of the request, and the events and metrics of the request carry it as their
+/// correlation id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Gives every request an id, returned in the `header` and [`REQUEST_ID_HEADER`] of its
+/// response and in the body of the errors it fails with, so that callers can correlate a
+/// failure with the logs. The id is read from either header when the caller sends one.
+pub async fn request_id(
+    State(header): State<Arc<HeaderName>>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response {
+    let request_id = [header.as_str(), REQUEST_ID_HEADER]
+        .into_iter()
+        .find_map(|header| {
+            req.headers()
+                .get(header)
+                .and_then(|value| value.to_str().ok())
+                .filter(|value| !value.is_empty())
+        })
+        .map(ToOwned::to_owned)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let value = HeaderValue::from_str(&request_id).ok();
+
+    // Handlers stamping the correlation id of their response then reuse the request id
+    if let Some(value) = &value {
+        req.headers_mut()
+            .insert(header.as_ref().clone(), value.clone());
+    }
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let span = info_span!("request", request_id = %request_id);
+    let mut res = next.run(req).instrument(span).await;
+
+    if let Some(error) = res.extensions_mut().remove::<IntegrationOSError>() {
+        let (mut parts, _) = res.into_parts();
+        let (_, body) = error.response_for(Some(&request_id)).into_parts();
+        parts.headers.remove(CONTENT_LENGTH);
+        res = Response::from_parts(parts, body);
+    }
+    if let Some(value) = value {
+        res.headers_mut()
+            .entry(header.as_ref().clone())
+            .or_insert(value.clone());
+        res.headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{middleware::from_fn_with_state, routing::get, Extension, Router};
+    use http::StatusCode;
+    use integrationos_domain::ApplicationError;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    const HEADER: &str = "x-integrationos-correlation-id";
+
+    fn router() -> Router {
+        Router::new()
+            .route(
+                "/missing",
+                get(|Extension(id): Extension<RequestId>| async move {
+                    Err::<(), _>(ApplicationError::not_found(
+                        &format!("Nothing found for {}", id.0),
+                        None,
+                    ))
+                }),
+            )
+            .route("/found", get(|| async { "found" }))
+            .layer(from_fn_with_state(
+                Arc::new(HeaderName::from_static(HEADER)),
+                request_id,
+            ))
+    }
+
+    async fn send(uri: &str, request_id: Option<&str>) -> (StatusCode, String, Vec<u8>) {
+        send_with(uri, request_id.map(|request_id| (HEADER, request_id))).await
+    }
+
+    async fn send_with(
+        uri: &str,
+        request_id: Option<(&'static str, &str)>,
+    ) -> (StatusCode, String, Vec<u8>) {
+        let mut req = Request::builder().uri(uri);
+        if let Some((header, request_id)) = request_id {
+            req = req.header(header, request_id);
+        }
+        let res = router()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        let status = res.status();
+        let header = res.headers()[HEADER].to_str().unwrap().to_owned();
+        assert_eq!(res.headers()[REQUEST_ID_HEADER], header.as_str());
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, header, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_errors_carry_the_request_id() {
+        let (status, header, body) = send("/missing", Some("request")).await;
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(header, "request");
+        assert_eq!(body["key"], "err::application::not_found");
+        assert_eq!(body["message"], "Nothing found for request");
+        assert_eq!(body["requestId"], "request");
+
+        // A request id is made up for the requests sent without one
+        let (_, header, body) = send("/missing", None).await;
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert!(Uuid::parse_str(&header).is_ok());
+        assert_eq!(body["requestId"], header.as_str());
+
+        let (status, header, body) = send("/found", Some("request")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(header, "request");
+        assert_eq!(body, b"found");
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_read_from_either_header() {
+        let (_, header, body) = send_with("/missing", Some(("x-request-id", "request"))).await;
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(header, "request");
+        assert_eq!(body["requestId"], "request");
+
+        let (_, header, _) = send_with("/found", Some(("x-request-id", ""))).await;
+        assert!(Uuid::parse_str(&header).is_ok());
+    }
+}
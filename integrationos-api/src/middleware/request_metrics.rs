@@ -0,0 +1,31 @@
+use crate::helper::{
+    HTTP_REQUESTS_COUNTER, HTTP_REQUEST_DURATION_HISTOGRAM, METHOD_LABEL, STATUS_LABEL,
+};
+use axum::{body::Body, middleware::Next, response::Response};
+use http::Request;
+use std::time::Instant;
+
+/// Counts the requests served and records the time taken to serve them, rejected requests
+/// included
+pub async fn record_request_metrics(req: Request<Body>, next: Next) -> Response {
+    let method = req.method().to_string();
+    let started = Instant::now();
+
+    let res = next.run(req).await;
+
+    let status = res.status().as_u16().to_string();
+    metrics::histogram!(
+        HTTP_REQUEST_DURATION_HISTOGRAM,
+        started.elapsed().as_secs_f64(),
+        METHOD_LABEL => method.clone(),
+        STATUS_LABEL => status.clone()
+    );
+    metrics::counter!(
+        HTTP_REQUESTS_COUNTER,
+        1,
+        METHOD_LABEL => method,
+        STATUS_LABEL => status
+    );
+
+    res
+}
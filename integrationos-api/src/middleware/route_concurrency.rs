@@ -0,0 +1,194 @@
+use axum::{body::Body, extract::State, middleware::Next, response::Response};
+use http::Request;
+use integrationos_domain::{ApplicationError, IntegrationOSError};
+use std::{str::FromStr, sync::Arc};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::warn;
+
+/// Concurrency caps of expensive routes, parsed from a comma separated list of
+/// `pattern=limit` pairs such as `/v1/openapi=1,/v1/connection-definitions/:id/derive=4`.
+/// Segments of a pattern starting with `:` match any segment, a last segment `*` matches
+/// the rest of the path. Requests are capped by the first pattern their path matches.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouteConcurrencyLimits {
+    routes: Vec<(String, usize)>,
+}
+
+impl FromStr for RouteConcurrencyLimits {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let routes = s
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (pattern, limit) = pair.split_once('=').ok_or_else(|| {
+                    format!("Invalid route concurrency limit {pair}, expected pattern=limit")
+                })?;
+                let limit = limit
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|e| format!("Invalid route concurrency limit {pair}: {e}"))?;
+                let pattern = pattern.trim();
+                if !pattern.starts_with('/') {
+                    return Err(format!(
+                        "Invalid route concurrency limit {pair}, patterns start with /"
+                    ));
+                }
+
+                Ok((pattern.to_string(), limit))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { routes })
+    }
+}
+
+impl RouteConcurrencyLimits {
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+fn matches(pattern: &str, path: &str) -> bool {
+    let mut path = path.trim_matches('/').split('/');
+    for segment in pattern.trim_matches('/').split('/') {
+        if segment == "*" {
+            return true;
+        }
+        match path.next() {
+            Some(part) if segment.starts_with(':') && !part.is_empty() => {}
+            Some(part) if part == segment => {}
+            _ => return false,
+        }
+    }
+
+    path.next().is_none()
+}
+
+/// Limits the requests of each route of [`RouteConcurrencyLimits`] handled concurrently, so
+/// that an expensive route can't take the slots of the
+/// [`RequestQueue`](super::load_shedder::RequestQueue) shared by every route. Requests above
+/// the cap of their route are shed right away, other routes remain available.
+#[derive(Debug)]
+pub struct RouteConcurrencyLimiter {
+    routes: Vec<(String, Arc<Semaphore>)>,
+}
+
+impl RouteConcurrencyLimiter {
+    pub fn new(limits: &RouteConcurrencyLimits) -> Self {
+        Self {
+            routes: limits
+                .routes
+                .iter()
+                .map(|(pattern, limit)| (pattern.clone(), Arc::new(Semaphore::new(*limit))))
+                .collect(),
+        }
+    }
+
+    /// Permit of a request to `path`, none when its route isn't capped
+    pub fn acquire(&self, path: &str) -> Result<Option<OwnedSemaphorePermit>, IntegrationOSError> {
+        let Some((pattern, semaphore)) = self
+            .routes
+            .iter()
+            .find(|(pattern, _)| matches(pattern, path))
+        else {
+            return Ok(None);
+        };
+
+        semaphore
+            .clone()
+            .try_acquire_owned()
+            .map(Some)
+            .map_err(|_| {
+                warn!("Route {pattern} has too many requests in flight, shedding request");
+                ApplicationError::service_unavailable(
+                    "Endpoint is overloaded, please try again later",
+                    None,
+                )
+            })
+    }
+}
+
+pub async fn route_concurrency_limit(
+    State(limiter): State<Arc<RouteConcurrencyLimiter>>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, IntegrationOSError> {
+    let _permit = limiter.acquire(req.uri().path())?;
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{middleware::from_fn_with_state, routing::get, Router};
+    use http::StatusCode;
+    use tokio::sync::Notify;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_limits_are_parsed_from_patterns() {
+        let limits: RouteConcurrencyLimits =
+            " /v1/openapi=1, /v1/connections/:id/*=2 ,".parse().unwrap();
+        assert_eq!(
+            limits.routes,
+            [
+                ("/v1/openapi".to_string(), 1),
+                ("/v1/connections/:id/*".to_string(), 2)
+            ]
+        );
+        assert!(RouteConcurrencyLimits::from_str("").unwrap().is_empty());
+        assert!(RouteConcurrencyLimits::from_str("/v1/openapi").is_err());
+        assert!(RouteConcurrencyLimits::from_str("/v1/openapi=many").is_err());
+        assert!(RouteConcurrencyLimits::from_str("v1/openapi=1").is_err());
+
+        assert!(matches("/v1/openapi", "/v1/openapi/"));
+        assert!(!matches("/v1/openapi", "/v1/openapi/yaml"));
+        assert!(matches(
+            "/v1/connections/:id/*",
+            "/v1/connections/conn_1/oauth/refresh"
+        ));
+        assert!(!matches(
+            "/v1/connections/:id/test",
+            "/v1/connections//test"
+        ));
+        assert!(!matches("/v1/connections/:id", "/v1/connections"));
+    }
+
+    #[tokio::test]
+    async fn test_capped_route_sheds_excess_while_other_routes_are_served() {
+        let release = Arc::new(Notify::new());
+        let limits = "/slow/:id=1".parse().unwrap();
+        let router = Router::new()
+            .route(
+                "/slow/:id",
+                get({
+                    let release = release.clone();
+                    move || async move { release.notified().await }
+                }),
+            )
+            .route("/fast", get(|| async { "fast" }))
+            .layer(from_fn_with_state(
+                Arc::new(RouteConcurrencyLimiter::new(&limits)),
+                route_concurrency_limit,
+            ));
+        let send = |uri: &str| {
+            let router = router.clone();
+            let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            async move { router.oneshot(req).await.unwrap().status() }
+        };
+
+        let in_flight = tokio::spawn(send("/slow/1"));
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        assert_eq!(send("/slow/2").await, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(send("/fast").await, StatusCode::OK);
+
+        release.notify_one();
+        assert_eq!(in_flight.await.unwrap(), StatusCode::OK);
+        release.notify_one();
+        assert_eq!(send("/slow/2").await, StatusCode::OK);
+    }
+}
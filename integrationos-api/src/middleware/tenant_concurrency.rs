@@ -0,0 +1,90 @@
+use axum::{body::Body, extract::State, middleware::Next, response::Response, Extension};
+use http::Request;
+use integrationos_domain::{event_access::EventAccess, ApplicationError, IntegrationOSError};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::warn;
+
+/// Limits the number of requests of each client handled concurrently, so that a client can't
+/// take all the slots of the [`RequestQueue`](super::load_shedder::RequestQueue) shared by
+/// every client. Requests above the limit of their client are rejected right away.
+#[derive(Debug)]
+pub struct TenantConcurrencyLimiter {
+    semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
+    limit: usize,
+}
+
+impl TenantConcurrencyLimiter {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            semaphores: Mutex::new(HashMap::new()),
+            limit,
+        }
+    }
+
+    pub fn acquire(&self, client_id: &str) -> Result<OwnedSemaphorePermit, IntegrationOSError> {
+        let semaphore = {
+            let mut semaphores = self
+                .semaphores
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if !semaphores.contains_key(client_id) {
+                // Permits hold their semaphore, clients without requests in flight are dropped
+                semaphores.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+            }
+            semaphores
+                .entry(client_id.to_owned())
+                .or_insert_with(|| Arc::new(Semaphore::new(self.limit)))
+                .clone()
+        };
+
+        semaphore.try_acquire_owned().map_err(|_| {
+            warn!("Client {client_id} has too many requests in flight, rejecting request");
+            ApplicationError::too_many_requests("Too many concurrent requests", None)
+        })
+    }
+}
+
+pub async fn tenant_concurrency_limit(
+    Extension(event_access): Extension<Arc<EventAccess>>,
+    State(limiter): State<Arc<TenantConcurrencyLimiter>>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, IntegrationOSError> {
+    let _permit = limiter.acquire(&event_access.ownership.client_id)?;
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http::StatusCode;
+
+    #[test]
+    fn test_client_cap_does_not_affect_other_clients() {
+        let limiter = TenantConcurrencyLimiter::new(2);
+        let busy = (0..2)
+            .map(|_| limiter.acquire("busy").expect("Failed to acquire permit"))
+            .collect::<Vec<_>>();
+
+        let rejected = limiter
+            .acquire("busy")
+            .expect_err("Client should be over its limit");
+        assert_eq!(StatusCode::from(rejected), StatusCode::TOO_MANY_REQUESTS);
+
+        let quiet = (0..2)
+            .map(|_| {
+                limiter
+                    .acquire("quiet")
+                    .expect("Other client should be admitted")
+            })
+            .collect::<Vec<_>>();
+
+        drop(busy);
+        assert!(limiter.acquire("busy").is_ok());
+        drop(quiet);
+    }
+}
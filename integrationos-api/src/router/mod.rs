@@ -2,16 +2,30 @@ pub mod public;
 pub mod secured_jwt;
 pub mod secured_key;
 
-use crate::server::AppState;
+use crate::{
+    logic::health,
+    middleware::{
+        global_rate_limit::{global_rate_limit, GlobalRateLimiter},
+        load_shedder::{load_shed, RequestQueue},
+        request_id::request_id,
+        request_metrics::record_request_metrics,
+        route_concurrency::{route_concurrency_limit, RouteConcurrencyLimiter},
+    },
+    server::AppState,
+};
 use axum::{
-    body::Body, extract::Request, middleware::Next, response::IntoResponse, routing::get, Json,
-    Router,
+    body::Body,
+    extract::Request,
+    middleware::{from_fn, from_fn_with_state, Next},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
 };
-use http::StatusCode;
+use http::{HeaderName, StatusCode};
 use integrationos_domain::TimedExt;
 use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
 use serde_json::{json, Value};
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tower_http::cors::CorsLayer;
 
 #[derive(Deserialize, Debug)]
@@ -76,13 +90,59 @@ where
 pub async fn get_router(state: &Arc<AppState>) -> Router<Arc<AppState>> {
     let path = format!("/{}", state.config.api_version);
     let public_path = format!("{path}/public");
-    Router::new()
+    let router = Router::new()
         .nest(&public_path, public::get_router(state))
         .nest(&path, secured_key::get_router(state).await)
         .nest(&path, secured_jwt::get_router(state).await)
         .route("/", get(get_root))
-        .fallback(not_found_handler)
-        .layer(CorsLayer::permissive())
+        .fallback(not_found_handler);
+
+    let router = match state.config.max_concurrent_requests {
+        Some(max_concurrent_requests) => router.layer(from_fn_with_state(
+            Arc::new(RequestQueue::new(
+                max_concurrent_requests,
+                state.config.request_queue_size,
+                Duration::from_millis(state.config.request_queue_timeout_millis),
+            )),
+            load_shed,
+        )),
+        None => router,
+    };
+
+    // Requests over the cap of their route are shed before they take a slot in the queue
+    let router = if state.config.route_concurrency_limits.is_empty() {
+        router
+    } else {
+        router.layer(from_fn_with_state(
+            Arc::new(RouteConcurrencyLimiter::new(
+                &state.config.route_concurrency_limits,
+            )),
+            route_concurrency_limit,
+        ))
+    };
+
+    // Requests over the global limit are rejected before they take a slot in the queue
+    let router = match state.config.global_rate_limit {
+        Some(limit) => router.layer(from_fn_with_state(
+            Arc::new(GlobalRateLimiter::new(limit, state.clock.clone())),
+            global_rate_limit,
+        )),
+        None => router,
+    };
+
+    // Probes are added last, so that they are neither shed, rate limited nor metered
+    let router = router
+        .layer(from_fn(record_request_metrics))
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz));
+
+    // Wraps every route, so that the errors of the middlewares carry the request id as well
+    let router = match HeaderName::try_from(state.config.headers.correlation_id_header.as_str()) {
+        Ok(header) => router.layer(from_fn_with_state(Arc::new(header), request_id)),
+        Err(_) => router,
+    };
+
+    router.layer(CorsLayer::permissive())
 }
 
 pub async fn get_root() -> impl IntoResponse {
@@ -5,7 +5,7 @@ use crate::{
         connection_definition::{self, GetPublicConnectionDetailsRequest},
         connection_model_schema, connection_oauth_definition,
         event_access::create_event_access_for_new_user,
-        openapi, read, schema_generator, utils,
+        openapi, read, schema_generator, utils, webhook,
     },
     middleware::jwt_auth::{self, JwtState},
     server::AppState,
@@ -17,7 +17,7 @@ use axum::{
 };
 use integrationos_domain::{
     common_model::{CommonEnum, CommonModel},
-    connection_definition::{ConnectionDefinition, PublicConnectionDetails},
+    connection_definition::PublicConnectionDetails,
 };
 use std::sync::Arc;
 use tower_http::trace::TraceLayer;
@@ -33,7 +33,7 @@ pub fn get_router(state: &Arc<AppState>) -> Router<Arc<AppState>> {
         )
         .route(
             "/connection-definitions",
-            get(read::<connection_definition::CreateRequest, ConnectionDefinition>),
+            get(connection_definition::read_supported),
         )
         .nest("/schemas", schema_generator::get_router())
         .route(
@@ -45,6 +45,7 @@ pub fn get_router(state: &Arc<AppState>) -> Router<Arc<AppState>> {
         )
         .route("/openapi", get(openapi::get_openapi))
         .route("/openapi/yaml", get(openapi::get_openapi_yaml))
+        .route("/openapi/changes", get(openapi::get_openapi_changes))
         .route(
             "/connection-data/models/:platform_name",
             get(connection_model_schema::get_platform_models),
@@ -70,6 +71,7 @@ pub fn get_router(state: &Arc<AppState>) -> Router<Arc<AppState>> {
             get(read::<GetPublicConnectionDetailsRequest, PublicConnectionDetails>),
         )
         .route("/generate-id/:prefix", get(utils::generate_id))
+        .nest("/webhooks", webhook::get_router())
         .layer(from_fn(log_request_middleware))
         .layer(TraceLayer::new_for_http())
 }
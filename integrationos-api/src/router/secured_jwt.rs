@@ -1,15 +1,16 @@
 use crate::{
     logic::{
-        common_model, connection_definition,
+        cache, common_model, connection, connection_definition,
         connection_model_definition::{self},
-        connection_model_schema, connection_oauth_definition, openapi, platform, platform_page,
+        connection_model_schema, connection_oauth_definition, credential_rotation, events, openapi,
+        operation, platform, platform_page, storage,
     },
     middleware::jwt_auth::{self, JwtState},
     server::AppState,
 };
 use axum::{
     middleware::{from_fn, from_fn_with_state},
-    routing::post,
+    routing::{get, post},
     Router,
 };
 use std::sync::Arc;
@@ -32,12 +33,29 @@ pub async fn get_router(state: &Arc<AppState>) -> Router<Arc<AppState>> {
             connection_model_definition::get_router(),
         )
         .route("/openapi", post(openapi::refresh_openapi))
+        .route("/cache/invalidate", post(cache::invalidate_cache))
+        .nest("/operations", operation::get_router())
+        .route(
+            "/connections/validate",
+            post(connection::validate_all_connections),
+        )
+        .route(
+            "/connections/rotate-credentials",
+            post(credential_rotation::rotate_credentials),
+        )
+        .route("/events/backfill", post(events::backfill_events))
+        .route("/events/flush", post(events::flush_event_buffer))
+        .route(
+            "/events/denylist",
+            get(events::get_event_denylist).put(events::update_event_denylist),
+        )
         .nest(
             "/connection-model-schemas",
             connection_model_schema::get_router(),
         )
         .nest("/platforms", platform::get_router())
         .nest("/platform-pages", platform_page::get_router())
+        .nest("/storage", storage::get_router())
         .nest("/common-models", common_model::get_router());
 
     routes
@@ -6,13 +6,14 @@ use crate::{
         connection_model_schema::{
             public_get_connection_model_schema, PublicGetConnectionModelSchema,
         },
-        event_access, events, metrics, oauth, passthrough, pipeline, secrets, transactions,
-        unified,
+        correlation, event_access, event_schema, events, metrics, oauth, passthrough, pipeline,
+        secrets, transactions, unified,
     },
     middleware::{
         blocker::{handle_blocked_error, BlockInvalidHeaders},
         extractor::{rate_limit, RateLimiter},
         header_auth,
+        tenant_concurrency::{tenant_concurrency_limit, TenantConcurrencyLimiter},
     },
     server::AppState,
 };
@@ -26,16 +27,31 @@ use http::HeaderName;
 use integrationos_domain::connection_model_schema::PublicConnectionModelSchema;
 use std::{iter::once, sync::Arc};
 use tower::{filter::FilterLayer, ServiceBuilder};
-use tower_http::{sensitive_headers::SetSensitiveRequestHeadersLayer, trace::TraceLayer};
+use tower_http::{
+    limit::RequestBodyLimitLayer, sensitive_headers::SetSensitiveRequestHeadersLayer,
+    trace::TraceLayer,
+};
 use tracing::warn;
 
 pub async fn get_router(state: &Arc<AppState>) -> Router<Arc<AppState>> {
     let routes = Router::new()
         .nest("/connections", connection::get_router())
+        .nest("/correlations", correlation::get_router())
         .nest("/event-access", event_access::get_router())
+        .nest("/event-schemas", event_schema::get_router())
         .nest("/events", events::get_router())
         .nest("/oauth", oauth::get_router())
-        .nest("/passthrough", passthrough::get_router())
+        .nest(
+            "/passthrough",
+            // Bodies are streamed to the platforms of the streaming model definitions, and
+            // buffered up to the smaller limit of the unified destination for the others
+            passthrough::get_router().layer(RequestBodyLimitLayer::new(
+                state
+                    .config
+                    .passthrough_max_streaming_body_bytes
+                    .max(state.config.passthrough_max_body_bytes),
+            )),
+        )
         .nest("/pipelines", pipeline::get_router())
         .nest("/secrets", secrets::get_router())
         .nest("/transactions", transactions::get_router())
@@ -65,6 +81,14 @@ pub async fn get_router(state: &Arc<AppState>) -> Router<Arc<AppState>> {
         }
     };
 
+    let routes = match state.config.max_concurrent_requests_per_client {
+        Some(limit) => routes.layer(from_fn_with_state(
+            Arc::new(TenantConcurrencyLimiter::new(limit)),
+            tenant_concurrency_limit,
+        )),
+        None => routes,
+    };
+
     routes
         .layer(from_fn_with_state(state.clone(), header_auth::header_auth))
         .layer(from_fn(log_request_middleware))
@@ -1,39 +1,69 @@
 use crate::{
     config::ConnectionsConfig,
-    logic::{connection_oauth_definition::FrontendOauthConnectionDefinition, openapi::OpenAPIData},
-    metrics::Metric,
+    helper::{
+        bind_listener, buffer_and_flush, buffer_and_flush_by_key, encrypt_events, flush_trigger,
+        insert_in_batches, insert_or_dead_letter, prometheus_handle, report_dead_letters,
+        run_warmup, sample_channel_depth, sweep_dead_letters, warm_up, ConnectionCallStats,
+        ConnectionCircuitBreaker, DeadLetterMonitor, EventDenylist, FlushTrigger, InsertFailure,
+        InsertRetryPolicy, KeySequencer, PkceVerifiers, WarmupStatus,
+    },
+    logic::{
+        connection::notify_expiring_credentials,
+        connection_oauth_definition::FrontendOauthConnectionDefinition,
+        event_schema::index_event_schemas,
+        metrics::get_prometheus_metrics,
+        openapi::{OpenAPIData, OpenApiModelFilter},
+    },
+    metrics::{
+        index_metric_records, prune_metric_records, prune_metrics, Metric, MetricRecord,
+        SegmentTracker,
+    },
     router,
 };
 use anyhow::{anyhow, Context, Result};
-use axum::Router;
+use axum::{routing::get, Router};
+use futures::{future::join_all, FutureExt};
+use http::HeaderName;
 use integrationos_cache::local::{
     connection_cache::ConnectionCacheArcStrHeaderKey,
     connection_definition_cache::ConnectionDefinitionCache,
     connection_oauth_definition_cache::ConnectionOAuthDefinitionCache,
-    event_access_cache::EventAccessCache,
+    event_access_cache::EventAccessCache, event_schema_cache::EventSchemaCache,
 };
 use integrationos_domain::{
     algebra::{DefaultTemplate, MongoStore},
     common_model::{CommonEnum, CommonModel},
-    connection_definition::{ConnectionDefinition, PublicConnectionDetails},
+    connection_definition::{ConnectionDefinition, DownstreamRetryPolicy, PublicConnectionDetails},
     connection_model_definition::ConnectionModelDefinition,
     connection_model_schema::{ConnectionModelSchema, PublicConnectionModelSchema},
     connection_oauth_definition::{ConnectionOAuthDefinition, Settings},
     cursor::Cursor,
     event_access::EventAccess,
+    event_schema::EventSchema,
+    field_encryption::FieldEncryption,
     page::PlatformPage,
     secret::Secret,
-    secrets::SecretServiceProvider,
+    secrets_client,
     stage::Stage,
     user::UserClient,
-    Connection, Event, GoogleKms, IOSKms, Pipeline, PlatformData, SecretExt, Store, Transaction,
+    Clock, Connection, Event, IOSCrypto, Operation, Pipeline, PlatformData, SecretExt, Store,
+    SystemClock, Transaction,
 };
-use integrationos_unified::unified::{UnifiedCacheTTLs, UnifiedDestination};
-use mongodb::{options::UpdateOptions, Client, Database};
-use segment::{AutoBatcher, Batcher, HttpClient};
-use std::{sync::Arc, time::Duration};
-use tokio::{net::TcpListener, sync::mpsc::Sender, time::timeout, try_join};
-use tracing::{error, info, trace, warn};
+use integrationos_unified::unified::UnifiedDestination;
+use metrics_exporter_prometheus::PrometheusHandle;
+use mongodb::{options::InsertManyOptions, Client, Database};
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    net::TcpListener,
+    sync::{mpsc::Sender, watch},
+    task::JoinHandle,
+    time::timeout,
+};
+use tracing::{error, info, trace};
 
 #[derive(Clone)]
 pub struct AppStores {
@@ -54,11 +84,14 @@ pub struct AppStores {
     pub pipeline: MongoStore<Pipeline>,
     pub event_access: MongoStore<EventAccess>,
     pub event: MongoStore<Event>,
+    pub event_schema: MongoStore<EventSchema>,
     pub secrets: MongoStore<Secret>,
     pub transactions: MongoStore<Transaction>,
     pub cursors: MongoStore<Cursor>,
     pub stages: MongoStore<Stage>,
     pub clients: MongoStore<UserClient>,
+    pub operations: MongoStore<Operation>,
+    pub metric_records: MongoStore<MetricRecord>,
 }
 
 #[derive(Clone)]
@@ -68,19 +101,34 @@ pub struct AppState {
     pub openapi_data: OpenAPIData,
     pub http_client: reqwest::Client,
     pub event_access_cache: EventAccessCache,
+    pub event_schemas_cache: EventSchemaCache,
     pub connections_cache: ConnectionCacheArcStrHeaderKey,
     pub connection_definitions_cache: ConnectionDefinitionCache,
     pub connection_oauth_definitions_cache: ConnectionOAuthDefinitionCache,
     pub secrets_client: Arc<dyn SecretExt + Sync + Send>,
     pub extractor_caller: UnifiedDestination,
+    pub connection_call_stats: ConnectionCallStats,
+    pub connection_circuit_breaker: ConnectionCircuitBreaker,
+    pub pkce_verifiers: PkceVerifiers,
+    pub warmup: WarmupStatus,
+    pub clock: Arc<dyn Clock>,
+    pub event_field_encryption: Option<FieldEncryption>,
+    pub event_denylist: EventDenylist,
     pub event_tx: Sender<Event>,
+    /// Flushes the events buffered for saving on request
+    pub event_buffer_flush: FlushTrigger,
     pub metric_tx: Sender<Metric>,
+    /// Renders the metrics recorded through the `metrics` facade, served on `GET /metrics`
+    pub prometheus: PrometheusHandle,
     pub template: DefaultTemplate,
 }
 
 #[derive(Clone)]
 pub struct Server {
     state: Arc<AppState>,
+    shutdown: Arc<watch::Sender<bool>>,
+    /// Tasks saving buffered events and metrics, awaited on shutdown
+    buffers: Arc<Mutex<Vec<JoinHandle<()>>>>,
 }
 
 impl Server {
@@ -111,37 +159,75 @@ impl Server {
         let pipeline = MongoStore::new(&db, &Store::Pipelines).await?;
         let event_access = MongoStore::new(&db, &Store::EventAccess).await?;
         let event = MongoStore::new(&db, &Store::Events).await?;
+        let event_schema = MongoStore::new(&db, &Store::EventSchemas).await?;
+        index_event_schemas(&event_schema.collection)
+            .await
+            .with_context(|| "Could not index event schemas")?;
         let transactions = MongoStore::new(&db, &Store::Transactions).await?;
         let cursors = MongoStore::new(&db, &Store::Cursors).await?;
         let stages = MongoStore::new(&db, &Store::Stages).await?;
         let clients = MongoStore::new(&db, &Store::Clients).await?;
+        let operations = MongoStore::new(&db, &Store::Operations).await?;
+        let metric_records = MongoStore::new(&db, &Store::MetricRecords).await?;
+        index_metric_records(&metric_records.collection)
+            .await
+            .with_context(|| "Could not index metric records")?;
         let secrets_store = MongoStore::<Secret>::new(&db, &Store::Secrets).await?;
 
-        let secrets_client: Arc<dyn SecretExt + Sync + Send> = match config.secrets_config.provider
-        {
-            SecretServiceProvider::GoogleKms => {
-                Arc::new(GoogleKms::new(&config.secrets_config, secrets_store).await?)
-            }
-            SecretServiceProvider::IosKms => {
-                Arc::new(IOSKms::new(&config.secrets_config, secrets_store).await?)
-            }
+        let secrets_client = secrets_client(&config.secrets_config, secrets_store).await?;
+
+        let event_field_encryption = match &config.encrypted_event_fields {
+            Some(paths) => Some(FieldEncryption::new(
+                FieldEncryption::parse_paths(paths),
+                Arc::new(IOSCrypto::new(config.secrets_config.clone())?),
+            )),
+            None => None,
         };
 
-        let extractor_caller = UnifiedDestination::new(
+        let mut extractor_caller = UnifiedDestination::new(
             config.db_config.clone(),
             config.cache_size,
             secrets_client.clone(),
-            UnifiedCacheTTLs {
-                connection_cache_ttl_secs: config.connection_cache_ttl_secs,
-                connection_model_schema_cache_ttl_secs: config
-                    .connection_model_schema_cache_ttl_secs,
-                connection_model_definition_cache_ttl_secs: config
-                    .connection_model_definition_cache_ttl_secs,
-                secret_cache_ttl_secs: config.secret_cache_ttl_secs,
-            },
+            config.cache_ttls(),
+            config.rate_limit(),
         )
         .await
-        .with_context(|| "Could not initialize extractor caller")?;
+        .with_context(|| "Could not initialize extractor caller")?
+        .with_max_response_size(config.max_downstream_response_bytes)
+        .with_max_request_size(config.passthrough_max_body_bytes)
+        .with_connection_pool(
+            Duration::from_secs(config.downstream_pool_idle_timeout_secs),
+            config.downstream_pool_max_idle_per_host,
+        )
+        .with_context(|| "Could not configure downstream connection pool")?
+        .with_retry_policy(DownstreamRetryPolicy {
+            max_attempts: config.unified_retry_max_attempts,
+            initial_backoff_millis: config.unified_retry_initial_backoff_millis,
+            retryable_status_codes: config
+                .unified_retry_status_codes
+                .split(',')
+                .map(|code| code.trim().parse())
+                .collect::<Result<Vec<u16>, _>>()
+                .with_context(|| "Invalid status in UNIFIED_RETRY_STATUS_CODES")?,
+            ..Default::default()
+        })
+        .with_oauth_refresh_skew(Duration::from_secs(config.oauth_refresh_skew_secs))
+        .with_max_retry_after(Duration::from_secs(config.unified_max_retry_after_secs));
+
+        if let Some(forwarded_headers) = &config.unified_forwarded_headers {
+            let forwarded_headers = forwarded_headers
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(HeaderName::from_str)
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| "Invalid header in UNIFIED_FORWARDED_HEADERS")?;
+            extractor_caller = extractor_caller.with_forwarded_headers(forwarded_headers);
+        }
+        if let Some(call_timeout) = config.unified_call_timeout_millis {
+            extractor_caller =
+                extractor_caller.with_call_timeout(Duration::from_millis(call_timeout));
+        }
 
         let app_stores = AppStores {
             db: db.clone(),
@@ -162,18 +248,30 @@ impl Server {
             pipeline,
             event_access,
             event,
+            event_schema,
             transactions,
             cursors,
             stages,
             clients,
+            operations,
+            metric_records,
         };
 
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
         let event_access_cache =
             EventAccessCache::new(config.cache_size, config.access_key_cache_ttl_secs);
+        let event_schemas_cache =
+            EventSchemaCache::new(config.cache_size, config.event_schema_cache_ttl_secs);
         let connections_cache = ConnectionCacheArcStrHeaderKey::create(
             config.cache_size,
             config.connection_cache_ttl_secs,
-        );
+        )
+        .with_stale_fallback(config.connection_stale_fallback_enabled)
+        .with_clock(clock.clone());
+        let connections_cache = match config.connection_cache_tenant_capacity {
+            Some(capacity) => connections_cache.with_buildable_id_partitions(capacity),
+            None => connections_cache,
+        };
         let connection_definitions_cache = ConnectionDefinitionCache::new(
             config.cache_size,
             config.connection_definition_cache_ttl_secs,
@@ -182,134 +280,333 @@ impl Server {
             config.cache_size,
             config.connection_oauth_definition_cache_ttl_secs,
         );
-        let openapi_data = OpenAPIData::default();
+        let connection_call_stats = ConnectionCallStats::new(config.connection_call_stats_window);
+        let connection_circuit_breaker = ConnectionCircuitBreaker::new(
+            config.circuit_breaker_failure_threshold,
+            Duration::from_secs(config.circuit_breaker_window_secs),
+            Duration::from_secs(config.circuit_breaker_cool_down_secs),
+            clock.clone(),
+        );
+        let pkce_verifiers = PkceVerifiers::new(
+            &db,
+            chrono::Duration::seconds(config.oauth_pkce_verifier_ttl_secs),
+        )
+        .await
+        .with_context(|| "Could not index PKCE verifiers")?;
+        let openapi_data = OpenAPIData::default()
+            .with_model_filter(OpenApiModelFilter::parse(
+                config.openapi_model_allowlist.as_deref(),
+                config.openapi_model_denylist.as_deref(),
+            ))
+            .with_version(config.openapi_version.clone());
+        let warmup = WarmupStatus::new(&config.warmups);
+        let event_denylist = config
+            .event_denylist
+            .as_deref()
+            .map(EventDenylist::parse)
+            .unwrap_or_default();
+        let prometheus = prometheus_handle()?;
         openapi_data.spawn_openapi_generation(
             app_stores.common_model.clone(),
             app_stores.common_enum.clone(),
         );
 
         // Create Event buffer in separate thread and batch saves
-        let events = db.collection::<Event>(&Store::Events.to_string());
-        let (event_tx, mut receiver) =
+        let db_events = db.clone();
+        let event_routing = config.dedicated_event_tenants.clone();
+        let event_partition_key = config.db_config.event_partition_key;
+        let event_processors = config.event_processors.clone();
+        let event_ordering = config.event_ordering;
+        let event_sequencer = KeySequencer::default();
+        let save_field_encryption = event_field_encryption.clone();
+        let event_retry_policy = InsertRetryPolicy {
+            max_retries: config.event_save_max_retries,
+            base_delay: Duration::from_millis(config.event_save_retry_base_delay_millis),
+        };
+        let dead_letter_events = db.collection::<Event>(&Store::DeadLetterEvents.to_string());
+        let (event_tx, receiver) =
             tokio::sync::mpsc::channel::<Event>(config.event_save_buffer_size);
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let (event_buffer_flush, event_flush_requests) = flush_trigger();
+        let save_events = move |to_save: Vec<Event>| {
+            let to_save = to_save
+                .into_iter()
+                .filter_map(|event| event_processors.apply(event))
+                .map(|event| event.with_partition_key(event_partition_key))
+                .collect::<Vec<_>>();
+            trace!("Saving {} events", to_save.len());
+            let mut saves = vec![];
+            for (collection, to_save) in event_routing.group(to_save) {
+                for (reference, to_save) in event_ordering.split(to_save) {
+                    let events = db_events.collection::<Event>(&collection);
+                    let dead_letter_events = dead_letter_events.clone();
+                    let collection = collection.clone();
+                    let field_encryption = save_field_encryption.clone();
+                    let save = async move {
+                        // Fields are encrypted before the events are either stored or dead
+                        // lettered
+                        let to_save = match &field_encryption {
+                            Some(field_encryption) => {
+                                encrypt_events(field_encryption, to_save).await
+                            }
+                            None => to_save,
+                        };
+                        // Unordered so that the events following a failing one are still
+                        // inserted, and only the failing ones are retried
+                        let options = InsertManyOptions::builder().ordered(false).build();
+                        if let Err(e) = insert_in_batches(
+                            to_save,
+                            config.event_save_max_batch_size,
+                            config.event_save_max_batch_bytes,
+                            |batch| {
+                                insert_or_dead_letter(
+                                    batch,
+                                    event_retry_policy,
+                                    |docs| async {
+                                        events
+                                            .insert_many(docs, options.clone())
+                                            .await
+                                            .map(|_| ())
+                                            .map_err(InsertFailure::from)
+                                    },
+                                    |docs| async {
+                                        dead_letter_events
+                                            .insert_many(docs, options.clone())
+                                            .await
+                                            .map(|_| ())
+                                            .map_err(InsertFailure::from)
+                                    },
+                                )
+                            },
+                        )
+                        .await
+                        {
+                            error!(
+                                    "Could not save buffer of events in {collection} nor in the dead letter store: {}",
+                                    e.message
+                                );
+                        }
+                    };
+                    saves.push(match reference {
+                        Some(reference) => event_sequencer.schedule(reference, save),
+                        None => save.boxed(),
+                    });
+                }
+            }
+            async move {
+                join_all(saves).await;
+            }
+        };
+        let event_buffer = if config.event_buffer_per_tenant {
+            tokio::spawn(buffer_and_flush_by_key(
+                receiver,
+                config.tenant_event_buffer_size,
+                Duration::from_millis(config.tenant_event_buffer_max_age_millis),
+                |event: &Event| event.ownership.client_id.clone(),
+                event_flush_requests,
+                shutdown_signalled(shutdown_rx.clone()),
+                save_events,
+            ))
+        } else {
+            tokio::spawn(buffer_and_flush(
+                receiver,
+                config.event_save_buffer_size,
+                Duration::from_secs(config.event_save_timeout_secs),
+                event_flush_requests,
+                shutdown_signalled(shutdown_rx.clone()),
+                save_events,
+            ))
+        };
+
+        tokio::spawn(sample_channel_depth(
+            event_tx.downgrade(),
+            Duration::from_secs(config.event_buffer_depth_sample_interval_secs),
+            shutdown_signalled(shutdown_rx.clone()),
+        ));
+
+        if let Some(retention_days) = config.metric_retention_days {
+            let metrics = db.collection::<bson::Document>(&Store::Metrics.to_string());
+            let retention = chrono::Duration::days(retention_days as i64);
+            let interval = Duration::from_secs(config.metric_prune_interval_secs);
+            let clock = clock.clone();
+            let timezone = config.metric_timezone;
+            tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(interval);
+                loop {
+                    ticker.tick().await;
+                    match prune_metrics(&metrics, retention, clock.now(), &timezone).await {
+                        Ok(pruned) => trace!("Pruned old buckets of {pruned} metrics"),
+                        Err(e) => error!("Could not prune metrics: {e}"),
+                    }
+                }
+            });
+        }
+
+        let metric_record_collection = app_stores.metric_records.collection.clone();
+        let metric_record_retention =
+            chrono::Duration::days(config.metric_record_retention_days as i64);
+        let metric_record_interval = Duration::from_secs(config.metric_prune_interval_secs);
+        let metric_record_clock = clock.clone();
         tokio::spawn(async move {
-            let mut buffer = Vec::with_capacity(config.event_save_buffer_size);
+            let mut ticker = tokio::time::interval(metric_record_interval);
             loop {
-                let res = timeout(
-                    Duration::from_secs(config.event_save_timeout_secs),
-                    receiver.recv(),
+                ticker.tick().await;
+                match prune_metric_records(
+                    &metric_record_collection,
+                    metric_record_retention,
+                    metric_record_clock.now(),
                 )
-                .await;
-                let is_timeout = if let Ok(Some(event)) = res {
-                    buffer.push(event);
-                    false
-                } else if let Ok(None) = res {
-                    break;
-                } else {
-                    trace!("Event receiver timed out waiting for new event");
-                    true
-                };
-                // Save when buffer is full or timeout elapsed
-                if buffer.len() == config.event_save_buffer_size
-                    || (is_timeout && !buffer.is_empty())
+                .await
                 {
-                    trace!("Saving {} events", buffer.len());
-                    let to_save = std::mem::replace(
-                        &mut buffer,
-                        Vec::with_capacity(config.event_save_buffer_size),
-                    );
-                    let events = events.clone();
-                    tokio::spawn(async move {
-                        if let Err(e) = events.insert_many(to_save, None).await {
-                            error!("Could not save buffer of events: {e}");
-                        }
-                    });
+                    Ok(pruned) => trace!("Pruned {pruned} expired metric records"),
+                    Err(e) => error!("Could not prune metric records: {e}"),
+                }
+            }
+        });
+
+        let dead_letters = db.collection::<bson::Document>(&Store::DeadLetterEvents.to_string());
+        let dead_letter_retention = config
+            .dead_letter_retention_days
+            .map(|days| chrono::Duration::days(days as i64));
+        let mut dead_letter_monitor = config
+            .dead_letter_alert_threshold
+            .map(DeadLetterMonitor::new);
+        let dead_letter_interval = Duration::from_secs(config.dead_letter_check_interval_secs);
+        let dead_letter_clock = clock.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(dead_letter_interval);
+            loop {
+                ticker.tick().await;
+                if let Some(retention) = dead_letter_retention {
+                    match sweep_dead_letters(&dead_letters, retention, dead_letter_clock.now())
+                        .await
+                    {
+                        Ok(swept) => trace!("Swept {swept} expired dead letter events"),
+                        Err(e) => error!("Could not sweep dead letter events: {e}"),
+                    }
+                }
+                match dead_letters.estimated_document_count(None).await {
+                    Ok(count) => report_dead_letters(count, dead_letter_monitor.as_mut()),
+                    Err(e) => error!("Could not count dead letter events: {e}"),
                 }
             }
         });
 
         // Update metrics in separate thread
-        let client = HttpClient::default();
-        let batcher = Batcher::new(None);
         let template = DefaultTemplate::default();
-        let mut batcher = config
-            .segment_write_key
-            .as_ref()
-            .map(|k| AutoBatcher::new(client, batcher, k.to_string()));
+        let mut tracker = SegmentTracker::new(config.segment_write_key.as_deref());
 
-        let metrics = db.collection::<Metric>(&Store::Metrics.to_string());
+        let metrics = MongoStore::<bson::Document>::new(&db, &Store::Metrics).await?;
         let (metric_tx, mut receiver) =
             tokio::sync::mpsc::channel::<Metric>(config.metric_save_channel_size);
         let metric_system_id = config.metric_system_id.clone();
-        tokio::spawn(async move {
-            let options = UpdateOptions::builder().upsert(true).build();
+        let metric_timezone = config.metric_timezone;
+        let metric_records = app_stores.metric_records.clone();
+        let mut metric_shutdown = shutdown_rx;
+        let metric_buffer = tokio::spawn(async move {
+            let mut closed = false;
 
             loop {
-                let res = timeout(
-                    Duration::from_secs(config.event_save_timeout_secs),
-                    receiver.recv(),
-                )
-                .await;
+                let res = tokio::select! {
+                    res = timeout(
+                        Duration::from_secs(config.event_save_timeout_secs),
+                        receiver.recv(),
+                    ) => res,
+                    _ = metric_shutdown.wait_for(|shutdown| *shutdown), if !closed => {
+                        // Metrics already sent are still received, and saved, before stopping
+                        receiver.close();
+                        closed = true;
+                        continue;
+                    }
+                };
                 if let Ok(Some(metric)) = res {
-                    let doc = metric.update_doc();
-                    let client = metrics.update_one(
-                        bson::doc! {
-                            "clientId": &metric.ownership().client_id,
-                        },
-                        doc.clone(),
-                        options.clone(),
-                    );
-                    let system = metrics.update_one(
-                        bson::doc! {
-                            "clientId": metric_system_id.as_str(),
-                        },
-                        doc,
-                        options.clone(),
-                    );
-                    if let Err(e) = try_join!(client, system) {
+                    let doc = metric.update_doc(&metric_timezone);
+                    let updates = vec![
+                        (
+                            bson::doc! { "clientId": &metric.ownership().client_id },
+                            doc.clone(),
+                        ),
+                        (bson::doc! { "clientId": metric_system_id.as_str() }, doc),
+                    ];
+                    if let Err(e) = metrics.bulk_update_upsert(updates).await {
                         error!("Could not upsert metric: {e}");
                     }
-
-                    if let Some(ref mut batcher) = batcher {
-                        let msg = metric.segment_track();
-                        if let Err(e) = batcher.push(msg).await {
-                            warn!("Tracking msg is too large: {e}");
+                    if let Some(record) = metric.record() {
+                        if let Err(e) = metric_records.create_one(&record).await {
+                            error!("Could not save metric record: {e}");
                         }
                     }
+
+                    metric.count_call();
+                    tracker.track(&metric).await;
                 } else if let Ok(None) = res {
                     break;
                 } else {
                     trace!("Event receiver timed out waiting for new event");
-                    if let Some(ref mut batcher) = batcher {
-                        if let Err(e) = batcher.flush().await {
-                            warn!("Tracking flush is too large: {e}");
-                        }
-                    }
+                    tracker.flush().await;
                 }
             }
-            if let Some(ref mut batcher) = batcher {
-                if let Err(e) = batcher.flush().await {
-                    warn!("Tracking flush is too large: {e}");
+            tracker.flush().await;
+        });
+
+        let state = Arc::new(AppState {
+            app_stores,
+            config,
+            event_access_cache,
+            event_schemas_cache,
+            http_client,
+            connections_cache,
+            connection_definitions_cache,
+            connection_oauth_definitions_cache,
+            openapi_data,
+            secrets_client,
+            extractor_caller,
+            connection_call_stats,
+            connection_circuit_breaker,
+            pkce_verifiers,
+            warmup,
+            clock,
+            event_field_encryption,
+            event_denylist,
+            event_tx,
+            event_buffer_flush,
+            metric_tx,
+            prometheus,
+            template,
+        });
+
+        // Run the warmups in a separate thread, the instance is reported as ready once done
+        let warmup_state = state.clone();
+        tokio::spawn(async move {
+            warm_up(
+                warmup_state.config.warmups.clone(),
+                warmup_state.warmup.clone(),
+                Duration::from_millis(warmup_state.config.warmup_retry_interval_millis),
+                |warmup| run_warmup(&warmup_state, warmup),
+            )
+            .await
+        });
+
+        // Warn about credentials expiring soon in a separate thread
+        let expiry_state = state.clone();
+        tokio::spawn(async move {
+            let window =
+                chrono::Duration::seconds(expiry_state.config.credential_expiry_window_secs as i64);
+            let mut ticker = tokio::time::interval(Duration::from_secs(
+                expiry_state.config.credential_expiry_check_interval_secs,
+            ));
+            loop {
+                ticker.tick().await;
+                if let Err(e) = notify_expiring_credentials(&expiry_state, window).await {
+                    error!("Could not notify expiring credentials: {e}");
                 }
             }
         });
 
         Ok(Self {
-            state: Arc::new(AppState {
-                app_stores,
-                config,
-                event_access_cache,
-                http_client,
-                connections_cache,
-                connection_definitions_cache,
-                connection_oauth_definitions_cache,
-                openapi_data,
-                secrets_client,
-                extractor_caller,
-                event_tx,
-                metric_tx,
-                template,
-            }),
+            state,
+            shutdown: Arc::new(shutdown_tx),
+            buffers: Arc::new(Mutex::new(vec![event_buffer, metric_buffer])),
         })
     }
 
@@ -318,12 +615,82 @@ impl Server {
 
         let app: Router<()> = app.with_state(self.state.clone());
 
+        let tcp_listener = bind_listener(self.state.config.address).await?;
+
         info!("Api server listening on {}", self.state.config.address);
 
-        let tcp_listener = TcpListener::bind(&self.state.config.address).await?;
+        if let Some(address) = self.state.config.metrics_address {
+            let metrics_app = Router::new()
+                .route("/metrics", get(get_prometheus_metrics))
+                .with_state(self.state.clone());
+            let metrics_listener = TcpListener::bind(address)
+                .await
+                .with_context(|| format!("Could not listen for metrics on {address}"))?;
+
+            info!("Metrics served on {address}/metrics");
+
+            tokio::spawn(async move {
+                if let Err(e) = axum::serve(metrics_listener, metrics_app.into_make_service()).await
+                {
+                    error!("Metrics server error: {e}");
+                }
+            });
+        }
 
         axum::serve(tcp_listener, app.into_make_service())
+            .with_graceful_shutdown(shutdown_signal())
             .await
-            .map_err(|e| anyhow!("Server error: {}", e))
+            .map_err(|e| anyhow!("Server error: {}", e))?;
+
+        self.shutdown().await;
+        Ok(())
+    }
+
+    /// Stops buffering events and metrics, and waits until the buffered ones are saved
+    pub async fn shutdown(&self) {
+        info!("Saving buffered events and metrics before shutting down");
+        self.shutdown.send_replace(true);
+
+        let buffers = std::mem::take(&mut *self.buffers.lock().unwrap_or_else(|e| e.into_inner()));
+        for buffer in buffers {
+            if let Err(e) = buffer.await {
+                error!("Could not save buffered events or metrics: {e}");
+            }
+        }
+    }
+}
+
+async fn shutdown_signalled(mut shutdown: watch::Receiver<bool>) {
+    let _ = shutdown.wait_for(|shutdown| *shutdown).await;
+}
+
+/// Resolves on SIGINT, or SIGTERM on unix
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Could not listen for SIGINT: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("Could not listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received SIGINT"),
+        _ = terminate => info!("Received SIGTERM"),
     }
 }
@@ -1,6 +1,7 @@
 use super::test_server::{ApiResponse, TestServer, PUBLIC_PATHS};
 use http::{Method, StatusCode};
 use serde_json::{json, Value};
+use std::collections::BTreeMap;
 
 #[tokio::test]
 async fn test_root() {
@@ -45,3 +46,27 @@ async fn test_404() {
         );
     }
 }
+
+#[tokio::test]
+async fn test_errors_carry_the_request_id() {
+    let server = TestServer::new(None).await;
+
+    let res = server
+        .send_request_with_headers::<Value, Value>(
+            "v1/connections",
+            Method::GET,
+            None,
+            None,
+            Some(BTreeMap::from([(
+                "x-integrationos-correlation-id".to_string(),
+                "request".to_string(),
+            )])),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(res.code, StatusCode::UNAUTHORIZED);
+    assert_eq!(res.data["status"], 401);
+    assert_eq!(res.data["key"], "err::application::unauthorized");
+    assert_eq!(res.data["requestId"], "request");
+}
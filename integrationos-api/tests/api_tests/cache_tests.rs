@@ -0,0 +1,54 @@
+use crate::test_server::TestServer;
+use http::{Method, StatusCode};
+use integrationos_api::logic::cache::{InvalidateCacheRequest, InvalidateCacheResponse};
+use serde_json::Value;
+
+#[tokio::test]
+async fn test_invalidate_cached_event_access() {
+    let server = TestServer::new(None).await;
+
+    // Authenticating caches the event access of the key
+    let res = server
+        .send_request::<Value, Value>("v1/events", Method::GET, Some(&server.live_key), None)
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let request = InvalidateCacheRequest {
+        cache: "event-access".to_string(),
+        key: Some(server.live_key.clone()),
+    };
+    for invalidated in [1, 0] {
+        let res = server
+            .send_request::<InvalidateCacheRequest, InvalidateCacheResponse>(
+                "v1/cache/invalidate",
+                Method::POST,
+                None,
+                Some(&request),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.code, StatusCode::OK);
+        assert_eq!(
+            res.data,
+            InvalidateCacheResponse {
+                cache: "event-access".to_string(),
+                invalidated,
+            }
+        );
+    }
+
+    let res = server
+        .send_request::<InvalidateCacheRequest, Value>(
+            "v1/cache/invalidate",
+            Method::POST,
+            None,
+            Some(&InvalidateCacheRequest {
+                cache: "unknown".to_string(),
+                key: None,
+            }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::NOT_FOUND);
+}
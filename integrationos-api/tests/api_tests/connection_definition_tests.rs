@@ -0,0 +1,291 @@
+use crate::test_server::TestServer;
+use fake::{Fake, Faker};
+use http::{
+    header::{ACCEPT_LANGUAGE, AGE, CACHE_CONTROL},
+    Method, StatusCode,
+};
+use integrationos_api::{
+    helper::CACHE_STATUS_HEADER,
+    logic::{
+        connection_definition::{
+            AuthenticationItem, ConnectionDefinitionChanges,
+            CreateRequest as CreateConnectionDefinitionRequest,
+        },
+        connection_model_definition::CreateRequest as CreateConnectionModelDefinitionRequest,
+        events::FlushEventBufferResponse,
+        ReadResponse,
+    },
+};
+use integrationos_domain::{
+    algebra::MongoStore,
+    connection_definition::{
+        ConnectionDefinition, ConnectionDefinitionType, LocalizedFormDataItem,
+    },
+    connection_model_definition::CrudAction,
+    environment::Environment,
+    Store,
+};
+use mongodb::{
+    bson::{doc, Document},
+    Client,
+};
+use serde_json::{from_value, Value};
+use std::collections::{BTreeMap, HashMap};
+
+async fn create_definition_supporting(server: &TestServer, action: CrudAction) -> String {
+    let mut connection_def: CreateConnectionDefinitionRequest = Faker.fake();
+    connection_def.r#type = ConnectionDefinitionType::Api;
+    connection_def.active = true;
+
+    let res = server
+        .send_request::<CreateConnectionDefinitionRequest, ConnectionDefinition>(
+            "v1/connection-definitions",
+            Method::POST,
+            Some(&server.live_key),
+            Some(&connection_def),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    let connection_def = res.data;
+
+    let mut model_def: CreateConnectionModelDefinitionRequest = Faker.fake();
+    model_def.connection_definition_id = connection_def.id;
+    model_def.connection_platform = connection_def.platform.clone();
+    model_def.action_name = action;
+    model_def.supported = Some(true);
+    model_def.active = Some(true);
+
+    let res = server
+        .send_request::<CreateConnectionModelDefinitionRequest, Value>(
+            "v1/connection-model-definitions",
+            Method::POST,
+            Some(&server.live_key),
+            Some(&model_def),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    connection_def.id.to_string()
+}
+
+#[tokio::test]
+async fn test_connection_definitions_filtered_by_supported_action() {
+    let server = TestServer::new(None).await;
+    let creating = create_definition_supporting(&server, CrudAction::Create).await;
+    let listing = create_definition_supporting(&server, CrudAction::GetMany).await;
+
+    let res = server
+        .send_request::<Value, Value>(
+            "v1/public/connection-definitions?supportedAction=create",
+            Method::GET,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let res = from_value::<ReadResponse<ConnectionDefinition>>(res.data).unwrap();
+    let ids = res
+        .rows
+        .iter()
+        .map(|definition| definition.id.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, [creating]);
+    assert!(!ids.contains(&listing));
+
+    let res = server
+        .send_request::<Value, Value>(
+            "v1/public/connection-definitions?supportedAction=invoice",
+            Method::GET,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_connection_definition_labels_served_in_requested_locale() {
+    let server = TestServer::new(None).await;
+
+    let mut connection_def: CreateConnectionDefinitionRequest = Faker.fake();
+    connection_def.active = true;
+    connection_def.authentication = vec![AuthenticationItem {
+        name: "API_KEY".to_string(),
+        label: "API key".to_string(),
+        r#type: "text".to_string(),
+        placeholder: "Your API key".to_string(),
+        localized: BTreeMap::from([(
+            "fr".to_string(),
+            LocalizedFormDataItem {
+                label: Some("Clé d'API".to_string()),
+                placeholder: Some("Votre clé d'API".to_string()),
+            },
+        )]),
+    }];
+
+    let res = server
+        .send_request::<CreateConnectionDefinitionRequest, ConnectionDefinition>(
+            "v1/connection-definitions",
+            Method::POST,
+            Some(&server.live_key),
+            Some(&connection_def),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    let id = res.data.id;
+
+    let read_labels = |path: String, accept_language: Option<&'static str>| {
+        let server = &server;
+        async move {
+            let res = server
+                .send_request_with_headers::<Value, Value>(
+                    &path,
+                    Method::GET,
+                    None,
+                    None,
+                    accept_language.map(|accept_language| {
+                        BTreeMap::from([(ACCEPT_LANGUAGE.to_string(), accept_language.to_string())])
+                    }),
+                )
+                .await
+                .unwrap();
+            assert_eq!(res.code, StatusCode::OK);
+
+            let mut res = from_value::<ReadResponse<ConnectionDefinition>>(res.data).unwrap();
+            assert_eq!(res.rows.len(), 1);
+            let item = res
+                .rows
+                .remove(0)
+                .frontend
+                .connection_form
+                .form_data
+                .remove(0);
+            (item.label, item.placeholder, item.localized.is_empty())
+        }
+    };
+    let path = format!("v1/public/connection-definitions?_id={id}");
+
+    assert_eq!(
+        read_labels(path.clone(), Some("de;q=0.9, fr-CA")).await,
+        ("Clé d'API".to_string(), "Votre clé d'API".to_string(), true)
+    );
+    assert_eq!(
+        read_labels(format!("{path}&locale=fr"), Some("de")).await,
+        ("Clé d'API".to_string(), "Votre clé d'API".to_string(), true)
+    );
+
+    // Locales without localized labels fall back to the default ones
+    assert_eq!(
+        read_labels(format!("{path}&locale=es"), None).await,
+        ("API key".to_string(), "Your API key".to_string(), true)
+    );
+
+    // Without a locale, definitions are served as they are stored
+    assert_eq!(
+        read_labels(path, None).await,
+        ("API key".to_string(), "Your API key".to_string(), false)
+    );
+}
+
+#[tokio::test]
+async fn test_definition_update_notifies_its_connections_of_the_changes() {
+    // Buffered events are only saved on request within the test
+    let mut server = TestServer::new_with_config(
+        None,
+        HashMap::from([("EVENT_SAVE_TIMEOUT_SECS".to_string(), "3600".to_string())]),
+    )
+    .await;
+    let (connection, _) = server.create_connection(Environment::Live).await;
+
+    let mut update: CreateConnectionDefinitionRequest = Faker.fake();
+    update.r#type = ConnectionDefinitionType::Api;
+    update.description = "Updated description".to_string();
+    let res = server
+        .send_request::<CreateConnectionDefinitionRequest, Value>(
+            &format!(
+                "v1/connection-definitions/{}",
+                connection.connection_definition_id
+            ),
+            Method::PATCH,
+            Some(&server.live_key),
+            Some(&update),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let res = server
+        .send_request::<(), FlushEventBufferResponse>("v1/events/flush", Method::POST, None, None)
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let db = Client::with_uri_str(&server.config.db_config.event_db_url)
+        .await
+        .unwrap()
+        .database(&server.config.db_config.event_db_name);
+    let store: MongoStore<Document> = MongoStore::new(&db, &Store::Events).await.unwrap();
+    let events = store
+        .get_many(
+            Some(doc! {
+                "name": format!("{}::connection-definition-changed", connection.platform)
+            }),
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+
+    let changes: ConnectionDefinitionChanges =
+        serde_json::from_str(events[0].get_str("body").unwrap()).unwrap();
+    assert_eq!(
+        changes.connection_definition_id,
+        connection.connection_definition_id
+    );
+    assert!(changes
+        .changed_fields
+        .contains(&"$.frontend.spec.description".to_string()));
+}
+
+#[tokio::test]
+async fn test_cached_definition_reads_carry_their_cache_status_and_age() {
+    let server = TestServer::new(None).await;
+    let id = create_definition_supporting(&server, CrudAction::GetMany).await;
+    let path = format!("v1/connection-definitions/{id}");
+
+    // Reads bypassing the cache read the database, and cache the definition they read
+    let res = server
+        .send_raw_request::<()>(
+            &path,
+            Method::GET,
+            Some(&server.live_key),
+            None,
+            Some(BTreeMap::from([(
+                CACHE_CONTROL.to_string(),
+                "no-cache".to_string(),
+            )])),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[CACHE_STATUS_HEADER], "MISS");
+    assert!(res.headers().get(AGE).is_none());
+
+    let res = server
+        .send_raw_request::<()>(&path, Method::GET, Some(&server.live_key), None, None)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[CACHE_STATUS_HEADER], "HIT");
+    let age: u64 = res.headers()[AGE].to_str().unwrap().parse().unwrap();
+    assert!(age < 60, "{age}");
+}
@@ -1,6 +1,13 @@
 use crate::test_server::TestServer;
+use fake::{Fake, Faker};
 use http::{Method, StatusCode};
-use serde_json::Value;
+use integrationos_api::logic::{
+    connection::CreateConnectionPayload,
+    connection_definition::CreateRequest as CreateConnectionDefinitionRequest,
+};
+use integrationos_domain::connection_definition::{ConnectionDefinition, ConnectionDefinitionType};
+use serde_json::{json, Value};
+use std::collections::HashMap;
 
 #[tokio::test]
 async fn test_connection_data_models_api() {
@@ -11,3 +18,61 @@ async fn test_connection_data_models_api() {
         .unwrap();
     assert_eq!(res.code, StatusCode::OK);
 }
+
+#[tokio::test]
+async fn test_connection_violating_input_rules_is_rejected() {
+    let server = TestServer::new(None).await;
+
+    let mut connection_def: CreateConnectionDefinitionRequest = Faker.fake();
+    connection_def.r#type = ConnectionDefinitionType::Api;
+    connection_def.test_connection = None;
+    connection_def.input_rules = serde_json::from_value(json!([
+        { "input": "BASE_URL", "rule": "regex", "pattern": "https://.+" }
+    ]))
+    .unwrap();
+
+    let res = server
+        .send_request::<CreateConnectionDefinitionRequest, ConnectionDefinition>(
+            "v1/connection-definitions",
+            Method::POST,
+            Some(&server.live_key),
+            Some(&connection_def),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let payload = CreateConnectionPayload {
+        connection_definition_id: res.data.id,
+        name: Faker.fake(),
+        group: server.live_access_key.data.group.clone(),
+        auth_form_data: HashMap::from([(
+            "BASE_URL".to_string(),
+            "http://api.example.com".to_string(),
+        )]),
+        active: true,
+        mode: Default::default(),
+        webhook_secret: None,
+        credentials_expire_at: None,
+    };
+
+    let res = server
+        .send_request::<CreateConnectionPayload, Value>(
+            "v1/connections",
+            Method::POST,
+            Some(&server.live_key),
+            Some(&payload),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(res.code, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        res.data["meta"]["violations"],
+        json!([{
+            "input": "BASE_URL",
+            "rule": "regex",
+            "message": "BASE_URL must match the pattern https://.+"
+        }])
+    );
+}
@@ -0,0 +1,45 @@
+use crate::test_server::TestServer;
+use http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn test_event_access_with_empty_scopes_is_rejected() {
+    let server = TestServer::new(None).await;
+
+    let payload = json!({
+        "name": "read-only",
+        "platform": "stripe",
+        "connectionType": "api",
+        "paths": {},
+        "scopes": [],
+    });
+    let res = server
+        .send_request::<Value, Value>(
+            "v1/event-access",
+            Method::POST,
+            Some(&server.live_key),
+            Some(&payload),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::BAD_REQUEST);
+
+    let payload = json!({
+        "name": "read-only",
+        "platform": "stripe",
+        "connectionType": "api",
+        "paths": {},
+        "scopes": ["read"],
+    });
+    let res = server
+        .send_request::<Value, Value>(
+            "v1/event-access",
+            Method::POST,
+            Some(&server.live_key),
+            Some(&payload),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    assert_eq!(res.data["scopes"], json!(["read"]));
+}
@@ -0,0 +1,133 @@
+use crate::test_server::TestServer;
+use http::{Method, StatusCode};
+use integrationos_domain::{
+    environment::Environment, event_response::EventResponse, event_schema::EventSchema,
+    webhook::sign_webhook,
+};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+#[tokio::test]
+async fn test_webhooks_are_validated_against_the_registered_schema() {
+    let mut server = TestServer::new(None).await;
+    let (connection, _) = server.create_connection(Environment::Live).await;
+    let event_type = format!("{}::webhook-received", connection.platform);
+
+    let res = server
+        .send_request::<Value, Value>(
+            &format!("v1/connections/{}", connection.id),
+            Method::PATCH,
+            Some(&server.live_key),
+            Some(&json!({ "webhookSecret": "secret" })),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let res = server
+        .send_request::<Value, EventSchema>(
+            "v1/event-schemas",
+            Method::POST,
+            Some(&server.live_key),
+            Some(&json!({
+                "eventType": event_type,
+                "schema": {
+                    "type": "object",
+                    "required": ["type", "amount"],
+                    "properties": {
+                        "type": { "type": "string" },
+                        "amount": { "type": "integer" }
+                    }
+                }
+            })),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    assert_eq!(res.data.version, 1);
+
+    let path = format!("v1/public/webhooks/{}", connection.id);
+    let send = |payload: Value| {
+        let signature = sign_webhook("secret", &serde_json::to_vec(&payload).unwrap()).unwrap();
+        let headers = BTreeMap::from([(
+            server.config.headers.webhook_signature_header.clone(),
+            signature,
+        )]);
+        let path = path.clone();
+        let server = &server;
+        async move {
+            server
+                .send_request_with_headers::<Value, Value>(
+                    &path,
+                    Method::POST,
+                    None,
+                    Some(&payload),
+                    Some(headers),
+                )
+                .await
+                .unwrap()
+        }
+    };
+
+    let res = send(json!({ "type": "invoice.paid", "amount": 4200 })).await;
+    assert_eq!(res.code, StatusCode::OK);
+    serde_json::from_value::<EventResponse>(res.data).expect("Event was ingested");
+
+    let res = send(json!({ "type": "invoice.paid", "amount": "4200" })).await;
+    assert_eq!(res.code, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let res = send(json!({ "type": "invoice.paid" })).await;
+    assert_eq!(res.code, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_incompatible_schema_version_is_rejected() {
+    let server = TestServer::new(None).await;
+    let register = |schema: Value| {
+        let server = &server;
+        async move {
+            server
+                .send_request::<Value, Value>(
+                    "v1/event-schemas",
+                    Method::POST,
+                    Some(&server.live_key),
+                    Some(&json!({ "eventType": "invoice.paid", "schema": schema })),
+                )
+                .await
+                .unwrap()
+        }
+    };
+
+    let res = register(json!({ "type": "object", "required": ["id"] })).await;
+    assert_eq!(res.code, StatusCode::OK);
+
+    // Payloads of the first version lack the new required property
+    let res = register(json!({ "type": "object", "required": ["id", "amount"] })).await;
+    assert_eq!(res.code, StatusCode::CONFLICT);
+
+    let res = register(json!({
+        "type": "object",
+        "required": ["id"],
+        "properties": { "amount": { "type": "number" } }
+    }))
+    .await;
+    assert_eq!(res.code, StatusCode::OK);
+
+    let res = server
+        .send_request::<Value, Vec<EventSchema>>(
+            "v1/event-schemas/invoice.paid",
+            Method::GET,
+            Some(&server.live_key),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    assert_eq!(
+        res.data
+            .iter()
+            .map(|schema| schema.version)
+            .collect::<Vec<_>>(),
+        vec![2, 1]
+    );
+}
@@ -0,0 +1,238 @@
+use crate::test_server::TestServer;
+use http::{HeaderMap, Method, StatusCode};
+use integrationos_api::logic::{
+    events::{BackfillEventsRequest, FlushEventBufferResponse},
+    ReadResponse,
+};
+use integrationos_domain::{
+    algebra::MongoStore,
+    encrypted_access_key::EncryptedAccessKey,
+    environment::Environment,
+    event_response::EventResponse,
+    id::{prefix::IdPrefix, Id},
+    migration::CURRENT_EVENT_VERSION,
+    webhook::sign_webhook,
+    Event, Operation, OperationState, Store,
+};
+use mongodb::{
+    bson::{doc, Document},
+    Client,
+};
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+#[tokio::test]
+async fn test_backfill_populates_missing_fields_of_stored_events() {
+    let server = TestServer::new(None).await;
+
+    let db = Client::with_uri_str(&server.config.db_config.event_db_url)
+        .await
+        .unwrap()
+        .database(&server.config.db_config.event_db_name);
+    let store: MongoStore<Document> = MongoStore::new(&db, &Store::Events).await.unwrap();
+
+    let ids = (0..3)
+        .map(|_| Id::now(IdPrefix::Event).to_string())
+        .collect::<Vec<_>>();
+    let documents = ids
+        .iter()
+        .map(|id| {
+            doc! {
+                "_id": id,
+                "body": "hello world",
+                "arrivedAt": 1_700_000_000_123_i64,
+            }
+        })
+        .collect::<Vec<_>>();
+    store.create_many(&documents).await.unwrap();
+
+    let payload = BackfillEventsRequest {
+        defaults: BTreeMap::from([("region".to_string(), json!("us"))]),
+        batch_size: 2,
+    };
+    let res = server
+        .send_request::<BackfillEventsRequest, Operation>(
+            "v1/events/backfill",
+            Method::POST,
+            None,
+            Some(&payload),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let polled = wait_for_operation(&server, res.data).await;
+    assert_eq!(polled.state, OperationState::Succeeded);
+    assert_eq!(polled.result, Some(json!({ "backfilled": 3 })));
+
+    for id in ids {
+        let document = store.get_one_by_id(&id).await.unwrap().unwrap();
+        assert_eq!(document.get_str("region").unwrap(), "us");
+        assert_eq!(document.get_i64("payloadByteLength").unwrap(), 11);
+        assert_eq!(
+            document.get_i64("schemaVersion").unwrap(),
+            CURRENT_EVENT_VERSION as i64
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_events_of_dedicated_tenant_are_read_and_backfilled() {
+    let server = TestServer::new_with_dedicated_events(None).await;
+
+    let db = Client::with_uri_str(&server.config.db_config.event_db_url)
+        .await
+        .unwrap()
+        .database(&server.config.db_config.event_db_name);
+    let shared: MongoStore<Document> = MongoStore::new(&db, &Store::Events).await.unwrap();
+    let collection = server
+        .config
+        .dedicated_event_tenants
+        .collection_for(&server.live_access_key.data.id);
+    assert_ne!(collection, Store::Events.to_string());
+    let dedicated = MongoStore::<Document> {
+        collection: db.collection(&collection),
+    };
+
+    let encrypted_access_key = EncryptedAccessKey::parse(&server.live_key).unwrap();
+    let events = (0..2)
+        .map(|_| {
+            Event::new(
+                &server.live_access_key,
+                &encrypted_access_key,
+                "event",
+                HeaderMap::new(),
+                "{}".to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let ids = events
+        .iter()
+        .map(|event| event.id.to_string())
+        .collect::<Vec<_>>();
+    let documents = events
+        .iter()
+        .map(|event| bson::to_document(event).unwrap())
+        .collect::<Vec<_>>();
+    dedicated.create_many(&documents).await.unwrap();
+
+    let res = server
+        .send_request::<(), ReadResponse<Value>>(
+            "v1/events",
+            Method::GET,
+            Some(&server.live_key),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    assert_eq!(res.data.rows.len(), 2);
+    assert!(res.data.rows.iter().all(|row| !row.is_null()));
+
+    let payload = BackfillEventsRequest {
+        defaults: BTreeMap::from([("region".to_string(), json!("eu"))]),
+        batch_size: 10,
+    };
+    let res = server
+        .send_request::<BackfillEventsRequest, Operation>(
+            "v1/events/backfill",
+            Method::POST,
+            None,
+            Some(&payload),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let polled = wait_for_operation(&server, res.data).await;
+    assert_eq!(polled.state, OperationState::Succeeded);
+    assert_eq!(polled.result, Some(json!({ "backfilled": 2 })));
+
+    for id in ids {
+        let document = dedicated.get_one_by_id(&id).await.unwrap().unwrap();
+        assert_eq!(document.get_str("region").unwrap(), "eu");
+    }
+    assert_eq!(shared.count(doc! {}, None).await.unwrap(), 0);
+}
+
+/// Polls the operation until it finishes, or gives up after 5 seconds
+async fn wait_for_operation(server: &TestServer, operation: Operation) -> Operation {
+    let mut polled = operation.clone();
+    for _ in 0..50 {
+        polled = server
+            .send_request::<(), Operation>(
+                &format!("v1/operations/{}", operation.id),
+                Method::GET,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .data;
+        if polled.is_finished() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    polled
+}
+
+#[tokio::test]
+async fn test_requested_flush_persists_buffered_events_immediately() {
+    // Buffered events are only saved on request within the test
+    let mut server = TestServer::new_with_config(
+        None,
+        HashMap::from([("EVENT_SAVE_TIMEOUT_SECS".to_string(), "3600".to_string())]),
+    )
+    .await;
+    let (connection, _) = server.create_connection(Environment::Live).await;
+
+    let res = server
+        .send_request::<Value, Value>(
+            &format!("v1/connections/{}", connection.id),
+            Method::PATCH,
+            Some(&server.live_key),
+            Some(&json!({ "webhookSecret": "secret" })),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let db = Client::with_uri_str(&server.config.db_config.event_db_url)
+        .await
+        .unwrap()
+        .database(&server.config.db_config.event_db_name);
+    let store: MongoStore<Document> = MongoStore::new(&db, &Store::Events).await.unwrap();
+
+    let path = format!("v1/public/webhooks/{}", connection.id);
+    for amount in [4200, 9900] {
+        let payload = json!({ "type": "invoice.paid", "amount": amount });
+        let signature = sign_webhook("secret", &serde_json::to_vec(&payload).unwrap()).unwrap();
+        let res = server
+            .send_request_with_headers::<Value, EventResponse>(
+                &path,
+                Method::POST,
+                None,
+                Some(&payload),
+                Some(BTreeMap::from([(
+                    server.config.headers.webhook_signature_header.clone(),
+                    signature,
+                )])),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.code, StatusCode::OK);
+    }
+    assert_eq!(store.count(doc! {}, None).await.unwrap(), 0);
+
+    let res = server
+        .send_request::<(), FlushEventBufferResponse>("v1/events/flush", Method::POST, None, None)
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    assert_eq!(res.data.flushed, 2);
+    assert_eq!(store.count(doc! {}, None).await.unwrap(), 2);
+}
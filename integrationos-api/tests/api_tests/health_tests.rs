@@ -0,0 +1,27 @@
+use crate::test_server::TestServer;
+use http::{Method, StatusCode};
+use integrationos_api::logic::health::{HealthResponse, Status};
+use serde_json::Value;
+
+#[tokio::test]
+async fn test_liveness_and_readiness_probes() {
+    let server = TestServer::new(None).await;
+
+    let res = server
+        .send_request::<Value, HealthResponse>("healthz", Method::GET, None, None)
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    assert_eq!(res.data.status, Status::Up);
+    assert!(res.data.checks.is_empty());
+
+    let res = server
+        .send_request::<Value, HealthResponse>("readyz", Method::GET, None, None)
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    assert_eq!(res.data.status, Status::Up);
+    for name in ["mongo", "secrets", "warmup"] {
+        assert_eq!(res.data.checks[name].status, Status::Up);
+    }
+}
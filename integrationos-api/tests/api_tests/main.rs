@@ -1,10 +1,23 @@
 mod auth_tests;
+mod cache_tests;
+mod connection_definition_tests;
 mod connection_tests;
+mod event_access_tests;
+mod event_schema_tests;
+mod event_tests;
 mod get_tests;
+mod health_tests;
+mod openapi_tests;
+mod operation_tests;
 mod pagination_tests;
 mod passthrough_tests;
+mod pkce_verifier_tests;
+mod prometheus_tests;
 mod schema_tests;
+mod storage_tests;
+mod store_tests;
 mod test_crud;
 mod test_server;
 mod transaction_tests;
 mod unified_tests;
+mod webhook_tests;
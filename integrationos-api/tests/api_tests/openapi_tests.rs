@@ -0,0 +1,68 @@
+use crate::test_server::TestServer;
+use http::{
+    header::{ETAG, IF_NONE_MATCH, LAST_MODIFIED},
+    Method, StatusCode,
+};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+#[tokio::test]
+async fn test_repeated_openapi_request_with_matching_etag_is_not_modified() {
+    let server = TestServer::new_with_config(
+        None,
+        HashMap::from([("OPENAPI_VERSION".to_string(), "2.3.0".to_string())]),
+    )
+    .await;
+
+    // The schema is generated when the server starts, it has no tag until it is
+    let mut res = None;
+    for _ in 0..50 {
+        let sent = server
+            .send_raw_request::<Value>("v1/openapi", Method::GET, None, None, None)
+            .await
+            .unwrap();
+        if sent.headers().contains_key(ETAG) {
+            res = Some(sent);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let res = res.expect("The schema was generated");
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers().contains_key(LAST_MODIFIED));
+    let etag = res.headers()[ETAG].to_str().unwrap().to_owned();
+    let schema: Value = res.json().await.unwrap();
+    assert_eq!(schema["info"]["version"], "2.3.0");
+
+    let res = server
+        .send_raw_request::<Value>(
+            "v1/openapi",
+            Method::GET,
+            None,
+            None,
+            Some(BTreeMap::from([(IF_NONE_MATCH.to_string(), etag.clone())])),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(res.headers()[ETAG], etag.as_str());
+    assert!(res.bytes().await.unwrap().is_empty());
+
+    let res = server
+        .send_raw_request::<Value>(
+            "v1/openapi",
+            Method::GET,
+            None,
+            None,
+            Some(BTreeMap::from([(
+                IF_NONE_MATCH.to_string(),
+                "\"outdated\"".to_string(),
+            )])),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
@@ -0,0 +1,64 @@
+use crate::test_server::TestServer;
+use http::{Method, StatusCode};
+use integrationos_domain::{
+    id::{prefix::IdPrefix, Id},
+    Operation, OperationKind, OperationState,
+};
+use serde_json::Value;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_poll_operation_until_completion() {
+    let server = TestServer::new(None).await;
+
+    let res = server
+        .send_request::<Value, Operation>("v1/openapi", Method::POST, None, None)
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let operation = res.data;
+    assert_eq!(operation.kind, OperationKind::OpenApiGeneration);
+    assert_eq!(operation.state, OperationState::Pending);
+
+    let mut polled = operation.clone();
+    for _ in 0..50 {
+        let res = server
+            .send_request::<Value, Operation>(
+                &format!("v1/operations/{}", operation.id),
+                Method::GET,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.code, StatusCode::OK);
+
+        polled = res.data;
+        if polled.is_finished() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    assert_eq!(polled.id, operation.id);
+    assert_eq!(polled.state, OperationState::Succeeded);
+    assert_eq!(polled.progress, 1.0);
+    assert!(polled.error.is_none());
+}
+
+#[tokio::test]
+async fn test_get_unknown_operation() {
+    let server = TestServer::new(None).await;
+
+    let res = server
+        .send_request::<Value, Value>(
+            &format!("v1/operations/{}", Id::now(IdPrefix::Operation)),
+            Method::GET,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::NOT_FOUND);
+}
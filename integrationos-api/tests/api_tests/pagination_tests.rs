@@ -56,6 +56,43 @@ async fn test_pagination() {
     check_response(&server, 5, 0, &pipelines[..5]).await;
     check_response(&server, 5, 5, &pipelines[5..]).await;
     check_response(&server, 5, 10, &pipelines[10..]).await;
+
+    // Keyset pages, starting from an empty cursor, cover the same rows as the offset pages
+    let mut rows = vec![];
+    let mut cursor = String::new();
+    loop {
+        let res = server
+            .send_request::<Value, Value>(
+                &format!("v1/pipelines?limit=4&cursor={cursor}"),
+                Method::GET,
+                Some(&server.live_key),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.code, StatusCode::OK);
+
+        let res: ReadResponse<Pipeline> = serde_json::from_value(res.data).unwrap();
+        assert_eq!(res.total, 10);
+        assert!(res.rows.len() <= 4);
+        rows.extend(res.rows);
+        match res.next_cursor {
+            Some(next) => cursor = next,
+            None => break,
+        }
+    }
+    assert_eq!(rows, pipelines);
+
+    let res = server
+        .send_request::<Value, Value>(
+            "v1/pipelines?cursor=not-a-cursor",
+            Method::GET,
+            Some(&server.live_key),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::BAD_REQUEST);
 }
 
 async fn check_response(server: &TestServer, limit: u64, skip: u64, pipelines: &[Pipeline]) {
@@ -7,11 +7,13 @@ use http::{
 use integrationos_api::logic::connection_model_definition::CreateRequest as CreateConnectionModelDefinitionRequest;
 use integrationos_domain::{
     api_model_config::{AuthMethod, SamplesInput, SchemasInput},
-    connection_model_definition::CrudAction,
+    connection_model_definition::{ConnectionModelDefinition, CrudAction},
     environment::Environment,
+    SanitizedConnection,
 };
 use mockito::Server;
 use serde_json::Value;
+use std::collections::HashMap;
 
 #[tokio::test]
 async fn test_passthrough_api() {
@@ -72,6 +74,11 @@ async fn test_passthrough_api() {
         mapping: None,
         supported: Some(true),
         active: Some(true),
+        shadow: None,
+        streaming: None,
+        timeout: None,
+        error_format: None,
+        pagination_limits: None,
     };
 
     let create_model_definition_response = server
@@ -163,3 +170,152 @@ async fn test_passthrough_api() {
 
     mock.assert_async().await;
 }
+
+#[tokio::test]
+async fn test_passthrough_bodies_are_streamed_or_capped() {
+    let mut server = TestServer::new_with_config(
+        None,
+        HashMap::from([
+            ("PASSTHROUGH_MAX_BODY_BYTES".to_string(), "1024".to_string()),
+            (
+                "PASSTHROUGH_MAX_STREAMING_BODY_BYTES".to_string(),
+                "65536".to_string(),
+            ),
+        ]),
+    )
+    .await;
+    let (connection, conn_def) = server.create_connection(Environment::Live).await;
+
+    let mut mock_server = Server::new_async().await;
+    let upload = (0..32 * 1024).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    let upload_mock = mock_server
+        .mock("POST", "/uploads")
+        .match_body(upload.clone())
+        .expect(1)
+        .with_status(200)
+        .with_body("{}")
+        .create_async()
+        .await;
+    let notes_mock = mock_server
+        .mock("POST", "/notes")
+        .expect(0)
+        .create_async()
+        .await;
+
+    for (path, streaming) in [("uploads", true), ("notes", false)] {
+        create_model_definition(
+            &server,
+            &connection,
+            &conn_def,
+            mock_server.url(),
+            path,
+            streaming,
+        )
+        .await;
+    }
+
+    let send = |path: &'static str, body: reqwest::Body| {
+        let server = &server;
+        let connection_key = connection.key.to_string();
+        async move {
+            server
+                .send_raw_body(
+                    &format!("v1/passthrough/{path}"),
+                    Method::POST,
+                    &server.live_key,
+                    &connection_key,
+                    body,
+                )
+                .await
+                .expect("Failed to call passthrough API")
+                .status()
+        }
+    };
+
+    // Sent in chunks without a length, the upload is streamed to the platform whole despite
+    // being larger than the buffered body limit
+    let chunks = upload
+        .chunks(4096)
+        .map(|chunk| Ok::<_, std::io::Error>(chunk.to_vec()))
+        .collect::<Vec<_>>();
+    let status = send(
+        "uploads",
+        reqwest::Body::wrap_stream(futures::stream::iter(chunks)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Bodies past the streaming limit are rejected on the route
+    let status = send("uploads", reqwest::Body::from(vec![0u8; 128 * 1024])).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Bodies of the other definitions are buffered up to their own limit
+    let status = send("notes", reqwest::Body::from(vec![0u8; 4096])).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+    upload_mock.assert_async().await;
+    notes_mock.assert_async().await;
+}
+
+async fn create_model_definition(
+    server: &TestServer,
+    connection: &SanitizedConnection,
+    conn_def: &ConnectionModelDefinition,
+    base_url: String,
+    path: &str,
+    streaming: bool,
+) {
+    let payload = CreateConnectionModelDefinitionRequest {
+        id: None,
+        connection_platform: connection.platform.to_string(),
+        connection_definition_id: conn_def.id,
+        platform_version: conn_def.record_metadata.version.to_string(),
+        title: Faker.fake(),
+        name: Faker.fake(),
+        model_name: Faker.fake(),
+        action_name: CrudAction::Create,
+        base_url,
+        path: path.to_string(),
+        auth_method: AuthMethod::None,
+        http_method: http::Method::POST,
+        headers: None,
+        query_params: None,
+        extractor_config: None,
+        version: "1.0.0".parse().unwrap(),
+        schemas: SchemasInput {
+            headers: None,
+            query_params: None,
+            path_params: None,
+            body: None,
+        },
+        samples: SamplesInput {
+            headers: None,
+            query_params: None,
+            path_params: None,
+            body: None,
+        },
+        paths: None,
+        responses: vec![],
+        is_default_crud_mapping: None,
+        test_connection_payload: None,
+        mapping: None,
+        supported: Some(true),
+        active: Some(true),
+        shadow: None,
+        streaming: Some(streaming),
+        timeout: None,
+        error_format: None,
+        pagination_limits: None,
+    };
+
+    let res = server
+        .send_request::<Value, Value>(
+            "v1/connection-model-definitions",
+            Method::POST,
+            Some(&server.live_key),
+            Some(&serde_json::to_value(&payload).unwrap()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+}
@@ -0,0 +1,72 @@
+use crate::test_server::TestServer;
+use chrono::{Duration, Utc};
+use integrationos_api::helper::PkceVerifiers;
+use mongodb::Client;
+
+async fn verifiers(server: &TestServer) -> PkceVerifiers {
+    let db = Client::with_uri_str(&server.config.db_config.control_db_url)
+        .await
+        .unwrap()
+        .database(&server.config.db_config.control_db_name);
+
+    PkceVerifiers::new(&db, Duration::minutes(10))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_verifier_is_taken_once_before_it_expires() {
+    let server = TestServer::new(None).await;
+    let verifiers = verifiers(&server).await;
+    let now = Utc::now();
+
+    verifiers
+        .insert("state".to_string(), "verifier".to_string(), now)
+        .await
+        .unwrap();
+    verifiers
+        .insert("late".to_string(), "other".to_string(), now)
+        .await
+        .unwrap();
+    assert_eq!(verifiers.take("unknown", now).await.unwrap(), None);
+    assert_eq!(
+        verifiers
+            .take("state", now + Duration::minutes(1))
+            .await
+            .unwrap(),
+        Some("verifier".to_string())
+    );
+    assert_eq!(
+        verifiers
+            .take("state", now + Duration::minutes(1))
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        verifiers
+            .take("late", now + Duration::minutes(10))
+            .await
+            .unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_verifier_issued_by_another_instance_is_taken_once() {
+    let server = TestServer::new(None).await;
+    let issuer = verifiers(&server).await;
+    let now = Utc::now();
+
+    issuer
+        .insert("state".to_string(), "verifier".to_string(), now)
+        .await
+        .unwrap();
+
+    // Instances racing for the same state, only one of them gets the verifier
+    let (first, second) = (verifiers(&server).await, verifiers(&server).await);
+    let (first, second) = tokio::join!(first.take("state", now), second.take("state", now));
+    let mut taken = vec![first.unwrap(), second.unwrap()];
+    taken.sort();
+    assert_eq!(taken, vec![None, Some("verifier".to_string())]);
+}
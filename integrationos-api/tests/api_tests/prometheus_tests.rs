@@ -0,0 +1,148 @@
+use crate::test_server::TestServer;
+use fake::{Fake, Faker};
+use http::{header::CONTENT_TYPE, Method, StatusCode};
+use integrationos_api::logic::connection_model_definition::CreateRequest as CreateConnectionModelDefinitionRequest;
+use integrationos_domain::{
+    api_model_config::{AuthMethod, SamplesInput, SchemasInput},
+    connection_model_definition::CrudAction,
+    environment::Environment,
+};
+use mockito::Server;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+use tokio::net::TcpListener;
+
+#[tokio::test]
+async fn test_metrics_endpoint_exposes_the_expected_families() {
+    let metrics_port = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let metrics_url = format!("http://127.0.0.1:{metrics_port}/metrics");
+    let mut server = TestServer::new_with_config(
+        None,
+        HashMap::from([(
+            "METRICS_ADDRESS".to_string(),
+            format!("127.0.0.1:{metrics_port}"),
+        )]),
+    )
+    .await;
+    let (connection, conn_def) = server.create_connection(Environment::Live).await;
+
+    let mut mock_server = Server::new_async().await;
+    mock_server
+        .mock("GET", "/customers")
+        .with_status(200)
+        .with_body("{}")
+        .create();
+    let payload = CreateConnectionModelDefinitionRequest {
+        id: None,
+        connection_platform: connection.platform.to_string(),
+        connection_definition_id: conn_def.id,
+        platform_version: conn_def.record_metadata.version.to_string(),
+        title: Faker.fake(),
+        name: Faker.fake(),
+        model_name: Faker.fake(),
+        action_name: CrudAction::Create,
+        base_url: mock_server.url(),
+        path: "customers".to_string(),
+        auth_method: AuthMethod::None,
+        http_method: http::Method::GET,
+        headers: None,
+        query_params: None,
+        extractor_config: None,
+        version: "1.0.0".parse().unwrap(),
+        schemas: SchemasInput {
+            headers: None,
+            query_params: None,
+            path_params: None,
+            body: None,
+        },
+        samples: SamplesInput {
+            headers: None,
+            query_params: None,
+            path_params: None,
+            body: None,
+        },
+        paths: None,
+        responses: vec![],
+        is_default_crud_mapping: None,
+        test_connection_payload: None,
+        mapping: None,
+        supported: Some(true),
+        active: Some(true),
+        shadow: None,
+        streaming: None,
+        timeout: None,
+        error_format: None,
+        pagination_limits: None,
+    };
+    let res = server
+        .send_request::<Value, Value>(
+            "v1/connection-model-definitions",
+            Method::POST,
+            Some(&server.live_key),
+            Some(&serde_json::to_value(&payload).unwrap()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let res = server
+        .send_request_with_headers::<Value, Value>(
+            "v1/passthrough/customers",
+            Method::GET,
+            Some(&server.live_key),
+            None,
+            Some(BTreeMap::from([
+                (CONTENT_TYPE.to_string(), "application/json".to_string()),
+                (
+                    "x-integrationos-connection-key".to_string(),
+                    connection.key.to_string(),
+                ),
+            ])),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    // Metrics of calls are recorded once the metric is received, after the response
+    let mut scraped = String::new();
+    for _ in 0..50 {
+        let res = reqwest::get(&metrics_url).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        scraped = res.text().await.unwrap();
+        if scraped.contains(&format!("connection=\"{}\"", connection.key)) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    for family in [
+        "# TYPE http_requests_total counter",
+        "# TYPE http_request_duration_seconds histogram",
+        "# TYPE connection_calls_total counter",
+        "# TYPE event_channel_depth gauge",
+    ] {
+        assert!(
+            scraped.contains(family),
+            "{family} is missing from {scraped}"
+        );
+    }
+    assert!(scraped.contains(&format!(
+        "connection_calls_total{{platform=\"{}\",connection=\"{}\",type=\"passthrough\"}} 1",
+        connection.platform, connection.key
+    )));
+
+    // Metrics aren't served along the API
+    let res = server
+        .send_request::<Value, Value>("metrics", Method::GET, None, None)
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::NOT_FOUND);
+}
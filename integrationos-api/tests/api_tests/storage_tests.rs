@@ -0,0 +1,55 @@
+use crate::test_server::TestServer;
+use http::{Method, StatusCode};
+use integrationos_api::logic::storage::{CompactResult, StorageStats};
+use integrationos_domain::environment::Environment;
+use serde_json::Value;
+
+#[tokio::test]
+async fn test_storage_stats_of_seeded_collections() {
+    let mut server = TestServer::new(None).await;
+    server.create_connection(Environment::Live).await;
+
+    let res = server
+        .send_request::<Value, StorageStats>("v1/storage", Method::GET, None, None)
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    for name in ["connections", "connection-definitions", "event-access"] {
+        let stats = res
+            .data
+            .collections
+            .iter()
+            .find(|stats| stats.name == name)
+            .unwrap_or_else(|| panic!("Missing stats for {name}"));
+
+        assert!(stats.count >= 1);
+        assert!(stats.size > 0);
+        assert!(stats.storage_size > 0);
+        assert!(stats.total_index_size > 0);
+    }
+}
+
+#[tokio::test]
+async fn test_compact_collection() {
+    let mut server = TestServer::new(None).await;
+    server.create_connection(Environment::Live).await;
+
+    let res = server
+        .send_request::<Value, CompactResult>(
+            "v1/storage/connections/compact",
+            Method::POST,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    assert_eq!(res.data.name, "connections");
+
+    let res = server
+        .send_request::<Value, Value>("v1/storage/unknown/compact", Method::POST, None, None)
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,139 @@
+use crate::test_server::TestServer;
+use integrationos_domain::algebra::{MongoStore, UpsertCounts, MAX_UPSERT_BATCH_SIZE};
+use mongodb::{
+    bson::{doc, Document},
+    Client,
+};
+
+async fn store(server: &TestServer) -> MongoStore<Document> {
+    let db = Client::with_uri_str(&server.config.db_config.control_db_url)
+        .await
+        .unwrap()
+        .database(&server.config.db_config.control_db_name);
+
+    MongoStore {
+        collection: db.collection("bulk-upserts"),
+    }
+}
+
+fn item(id: usize, value: i32) -> Document {
+    doc! { "_id": format!("item-{id}"), "value": value }
+}
+
+fn key(item: &Document) -> Document {
+    doc! { "_id": item.get_str("_id").unwrap() }
+}
+
+#[tokio::test]
+async fn test_bulk_upsert_inserts_and_updates() {
+    let server = TestServer::new(None).await;
+    let store = store(&server).await;
+
+    let items = (0..2).map(|i| item(i, 0)).collect::<Vec<_>>();
+    assert_eq!(
+        store.bulk_upsert(&items, key).await.unwrap(),
+        UpsertCounts {
+            inserted: 2,
+            modified: 0
+        }
+    );
+
+    let items = (0..2).map(|i| item(i, 1)).collect::<Vec<_>>();
+    assert_eq!(
+        store.bulk_upsert(&items, key).await.unwrap(),
+        UpsertCounts {
+            inserted: 0,
+            modified: 2
+        }
+    );
+
+    // Unchanged items are matched but not modified
+    let items = vec![item(0, 1), item(1, 2), item(2, 0)];
+    assert_eq!(
+        store.bulk_upsert(&items, key).await.unwrap(),
+        UpsertCounts {
+            inserted: 1,
+            modified: 1
+        }
+    );
+
+    let stored = store.get_many(None, None, None, None, None).await.unwrap();
+    let mut values = stored
+        .iter()
+        .map(|doc| (doc.get_str("_id").unwrap(), doc.get_i32("value").unwrap()))
+        .collect::<Vec<_>>();
+    values.sort();
+    assert_eq!(values, vec![("item-0", 1), ("item-1", 2), ("item-2", 0)]);
+}
+
+#[tokio::test]
+async fn test_bulk_upsert_chunks_large_inputs() {
+    let server = TestServer::new(None).await;
+    let store = store(&server).await;
+
+    let count = MAX_UPSERT_BATCH_SIZE * 2 + 1;
+    let items = (0..count).map(|i| item(i, 0)).collect::<Vec<_>>();
+    assert_eq!(
+        store.bulk_upsert(&items, key).await.unwrap(),
+        UpsertCounts {
+            inserted: count as u64,
+            modified: 0
+        }
+    );
+    assert_eq!(store.count(doc! {}, None).await.unwrap(), count as u64);
+
+    let updates = (0..count)
+        .map(|i| (key(&item(i, 0)), doc! { "$inc": { "value": 1 } }))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        store.bulk_update_upsert(updates).await.unwrap(),
+        UpsertCounts {
+            inserted: 0,
+            modified: count as u64
+        }
+    );
+    assert_eq!(
+        store.count(doc! { "value": 1 }, None).await.unwrap(),
+        count as u64
+    );
+}
+
+#[tokio::test]
+async fn test_paginate_visits_every_row_once_despite_ties() {
+    let server = TestServer::new(None).await;
+    let store = store(&server).await;
+
+    // Rows share `createdAt` by threes, `_id` orders each group
+    let items = (0..10)
+        .map(|i| doc! { "_id": format!("item-{i}"), "createdAt": (i / 3) as i64 })
+        .collect::<Vec<_>>();
+    store.bulk_upsert(&items, key).await.unwrap();
+
+    let mut pages = vec![];
+    let mut cursor = None;
+    loop {
+        let page = store
+            .paginate(doc! {}, doc! { "createdAt": -1 }, 4, cursor.as_deref())
+            .await
+            .unwrap();
+        assert_eq!(page.total, 10);
+        pages.push(page.rows);
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    let ids = |pages: &[Vec<Document>]| {
+        pages
+            .iter()
+            .map(|rows| {
+                rows.iter()
+                    .map(|row| row.get_str("_id").unwrap().trim_start_matches("item-"))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ids(&pages), ["9,8,7,6", "5,4,3,2", "1,0"]);
+}
@@ -105,6 +105,10 @@ impl SecretExt for MockSecretsClient {
             None,
         ))
     }
+
+    async fn ping(&self) -> Result<(), IntegrationOSError> {
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -115,6 +119,28 @@ pub struct ApiResponse<T: DeserializeOwned = Value> {
 
 impl TestServer {
     pub async fn new(db_name: Option<String>) -> Self {
+        Self::new_with_config(db_name, HashMap::new()).await
+    }
+
+    /// Server whose configuration has the `overrides` applied over the one of the tests
+    pub async fn new_with_config(
+        db_name: Option<String>,
+        overrides: HashMap<String, String>,
+    ) -> Self {
+        Self::init(db_name, overrides, false).await
+    }
+
+    /// Server whose tenant has its events saved in a collection of its own
+    #[allow(dead_code)]
+    pub async fn new_with_dedicated_events(db_name: Option<String>) -> Self {
+        Self::init(db_name, HashMap::new(), true).await
+    }
+
+    async fn init(
+        db_name: Option<String>,
+        overrides: HashMap<String, String>,
+        dedicated_events: bool,
+    ) -> Self {
         // init tracing once
         TRACING.get_or_init(|| {
             let filter = EnvFilter::builder()
@@ -146,7 +172,10 @@ impl TestServer {
         let db_name = db_name.unwrap_or_else(|| Uuid::new_v4().to_string());
         let token_secret = "Qsfb9YUkdjwUULX.u96HdTCX4q7GuB".to_string();
 
-        let config = ConnectionsConfig::init_from_hashmap(&HashMap::from([
+        let data: AccessKeyData = Faker.fake();
+        let ownership_id = data.id.clone();
+
+        let mut config = HashMap::from([
             ("CONTROL_DATABASE_URL".to_string(), db.clone()),
             ("CONTROL_DATABASE_NAME".to_string(), db_name.clone()),
             ("CONTEXT_DATABASE_URL".to_string(), db.clone()),
@@ -160,21 +189,24 @@ impl TestServer {
             ("OPENAI_API_KEY".to_string(), "".to_string()),
             ("MOCK_LLM".to_string(), "true".to_string()),
             ("CACHE_SIZE".to_string(), "0".to_string()),
+            ("EVENT_SAVE_TIMEOUT_SECS".to_string(), "1".to_string()),
             ("REDIS_URL".to_string(), redis),
             ("JWT_SECRET".to_string(), token_secret.clone()),
             (
                 "SECRETS_SERVICE_PROVIDER".to_string(),
                 "ios-kms".to_string(),
             ),
-        ]))
-        .unwrap();
+        ]);
+        if dedicated_events {
+            config.insert("DEDICATED_EVENT_TENANTS".to_string(), ownership_id.clone());
+        }
+        config.extend(overrides);
+        let config = ConnectionsConfig::init_from_hashmap(&config).unwrap();
 
         let secrets_client = Arc::new(MockSecretsClient::default());
 
-        let data: AccessKeyData = Faker.fake();
         // this is missing a setup part
 
-        let ownership_id = data.id.clone();
         let prefix = AccessKeyPrefix {
             environment: Environment::Live,
             event_type: EventType::SecretKey,
@@ -209,13 +241,15 @@ impl TestServer {
         let mut live: EventAccess = Faker.fake();
         live.throughput = 500;
         live.ownership.id = ownership_id.clone().into();
+        live.ownership.client_id = ownership_id.clone();
         live.environment = Environment::Live;
         live.record_metadata = Default::default();
         live.access_key = live_encrypted_key.to_string();
 
         let mut test: EventAccess = Faker.fake();
         test.throughput = 500;
-        test.ownership.id = ownership_id.into();
+        test.ownership.id = ownership_id.clone().into();
+        test.ownership.client_id = ownership_id;
         test.environment = Environment::Test;
         test.record_metadata = Default::default();
         test.access_key = test_encrypted_key.to_string();
@@ -299,6 +333,25 @@ impl TestServer {
         payload: Option<&T>,
         headers: Option<BTreeMap<String, String>>,
     ) -> Result<ApiResponse<U>> {
+        let res = self
+            .send_raw_request(path, method, key, payload, headers)
+            .await?;
+
+        Ok(ApiResponse {
+            code: res.status(),
+            data: res.json().await?,
+        })
+    }
+
+    /// Sends a request and returns its response as is, for the tests reading its headers
+    pub async fn send_raw_request<T: Serialize>(
+        &self,
+        path: &str,
+        method: http::Method,
+        key: Option<&str>,
+        payload: Option<&T>,
+        headers: Option<BTreeMap<String, String>>,
+    ) -> Result<reqwest::Response> {
         let mut req = self
             .client
             .request(method, format!("http://localhost:{}/{path}", self.port));
@@ -314,12 +367,27 @@ impl TestServer {
             }
         }
 
-        let res = req.send().await?;
+        Ok(req.send().await?)
+    }
 
-        Ok(ApiResponse {
-            code: res.status(),
-            data: res.json().await?,
-        })
+    /// Sends a passthrough request for the connection with the given raw body
+    #[allow(dead_code)]
+    pub async fn send_raw_body(
+        &self,
+        path: &str,
+        method: http::Method,
+        key: &str,
+        connection_key: &str,
+        body: reqwest::Body,
+    ) -> Result<reqwest::Response> {
+        Ok(self
+            .client
+            .request(method, format!("http://localhost:{}/{path}", self.port))
+            .header(&self.config.headers.auth_header, key)
+            .header(&self.config.headers.connection_header, connection_key)
+            .body(body)
+            .send()
+            .await?)
     }
 
     pub async fn send_request_with_auth_headers<T: Serialize, U: DeserializeOwned>(
@@ -463,6 +531,9 @@ impl TestServer {
             group: access_key.data.group.clone(),
             auth_form_data: HashMap::from([(template, bearer_key.to_string())]),
             active: true,
+            mode: Default::default(),
+            webhook_secret: None,
+            credentials_expire_at: None,
         };
 
         let res = self
@@ -526,6 +597,11 @@ impl TestServer {
             }),
             supported: Some(true),
             active: Some(true),
+            shadow: None,
+            streaming: None,
+            timeout: None,
+            error_format: None,
+            pagination_limits: None,
         };
 
         let res = self
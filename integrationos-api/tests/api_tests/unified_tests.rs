@@ -6,9 +6,10 @@ use http::{
     Method, StatusCode,
 };
 use integrationos_api::logic::{
+    connection::ConnectionObservability,
     connection_model_definition::CreateRequest as CreateConnectionModelDefinitionRequest,
     connection_model_schema::CreateRequest as CreateConnectionModelSchemaRequest,
-    metrics::MetricResponse,
+    correlation::CorrelationResponse, metrics::MetricResponse,
 };
 use integrationos_domain::{
     api_model_config::{AuthMethod, SamplesInput, SchemasInput},
@@ -18,6 +19,7 @@ use integrationos_domain::{
     id::{prefix::IdPrefix, Id},
     SanitizedConnection,
 };
+use integrationos_unified::{rate_limit::RateLimitState, unified::DEFAULT_MAX_RETRY_AFTER_SECS};
 use mockito::Mock;
 use serde_json::Value;
 use std::time::Duration;
@@ -523,17 +525,349 @@ async fn test_unified_metrics() {
     assert_eq!(res.data.count, 0);
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_unified_event_and_metric_share_correlation_id() {
+    let server = TestServer::new(None).await;
+    assert_event_and_metric_share_correlation_id(server).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_correlation_reads_events_of_dedicated_tenant() {
+    let server = TestServer::new_with_dedicated_events(None).await;
+    assert_event_and_metric_share_correlation_id(server).await;
+}
+
+async fn assert_event_and_metric_share_correlation_id(mut server: TestServer) {
+    let (connection, _) = server.create_connection(Environment::Live).await;
+
+    let name = "Model".to_string();
+
+    let mock = create_connection_model_definition(
+        &mut server,
+        &connection,
+        CrudMapping {
+            action: CrudAction::Create,
+            common_model_name: name.clone(),
+            from_common_model: None,
+            to_common_model: None,
+        },
+    )
+    .await;
+
+    let payload: Value = Faker.fake();
+    let correlation_id = Id::now(IdPrefix::Event).to_string();
+    let correlation_header = server.config.headers.correlation_id_header.clone();
+
+    let res = server
+        .send_request_with_headers::<Value, Value>(
+            &format!("v1/unified/{}", name.to_lowercase()),
+            Method::POST,
+            Some(&server.live_key),
+            Some(&payload),
+            Some(
+                vec![
+                    (CONTENT_TYPE.to_string(), "application/json".to_string()),
+                    (
+                        "x-integrationos-connection-key".to_string(),
+                        connection.key.to_string(),
+                    ),
+                    (correlation_header, correlation_id.clone()),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+        )
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(res.code, StatusCode::OK);
+    mock.assert_async().await;
+
+    // Events are saved in batches, wait for the batch holding the event to be saved
+    let mut correlation = None;
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let res = server
+            .send_request::<(), CorrelationResponse>(
+                &format!("v1/correlations/{correlation_id}"),
+                Method::GET,
+                Some(&server.live_key),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.code, StatusCode::OK);
+
+        if !res.data.events.is_empty() && !res.data.metrics.is_empty() {
+            correlation = Some(res.data);
+            break;
+        }
+    }
+
+    let correlation = correlation.expect("Event and metric should be retrievable");
+    assert_eq!(correlation.events.len(), 1);
+    assert_eq!(correlation.metrics.len(), 1);
+    assert_eq!(
+        correlation.events[0].correlation_id.as_deref(),
+        Some(correlation_id.as_str())
+    );
+    assert_eq!(correlation.metrics[0].correlation_id, correlation_id);
+    assert_eq!(correlation.metrics[0].r#type, "unified");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_generated_request_id_is_returned_and_carried_by_metric() {
+    let mut server = TestServer::new(None).await;
+    let (connection, _) = server.create_connection(Environment::Live).await;
+
+    let name = "Model".to_string();
+
+    let mock = create_connection_model_definition(
+        &mut server,
+        &connection,
+        CrudMapping {
+            action: CrudAction::Create,
+            common_model_name: name.clone(),
+            from_common_model: None,
+            to_common_model: None,
+        },
+    )
+    .await;
+
+    let payload: Value = Faker.fake();
+
+    let res = server
+        .send_raw_request(
+            &format!("v1/unified/{}", name.to_lowercase()),
+            Method::POST,
+            Some(&server.live_key),
+            Some(&payload),
+            Some(
+                vec![
+                    (CONTENT_TYPE.to_string(), "application/json".to_string()),
+                    (
+                        "x-integrationos-connection-key".to_string(),
+                        connection.key.to_string(),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+        )
+        .await
+        .expect("Failed to send request");
+
+    assert_eq!(res.status(), StatusCode::OK);
+    mock.assert_async().await;
+
+    let request_id = res
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .expect("Response should carry the request id")
+        .to_owned();
+    assert_eq!(
+        res.headers()[server.config.headers.correlation_id_header.as_str()],
+        request_id.as_str()
+    );
+
+    let mut metrics = vec![];
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let res = server
+            .send_request::<(), CorrelationResponse>(
+                &format!("v1/correlations/{request_id}"),
+                Method::GET,
+                Some(&server.live_key),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.code, StatusCode::OK);
+
+        if !res.data.metrics.is_empty() {
+            metrics = res.data.metrics;
+            break;
+        }
+    }
+
+    assert_eq!(metrics.len(), 1);
+    assert_eq!(metrics[0].correlation_id, request_id);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_connection_observability_of_unified_calls() {
+    let mut server = TestServer::new(None).await;
+    let (connection, _) = server.create_connection(Environment::Live).await;
+
+    let name = "Model".to_string();
+
+    // The mock keeps answering once its expected hit is reached
+    create_connection_model_definition(
+        &mut server,
+        &connection,
+        CrudMapping {
+            action: CrudAction::Create,
+            common_model_name: name.clone(),
+            from_common_model: None,
+            to_common_model: None,
+        },
+    )
+    .await;
+
+    for _ in 0..3 {
+        let payload: Value = Faker.fake();
+        let res = server
+            .send_request_with_headers::<Value, Value>(
+                &format!("v1/unified/{}", name.to_lowercase()),
+                Method::POST,
+                Some(&server.live_key),
+                Some(&payload),
+                Some(
+                    vec![
+                        (CONTENT_TYPE.to_string(), "application/json".to_string()),
+                        (
+                            "x-integrationos-connection-key".to_string(),
+                            connection.key.to_string(),
+                        ),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+            )
+            .await
+            .expect("Failed to send request");
+        assert_eq!(res.code, StatusCode::OK);
+    }
+
+    let res = server
+        .send_request::<(), ConnectionObservability>(
+            &format!("v1/connections/{}/observability", connection.id),
+            Method::GET,
+            Some(&server.live_key),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let observability = res.data;
+    assert_eq!(observability.connection_id, connection.id);
+    assert_eq!(observability.recent_calls.calls, 3);
+    assert_eq!(observability.recent_calls.errors, 0);
+    assert_eq!(observability.recent_calls.error_rate, 0.0);
+    let latency = observability
+        .recent_calls
+        .latency_millis
+        .expect("Latency of the calls should be reported");
+    assert!(latency.p50 <= latency.p90 && latency.p90 <= latency.p99);
+    assert!(latency.p99 <= latency.max);
+    assert_eq!(observability.retry_policy.max_attempts, 1);
+    assert!(observability
+        .timeouts
+        .iter()
+        .any(|timeout| timeout.action_name == CrudAction::Create && timeout.timeout.is_none()));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_retry_after_of_platform_pauses_the_connection() {
+    let mut server = TestServer::new(None).await;
+    let (connection, _) = server.create_connection(Environment::Live).await;
+
+    let name = "Model".to_string();
+
+    let mock = create_connection_model_definition_answering(
+        &mut server,
+        &connection,
+        CrudMapping {
+            action: CrudAction::Create,
+            common_model_name: name.clone(),
+            from_common_model: None,
+            to_common_model: None,
+        },
+        429,
+        &[("retry-after", "86400")],
+        "{}".to_string(),
+    )
+    .await;
+
+    // The second call is held off by the connection rate limiter, never reaching the platform.
+    // The pause is capped however long the platform asks for.
+    for _ in 0..2 {
+        let payload: Value = Faker.fake();
+        let res = server
+            .send_request_with_headers::<Value, Value>(
+                &format!("v1/unified/{}", name.to_lowercase()),
+                Method::POST,
+                Some(&server.live_key),
+                Some(&payload),
+                Some(
+                    vec![
+                        (CONTENT_TYPE.to_string(), "application/json".to_string()),
+                        (
+                            "x-integrationos-connection-key".to_string(),
+                            connection.key.to_string(),
+                        ),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+            )
+            .await
+            .expect("Failed to send request");
+        assert_eq!(res.code, StatusCode::TOO_MANY_REQUESTS);
+    }
+    mock.assert_async().await;
+
+    let res = server
+        .send_request::<(), RateLimitState>(
+            &format!("v1/connections/{}/rate-limit", connection.id),
+            Method::GET,
+            Some(&server.live_key),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    assert_eq!(res.data.available, 0);
+    let max_pause = DEFAULT_MAX_RETRY_AFTER_SECS as i64 * 1000;
+    let now = Utc::now().timestamp_millis();
+    assert!(res.data.refill_at >= now + max_pause - 60_000);
+    assert!(res.data.refill_at <= now + max_pause);
+}
+
 async fn create_connection_model_definition(
     server: &mut TestServer,
     connection: &SanitizedConnection,
     mapping: CrudMapping,
+) -> Mock {
+    let response_body = format!("{{\"id\": \"{}\"}}", Faker.fake::<String>());
+    create_connection_model_definition_answering(
+        server,
+        connection,
+        mapping,
+        200,
+        &[],
+        response_body,
+    )
+    .await
+}
+
+/// Same as [`create_connection_model_definition`], the platform answering with `status`,
+/// `headers` and `body`
+async fn create_connection_model_definition_answering(
+    server: &mut TestServer,
+    connection: &SanitizedConnection,
+    mapping: CrudMapping,
+    status: usize,
+    headers: &[(&'static str, &str)],
+    body: String,
 ) -> Mock {
     let secret_key = Faker.fake::<String>();
     let url_path: String = DirPath(EN).fake();
     let path: String = Faker.fake();
-    let response_body = format!("{{\"id\": \"{}\"}}", Faker.fake::<String>());
 
-    let mock = server
+    let mut mock = server
         .mock_server
         .mock("GET", format!("{url_path}/{path}").as_str())
         .match_header(
@@ -541,10 +875,12 @@ async fn create_connection_model_definition(
             format!("Bearer {secret_key}").as_str(),
         )
         .expect(1)
-        .with_status(200)
-        .with_body(response_body.clone())
-        .create_async()
-        .await;
+        .with_status(status)
+        .with_body(body);
+    for (name, value) in headers {
+        mock = mock.with_header(*name, value);
+    }
+    let mock = mock.create_async().await;
 
     let create_model_definition_payload = CreateConnectionModelDefinitionRequest {
         id: None,
@@ -584,6 +920,11 @@ async fn create_connection_model_definition(
         mapping: Some(mapping.clone()),
         supported: Some(true),
         active: Some(true),
+        shadow: None,
+        streaming: None,
+        timeout: None,
+        error_format: None,
+        pagination_limits: None,
     };
 
     let create_model_definition_response = server
@@ -0,0 +1,224 @@
+use crate::test_server::{ApiResponse, TestServer};
+use http::{Method, StatusCode};
+use integrationos_api::logic::{
+    events::{EventDenylistPayload, FlushEventBufferResponse},
+    ReadResponse,
+};
+use integrationos_domain::{
+    algebra::MongoStore, environment::Environment, event_response::EventResponse,
+    event_state::EventState, webhook::sign_webhook, Store,
+};
+use mongodb::{
+    bson::{doc, Document},
+    Client,
+};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+
+#[tokio::test]
+async fn test_signed_webhook_is_ingested_and_tampered_one_rejected() {
+    let mut server = TestServer::new(None).await;
+    let (connection, _) = server.create_connection(Environment::Live).await;
+    let path = format!("v1/public/webhooks/{}", connection.id);
+    let payload = json!({ "type": "invoice.paid", "amount": 4200 });
+
+    let res = server
+        .send_request::<Value, Value>(&path, Method::POST, None, Some(&payload))
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::UNAUTHORIZED);
+
+    // The mock secrets client resolves every secret to "secret"
+    let res = server
+        .send_request::<Value, Value>(
+            &format!("v1/connections/{}", connection.id),
+            Method::PATCH,
+            Some(&server.live_key),
+            Some(&json!({ "webhookSecret": "secret" })),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let signature = sign_webhook("secret", &serde_json::to_vec(&payload).unwrap()).unwrap();
+    let headers = BTreeMap::from([(
+        server.config.headers.webhook_signature_header.clone(),
+        signature,
+    )]);
+
+    let res = server
+        .send_request_with_headers::<Value, EventResponse>(
+            &path,
+            Method::POST,
+            None,
+            Some(&payload),
+            Some(headers.clone()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let tampered = json!({ "type": "invoice.paid", "amount": 9900 });
+    let res = server
+        .send_request_with_headers::<Value, Value>(
+            &path,
+            Method::POST,
+            None,
+            Some(&tampered),
+            Some(headers),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_denylisted_webhook_events_are_dropped() {
+    let mut server = TestServer::new(None).await;
+    let (connection, _) = server.create_connection(Environment::Live).await;
+    let path = format!("v1/public/webhooks/{}", connection.id);
+
+    let res = server
+        .send_request::<Value, Value>(
+            &format!("v1/connections/{}", connection.id),
+            Method::PATCH,
+            Some(&server.live_key),
+            Some(&json!({ "webhookSecret": "secret" })),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let payload = json!({ "type": "invoice.paid", "amount": 4200 });
+    let signature = sign_webhook("secret", &serde_json::to_vec(&payload).unwrap()).unwrap();
+    let headers = BTreeMap::from([(
+        server.config.headers.webhook_signature_header.clone(),
+        signature,
+    )]);
+    let receive = || {
+        server.send_request_with_headers::<Value, EventResponse>(
+            &path,
+            Method::POST,
+            None,
+            Some(&payload),
+            Some(headers.clone()),
+        )
+    };
+
+    let event_type = format!("{}::webhook-received", connection.platform);
+    let res = deny(&server, &[&event_type, "other::event"]).await;
+    assert_eq!(res.code, StatusCode::OK);
+    assert!(res.data.event_types.contains(&event_type));
+
+    let res = receive().await.unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    assert_eq!(res.data.status, EventState::Dropped);
+
+    // The updated denylist applies to the very next event
+    let res = deny(&server, &["other::event"]).await;
+    assert_eq!(res.code, StatusCode::OK);
+
+    let res = receive().await.unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    assert_eq!(res.data.status, EventState::Acknowledged);
+
+    let res = server
+        .send_request::<(), EventDenylistPayload>("v1/events/denylist", Method::GET, None, None)
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    assert_eq!(
+        res.data.event_types.into_iter().collect::<Vec<_>>(),
+        vec!["other::event".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn test_webhook_event_fields_are_stored_encrypted_and_read_decrypted() {
+    let mut server = TestServer::new_with_config(
+        None,
+        HashMap::from([
+            (
+                "ENCRYPTED_EVENT_FIELDS".to_string(),
+                "/customer".to_string(),
+            ),
+            // Buffered events are only saved on request within the test
+            ("EVENT_SAVE_TIMEOUT_SECS".to_string(), "3600".to_string()),
+        ]),
+    )
+    .await;
+    let (connection, _) = server.create_connection(Environment::Live).await;
+
+    let res = server
+        .send_request::<Value, Value>(
+            &format!("v1/connections/{}", connection.id),
+            Method::PATCH,
+            Some(&server.live_key),
+            Some(&json!({ "webhookSecret": "secret" })),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let payload = json!({
+        "type": "invoice.paid",
+        "customer": { "email": "jane@example.com" },
+        "amount": 4200
+    });
+    let signature = sign_webhook("secret", &serde_json::to_vec(&payload).unwrap()).unwrap();
+    let res = server
+        .send_request_with_headers::<Value, EventResponse>(
+            &format!("v1/public/webhooks/{}", connection.id),
+            Method::POST,
+            None,
+            Some(&payload),
+            Some(BTreeMap::from([(
+                server.config.headers.webhook_signature_header.clone(),
+                signature,
+            )])),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+
+    let res = server
+        .send_request::<(), FlushEventBufferResponse>("v1/events/flush", Method::POST, None, None)
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    assert_eq!(res.data.flushed, 1);
+
+    let db = Client::with_uri_str(&server.config.db_config.event_db_url)
+        .await
+        .unwrap()
+        .database(&server.config.db_config.event_db_name);
+    let store: MongoStore<Document> = MongoStore::new(&db, &Store::Events).await.unwrap();
+    let stored = store.get_one(doc! {}).await.unwrap().unwrap();
+    let stored: Value = serde_json::from_str(stored.get_str("body").unwrap()).unwrap();
+    assert!(stored["customer"]["$encrypted"].is_string());
+    assert_eq!(stored["amount"], payload["amount"]);
+
+    let res = server
+        .send_request::<Value, Value>("v1/events", Method::GET, Some(&server.live_key), None)
+        .await
+        .unwrap();
+    assert_eq!(res.code, StatusCode::OK);
+    let res: ReadResponse<Value> = serde_json::from_value(res.data).unwrap();
+    assert_eq!(res.rows.len(), 1);
+    let body: Value = serde_json::from_str(res.rows[0]["body"].as_str().unwrap()).unwrap();
+    assert_eq!(body, payload);
+}
+
+async fn deny(server: &TestServer, event_types: &[&str]) -> ApiResponse<EventDenylistPayload> {
+    server
+        .send_request::<EventDenylistPayload, EventDenylistPayload>(
+            "v1/events/denylist",
+            Method::PUT,
+            None,
+            Some(&EventDenylistPayload {
+                event_types: event_types.iter().map(ToString::to_string).collect(),
+            }),
+        )
+        .await
+        .unwrap()
+}
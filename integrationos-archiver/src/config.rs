@@ -1,6 +1,9 @@
-use crate::storage::StorageProvider;
+use crate::limiter::RunLimitBehaviour;
+use crate::report::ReportFormat;
+use crate::storage::{chunking::ChunkLimits, compression::Compression, StorageProvider};
 use envconfig::Envconfig;
-use integrationos_domain::database::DatabaseConfig;
+use integrationos_domain::{database::DatabaseConfig, secrets::SecretsConfig};
+use secrecy::SecretString;
 use std::fmt::{Display, Formatter};
 use strum::{AsRefStr, EnumString};
 
@@ -9,14 +12,28 @@ use strum::{AsRefStr, EnumString};
 pub enum Mode {
     Dump,
     DumpDelete,
+    /// Restores the archive of `ARCHIVE_REFERENCE`, or the latest archive without one
     Restore,
     NoOp,
+    /// Prints the timeline of the run of `ARCHIVE_REFERENCE` instead of running the archiver
+    Report,
+    /// Re-encrypts the secrets of the connections and settings with `NEW_IOS_CRYPTO_SECRET`
+    RotateSecrets,
 }
 
 #[derive(Envconfig, Clone)]
 pub struct ArchiverConfig {
     #[envconfig(nested = true)]
     pub db_config: DatabaseConfig,
+    /// Secrets are read with these in rotate-secrets mode
+    #[envconfig(nested = true)]
+    pub secrets_config: SecretsConfig,
+    /// Key secrets are encrypted with in rotate-secrets mode
+    #[envconfig(from = "NEW_IOS_CRYPTO_SECRET")]
+    pub new_ios_crypto_secret: Option<SecretString>,
+    /// Counts the secrets rotate-secrets mode would rewrite instead of rewriting them
+    #[envconfig(from = "DRY_RUN", default = "false")]
+    pub dry_run: bool,
     #[envconfig(from = "EVENT_COLLECTION_NAME", default = "clients")]
     pub event_collection_name: String,
     #[envconfig(from = "GS_STORAGE_BUCKET", default = "integrationos-zsk")]
@@ -25,6 +42,15 @@ pub struct ArchiverConfig {
     pub gs_storage_uri: String,
     #[envconfig(from = "STORAGE_PROVIDER", default = "google-cloud")]
     pub storage_provider: StorageProvider,
+    /// Codec the files of new dumps are compressed with, restores use the codec of their dump
+    #[envconfig(from = "DUMP_COMPRESSION", default = "gzip")]
+    pub dump_compression: Compression,
+    /// Largest number of documents in a chunk of a dump, dumps are split into chunks when set
+    #[envconfig(from = "ARCHIVE_CHUNK_MAX_DOCS")]
+    pub archive_chunk_max_docs: Option<u64>,
+    /// Largest size of a chunk of a dump before compression, in bytes
+    #[envconfig(from = "ARCHIVE_CHUNK_MAX_BYTES")]
+    pub archive_chunk_max_bytes: Option<u64>,
     #[envconfig(from = "MAX_RETRIES", default = "3")]
     pub max_retries: u32,
     #[envconfig(from = "READ_BUFFER_SIZE_BYTES", default = "262144")]
@@ -33,6 +59,32 @@ pub struct ArchiverConfig {
     pub processing_chunk_timeout_secs: u64,
     #[envconfig(from = "MODE", default = "dump")]
     pub mode: Mode,
+    #[envconfig(from = "MAX_CONCURRENT_RUNS", default = "1")]
+    pub max_concurrent_runs: usize,
+    #[envconfig(from = "RUN_LIMIT_BEHAVIOUR", default = "queue")]
+    pub run_limit_behaviour: RunLimitBehaviour,
+    #[envconfig(from = "RUN_QUEUE_TIMEOUT_SECS", default = "3600")]
+    pub run_queue_timeout_secs: u64,
+    #[envconfig(from = "RUN_QUEUE_POLL_INTERVAL_SECS", default = "30")]
+    pub run_queue_poll_interval_secs: u64,
+    #[envconfig(from = "RUN_STALE_AFTER_SECS", default = "86400")]
+    pub run_stale_after_secs: u64,
+    #[envconfig(from = "ARCHIVE_REFERENCE")]
+    pub archive_reference: Option<String>,
+    #[envconfig(from = "REPORT_FORMAT", default = "json")]
+    pub report_format: ReportFormat,
+    /// Push gateway the metrics of the run are pushed to once it ends, none to not push them
+    #[envconfig(from = "PROMETHEUS_PUSH_GATEWAY_URL")]
+    pub prometheus_push_gateway_url: Option<String>,
+}
+
+impl ArchiverConfig {
+    pub fn chunk_limits(&self) -> ChunkLimits {
+        ChunkLimits {
+            max_documents: self.archive_chunk_max_docs,
+            max_bytes: self.archive_chunk_max_bytes,
+        }
+    }
 }
 
 impl Display for ArchiverConfig {
@@ -41,6 +93,17 @@ impl Display for ArchiverConfig {
         writeln!(f, "GS_STORAGE_URI: {}", self.gs_storage_uri)?;
         writeln!(f, "MAX_RETRIES: {}", self.max_retries)?;
         writeln!(f, "STORAGE_PROVIDER: {}", self.storage_provider.as_ref())?;
+        writeln!(f, "DUMP_COMPRESSION: {}", self.dump_compression.as_ref())?;
+        writeln!(
+            f,
+            "ARCHIVE_CHUNK_MAX_DOCS: {:?}",
+            self.archive_chunk_max_docs
+        )?;
+        writeln!(
+            f,
+            "ARCHIVE_CHUNK_MAX_BYTES: {:?}",
+            self.archive_chunk_max_bytes
+        )?;
         writeln!(
             f,
             "PROCESSING_CHUNK_TIMEOUT_SECS: {}",
@@ -49,6 +112,29 @@ impl Display for ArchiverConfig {
         writeln!(f, "READ_BUFFER_SIZE_BYTES: {}", self.read_buffer_size)?;
         writeln!(f, "EVENT_COLLECTION_NAME: {}", self.event_collection_name)?;
         writeln!(f, "MODE: {}", self.mode.as_ref())?;
+        writeln!(f, "MAX_CONCURRENT_RUNS: {}", self.max_concurrent_runs)?;
+        writeln!(
+            f,
+            "RUN_LIMIT_BEHAVIOUR: {}",
+            self.run_limit_behaviour.as_ref()
+        )?;
+        writeln!(f, "RUN_QUEUE_TIMEOUT_SECS: {}", self.run_queue_timeout_secs)?;
+        writeln!(
+            f,
+            "RUN_QUEUE_POLL_INTERVAL_SECS: {}",
+            self.run_queue_poll_interval_secs
+        )?;
+        writeln!(f, "RUN_STALE_AFTER_SECS: {}", self.run_stale_after_secs)?;
+        writeln!(f, "ARCHIVE_REFERENCE: {:?}", self.archive_reference)?;
+        writeln!(f, "REPORT_FORMAT: {}", self.report_format.as_ref())?;
+        writeln!(
+            f,
+            "PROMETHEUS_PUSH_GATEWAY_URL: {:?}",
+            self.prometheus_push_gateway_url
+        )?;
+        writeln!(f, "NEW_IOS_CRYPTO_SECRET: ****")?;
+        writeln!(f, "DRY_RUN: {}", self.dry_run)?;
+        write!(f, "{}", self.secrets_config)?;
         write!(f, "{}", self.db_config)
     }
 }
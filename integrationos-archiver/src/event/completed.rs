@@ -20,6 +20,10 @@ impl Completed {
         }
     }
 
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
     pub fn date(&self) -> NaiveDate {
         self.completed_at.date_naive()
     }
@@ -35,4 +39,7 @@ impl EventMetadata for Completed {
     fn reference(&self) -> Id {
         self.id
     }
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.completed_at
+    }
 }
@@ -1,13 +1,40 @@
 use super::EventMetadata;
+use crate::storage::{checksum::DumpChecksum, chunking::chunk_name, compression::Compression};
+use anyhow::{anyhow, Result};
 use chrono::{DateTime, NaiveDate, Utc};
-use integrationos_domain::Id;
+use integrationos_domain::{Id, Unit};
 use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, path::Path};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Dumped {
     id: Id,
     dumped_at: DateTime<Utc>,
+    /// Number of documents in the dump, missing from the dumps made before it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    documents: Option<u64>,
+    /// Documents created before this date were dumped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_before: Option<DateTime<Utc>>,
+    /// Codec of the files of the dump, dumps made before it was recorded are gzipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    compression: Option<Compression>,
+    /// Size of the files of the dump before and after compression
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    original_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    compressed_bytes: Option<u64>,
+    /// Checksum of the documents of the dump, missing from the dumps made before it was
+    /// recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    checksum: Option<DumpChecksum>,
+    /// Chunk of the dump this event is about, out of the chunks the dump was split into.
+    /// Dumps of a single chunk, and those made before dumps were chunked, have neither.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chunk_index: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chunk_total: Option<u32>,
 }
 
 impl Dumped {
@@ -15,16 +42,167 @@ impl Dumped {
         Self {
             id,
             dumped_at: Utc::now(),
+            documents: None,
+            created_before: None,
+            compression: None,
+            original_bytes: None,
+            compressed_bytes: None,
+            checksum: None,
+            chunk_index: None,
+            chunk_total: None,
         }
     }
 
+    pub fn with_documents(mut self, documents: u64, created_before: DateTime<Utc>) -> Self {
+        self.documents = Some(documents);
+        self.created_before = Some(created_before);
+        self
+    }
+
+    pub fn with_compression(
+        mut self,
+        compression: Compression,
+        original_bytes: u64,
+        compressed_bytes: u64,
+    ) -> Self {
+        self.compression = Some(compression);
+        self.original_bytes = Some(original_bytes);
+        self.compressed_bytes = Some(compressed_bytes);
+        self
+    }
+
+    pub fn with_checksum(mut self, checksum: DumpChecksum) -> Self {
+        self.checksum = Some(checksum);
+        self
+    }
+
+    pub fn with_chunk(mut self, index: u32, total: u32) -> Self {
+        self.chunk_index = Some(index);
+        self.chunk_total = Some(total);
+        self
+    }
+
+    pub fn chunk_index(&self) -> u32 {
+        self.chunk_index.unwrap_or(0)
+    }
+
+    pub fn chunk_total(&self) -> u32 {
+        self.chunk_total.unwrap_or(1)
+    }
+
+    /// Name the files of the chunk were uploaded with, for a dump of `collection`
+    pub fn archive_name(&self, collection: &str) -> String {
+        chunk_name(collection, self.chunk_index(), self.chunk_total())
+    }
+
+    /// Orders the chunks of a dump, as read back from its `Dumped` events, checking that none
+    /// is missing so that a dump is never partially restored
+    pub fn in_order(mut chunks: Vec<Dumped>) -> Result<Vec<Dumped>> {
+        let Some(first) = chunks.first() else {
+            return Err(anyhow!("No chunk found for the dump"));
+        };
+        let (id, total) = (first.id, first.chunk_total());
+        if let Some(other) = chunks.iter().find(|chunk| chunk.chunk_total() != total) {
+            return Err(anyhow!(
+                "Dump {id} was split into both {total} and {} chunks",
+                other.chunk_total()
+            ));
+        }
+
+        chunks.sort_by_key(Dumped::chunk_index);
+        chunks.dedup_by_key(|chunk| chunk.chunk_index());
+        if let Some(last) = chunks.last().filter(|last| last.chunk_index() >= total) {
+            return Err(anyhow!(
+                "Dump {id} has a chunk {} out of its {total} chunks",
+                last.chunk_index()
+            ));
+        }
+
+        let found = chunks
+            .iter()
+            .map(Dumped::chunk_index)
+            .collect::<BTreeSet<_>>();
+        let missing = (0..total)
+            .filter(|index| !found.contains(index))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "Dump {id} is missing chunks {missing:?} of {total}, it can't be restored"
+            ));
+        }
+
+        Ok(chunks)
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression.unwrap_or(Compression::Gzip)
+    }
+
+    pub fn created_before(&self) -> Option<DateTime<Utc>> {
+        self.created_before
+    }
+
     pub fn date(&self) -> NaiveDate {
         self.dumped_at.date_naive()
     }
+
+    /// Checks that `restored` documents of the dump were found once it was restored. Dumps
+    /// which didn't record their number of documents can't be checked.
+    pub fn verify_restored(&self, restored: u64) -> Result<Unit> {
+        match self.documents {
+            Some(documents) if documents != restored => Err(anyhow!(
+                "Restored {restored} documents but {documents} were dumped with reference {}",
+                self.id
+            )),
+            Some(_) => Ok(()),
+            None => {
+                tracing::warn!(
+                    "Dump {} didn't record its number of documents, the restore can't be verified",
+                    self.id
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Checks that the decompressed BSON file at `path` holds the documents that were dumped,
+    /// before it is restored. Dumps which didn't record their checksum can't be checked.
+    pub fn verify_checksum(&self, path: &Path) -> Result<Unit> {
+        let Some(dumped) = &self.checksum else {
+            tracing::warn!(
+                "Dump {} didn't record its checksum, its integrity can't be verified",
+                self.id
+            );
+            return Ok(());
+        };
+
+        let read = DumpChecksum::of_file(path)?;
+        if &read != dumped {
+            return Err(anyhow!(
+                "Dump {} is corrupted or truncated: read {} documents with SHA-256 {} but {} documents with SHA-256 {} were dumped",
+                self.id,
+                read.documents,
+                read.sha256,
+                dumped.documents,
+                dumped.sha256
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub fn with_date(mut self, date: DateTime<Utc>) -> Self {
+        self.dumped_at = date;
+        self
+    }
 }
 
 impl EventMetadata for Dumped {
     fn reference(&self) -> Id {
         self.id
     }
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.dumped_at
+    }
 }
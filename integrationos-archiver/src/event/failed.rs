@@ -20,6 +20,10 @@ impl Failed {
         }
     }
 
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
     pub fn date(&self) -> NaiveDate {
         self.failed_at.date_naive()
     }
@@ -29,4 +33,7 @@ impl EventMetadata for Failed {
     fn reference(&self) -> Id {
         self.id
     }
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.failed_at
+    }
 }
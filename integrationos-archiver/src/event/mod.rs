@@ -1,40 +1,474 @@
 pub mod completed;
 pub mod dumped;
 pub mod failed;
+pub mod restore;
 pub mod started;
 pub mod uploaded;
 
-use chrono::NaiveDate;
+use anyhow::{anyhow, Result};
+use bson::Document;
+use chrono::{DateTime, NaiveDate, Utc};
 use completed::Completed;
 use dumped::Dumped;
 use failed::Failed;
 use integrationos_domain::Id;
-use serde::{Deserialize, Serialize};
+use restore::Restore;
+use serde::{Deserialize, Deserializer, Serialize};
 use started::Started;
+use strum::IntoStaticStr;
 use uploaded::Uploaded;
 
 pub trait EventMetadata {
     fn reference(&self) -> Id;
+    /// When the event happened, set when it is created and stored with it
+    fn timestamp(&self) -> DateTime<Utc>;
 }
 
+/// Events are stored with their kind in a `type` field. Events stored before it was added are
+/// told apart by their fields instead, see [`StoredEvent`].
 #[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(untagged)]
+#[serde(tag = "type", rename_all = "kebab-case", from = "StoredEvent")]
 pub enum Event {
     Started(Started),
     Dumped(Dumped),
     Failed(Failed),
     Uploaded(Uploaded),
+    #[serde(rename = "restored")]
+    Restore(Restore),
+    Completed(Completed),
+}
+
+enum StoredEvent {
+    Tagged(TaggedEvent),
+    Untagged(UntaggedEvent),
+}
+
+/// Events with a `type` are read by it, and fail to be read when it is not a known one. Only
+/// the legacy events without a `type` are told apart by their fields.
+impl<'de> Deserialize<'de> for StoredEvent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let document = Document::deserialize(deserializer)?;
+        let event = if document.contains_key("type") {
+            bson::from_document(document).map(StoredEvent::Tagged)
+        } else {
+            bson::from_document(document).map(StoredEvent::Untagged)
+        };
+
+        event.map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum TaggedEvent {
+    Started(Started),
+    Dumped(Dumped),
+    Failed(Failed),
+    Uploaded(Uploaded),
+    #[serde(rename = "restored")]
+    Restore(Restore),
+    Completed(Completed),
+}
+
+/// Legacy events without a `type`, the first variant whose fields match is the one read
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum UntaggedEvent {
+    Started(Started),
+    Dumped(Dumped),
+    Failed(Failed),
+    Uploaded(Uploaded),
+    Restore(Restore),
     Completed(Completed),
 }
 
+impl From<StoredEvent> for Event {
+    fn from(event: StoredEvent) -> Self {
+        match event {
+            StoredEvent::Tagged(event) => match event {
+                TaggedEvent::Started(e) => Event::Started(e),
+                TaggedEvent::Dumped(e) => Event::Dumped(e),
+                TaggedEvent::Failed(e) => Event::Failed(e),
+                TaggedEvent::Uploaded(e) => Event::Uploaded(e),
+                TaggedEvent::Restore(e) => Event::Restore(e),
+                TaggedEvent::Completed(e) => Event::Completed(e),
+            },
+            StoredEvent::Untagged(event) => match event {
+                UntaggedEvent::Started(e) => Event::Started(e),
+                UntaggedEvent::Dumped(e) => Event::Dumped(e),
+                UntaggedEvent::Failed(e) => Event::Failed(e),
+                UntaggedEvent::Uploaded(e) => Event::Uploaded(e),
+                UntaggedEvent::Restore(e) => Event::Restore(e),
+                UntaggedEvent::Completed(e) => Event::Completed(e),
+            },
+        }
+    }
+}
+
+/// Kind of an [`Event`], serialized as the `type` its events are stored with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, IntoStaticStr)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum EventType {
+    Started,
+    Dumped,
+    Failed,
+    Uploaded,
+    Restored,
+    Completed,
+}
+
 impl Event {
+    pub fn event_type(&self) -> EventType {
+        match self {
+            Event::Started(_) => EventType::Started,
+            Event::Dumped(_) => EventType::Dumped,
+            Event::Failed(_) => EventType::Failed,
+            Event::Uploaded(_) => EventType::Uploaded,
+            Event::Restore(_) => EventType::Restored,
+            Event::Completed(_) => EventType::Completed,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.event_type().into()
+    }
+
     pub fn date(&self) -> NaiveDate {
         match self {
             Event::Started(e) => e.date(),
             Event::Dumped(e) => e.date(),
             Event::Failed(e) => e.date(),
             Event::Uploaded(e) => e.date(),
+            Event::Restore(e) => e.date(),
             Event::Completed(e) => e.date(),
         }
     }
 }
+
+impl EventMetadata for Event {
+    fn reference(&self) -> Id {
+        match self {
+            Event::Started(e) => e.reference(),
+            Event::Dumped(e) => e.reference(),
+            Event::Failed(e) => e.reference(),
+            Event::Uploaded(e) => e.reference(),
+            Event::Restore(e) => e.reference(),
+            Event::Completed(e) => e.reference(),
+        }
+    }
+
+    fn timestamp(&self) -> DateTime<Utc> {
+        match self {
+            Event::Started(e) => e.timestamp(),
+            Event::Dumped(e) => e.timestamp(),
+            Event::Failed(e) => e.timestamp(),
+            Event::Uploaded(e) => e.timestamp(),
+            Event::Restore(e) => e.timestamp(),
+            Event::Completed(e) => e.timestamp(),
+        }
+    }
+}
+
+/// Orders the events of a run chronologically and checks that they follow its lifecycle: the
+/// run is started first, each chunk is dumped before it is uploaded, every dumped chunk is
+/// uploaded before the run completes, and nothing happens once the run completed or failed.
+/// Runs still in progress are valid.
+pub fn lifecycle(events: &[Event]) -> Result<Vec<Event>> {
+    let Some(reference) = events.first().map(Event::reference) else {
+        return Err(anyhow!("No events to order"));
+    };
+    if let Some(other) = events.iter().find(|event| event.reference() != reference) {
+        return Err(anyhow!(
+            "Events of the runs {reference} and {} can't be ordered together",
+            other.reference()
+        ));
+    }
+
+    // Events stored at the same time are ordered by the stage of the run they are about
+    let stage = |event: &Event| match event {
+        Event::Started(_) => 0,
+        Event::Dumped(_) => 1,
+        Event::Uploaded(_) => 2,
+        Event::Restore(_) => 3,
+        Event::Failed(_) | Event::Completed(_) => 4,
+    };
+    let mut events = events.to_vec();
+    events.sort_by_key(|event| (event.timestamp(), stage(event)));
+
+    let (mut dumped, mut uploaded) = (0, 0);
+    let mut ended: Option<&Event> = None;
+    for (index, event) in events.iter().enumerate() {
+        if let Some(ended) = ended {
+            return Err(anyhow!(
+                "Run {reference} has a {} event after it {}",
+                event.name(),
+                ended.name()
+            ));
+        }
+
+        match event {
+            Event::Started(_) if index > 0 => {
+                return Err(anyhow!("Run {reference} was started more than once"));
+            }
+            Event::Started(_) => {}
+            _ if index == 0 => {
+                return Err(anyhow!(
+                    "Run {reference} has a {} event before it started",
+                    event.name()
+                ));
+            }
+            Event::Dumped(_) => dumped += 1,
+            Event::Uploaded(_) if uploaded == dumped => {
+                return Err(anyhow!(
+                    "Run {reference} uploaded more chunks than it dumped"
+                ));
+            }
+            Event::Uploaded(_) => uploaded += 1,
+            Event::Completed(_) if uploaded < dumped => {
+                return Err(anyhow!(
+                    "Run {reference} completed before uploading {} of its dumped chunks",
+                    dumped - uploaded
+                ));
+            }
+            Event::Completed(_) | Event::Failed(_) => ended = Some(event),
+            Event::Restore(_) => {}
+        }
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integrationos_domain::{prefix::IdPrefix, Store};
+    use serde_json::json;
+
+    #[test]
+    fn test_tagged_events_are_read_by_their_type() {
+        let reference = Id::now(IdPrefix::Archive);
+        let event = Event::Dumped(Dumped::new(reference));
+
+        let value = serde_json::to_value(event).unwrap();
+        assert_eq!(value["type"], "dumped");
+
+        // The type decides the variant, even when the fields would match an earlier one
+        let completed = json!({
+            "type": "completed",
+            "id": reference,
+            "path": "gs://bucket/clients",
+            "completedAt": Utc::now(),
+            "failedAt": Utc::now(),
+            "reason": "unused",
+        });
+        let event: Event = serde_json::from_value(completed).unwrap();
+        assert_eq!(event.name(), "completed");
+
+        let restore =
+            serde_json::to_value(Event::Restore(Restore::new(reference, reference, 1))).unwrap();
+        assert_eq!(restore["type"], "restored");
+        let event: Event = serde_json::from_value(restore).unwrap();
+        assert_eq!(event.name(), "restored");
+    }
+
+    #[test]
+    fn test_event_type_matches_stored_type() {
+        let reference = Id::now(IdPrefix::Archive);
+        let events = [
+            Event::Started(Started::new("clients".to_string()).unwrap()),
+            Event::Dumped(Dumped::new(reference)),
+            Event::Failed(Failed::new("Failed to dump".to_string(), reference)),
+            Event::Uploaded(Uploaded::new(reference)),
+            Event::Restore(Restore::new(reference, reference, 1)),
+            Event::Completed(Completed::new("gs://bucket/clients".to_string(), reference)),
+        ];
+
+        for event in events {
+            let value = serde_json::to_value(&event).unwrap();
+            assert_eq!(
+                value["type"],
+                serde_json::to_value(event.event_type()).unwrap()
+            );
+            assert_eq!(value["type"], event.name());
+
+            let read: Event = serde_json::from_value(value).unwrap();
+            assert_eq!(read.event_type(), event.event_type());
+            assert_eq!(read.reference(), event.reference());
+
+            // As read from the store
+            let read: Event = bson::from_document(bson::to_document(&event).unwrap()).unwrap();
+            assert_eq!(read.event_type(), event.event_type());
+        }
+    }
+
+    #[test]
+    fn test_legacy_events_are_read_by_their_fields() {
+        let reference = Id::now(IdPrefix::Archive);
+        let now = Utc::now();
+        let legacy = [
+            (
+                json!({ "_id": reference, "startedAt": now, "collection": Store::Clients }),
+                "started",
+            ),
+            (json!({ "id": reference, "dumpedAt": now }), "dumped"),
+            (
+                json!({ "id": reference, "failedAt": now, "reason": "Failed to dump" }),
+                "failed",
+            ),
+            (json!({ "id": reference, "uploadedAt": now }), "uploaded"),
+            (
+                json!({ "id": reference, "path": "gs://bucket/clients", "completedAt": now }),
+                "completed",
+            ),
+        ];
+
+        for (value, name) in legacy {
+            let event: Event = serde_json::from_value(value).unwrap();
+            assert_eq!(event.name(), name);
+            assert_eq!(event.reference(), reference);
+        }
+    }
+
+    #[test]
+    fn test_events_with_an_unknown_type_are_rejected() {
+        let reference = Id::now(IdPrefix::Archive);
+        let now = Utc::now();
+
+        // The fields alone would be read as a legacy dumped event
+        for event_type in [json!("dumpd"), json!(null), json!(1)] {
+            let value = json!({ "type": event_type, "id": reference, "dumpedAt": now });
+            assert!(serde_json::from_value::<Event>(value).is_err());
+        }
+    }
+
+    fn run() -> (Started, DateTime<Utc>) {
+        let start = Utc::now() - chrono::Duration::minutes(1);
+        let started = Started::new("clients".to_string())
+            .unwrap()
+            .with_date(start);
+        (started, start)
+    }
+
+    fn names(events: &[Event]) -> Vec<&'static str> {
+        events.iter().map(Event::name).collect()
+    }
+
+    #[test]
+    fn test_events_of_a_run_are_ordered_by_their_lifecycle() {
+        let (started, start) = run();
+        let reference = started.reference();
+        let at = |seconds| start + chrono::Duration::seconds(seconds);
+        let events = [
+            Event::Completed(
+                Completed::new("gs://bucket/clients".to_string(), reference)
+                    .with_date(Utc::now() + chrono::Duration::seconds(1)),
+            ),
+            Event::Uploaded(Uploaded::new(reference)),
+            Event::Dumped(Dumped::new(reference).with_date(at(20))),
+            Event::Dumped(Dumped::new(reference).with_date(at(10))),
+            Event::Started(started.clone()),
+            Event::Uploaded(Uploaded::new(reference)),
+        ];
+
+        let ordered = lifecycle(&events).unwrap();
+        assert_eq!(
+            names(&ordered),
+            [
+                "started",
+                "dumped",
+                "dumped",
+                "uploaded",
+                "uploaded",
+                "completed"
+            ]
+        );
+        assert!(ordered
+            .windows(2)
+            .all(|pair| pair[0].timestamp() <= pair[1].timestamp()));
+
+        // Events stored at the same time are ordered by their stage
+        let completed =
+            Completed::new("gs://bucket/clients".to_string(), reference).with_date(start);
+        let ordered =
+            lifecycle(&[Event::Completed(completed), Event::Started(started.clone())]).unwrap();
+        assert_eq!(names(&ordered), ["started", "completed"]);
+
+        // Runs still in progress are valid
+        let single = lifecycle(&[Event::Started(started)]).unwrap();
+        assert_eq!(names(&single), ["started"]);
+    }
+
+    #[test]
+    fn test_events_out_of_their_lifecycle_are_rejected() {
+        let (started, start) = run();
+        let reference = started.reference();
+        let completed = |date| {
+            Event::Completed(
+                Completed::new("gs://bucket/clients".to_string(), reference).with_date(date),
+            )
+        };
+        let later = Utc::now() + chrono::Duration::seconds(1);
+
+        for (events, expected) in [
+            (vec![], "No events to order"),
+            (
+                vec![
+                    Event::Started(started.clone()),
+                    Event::Dumped(
+                        Dumped::new(reference).with_date(start - chrono::Duration::seconds(1)),
+                    ),
+                ],
+                "has a dumped event before it started",
+            ),
+            (
+                vec![
+                    Event::Started(started.clone()),
+                    Event::Started(started.clone()),
+                ],
+                "was started more than once",
+            ),
+            (
+                vec![
+                    Event::Started(started.clone()),
+                    Event::Uploaded(Uploaded::new(reference)),
+                ],
+                "uploaded more chunks than it dumped",
+            ),
+            (
+                vec![
+                    Event::Started(started.clone()),
+                    Event::Dumped(Dumped::new(reference).with_date(start)),
+                    completed(later),
+                ],
+                "completed before uploading 1 of its dumped chunks",
+            ),
+            (
+                vec![
+                    Event::Started(started.clone()),
+                    completed(start),
+                    Event::Dumped(Dumped::new(reference).with_date(later)),
+                ],
+                "has a dumped event after it completed",
+            ),
+            (
+                vec![
+                    Event::Started(started.clone()),
+                    Event::Failed(Failed::new("Failed to dump".to_string(), reference)),
+                    completed(later),
+                ],
+                "has a completed event after it failed",
+            ),
+            (
+                vec![
+                    Event::Started(started.clone()),
+                    Event::Uploaded(Uploaded::new(Id::now(IdPrefix::Archive))),
+                ],
+                "can't be ordered together",
+            ),
+        ] {
+            let err = lifecycle(&events).unwrap_err();
+            assert!(err.to_string().contains(expected), "{err}");
+        }
+    }
+}
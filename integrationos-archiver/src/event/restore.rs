@@ -0,0 +1,161 @@
+use super::EventMetadata;
+use chrono::{DateTime, NaiveDate, Utc};
+use integrationos_domain::Id;
+use serde::{Deserialize, Serialize};
+
+/// An archive restored into its collection by the run with reference `id`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Restore {
+    id: Id,
+    /// Reference of the run which dumped the archive
+    archive: Id,
+    documents: u64,
+    restored_at: DateTime<Utc>,
+}
+
+impl Restore {
+    pub fn new(id: Id, archive: Id, documents: u64) -> Self {
+        Self {
+            id,
+            archive,
+            documents,
+            restored_at: Utc::now(),
+        }
+    }
+
+    pub fn archive(&self) -> Id {
+        self.archive
+    }
+
+    pub fn documents(&self) -> u64 {
+        self.documents
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.restored_at.date_naive()
+    }
+}
+
+impl EventMetadata for Restore {
+    fn reference(&self) -> Id {
+        self.id
+    }
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.restored_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event::{dumped::Dumped, Event},
+        storage::checksum::DumpChecksum,
+    };
+    use integrationos_domain::prefix::IdPrefix;
+
+    #[test]
+    fn test_restore_is_read_back_as_restore() {
+        let archive = Id::now(IdPrefix::Archive);
+        let restore = Restore::new(Id::now(IdPrefix::Archive), archive, 42);
+
+        let value = serde_json::to_value(Event::Restore(restore.clone())).unwrap();
+        let Event::Restore(read) = serde_json::from_value(value).unwrap() else {
+            panic!("Restore was read back as another event");
+        };
+        assert_eq!(read.reference(), restore.reference());
+        assert_eq!(read.archive(), archive);
+        assert_eq!(read.documents(), 42);
+
+        // Dumps made before their number of documents was recorded are still read
+        let legacy = serde_json::json!({ "id": archive, "dumpedAt": Utc::now() });
+        assert!(matches!(
+            serde_json::from_value(legacy).unwrap(),
+            Event::Dumped(_)
+        ));
+    }
+
+    #[test]
+    fn test_restored_documents_must_match_dump() {
+        let archive = Id::now(IdPrefix::Archive);
+        let dumped = Dumped::new(archive).with_documents(42, Utc::now());
+
+        assert!(dumped.verify_restored(42).is_ok());
+        let err = dumped.verify_restored(41).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Restored 41 documents but 42 were dumped"));
+
+        // Dumps which didn't record their documents can't be verified
+        assert!(Dumped::new(archive).verify_restored(41).is_ok());
+    }
+
+    #[test]
+    fn test_corrupted_dump_is_not_restored() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("clients.bson");
+        let mut dump = (0..100)
+            .flat_map(|i: u32| bson::to_vec(&bson::doc! { "_id": i, "name": "event" }).unwrap())
+            .collect::<Vec<_>>();
+        std::fs::write(&path, &dump).unwrap();
+
+        let archive = Id::now(IdPrefix::Archive);
+        let dumped = Dumped::new(archive).with_checksum(DumpChecksum::of_file(&path).unwrap());
+        assert!(dumped.verify_checksum(&path).is_ok());
+
+        // A byte flipped within a document keeps the dump readable
+        let last = dump.len() - 2;
+        dump[last] ^= 0xff;
+        std::fs::write(&path, &dump).unwrap();
+        let err = dumped.verify_checksum(&path).unwrap_err();
+        assert!(
+            err.to_string().contains("is corrupted or truncated"),
+            "{err}"
+        );
+
+        // As does a dump cut at a document boundary
+        let one = bson::to_vec(&bson::doc! { "_id": 0, "name": "event" }).unwrap();
+        std::fs::write(&path, one).unwrap();
+        let err = dumped.verify_checksum(&path).unwrap_err();
+        assert!(err.to_string().contains("read 1 documents"), "{err}");
+
+        // Dumps which didn't record their checksum can't be verified
+        assert!(Dumped::new(archive).verify_checksum(&path).is_ok());
+    }
+
+    #[test]
+    fn test_dump_missing_chunks_is_not_restored() {
+        let archive = Id::now(IdPrefix::Archive);
+        let chunk = |index| Dumped::new(archive).with_chunk(index, 4);
+        let indexes = |chunks: Vec<Dumped>| {
+            chunks
+                .iter()
+                .map(|chunk| (chunk.chunk_index(), chunk.archive_name("clients")))
+                .collect::<Vec<_>>()
+        };
+
+        // Chunks are read back in any order, and twice when their event was retried
+        let chunks = Dumped::in_order(vec![chunk(2), chunk(0), chunk(3), chunk(1), chunk(2)]);
+        assert_eq!(
+            indexes(chunks.unwrap()),
+            (0..4)
+                .map(|index| (index, format!("clients-chunk-{index}")))
+                .collect::<Vec<_>>()
+        );
+
+        let err = Dumped::in_order(vec![chunk(3), chunk(0)]).unwrap_err();
+        assert!(
+            err.to_string().contains("is missing chunks [1, 2] of 4"),
+            "{err}"
+        );
+        let err = Dumped::in_order(vec![chunk(0), chunk(1), chunk(2), chunk(4)]).unwrap_err();
+        assert!(err.to_string().contains("chunk 4 out of its 4"), "{err}");
+        let err = Dumped::in_order(vec![chunk(0), Dumped::new(archive).with_chunk(1, 2)]);
+        assert!(err.is_err());
+
+        // Dumps made before they were chunked are a single chunk named after the collection
+        let chunks = Dumped::in_order(vec![Dumped::new(archive)]).unwrap();
+        assert_eq!(indexes(chunks), [(0, "clients".to_owned())]);
+    }
+}
@@ -29,13 +29,26 @@ impl Started {
         &self.collection
     }
 
+    pub fn started_at(&self) -> DateTime<Utc> {
+        self.started_at
+    }
+
     pub fn date(&self) -> NaiveDate {
         self.started_at.date_naive()
     }
+
+    #[cfg(test)]
+    pub fn with_date(mut self, date: DateTime<Utc>) -> Self {
+        self.started_at = date;
+        self
+    }
 }
 
 impl EventMetadata for Started {
     fn reference(&self) -> Id {
         self.id
     }
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.started_at
+    }
 }
@@ -27,4 +27,7 @@ impl EventMetadata for Uploaded {
     fn reference(&self) -> Id {
         self.id
     }
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.uploaded_at
+    }
 }
@@ -0,0 +1,182 @@
+use crate::config::ArchiverConfig;
+use crate::event::{started::Started, Event, EventMetadata};
+use anyhow::{anyhow, Result};
+use bson::doc;
+use chrono::{Duration as CDuration, Utc};
+use integrationos_domain::{Id, MongoStore, Unit};
+use std::collections::HashSet;
+use std::time::Duration;
+use strum::{AsRefStr, EnumString};
+use tokio::time::{sleep, Instant};
+
+/// What happens to a run started while `MAX_CONCURRENT_RUNS` runs are already in progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, AsRefStr)]
+#[strum(serialize_all = "kebab-case")]
+pub enum RunLimitBehaviour {
+    Queue,
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Admitted,
+    Queued { runs_ahead: usize },
+    Rejected { runs_ahead: usize },
+}
+
+impl Admission {
+    pub fn new(
+        runs_ahead: usize,
+        max_concurrent_runs: usize,
+        behaviour: RunLimitBehaviour,
+    ) -> Self {
+        if runs_ahead < max_concurrent_runs.max(1) {
+            return Admission::Admitted;
+        }
+
+        match behaviour {
+            RunLimitBehaviour::Queue => Admission::Queued { runs_ahead },
+            RunLimitBehaviour::Reject => Admission::Rejected { runs_ahead },
+        }
+    }
+}
+
+/// Caps the number of archive runs in progress across all archiver instances. The archives
+/// collection acts as the semaphore: every run records a `Started` event before doing any
+/// work and holds a permit until it records a `Completed` or `Failed` one. Runs are admitted
+/// in the order they started so that queued runs cannot starve each other.
+pub struct RunLimiter<'a> {
+    archives: &'a MongoStore<Event>,
+    max_concurrent_runs: usize,
+    behaviour: RunLimitBehaviour,
+    queue_timeout: Duration,
+    poll_interval: Duration,
+    stale_after: CDuration,
+}
+
+impl<'a> RunLimiter<'a> {
+    pub fn new(config: &ArchiverConfig, archives: &'a MongoStore<Event>) -> Self {
+        Self {
+            archives,
+            max_concurrent_runs: config.max_concurrent_runs,
+            behaviour: config.run_limit_behaviour,
+            queue_timeout: Duration::from_secs(config.run_queue_timeout_secs),
+            poll_interval: Duration::from_secs(config.run_queue_poll_interval_secs),
+            stale_after: CDuration::seconds(config.run_stale_after_secs as i64),
+        }
+    }
+
+    /// Waits until `started` may run, or fails if it is rejected or queued for too long
+    pub async fn acquire(&self, started: &Started) -> Result<Unit> {
+        let deadline = Instant::now() + self.queue_timeout;
+
+        loop {
+            let runs_ahead = self.runs_ahead(started).await?;
+
+            match Admission::new(runs_ahead, self.max_concurrent_runs, self.behaviour) {
+                Admission::Admitted => return Ok(()),
+                Admission::Rejected { runs_ahead } => {
+                    return Err(anyhow!(
+                    "Run rejected as {runs_ahead} archive runs are already in progress (limit {})",
+                    self.max_concurrent_runs
+                ))
+                }
+                Admission::Queued { runs_ahead } => {
+                    if Instant::now() >= deadline {
+                        return Err(anyhow!(
+                            "Run timed out in queue with {runs_ahead} archive runs still ahead of it"
+                        ));
+                    }
+
+                    tracing::info!(
+                        "Run {} queued behind {runs_ahead} archive runs in progress",
+                        started.reference()
+                    );
+                    sleep(self.poll_interval).await;
+                }
+            }
+        }
+    }
+
+    /// Number of unfinished runs that started before `started`. Runs older than
+    /// `RUN_STALE_AFTER_SECS` are considered dead and don't hold a permit anymore.
+    async fn runs_ahead(&self, started: &Started) -> Result<usize> {
+        let cutoff = Utc::now() - self.stale_after;
+        let ahead = |other: &Started| {
+            other.started_at() > cutoff
+                && (other.started_at(), other.reference().to_string())
+                    < (started.started_at(), started.reference().to_string())
+        };
+
+        let candidates: Vec<Id> = self
+            .archives
+            .get_many(
+                Some(doc! { "startedAt": { "$exists": true } }),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await?
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Started(other) if ahead(&other) => Some(other.reference()),
+                _ => None,
+            })
+            .collect();
+
+        if candidates.is_empty() {
+            return Ok(0);
+        }
+
+        let finished: HashSet<Id> = self
+            .archives
+            .get_many(
+                Some(doc! {
+                    "id": { "$in": candidates.iter().map(|id| id.to_string()).collect::<Vec<_>>() },
+                    "$or": [
+                        { "completedAt": { "$exists": true } },
+                        { "failedAt": { "$exists": true } },
+                    ]
+                }),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await?
+            .iter()
+            .map(EventMetadata::reference)
+            .collect();
+
+        Ok(candidates
+            .iter()
+            .filter(|id| !finished.contains(id))
+            .count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs_beyond_cap_are_queued_or_rejected() {
+        assert_eq!(
+            Admission::new(1, 2, RunLimitBehaviour::Queue),
+            Admission::Admitted
+        );
+        assert_eq!(
+            Admission::new(2, 2, RunLimitBehaviour::Queue),
+            Admission::Queued { runs_ahead: 2 }
+        );
+        assert_eq!(
+            Admission::new(3, 2, RunLimitBehaviour::Reject),
+            Admission::Rejected { runs_ahead: 3 }
+        );
+        assert_eq!(
+            Admission::new(0, 0, RunLimitBehaviour::Reject),
+            Admission::Admitted
+        );
+    }
+}
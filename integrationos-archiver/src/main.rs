@@ -1,5 +1,9 @@
 mod config;
 mod event;
+mod limiter;
+mod metrics;
+mod report;
+mod rotation;
 mod storage;
 
 use anyhow::{anyhow, Result};
@@ -10,14 +14,24 @@ use envconfig::Envconfig;
 use event::completed::Completed;
 use event::dumped::Dumped;
 use event::failed::Failed;
+use event::restore::Restore;
 use event::started::Started;
 use event::uploaded::Uploaded;
-use event::{Event, EventMetadata};
+use event::{lifecycle, Event, EventMetadata};
+use integrationos_domain::secrets::SecretsConfig;
 use integrationos_domain::telemetry::{get_subscriber, init_subscriber};
-use integrationos_domain::{MongoStore, Store, Unit};
+use integrationos_domain::{provider_crypto, IOSCrypto, Id, MongoStore, Store, Unit};
+use limiter::RunLimiter;
+use metrics_exporter_prometheus::PrometheusBuilder;
 use mongodb::options::FindOneOptions;
 use mongodb::{Client, Database};
+use report::Timeline;
+use rotation::{rotate_secrets, KeyRotation};
 use std::process::Command;
+use std::str::FromStr;
+use std::sync::Arc;
+use storage::chunking::{join_chunks, split_dump};
+use storage::compression::Compression;
 use storage::google_cloud::GoogleCloudStorage;
 use storage::{Extension, Storage, StorageProvider};
 use tempfile::TempDir;
@@ -38,78 +52,357 @@ async fn main() -> Result<Unit> {
     let database = client.database(&config.db_config.event_db_name);
     let archives: MongoStore<Event> = MongoStore::new(&database, &Store::Archives).await?;
 
-    let started = Started::new(config.event_collection_name.clone())?;
-    archives
-        .create_one(&Event::Started(started.clone()))
-        .await?;
+    if config.mode == Mode::Report {
+        return report(&config, &archives).await;
+    }
+
+    if config.mode == Mode::RotateSecrets && config.dry_run {
+        let control = control_database(&config).await?;
+        let summary = rotate_secrets(&control, &key_rotation(&config).await?, true).await?;
+        tracing::info!(
+            "Dry run: {} of the {} secrets referenced would be rewritten, {} are already encrypted with the new key",
+            summary.rewritten,
+            summary.referenced,
+            summary.already_rotated
+        );
+
+        return Ok(());
+    }
+
+    let handle = PrometheusBuilder::new().install_recorder()?;
+    let result = run(config.clone(), &archives, storage, database).await;
+
+    if let Some(gateway_url) = &config.prometheus_push_gateway_url {
+        if let Err(e) = metrics::push(&handle, gateway_url).await {
+            tracing::warn!("{e:#}");
+        }
+    }
+
+    result
+}
+
+async fn run(
+    config: ArchiverConfig,
+    archives: &MongoStore<Event>,
+    storage: impl Storage,
+    database: Database,
+) -> Result<Unit> {
+    let collection = match config.mode {
+        Mode::RotateSecrets => Store::Secrets.to_string(),
+        _ => config.event_collection_name.clone(),
+    };
+    let started = Started::new(collection)?;
+    transition(archives, &started, Event::Started(started.clone())).await?;
+
+    if config.mode != Mode::NoOp {
+        if let Err(e) = RunLimiter::new(&config, archives).acquire(&started).await {
+            transition(
+                archives,
+                &started,
+                Event::Failed(Failed::new(e.to_string(), started.reference())),
+            )
+            .await?;
+
+            tracing::error!("Archive run was not started: {e}");
+
+            return Err(e);
+        }
+    }
 
     match config.mode {
-        Mode::Restore => restore(config, &archives, &started, storage).await,
-        Mode::Dump => dump(config, &archives, &started, storage, database, false).await,
-        Mode::DumpDelete => dump(config, &archives, &started, storage, database, true).await,
-        Mode::NoOp => Ok(()),
+        Mode::Restore => restore(config, archives, &started, storage, database).await,
+        Mode::Dump => dump(config, archives, &started, storage, database, false).await,
+        Mode::DumpDelete => dump(config, archives, &started, storage, database, true).await,
+        Mode::RotateSecrets => rotate(config, archives, &started).await,
+        Mode::NoOp | Mode::Report => Ok(()),
     }
 }
 
+async fn control_database(config: &ArchiverConfig) -> Result<Database> {
+    let client = Client::with_uri_str(&config.db_config.control_db_url).await?;
+    Ok(client.database(&config.db_config.control_db_name))
+}
+
+/// Rotation from the key secrets are read with to `NEW_IOS_CRYPTO_SECRET`
+async fn key_rotation(config: &ArchiverConfig) -> Result<KeyRotation> {
+    let new_ios_crypto_secret = config
+        .new_ios_crypto_secret
+        .clone()
+        .ok_or_else(|| anyhow!("NEW_IOS_CRYPTO_SECRET is required in rotate-secrets mode"))?;
+
+    let old = provider_crypto(config.secrets_config.provider, &config.secrets_config).await?;
+    let new = IOSCrypto::new(SecretsConfig {
+        ios_crypto_secret: new_ios_crypto_secret,
+        ..config.secrets_config.clone()
+    })?;
+
+    Ok(KeyRotation::new(old, Arc::new(new)))
+}
+
+/// Re-encrypts the secrets of the connections and settings with `NEW_IOS_CRYPTO_SECRET`.
+/// Services keep reading secrets with the old key until they are restarted with the new one
+/// as `IOS_CRYPTO_SECRET`, so they should be stopped while secrets are rotated.
+async fn rotate(
+    config: ArchiverConfig,
+    archives: &MongoStore<Event>,
+    started: &Started,
+) -> Result<Unit> {
+    tracing::info!("Starting archiver in rotate-secrets mode");
+
+    let rotated = async {
+        let control = control_database(&config).await?;
+        rotate_secrets(&control, &key_rotation(&config).await?, false).await
+    }
+    .await;
+
+    let summary = match rotated {
+        Ok(summary) => summary,
+        Err(e) => {
+            transition(
+                archives,
+                started,
+                Event::Failed(Failed::new(e.to_string(), started.reference())),
+            )
+            .await?;
+
+            tracing::error!("Failed to rotate secrets: {e}");
+
+            return Err(e);
+        }
+    };
+
+    let target = format!("{}.{}", config.db_config.control_db_name, Store::Secrets);
+    transition(
+        archives,
+        started,
+        Event::Completed(Completed::new(target, started.reference())),
+    )
+    .await?;
+
+    tracing::info!(
+        "Rotated the secrets referenced by connections and settings: {} rewritten, {} already rotated, {} changed while being rotated and left as they are",
+        summary.rewritten,
+        summary.already_rotated,
+        summary.skipped
+    );
+
+    Ok(())
+}
+
+/// Stores an event of the run started by `started` and records its metrics
+async fn transition(archives: &MongoStore<Event>, started: &Started, event: Event) -> Result<Unit> {
+    archives.create_one(&event).await?;
+    metrics::record_transition(started, &event);
+
+    Ok(())
+}
+
+async fn report(config: &ArchiverConfig, archives: &MongoStore<Event>) -> Result<Unit> {
+    let reference = config
+        .archive_reference
+        .as_deref()
+        .ok_or_else(|| anyhow!("ARCHIVE_REFERENCE is required in report mode"))?;
+    let reference = Id::from_str(reference).map_err(|e| anyhow!(e))?;
+
+    let timeline = Timeline::fetch(archives, reference).await?;
+    println!("{}", timeline.render(config.report_format)?);
+
+    Ok(())
+}
+
 async fn restore(
     config: ArchiverConfig,
     archives: &MongoStore<Event>,
     started: &Started,
     storage: impl Storage,
+    database: Database,
 ) -> Result<Unit> {
     tracing::info!(
-        "Starting archiver in restore mode for the {} collection. Events will not be restored to the original collection, rather a new collection will be created",
+        "Starting archiver in restore mode for the {} collection. Events already present in the collection are kept as they are",
         started.collection()
     );
 
+    if let Err(e) = replay(config, archives, started, storage, database).await {
+        transition(
+            archives,
+            started,
+            Event::Failed(Failed::new(e.to_string(), started.reference())),
+        )
+        .await?;
+
+        tracing::error!("Failed to restore archive: {e}");
+
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+/// Reference of the archive to restore, the one of `ARCHIVE_REFERENCE` or else the latest dump
+async fn archive_reference(config: &ArchiverConfig, archives: &MongoStore<Event>) -> Result<Id> {
+    if let Some(reference) = config.archive_reference.as_deref() {
+        return Id::from_str(reference).map_err(|e| anyhow!(e));
+    }
+
     let filter = doc! {
-        "completedAt": {
+        "dumpedAt": {
             "$exists": true,
         }
     };
     let options = FindOneOptions::builder()
-        .sort(doc! { "completedAt": -1 })
+        .sort(doc! { "dumpedAt": -1 })
         .build();
-    let archive = archives.collection.find_one(filter, options).await?;
-
-    match archive {
-        None => Err(anyhow!(
-            "No archive found for the collection {}",
-            started.collection()
-        )),
-        Some(event) => {
-            let archive_bson_file_path = storage
-                .download_file(&config, &event, &Extension::Bson)
-                .await?;
 
-            // * Restore: mongorestore --gzip --nsInclude=events-service.clients events-service/clients.bson.gz --verbose (nsInclude=${DB_NAME}.${COLLECTION_NAME})
-            tracing::info!("Restoring collection {}", config.event_collection_name);
-            let output = Command::new("mongorestore")
-                .arg("--gzip")
-                .arg("--nsInclude")
-                .arg(format!(
-                    "{}.{}",
-                    config.db_config.event_db_name, config.event_collection_name
+    archives
+        .collection
+        .find_one(filter, options)
+        .await?
+        .map(|event| event.reference())
+        .ok_or_else(|| {
+            anyhow!(
+                "No archive found for the collection {}",
+                config.event_collection_name
+            )
+        })
+}
+
+/// Restores the archive dumped by a previous run into the collection of the run started by
+/// `started`. Archives restored already aren't restored again.
+async fn replay(
+    config: ArchiverConfig,
+    archives: &MongoStore<Event>,
+    started: &Started,
+    storage: impl Storage,
+    database: Database,
+) -> Result<Unit> {
+    let reference = archive_reference(&config, archives).await?;
+    let target = format!(
+        "{}.{}",
+        config.db_config.event_db_name, config.event_collection_name
+    );
+
+    let restored = archives
+        .get_one(doc! {
+            "archive": reference.to_string(),
+            "restoredAt": { "$exists": true },
+        })
+        .await?;
+    if let Some(restored) = restored {
+        tracing::info!(
+            "Archive {reference} was already restored by the run {}, nothing to restore",
+            restored.reference()
+        );
+    } else {
+        // Started events hold the reference as their id, the other events refer to it
+        let events = archives
+            .get_many(
+                Some(doc! {
+                    "$or": [
+                        { "_id": reference.to_string() },
+                        { "id": reference.to_string() },
+                    ]
+                }),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await?;
+        if !events.iter().any(|event| matches!(event, Event::Dumped(_))) {
+            return Err(anyhow!("No dump found with reference {reference}"));
+        }
+
+        let events = lifecycle(&events)?;
+        let completed = match events.last() {
+            Some(completed @ Event::Completed(_)) => completed.clone(),
+            _ => {
+                return Err(anyhow!(
+                    "Archive {reference} was dumped but never completed"
                 ))
-                .arg(archive_bson_file_path)
-                .arg("--verbose")
-                .output()?;
-
-            if !output.status.success() {
-                anyhow::bail!(
-                    "Archive restore failed with status {}",
-                    String::from_utf8_lossy(&output.stderr)
-                );
             }
+        };
+        let chunks = Dumped::in_order(
+            events
+                .into_iter()
+                .filter_map(|event| match event {
+                    Event::Dumped(dumped) => Some(dumped),
+                    _ => None,
+                })
+                .collect(),
+        )?;
+        let dumped = &chunks[0];
+
+        // Chunks are checked one at a time and only joined once all of them are intact
+        let tmp_dir = TempDir::new()?;
+        let mut chunk_paths = Vec::with_capacity(chunks.len());
+        for chunk in &chunks {
+            let name = chunk.archive_name(&config.event_collection_name);
+            let compression = chunk.compression();
+            let archive_file_path = storage
+                .download_file(&config, &completed, &name, &Extension::Bson(compression))
+                .await?;
+            let chunk_path = tmp_dir.path().join(format!(
+                "{name}.chunk.{}",
+                Extension::Bson(Compression::None).as_ref()
+            ));
+            compression.decompress(&archive_file_path, &chunk_path)?;
+            chunk.verify_checksum(&chunk_path)?;
+            chunk_paths.push(chunk_path);
+        }
 
-            tracing::info!(
-                "Collection {} restored successfully",
-                config.event_collection_name
+        let archive_bson_file_path = tmp_dir
+            .path()
+            .join(&config.event_collection_name)
+            .with_extension(Extension::Bson(Compression::None).as_ref());
+        join_chunks(&chunk_paths, &archive_bson_file_path)?;
+
+        // * Restore: mongorestore --nsInclude=events-service.clients events-service/clients.bson --verbose (nsInclude=${DB_NAME}.${COLLECTION_NAME})
+        // Documents already in the collection are skipped rather than duplicated
+        tracing::info!("Restoring archive {reference} into {target}");
+        let output = Command::new("mongorestore")
+            .arg("--uri")
+            .arg(&config.db_config.event_db_url)
+            .arg("--nsInclude")
+            .arg(&target)
+            .arg(archive_bson_file_path)
+            .arg("--verbose")
+            .output()?;
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "Archive restore failed with status {}",
+                String::from_utf8_lossy(&output.stderr)
             );
-
-            Ok(())
         }
+
+        let filter = match dumped.created_before() {
+            Some(date) => doc! { "createdAt": { "$lt": date.timestamp_millis() } },
+            None => doc! {},
+        };
+        let documents = database
+            .collection::<Document>(&config.event_collection_name)
+            .count_documents(filter, None)
+            .await?;
+        dumped.verify_restored(documents)?;
+
+        transition(
+            archives,
+            started,
+            Event::Restore(Restore::new(started.reference(), reference, documents)),
+        )
+        .await?;
+
+        tracing::info!("Archive {reference} restored successfully into {target}");
     }
+
+    transition(
+        archives,
+        started,
+        Event::Completed(Completed::new(target, started.reference())),
+    )
+    .await?;
+
+    Ok(())
 }
 
 async fn dump(
@@ -126,15 +419,15 @@ async fn dump(
     );
 
     let date = Utc::now() - CDuration::days(30);
-    let saved = save(config, archives, storage, started, &date).await;
+    let saved = save(config, archives, storage, started, &database, &date).await;
 
     if let Err(e) = saved {
-        archives
-            .create_one(&Event::Failed(Failed::new(
-                e.to_string(),
-                started.reference(),
-            )))
-            .await?;
+        transition(
+            archives,
+            started,
+            Event::Failed(Failed::new(e.to_string(), started.reference())),
+        )
+        .await?;
 
         tracing::error!("Failed to save archive: {e}");
 
@@ -163,10 +456,15 @@ async fn save(
     archive: &MongoStore<Event>,
     storage: impl Storage,
     started: &Started,
+    database: &Database,
     date: &DateTime<Utc>,
 ) -> Result<Unit> {
     let tmp_dir = TempDir::new()?;
     let filter = doc! { "createdAt": { "$lt": date.timestamp_millis() } };
+    let documents = database
+        .collection::<Document>(&config.event_collection_name)
+        .count_documents(filter.clone(), None)
+        .await?;
 
     let command = Command::new("mongodump")
         .arg("--uri")
@@ -179,35 +477,86 @@ async fn save(
         .arg(serde_json::to_string(&filter)?)
         .arg("--out")
         .arg(tmp_dir.path())
-        .arg("--gzip")
         .output()?;
 
     if !command.status.success() {
         return Err(anyhow!("Command mongodump failed: {:?}", command));
     }
 
-    archive
-        .create_one(&Event::Dumped(Dumped::new(started.reference())))
-        .await?;
-
     let base_path = tmp_dir
         .path()
         .join(&config.db_config.event_db_name)
         .join(&config.event_collection_name);
 
-    if let Err(e) = storage
-        .upload_file(&base_path, &Extension::Bson, &config)
-        .await
-    {
-        return Err(anyhow!("Failed to upload bson file: {e}"));
-    }
+    // Chunks are hashed before compression, the restore checks the decompressed chunks
+    let chunks = split_dump(
+        tmp_dir
+            .path()
+            .join(&config.db_config.event_db_name)
+            .as_path(),
+        &config.event_collection_name,
+        Extension::Bson(Compression::None).as_ref(),
+        config.chunk_limits(),
+    )?;
+    let total = chunks.len() as u32;
+    tracing::info!("Dumped {documents} documents in {total} chunks");
+
+    // The metadata of the collection is counted with the first chunk
+    let compression = config.dump_compression;
+    let metadata = base_path.with_extension(Extension::Metadata(Compression::None).as_ref());
+    let metadata_bytes = (
+        metadata.metadata()?.len(),
+        compression.compress(
+            &metadata,
+            &base_path.with_extension(Extension::Metadata(compression).as_ref()),
+        )?,
+    );
 
-    archive
-        .create_one(&Event::Uploaded(Uploaded::new(started.reference())))
+    for (chunk, index) in chunks.into_iter().zip(0..) {
+        let (mut original_bytes, mut compressed_bytes) = match index {
+            0 => metadata_bytes,
+            _ => (0, 0),
+        };
+        original_bytes += chunk.bytes;
+        compressed_bytes += compression.compress(
+            &chunk
+                .base_path
+                .with_extension(Extension::Bson(Compression::None).as_ref()),
+            &chunk
+                .base_path
+                .with_extension(Extension::Bson(compression).as_ref()),
+        )?;
+
+        transition(
+            archive,
+            started,
+            Event::Dumped(
+                Dumped::new(started.reference())
+                    .with_documents(documents, *date)
+                    .with_compression(compression, original_bytes, compressed_bytes)
+                    .with_checksum(chunk.checksum)
+                    .with_chunk(index, total),
+            ),
+        )
         .await?;
 
+        if let Err(e) = storage
+            .upload_file(&chunk.base_path, &Extension::Bson(compression), &config)
+            .await
+        {
+            return Err(anyhow!("Failed to upload bson file of chunk {index}: {e}"));
+        }
+
+        transition(
+            archive,
+            started,
+            Event::Uploaded(Uploaded::new(started.reference())),
+        )
+        .await?;
+    }
+
     if let Err(e) = storage
-        .upload_file(&base_path, &Extension::Metadata, &config)
+        .upload_file(&base_path, &Extension::Metadata(compression), &config)
         .await
     {
         return Err(anyhow!("Failed to upload json file: {e}"));
@@ -215,12 +564,12 @@ async fn save(
 
     let remote_path = format!("gs://{}{}", config.gs_storage_bucket, base_path.display());
 
-    archive
-        .create_one(&Event::Completed(Completed::new(
-            remote_path.clone(),
-            started.reference(),
-        )))
-        .await?;
+    transition(
+        archive,
+        started,
+        Event::Completed(Completed::new(remote_path.clone(), started.reference())),
+    )
+    .await?;
 
     tracing::info!(
         "Archive completed at {}, saved to {} with reference {}",
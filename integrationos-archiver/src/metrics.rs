@@ -0,0 +1,239 @@
+use crate::event::{started::Started, Event, EventMetadata};
+use anyhow::{Context, Result};
+use integrationos_domain::Unit;
+use metrics_exporter_prometheus::PrometheusHandle;
+
+/// Counters of the archive runs that reached each transition, labelled with
+/// [`COLLECTION_LABEL`]. Failures are also labelled with [`REASON_LABEL`].
+pub const RUNS_STARTED_COUNTER: &str = "archive_runs_started_total";
+pub const RUNS_COMPLETED_COUNTER: &str = "archive_runs_completed_total";
+pub const RUNS_FAILED_COUNTER: &str = "archive_runs_failed_total";
+/// Histogram of the time from the start of a run to its completion or failure, labelled with
+/// [`COLLECTION_LABEL`] and [`OUTCOME_LABEL`]
+pub const RUN_DURATION_HISTOGRAM: &str = "archive_run_duration_seconds";
+pub const COLLECTION_LABEL: &str = "collection";
+pub const OUTCOME_LABEL: &str = "outcome";
+pub const REASON_LABEL: &str = "reason";
+
+/// Records the metrics of a transition of the run started by `started`. Dumps, uploads and
+/// restores are intermediate steps and aren't recorded.
+pub fn record_transition(started: &Started, event: &Event) {
+    let collection = started.collection().to_string();
+    match event {
+        Event::Started(_) => {
+            metrics::counter!(RUNS_STARTED_COUNTER, 1, COLLECTION_LABEL => collection);
+        }
+        Event::Completed(_) => {
+            metrics::counter!(RUNS_COMPLETED_COUNTER, 1, COLLECTION_LABEL => collection.clone());
+            record_duration(started, event, collection, "completed");
+        }
+        Event::Failed(failed) => {
+            metrics::counter!(
+                RUNS_FAILED_COUNTER,
+                1,
+                COLLECTION_LABEL => collection.clone(),
+                REASON_LABEL => reason_label(failed.reason())
+            );
+            record_duration(started, event, collection, "failed");
+        }
+        Event::Dumped(_) | Event::Uploaded(_) | Event::Restore(_) => {}
+    }
+}
+
+fn record_duration(started: &Started, event: &Event, collection: String, outcome: &'static str) {
+    let duration = (event.timestamp() - started.started_at())
+        .to_std()
+        .unwrap_or_default();
+    metrics::histogram!(
+        RUN_DURATION_HISTOGRAM,
+        duration.as_secs_f64(),
+        COLLECTION_LABEL => collection,
+        OUTCOME_LABEL => outcome
+    );
+}
+
+/// Failure reasons embed the error they were caused by, e.g. `Failed to upload bson file:
+/// <error>`, only the part before the error is kept to label failures by their kind
+fn reason_label(reason: &str) -> String {
+    reason
+        .split(':')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_owned()
+}
+
+/// Pushes the recorded metrics to a Prometheus push gateway, as the archiver doesn't live long
+/// enough to be scraped
+pub async fn push(handle: &PrometheusHandle, gateway_url: &str) -> Result<Unit> {
+    let url = format!("{}/metrics/job/archiver", gateway_url.trim_end_matches('/'));
+    reqwest::Client::new()
+        .put(&url)
+        .body(handle.render())
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .with_context(|| format!("Failed to push metrics to {url}"))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{completed::Completed, failed::Failed, EventMetadata};
+    use chrono::Utc;
+    use metrics::{
+        Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Recorder, SharedString,
+        Unit,
+    };
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    #[derive(Default)]
+    struct Values(Mutex<Vec<f64>>);
+
+    impl HistogramFn for Values {
+        fn record(&self, value: f64) {
+            self.0.lock().unwrap().push(value);
+        }
+    }
+
+    #[derive(Default)]
+    struct Count(AtomicU64);
+
+    impl CounterFn for Count {
+        fn increment(&self, value: u64) {
+            self.0.fetch_add(value, Ordering::SeqCst);
+        }
+
+        fn absolute(&self, value: u64) {
+            self.0.store(value, Ordering::SeqCst);
+        }
+    }
+
+    /// Keeps every counter and histogram, keyed by their name and labels
+    #[derive(Default)]
+    struct CapturingRecorder {
+        counters: Mutex<HashMap<String, Arc<Count>>>,
+        histograms: Mutex<HashMap<String, Arc<Values>>>,
+    }
+
+    impl CapturingRecorder {
+        fn key(key: &Key) -> String {
+            let labels = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect::<Vec<_>>()
+                .join(",");
+            format!("{}{{{labels}}}", key.name())
+        }
+
+        fn counter(&self, key: &str) -> u64 {
+            self.counters
+                .lock()
+                .unwrap()
+                .get(key)
+                .map(|counter| counter.0.load(Ordering::SeqCst))
+                .unwrap_or_default()
+        }
+
+        fn histogram(&self, key: &str) -> Vec<f64> {
+            self.histograms
+                .lock()
+                .unwrap()
+                .get(key)
+                .map(|values| values.0.lock().unwrap().clone())
+                .unwrap_or_default()
+        }
+    }
+
+    impl Recorder for CapturingRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key) -> Counter {
+            let counter = self
+                .counters
+                .lock()
+                .unwrap()
+                .entry(Self::key(key))
+                .or_default()
+                .clone();
+            Counter::from_arc(counter)
+        }
+
+        fn register_gauge(&self, _: &Key) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key) -> Histogram {
+            let histogram = self
+                .histograms
+                .lock()
+                .unwrap()
+                .entry(Self::key(key))
+                .or_default()
+                .clone();
+            Histogram::from_arc(histogram)
+        }
+    }
+
+    #[test]
+    fn test_transitions_of_runs_are_recorded() {
+        let recorder: &'static CapturingRecorder = Box::leak(Box::default());
+        metrics::set_recorder(recorder).expect("Failed to install recorder");
+
+        let started = Started::new("clients".to_string())
+            .unwrap()
+            .with_date(Utc::now() - chrono::Duration::seconds(90));
+        record_transition(&started, &Event::Started(started.clone()));
+        record_transition(
+            &started,
+            &Event::Completed(Completed::new(
+                "gs://bucket/clients".to_string(),
+                started.reference(),
+            )),
+        );
+
+        let failing = Started::new("clients".to_string()).unwrap();
+        record_transition(&failing, &Event::Started(failing.clone()));
+        record_transition(
+            &failing,
+            &Event::Failed(Failed::new(
+                "Failed to upload bson file: connection reset".to_string(),
+                failing.reference(),
+            )),
+        );
+
+        assert_eq!(
+            recorder.counter("archive_runs_started_total{collection=clients}"),
+            2
+        );
+        assert_eq!(
+            recorder.counter("archive_runs_completed_total{collection=clients}"),
+            1
+        );
+        assert_eq!(
+            recorder.counter(
+                "archive_runs_failed_total{collection=clients,reason=Failed to upload bson file}"
+            ),
+            1
+        );
+
+        let completed = recorder
+            .histogram("archive_run_duration_seconds{collection=clients,outcome=completed}");
+        assert_eq!(completed.len(), 1);
+        assert!((90.0..100.0).contains(&completed[0]));
+        let failed =
+            recorder.histogram("archive_run_duration_seconds{collection=clients,outcome=failed}");
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0] < 10.0);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::event::{Event, EventMetadata};
+use anyhow::{anyhow, Result};
+use bson::doc;
+use chrono::{DateTime, Utc};
+use integrationos_domain::{Id, MongoStore};
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use strum::{AsRefStr, EnumString};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, AsRefStr)]
+#[strum(serialize_all = "kebab-case")]
+pub enum ReportFormat {
+    Json,
+    Text,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    Completed,
+    Failed,
+    InProgress,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transition {
+    pub event: &'static str,
+    pub at: DateTime<Utc>,
+    /// Time elapsed since the previous transition, zero for the first one
+    pub since_previous_millis: i64,
+    pub detail: Option<String>,
+}
+
+/// Chronological lifecycle of an archive run, assembled from the events sharing its reference
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Timeline {
+    pub reference: Id,
+    pub outcome: Outcome,
+    pub duration_millis: i64,
+    pub transitions: Vec<Transition>,
+}
+
+impl Timeline {
+    pub fn new(reference: Id, mut events: Vec<Event>) -> Self {
+        events.retain(|event| event.reference() == reference);
+        events.sort_by_key(Event::timestamp);
+
+        let outcome = match events.last() {
+            Some(Event::Completed(_)) => Outcome::Completed,
+            Some(Event::Failed(_)) => Outcome::Failed,
+            _ => Outcome::InProgress,
+        };
+
+        let mut previous = None;
+        let transitions = events
+            .iter()
+            .map(|event| {
+                let at = event.timestamp();
+                let since_previous = previous.map(|previous| at - previous).unwrap_or_default();
+                previous = Some(at);
+
+                Transition {
+                    event: event.name(),
+                    at,
+                    since_previous_millis: since_previous.num_milliseconds(),
+                    detail: match event {
+                        Event::Started(e) => Some(format!("collection {}", e.collection())),
+                        Event::Failed(e) => Some(e.reason().to_owned()),
+                        Event::Restore(e) => Some(format!(
+                            "restored {} documents of {}",
+                            e.documents(),
+                            e.archive()
+                        )),
+                        Event::Completed(e) => Some(format!("saved to {}", e.path())),
+                        Event::Dumped(_) | Event::Uploaded(_) => None,
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let duration_millis = match (transitions.first(), transitions.last()) {
+            (Some(first), Some(last)) => (last.at - first.at).num_milliseconds(),
+            _ => 0,
+        };
+
+        Self {
+            reference,
+            outcome,
+            duration_millis,
+            transitions,
+        }
+    }
+
+    pub async fn fetch(archives: &MongoStore<Event>, reference: Id) -> Result<Self> {
+        // Started events hold the reference as their id, the other events refer to it
+        let filter = doc! {
+            "$or": [
+                { "_id": reference.to_string() },
+                { "id": reference.to_string() },
+            ]
+        };
+        let events = archives
+            .get_many(Some(filter), None, None, None, None)
+            .await?;
+        if events.is_empty() {
+            return Err(anyhow!("No archive run found with reference {reference}"));
+        }
+
+        Ok(Self::new(reference, events))
+    }
+
+    pub fn render(&self, format: ReportFormat) -> Result<String> {
+        match format {
+            ReportFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+            ReportFormat::Text => Ok(self.to_string()),
+        }
+    }
+}
+
+impl Display for Timeline {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Archive run {}", self.reference)?;
+        for transition in &self.transitions {
+            write!(
+                f,
+                "{} {:<10} +{}ms",
+                transition.at.to_rfc3339(),
+                transition.event,
+                transition.since_previous_millis
+            )?;
+            match &transition.detail {
+                Some(detail) => writeln!(f, " {detail}")?,
+                None => writeln!(f)?,
+            }
+        }
+        write!(
+            f,
+            "Outcome: {:?} after {}ms",
+            self.outcome, self.duration_millis
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{completed::Completed, dumped::Dumped, started::Started};
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_timeline_of_completed_run() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let started = Started::new("clients".to_string())
+            .unwrap()
+            .with_date(start);
+        let reference = started.reference();
+        let dumped = Dumped::new(reference).with_date(start + chrono::Duration::seconds(90));
+        let completed = Completed::new("gs://bucket/clients".to_string(), reference)
+            .with_date(start + chrono::Duration::seconds(150));
+        let other_run = Dumped::new(Started::new("clients".to_string()).unwrap().reference());
+
+        // Events are stored out of order and alongside the events of other runs
+        let timeline = Timeline::new(
+            reference,
+            vec![
+                Event::Completed(completed),
+                Event::Dumped(other_run),
+                Event::Started(started),
+                Event::Dumped(dumped),
+            ],
+        );
+
+        assert_eq!(timeline.outcome, Outcome::Completed);
+        assert_eq!(timeline.duration_millis, 150_000);
+        let transitions = timeline
+            .transitions
+            .iter()
+            .map(|t| (t.event, t.since_previous_millis))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            transitions,
+            vec![("started", 0), ("dumped", 90_000), ("completed", 60_000)]
+        );
+
+        let json = serde_json::from_str::<serde_json::Value>(
+            &timeline.render(ReportFormat::Json).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["outcome"], "completed");
+        assert_eq!(json["transitions"][1]["sincePreviousMillis"], 90_000);
+
+        let text = timeline.render(ReportFormat::Text).unwrap();
+        assert!(text.contains("completed  +60000ms saved to gs://bucket/clients"));
+        assert!(text.ends_with("Outcome: Completed after 150000ms"));
+    }
+}
@@ -0,0 +1,246 @@
+use anyhow::{anyhow, Result};
+use bson::{doc, Document};
+use futures::TryStreamExt;
+use integrationos_domain::{secret::Secret, CryptoExt, MongoStore, SecretVersion, Store};
+use mongodb::Database;
+use secrecy::ExposeSecret;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// Secrets read from the store at once
+const BATCH_SIZE: usize = 500;
+/// Secrets between two progress logs
+const PROGRESS_INTERVAL: u64 = 100;
+
+/// What rotating a secret did, or would do in a dry run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rotation {
+    /// The secret was encrypted with the old key, this is its ciphertext under the new one
+    Rewritten(String),
+    /// The secret is already encrypted with the new key, by an interrupted rotation
+    AlreadyRotated,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RotationSummary {
+    pub referenced: u64,
+    pub rewritten: u64,
+    pub already_rotated: u64,
+    /// Secrets that changed between being read and being rewritten, left as they are
+    pub skipped: u64,
+}
+
+impl RotationSummary {
+    fn processed(&self) -> u64 {
+        self.rewritten + self.already_rotated + self.skipped
+    }
+}
+
+/// Re-encrypts secrets from the key of `old` to the one of `new`
+pub struct KeyRotation {
+    old: Arc<dyn CryptoExt + Sync + Send>,
+    new: Arc<dyn CryptoExt + Sync + Send>,
+}
+
+impl KeyRotation {
+    pub fn new(
+        old: Arc<dyn CryptoExt + Sync + Send>,
+        new: Arc<dyn CryptoExt + Sync + Send>,
+    ) -> Self {
+        Self { old, new }
+    }
+
+    /// Ciphertext of `secret` under the new key. Secrets the new key decrypts already are
+    /// left as they are, so that a rotation can be run again after being interrupted.
+    pub async fn rotate(&self, secret: &Secret) -> Result<Rotation> {
+        let encrypted = secret.encrypted_secret().expose_secret().to_owned();
+        if self
+            .new
+            .decrypt(encrypted.clone(), Some(SecretVersion::V2))
+            .await
+            .is_ok()
+        {
+            return Ok(Rotation::AlreadyRotated);
+        }
+
+        let decrypted = self
+            .old
+            .decrypt(encrypted, secret.version())
+            .await
+            .map_err(|e| anyhow!("Failed to decrypt secret {}: {e}", secret.id()))?;
+        let rotated = self.new.encrypt(decrypted.clone()).await?;
+
+        if self
+            .new
+            .decrypt(rotated.clone(), Some(SecretVersion::V2))
+            .await?
+            != decrypted
+        {
+            return Err(anyhow!(
+                "Secret {} does not decrypt to its value under the new key",
+                secret.id()
+            ));
+        }
+
+        Ok(Rotation::Rewritten(rotated))
+    }
+}
+
+/// Ids of the secrets of the connections and of the platforms of the settings
+pub async fn referenced_secrets(database: &Database) -> Result<BTreeSet<String>> {
+    let connections = database
+        .collection::<Document>(Store::Connections.to_string().as_str())
+        .distinct("secretsServiceId", None, None)
+        .await?;
+    let settings = database
+        .collection::<Document>(Store::Settings.to_string().as_str())
+        .distinct("connectedPlatforms.secretsServiceId", None, None)
+        .await?;
+
+    Ok(connections
+        .into_iter()
+        .chain(settings)
+        .filter_map(|id| id.as_str().map(ToOwned::to_owned))
+        .collect())
+}
+
+/// Rotates the key of the secrets referenced by the connections and settings of `database`.
+/// Each secret is rewritten on its own as soon as it is re-encrypted, the ones rewritten by a
+/// previous run being skipped, so an interrupted rotation resumes where it stopped when run
+/// again. A dry run counts the secrets that would be rewritten without writing any.
+pub async fn rotate_secrets(
+    database: &Database,
+    rotation: &KeyRotation,
+    dry_run: bool,
+) -> Result<RotationSummary> {
+    let ids = referenced_secrets(database)
+        .await?
+        .into_iter()
+        .collect::<Vec<_>>();
+    let secrets: MongoStore<Secret> = MongoStore::new(database, &Store::Secrets).await?;
+    let mut summary = RotationSummary {
+        referenced: ids.len() as u64,
+        ..Default::default()
+    };
+
+    for ids in ids.chunks(BATCH_SIZE) {
+        let mut cursor = secrets
+            .collection
+            .find(doc! { "_id": { "$in": ids } }, None)
+            .await?;
+
+        while let Some(secret) = cursor.try_next().await? {
+            match rotation.rotate(&secret).await? {
+                Rotation::AlreadyRotated => summary.already_rotated += 1,
+                Rotation::Rewritten(_) if dry_run => summary.rewritten += 1,
+                Rotation::Rewritten(rotated) => {
+                    let updated = secrets
+                        .collection
+                        .update_one(
+                            doc! {
+                                "_id": secret.id(),
+                                "encryptedSecret": secret.encrypted_secret().expose_secret(),
+                            },
+                            doc! {
+                                "$set": {
+                                    "encryptedSecret": rotated,
+                                    "version": bson::to_bson(&SecretVersion::V2)?,
+                                }
+                            },
+                            None,
+                        )
+                        .await?;
+
+                    if updated.modified_count == 1 {
+                        summary.rewritten += 1;
+                    } else {
+                        tracing::warn!(
+                            "Secret {} changed while being rotated and was left as it is",
+                            secret.id()
+                        );
+                        summary.skipped += 1;
+                    }
+                }
+            }
+
+            if summary.processed() % PROGRESS_INTERVAL == 0 {
+                tracing::info!(
+                    "Rotated {} of {} secrets, {} were already rotated",
+                    summary.processed(),
+                    summary.referenced,
+                    summary.already_rotated
+                );
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integrationos_domain::{secrets::SecretsConfig, IOSCrypto};
+    use secrecy::SecretString;
+
+    fn crypto(key: &str) -> Arc<IOSCrypto> {
+        Arc::new(
+            IOSCrypto::new(SecretsConfig {
+                ios_crypto_secret: SecretString::new(key.to_owned()),
+                ..Default::default()
+            })
+            .expect("Failed to create IOSCrypto"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_rotated_secrets_decrypt_under_the_new_key() {
+        let old = crypto("xTtUQejH8eSNmWP5rlnHLkOWkHeflivG");
+        let new = crypto("Y0bJw5Kq2dZr8TfLmN3pVx6sHc9gAe1U");
+        let rotation = KeyRotation::new(old.clone(), new.clone());
+
+        let values = ["first", "second", "third", "fourth"];
+        let mut seeded = Vec::new();
+        for (i, value) in values.iter().enumerate() {
+            // The last secret was rotated by a previous, interrupted run
+            let crypto = if i == values.len() - 1 { &new } else { &old };
+            let encrypted = crypto.encrypt(value.to_string()).await.unwrap();
+            seeded.push(Secret::new(
+                encrypted,
+                Some(SecretVersion::V2),
+                "buildable_id".to_owned(),
+                None,
+            ));
+        }
+
+        let mut rewritten = 0;
+        for (secret, value) in seeded.iter().zip(values) {
+            let rotated = match rotation.rotate(secret).await.unwrap() {
+                Rotation::Rewritten(rotated) => {
+                    rewritten += 1;
+                    rotated
+                }
+                Rotation::AlreadyRotated => secret.encrypted_secret().expose_secret().to_owned(),
+            };
+
+            assert_eq!(new.decrypt(rotated.clone(), None).await.unwrap(), value);
+            assert!(old.decrypt(rotated, None).await.is_err());
+        }
+        assert_eq!(rewritten, 3);
+    }
+
+    #[tokio::test]
+    async fn test_secrets_of_another_key_are_not_rotated() {
+        let rotation = KeyRotation::new(
+            crypto("xTtUQejH8eSNmWP5rlnHLkOWkHeflivG"),
+            crypto("Y0bJw5Kq2dZr8TfLmN3pVx6sHc9gAe1U"),
+        );
+        let encrypted = crypto("Qm7Zp4Tb1Rk9Wc2Xv5Ns8Jd3Hf6Lg0Ya")
+            .encrypt("value".to_owned())
+            .await
+            .unwrap();
+        let secret = Secret::new(encrypted, None, "buildable_id".to_owned(), None);
+
+        assert!(rotation.rotate(&secret).await.is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::{BufReader, ErrorKind, Read},
+    path::Path,
+};
+
+/// Size of the length prefix of a BSON document
+const LENGTH_BYTES: usize = 4;
+
+/// Rolling digest of the documents of a dump, recorded when it is dumped and computed again
+/// when it is restored so that a corrupted or truncated dump isn't restored
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpChecksum {
+    pub documents: u64,
+    /// Hex encoded SHA-256 of the documents, in the order they were dumped
+    pub sha256: String,
+}
+
+impl DumpChecksum {
+    /// Checksum of the BSON file at `path`, as written by `mongodump`
+    pub fn of_file(path: &Path) -> Result<Self> {
+        Self::of_reader(BufReader::new(File::open(path)?))
+            .map_err(|e| anyhow!("Could not read dump {}: {e}", path.display()))
+    }
+
+    /// Reads the documents of `reader` one at a time, adding each to the digest as it is read
+    pub fn of_reader(reader: impl Read) -> Result<Self> {
+        let mut checksum = RollingChecksum::default();
+        for document in Documents::new(reader) {
+            checksum.add(&document?);
+        }
+
+        Ok(checksum.finish())
+    }
+}
+
+/// Checksum of documents added one at a time, as they are read or written
+#[derive(Default)]
+pub struct RollingChecksum {
+    hasher: Sha256,
+    documents: u64,
+}
+
+impl RollingChecksum {
+    pub fn add(&mut self, document: &[u8]) {
+        self.hasher.update(document);
+        self.documents += 1;
+    }
+
+    pub fn finish(self) -> DumpChecksum {
+        DumpChecksum {
+            documents: self.documents,
+            sha256: format!("{:x}", self.hasher.finalize()),
+        }
+    }
+}
+
+/// Documents of a BSON file as written by `mongodump`, each read with its length prefix
+pub struct Documents<R> {
+    reader: R,
+    index: u64,
+}
+
+impl<R: Read> Documents<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, index: 0 }
+    }
+
+    fn read_document(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut length = [0u8; LENGTH_BYTES];
+        match self.reader.read_exact(&mut length[..1]) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        self.read_exact(&mut length[1..])?;
+
+        let size = i32::from_le_bytes(length);
+        // The smallest document is its length followed by its terminating null byte
+        if size < LENGTH_BYTES as i32 + 1 {
+            return Err(anyhow!(
+                "Document {} has an invalid length of {size} bytes",
+                self.index
+            ));
+        }
+
+        let mut document = vec![0; size as usize];
+        document[..LENGTH_BYTES].copy_from_slice(&length);
+        self.read_exact(&mut document[LENGTH_BYTES..])?;
+        if document.last() != Some(&0) {
+            return Err(anyhow!("Document {} is not null terminated", self.index));
+        }
+
+        self.index += 1;
+        Ok(Some(document))
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.reader.read_exact(buf).map_err(|e| match e.kind() {
+            ErrorKind::UnexpectedEof => anyhow!("Dump is truncated in document {}", self.index),
+            _ => e.into(),
+        })
+    }
+}
+
+impl<R: Read> Iterator for Documents<R> {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_document().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bson::doc;
+
+    fn dump(documents: u32) -> Vec<u8> {
+        (0..documents)
+            .flat_map(|i| bson::to_vec(&doc! { "_id": i, "name": "event" }).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_checksum_counts_and_hashes_documents() {
+        let checksum = DumpChecksum::of_reader(dump(3).as_slice()).unwrap();
+        assert_eq!(checksum.documents, 3);
+        assert_eq!(checksum.sha256.len(), 64);
+        assert_eq!(
+            DumpChecksum::of_reader(dump(3).as_slice()).unwrap(),
+            checksum
+        );
+
+        // A dump cut at a document boundary only differs by its checksum
+        let shorter = DumpChecksum::of_reader(dump(2).as_slice()).unwrap();
+        assert_eq!(shorter.documents, 2);
+        assert_ne!(shorter.sha256, checksum.sha256);
+
+        let empty = DumpChecksum::of_reader([].as_slice()).unwrap();
+        assert_eq!(empty.documents, 0);
+    }
+
+    #[test]
+    fn test_truncated_dump_is_rejected() {
+        let dump = dump(3);
+
+        for cut in [dump.len() - 1, dump.len() - 20, 2] {
+            let err = DumpChecksum::of_reader(&dump[..cut]).unwrap_err();
+            assert!(err.to_string().contains("Dump is truncated"), "{err}");
+        }
+    }
+}
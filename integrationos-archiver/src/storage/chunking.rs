@@ -0,0 +1,232 @@
+use super::checksum::{Documents, DumpChecksum, RollingChecksum};
+use anyhow::Result;
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// Largest chunks a dump is split into, a dump is a single chunk when neither limit is set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChunkLimits {
+    pub max_documents: Option<u64>,
+    pub max_bytes: Option<u64>,
+}
+
+impl ChunkLimits {
+    /// Whether a chunk holding `documents` documents in `bytes` bytes can't take a document of
+    /// `size` bytes more. An empty chunk takes any document, so that a document larger than
+    /// `max_bytes` makes a chunk of its own.
+    fn is_full(&self, documents: u64, bytes: u64, size: u64) -> bool {
+        documents > 0
+            && (self.max_documents.is_some_and(|max| documents >= max)
+                || self.max_bytes.is_some_and(|max| bytes + size > max))
+    }
+}
+
+/// Name of the files of the chunk `index` of a dump of `total` chunks of the collection `name`.
+/// Dumps of a single chunk are named after their collection, as the dumps made before they
+/// were chunked.
+pub fn chunk_name(name: &str, index: u32, total: u32) -> String {
+    if total > 1 {
+        format!("{name}-chunk-{index}")
+    } else {
+        name.to_owned()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DumpChunk {
+    /// Path of the files of the chunk, without their extension
+    pub base_path: PathBuf,
+    pub bytes: u64,
+    pub checksum: DumpChecksum,
+}
+
+struct ChunkWriter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    documents: u64,
+    bytes: u64,
+    checksum: RollingChecksum,
+}
+
+impl ChunkWriter {
+    fn create(path: PathBuf) -> Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(&path)?),
+            path,
+            documents: 0,
+            bytes: 0,
+            checksum: RollingChecksum::default(),
+        })
+    }
+
+    fn write(&mut self, document: &[u8]) -> Result<()> {
+        self.writer.write_all(document)?;
+        self.documents += 1;
+        self.bytes += document.len() as u64;
+        self.checksum.add(document);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(PathBuf, u64, DumpChecksum)> {
+        self.writer.flush()?;
+        Ok((self.path, self.bytes, self.checksum.finish()))
+    }
+}
+
+/// Splits the BSON file `name.extension` of `directory`, as written by `mongodump`, into
+/// chunks within `limits`, removing it. Documents are never split across chunks, and each
+/// chunk is checksummed as it is written.
+pub fn split_dump(
+    directory: &Path,
+    name: &str,
+    extension: &str,
+    limits: ChunkLimits,
+) -> Result<Vec<DumpChunk>> {
+    let source = directory.join(name).with_extension(extension);
+    let chunk_path = |index: u32| {
+        directory
+            .join(format!("{name}-chunk-{index}"))
+            .with_extension(extension)
+    };
+
+    let mut chunks = vec![];
+    let mut current = ChunkWriter::create(chunk_path(0))?;
+    for document in Documents::new(BufReader::new(File::open(&source)?)) {
+        let document = document?;
+        if limits.is_full(current.documents, current.bytes, document.len() as u64) {
+            let next = ChunkWriter::create(chunk_path(chunks.len() as u32 + 1))?;
+            chunks.push(std::mem::replace(&mut current, next).finish()?);
+        }
+        current.write(&document)?;
+    }
+    chunks.push(current.finish()?);
+    fs::remove_file(&source)?;
+
+    let total = chunks.len() as u32;
+    chunks
+        .into_iter()
+        .zip(0..)
+        .map(|((path, bytes, checksum), index)| {
+            let base_path = directory.join(chunk_name(name, index, total));
+            let final_path = base_path.with_extension(extension);
+            if final_path != path {
+                fs::rename(&path, &final_path)?;
+            }
+
+            Ok(DumpChunk {
+                base_path,
+                bytes,
+                checksum,
+            })
+        })
+        .collect()
+}
+
+/// Concatenates the files of `chunks`, in order, into `destination`, removing them, and
+/// returns the size of `destination`
+pub fn join_chunks(chunks: &[PathBuf], destination: &Path) -> Result<u64> {
+    let mut writer = BufWriter::new(File::create(destination)?);
+    for chunk in chunks {
+        io::copy(&mut BufReader::new(File::open(chunk)?), &mut writer)?;
+        fs::remove_file(chunk)?;
+    }
+    writer.flush()?;
+
+    Ok(destination.metadata()?.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bson::doc;
+    use tempfile::TempDir;
+
+    fn document(i: u32) -> Vec<u8> {
+        bson::to_vec(&doc! { "_id": i, "name": "event" }).unwrap()
+    }
+
+    fn dump(dir: &TempDir, documents: u32) -> Vec<u8> {
+        let dump = (0..documents).flat_map(document).collect::<Vec<_>>();
+        fs::write(dir.path().join("clients.bson"), &dump).unwrap();
+        dump
+    }
+
+    fn documents(chunks: &[DumpChunk]) -> Vec<u64> {
+        chunks
+            .iter()
+            .map(|chunk| chunk.checksum.documents)
+            .collect()
+    }
+
+    #[test]
+    fn test_dump_is_split_at_the_chunk_boundaries() {
+        let dir = TempDir::new().unwrap();
+        // Documents all have the same size
+        let size = document(0).len() as u64;
+        let limits = |max_documents, max_bytes| ChunkLimits {
+            max_documents,
+            max_bytes,
+        };
+
+        for (limits, expected) in [
+            (limits(Some(4), None), vec![4, 4, 2]),
+            // A dump filling its last chunk exactly doesn't end with an empty chunk
+            (limits(Some(5), None), vec![5, 5]),
+            (limits(None, Some(size * 3)), vec![3, 3, 3, 1]),
+            (limits(None, Some(size * 3 - 1)), vec![2, 2, 2, 2, 2]),
+            (limits(Some(2), Some(size * 3)), vec![2, 2, 2, 2, 2]),
+            // Documents larger than the chunks make chunks of their own
+            (limits(None, Some(10)), vec![1; 10]),
+        ] {
+            let dump = dump(&dir, 10);
+            let chunks = split_dump(dir.path(), "clients", "bson", limits).unwrap();
+            assert_eq!(documents(&chunks), expected, "{limits:?}");
+            assert!(!dir.path().join("clients.bson").exists());
+
+            let paths = chunks
+                .iter()
+                .enumerate()
+                .map(|(index, chunk)| {
+                    assert_eq!(
+                        chunk.base_path,
+                        dir.path().join(format!("clients-chunk-{index}"))
+                    );
+                    let path = chunk.base_path.with_extension("bson");
+                    assert_eq!(DumpChecksum::of_file(&path).unwrap(), chunk.checksum);
+                    path
+                })
+                .collect::<Vec<_>>();
+
+            let restored = dir.path().join("restored.bson");
+            join_chunks(&paths, &restored).unwrap();
+            assert_eq!(fs::read(&restored).unwrap(), dump);
+        }
+    }
+
+    #[test]
+    fn test_dump_within_limits_is_a_single_chunk() {
+        let dir = TempDir::new().unwrap();
+
+        for limits in [
+            ChunkLimits::default(),
+            ChunkLimits {
+                max_documents: Some(10),
+                max_bytes: None,
+            },
+        ] {
+            let dump = dump(&dir, 10);
+            let chunks = split_dump(dir.path(), "clients", "bson", limits).unwrap();
+            assert_eq!(documents(&chunks), [10]);
+            assert_eq!(chunks[0].base_path, dir.path().join("clients"));
+            assert_eq!(chunks[0].bytes, dump.len() as u64);
+            assert_eq!(fs::read(dir.path().join("clients.bson")).unwrap(), dump);
+        }
+
+        dump(&dir, 0);
+        let chunks = split_dump(dir.path(), "clients", "bson", ChunkLimits::default()).unwrap();
+        assert_eq!(documents(&chunks), [0]);
+    }
+}
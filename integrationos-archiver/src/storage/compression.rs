@@ -0,0 +1,118 @@
+use anyhow::{anyhow, Result};
+use flate2::{read::MultiGzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+    process::Command,
+};
+use strum::{AsRefStr, EnumString};
+
+/// Codec of the files of a dump. Files are compressed and decompressed as streams, zstd uses
+/// the `zstd` command line tool in the same way dumps use `mongodump`.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumString, AsRefStr, Default,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum Compression {
+    None,
+    #[default]
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Compresses `source` into `destination`, removing `source`, and returns the size of the
+    /// compressed file
+    pub fn compress(self, source: &Path, destination: &Path) -> Result<u64> {
+        match self {
+            Compression::None => fs::rename(source, destination)?,
+            Compression::Gzip => {
+                let mut reader = BufReader::new(File::open(source)?);
+                let mut encoder = GzEncoder::new(
+                    BufWriter::new(File::create(destination)?),
+                    flate2::Compression::default(),
+                );
+                io::copy(&mut reader, &mut encoder)?;
+                encoder.finish()?.flush()?;
+                fs::remove_file(source)?;
+            }
+            Compression::Zstd => zstd(&["-q", "-f", "--rm"], source, destination)?,
+        }
+
+        Ok(destination.metadata()?.len())
+    }
+
+    /// Decompresses `source` into `destination`, removing `source`, and returns the size of the
+    /// decompressed file
+    pub fn decompress(self, source: &Path, destination: &Path) -> Result<u64> {
+        match self {
+            Compression::None => fs::rename(source, destination)?,
+            Compression::Gzip => {
+                let mut decoder = MultiGzDecoder::new(BufReader::new(File::open(source)?));
+                let mut writer = BufWriter::new(File::create(destination)?);
+                io::copy(&mut decoder, &mut writer)?;
+                writer.flush()?;
+                fs::remove_file(source)?;
+            }
+            Compression::Zstd => zstd(&["-d", "-q", "-f", "--rm"], source, destination)?,
+        }
+
+        Ok(destination.metadata()?.len())
+    }
+}
+
+fn zstd(args: &[&str], source: &Path, destination: &Path) -> Result<()> {
+    let output = Command::new("zstd")
+        .args(args)
+        .arg("-o")
+        .arg(destination)
+        .arg(source)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Command zstd failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_compressed_dump_restores_identical_to_uncompressed() {
+        let dir = TempDir::new().expect("Failed to create temp dir");
+        let dump = (0..10_000)
+            .flat_map(|i: u32| format!("{{\"_id\":{i},\"name\":\"event\"}}").into_bytes())
+            .collect::<Vec<_>>();
+
+        for compression in [Compression::None, Compression::Gzip] {
+            let source = dir.path().join("clients.bson");
+            let compressed = dir.path().join("clients.bson.compressed");
+            let restored = dir.path().join("clients.restored.bson");
+            fs::write(&source, &dump).unwrap();
+
+            let compressed_size = compression
+                .compress(&source, &compressed)
+                .expect("Failed to compress");
+            assert!(!source.exists());
+            if compression == Compression::Gzip {
+                assert!(compressed_size < dump.len() as u64);
+            }
+
+            let restored_size = compression
+                .decompress(&compressed, &restored)
+                .expect("Failed to decompress");
+            assert_eq!(restored_size, dump.len() as u64);
+            assert_eq!(fs::read(&restored).unwrap(), dump);
+        }
+    }
+}
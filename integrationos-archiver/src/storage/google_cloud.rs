@@ -64,9 +64,10 @@ impl Storage for GoogleCloudStorage {
         &self,
         config: &ArchiverConfig,
         event: &Event,
+        name: &str,
         extension: &Extension,
     ) -> Result<PathBuf> {
-        download_file_google(config, &self.client, event, extension).await
+        download_file_google(config, &self.client, event, name, extension).await
     }
 }
 
@@ -74,6 +75,7 @@ async fn download_file_google(
     config: &ArchiverConfig,
     storage: &GClient,
     event: &Event,
+    name: &str,
     extension: &Extension,
 ) -> Result<PathBuf> {
     let objects = storage
@@ -91,7 +93,7 @@ async fn download_file_google(
 
     tracing::info!("Found {:?} objects in the bucket", names);
 
-    let archive_name = find_latest_archive(&names, config, event, extension)?;
+    let archive_name = find_latest_archive(&names, name, event, extension)?;
 
     let mut download = storage
         .download_streamed_object(
@@ -108,7 +110,7 @@ async fn download_file_google(
         .suffix(&archive_name.extension.with_leading_dot())
         .prefix(&format!(
             "{}.{}-",
-            config.event_collection_name,
+            archive_name.name,
             archive_name.date.format("%Y-%m-%d")
         ))
         .tempfile()?
@@ -232,16 +234,8 @@ fn get_file_name(path: &Path) -> Result<String> {
     Ok(file_name)
 }
 
-fn parse_archive_name(
-    name: &str,
-    config: &ArchiverConfig,
-    extension: &Extension,
-) -> Option<ArchiveName> {
-    let expected_suffix = format!(
-        "-{}{}",
-        config.event_collection_name,
-        extension.with_leading_dot()
-    );
+fn parse_archive_name(name: &str, archive: &str, extension: &Extension) -> Option<ArchiveName> {
+    let expected_suffix = format!("-{}{}", archive, extension.with_leading_dot());
 
     if let Some(point) = name.rfind(&expected_suffix) {
         if point > 0 && point + expected_suffix.len() <= name.len() {
@@ -249,16 +243,10 @@ fn parse_archive_name(
             match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
                 Ok(date) => {
                     let file_name = file_name[1..].to_string(); // Skip the leading hyphen
-                    if file_name
-                        == format!(
-                            "{}{}",
-                            config.event_collection_name,
-                            extension.with_leading_dot()
-                        )
-                    {
+                    if file_name == format!("{}{}", archive, extension.with_leading_dot()) {
                         return Some(ArchiveName {
                             date,
-                            name: config.event_collection_name.clone(),
+                            name: archive.to_owned(),
                             extension: *extension,
                         });
                     }
@@ -277,13 +265,13 @@ fn parse_archive_name(
 
 fn find_latest_archive(
     names: &[String],
-    config: &ArchiverConfig,
+    archive: &str,
     event: &Event,
     extension: &Extension,
 ) -> Result<ArchiveName, anyhow::Error> {
     names
         .iter()
-        .flat_map(|name| parse_archive_name(name, config, extension))
+        .flat_map(|name| parse_archive_name(name, archive, extension))
         .filter(|archive| archive.date == event.date())
         .max_by_key(|archive| archive.date)
         .ok_or_else(|| anyhow!("No valid archive found. Please check the date you are restoring is the same as the event date. Or that there are any archived events for this collection."))
@@ -292,6 +280,7 @@ fn find_latest_archive(
 #[cfg(test)]
 mod tests {
     use crate::event::completed::Completed;
+    use crate::storage::compression::Compression;
 
     use super::*;
     use envconfig::Envconfig;
@@ -334,7 +323,7 @@ mod tests {
 
         let event = Event::Completed(completed);
 
-        let extension = Extension::Bson;
+        let extension = Extension::Bson(Compression::Gzip);
 
         let archive_name = find_latest_archive(
             &[
@@ -349,14 +338,22 @@ mod tests {
                     config.event_collection_name
                 ),
             ],
-            &config,
+            &config.event_collection_name,
             &event,
             &extension,
         )
         .expect("Failed to find latest archive");
 
         assert_eq!(archive_name.name, "clients".to_string());
-        assert_eq!(archive_name.extension, Extension::Bson);
+        assert_eq!(archive_name.extension, Extension::Bson(Compression::Gzip));
+
+        // Chunks of a dump are told apart from each other and from the unchunked dumps
+        let names = [0, 1, 11]
+            .map(|index| format!("{}-clients-chunk-{index}.bson.gz", date.format("%Y-%m-%d")));
+        let archive_name = find_latest_archive(&names, "clients-chunk-1", &event, &extension)
+            .expect("Failed to find chunk");
+        assert_eq!(archive_name.name(), names[1]);
+        assert!(find_latest_archive(&names, "clients", &event, &extension).is_err());
     }
 
     #[tokio::test]
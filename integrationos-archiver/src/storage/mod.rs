@@ -1,8 +1,12 @@
+pub mod checksum;
+pub mod chunking;
+pub mod compression;
 pub mod google_cloud;
 
 use crate::{config::ArchiverConfig, event::Event};
 use anyhow::Result;
 use chrono::NaiveDate;
+use compression::Compression;
 use integrationos_domain::Unit;
 use std::{
     future::Future,
@@ -53,10 +57,11 @@ impl ArchiveName {
     }
 }
 
+/// Files of a dump, named after the codec they are compressed with
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Extension {
-    Bson,
-    Metadata,
+    Bson(Compression),
+    Metadata(Compression),
 }
 
 impl Extension {
@@ -69,8 +74,12 @@ impl Extension {
 impl AsRef<str> for Extension {
     fn as_ref(&self) -> &str {
         match self {
-            Extension::Bson => "bson.gz",
-            Extension::Metadata => "metadata.json.gz",
+            Extension::Bson(Compression::None) => "bson",
+            Extension::Bson(Compression::Gzip) => "bson.gz",
+            Extension::Bson(Compression::Zstd) => "bson.zst",
+            Extension::Metadata(Compression::None) => "metadata.json",
+            Extension::Metadata(Compression::Gzip) => "metadata.json.gz",
+            Extension::Metadata(Compression::Zstd) => "metadata.json.zst",
         }
     }
 }
@@ -91,10 +100,13 @@ pub trait Storage {
         config: &ArchiverConfig,
     ) -> impl Future<Output = Result<Unit>>;
 
+    /// Downloads the file `name` of the archive completed by `event`, `name` being the
+    /// collection of the archive or one of its chunks
     fn download_file(
         &self,
         config: &ArchiverConfig,
         event: &Event,
+        name: &str,
         extension: &Extension,
     ) -> impl Future<Output = Result<PathBuf>>;
 }
@@ -1,15 +1,52 @@
-use crate::LocalCacheExt;
+use crate::{
+    local::eviction::{eviction_listener, record_eviction},
+    LocalCacheExt,
+};
+use chrono::{DateTime, Utc};
 use http::HeaderValue;
-use integrationos_domain::{Connection, IntegrationOSError, MongoStore, Unit};
-use moka::future::Cache;
+use integrationos_domain::{
+    ApplicationError, Clock, Connection, IntegrationOSError, MongoStore, SystemClock, Unit,
+};
+use moka::{future::Cache, notification::RemovalCause};
 use mongodb::bson::Document;
 use std::fmt::Debug;
 use std::hash::Hash;
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
 
+const CACHE_NAME: &str = "connections";
+
+/// A connection looked up through [`ConnectionCacheForKey::get_or_insert_with_filter_or_stale`]
+#[derive(Debug, Clone)]
+pub struct CachedConnection {
+    pub connection: Connection,
+    /// Whether the connection is a last-known copy served because the database was unavailable
+    pub stale: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    connection: Connection,
+    expires_at: DateTime<Utc>,
+}
+
+/// Gives every tenant a cache of its own with a fixed capacity, so that a tenant looking up
+/// many connections only evicts its own entries
+struct TenantPartitions<K: Clone + Send + Sync + Eq + Hash + Debug + 'static> {
+    caches: Cache<Arc<str>, Arc<Cache<K, Entry>>>,
+    capacity: u64,
+    tenant: fn(&K) -> Arc<str>,
+}
+
+/// Connections expire according to `clock` rather than the internal clock of the cache, so
+/// that tests can expire them without waiting
 #[derive(Clone)]
 pub struct ConnectionCacheForKey<K: Clone + Send + Sync + Eq + Hash + Debug + 'static> {
-    inner: Arc<Cache<K, Connection>>,
+    inner: Arc<Cache<K, Entry>>,
+    /// Last-known connections, kept past the TTL of `inner` to survive database outages
+    last_known: Option<Arc<Cache<K, Connection>>>,
+    partitions: Option<Arc<TenantPartitions<K>>>,
+    ttl: chrono::Duration,
+    clock: Arc<dyn Clock>,
 }
 
 impl<K: Clone + Send + Sync + Eq + Hash + Debug + 'static> ConnectionCacheForKey<K> {
@@ -18,9 +55,63 @@ impl<K: Clone + Send + Sync + Eq + Hash + Debug + 'static> ConnectionCacheForKey
             inner: Arc::new(
                 Cache::builder()
                     .max_capacity(size)
-                    .time_to_live(Duration::from_secs(ttl))
+                    .eviction_listener(eviction_listener(CACHE_NAME))
                     .build(),
             ),
+            last_known: None,
+            partitions: None,
+            ttl: chrono::Duration::seconds(ttl as i64),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Keeps the last-known value of every connection, without expiry, so that it can be
+    /// served when the database is unavailable
+    pub fn with_stale_fallback(mut self, enabled: bool) -> Self {
+        self.last_known = enabled.then(|| {
+            Arc::new(
+                Cache::builder()
+                    .max_capacity(self.inner.policy().max_capacity().unwrap_or_default())
+                    .eviction_listener(eviction_listener("connections_last_known"))
+                    .build(),
+            )
+        });
+        self
+    }
+
+    /// Partitions the cache by the tenant of the keys, each tenant holding up to `capacity`
+    /// connections. The cache is shared by all tenants otherwise.
+    pub fn with_tenant_partitions(mut self, capacity: u64, tenant: fn(&K) -> Arc<str>) -> Self {
+        self.partitions = Some(Arc::new(TenantPartitions {
+            caches: Cache::new(self.inner.policy().max_capacity().unwrap_or_default()),
+            capacity,
+            tenant,
+        }));
+        self
+    }
+
+    /// Cache holding the entry of `key`
+    async fn entries(&self, key: &K) -> Arc<Cache<K, Entry>> {
+        match &self.partitions {
+            Some(partitions) => {
+                partitions
+                    .caches
+                    .get_with((partitions.tenant)(key), async {
+                        Arc::new(
+                            Cache::builder()
+                                .max_capacity(partitions.capacity)
+                                .eviction_listener(eviction_listener(CACHE_NAME))
+                                .build(),
+                        )
+                    })
+                    .await
+            }
+            None => self.inner.clone(),
         }
     }
 
@@ -30,21 +121,136 @@ impl<K: Clone + Send + Sync + Eq + Hash + Debug + 'static> ConnectionCacheForKey
         store: MongoStore<Connection>,
         filter: Document,
     ) -> Result<Connection, IntegrationOSError> {
-        self.inner
-            .get_or_insert_with_filter(&key, store, filter)
+        self.get_or_insert_with_filter_or_stale(key, store, filter)
             .await
+            .map(|cached| cached.connection)
+    }
+
+    /// Same as [`Self::get_or_insert_with_filter`], but if the database fails while looking up
+    /// a connection missing from the cache, the last-known value is served instead and flagged
+    /// as stale. Without the stale fallback enabled, database errors are returned as is.
+    pub async fn get_or_insert_with_filter_or_stale(
+        &self,
+        key: K,
+        store: MongoStore<Connection>,
+        filter: Document,
+    ) -> Result<CachedConnection, IntegrationOSError> {
+        if let Some(connection) = self.get(key.clone()).await? {
+            tracing::debug!("Cache hit for key: {:?}", key);
+            return Ok(CachedConnection {
+                connection,
+                stale: false,
+            });
+        }
+
+        tracing::debug!("Cache miss for key: {:?}", key);
+        match store.get_one(filter).await {
+            Ok(Some(connection)) => {
+                self.set(key, &connection).await?;
+                Ok(CachedConnection {
+                    connection,
+                    stale: false,
+                })
+            }
+            Ok(None) => {
+                tracing::warn!("Value with id {:?} not found", key);
+                Err(ApplicationError::not_found("Value not found", None))
+            }
+            Err(e) => {
+                let last_known = match &self.last_known {
+                    Some(last_known) => last_known.get(&key).await?,
+                    None => None,
+                };
+
+                match last_known {
+                    Some(connection) => {
+                        tracing::warn!(
+                            "Serving stale connection for key {:?} after database error: {e}",
+                            key
+                        );
+                        Ok(CachedConnection {
+                            connection,
+                            stale: true,
+                        })
+                    }
+                    None => Err(e),
+                }
+            }
+        }
     }
 
     pub async fn get(&self, key: K) -> Result<Option<Connection>, IntegrationOSError> {
-        self.inner.get(&key).await
+        let entries = self.entries(&key).await;
+        match entries.get(&key).await {
+            Some(entry) if entry.expires_at > self.clock.now() => Ok(Some(entry.connection)),
+            Some(_) => {
+                // Expiry is checked here rather than by the cache, so it is recorded here too
+                record_eviction(CACHE_NAME, RemovalCause::Expired);
+                entries.invalidate(&key).await;
+                Ok(None)
+            }
+            None => Ok(None),
+        }
     }
 
     pub async fn set(&self, key: K, value: &Connection) -> Result<Unit, IntegrationOSError> {
-        self.inner.set(&key, value).await
+        if let Some(last_known) = &self.last_known {
+            last_known.set(&key, value).await?;
+        }
+        let entry = Entry {
+            connection: value.clone(),
+            expires_at: self.clock.now() + self.ttl,
+        };
+        self.entries(&key).await.insert(key, entry).await;
+        Ok(())
     }
 
     pub async fn remove(&self, key: K) -> Result<Unit, IntegrationOSError> {
-        self.inner.remove(&key).await
+        if let Some(last_known) = &self.last_known {
+            last_known.remove(&key).await?;
+        }
+        self.entries(&key).await.invalidate(&key).await;
+        Ok(())
+    }
+
+    /// Drops the cached connections whose key `matches`, including their last-known copies,
+    /// returning the number of cached connections dropped
+    pub async fn invalidate_if(&self, matches: impl Fn(&K) -> bool) -> u64 {
+        let mut caches = vec![self.inner.clone()];
+        if let Some(partitions) = &self.partitions {
+            caches.extend(partitions.caches.iter().map(|(_, cache)| cache));
+        }
+
+        let mut invalidated = 0;
+        for cache in caches {
+            let keys = cache
+                .iter()
+                .filter(|(key, _)| matches(key))
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>();
+            for key in keys {
+                if cache.remove(key.as_ref()).await.is_some() {
+                    invalidated += 1;
+                }
+            }
+        }
+
+        if let Some(last_known) = &self.last_known {
+            let keys = last_known
+                .iter()
+                .filter(|(key, _)| matches(key))
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>();
+            for key in keys {
+                last_known.invalidate(key.as_ref()).await;
+            }
+        }
+        invalidated
+    }
+
+    /// Drops every cached connection, see [`Self::invalidate_if`]
+    pub async fn invalidate_all(&self) -> u64 {
+        self.invalidate_if(|_| true).await
     }
 }
 
@@ -62,4 +268,173 @@ impl ConnectionCacheArcStrHeaderKey {
     pub fn create(size: u64, ttl: u64) -> ConnectionCacheForKey<(Arc<str>, HeaderValue)> {
         ConnectionCacheForKey::new(size, ttl)
     }
+
+    /// Partitions the cache by buildable id, see [`ConnectionCacheForKey::with_tenant_partitions`]
+    pub fn with_buildable_id_partitions(self, capacity: u64) -> Self {
+        self.with_tenant_partitions(capacity, |(buildable_id, _)| buildable_id.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integrationos_domain::{
+        environment::Environment,
+        id::{prefix::IdPrefix, Id},
+        ownership::Ownership,
+        record_metadata::RecordMetadata,
+        settings::Settings,
+        ConnectionType, MockClock, Store, Throughput,
+    };
+    use mongodb::{bson::doc, Client};
+
+    fn connection() -> Connection {
+        Connection {
+            id: Id::now(IdPrefix::Connection),
+            platform_version: "platformVersion".to_string(),
+            connection_definition_id: Id::now(IdPrefix::ConnectionDefinition),
+            r#type: ConnectionType::Api {},
+            name: "name".to_string(),
+            key: "key".into(),
+            group: "group".to_string(),
+            platform: "platform".into(),
+            environment: Environment::Test,
+            mode: Default::default(),
+            secrets_service_id: "secrets_service_id".to_string(),
+            event_access_id: Id::now(IdPrefix::EventAccess),
+            access_key: "accessKey".to_string(),
+            settings: Settings::default(),
+            throughput: Throughput {
+                key: "throughputKey".to_string(),
+                limit: 100,
+            },
+            ownership: Ownership::default(),
+            oauth: None,
+            health: Default::default(),
+            webhook_secret: None,
+            credential_expiry: Default::default(),
+            credentials_rotated_at: None,
+            record_metadata: RecordMetadata::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connection_expires_after_ttl() {
+        let clock = MockClock::default();
+        let cache = ConnectionCacheArcStrKey::create(10, 60).with_clock(Arc::new(clock.clone()));
+        let key: Arc<str> = "key".into();
+        cache
+            .set(key.clone(), &connection())
+            .await
+            .expect("set failed");
+
+        clock.advance(chrono::Duration::seconds(59));
+        assert!(cache.get(key.clone()).await.expect("get failed").is_some());
+
+        clock.advance(chrono::Duration::seconds(1));
+        assert!(cache.get(key).await.expect("get failed").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_heavy_tenant_does_not_evict_other_tenants_under_partitioning() {
+        let cache = ConnectionCacheArcStrHeaderKey::create(10, 60).with_buildable_id_partitions(2);
+        let quiet: (Arc<str>, HeaderValue) = ("quiet".into(), HeaderValue::from_static("key"));
+        cache
+            .set(quiet.clone(), &connection())
+            .await
+            .expect("set failed");
+
+        for i in 0..20 {
+            let key = (
+                "heavy".into(),
+                HeaderValue::from_str(&format!("key-{i}")).unwrap(),
+            );
+            cache.set(key, &connection()).await.expect("set failed");
+        }
+        for partition in cache.partitions.as_ref().unwrap().caches.iter() {
+            partition.1.run_pending_tasks().await;
+        }
+
+        assert!(cache.get(quiet).await.expect("get failed").is_some());
+        let heavy = cache
+            .partitions
+            .as_ref()
+            .unwrap()
+            .caches
+            .get(&Arc::from("heavy"))
+            .await
+            .unwrap();
+        assert!(heavy.entry_count() <= 2);
+    }
+
+    #[tokio::test]
+    async fn test_invalidation_drops_matching_connections_of_every_tenant() {
+        let cache = ConnectionCacheArcStrHeaderKey::create(10, 60)
+            .with_buildable_id_partitions(5)
+            .with_stale_fallback(true);
+        for (buildable_id, key) in [("a", "stale"), ("b", "stale"), ("a", "fresh")] {
+            cache
+                .set(
+                    (buildable_id.into(), HeaderValue::from_static(key)),
+                    &connection(),
+                )
+                .await
+                .expect("set failed");
+        }
+
+        let stale = HeaderValue::from_static("stale");
+        assert_eq!(cache.invalidate_if(|(_, key)| key == stale).await, 2);
+        assert_eq!(cache.invalidate_if(|(_, key)| key == stale).await, 0);
+        assert!(cache
+            .get(("b".into(), stale.clone()))
+            .await
+            .expect("get failed")
+            .is_none());
+        let last_known = cache.last_known.as_ref().unwrap();
+        assert!(last_known
+            .get(&("b".into(), stale))
+            .await
+            .expect("get failed")
+            .is_none());
+
+        assert_eq!(cache.invalidate_all().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stale_connection_is_served_on_database_error() {
+        let client = Client::with_uri_str(
+            "mongodb://127.0.0.1:1/?serverSelectionTimeoutMS=100&connectTimeoutMS=100",
+        )
+        .await
+        .expect("Failed to create client");
+        let store = MongoStore::new(&client.database("test"), &Store::Connections)
+            .await
+            .expect("Failed to create store");
+
+        let clock = MockClock::default();
+        let cache = ConnectionCacheArcStrKey::create(10, 1)
+            .with_clock(Arc::new(clock.clone()))
+            .with_stale_fallback(true);
+        let key: Arc<str> = "key".into();
+        let connection = connection();
+        cache
+            .set(key.clone(), &connection)
+            .await
+            .expect("set failed");
+
+        clock.advance(chrono::Duration::seconds(2));
+        assert!(cache.get(key.clone()).await.expect("get failed").is_none());
+
+        let cached = cache
+            .get_or_insert_with_filter_or_stale(key, store.clone(), doc! { "key": "key" })
+            .await
+            .expect("Stale connection should be served");
+        assert!(cached.stale);
+        assert_eq!(cached.connection.id, connection.id);
+
+        let unknown = cache
+            .get_or_insert_with_filter_or_stale("unknown".into(), store, doc! { "key": "unknown" })
+            .await;
+        assert!(unknown.is_err());
+    }
 }
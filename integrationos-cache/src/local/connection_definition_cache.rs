@@ -1,15 +1,35 @@
 // pub type InMemoryCache<T> = Arc<Cache<Option<BTreeMap<String, String>>, Arc<T>>>;
-use crate::LocalCacheExt;
+use crate::local::{eviction::eviction_listener, invalidate_all_counted};
+use chrono::{DateTime, Utc};
 use integrationos_domain::{
-    connection_definition::ConnectionDefinition, Id, IntegrationOSError, MongoStore, Unit,
+    connection_definition::ConnectionDefinition, ApplicationError, Clock, Id, IntegrationOSError,
+    MongoStore, SystemClock, Unit,
 };
 use moka::future::Cache;
 use mongodb::bson::Document;
 use std::{sync::Arc, time::Duration};
 
+/// A definition looked up through [`ConnectionDefinitionCache::lookup_with_filter`]
+#[derive(Debug, Clone)]
+pub struct CachedDefinition {
+    pub definition: ConnectionDefinition,
+    /// How long the definition had been cached when it was served, none when it was read from
+    /// the database
+    pub age: Option<chrono::Duration>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    definition: ConnectionDefinition,
+    cached_at: DateTime<Utc>,
+}
+
+/// Definitions remember when they were cached according to `clock`, so that tests can age
+/// them without waiting
 #[derive(Clone)]
 pub struct ConnectionDefinitionCache {
-    inner: Arc<Cache<Id, ConnectionDefinition>>,
+    inner: Arc<Cache<Id, Entry>>,
+    clock: Arc<dyn Clock>,
 }
 
 impl ConnectionDefinitionCache {
@@ -19,24 +39,75 @@ impl ConnectionDefinitionCache {
                 Cache::builder()
                     .max_capacity(size)
                     .time_to_live(Duration::from_secs(ttl))
+                    .eviction_listener(eviction_listener("connection_definitions"))
                     .build(),
             ),
+            clock: Arc::new(SystemClock),
         }
     }
 
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub async fn get_or_insert_with_filter(
         &self,
         key: &Id,
         store: MongoStore<ConnectionDefinition>,
         filter: Document,
     ) -> Result<ConnectionDefinition, IntegrationOSError> {
-        self.inner
-            .get_or_insert_with_filter(key, store, filter)
+        self.lookup_with_filter(key, store, filter)
             .await
+            .map(|cached| cached.definition)
+    }
+
+    /// Same as [`Self::get_or_insert_with_filter`], along with the age of the cached entry the
+    /// definition was served from
+    pub async fn lookup_with_filter(
+        &self,
+        key: &Id,
+        store: MongoStore<ConnectionDefinition>,
+        filter: Document,
+    ) -> Result<CachedDefinition, IntegrationOSError> {
+        if let Some(entry) = self.inner.get(key).await {
+            tracing::debug!("Cache hit for key: {:?}", key);
+            return Ok(CachedDefinition {
+                definition: entry.definition,
+                age: Some((self.clock.now() - entry.cached_at).max(chrono::Duration::zero())),
+            });
+        }
+
+        tracing::debug!("Cache miss for key: {:?}", key);
+        let definition = store.get_one(filter).await?.ok_or_else(|| {
+            tracing::warn!("Value with id {:?} not found", key);
+            ApplicationError::not_found("Value not found", None)
+        })?;
+        self.set(key, &definition).await?;
+
+        Ok(CachedDefinition {
+            definition,
+            age: None,
+        })
+    }
+
+    /// Reads the definition from the database even if it is cached, replacing the cached entry
+    pub async fn refresh_with_filter(
+        &self,
+        key: &Id,
+        store: MongoStore<ConnectionDefinition>,
+        filter: Document,
+    ) -> Result<ConnectionDefinition, IntegrationOSError> {
+        let definition = store
+            .get_one(filter)
+            .await?
+            .ok_or_else(|| ApplicationError::not_found("Value not found", None))?;
+        self.set(key, &definition).await?;
+        Ok(definition)
     }
 
     pub async fn get(&self, key: &Id) -> Result<Option<ConnectionDefinition>, IntegrationOSError> {
-        self.inner.get(key).await
+        Ok(self.inner.get(key).await.map(|entry| entry.definition))
     }
 
     pub async fn set(
@@ -44,10 +115,111 @@ impl ConnectionDefinitionCache {
         key: &Id,
         value: &ConnectionDefinition,
     ) -> Result<Unit, IntegrationOSError> {
-        self.inner.set(key, value).await
+        let entry = Entry {
+            definition: value.clone(),
+            cached_at: self.clock.now(),
+        };
+        self.inner.insert(*key, entry).await;
+        Ok(())
     }
 
     pub async fn remove(&self, key: &Id) -> Result<Unit, IntegrationOSError> {
-        self.inner.remove(key).await
+        self.inner.remove(key).await;
+        Ok(())
+    }
+
+    /// Drops the cached definition of `key`, returning the number of entries dropped
+    pub async fn invalidate(&self, key: &Id) -> u64 {
+        Cache::remove(&self.inner, key).await.map_or(0, |_| 1)
+    }
+
+    /// Drops every cached definition, returning the number of entries dropped
+    pub async fn invalidate_all(&self) -> u64 {
+        invalidate_all_counted(&self.inner).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::{Fake, Faker};
+    use integrationos_domain::{prefix::IdPrefix, MockClock, Store};
+    use mongodb::{bson::doc, Client};
+
+    #[tokio::test]
+    async fn test_refresh_reads_database_even_when_cached() {
+        // The database is unreachable, so any read of it fails
+        let client = Client::with_uri_str(
+            "mongodb://127.0.0.1:1/?serverSelectionTimeoutMS=100&connectTimeoutMS=100",
+        )
+        .await
+        .expect("Failed to create client");
+        let store = MongoStore::new(&client.database("test"), &Store::ConnectionDefinitions)
+            .await
+            .expect("Failed to create store");
+
+        let cache = ConnectionDefinitionCache::new(10, 60);
+        let key = Id::now(IdPrefix::ConnectionDefinition);
+        let definition: ConnectionDefinition = Faker.fake();
+        cache.set(&key, &definition).await.expect("set failed");
+
+        let filter = doc! { "_id": key.to_string() };
+        let cached = cache
+            .get_or_insert_with_filter(&key, store.clone(), filter.clone())
+            .await
+            .expect("Cached definition should be served without the database");
+        assert_eq!(cached, definition);
+
+        assert!(cache
+            .refresh_with_filter(&key, store, filter)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cached_definition_is_served_with_its_age() {
+        let client = Client::with_uri_str(
+            "mongodb://127.0.0.1:1/?serverSelectionTimeoutMS=100&connectTimeoutMS=100",
+        )
+        .await
+        .expect("Failed to create client");
+        let store = MongoStore::new(&client.database("test"), &Store::ConnectionDefinitions)
+            .await
+            .expect("Failed to create store");
+
+        let clock = MockClock::default();
+        let cache = ConnectionDefinitionCache::new(10, 60).with_clock(Arc::new(clock.clone()));
+        let key = Id::now(IdPrefix::ConnectionDefinition);
+        let definition: ConnectionDefinition = Faker.fake();
+        cache.set(&key, &definition).await.expect("set failed");
+
+        clock.advance(chrono::Duration::seconds(42));
+        let filter = doc! { "_id": key.to_string() };
+        let cached = cache
+            .lookup_with_filter(&key, store.clone(), filter.clone())
+            .await
+            .expect("Cached definition should be served without the database");
+        assert_eq!(cached.definition, definition);
+        assert_eq!(cached.age, Some(chrono::Duration::seconds(42)));
+
+        // Setting the definition again makes it fresh
+        cache.set(&key, &definition).await.expect("set failed");
+        let cached = cache
+            .lookup_with_filter(&key, store, filter)
+            .await
+            .expect("Cached definition should be served without the database");
+        assert_eq!(cached.age, Some(chrono::Duration::zero()));
+    }
+
+    #[tokio::test]
+    async fn test_definition_expires_after_ttl() {
+        let cache = ConnectionDefinitionCache::new(10, 1);
+        let key = Id::now(IdPrefix::ConnectionDefinition);
+        let definition: ConnectionDefinition = Faker.fake();
+        cache.set(&key, &definition).await.expect("set failed");
+        assert_eq!(cache.get(&key).await.expect("get failed"), Some(definition));
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(cache.get(&key).await.expect("get failed").is_none());
     }
 }
@@ -1,4 +1,4 @@
-use crate::LocalCacheExt;
+use crate::{local::eviction::eviction_listener, LocalCacheExt};
 use futures::Future;
 use integrationos_domain::{
     connection_model_definition::ConnectionModelDefinition, destination::Destination, Id,
@@ -22,6 +22,7 @@ impl<K: Clone + Send + Sync + Eq + Hash + Debug + 'static> ConnectionModelDefini
                 Cache::builder()
                     .max_capacity(size)
                     .time_to_live(Duration::from_secs(ttl))
+                    .eviction_listener(eviction_listener("connection_model_definitions"))
                     .build(),
             ),
         }
@@ -1,4 +1,4 @@
-use crate::LocalCacheExt;
+use crate::{local::eviction::eviction_listener, LocalCacheExt};
 use integrationos_domain::{
     connection_model_schema::ConnectionModelSchema, ApplicationError, IntegrationOSError,
     MongoStore, Unit,
@@ -21,6 +21,7 @@ impl ConnectionModelSchemaCache {
                 Cache::builder()
                     .max_capacity(size)
                     .time_to_live(Duration::from_secs(ttl))
+                    .eviction_listener(eviction_listener("connection_model_schemas"))
                     .build(),
             ),
         }
@@ -1,4 +1,7 @@
-use crate::LocalCacheExt;
+use crate::{
+    local::{eviction::eviction_listener, invalidate_all_counted},
+    LocalCacheExt,
+};
 use integrationos_domain::{
     connection_oauth_definition::ConnectionOAuthDefinition, Id, IntegrationOSError, MongoStore,
     Unit,
@@ -19,6 +22,7 @@ impl ConnectionOAuthDefinitionCache {
                 Cache::builder()
                     .max_capacity(size)
                     .time_to_live(Duration::from_secs(ttl))
+                    .eviction_listener(eviction_listener("connection_oauth_definitions"))
                     .build(),
             ),
         }
@@ -53,4 +57,14 @@ impl ConnectionOAuthDefinitionCache {
     pub async fn remove(&self, key: &Id) -> Result<Unit, IntegrationOSError> {
         self.inner.remove(key).await
     }
+
+    /// Drops the cached definition of `key`, returning the number of entries dropped
+    pub async fn invalidate(&self, key: &Id) -> u64 {
+        Cache::remove(&self.inner, key).await.map_or(0, |_| 1)
+    }
+
+    /// Drops every cached definition, returning the number of entries dropped
+    pub async fn invalidate_all(&self) -> u64 {
+        invalidate_all_counted(&self.inner).await
+    }
 }
@@ -1,4 +1,7 @@
-use crate::LocalCacheExt;
+use crate::{
+    local::{eviction::eviction_listener, invalidate_all_counted},
+    LocalCacheExt,
+};
 use http::HeaderValue;
 use integrationos_domain::{event_access::EventAccess, IntegrationOSError, MongoStore, Unit};
 use moka::future::Cache;
@@ -17,6 +20,7 @@ impl EventAccessCache {
                 Cache::builder()
                     .max_capacity(size)
                     .time_to_live(Duration::from_secs(ttl))
+                    .eviction_listener(eviction_listener("event_access"))
                     .build(),
             ),
         }
@@ -48,4 +52,14 @@ impl EventAccessCache {
     pub async fn remove(&self, key: &HeaderValue) -> Result<Unit, IntegrationOSError> {
         self.inner.remove(key).await
     }
+
+    /// Drops the cached event access of `key`, returning the number of entries dropped
+    pub async fn invalidate(&self, key: &HeaderValue) -> u64 {
+        Cache::remove(&self.inner, key).await.map_or(0, |_| 1)
+    }
+
+    /// Drops every cached event access, returning the number of entries dropped
+    pub async fn invalidate_all(&self) -> u64 {
+        invalidate_all_counted(&self.inner).await
+    }
 }
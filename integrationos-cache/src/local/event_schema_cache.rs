@@ -0,0 +1,61 @@
+use crate::local::{eviction::eviction_listener, invalidate_all_counted};
+use integrationos_domain::{
+    event_schema::{latest_event_schema, EventSchema},
+    IntegrationOSError, MongoStore,
+};
+use moka::future::Cache;
+use std::{sync::Arc, time::Duration};
+
+/// Latest schema of each event type of each owner, keyed by owner id and event type. Types
+/// without a schema are cached as well so unvalidated events do not hit the store.
+#[derive(Clone)]
+pub struct EventSchemaCache {
+    inner: Arc<Cache<(String, String), Option<EventSchema>>>,
+}
+
+impl EventSchemaCache {
+    pub fn new(size: u64, ttl: u64) -> Self {
+        Self {
+            inner: Arc::new(
+                Cache::builder()
+                    .max_capacity(size)
+                    .time_to_live(Duration::from_secs(ttl))
+                    .eviction_listener(eviction_listener("event_schema"))
+                    .build(),
+            ),
+        }
+    }
+
+    /// Latest schema registered by `ownership_id` for `event_type`, read from `store` on a miss.
+    /// Concurrent misses on the same key share a single read.
+    pub async fn get_or_insert_latest(
+        &self,
+        store: &MongoStore<EventSchema>,
+        ownership_id: &str,
+        event_type: &str,
+    ) -> Result<Option<EventSchema>, IntegrationOSError> {
+        self.inner
+            .try_get_with(
+                (ownership_id.to_string(), event_type.to_string()),
+                latest_event_schema(store, ownership_id, event_type),
+            )
+            .await
+            .map_err(|e| e.as_ref().clone())
+    }
+
+    /// Drops the cached schema of `event_type` of `ownership_id`, returning the number of entries
+    /// dropped
+    pub async fn invalidate(&self, ownership_id: &str, event_type: &str) -> u64 {
+        Cache::remove(
+            &self.inner,
+            &(ownership_id.to_string(), event_type.to_string()),
+        )
+        .await
+        .map_or(0, |_| 1)
+    }
+
+    /// Drops every cached schema, returning the number of entries dropped
+    pub async fn invalidate_all(&self) -> u64 {
+        invalidate_all_counted(&self.inner).await
+    }
+}
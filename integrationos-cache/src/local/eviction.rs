@@ -0,0 +1,117 @@
+use moka::notification::RemovalCause;
+use std::sync::Arc;
+
+/// Counter of the entries evicted from the local caches, labelled with [`CACHE_LABEL`] and
+/// [`CAUSE_LABEL`]. A steady rate of `size` evictions means the cache is too small.
+pub const CACHE_EVICTIONS_COUNTER: &str = "cache_evictions_total";
+pub const CACHE_LABEL: &str = "cache";
+pub const CAUSE_LABEL: &str = "cause";
+
+/// Counts the removal of an entry of `cache` if the cache evicted it by itself
+pub fn record_eviction(cache: &'static str, cause: RemovalCause) {
+    let cause = match cause {
+        RemovalCause::Expired => "expired",
+        RemovalCause::Size => "size",
+        RemovalCause::Explicit | RemovalCause::Replaced => return,
+    };
+
+    metrics::counter!(CACHE_EVICTIONS_COUNTER, 1, CACHE_LABEL => cache, CAUSE_LABEL => cause);
+}
+
+/// Eviction listener recording the evictions of `cache`
+pub fn eviction_listener<K, V>(
+    cache: &'static str,
+) -> impl Fn(Arc<K>, V, RemovalCause) + Send + Sync + 'static {
+    move |_, _, cause| record_eviction(cache, cause)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metrics::{Counter, Gauge, Histogram, Key, KeyName, Recorder, SharedString, Unit};
+    use moka::future::Cache;
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Mutex,
+        },
+    };
+
+    /// Keeps the value of every counter, keyed by its name and labels
+    #[derive(Default)]
+    struct CountingRecorder {
+        counters: Mutex<HashMap<String, Arc<AtomicU64>>>,
+    }
+
+    impl CountingRecorder {
+        fn key(key: &Key) -> String {
+            let labels = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect::<Vec<_>>()
+                .join(",");
+            format!("{}{{{labels}}}", key.name())
+        }
+
+        fn counter(&self, key: &str) -> u64 {
+            self.counters
+                .lock()
+                .unwrap()
+                .get(key)
+                .map(|counter| counter.load(Ordering::SeqCst))
+                .unwrap_or_default()
+        }
+    }
+
+    impl Recorder for CountingRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key) -> Counter {
+            let counter = self
+                .counters
+                .lock()
+                .unwrap()
+                .entry(Self::key(key))
+                .or_default()
+                .clone();
+            Counter::from_arc(counter)
+        }
+
+        fn register_gauge(&self, _: &Key) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, _: &Key) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_evictions_past_capacity_are_recorded() {
+        let recorder: &'static CountingRecorder = Box::leak(Box::default());
+        metrics::set_recorder(recorder).expect("Failed to install recorder");
+
+        let cache = Cache::builder()
+            .max_capacity(2)
+            .eviction_listener(eviction_listener::<u32, u32>("test"))
+            .build();
+        for i in 0..10 {
+            cache.insert(i, i).await;
+            cache.run_pending_tasks().await;
+        }
+        cache.invalidate(&9).await;
+        cache.run_pending_tasks().await;
+
+        assert_eq!(
+            recorder.counter("cache_evictions_total{cache=test,cause=size}"),
+            8
+        );
+        assert_eq!(
+            recorder.counter("cache_evictions_total{cache=test,cause=expired}"),
+            0
+        );
+    }
+}
@@ -4,6 +4,8 @@ pub mod connection_model_definition_cache;
 pub mod connection_model_schema_cache;
 pub mod connection_oauth_definition_cache;
 pub mod event_access_cache;
+pub mod event_schema_cache;
+pub mod eviction;
 pub mod secrets_cache;
 
 use crate::LocalCacheExt;
@@ -12,6 +14,19 @@ use moka::future::Cache;
 use serde::{de::DeserializeOwned, Serialize};
 use std::{fmt::Debug, hash::Hash, sync::Arc};
 
+/// Drops every entry of `cache`, returning how many there were. Pending evictions are applied
+/// first, so entries that already expired are not counted.
+pub(crate) async fn invalidate_all_counted<K, V>(cache: &Cache<K, V>) -> u64
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    cache.run_pending_tasks().await;
+    let count = cache.entry_count();
+    cache.invalidate_all();
+    count
+}
+
 impl<K, V> LocalCacheExt<K, V> for Arc<Cache<K, V>>
 where
     K: Hash + Eq + Clone + Debug + Send + Sync + 'static,
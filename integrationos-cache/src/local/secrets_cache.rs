@@ -1,14 +1,16 @@
-use crate::LocalCacheExt;
+use crate::{local::eviction::eviction_listener, LocalCacheExt};
 use futures::Future;
-use integrationos_domain::{Connection, IntegrationOSError, InternalError, MongoStore, Unit};
+use integrationos_domain::{Id, IntegrationOSError, InternalError, MongoStore, Unit};
 use moka::future::Cache;
 use mongodb::bson::Document;
 use serde_json::Value;
 use std::{sync::Arc, time::Duration};
 
+/// Decrypted secrets of connections, keyed by connection id. Its TTL is kept short so that
+/// rotated secrets are picked up promptly, while repeated calls skip the decryption.
 #[derive(Clone)]
 pub struct SecretCache {
-    inner: Arc<Cache<Connection, Value>>,
+    inner: Arc<Cache<Id, Value>>,
 }
 
 impl SecretCache {
@@ -18,6 +20,7 @@ impl SecretCache {
                 Cache::builder()
                     .max_capacity(size)
                     .time_to_live(Duration::from_secs(ttl))
+                    .eviction_listener(eviction_listener("secrets"))
                     .build(),
             ),
         }
@@ -29,7 +32,7 @@ impl SecretCache {
 
     pub async fn get_or_insert_with_filter(
         &self,
-        _: &Connection,
+        _: &Id,
         _: MongoStore<Value>,
         _: Document,
     ) -> Result<Value, IntegrationOSError> {
@@ -41,7 +44,7 @@ impl SecretCache {
 
     pub async fn get_or_insert_with_fn<F, Fut>(
         &self,
-        key: Connection,
+        key: Id,
         fa: F,
     ) -> Result<Value, IntegrationOSError>
     where
@@ -60,15 +63,50 @@ impl SecretCache {
         }
     }
 
-    pub async fn get(&self, key: &Connection) -> Result<Option<Value>, IntegrationOSError> {
+    pub async fn get(&self, key: &Id) -> Result<Option<Value>, IntegrationOSError> {
         self.inner.get(key).await
     }
 
-    pub async fn set(&self, key: &Connection, value: &Value) -> Result<Unit, IntegrationOSError> {
+    pub async fn set(&self, key: &Id, value: &Value) -> Result<Unit, IntegrationOSError> {
         self.inner.set(key, value).await
     }
 
-    pub async fn remove(&self, key: &Connection) -> Result<Unit, IntegrationOSError> {
+    pub async fn remove(&self, key: &Id) -> Result<Unit, IntegrationOSError> {
         self.inner.remove(key).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integrationos_domain::prefix::IdPrefix;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_secret_is_decrypted_again_only_after_ttl() {
+        let cache = SecretCache::new(10, 1);
+        let connection_id = Id::now(IdPrefix::Connection);
+        let decryptions = AtomicUsize::new(0);
+        let decrypt = || async {
+            decryptions.fetch_add(1, Ordering::SeqCst);
+            Ok(json!({ "apiKey": "secret" }))
+        };
+
+        for _ in 0..2 {
+            let secret = cache
+                .get_or_insert_with_fn(connection_id, decrypt)
+                .await
+                .expect("get failed");
+            assert_eq!(secret["apiKey"], "secret");
+        }
+        assert_eq!(decryptions.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        cache
+            .get_or_insert_with_fn(connection_id, decrypt)
+            .await
+            .expect("get failed");
+        assert_eq!(decryptions.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,17 +1,32 @@
-use crate::{secrets::SecretsConfig, IntegrationOSError, InternalError, SecretVersion};
+use super::sigv4::{sign, SignableRequest, SigningParams};
+use crate::{
+    secrets::{CryptoAlgorithm, SecretServiceProvider, SecretsConfig},
+    IntegrationOSError, InternalError, SecretVersion,
+};
+use aes_gcm::Aes256Gcm;
 use async_trait::async_trait;
 use base64::{prelude::BASE64_STANDARD, Engine};
 use chacha20poly1305::aead::generic_array::typenum::Unsigned;
 use chacha20poly1305::aead::generic_array::GenericArray;
 use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
 use chacha20poly1305::ChaCha20Poly1305;
+use chrono::Utc;
 use google_cloud_kms::{
     client::{Client, ClientConfig},
     grpc::kms::v1::DecryptRequest,
 };
 use secrecy::ExposeSecret;
+use serde_json::{json, Value};
+use std::{collections::HashMap, str::FromStr, sync::Arc};
 use tracing::debug;
 
+const AWS_KMS_SERVICE: &str = "kms";
+const AWS_KMS_CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+/// Separates the provider prefixing the ciphertexts of [`CompositeCrypto`] from the ciphertext
+const PROVIDER_SEPARATOR: char = ':';
+/// Separates the algorithm prefixing the ciphertexts of [`IOSCrypto`] from the ciphertext
+const ALGORITHM_SEPARATOR: char = '$';
+
 #[async_trait]
 pub trait CryptoExt {
     async fn encrypt(&self, encrypted_secret: String) -> Result<String, IntegrationOSError>;
@@ -23,11 +38,14 @@ pub trait CryptoExt {
     ) -> Result<String, IntegrationOSError>;
 }
 
-type NonceSize = <ChaCha20Poly1305 as AeadCore>::NonceSize;
-
+/// Crypto encrypting with a key of its own, using the configured [`CryptoAlgorithm`]. Its
+/// ciphertexts are prefixed with the id of their algorithm, as in `aes-256-gcm$<ciphertext>`,
+/// and decrypted with it whatever the configured one, so that changing algorithms doesn't
+/// break existing secrets.
 #[derive(Debug, Clone)]
 pub struct IOSCrypto {
     key: Vec<u8>,
+    algorithm: CryptoAlgorithm,
 }
 
 #[async_trait]
@@ -72,19 +90,35 @@ impl IOSCrypto {
                 )
             })?;
 
-        Ok(Self { key: key.to_vec() })
+        Ok(Self {
+            key: key.to_vec(),
+            algorithm: config.ios_crypto_algorithm,
+        })
     }
 
     async fn decrypt(&self, encrypted_secret: String) -> Result<String, IntegrationOSError> {
-        let obsf = hex::decode(encrypted_secret).map_err(|_| {}).map_err(|_| {
-            InternalError::deserialize_error("The provided value is not a valid UTF-8 string", None)
-        })?;
-        let cipher = ChaCha20Poly1305::new(GenericArray::from_slice(&self.key));
-        let (nonce, ciphertext) = obsf.split_at(NonceSize::to_usize());
-        let nonce = GenericArray::from_slice(nonce);
-        let plaintext = cipher.decrypt(nonce, ciphertext).map_err(|_| {
+        // Ciphertexts from before the algorithm could be chosen have no prefix, they were all
+        // encrypted with ChaCha20-Poly1305
+        let (algorithm, encrypted) = match encrypted_secret.split_once(ALGORITHM_SEPARATOR) {
+            Some((algorithm, encrypted)) => (
+                CryptoAlgorithm::from_str(algorithm).map_err(|_| {
+                    InternalError::deserialize_error(
+                        &format!("Secret was encrypted with unknown algorithm {algorithm}"),
+                        None,
+                    )
+                })?,
+                encrypted,
+            ),
+            None => (CryptoAlgorithm::ChaCha20Poly1305, encrypted_secret.as_str()),
+        };
+
+        let obsf = hex::decode(encrypted).map_err(|_| {
             InternalError::deserialize_error("The provided value is not a valid UTF-8 string", None)
         })?;
+        let plaintext = match algorithm {
+            CryptoAlgorithm::ChaCha20Poly1305 => open::<ChaCha20Poly1305>(&self.key, &obsf),
+            CryptoAlgorithm::Aes256Gcm => open::<Aes256Gcm>(&self.key, &obsf),
+        }?;
         let plaintext = String::from_utf8(plaintext).map_err(|_| {
             InternalError::deserialize_error("The provided value is not a valid UTF-8 string", None)
         })?;
@@ -93,15 +127,57 @@ impl IOSCrypto {
     }
 
     async fn encrypt(&self, secret: String) -> Result<String, IntegrationOSError> {
-        let cipher = ChaCha20Poly1305::new(GenericArray::from_slice(&self.key));
-        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
-        let mut obsf = cipher.encrypt(&nonce, secret.as_bytes()).map_err(|_| {
-            InternalError::serialize_error("The provided value is not a valid UTF-8 string", None)
-        })?;
-        obsf.splice(..0, nonce.iter().copied());
+        let obsf = match self.algorithm {
+            CryptoAlgorithm::ChaCha20Poly1305 => {
+                seal::<ChaCha20Poly1305>(&self.key, secret.as_bytes())
+            }
+            CryptoAlgorithm::Aes256Gcm => seal::<Aes256Gcm>(&self.key, secret.as_bytes()),
+        }?;
+
+        Ok(format!(
+            "{}{ALGORITHM_SEPARATOR}{}",
+            self.algorithm.as_ref(),
+            hex::encode(obsf)
+        ))
+    }
+}
+
+/// Encrypts `plaintext` with a random nonce, which is prepended to the ciphertext
+fn seal<C: KeyInit + Aead + AeadCore>(
+    key: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, IntegrationOSError> {
+    let cipher = C::new_from_slice(key)
+        .map_err(|_| InternalError::encryption_error("Invalid encryption key", None))?;
+    let nonce = C::generate_nonce(&mut OsRng);
+    let mut obsf = cipher.encrypt(&nonce, plaintext).map_err(|_| {
+        InternalError::serialize_error("The provided value is not a valid UTF-8 string", None)
+    })?;
+    obsf.splice(..0, nonce.iter().copied());
+
+    Ok(obsf)
+}
 
-        Ok(hex::encode(obsf))
+/// Decrypts the output of [`seal`]
+fn open<C: KeyInit + Aead + AeadCore>(
+    key: &[u8],
+    obsf: &[u8],
+) -> Result<Vec<u8>, IntegrationOSError> {
+    let cipher = C::new_from_slice(key)
+        .map_err(|_| InternalError::decryption_error("Invalid encryption key", None))?;
+    if obsf.len() < C::NonceSize::to_usize() {
+        return Err(InternalError::deserialize_error(
+            "The provided value is not a valid UTF-8 string",
+            None,
+        ));
     }
+    let (nonce, ciphertext) = obsf.split_at(C::NonceSize::to_usize());
+
+    cipher
+        .decrypt(GenericArray::from_slice(nonce), ciphertext)
+        .map_err(|_| {
+            InternalError::deserialize_error("The provided value is not a valid UTF-8 string", None)
+        })
 }
 
 #[derive(Debug, Clone)]
@@ -200,6 +276,255 @@ impl GoogleCryptoKms {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct AwsCryptoKms {
+    http_client: reqwest::Client,
+    config: SecretsConfig,
+}
+
+#[async_trait]
+impl CryptoExt for AwsCryptoKms {
+    async fn encrypt(&self, encrypted_secret: String) -> Result<String, IntegrationOSError> {
+        self.encrypt(encrypted_secret).await
+    }
+
+    async fn decrypt(
+        &self,
+        data: String,
+        _: Option<SecretVersion>,
+    ) -> Result<String, IntegrationOSError> {
+        self.decrypt(data).await
+    }
+}
+
+impl AwsCryptoKms {
+    pub fn new(secrets_config: &SecretsConfig) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            config: secrets_config.clone(),
+        }
+    }
+
+    /// Calls an operation of the JSON API of AWS KMS, signed with the configured credentials
+    async fn call(&self, operation: &str, body: &Value) -> Result<Value, IntegrationOSError> {
+        let payload = serde_json::to_vec(body)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), None))?;
+        let host = format!(
+            "{AWS_KMS_SERVICE}.{}.amazonaws.com",
+            self.config.aws_kms_region
+        );
+        let target = format!("TrentService.{operation}");
+        let headers = [
+            ("content-type", AWS_KMS_CONTENT_TYPE),
+            ("x-amz-target", target.as_str()),
+        ];
+
+        let authentication = sign(
+            &SigningParams {
+                access_key_id: &self.config.aws_access_key_id,
+                secret_access_key: self.config.aws_secret_access_key.expose_secret(),
+                session_token: self
+                    .config
+                    .aws_session_token
+                    .as_ref()
+                    .map(|token| token.expose_secret().as_str()),
+                region: &self.config.aws_kms_region,
+                service: AWS_KMS_SERVICE,
+                time: Utc::now(),
+            },
+            &SignableRequest {
+                method: "POST",
+                host: &host,
+                path: "/",
+                query: "",
+                headers: &headers,
+                payload: &payload,
+            },
+        );
+
+        let request = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .chain(authentication)
+            .fold(
+                self.http_client.post(format!("https://{host}/")),
+                |request, (name, value)| request.header(name, value),
+            );
+        let response = request.body(payload).send().await.map_err(|e| {
+            debug!("Error calling AWS KMS: {e}");
+            InternalError::connection_error("Failed to reach AWS KMS", None)
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            debug!(
+                "AWS KMS {operation} failed with {status}: {}",
+                response.text().await.unwrap_or_default()
+            );
+            return Err(InternalError::connection_error(
+                &format!("AWS KMS {operation} failed with {status}"),
+                None,
+            ));
+        }
+
+        response.json::<Value>().await.map_err(|e| {
+            debug!("Error decoding AWS KMS response: {e}");
+            InternalError::deserialize_error("Invalid response from AWS KMS", None)
+        })
+    }
+
+    async fn decrypt(&self, encrypted_secret: String) -> Result<String, IntegrationOSError> {
+        let response = self
+            .call(
+                "Decrypt",
+                &json!({
+                    "KeyId": self.config.aws_kms_key_id,
+                    "CiphertextBlob": encrypted_secret,
+                }),
+            )
+            .await?;
+
+        let plaintext = response
+            .get("Plaintext")
+            .and_then(Value::as_str)
+            .and_then(|plaintext| BASE64_STANDARD.decode(plaintext).ok())
+            .ok_or_else(|| {
+                InternalError::deserialize_error("AWS KMS returned no plaintext", None)
+            })?;
+
+        String::from_utf8(plaintext).map_err(|_| {
+            InternalError::deserialize_error("The provided value is not a valid UTF-8 string", None)
+        })
+    }
+
+    async fn encrypt(&self, secret: String) -> Result<String, IntegrationOSError> {
+        let response = self
+            .call(
+                "Encrypt",
+                &json!({
+                    "KeyId": self.config.aws_kms_key_id,
+                    "Plaintext": BASE64_STANDARD.encode(secret),
+                }),
+            )
+            .await?;
+
+        response
+            .get("CiphertextBlob")
+            .and_then(Value::as_str)
+            .map(ToOwned::to_owned)
+            .ok_or_else(|| InternalError::deserialize_error("AWS KMS returned no ciphertext", None))
+    }
+}
+
+/// Crypto of the given provider
+pub async fn provider_crypto(
+    provider: SecretServiceProvider,
+    secrets_config: &SecretsConfig,
+) -> Result<Arc<dyn CryptoExt + Sync + Send>, IntegrationOSError> {
+    Ok(match provider {
+        SecretServiceProvider::GoogleKms => Arc::new(GoogleCryptoKms::new(secrets_config).await?),
+        SecretServiceProvider::IosKms => Arc::new(IOSCrypto::new(secrets_config.clone())?),
+        SecretServiceProvider::AwsKms => Arc::new(AwsCryptoKms::new(secrets_config)),
+    })
+}
+
+/// Crypto of several providers, to migrate secrets from one to another. Secrets are encrypted
+/// by `primary` and prefixed with its name, as in `aws-kms:<ciphertext>`. Ciphertexts are
+/// decrypted by the provider they are prefixed with, and the unprefixed ones, encrypted before
+/// the migration, by `unprefixed`.
+#[derive(Clone)]
+pub struct CompositeCrypto {
+    primary: SecretServiceProvider,
+    unprefixed: SecretServiceProvider,
+    providers: HashMap<SecretServiceProvider, Arc<dyn CryptoExt + Sync + Send>>,
+}
+
+#[async_trait]
+impl CryptoExt for CompositeCrypto {
+    async fn encrypt(&self, encrypted_secret: String) -> Result<String, IntegrationOSError> {
+        let encrypted = self.crypto(self.primary)?.encrypt(encrypted_secret).await?;
+        Ok(format!(
+            "{}{PROVIDER_SEPARATOR}{encrypted}",
+            self.primary.as_ref()
+        ))
+    }
+
+    async fn decrypt(
+        &self,
+        data: String,
+        version: Option<SecretVersion>,
+    ) -> Result<String, IntegrationOSError> {
+        let (provider, data) =
+            match data
+                .split_once(PROVIDER_SEPARATOR)
+                .and_then(|(prefix, encrypted)| {
+                    SecretServiceProvider::from_str(prefix)
+                        .ok()
+                        .map(|provider| (provider, encrypted.to_owned()))
+                }) {
+                Some(prefixed) => prefixed,
+                None => (self.unprefixed, data),
+            };
+
+        self.crypto(provider)?.decrypt(data, version).await
+    }
+}
+
+impl CompositeCrypto {
+    pub fn new(
+        primary: SecretServiceProvider,
+        unprefixed: SecretServiceProvider,
+        providers: HashMap<SecretServiceProvider, Arc<dyn CryptoExt + Sync + Send>>,
+    ) -> Result<Self, IntegrationOSError> {
+        for provider in [primary, unprefixed] {
+            if !providers.contains_key(&provider) {
+                return Err(InternalError::invalid_argument(
+                    &format!("No crypto given for {}", provider.as_ref()),
+                    None,
+                ));
+            }
+        }
+
+        Ok(Self {
+            primary,
+            unprefixed,
+            providers,
+        })
+    }
+
+    /// Crypto encrypting with the configured provider and decrypting the secrets of it and of
+    /// the one `migrate_from`
+    pub async fn from_config(
+        secrets_config: &SecretsConfig,
+        migrate_from: SecretServiceProvider,
+    ) -> Result<Self, IntegrationOSError> {
+        let mut providers = HashMap::from([(
+            secrets_config.provider,
+            provider_crypto(secrets_config.provider, secrets_config).await?,
+        )]);
+        if migrate_from != secrets_config.provider {
+            providers.insert(
+                migrate_from,
+                provider_crypto(migrate_from, secrets_config).await?,
+            );
+        }
+
+        Self::new(secrets_config.provider, migrate_from, providers)
+    }
+
+    fn crypto(
+        &self,
+        provider: SecretServiceProvider,
+    ) -> Result<&Arc<dyn CryptoExt + Sync + Send>, IntegrationOSError> {
+        self.providers.get(&provider).ok_or_else(|| {
+            InternalError::invalid_argument(
+                &format!("Secret was encrypted with {}", provider.as_ref()),
+                None,
+            )
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -207,6 +532,109 @@ mod tests {
 
     use super::*;
 
+    /// Crypto wrapping the data in its name, failing to decrypt what it did not wrap
+    struct NamedCrypto(&'static str);
+
+    #[async_trait]
+    impl CryptoExt for NamedCrypto {
+        async fn encrypt(&self, data: String) -> Result<String, IntegrationOSError> {
+            Ok(format!("{}({data})", self.0))
+        }
+
+        async fn decrypt(
+            &self,
+            data: String,
+            _: Option<SecretVersion>,
+        ) -> Result<String, IntegrationOSError> {
+            data.strip_prefix(&format!("{}(", self.0))
+                .and_then(|data| data.strip_suffix(')'))
+                .map(ToOwned::to_owned)
+                .ok_or_else(|| InternalError::deserialize_error("Not encrypted by me", None))
+        }
+    }
+
+    fn composite() -> CompositeCrypto {
+        CompositeCrypto::new(
+            SecretServiceProvider::AwsKms,
+            SecretServiceProvider::GoogleKms,
+            HashMap::from([
+                (
+                    SecretServiceProvider::AwsKms,
+                    Arc::new(NamedCrypto("aws")) as Arc<dyn CryptoExt + Sync + Send>,
+                ),
+                (
+                    SecretServiceProvider::GoogleKms,
+                    Arc::new(NamedCrypto("gcp")),
+                ),
+            ]),
+        )
+        .expect("Failed to create CompositeCrypto")
+    }
+
+    #[tokio::test]
+    async fn should_encrypt_with_the_primary_provider_and_prefix_it() {
+        let crypto = composite();
+
+        let encrypted = CryptoExt::encrypt(&crypto, "secret".to_owned())
+            .await
+            .expect("Failed to encrypt data");
+        assert_eq!(encrypted, "aws-kms:aws(secret)");
+
+        let decrypted = CryptoExt::decrypt(&crypto, encrypted, None)
+            .await
+            .expect("Failed to decrypt data");
+        assert_eq!(decrypted, "secret");
+    }
+
+    #[tokio::test]
+    async fn should_decrypt_with_the_provider_of_the_prefix() {
+        let crypto = composite();
+
+        let decrypted = CryptoExt::decrypt(&crypto, "google-kms:gcp(secret)".to_owned(), None)
+            .await
+            .expect("Failed to decrypt data");
+        assert_eq!(decrypted, "secret");
+
+        // Ciphertexts from before the migration have no prefix
+        let decrypted = CryptoExt::decrypt(&crypto, "gcp(legacy)".to_owned(), None)
+            .await
+            .expect("Failed to decrypt data");
+        assert_eq!(decrypted, "legacy");
+
+        // The prefix decides the provider, not whichever can decrypt
+        assert!(
+            CryptoExt::decrypt(&crypto, "google-kms:aws(secret)".to_owned(), None)
+                .await
+                .is_err()
+        );
+        assert!(
+            CryptoExt::decrypt(&crypto, "ios-kms:secret".to_owned(), None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn should_require_a_crypto_for_the_primary_and_unprefixed_providers() {
+        let providers = HashMap::from([(
+            SecretServiceProvider::AwsKms,
+            Arc::new(NamedCrypto("aws")) as Arc<dyn CryptoExt + Sync + Send>,
+        )]);
+
+        assert!(CompositeCrypto::new(
+            SecretServiceProvider::AwsKms,
+            SecretServiceProvider::GoogleKms,
+            providers.clone(),
+        )
+        .is_err());
+        assert!(CompositeCrypto::new(
+            SecretServiceProvider::AwsKms,
+            SecretServiceProvider::AwsKms,
+            providers,
+        )
+        .is_ok());
+    }
+
     #[tokio::test]
     async fn should_encrypt_and_decrypt_data() {
         let config = SecretsConfig::default().with_provider(SecretServiceProvider::IosKms);
@@ -225,6 +653,57 @@ mod tests {
         assert_eq!(data, decrypted);
     }
 
+    #[tokio::test]
+    async fn should_round_trip_with_each_algorithm() {
+        let data = "lorem_ipsum-dolor_sit-amet";
+        let mut encrypted = Vec::new();
+
+        for algorithm in [
+            CryptoAlgorithm::ChaCha20Poly1305,
+            CryptoAlgorithm::Aes256Gcm,
+        ] {
+            let config = SecretsConfig::default()
+                .with_provider(SecretServiceProvider::IosKms)
+                .with_algorithm(algorithm);
+            let crypto = IOSCrypto::new(config).expect("Failed to create IOSCrypto client");
+
+            let ciphertext = crypto
+                .encrypt(data.to_owned())
+                .await
+                .expect("Failed to encrypt data");
+            assert!(ciphertext.starts_with(&format!("{}$", algorithm.as_ref())));
+            assert_eq!(
+                crypto
+                    .decrypt(ciphertext.clone())
+                    .await
+                    .expect("Failed to decrypt data"),
+                data
+            );
+            encrypted.push(ciphertext);
+        }
+
+        // Whatever the configured algorithm, secrets are decrypted with the one they carry,
+        // and the ones without it with ChaCha20-Poly1305
+        let config = SecretsConfig::default()
+            .with_provider(SecretServiceProvider::IosKms)
+            .with_algorithm(CryptoAlgorithm::Aes256Gcm);
+        let crypto = IOSCrypto::new(config).expect("Failed to create IOSCrypto client");
+        let legacy = encrypted[0]
+            .strip_prefix("chacha20-poly1305$")
+            .expect("Ciphertext should carry its algorithm")
+            .to_owned();
+        for ciphertext in encrypted.into_iter().chain([legacy]) {
+            assert_eq!(
+                crypto
+                    .decrypt(ciphertext)
+                    .await
+                    .expect("Failed to decrypt data"),
+                data
+            );
+        }
+        assert!(crypto.decrypt("rot13$00".to_owned()).await.is_err());
+    }
+
     #[tokio::test]
     async fn should_fail_to_decrypt_if_the_key_is_different() {
         let config = SecretsConfig::default().with_provider(SecretServiceProvider::IosKms);
@@ -257,9 +736,12 @@ mod tests {
             .await
             .expect("Failed to encrypt data");
 
+        let (algorithm, encrypted) = encrypted
+            .split_once(ALGORITHM_SEPARATOR)
+            .expect("Ciphertext should carry its algorithm");
         let mut obsf = hex::decode(encrypted).expect("Failed to decode encrypted data");
         obsf[0] = 0;
-        let tampered = hex::encode(obsf);
+        let tampered = format!("{algorithm}{ALGORITHM_SEPARATOR}{}", hex::encode(obsf));
 
         let decrypted = crypto.decrypt(tampered).await;
 
@@ -1,18 +1,23 @@
+mod clock;
 mod crypto;
 mod fetcher;
 mod hash;
 mod oauth;
+mod pagination;
 mod pipeline;
 mod secret;
+mod sigv4;
 mod store;
 mod string;
 mod template;
 mod timed;
 
+pub use clock::*;
 pub use crypto::*;
 pub use fetcher::*;
 pub use hash::*;
 pub use oauth::*;
+pub use pagination::*;
 pub use pipeline::*;
 pub use secret::*;
 pub use store::*;
@@ -0,0 +1,253 @@
+use super::MongoStore;
+use crate::{ApplicationError, IntegrationOSError, InternalError};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use bson::{doc, Bson, Document};
+use futures::TryStreamExt;
+use mongodb::options::FindOptions;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+const ID_KEY: &str = "_id";
+const SORT_KEY: &str = "sort";
+const AFTER_KEY: &str = "after";
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadResponse<T> {
+    pub rows: Vec<T>,
+    pub total: u64,
+    pub skip: u64,
+    pub limit: u64,
+    /// Cursor of the next page of a keyset paginated read, none on the last page and for
+    /// reads paginated by offset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl<T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static> MongoStore<T> {
+    /// Page of the documents matching `filter` in the order of `sort`, starting after the row
+    /// `cursor` was issued for, or at the first row without one. Ranges on the sort keys are
+    /// used instead of skipping documents, so that reading a page costs the same wherever it
+    /// is in the collection. `_id` breaks the ties of the sort.
+    pub async fn paginate(
+        &self,
+        filter: Document,
+        sort: Document,
+        page_size: u64,
+        cursor: Option<&str>,
+    ) -> Result<ReadResponse<T>, IntegrationOSError> {
+        let total = self.count(filter.clone(), None);
+        let page = self.paginate_without_count(filter, sort, page_size, cursor);
+        let (total, page) = futures::try_join!(total, page)?;
+
+        Ok(ReadResponse { total, ..page })
+    }
+
+    /// [`Self::paginate`] without counting the documents matching `filter`, `total` is zero
+    pub async fn paginate_without_count(
+        &self,
+        filter: Document,
+        sort: Document,
+        page_size: u64,
+        cursor: Option<&str>,
+    ) -> Result<ReadResponse<T>, IntegrationOSError> {
+        let sort = keyset_sort(sort);
+        let page_size = page_size.max(1);
+
+        let filter = match cursor {
+            Some(cursor) => {
+                let after = decode_cursor(cursor, &sort)?;
+                doc! { "$and": [filter, after_filter(&sort, &after)] }
+            }
+            None => filter,
+        };
+
+        let options = FindOptions::builder()
+            .sort(sort.clone())
+            .limit((page_size + 1) as i64)
+            .build();
+        let mut documents = self
+            .collection
+            .clone_with_type::<Document>()
+            .find(filter, options)
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        let next_cursor = if documents.len() as u64 > page_size {
+            documents.truncate(page_size as usize);
+            documents
+                .last()
+                .map(|last| encode_cursor(&sort, last))
+                .transpose()?
+        } else {
+            None
+        };
+
+        let rows = documents
+            .into_iter()
+            .map(|document| {
+                bson::from_document(document)
+                    .map_err(|e| InternalError::deserialize_error(&e.to_string(), None))
+            })
+            .collect::<Result<Vec<T>, _>>()?;
+
+        Ok(ReadResponse {
+            rows,
+            total: 0,
+            skip: 0,
+            limit: page_size,
+            next_cursor,
+        })
+    }
+}
+
+/// `sort` with `_id` appended, in the direction of the last key, so that rows are totally
+/// ordered
+fn keyset_sort(mut sort: Document) -> Document {
+    if !sort.contains_key(ID_KEY) {
+        let direction = sort
+            .iter()
+            .last()
+            .map_or(Bson::Int32(1), |(_, d)| d.clone());
+        sort.insert(ID_KEY, direction);
+    }
+    sort
+}
+
+fn is_descending(direction: &Bson) -> bool {
+    matches!(direction.as_i64().or(direction.as_i32().map(i64::from)), Some(d) if d < 0)
+}
+
+/// Value of a dotted `path` of `document`, null if it is missing
+fn sort_value(document: &Document, path: &str) -> Bson {
+    let mut keys = path.split('.');
+    let mut value = keys.next().and_then(|key| document.get(key));
+    for key in keys {
+        value = value
+            .and_then(Bson::as_document)
+            .and_then(|document| document.get(key));
+    }
+    value.cloned().unwrap_or(Bson::Null)
+}
+
+fn encode_cursor(sort: &Document, last: &Document) -> Result<String, IntegrationOSError> {
+    let after = sort
+        .keys()
+        .map(|key| (key.clone(), sort_value(last, key)))
+        .collect::<Document>();
+    let cursor = doc! { SORT_KEY: sort, AFTER_KEY: after };
+
+    let bytes =
+        bson::to_vec(&cursor).map_err(|e| InternalError::serialize_error(&e.to_string(), None))?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Sort keys of the row `cursor` was issued for. Cursors that can't be decoded, or were issued
+/// for another sort, are rejected.
+fn decode_cursor(cursor: &str, sort: &Document) -> Result<Document, IntegrationOSError> {
+    let invalid = || ApplicationError::bad_request("Invalid cursor", None);
+
+    let bytes = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let cursor = bson::from_slice::<Document>(&bytes).map_err(|_| invalid())?;
+    match (
+        cursor.get_document(SORT_KEY),
+        cursor.get_document(AFTER_KEY),
+    ) {
+        (Ok(cursor_sort), Ok(after))
+            if cursor_sort == sort && sort.keys().all(|key| after.contains_key(key)) =>
+        {
+            Ok(after.clone())
+        }
+        _ => Err(ApplicationError::bad_request(
+            "Invalid cursor, it was issued for another sort",
+            None,
+        )),
+    }
+}
+
+/// Filter of the rows following `after` in the order of `sort`: rows equal on the first keys
+/// and past `after` on the next one, for each of the keys
+fn after_filter(sort: &Document, after: &Document) -> Document {
+    let keys = sort.iter().collect::<Vec<_>>();
+    let branches = (0..keys.len())
+        .map(|i| {
+            let mut branch = keys[..i]
+                .iter()
+                .map(|(key, _)| {
+                    (
+                        (*key).clone(),
+                        after.get(*key).cloned().unwrap_or(Bson::Null),
+                    )
+                })
+                .collect::<Document>();
+            let (key, direction) = keys[i];
+            let operator = if is_descending(direction) {
+                "$lt"
+            } else {
+                "$gt"
+            };
+            let value = after.get(key).cloned().unwrap_or(Bson::Null);
+            branch.insert(key.clone(), doc! { operator: value });
+            Bson::Document(branch)
+        })
+        .collect::<Vec<_>>();
+
+    doc! { "$or": branches }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trips_the_sort_keys_of_the_last_row() {
+        let sort = keyset_sort(doc! { "createdAt": -1 });
+        assert_eq!(sort, doc! { "createdAt": -1, "_id": -1 });
+
+        let last = doc! { "_id": "pipe_1", "createdAt": 42_i64, "name": "ignored" };
+        let cursor = encode_cursor(&sort, &last).unwrap();
+        assert_eq!(
+            decode_cursor(&cursor, &sort).unwrap(),
+            doc! { "createdAt": 42_i64, "_id": "pipe_1" }
+        );
+    }
+
+    #[test]
+    fn test_invalid_cursors_are_bad_requests() {
+        let sort = keyset_sort(doc! { "createdAt": -1 });
+        let other_sort = keyset_sort(doc! { "name": 1 });
+        let cursor = encode_cursor(&other_sort, &doc! { "_id": "a", "name": "b" }).unwrap();
+
+        for cursor in ["not a cursor", "bm90IGJzb24", cursor.as_str()] {
+            let err = decode_cursor(cursor, &sort).unwrap_err();
+            assert!(matches!(
+                err,
+                IntegrationOSError::Application(ApplicationError::BadRequest { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_after_filter_ranges_over_every_sort_key() {
+        let sort = keyset_sort(doc! { "createdAt": -1, "name": 1 });
+        let after = doc! { "createdAt": 42_i64, "name": "b", "_id": "a" };
+
+        assert_eq!(
+            after_filter(&sort, &after),
+            doc! {
+                "$or": [
+                    { "createdAt": { "$lt": 42_i64 } },
+                    { "createdAt": 42_i64, "name": { "$gt": "b" } },
+                    { "createdAt": 42_i64, "name": "b", "_id": { "$gt": "a" } },
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_nested_sort_keys_are_read_from_the_row() {
+        let row = doc! { "record": { "updatedAt": 7 } };
+        assert_eq!(sort_value(&row, "record.updatedAt"), Bson::Int32(7));
+        assert_eq!(sort_value(&row, "record.missing"), Bson::Null);
+    }
+}
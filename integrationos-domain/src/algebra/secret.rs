@@ -1,12 +1,14 @@
-use super::{CryptoExt, GoogleCryptoKms, IOSCrypto, MongoStore};
+use super::{AwsCryptoKms, CompositeCrypto, CryptoExt, GoogleCryptoKms, IOSCrypto, MongoStore};
 use crate::{
-    prelude::secret::Secret, secrets::SecretsConfig, IntegrationOSError, InternalError,
-    SecretVersion,
+    prelude::secret::Secret,
+    secrets::{SecretServiceProvider, SecretsConfig},
+    IntegrationOSError, InternalError, SecretVersion,
 };
 use async_trait::async_trait;
 use bson::doc;
 use secrecy::ExposeSecret;
 use serde_json::Value;
+use std::sync::Arc;
 
 #[async_trait]
 pub trait SecretExt {
@@ -17,6 +19,9 @@ pub trait SecretExt {
         secret: &Value,
         buildable_id: &str,
     ) -> Result<Secret, IntegrationOSError>;
+
+    /// Checks that secrets can be read, without reading any
+    async fn ping(&self) -> Result<(), IntegrationOSError>;
 }
 
 #[derive(Debug, Clone)]
@@ -82,6 +87,10 @@ impl SecretExt for IOSKms {
 
         Ok(secret)
     }
+
+    async fn ping(&self) -> Result<(), IntegrationOSError> {
+        self.storage.count(doc! {}, Some(1)).await.map(|_| ())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -146,4 +155,163 @@ impl SecretExt for GoogleKms {
 
         Ok(secret)
     }
+
+    async fn ping(&self) -> Result<(), IntegrationOSError> {
+        self.storage.count(doc! {}, Some(1)).await.map(|_| ())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AwsKms {
+    storage: MongoStore<Secret>,
+    crypto: AwsCryptoKms,
+}
+
+impl AwsKms {
+    pub fn new(secrets_config: &SecretsConfig, storage: MongoStore<Secret>) -> Self {
+        let crypto = AwsCryptoKms::new(secrets_config);
+        Self { crypto, storage }
+    }
+}
+
+#[async_trait]
+impl SecretExt for AwsKms {
+    async fn get(&self, id: &str, buildable_id: &str) -> Result<Secret, IntegrationOSError> {
+        let secret = self
+            .storage
+            .get_one(doc! { "_id": id, "buildableId": buildable_id })
+            .await?
+            .ok_or_else(|| InternalError::key_not_found("Secret", None))?;
+
+        let encrypted_secret = secret.encrypted_secret().expose_secret().to_owned();
+        let version = secret.version();
+
+        let decrypted_secret = self.crypto.decrypt(encrypted_secret, version).await?;
+
+        Ok(Secret::new(
+            decrypted_secret,
+            secret.version(),
+            secret.buildable_id(),
+            Some(secret.created_at()),
+        ))
+    }
+
+    async fn create(
+        &self,
+        secret: &Value,
+        buildable_id: &str,
+    ) -> Result<Secret, IntegrationOSError> {
+        let string = serde_json::to_string(&secret).map_err(|_| {
+            InternalError::serialize_error("The provided value is not a valid UTF-8 string", None)
+        })?;
+        let encrypted_secret = self.crypto.encrypt(string).await?;
+
+        let secret = Secret::new(
+            encrypted_secret,
+            Some(SecretVersion::V2),
+            buildable_id.to_owned(),
+            None,
+        );
+
+        self.storage
+            .create_one(&secret)
+            .await
+            .map_err(|e| InternalError::io_err(e.as_ref(), None))?;
+
+        Ok(secret)
+    }
+
+    async fn ping(&self) -> Result<(), IntegrationOSError> {
+        self.storage.count(doc! {}, Some(1)).await.map(|_| ())
+    }
+}
+
+/// Secrets migrating from one provider to another, see [`CompositeCrypto`]
+#[derive(Clone)]
+pub struct CompositeKms {
+    storage: MongoStore<Secret>,
+    crypto: CompositeCrypto,
+}
+
+impl CompositeKms {
+    pub async fn new(
+        secrets_config: &SecretsConfig,
+        migrate_from: SecretServiceProvider,
+        storage: MongoStore<Secret>,
+    ) -> Result<Self, IntegrationOSError> {
+        let crypto = CompositeCrypto::from_config(secrets_config, migrate_from).await?;
+        Ok(Self { crypto, storage })
+    }
+}
+
+#[async_trait]
+impl SecretExt for CompositeKms {
+    async fn get(&self, id: &str, buildable_id: &str) -> Result<Secret, IntegrationOSError> {
+        let secret = self
+            .storage
+            .get_one(doc! { "_id": id, "buildableId": buildable_id })
+            .await?
+            .ok_or_else(|| InternalError::key_not_found("Secret", None))?;
+
+        let encrypted_secret = secret.encrypted_secret().expose_secret().to_owned();
+        let version = secret.version();
+
+        let decrypted_secret = self.crypto.decrypt(encrypted_secret, version).await?;
+
+        Ok(Secret::new(
+            decrypted_secret,
+            secret.version(),
+            secret.buildable_id(),
+            Some(secret.created_at()),
+        ))
+    }
+
+    async fn create(
+        &self,
+        secret: &Value,
+        buildable_id: &str,
+    ) -> Result<Secret, IntegrationOSError> {
+        let string = serde_json::to_string(&secret).map_err(|_| {
+            InternalError::serialize_error("The provided value is not a valid UTF-8 string", None)
+        })?;
+        let encrypted_secret = self.crypto.encrypt(string).await?;
+
+        let secret = Secret::new(
+            encrypted_secret,
+            Some(SecretVersion::V2),
+            buildable_id.to_owned(),
+            None,
+        );
+
+        self.storage
+            .create_one(&secret)
+            .await
+            .map_err(|e| InternalError::io_err(e.as_ref(), None))?;
+
+        Ok(secret)
+    }
+
+    async fn ping(&self) -> Result<(), IntegrationOSError> {
+        self.storage.count(doc! {}, Some(1)).await.map(|_| ())
+    }
+}
+
+/// Secrets client of the configured provider, or of the migration from another one
+pub async fn secrets_client(
+    secrets_config: &SecretsConfig,
+    storage: MongoStore<Secret>,
+) -> Result<Arc<dyn SecretExt + Sync + Send>, IntegrationOSError> {
+    if let Some(migrate_from) = secrets_config.migrate_from {
+        return Ok(Arc::new(
+            CompositeKms::new(secrets_config, migrate_from, storage).await?,
+        ));
+    }
+
+    Ok(match secrets_config.provider {
+        SecretServiceProvider::GoogleKms => {
+            Arc::new(GoogleKms::new(secrets_config, storage).await?)
+        }
+        SecretServiceProvider::IosKms => Arc::new(IOSKms::new(secrets_config, storage).await?),
+        SecretServiceProvider::AwsKms => Arc::new(AwsKms::new(secrets_config, storage)),
+    })
 }
@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// Credentials and scope requests to an AWS service are signed with (Signature Version 4)
+pub(crate) struct SigningParams<'a> {
+    pub access_key_id: &'a str,
+    pub secret_access_key: &'a str,
+    pub session_token: Option<&'a str>,
+    pub region: &'a str,
+    pub service: &'a str,
+    pub time: DateTime<Utc>,
+}
+
+/// Request to sign. `query` is the canonical query string, its parameters sorted and encoded.
+pub(crate) struct SignableRequest<'a> {
+    pub method: &'a str,
+    pub host: &'a str,
+    pub path: &'a str,
+    pub query: &'a str,
+    pub headers: &'a [(&'a str, &'a str)],
+    pub payload: &'a [u8],
+}
+
+/// Headers to add to `request` for AWS to authenticate it: its date, the session token if any
+/// and its authorization. `host` and the headers of the request are signed along with them.
+pub(crate) fn sign(params: &SigningParams, request: &SignableRequest) -> Vec<(String, String)> {
+    let amz_date = params.time.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = params.time.format("%Y%m%d").to_string();
+
+    let mut added = vec![("x-amz-date".to_owned(), amz_date.clone())];
+    if let Some(token) = params.session_token {
+        added.push(("x-amz-security-token".to_owned(), token.to_owned()));
+    }
+
+    let mut signed = request
+        .headers
+        .iter()
+        .map(|(name, value)| (name.to_lowercase(), value.trim().to_owned()))
+        .chain([("host".to_owned(), request.host.to_owned())])
+        .chain(added.iter().cloned())
+        .collect::<Vec<_>>();
+    signed.sort();
+
+    let canonical_headers = signed
+        .iter()
+        .map(|(name, value)| format!("{name}:{value}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let signed_headers = signed
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{canonical_headers}\n\n{signed_headers}\n{}",
+        request.method,
+        request.path,
+        request.query,
+        hex::encode(Sha256::digest(request.payload)),
+    );
+
+    let scope = format!("{date}/{}/{}/aws4_request", params.region, params.service);
+    let string_to_sign = format!(
+        "{ALGORITHM}\n{amz_date}\n{scope}\n{}",
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = [params.region, params.service, "aws4_request"].iter().fold(
+        hmac(
+            format!("AWS4{}", params.secret_access_key).as_bytes(),
+            date.as_bytes(),
+        ),
+        |key, part| hmac(&key, part.as_bytes()),
+    );
+    let signature = hex::encode(hmac(&key, string_to_sign.as_bytes()));
+
+    added.push((
+        "authorization".to_owned(),
+        format!(
+            "{ALGORITHM} Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            params.access_key_id
+        ),
+    ));
+    added
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_signature_of_the_aws_documentation_example() {
+        let params = SigningParams {
+            access_key_id: "AKIDEXAMPLE",
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            session_token: None,
+            region: "us-east-1",
+            service: "iam",
+            time: Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap(),
+        };
+        let request = SignableRequest {
+            method: "GET",
+            host: "iam.amazonaws.com",
+            path: "/",
+            query: "Action=ListUsers&Version=2010-05-08",
+            headers: &[(
+                "Content-Type",
+                "application/x-www-form-urlencoded; charset=utf-8",
+            )],
+            payload: b"",
+        };
+
+        assert_eq!(
+            sign(&params, &request),
+            vec![
+                ("x-amz-date".to_owned(), "20150830T123600Z".to_owned()),
+                (
+                    "authorization".to_owned(),
+                    "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+                     SignedHeaders=content-type;host;x-amz-date, \
+                     Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+                        .to_owned()
+                ),
+            ]
+        );
+    }
+}
@@ -1,12 +1,24 @@
-use crate::IntegrationOSError;
 use crate::Store;
+use crate::{IntegrationOSError, InternalError};
 use bson::doc;
 use futures::TryStreamExt;
 use mongodb::bson::Document;
 use mongodb::options::CountOptions;
 use mongodb::{Collection, Database};
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+/// Upserts sent to the database in a single command, larger inputs are split into chunks
+pub const MAX_UPSERT_BATCH_SIZE: usize = 1000;
+
+/// Outcome of a bulk upsert
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpsertCounts {
+    /// Documents inserted because no document matched their filter
+    pub inserted: u64,
+    /// Existing documents that were changed
+    pub modified: u64,
+}
 
 #[derive(Debug, Clone)]
 pub struct MongoStore<T: Serialize + DeserializeOwned + Unpin + Sync> {
@@ -113,6 +125,49 @@ impl<T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static> MongoStore
         Ok(())
     }
 
+    /// Replaces the document matching `key_fn` of each item with the item, inserting the items
+    /// matching no document. Items are sent in chunks of [`MAX_UPSERT_BATCH_SIZE`].
+    pub async fn bulk_upsert(
+        &self,
+        items: &[T],
+        key_fn: impl Fn(&T) -> Document,
+    ) -> Result<UpsertCounts, IntegrationOSError> {
+        let updates = items
+            .iter()
+            .map(|item| {
+                bson::to_document(item)
+                    .map(|document| (key_fn(item), document))
+                    .map_err(|e| InternalError::serialize_error(&e.to_string(), None))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.bulk_update_upsert(updates).await
+    }
+
+    /// Applies each update, or replacement, to the document matching its filter, creating the
+    /// document if none matches. Updates are sent in chunks of [`MAX_UPSERT_BATCH_SIZE`].
+    pub async fn bulk_update_upsert(
+        &self,
+        updates: Vec<(Document, Document)>,
+    ) -> Result<UpsertCounts, IntegrationOSError> {
+        let database = self
+            .collection
+            .client()
+            .database(&self.collection.namespace().db);
+
+        let mut counts = UpsertCounts::default();
+        for chunk in updates.chunks(MAX_UPSERT_BATCH_SIZE) {
+            let reply = database
+                .run_command(upsert_command(self.collection.name(), chunk), None)
+                .await?;
+            let chunk_counts = upsert_counts(&reply)?;
+            counts.inserted += chunk_counts.inserted;
+            counts.modified += chunk_counts.modified;
+        }
+
+        Ok(counts)
+    }
+
     pub async fn count(
         &self,
         filter: Document,
@@ -124,3 +179,103 @@ impl<T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static> MongoStore
             .await?)
     }
 }
+
+/// `update` command upserting every `(filter, update)` of `updates`, unordered so that a
+/// failing update does not stop the following ones
+fn upsert_command(collection: &str, updates: &[(Document, Document)]) -> Document {
+    let statements = updates
+        .iter()
+        .map(|(filter, update)| doc! { "q": filter, "u": update, "upsert": true })
+        .collect::<Vec<_>>();
+
+    doc! {
+        "update": collection,
+        "updates": statements,
+        "ordered": false,
+    }
+}
+
+/// Counts of the reply to an [`upsert_command`], failing if any of the updates failed
+fn upsert_counts(reply: &Document) -> Result<UpsertCounts, IntegrationOSError> {
+    if let Ok(errors) = reply.get_array("writeErrors") {
+        let messages = errors
+            .iter()
+            .filter_map(|error| error.as_document()?.get_str("errmsg").ok())
+            .collect::<Vec<_>>();
+        return Err(InternalError::unknown(
+            &format!("{} upserts failed: {}", errors.len(), messages.join(", ")),
+            None,
+        ));
+    }
+
+    let modified = match reply.get("nModified") {
+        Some(bson::Bson::Int32(n)) => *n as u64,
+        Some(bson::Bson::Int64(n)) => *n as u64,
+        _ => 0,
+    };
+    let inserted = reply
+        .get_array("upserted")
+        .map(|upserted| upserted.len() as u64)
+        .unwrap_or_default();
+
+    Ok(UpsertCounts { inserted, modified })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upsert_command_holds_an_upsert_per_update() {
+        let updates = vec![
+            (doc! { "_id": "a" }, doc! { "_id": "a", "value": 1 }),
+            (doc! { "_id": "b" }, doc! { "$inc": { "value": 1 } }),
+        ];
+
+        assert_eq!(
+            upsert_command("metrics", &updates),
+            doc! {
+                "update": "metrics",
+                "updates": [
+                    { "q": { "_id": "a" }, "u": { "_id": "a", "value": 1 }, "upsert": true },
+                    { "q": { "_id": "b" }, "u": { "$inc": { "value": 1 } }, "upsert": true },
+                ],
+                "ordered": false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_upsert_counts_tell_inserts_from_updates() {
+        let inserts = doc! { "n": 2, "nModified": 0, "upserted": [{ "index": 0, "_id": "a" }, { "index": 1, "_id": "b" }], "ok": 1.0 };
+        assert_eq!(
+            upsert_counts(&inserts).unwrap(),
+            UpsertCounts {
+                inserted: 2,
+                modified: 0
+            }
+        );
+
+        let updates = doc! { "n": 2, "nModified": 2, "ok": 1.0 };
+        assert_eq!(
+            upsert_counts(&updates).unwrap(),
+            UpsertCounts {
+                inserted: 0,
+                modified: 2
+            }
+        );
+
+        let mixed =
+            doc! { "n": 3, "nModified": 1, "upserted": [{ "index": 2, "_id": "c" }], "ok": 1.0 };
+        assert_eq!(
+            upsert_counts(&mixed).unwrap(),
+            UpsertCounts {
+                inserted: 1,
+                modified: 1
+            }
+        );
+
+        let failed = doc! { "n": 0, "nModified": 0, "writeErrors": [{ "index": 0, "code": 66, "errmsg": "Performing an update on the path '_id' would modify the immutable field '_id'" }], "ok": 1.0 };
+        assert!(upsert_counts(&failed).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{IntegrationOSError, InternalError};
+use crate::{event_access::EventAccessScope, IntegrationOSError, InternalError};
 use napi_derive::napi;
 use prost::Message;
 
@@ -22,6 +22,9 @@ pub struct AccessKeyData {
     pub timestamp_path: Option<String>,
     #[prost(string, optional, tag = "8")]
     pub parent_access_key: Option<String>,
+    /// Scopes of the event access of the key, keys without any predate scopes and have them all
+    #[prost(string, repeated, tag = "9")]
+    pub scopes: Vec<String>,
 }
 
 impl TryFrom<&[u8]> for AccessKeyData {
@@ -42,6 +45,10 @@ impl AccessKeyData {
         })
     }
 
+    pub fn has_scope(&self, scope: EventAccessScope) -> bool {
+        self.scopes.is_empty() || self.scopes.iter().any(|s| s == scope.as_ref())
+    }
+
     pub fn to_vec(&self) -> Result<Vec<u8>, IntegrationOSError> {
         let mut buf = Vec::with_capacity(self.encoded_len());
         self.encode(&mut buf).map_err(|e| {
@@ -69,8 +76,19 @@ mod tests {
             event_object_id_path: Some("quuz".to_owned()),
             timestamp_path: None,
             parent_access_key: None,
+            scopes: vec!["read".to_owned()],
         };
         let vec = access_key_data.to_vec().unwrap();
         assert_eq!(access_key_data, AccessKeyData::from_slice(&vec).unwrap());
     }
+
+    #[test]
+    fn test_keys_without_scopes_have_every_scope() {
+        let mut access_key_data = AccessKeyData::default();
+        assert!(access_key_data.has_scope(EventAccessScope::Ingest));
+
+        access_key_data.scopes = vec!["read".to_owned()];
+        assert!(access_key_data.has_scope(EventAccessScope::Read));
+        assert!(!access_key_data.has_scope(EventAccessScope::Ingest));
+    }
 }
@@ -117,6 +117,7 @@ mod tests {
                 event_object_id_path: Some("foo.bar".to_owned()),
                 timestamp_path: Some("foo.bar".to_owned()),
                 parent_access_key: Some("foo.bar".to_owned()),
+                scopes: vec![],
             },
         };
 
@@ -142,6 +143,7 @@ mod tests {
                 event_object_id_path: Some("foo.bar".to_owned()),
                 timestamp_path: Some("foo.bar".to_owned()),
                 parent_access_key: Some("foo.bar".to_owned()),
+                scopes: vec![],
             },
         };
 
@@ -180,6 +182,7 @@ mod tests {
                 event_object_id_path: None,
                 timestamp_path: None,
                 parent_access_key: None,
+                scopes: vec![],
             },
         };
 
@@ -2,6 +2,9 @@ use std::fmt::{Display, Formatter};
 
 use envconfig::Envconfig;
 
+use crate::event::partition::EventPartitionKey;
+use crate::number_precision::NumberPrecision;
+
 #[derive(Envconfig, Clone)] // Intentionally no Debug so secret is not printed
 pub struct DatabaseConfig {
     #[envconfig(from = "CONTROL_DATABASE_URL", default = "mongodb://localhost:27017")]
@@ -22,6 +25,10 @@ pub struct DatabaseConfig {
     pub context_db_name: String,
     #[envconfig(from = "CONTEXT_COLLECTION_NAME", default = "event-transactions")]
     pub context_collection_name: String,
+    #[envconfig(from = "EVENT_PARTITION_KEY", default = "none")]
+    pub event_partition_key: EventPartitionKey,
+    #[envconfig(from = "JSON_NUMBER_PRECISION", default = "string")]
+    pub json_number_precision: NumberPrecision,
 }
 
 impl DatabaseConfig {
@@ -42,6 +49,8 @@ impl Default for DatabaseConfig {
             context_db_url: "mongodb://localhost:27017".to_owned(),
             context_db_name: "database".to_owned(),
             context_collection_name: "event-transactions".to_owned(),
+            event_partition_key: EventPartitionKey::None,
+            json_number_precision: NumberPrecision::String,
         }
     }
 }
@@ -60,7 +69,9 @@ impl Display for DatabaseConfig {
             f,
             "CONTEXT_COLLECTION_NAME: {}",
             self.context_collection_name
-        )
+        )?;
+        writeln!(f, "EVENT_PARTITION_KEY: {}", self.event_partition_key)?;
+        writeln!(f, "JSON_NUMBER_PRECISION: {}", self.json_number_precision)
     }
 }
 
@@ -105,6 +116,8 @@ mod tests {
             CONTEXT_DATABASE_URL: ****\n\
             CONTEXT_DATABASE_NAME: database\n\
             CONTEXT_COLLECTION_NAME: event-transactions\n\
+            EVENT_PARTITION_KEY: none\n\
+            JSON_NUMBER_PRECISION: string\n\
         ";
 
         assert_eq!(config_str, display);
@@ -1,20 +1,35 @@
 use envconfig::Envconfig;
 use secrecy::SecretString;
 use std::fmt::{Display, Formatter, Result};
-use strum::{AsRefStr, EnumString};
+use strum::{AsRefStr, Display, EnumString};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, AsRefStr)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString, AsRefStr)]
 #[strum(serialize_all = "kebab-case")]
 pub enum SecretServiceProvider {
     GoogleKms,
     IosKms,
+    AwsKms,
     // TODO: Implement LocalStorage
 }
 
+/// Symmetric algorithm secrets are encrypted with by [`crate::IOSCrypto`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, EnumString, AsRefStr, Display)]
+pub enum CryptoAlgorithm {
+    #[default]
+    #[strum(serialize = "chacha20-poly1305")]
+    ChaCha20Poly1305,
+    #[strum(serialize = "aes-256-gcm")]
+    Aes256Gcm,
+}
+
 #[derive(Debug, Clone, Envconfig)]
 pub struct SecretsConfig {
     #[envconfig(from = "SECRETS_SERVICE_PROVIDER", default = "google-kms")]
     pub provider: SecretServiceProvider,
+    /// Provider the secrets were encrypted with before `provider`, set while migrating them.
+    /// New secrets are encrypted with `provider` and the ones of both are decrypted.
+    #[envconfig(from = "SECRETS_MIGRATE_FROM")]
+    pub migrate_from: Option<SecretServiceProvider>,
     #[envconfig(from = "GOOGLE_KMS_PROJECT_ID", default = "buildable-production")]
     pub google_kms_project_id: String,
     #[envconfig(from = "GOOGLE_KMS_LOCATION_ID", default = "global")]
@@ -31,6 +46,22 @@ pub struct SecretsConfig {
         default = "xTtUQejH8eSNmWP5rlnHLkOWkHeflivG"
     )]
     pub ios_crypto_secret: SecretString,
+    /// Algorithm new secrets are encrypted with, the ones encrypted with any other are still
+    /// decrypted
+    #[envconfig(from = "IOS_CRYPTO_ALGORITHM", default = "chacha20-poly1305")]
+    pub ios_crypto_algorithm: CryptoAlgorithm,
+    #[envconfig(from = "AWS_KMS_REGION", default = "us-east-1")]
+    pub aws_kms_region: String,
+    /// Id, ARN or alias of the key secrets are encrypted with
+    #[envconfig(from = "AWS_KMS_KEY_ID", default = "alias/secrets-service-development")]
+    pub aws_kms_key_id: String,
+    #[envconfig(from = "AWS_ACCESS_KEY_ID", default = "")]
+    pub aws_access_key_id: String,
+    #[envconfig(from = "AWS_SECRET_ACCESS_KEY", default = "")]
+    pub aws_secret_access_key: SecretString,
+    /// Token of temporary credentials
+    #[envconfig(from = "AWS_SESSION_TOKEN")]
+    pub aws_session_token: Option<SecretString>,
 }
 
 impl SecretsConfig {
@@ -45,6 +76,12 @@ impl SecretsConfig {
         self
     }
 
+    #[cfg(test)]
+    pub fn with_algorithm(mut self, algorithm: CryptoAlgorithm) -> Self {
+        self.ios_crypto_algorithm = algorithm;
+        self
+    }
+
     #[cfg(test)]
     pub fn with_provider(mut self, provider: SecretServiceProvider) -> Self {
         self.provider = provider;
@@ -56,11 +93,18 @@ impl Default for SecretsConfig {
     fn default() -> Self {
         Self {
             provider: SecretServiceProvider::GoogleKms,
+            migrate_from: None,
             google_kms_project_id: "buildable-production".to_owned(),
             google_kms_location_id: "global".to_owned(),
             google_kms_key_ring_id: "secrets-service-local".to_owned(),
             google_kms_key_id: "secrets-service-local".to_owned(),
             ios_crypto_secret: SecretString::new("xTtUQejH8eSNmWP5rlnHLkOWkHeflivG".to_owned()),
+            ios_crypto_algorithm: CryptoAlgorithm::default(),
+            aws_kms_region: "us-east-1".to_owned(),
+            aws_kms_key_id: "alias/secrets-service-local".to_owned(),
+            aws_access_key_id: String::new(),
+            aws_secret_access_key: SecretString::new(String::new()),
+            aws_session_token: None,
         }
     }
 }
@@ -69,11 +113,22 @@ impl Display for SecretsConfig {
     // TODO: Update this
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         writeln!(f, "SECRETS_SERVICE_PROVIDER: {}", self.provider.as_ref())?;
+        writeln!(
+            f,
+            "SECRETS_MIGRATE_FROM: {:?}",
+            self.migrate_from.as_ref().map(AsRef::<str>::as_ref)
+        )?;
         writeln!(f, "GOOGLE_KMS_PROJECT_ID: ****")?;
         writeln!(f, "GOOGLE_KMS_LOCATION_ID: ****")?;
         writeln!(f, "GOOGLE_KMS_KEY_RING_ID: ****")?;
         writeln!(f, "GOOGLE_KMS_KEY_ID: ****")?;
-        writeln!(f, "IOS_CRYPTO_SECRET: ****")
+        writeln!(f, "IOS_CRYPTO_SECRET: ****")?;
+        writeln!(f, "IOS_CRYPTO_ALGORITHM: {}", self.ios_crypto_algorithm)?;
+        writeln!(f, "AWS_KMS_REGION: {}", self.aws_kms_region)?;
+        writeln!(f, "AWS_KMS_KEY_ID: ****")?;
+        writeln!(f, "AWS_ACCESS_KEY_ID: ****")?;
+        writeln!(f, "AWS_SECRET_ACCESS_KEY: ****")?;
+        writeln!(f, "AWS_SESSION_TOKEN: ****")
     }
 }
 
@@ -105,11 +160,18 @@ mod tests {
         let config_str = format!("{config}");
 
         let display = "SECRETS_SERVICE_PROVIDER: google-kms\n\
+            SECRETS_MIGRATE_FROM: None\n\
             GOOGLE_KMS_PROJECT_ID: ****\n\
             GOOGLE_KMS_LOCATION_ID: ****\n\
             GOOGLE_KMS_KEY_RING_ID: ****\n\
             GOOGLE_KMS_KEY_ID: ****\n\
             IOS_CRYPTO_SECRET: ****\n\
+            IOS_CRYPTO_ALGORITHM: chacha20-poly1305\n\
+            AWS_KMS_REGION: us-east-1\n\
+            AWS_KMS_KEY_ID: ****\n\
+            AWS_ACCESS_KEY_ID: ****\n\
+            AWS_SECRET_ACCESS_KEY: ****\n\
+            AWS_SESSION_TOKEN: ****\n\
             ";
 
         assert_eq!(config_str, display);
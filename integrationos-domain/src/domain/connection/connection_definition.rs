@@ -1,7 +1,10 @@
-use super::{api_model_config::AuthMethod, ConnectionType};
+use super::{api_model_config::AuthMethod, input_validation::InputValidationRule, ConnectionType};
 use crate::id::{prefix::IdPrefix, Id};
 use crate::prelude::shared::{record_metadata::RecordMetadata, settings::Settings};
+use crate::{ApplicationError, IntegrationOSError, InternalError};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::BTreeMap, time::Duration};
 use strum::{self, AsRefStr, Display};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -27,10 +30,78 @@ pub struct ConnectionDefinition {
     pub settings: Settings,
     pub hidden: bool,
     pub test_connection: Option<Id>,
+    /// Query parameters added to every outbound request of this platform, unless the request
+    /// already provides them. Values are templated with the connection secret.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub default_query_params: BTreeMap<String, String>,
+    /// Retry policy of the unified calls to this platform, overriding the default one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub retry_policy: Option<DownstreamRetryPolicy>,
+    /// Rules the auth form data of connections to this platform must satisfy to be saved
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub input_rules: Vec<InputValidationRule>,
+    /// Definition this one was derived from, see [`ConnectionDefinition::derive`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub template_id: Option<Id>,
+    /// How connections to this platform rotate their credentials through the platform, unset
+    /// when the platform can't rotate them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub credential_rotation: Option<CredentialRotation>,
     #[serde(flatten, default)]
     pub record_metadata: RecordMetadata,
 }
 
+/// How unified calls are retried when the platform answers with a retryable status. The
+/// wait before each retry grows by `backoff_multiplier` from `initial_backoff_millis`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct DownstreamRetryPolicy {
+    /// Attempts made in total, the first one included
+    #[cfg_attr(feature = "dummy", dummy(faker = "1..5"))]
+    pub max_attempts: u32,
+    pub initial_backoff_millis: u64,
+    #[serde(default = "default_backoff_multiplier")]
+    #[cfg_attr(feature = "dummy", dummy(faker = "1..3"))]
+    pub backoff_multiplier: u32,
+    pub retryable_status_codes: Vec<u16>,
+}
+
+fn default_backoff_multiplier() -> u32 {
+    2
+}
+
+impl Default for DownstreamRetryPolicy {
+    /// Makes a single attempt
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff_millis: 100,
+            backoff_multiplier: default_backoff_multiplier(),
+            retryable_status_codes: vec![429, 502, 503, 504],
+        }
+    }
+}
+
+impl DownstreamRetryPolicy {
+    /// Whether the call is retried after its `attempt`-th attempt, counted from 1, answered
+    /// with `status`
+    pub fn should_retry(&self, attempt: u32, status: u16) -> bool {
+        attempt < self.max_attempts && self.retryable_status_codes.contains(&status)
+    }
+
+    /// Wait before retrying after the `attempt`-th attempt, counted from 1
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self
+            .backoff_multiplier
+            .saturating_pow(attempt.saturating_sub(1));
+        Duration::from_millis(self.initial_backoff_millis.saturating_mul(factor as u64))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PublicConnectionDetails {
     pub platform: String,
@@ -98,6 +169,7 @@ impl ConnectionDefinition {
                     name,
                     description,
                     form_data: vec![],
+                    localized: BTreeMap::new(),
                 },
             },
             test_connection: None,
@@ -120,6 +192,11 @@ impl ConnectionDefinition {
                 oauth: false,
             },
             hidden: true,
+            default_query_params: BTreeMap::new(),
+            retry_policy: None,
+            input_rules: vec![],
+            template_id: None,
+            credential_rotation: None,
             record_metadata: RecordMetadata::default(),
         }
     }
@@ -138,6 +215,130 @@ impl ConnectionDefinition {
     pub fn set_oauth(&mut self, oauth: bool) {
         self.settings.oauth = oauth;
     }
+
+    /// Effective definition of a definition using this one as its template. `overrides` is a
+    /// partial definition merged over this one: objects are merged field by field, any other
+    /// value replaces the inherited one. Unless overridden, the key is computed from the
+    /// effective platform and version.
+    pub fn derive(&self, id: Id, overrides: &Value) -> Result<Self, IntegrationOSError> {
+        let mut base = serde_json::to_value(Self {
+            record_metadata: RecordMetadata::default(),
+            ..self.clone()
+        })
+        .map_err(|e| InternalError::serialize_error(&e.to_string(), None))?;
+        merge(&mut base, overrides);
+
+        let mut derived: Self = serde_json::from_value(base)
+            .map_err(|e| ApplicationError::bad_request(&format!("Invalid overrides: {e}"), None))?;
+        derived.id = id;
+        derived.template_id = Some(self.id);
+        if overrides.get("key").is_none() {
+            derived.key = format!("api::{}::{}", derived.platform, derived.platform_version);
+        }
+
+        Ok(derived)
+    }
+
+    /// Replaces the labels of the connection form with the ones of the first of `locales` they
+    /// are localized in, the default labels being kept otherwise. The localized labels are
+    /// dropped, only the ones of the chosen locale being served.
+    pub fn localize(&mut self, locales: &[String]) {
+        let form = &mut self.frontend.connection_form;
+        if let Some(labels) = find_locale(&form.localized, locales) {
+            if let Some(name) = &labels.name {
+                form.name.clone_from(name);
+            }
+            if let Some(description) = &labels.description {
+                form.description.clone_from(description);
+            }
+        }
+        form.localized.clear();
+
+        for item in form.form_data.iter_mut() {
+            if let Some(labels) = find_locale(&item.localized, locales) {
+                if let Some(label) = &labels.label {
+                    item.label.clone_from(label);
+                }
+                if let Some(placeholder) = &labels.placeholder {
+                    item.placeholder.clone_from(placeholder);
+                }
+            }
+            item.localized.clear();
+        }
+    }
+}
+
+/// Labels of the first of `locales` found in `localized`. A locale matches the labels of its
+/// primary language as well, `fr-CA` falling back to `fr`.
+fn find_locale<'a, T>(localized: &'a BTreeMap<String, T>, locales: &[String]) -> Option<&'a T> {
+    let lookup = |locale: &str| {
+        localized
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(locale))
+            .map(|(_, labels)| labels)
+    };
+
+    locales.iter().find_map(|locale| {
+        lookup(locale).or_else(|| {
+            locale
+                .split_once('-')
+                .and_then(|(language, _)| lookup(language))
+        })
+    })
+}
+
+/// Rotation of the credentials of a connection through the key-rotation endpoint of its
+/// platform
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialRotation {
+    /// Model definition calling the key-rotation endpoint, templated with the current secret
+    pub connection_model_definition_id: Id,
+    /// Secret fields replaced by the rotation, mapped to the JSON pointer of their new value in
+    /// the response of the endpoint
+    pub credentials: BTreeMap<String, String>,
+}
+
+impl CredentialRotation {
+    /// Secret holding the rotated credentials found in `response`, fields that aren't rotated
+    /// being kept from `secret`
+    pub fn rotate(&self, secret: &Value, response: &Value) -> Result<Value, IntegrationOSError> {
+        let Value::Object(mut rotated) = secret.clone() else {
+            return Err(InternalError::invalid_argument(
+                "Only object secrets can be rotated",
+                None,
+            ));
+        };
+
+        for (field, pointer) in &self.credentials {
+            let value = response.pointer(pointer).ok_or_else(|| {
+                InternalError::invalid_argument(
+                    &format!("Rotated {field} not found at {pointer} in the response"),
+                    None,
+                )
+            })?;
+            rotated.insert(field.clone(), value.clone());
+        }
+
+        Ok(Value::Object(rotated))
+    }
+}
+
+fn merge(base: &mut Value, overrides: &Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(key) {
+                    Some(inherited) => merge(inherited, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides.clone(),
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -189,6 +390,21 @@ pub struct ConnectionForm {
     pub name: String,
     pub description: String,
     pub form_data: Vec<FormDataItem>,
+    /// Labels of the form by locale, see [`ConnectionDefinition::localize`]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub localized: BTreeMap<String, LocalizedConnectionForm>,
+}
+
+/// Labels of a connection form in a locale, the missing ones falling back to the default ones
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct LocalizedConnectionForm {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -199,6 +415,22 @@ pub struct FormDataItem {
     pub r#type: String,
     pub label: String,
     pub placeholder: String,
+    /// Labels of the field by locale, see [`ConnectionDefinition::localize`]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub localized: BTreeMap<String, LocalizedFormDataItem>,
+}
+
+/// Labels of a field of a connection form in a locale, the missing ones falling back to the
+/// default ones
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct LocalizedFormDataItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<String>,
 }
 
 #[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -213,3 +445,150 @@ pub struct Paths {
     pub signature: Option<String>,
     pub cursor: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_derived_definition_inherits_base_and_applies_overrides() {
+        let mut base = ConnectionDefinition::new(
+            "Shopify".to_string(),
+            "Connect to Shopify".to_string(),
+            "shopify".to_string(),
+            "2024-01".to_string(),
+            "Commerce".to_string(),
+            "https://example.com/shopify.png".to_string(),
+            vec!["commerce".to_string()],
+        );
+        base.default_query_params = BTreeMap::from([("limit".to_string(), "50".to_string())]);
+
+        let id = Id::now(IdPrefix::ConnectionDefinition);
+        let derived = base
+            .derive(
+                id,
+                &json!({
+                    "name": "Shopify Plus",
+                    "platformVersion": "2024-04",
+                    "frontend": { "spec": { "title": "Shopify Plus" } },
+                    "settings": { "oauth": true },
+                    "hidden": false,
+                }),
+            )
+            .expect("Failed to derive definition");
+
+        assert_eq!(derived.id, id);
+        assert_eq!(derived.template_id, Some(base.id));
+        assert_eq!(derived.key, "api::shopify::2024-04");
+
+        // Overridden fields, nested ones included
+        assert_eq!(derived.name, "Shopify Plus");
+        assert_eq!(derived.platform_version, "2024-04");
+        assert_eq!(derived.frontend.spec.title, "Shopify Plus");
+        assert!(derived.settings.oauth);
+        assert!(!derived.hidden);
+
+        // Inherited fields, including the siblings of overridden nested fields
+        assert_eq!(derived.platform, base.platform);
+        assert_eq!(derived.frontend.spec.description, "Connect to Shopify");
+        assert_eq!(derived.frontend.spec.tags, vec!["commerce".to_string()]);
+        assert_eq!(
+            derived.frontend.connection_form,
+            base.frontend.connection_form
+        );
+        assert_eq!(derived.settings.show_secret, base.settings.show_secret);
+        assert_eq!(derived.default_query_params, base.default_query_params);
+
+        assert!(base.derive(id, &json!({ "name": 42 })).is_err());
+    }
+
+    #[test]
+    fn test_rotation_replaces_only_rotated_credentials() {
+        let rotation = CredentialRotation {
+            connection_model_definition_id: Id::now(IdPrefix::ConnectionModelDefinition),
+            credentials: BTreeMap::from([("API_KEY".to_string(), "/data/key".to_string())]),
+        };
+        let secret = json!({ "API_KEY": "old", "ACCOUNT_ID": "account" });
+
+        let rotated = rotation
+            .rotate(&secret, &json!({ "data": { "key": "new" } }))
+            .expect("Failed to rotate");
+        assert_eq!(
+            rotated,
+            json!({ "API_KEY": "new", "ACCOUNT_ID": "account" })
+        );
+
+        assert!(rotation.rotate(&secret, &json!({ "key": "new" })).is_err());
+        assert!(rotation
+            .rotate(&json!("old"), &json!({ "data": { "key": "new" } }))
+            .is_err());
+    }
+
+    #[test]
+    fn test_labels_are_localized_in_the_preferred_locale() {
+        let mut definition = ConnectionDefinition::new(
+            "Shopify".to_string(),
+            "Connect to Shopify".to_string(),
+            "shopify".to_string(),
+            "2024-01".to_string(),
+            "Commerce".to_string(),
+            "https://example.com/shopify.png".to_string(),
+            vec![],
+        );
+        definition.frontend.connection_form.localized = BTreeMap::from([(
+            "fr".to_string(),
+            LocalizedConnectionForm {
+                name: None,
+                description: Some("Connectez votre compte".to_string()),
+            },
+        )]);
+        definition.frontend.connection_form.form_data = vec![FormDataItem {
+            name: "API_KEY".to_string(),
+            r#type: "text".to_string(),
+            label: "API key".to_string(),
+            placeholder: "Your API key".to_string(),
+            localized: BTreeMap::from([
+                (
+                    "fr".to_string(),
+                    LocalizedFormDataItem {
+                        label: Some("Clé d'API".to_string()),
+                        placeholder: None,
+                    },
+                ),
+                (
+                    "de-DE".to_string(),
+                    LocalizedFormDataItem {
+                        label: Some("API-Schlüssel".to_string()),
+                        placeholder: Some("Ihr API-Schlüssel".to_string()),
+                    },
+                ),
+            ]),
+        }];
+
+        let localized = |locales: &[&str]| {
+            let mut definition = definition.clone();
+            definition.localize(&locales.iter().map(ToString::to_string).collect::<Vec<_>>());
+            definition.frontend.connection_form
+        };
+
+        // The primary language matches, labels missing in the locale keep the default
+        let form = localized(&["fr-CA", "de-DE"]);
+        assert_eq!(form.name, "Shopify");
+        assert_eq!(form.description, "Connectez votre compte");
+        assert_eq!(form.form_data[0].label, "Clé d'API");
+        assert_eq!(form.form_data[0].placeholder, "Your API key");
+        assert!(form.localized.is_empty());
+        assert!(form.form_data[0].localized.is_empty());
+
+        // Each label is served in the first locale it is localized in
+        let form = localized(&["de-de", "fr"]);
+        assert_eq!(form.description, "Connectez votre compte");
+        assert_eq!(form.form_data[0].label, "API-Schlüssel");
+
+        let form = localized(&["es"]);
+        assert_eq!(form.description, "Connect to Shopify");
+        assert_eq!(form.form_data[0].label, "API key");
+        assert_eq!(form.form_data[0].placeholder, "Your API key");
+    }
+}
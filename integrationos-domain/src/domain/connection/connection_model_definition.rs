@@ -52,6 +52,132 @@ pub struct ConnectionModelDefinition {
 
     #[serde(default)]
     pub supported: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub shadow: Option<ShadowConfig>,
+
+    /// Request bodies are streamed to the platform instead of being buffered in memory
+    #[serde(default)]
+    pub streaming: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub timeout: Option<TimeoutConfig>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub error_format: Option<ErrorFormat>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub pagination_limits: Option<PaginationLimits>,
+}
+
+/// Mirrors a sampled fraction of the traffic of a definition to an alternate definition, so the
+/// responses of both can be compared without affecting the caller.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowConfig {
+    pub connection_model_definition_id: Id,
+    /// Fraction of requests, between 0 and 1, that are also sent to the shadow definition
+    pub sample_rate: f64,
+}
+
+impl ShadowConfig {
+    pub fn should_sample(&self) -> bool {
+        self.sample_rate > 0.0 && rand::random::<f64>() < self.sample_rate
+    }
+}
+
+/// Bounds how long a unified call waits for the platform, and what it answers once that time
+/// has elapsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct TimeoutConfig {
+    pub timeout_millis: u64,
+    #[serde(default)]
+    pub fallback: TimeoutFallback,
+}
+
+/// Caps on the lists of records read from the platform, whatever clients ask for. The page size
+/// clients request is clamped to `max_page_size`, and no next cursor is returned once a client
+/// has paged through `max_records` records from its first page.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct PaginationLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_page_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_records: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "kebab-case")]
+pub enum TimeoutFallback {
+    /// Fails the call
+    #[default]
+    Error,
+    /// Answers with an empty result
+    Empty,
+    /// Answers with the last successful response to the same request, failing the call when
+    /// there is none
+    LastCached,
+}
+
+/// Where the platform puts the details of its errors. Paths are JSONPath expressions over
+/// `{"body": <error body>}`, like the response paths of [`ApiModelConfig`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorFormat {
+    pub code_path: Option<String>,
+    pub message_path: Option<String>,
+}
+
+/// Error of a platform in the shape shared by every platform
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownstreamError {
+    pub status: u16,
+    pub code: Option<String>,
+    pub message: Option<String>,
+    /// Error body as returned by the platform
+    pub raw: Value,
+}
+
+impl ErrorFormat {
+    /// Extracts the code and message of an error body. Missing fields, and paths that fail to
+    /// select anything, are left empty rather than failing, since the error body is all the
+    /// caller would get otherwise.
+    pub fn normalize(&self, status: u16, body: Value) -> DownstreamError {
+        let wrapped_body = json!({ "body": body });
+        let select = |path: &Option<String>| {
+            let path = path.as_deref()?;
+            match jsonpath_lib::select(&wrapped_body, path).ok()?.first()? {
+                Value::Null => None,
+                Value::String(value) => Some(value.clone()),
+                value => Some(value.to_string()),
+            }
+        };
+
+        let code = select(&self.code_path);
+        let message = select(&self.message_path);
+        let Value::Object(mut wrapped_body) = wrapped_body else {
+            unreachable!("The wrapped body is an object")
+        };
+
+        DownstreamError {
+            status,
+            code,
+            message,
+            raw: wrapped_body.remove("body").unwrap_or_default(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize)]
@@ -338,6 +464,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_error_format_normalizes_error_body() {
+        let format = ErrorFormat {
+            code_path: Some("$.body.error.code".to_string()),
+            message_path: Some("$.body.error.message".to_string()),
+        };
+        let body = json!({ "error": { "code": 1001, "message": "Invalid email" } });
+
+        let error = format.normalize(422, body.clone());
+        assert_eq!(
+            error,
+            DownstreamError {
+                status: 422,
+                code: Some("1001".to_string()),
+                message: Some("Invalid email".to_string()),
+                raw: body,
+            }
+        );
+
+        let error = format.normalize(500, json!("Internal Server Error"));
+        assert_eq!(error.code, None);
+        assert_eq!(error.message, None);
+        assert_eq!(error.raw, json!("Internal Server Error"));
+    }
+
     #[test]
     fn test_deserialize_parameter_location() {
         let query_parameter = json!("QueryParameter");
@@ -1,6 +1,7 @@
 use crate::{
     id::{prefix::IdPrefix, Id},
     prelude::{schema::json_schema::JsonSchema, shared::record_metadata::RecordMetadata},
+    ApplicationError, IntegrationOSError,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -24,10 +25,29 @@ pub struct ConnectionModelSchema {
     pub paths: Option<SchemaPaths>,
     #[cfg_attr(feature = "dummy", dummy(default))]
     pub mapping: Option<Mappings>,
+    #[serde(default)]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub drift_policy: SchemaDriftPolicy,
     #[serde(flatten, default)]
     pub record_metadata: RecordMetadata,
 }
 
+/// How a platform response that no longer matches the schema is handled before it is mapped
+/// to the common model. Schemas stored without a policy keep passing extra fields through,
+/// strict checking has to be opted into
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub enum SchemaDriftPolicy {
+    /// Fails on fields missing from the schema and on missing required fields
+    Strict,
+    /// Drops fields missing from the schema and ignores missing required fields
+    Lenient,
+    /// Keeps fields missing from the schema as they are and ignores missing required fields
+    #[default]
+    PassthroughExtras,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 #[serde(rename_all = "camelCase")]
@@ -90,6 +110,8 @@ pub struct ConnectionModelSchemaBuilder {
     pub schema: JsonSchema,
     pub paths: Option<SchemaPaths>,
     pub mapping: Option<Mappings>,
+    #[serde(default)]
+    pub drift_policy: SchemaDriftPolicy,
 }
 
 impl ConnectionModelSchema {
@@ -113,7 +135,162 @@ impl ConnectionModelSchema {
             schema: input.schema,
             paths: input.paths,
             mapping: input.mapping,
+            drift_policy: input.drift_policy,
             record_metadata: RecordMetadata::default(),
         }
     }
+
+    /// Checks a platform response object against the schema according to the drift policy.
+    /// Schemas without properties accept any object.
+    pub fn normalize(&self, value: Value) -> Result<Value, IntegrationOSError> {
+        let Value::Object(mut object) = value else {
+            return Ok(value);
+        };
+
+        if self.schema.properties.is_empty() {
+            return Ok(Value::Object(object));
+        }
+
+        match self.drift_policy {
+            SchemaDriftPolicy::Strict => {
+                let mut unknown = object
+                    .keys()
+                    .filter(|key| !self.schema.properties.contains_key(*key))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                unknown.sort();
+
+                let missing = self
+                    .schema
+                    .required
+                    .iter()
+                    .flatten()
+                    .filter(|key| !object.contains_key(*key))
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                if !unknown.is_empty() || !missing.is_empty() {
+                    return Err(ApplicationError::unprocessable_entity(
+                        &format!(
+                            "Response does not match schema of {}. Unknown fields: [{}], missing fields: [{}]",
+                            self.model_name,
+                            unknown.join(", "),
+                            missing.join(", ")
+                        ),
+                        None,
+                    ));
+                }
+            }
+            SchemaDriftPolicy::Lenient => {
+                object.retain(|key, _| self.schema.properties.contains_key(key));
+            }
+            SchemaDriftPolicy::PassthroughExtras => {}
+        }
+
+        Ok(Value::Object(object))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_schema::Property;
+    use serde_json::json;
+
+    fn schema(drift_policy: SchemaDriftPolicy) -> ConnectionModelSchema {
+        let property = Property {
+            r#type: "string".to_string(),
+            path: None,
+            description: None,
+            properties: None,
+            items: None,
+            r#enum: None,
+        };
+        ConnectionModelSchema::new(ConnectionModelSchemaBuilder {
+            platform_id: Id::now(IdPrefix::Platform),
+            platform_page_id: Id::now(IdPrefix::PlatformPage),
+            connection_platform: "platform".to_string(),
+            connection_definition_id: Id::now(IdPrefix::ConnectionDefinition),
+            platform_version: "1.0".to_string(),
+            model_name: "Contact".to_string(),
+            sample: json!({}),
+            schema: JsonSchema {
+                type_name: "object".to_string(),
+                properties: [
+                    ("id".to_string(), property.clone()),
+                    ("name".to_string(), property),
+                ]
+                .into(),
+                required: Some(vec!["id".to_string()]),
+                path: None,
+                items: None,
+            },
+            paths: None,
+            mapping: None,
+            drift_policy,
+        })
+    }
+
+    #[test]
+    fn test_normalize_extra_fields() {
+        let response = json!({"id": "1", "name": "a", "email": "a@b.c"});
+
+        let err = schema(SchemaDriftPolicy::Strict)
+            .normalize(response.clone())
+            .expect_err("strict policy should reject unknown fields");
+        assert!(err.to_string().contains("email"));
+
+        let normalized = schema(SchemaDriftPolicy::Lenient)
+            .normalize(response.clone())
+            .expect("lenient policy should accept unknown fields");
+        assert_eq!(normalized, json!({"id": "1", "name": "a"}));
+
+        let normalized = schema(SchemaDriftPolicy::PassthroughExtras)
+            .normalize(response.clone())
+            .expect("passthrough policy should accept unknown fields");
+        assert_eq!(normalized, response);
+    }
+
+    #[test]
+    fn test_normalize_missing_fields() {
+        let response = json!({"name": "a"});
+
+        assert!(schema(SchemaDriftPolicy::Strict)
+            .normalize(response.clone())
+            .is_err());
+        assert_eq!(
+            schema(SchemaDriftPolicy::Lenient)
+                .normalize(response.clone())
+                .ok(),
+            Some(response.clone())
+        );
+        assert_eq!(
+            schema(SchemaDriftPolicy::PassthroughExtras)
+                .normalize(response.clone())
+                .ok(),
+            Some(response)
+        );
+    }
+
+    #[test]
+    fn test_drift_policy_defaults_to_passthrough_extras() {
+        let policy: SchemaDriftPolicy = serde_json::from_value(json!("strict")).unwrap();
+        assert_eq!(policy, SchemaDriftPolicy::Strict);
+        assert_eq!(
+            SchemaDriftPolicy::default(),
+            SchemaDriftPolicy::PassthroughExtras
+        );
+    }
+
+    #[test]
+    fn test_stored_schema_without_drift_policy_passes_extras_through() {
+        let mut stored = serde_json::to_value(schema(SchemaDriftPolicy::Strict)).unwrap();
+        stored.as_object_mut().unwrap().remove("driftPolicy");
+
+        let stored: ConnectionModelSchema = serde_json::from_value(stored).unwrap();
+        assert_eq!(stored.drift_policy, SchemaDriftPolicy::PassthroughExtras);
+
+        let response = json!({ "id": "1", "extra": true });
+        assert_eq!(stored.normalize(response.clone()).ok(), Some(response));
+    }
 }
@@ -5,10 +5,17 @@ use crate::{
     prelude::{ownership::Ownership, shared::record_metadata::RecordMetadata},
     Feature, Hook,
 };
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use bson::doc;
+use rand::{thread_rng, RngCore};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::ops::Not;
+use strum::{AsRefStr, Display};
+
+/// Random bytes of a code verifier, 43 characters once encoded
+const CODE_VERIFIER_LEN: usize = 32;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
@@ -24,10 +31,81 @@ pub struct ConnectionOAuthDefinition {
     pub is_full_template_enabled: bool,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub hooks: Option<Hook>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub pkce: Option<Pkce>,
     #[serde(flatten, default)]
     pub record_metadata: RecordMetadata,
 }
 
+impl ConnectionOAuthDefinition {
+    /// Method of the code challenge sent on the authorize step, none if the definition does not
+    /// use PKCE
+    pub fn code_challenge_method(&self) -> Option<CodeChallengeMethod> {
+        self.pkce
+            .as_ref()
+            .filter(|pkce| pkce.enabled)
+            .map(|pkce| pkce.code_challenge_method)
+    }
+}
+
+/// Proof Key for Code Exchange (RFC 7636) of the authorization code flow: a challenge is sent
+/// on the authorize step and its verifier on the token exchange, for providers requiring it
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct Pkce {
+    pub enabled: bool,
+    #[serde(default)]
+    pub code_challenge_method: CodeChallengeMethod,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Display, AsRefStr)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+pub enum CodeChallengeMethod {
+    #[default]
+    S256,
+    #[serde(rename = "plain")]
+    #[strum(serialize = "plain")]
+    Plain,
+}
+
+impl CodeChallengeMethod {
+    /// Challenge sent for `code_verifier` on the authorize step
+    pub fn challenge(&self, code_verifier: &str) -> String {
+        match self {
+            Self::S256 => URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes())),
+            Self::Plain => code_verifier.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PkceChallenge {
+    /// Kept until the token exchange, never sent on the authorize step
+    #[serde(skip_serializing)]
+    pub code_verifier: String,
+    pub code_challenge: String,
+    pub code_challenge_method: CodeChallengeMethod,
+}
+
+impl PkceChallenge {
+    /// Challenge of a new random verifier
+    pub fn new(method: CodeChallengeMethod) -> Self {
+        let mut bytes = [0_u8; CODE_VERIFIER_LEN];
+        thread_rng().fill_bytes(&mut bytes);
+        Self::from_verifier(URL_SAFE_NO_PAD.encode(bytes), method)
+    }
+
+    pub fn from_verifier(code_verifier: String, method: CodeChallengeMethod) -> Self {
+        Self {
+            code_challenge: method.challenge(&code_verifier),
+            code_verifier,
+            code_challenge_method: method,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 #[serde(rename_all = "camelCase")]
@@ -75,6 +153,19 @@ fn default_separator() -> Option<String> {
     Some(String::from(" "))
 }
 
+impl Frontend {
+    /// Scopes requested by the definition, split by its separator
+    pub fn scopes(&self) -> Vec<String> {
+        let separator = self.separator.as_deref().unwrap_or(" ");
+        self.scopes
+            .split(separator)
+            .map(str::trim)
+            .filter(|scope| !scope.is_empty())
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+}
+
 #[derive(Deserialize, Debug, Clone, Serialize)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 #[serde(rename_all = "camelCase")]
@@ -161,3 +252,90 @@ pub struct PlatformSecret {
     pub client_id: String,
     pub client_secret: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prefix::IdPrefix, OAuth};
+
+    fn frontend(scopes: &str) -> Frontend {
+        Frontend {
+            platform_redirect_uri: "https://example.com/oauth".to_string(),
+            sandbox_platform_redirect_uri: None,
+            scopes: scopes.to_string(),
+            ios_redirect_uri: "https://example.com/ios".to_string(),
+            separator: Some(",".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_s256_challenge_is_the_unpadded_base64url_sha256_of_the_verifier() {
+        let challenge = PkceChallenge::from_verifier(
+            "dBjftJeZ4CVP-mJ0y9sAuJ3bBLEgXR8JR1QY9W2p9Ns".to_string(),
+            CodeChallengeMethod::default(),
+        );
+        assert_eq!(challenge.code_challenge_method, CodeChallengeMethod::S256);
+        assert_eq!(
+            challenge.code_challenge,
+            "28SDv1LqUX6lpppfB3RgZ1-Yvrb_a3_pn9JplpGJb1E"
+        );
+
+        let challenge = PkceChallenge::new(CodeChallengeMethod::S256);
+        assert_eq!(challenge.code_verifier.len(), 43);
+        assert_eq!(
+            URL_SAFE_NO_PAD
+                .decode(&challenge.code_challenge)
+                .unwrap()
+                .as_slice(),
+            Sha256::digest(challenge.code_verifier.as_bytes()).as_slice()
+        );
+        assert!(!challenge.code_challenge.contains(['=', '+', '/']));
+
+        let plain =
+            PkceChallenge::from_verifier("verifier".to_string(), CodeChallengeMethod::Plain);
+        assert_eq!(plain.code_challenge, "verifier");
+    }
+
+    #[test]
+    fn test_pkce_defaults_to_s256_once_enabled() {
+        let pkce: Pkce = serde_json::from_value(serde_json::json!({ "enabled": true })).unwrap();
+        assert_eq!(pkce.code_challenge_method, CodeChallengeMethod::S256);
+
+        let pkce: Pkce = serde_json::from_value(
+            serde_json::json!({ "enabled": true, "codeChallengeMethod": "plain" }),
+        )
+        .unwrap();
+        assert_eq!(pkce.code_challenge_method, CodeChallengeMethod::Plain);
+        assert_eq!(
+            serde_json::to_value(CodeChallengeMethod::S256).unwrap(),
+            serde_json::json!("S256")
+        );
+    }
+
+    #[test]
+    fn test_connection_missing_newly_required_scope_is_flagged() {
+        let oauth = OAuth::Enabled {
+            connection_oauth_definition_id: Id::now(IdPrefix::ConnectionOAuthDefinition),
+            expires_in: None,
+            expires_at: None,
+            scopes: Some(frontend("read, write").scopes()),
+        };
+        assert!(oauth.missing_scopes(&frontend("read,write")).is_empty());
+
+        // The provider now requires a scope the connection wasn't authorized with
+        assert_eq!(
+            oauth.missing_scopes(&frontend("read,write,admin")),
+            vec!["admin".to_string()]
+        );
+
+        // Scopes of connections authorized before they were recorded are unknown
+        let legacy = OAuth::Enabled {
+            connection_oauth_definition_id: Id::now(IdPrefix::ConnectionOAuthDefinition),
+            expires_in: None,
+            expires_at: None,
+            scopes: None,
+        };
+        assert!(legacy.missing_scopes(&frontend("read,admin")).is_empty());
+        assert!(OAuth::Disabled.missing_scopes(&frontend("read")).is_empty());
+    }
+}
@@ -0,0 +1,68 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Expiry of the credentials of a connection that aren't refreshed automatically, such as API
+/// keys, so that their owner can be warned before calls start failing
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialExpiry {
+    /// Unix timestamp, in milliseconds, at which the credentials expire
+    pub expires_at: Option<i64>,
+    /// Unix timestamp, in milliseconds, at which the upcoming expiry was notified
+    pub notified_at: Option<i64>,
+}
+
+impl CredentialExpiry {
+    pub fn new(expires_at: Option<i64>) -> Self {
+        Self {
+            expires_at,
+            notified_at: None,
+        }
+    }
+
+    /// Whether the credentials expire within `window` of `now` and that wasn't notified yet
+    pub fn needs_notification(&self, now: DateTime<Utc>, window: Duration) -> bool {
+        match (self.expires_at, self.notified_at) {
+            (Some(expires_at), None) => expires_at <= (now + window).timestamp_millis(),
+            _ => false,
+        }
+    }
+
+    /// Marks the upcoming expiry as notified if it needs to be, and returns whether it did
+    pub fn notify(&mut self, now: DateTime<Utc>, window: Duration) -> bool {
+        let notify = self.needs_notification(now, window);
+        if notify {
+            self.notified_at = Some(now.timestamp_millis());
+        }
+        notify
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_expiry_is_notified_once_within_window() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let window = Duration::days(7);
+        let mut expiry = CredentialExpiry::new(Some((now + Duration::days(10)).timestamp_millis()));
+
+        assert!(!expiry.notify(now, window));
+        assert!(expiry.notify(now + Duration::days(3), window));
+        assert!(!expiry.notify(now + Duration::days(4), window));
+        assert!(!expiry.notify(now + Duration::days(11), window));
+        assert_eq!(
+            expiry.notified_at,
+            Some((now + Duration::days(3)).timestamp_millis())
+        );
+
+        // Renewed credentials are notified again once their own expiry gets close
+        let mut expiry = CredentialExpiry::new(Some((now + Duration::days(40)).timestamp_millis()));
+        assert!(!expiry.notify(now + Duration::days(4), window));
+        assert!(expiry.notify(now + Duration::days(35), window));
+
+        assert!(!CredentialExpiry::default().notify(now, window));
+    }
+}
@@ -0,0 +1,102 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use strum::{AsRefStr, Display};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Display, AsRefStr)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum HealthStatus {
+    #[default]
+    Healthy,
+    Unhealthy,
+}
+
+/// Number of consecutive check results needed before the health status of a connection
+/// changes, so that a single transient failure does not flip it to unhealthy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthThresholds {
+    pub failures: u32,
+    pub successes: u32,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            failures: 3,
+            successes: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionHealth {
+    pub status: HealthStatus,
+    pub consecutive_failures: u32,
+    pub consecutive_successes: u32,
+    pub last_checked_at: Option<i64>,
+}
+
+impl ConnectionHealth {
+    /// Records the result of a health check and returns whether the status changed
+    pub fn record(&mut self, healthy: bool, thresholds: &HealthThresholds) -> bool {
+        self.last_checked_at = Some(Utc::now().timestamp_millis());
+
+        if healthy {
+            self.consecutive_failures = 0;
+            self.consecutive_successes = self.consecutive_successes.saturating_add(1);
+        } else {
+            self.consecutive_successes = 0;
+            self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        }
+
+        let status = match self.status {
+            HealthStatus::Healthy if self.consecutive_failures >= thresholds.failures.max(1) => {
+                HealthStatus::Unhealthy
+            }
+            HealthStatus::Unhealthy
+                if self.consecutive_successes >= thresholds.successes.max(1) =>
+            {
+                HealthStatus::Healthy
+            }
+            status => status,
+        };
+
+        let changed = status != self.status;
+        self.status = status;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_changes_after_consecutive_results() {
+        let thresholds = HealthThresholds {
+            failures: 3,
+            successes: 2,
+        };
+        let mut health = ConnectionHealth::default();
+
+        assert!(!health.record(false, &thresholds));
+        assert!(!health.record(false, &thresholds));
+        assert!(!health.record(true, &thresholds));
+        assert_eq!(health.status, HealthStatus::Healthy);
+
+        assert!(!health.record(false, &thresholds));
+        assert!(!health.record(false, &thresholds));
+        assert!(health.record(false, &thresholds));
+        assert_eq!(health.status, HealthStatus::Unhealthy);
+
+        assert!(!health.record(true, &thresholds));
+        assert!(!health.record(false, &thresholds));
+        assert!(!health.record(true, &thresholds));
+        assert_eq!(health.status, HealthStatus::Unhealthy);
+
+        assert!(health.record(true, &thresholds));
+        assert_eq!(health.status, HealthStatus::Healthy);
+        assert_eq!(health.consecutive_failures, 0);
+    }
+}
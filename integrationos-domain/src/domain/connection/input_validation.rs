@@ -0,0 +1,183 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use strum::AsRefStr;
+
+/// Rule the value of a connection input must satisfy. Inputs missing from the form data are
+/// not checked, their presence is up to the platform.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct InputValidationRule {
+    /// Name of the input, as in the form data of the connection definition
+    pub input: String,
+    #[serde(flatten)]
+    pub constraint: InputConstraint,
+    /// Message of the violation, a generic one describing the constraint is used otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, AsRefStr)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(tag = "rule", rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum InputConstraint {
+    /// The whole value must match `pattern`
+    Regex {
+        pattern: String,
+    },
+    Enum {
+        values: Vec<String>,
+    },
+    /// The value must be a number within the bounds, both included
+    Range {
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputViolation {
+    pub input: String,
+    pub rule: String,
+    pub message: String,
+}
+
+impl InputValidationRule {
+    /// Violation of the rule by `value`, if any
+    pub fn check(&self, value: &str) -> Option<InputViolation> {
+        let violated = match &self.constraint {
+            InputConstraint::Regex { pattern } => match Regex::new(&format!("^(?:{pattern})$")) {
+                Ok(regex) => !regex.is_match(value),
+                Err(_) => {
+                    return Some(self.violation(format!(
+                        "{} can't be validated, its pattern {pattern} is invalid",
+                        self.input
+                    )))
+                }
+            },
+            InputConstraint::Enum { values } => !values.iter().any(|allowed| allowed == value),
+            InputConstraint::Range { min, max } => match value.trim().parse::<f64>() {
+                Ok(number) => {
+                    min.is_some_and(|min| number < min) || max.is_some_and(|max| number > max)
+                }
+                Err(_) => true,
+            },
+        };
+
+        violated.then(|| {
+            let message = self
+                .message
+                .clone()
+                .unwrap_or_else(|| self.default_message());
+            self.violation(message)
+        })
+    }
+
+    fn violation(&self, message: String) -> InputViolation {
+        InputViolation {
+            input: self.input.clone(),
+            rule: self.constraint.as_ref().to_owned(),
+            message,
+        }
+    }
+
+    fn default_message(&self) -> String {
+        match &self.constraint {
+            InputConstraint::Regex { pattern } => {
+                format!("{} must match the pattern {pattern}", self.input)
+            }
+            InputConstraint::Enum { values } => {
+                format!("{} must be one of {}", self.input, values.join(", "))
+            }
+            InputConstraint::Range { min, max } => match (min, max) {
+                (Some(min), Some(max)) => {
+                    format!("{} must be a number between {min} and {max}", self.input)
+                }
+                (Some(min), None) => format!("{} must be a number of at least {min}", self.input),
+                (None, Some(max)) => format!("{} must be a number of at most {max}", self.input),
+                (None, None) => format!("{} must be a number", self.input),
+            },
+        }
+    }
+}
+
+/// Violations of `rules` by the auth form data of a connection, in the order of the rules
+pub fn validate_inputs(
+    rules: &[InputValidationRule],
+    inputs: &HashMap<String, String>,
+) -> Vec<InputViolation> {
+    rules
+        .iter()
+        .filter_map(|rule| rule.check(inputs.get(&rule.input)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rules() -> Vec<InputValidationRule> {
+        serde_json::from_value(json!([
+            { "input": "BASE_URL", "rule": "regex", "pattern": "https://.+" },
+            { "input": "REGION", "rule": "enum", "values": ["us", "eu"] },
+            {
+                "input": "PORT",
+                "rule": "range",
+                "min": 1,
+                "max": 65535,
+                "message": "PORT must be a valid port"
+            }
+        ]))
+        .expect("Failed to deserialize rules")
+    }
+
+    #[test]
+    fn test_inputs_satisfying_rules_have_no_violations() {
+        let inputs = HashMap::from([
+            (
+                "BASE_URL".to_string(),
+                "https://api.example.com".to_string(),
+            ),
+            ("REGION".to_string(), "eu".to_string()),
+            ("PORT".to_string(), "443".to_string()),
+        ]);
+        assert!(validate_inputs(&rules(), &inputs).is_empty());
+
+        // Inputs without a value aren't checked
+        assert!(validate_inputs(&rules(), &HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_violations_of_rules_are_reported() {
+        let inputs = HashMap::from([
+            ("BASE_URL".to_string(), "http://api.example.com".to_string()),
+            ("REGION".to_string(), "ap".to_string()),
+            ("PORT".to_string(), "70000".to_string()),
+        ]);
+
+        assert_eq!(
+            validate_inputs(&rules(), &inputs),
+            vec![
+                InputViolation {
+                    input: "BASE_URL".to_string(),
+                    rule: "regex".to_string(),
+                    message: "BASE_URL must match the pattern https://.+".to_string(),
+                },
+                InputViolation {
+                    input: "REGION".to_string(),
+                    rule: "enum".to_string(),
+                    message: "REGION must be one of us, eu".to_string(),
+                },
+                InputViolation {
+                    input: "PORT".to_string(),
+                    rule: "range".to_string(),
+                    message: "PORT must be a valid port".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -3,7 +3,14 @@ pub mod connection_definition;
 pub mod connection_model_definition;
 pub mod connection_model_schema;
 pub mod connection_oauth_definition;
+pub mod expiry;
+pub mod health;
+pub mod input_validation;
+pub mod webhook;
 
+use self::{
+    connection_oauth_definition::Frontend, expiry::CredentialExpiry, health::ConnectionHealth,
+};
 use super::{
     configuration::environment::Environment,
     shared::{ownership::Ownership, record_metadata::RecordMetadata, settings::Settings},
@@ -31,6 +38,8 @@ pub struct Connection {
     pub key: Arc<str>,
     pub group: String,
     pub environment: Environment,
+    #[serde(default)]
+    pub mode: ConnectionMode,
     pub platform: Arc<str>,
     pub secrets_service_id: String,
     pub event_access_id: Id,
@@ -40,6 +49,18 @@ pub struct Connection {
     pub ownership: Ownership,
     #[serde(default)]
     pub oauth: Option<OAuth>,
+    #[serde(default)]
+    pub health: ConnectionHealth,
+    /// Id of the secret holding the key inbound webhooks of the connection are signed with.
+    /// Webhooks are only accepted for connections that have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_secret: Option<String>,
+    #[serde(default)]
+    pub credential_expiry: CredentialExpiry,
+    /// Unix timestamp, in milliseconds, at which the credentials were last rotated through the
+    /// platform
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials_rotated_at: Option<i64>,
     #[serde(flatten, default)]
     pub record_metadata: RecordMetadata,
 }
@@ -56,6 +77,8 @@ pub struct SanitizedConnection {
     pub key: Arc<str>,
     pub group: String,
     pub environment: Environment,
+    #[serde(default)]
+    pub mode: ConnectionMode,
     pub platform: Arc<str>,
     pub secrets_service_id: String,
     pub event_access_id: Id,
@@ -64,6 +87,8 @@ pub struct SanitizedConnection {
     pub ownership: Ownership,
     #[serde(default)]
     pub oauth: Option<OAuth>,
+    #[serde(default)]
+    pub health: ConnectionHealth,
     #[serde(flatten, default)]
     pub record_metadata: RecordMetadata,
 }
@@ -88,6 +113,30 @@ impl PartialEq for Connection {
 
 impl Eq for Connection {}
 
+/// Whether a connection is used for testing or for production traffic, so that events and
+/// metrics of sandbox connections can be kept out of production analytics
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Display,
+    AsRefStr,
+    EnumString,
+)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ConnectionMode {
+    Sandbox,
+    #[default]
+    Production,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, AsRefStr, Default)]
 #[serde(rename_all = "camelCase")]
 #[strum(serialize_all = "camelCase")]
@@ -97,11 +146,34 @@ pub enum OAuth {
         expires_in: Option<i32>,
         #[serde(default)]
         expires_at: Option<i64>,
+        /// Scopes the connection was authorized with, unknown for the connections authorized
+        /// before they were recorded
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        scopes: Option<Vec<String>>,
     },
     #[default]
     Disabled,
 }
 
+impl OAuth {
+    /// Scopes required by `frontend`, the frontend of the current OAuth definition of the
+    /// connection, that the connection wasn't authorized with. Such connections need to be
+    /// authorized again to get the permissions of the missing scopes.
+    pub fn missing_scopes(&self, frontend: &Frontend) -> Vec<String> {
+        match self {
+            OAuth::Enabled {
+                scopes: Some(granted),
+                ..
+            } => frontend
+                .scopes()
+                .into_iter()
+                .filter(|scope| !granted.contains(scope))
+                .collect(),
+            OAuth::Enabled { scopes: None, .. } | OAuth::Disabled => vec![],
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Display, AsRefStr)]
 #[serde(rename_all = "lowercase")]
 #[strum(serialize_all = "lowercase")]
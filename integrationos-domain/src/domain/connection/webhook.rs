@@ -0,0 +1,70 @@
+use crate::{ApplicationError, IntegrationOSError};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+const SIGNATURE_PREFIX: &str = "sha256=";
+
+/// Hex encoded HMAC-SHA256 of a webhook body signed with the connection webhook secret
+pub fn sign_webhook(secret: &str, payload: &[u8]) -> Result<String, IntegrationOSError> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(|e| {
+        ApplicationError::bad_request(&format!("Invalid webhook secret: {e}"), None)
+    })?;
+    mac.update(payload);
+
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Checks that `signature` is the HMAC-SHA256 of the raw webhook body signed with `secret`.
+/// The signature is hex encoded and may be prefixed with `sha256=`. The comparison runs in
+/// constant time.
+pub fn verify_webhook_signature(
+    secret: &str,
+    payload: &[u8],
+    signature: &str,
+) -> Result<(), IntegrationOSError> {
+    let signature = signature.trim();
+    let signature = signature
+        .strip_prefix(SIGNATURE_PREFIX)
+        .unwrap_or(signature);
+
+    let signature = hex::decode(signature)
+        .map_err(|_| ApplicationError::unauthorized("Malformed webhook signature", None))?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(|e| {
+        ApplicationError::bad_request(&format!("Invalid webhook secret: {e}"), None)
+    })?;
+    mac.update(payload);
+
+    mac.verify_slice(&signature)
+        .map_err(|_| ApplicationError::unauthorized("Invalid webhook signature", None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::StatusCode;
+
+    const SECRET: &str = "whsec_5f1c2a8e";
+    const PAYLOAD: &[u8] = br#"{"type":"invoice.paid","data":{"amount":4200}}"#;
+
+    #[test]
+    fn test_signed_webhook_is_accepted_and_tampered_one_rejected() {
+        let signature = sign_webhook(SECRET, PAYLOAD).expect("Failed to sign webhook");
+
+        assert!(verify_webhook_signature(SECRET, PAYLOAD, &signature).is_ok());
+        assert!(verify_webhook_signature(SECRET, PAYLOAD, &format!("sha256={signature}")).is_ok());
+
+        let tampered = br#"{"type":"invoice.paid","data":{"amount":9900}}"#;
+        let err = verify_webhook_signature(SECRET, tampered, &signature)
+            .expect_err("Tampered payload should be rejected");
+        assert_eq!(StatusCode::from(&err), StatusCode::UNAUTHORIZED);
+
+        let err = verify_webhook_signature("another-secret", PAYLOAD, &signature)
+            .expect_err("Signature from another secret should be rejected");
+        assert_eq!(StatusCode::from(&err), StatusCode::UNAUTHORIZED);
+
+        let err = verify_webhook_signature(SECRET, PAYLOAD, "not-hex")
+            .expect_err("Malformed signature should be rejected");
+        assert_eq!(StatusCode::from(&err), StatusCode::UNAUTHORIZED);
+    }
+}
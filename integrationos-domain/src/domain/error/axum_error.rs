@@ -13,10 +13,107 @@ impl IntoResponse for IntegrationOSError {
 
 impl<'a> IntoResponse for &'a IntegrationOSError {
     fn into_response(self) -> Response {
-        let body = self.to_owned().as_application().as_json();
+        self.response_for(None)
+    }
+}
 
+impl IntegrationOSError {
+    /// Response of the error, with the id of the request that failed in its body when set.
+    /// The error is kept in the extensions of the response so that layers wrapping the handler
+    /// can render it again, e.g. once they know the request id.
+    pub fn response_for(&self, request_id: Option<&str>) -> Response {
         let status: StatusCode = self.into();
 
-        (status, Json(body)).into_response()
+        let mut body = self.to_owned().as_application().as_json();
+        if let Some(body) = body.as_object_mut() {
+            // Internal errors are rendered as application ones, whose status may differ from
+            // the one the response is sent with, e.g. for timeouts
+            body.insert("status".to_string(), status.as_u16().into());
+            if let Some(request_id) = request_id {
+                body.insert("requestId".to_string(), request_id.into());
+            }
+        }
+
+        let mut response = (status, Json(body)).into_response();
+        response.extensions_mut().insert(self.to_owned());
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ApplicationError, IntegrationOSError, InternalError};
+    use http::StatusCode;
+    use serde_json::Value;
+
+    async fn rendered(error: IntegrationOSError, request_id: Option<&str>) -> (StatusCode, Value) {
+        let response = error.response_for(request_id);
+        assert_eq!(
+            response.extensions().get::<IntegrationOSError>(),
+            Some(&error)
+        );
+
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_errors_render_their_status_and_key() {
+        for (error, status, key) in [
+            (
+                ApplicationError::bad_request("Invalid payload", None),
+                StatusCode::BAD_REQUEST,
+                "err::application::bad_request",
+            ),
+            (
+                InternalError::invalid_argument("Invalid field", None),
+                StatusCode::BAD_REQUEST,
+                "err::application::bad_request",
+            ),
+            (
+                ApplicationError::not_found("Connection not found", None),
+                StatusCode::NOT_FOUND,
+                "err::application::not_found",
+            ),
+            (
+                ApplicationError::unauthorized("Invalid access key", None),
+                StatusCode::UNAUTHORIZED,
+                "err::application::unauthorized",
+            ),
+            (
+                ApplicationError::forbidden("Access denied", None),
+                StatusCode::FORBIDDEN,
+                "err::application::forbidden",
+            ),
+            (
+                InternalError::connection_error("Platform unreachable", None),
+                StatusCode::BAD_GATEWAY,
+                "err::application::internal_server_error",
+            ),
+            (
+                InternalError::timeout("Platform took too long", None),
+                StatusCode::GATEWAY_TIMEOUT,
+                "err::application::internal_server_error",
+            ),
+            (
+                InternalError::unknown("Something broke", None),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "err::application::internal_server_error",
+            ),
+        ] {
+            let (rendered_status, body) = rendered(error, Some("request")).await;
+
+            assert_eq!(rendered_status, status);
+            assert_eq!(body["status"], status.as_u16());
+            assert_eq!(body["key"], key);
+            assert_eq!(body["requestId"], "request");
+        }
+
+        let (_, body) = rendered(ApplicationError::not_found("Not found", None), None).await;
+        assert_eq!(body["message"], "Not found");
+        assert!(body.get("requestId").is_none());
     }
 }
@@ -540,6 +540,12 @@ pub enum ApplicationError {
         subtype: Option<String>,
         meta: Option<Value>,
     },
+    #[error("Payload Too Large: {}", .message)]
+    PayloadTooLarge {
+        message: String,
+        subtype: Option<String>,
+        meta: Option<Value>,
+    },
     #[error("Precondition Failed: {}", .message)]
     FailedDependency {
         message: String,
@@ -642,6 +648,14 @@ impl ApplicationError {
         })
     }
 
+    pub fn payload_too_large(message: &str, subtype: Option<&str>) -> IntegrationOSError {
+        IntegrationOSError::application(ApplicationError::PayloadTooLarge {
+            message: message.to_string(),
+            subtype: subtype.map(|s| s.to_string().snake_case()),
+            meta: None,
+        })
+    }
+
     pub fn failed_dependency(message: &str, subtype: Option<&str>) -> IntegrationOSError {
         IntegrationOSError::application(ApplicationError::FailedDependency {
             message: message.to_string(),
@@ -733,6 +747,13 @@ impl ApplicationError {
                 subtype: subtype.clone(),
                 meta: Some(meta),
             },
+            ApplicationError::PayloadTooLarge {
+                message, subtype, ..
+            } => ApplicationError::PayloadTooLarge {
+                message: message.clone(),
+                subtype: subtype.clone(),
+                meta: Some(meta),
+            },
             ApplicationError::FailedDependency {
                 message, subtype, ..
             } => ApplicationError::FailedDependency {
@@ -787,6 +808,7 @@ impl ErrorMeta for ApplicationError {
             ApplicationError::TooManyRequests { .. } => ErrorCode(2009),
             ApplicationError::Unauthorized { .. } => ErrorCode(2010),
             ApplicationError::UnprocessableEntity { .. } => ErrorCode(2011),
+            ApplicationError::PayloadTooLarge { .. } => ErrorCode(2012),
         }
     }
 
@@ -813,6 +835,9 @@ impl ErrorMeta for ApplicationError {
             ApplicationError::NotImplemented { subtype, .. } => {
                 ErrorKey::application("not_implemented", subtype.as_deref())
             }
+            ApplicationError::PayloadTooLarge { subtype, .. } => {
+                ErrorKey::application("payload_too_large", subtype.as_deref())
+            }
             ApplicationError::FailedDependency { subtype, .. } => {
                 ErrorKey::application("failed_dependency", subtype.as_deref())
             }
@@ -842,6 +867,7 @@ impl ErrorMeta for ApplicationError {
             ApplicationError::MethodNotAllowed { message, .. } => ErrorMessage(message.to_string()),
             ApplicationError::NotFound { message, .. } => ErrorMessage(message.to_string()),
             ApplicationError::NotImplemented { message, .. } => ErrorMessage(message.to_string()),
+            ApplicationError::PayloadTooLarge { message, .. } => ErrorMessage(message.to_string()),
             ApplicationError::FailedDependency { message, .. } => ErrorMessage(message.to_string()),
             ApplicationError::ServiceUnavailable { message, .. } => {
                 ErrorMessage(message.to_string())
@@ -863,6 +889,7 @@ impl ErrorMeta for ApplicationError {
             ApplicationError::MethodNotAllowed { meta, .. } => meta.clone(),
             ApplicationError::NotFound { meta, .. } => meta.clone(),
             ApplicationError::NotImplemented { meta, .. } => meta.clone(),
+            ApplicationError::PayloadTooLarge { meta, .. } => meta.clone(),
             ApplicationError::FailedDependency { meta, .. } => meta.clone(),
             ApplicationError::ServiceUnavailable { meta, .. } => meta.clone(),
             ApplicationError::TooManyRequests { meta, .. } => meta.clone(),
@@ -1006,6 +1033,7 @@ impl<'a> From<&'a IntegrationOSError> for StatusCode {
                 ApplicationError::MethodNotAllowed { .. } => StatusCode::METHOD_NOT_ALLOWED,
                 ApplicationError::NotFound { .. } => StatusCode::NOT_FOUND,
                 ApplicationError::NotImplemented { .. } => StatusCode::NOT_IMPLEMENTED,
+                ApplicationError::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
                 ApplicationError::FailedDependency { .. } => StatusCode::FAILED_DEPENDENCY,
                 ApplicationError::ServiceUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
                 ApplicationError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
@@ -1203,6 +1231,14 @@ impl IntegrationOSError {
                 })
             }
 
+            StatusCode::PAYLOAD_TOO_LARGE => {
+                IntegrationOSError::application(ApplicationError::PayloadTooLarge {
+                    message,
+                    subtype,
+                    meta,
+                })
+            }
+
             StatusCode::FAILED_DEPENDENCY => {
                 IntegrationOSError::application(ApplicationError::FailedDependency {
                     message,
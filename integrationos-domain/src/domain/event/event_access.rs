@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use strum::{AsRefStr, Display, EnumString};
 
 use crate::{
     id::Id,
@@ -28,6 +29,9 @@ pub struct EventAccess {
     #[serde(default = "throughput_default")]
     pub throughput: u64,
     pub environment: Environment,
+    /// What the access key of this event access is allowed to do
+    #[serde(default = "EventAccessScope::all")]
+    pub scopes: Vec<EventAccessScope>,
     #[serde(flatten, default)]
     pub record_metadata: RecordMetadata,
 }
@@ -35,3 +39,22 @@ pub struct EventAccess {
 fn throughput_default() -> u64 {
     500
 }
+
+#[derive(
+    Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize, Display, EnumString, AsRefStr,
+)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum EventAccessScope {
+    Read,
+    /// Emitting events through the gateway
+    Ingest,
+}
+
+impl EventAccessScope {
+    /// Scopes of the event accesses created before scopes existed
+    pub fn all() -> Vec<Self> {
+        vec![Self::Read, Self::Ingest]
+    }
+}
@@ -0,0 +1,437 @@
+use crate::{
+    algebra::MongoStore,
+    id::{prefix::IdPrefix, Id},
+    prelude::shared::{ownership::Ownership, record_metadata::RecordMetadata},
+    ApplicationError, Event, IntegrationOSError,
+};
+use bson::doc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use strum::{AsRefStr, Display};
+
+/// Version of the schema the payloads of the events of a type must satisfy. Each registration
+/// for a type adds a version, events are validated against the latest one. Schemas are
+/// registered per owner and only apply to the events of their owner.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventSchema {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub ownership: Ownership,
+    /// Name of the events the schema applies to
+    pub event_type: String,
+    /// Starts at 1 and grows by one with each registration
+    pub version: u32,
+    pub schema: PayloadSchema,
+    /// Policy the next version is checked against this one with
+    pub compatibility: SchemaCompatibility,
+    #[serde(flatten, default)]
+    pub record_metadata: RecordMetadata,
+}
+
+impl EventSchema {
+    /// Next version of `previous`, the first one if there is none
+    pub fn new(
+        ownership: Ownership,
+        event_type: String,
+        schema: PayloadSchema,
+        compatibility: SchemaCompatibility,
+        previous: Option<&EventSchema>,
+    ) -> Self {
+        Self {
+            id: Id::now(IdPrefix::EventSchema),
+            ownership,
+            event_type,
+            version: previous.map_or(1, |previous| previous.version + 1),
+            schema,
+            compatibility,
+            record_metadata: RecordMetadata::default(),
+        }
+    }
+
+    /// Rejects `event` if its payload does not satisfy the schema
+    pub fn validate_event(&self, event: &Event) -> Result<(), IntegrationOSError> {
+        let violations = match serde_json::from_str(&event.body) {
+            Ok(payload) => self.schema.validate(&payload),
+            Err(_) => vec![SchemaViolation {
+                path: String::new(),
+                message: "Payload is not valid JSON".to_string(),
+            }],
+        };
+        if violations.is_empty() {
+            return Ok(());
+        }
+
+        let violations = violations
+            .iter()
+            .map(|violation| format!("{}: {}", violation.path, violation.message))
+            .collect::<Vec<_>>();
+        Err(ApplicationError::unprocessable_entity(
+            &format!(
+                "Payload does not satisfy version {} of the schema of {}: {}",
+                self.version,
+                event.name,
+                violations.join(", ")
+            ),
+            None,
+        ))
+    }
+}
+
+/// Latest version of the schema registered by `ownership_id` for `event_type`
+pub async fn latest_event_schema(
+    store: &MongoStore<EventSchema>,
+    ownership_id: &str,
+    event_type: &str,
+) -> Result<Option<EventSchema>, IntegrationOSError> {
+    let latest = store
+        .get_many(
+            Some(doc! {
+                "ownership.buildableId": ownership_id,
+                "eventType": event_type,
+                "deleted": false,
+            }),
+            None,
+            Some(doc! { "version": -1 }),
+            Some(1),
+            None,
+        )
+        .await?;
+
+    Ok(latest.into_iter().next())
+}
+
+/// How a new version of a schema must relate to the previous one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Display, AsRefStr)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum SchemaCompatibility {
+    /// Any new version is accepted
+    None,
+    /// Payloads valid for the previous version are valid for the new one
+    #[default]
+    Backward,
+    /// Payloads valid for the new version are valid for the previous one
+    Forward,
+    /// Both backward and forward
+    Full,
+}
+
+impl SchemaCompatibility {
+    /// Reasons `new` can't follow `previous` under this policy, none if it can
+    pub fn check(&self, previous: &PayloadSchema, new: &PayloadSchema) -> Vec<String> {
+        let mut issues = vec![];
+        if matches!(self, Self::Backward | Self::Full) {
+            narrowings(previous, new, "", &mut issues);
+        }
+        if matches!(self, Self::Forward | Self::Full) {
+            narrowings(new, previous, "", &mut issues);
+        }
+        issues
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum PayloadType {
+    Object,
+    Array,
+    String,
+    Number,
+    Integer,
+    Boolean,
+    Null,
+}
+
+impl PayloadType {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Object(_) => Self::Object,
+            Value::Array(_) => Self::Array,
+            Value::String(_) => Self::String,
+            Value::Number(number) if number.is_i64() || number.is_u64() => Self::Integer,
+            Value::Number(_) => Self::Number,
+            Value::Bool(_) => Self::Boolean,
+            Value::Null => Self::Null,
+        }
+    }
+
+    /// Whether the values of `other` are values of this type
+    fn includes(&self, other: PayloadType) -> bool {
+        *self == other || (*self == Self::Number && other == Self::Integer)
+    }
+}
+
+/// Subset of JSON Schema payloads are validated with: `type`, `properties`, `required`,
+/// `additionalProperties`, `items` and `enum`. Other keywords are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayloadSchema {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<PayloadType>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, PayloadSchema>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required: Vec<String>,
+    /// Whether properties missing from `properties` are allowed, they are if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub additional_properties: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub items: Option<Box<PayloadSchema>>,
+    #[serde(rename = "enum", default, skip_serializing_if = "Option::is_none")]
+    pub r#enum: Option<Vec<Value>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaViolation {
+    /// JSON pointer to the offending value, empty for the payload itself
+    pub path: String,
+    pub message: String,
+}
+
+impl PayloadSchema {
+    /// Violations of the schema by `value`, in document order
+    pub fn validate(&self, value: &Value) -> Vec<SchemaViolation> {
+        let mut violations = vec![];
+        self.validate_at(value, "", &mut violations);
+        violations
+    }
+
+    fn validate_at(&self, value: &Value, path: &str, violations: &mut Vec<SchemaViolation>) {
+        let violation = |message: String| SchemaViolation {
+            path: path.to_owned(),
+            message,
+        };
+
+        if let Some(r#type) = self.r#type {
+            let actual = PayloadType::of(value);
+            if !r#type.includes(actual) {
+                violations.push(violation(format!("Expected {type}, found {actual}")));
+                return;
+            }
+        }
+        if let Some(allowed) = &self.r#enum {
+            if !allowed.contains(value) {
+                violations.push(violation(format!(
+                    "{value} is not one of the allowed values"
+                )));
+            }
+        }
+
+        match value {
+            Value::Object(object) => {
+                for property in &self.required {
+                    if !object.contains_key(property) {
+                        violations.push(violation(format!("Missing required property {property}")));
+                    }
+                }
+                for (key, value) in object {
+                    let path = format!("{path}/{}", escape_pointer(key));
+                    match self.properties.get(key) {
+                        Some(schema) => schema.validate_at(value, &path, violations),
+                        None if self.additional_properties == Some(false) => {
+                            violations.push(SchemaViolation {
+                                path,
+                                message: format!("Property {key} is not allowed"),
+                            });
+                        }
+                        None => {}
+                    }
+                }
+            }
+            Value::Array(values) => {
+                if let Some(items) = &self.items {
+                    for (index, value) in values.iter().enumerate() {
+                        items.validate_at(value, &format!("{path}/{index}"), violations);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Ways `narrower` rejects payloads `wider` accepts, as far as the keywords of
+/// [`PayloadSchema`] tell
+fn narrowings(
+    wider: &PayloadSchema,
+    narrower: &PayloadSchema,
+    path: &str,
+    issues: &mut Vec<String>,
+) {
+    let at = if path.is_empty() { "/" } else { path };
+
+    if let Some(r#type) = narrower.r#type {
+        match wider.r#type {
+            Some(wider_type) if r#type.includes(wider_type) => {}
+            Some(wider_type) => {
+                issues.push(format!("{at}: type changed from {wider_type} to {type}"))
+            }
+            None => issues.push(format!("{at}: type restricted to {type}")),
+        }
+    }
+
+    for property in &narrower.required {
+        if !wider.required.contains(property) {
+            issues.push(format!("{at}: property {property} became required"));
+        }
+    }
+
+    if let Some(allowed) = &narrower.r#enum {
+        match &wider.r#enum {
+            Some(wider_allowed) => {
+                for value in wider_allowed
+                    .iter()
+                    .filter(|value| !allowed.contains(value))
+                {
+                    issues.push(format!("{at}: value {value} is no longer allowed"));
+                }
+            }
+            None => issues.push(format!("{at}: values restricted to an enum")),
+        }
+    }
+
+    if narrower.additional_properties == Some(false) {
+        if wider.additional_properties != Some(false) {
+            issues.push(format!("{at}: additional properties are no longer allowed"));
+        }
+        for property in wider.properties.keys() {
+            if !narrower.properties.contains_key(property) {
+                issues.push(format!("{at}: property {property} was removed"));
+            }
+        }
+    }
+
+    for (property, narrower_property) in &narrower.properties {
+        if let Some(wider_property) = wider.properties.get(property) {
+            let path = format!("{path}/{}", escape_pointer(property));
+            narrowings(wider_property, narrower_property, &path, issues);
+        }
+    }
+
+    if let (Some(wider_items), Some(narrower_items)) = (&wider.items, &narrower.items) {
+        narrowings(
+            wider_items,
+            narrower_items,
+            &format!("{path}/items"),
+            issues,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema(value: Value) -> PayloadSchema {
+        serde_json::from_value(value).expect("Failed to deserialize schema")
+    }
+
+    fn invoice() -> PayloadSchema {
+        schema(json!({
+            "type": "object",
+            "required": ["id", "amount"],
+            "properties": {
+                "id": { "type": "string" },
+                "amount": { "type": "number" },
+                "status": { "enum": ["paid", "open"] },
+                "lines": {
+                    "type": "array",
+                    "items": { "type": "object", "required": ["sku"] }
+                }
+            }
+        }))
+    }
+
+    #[test]
+    fn test_valid_payload_has_no_violations() {
+        let payload = json!({
+            "id": "in_1",
+            "amount": 42,
+            "status": "paid",
+            "lines": [{ "sku": "a" }],
+            "extra": true
+        });
+        assert!(invoice().validate(&payload).is_empty());
+    }
+
+    #[test]
+    fn test_violations_point_to_the_offending_values() {
+        let payload = json!({
+            "amount": "42",
+            "status": "void",
+            "lines": [{ "sku": "a" }, {}]
+        });
+
+        assert_eq!(
+            invoice().validate(&payload),
+            vec![
+                SchemaViolation {
+                    path: "".to_string(),
+                    message: "Missing required property id".to_string(),
+                },
+                SchemaViolation {
+                    path: "/amount".to_string(),
+                    message: "Expected number, found string".to_string(),
+                },
+                SchemaViolation {
+                    path: "/status".to_string(),
+                    message: "\"void\" is not one of the allowed values".to_string(),
+                },
+                SchemaViolation {
+                    path: "/lines/1".to_string(),
+                    message: "Missing required property sku".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compatibility_of_new_versions() {
+        let previous = invoice();
+
+        // An optional property is backward and forward compatible
+        let mut optional = previous.clone();
+        optional
+            .properties
+            .insert("note".to_string(), schema(json!({ "type": "string" })));
+        assert!(SchemaCompatibility::Full
+            .check(&previous, &optional)
+            .is_empty());
+
+        // A new required property rejects payloads of the previous version
+        let mut required = optional.clone();
+        required.required.push("note".to_string());
+        assert_eq!(
+            SchemaCompatibility::Backward.check(&previous, &required),
+            vec!["/: property note became required".to_string()]
+        );
+        assert!(SchemaCompatibility::Forward
+            .check(&previous, &required)
+            .is_empty());
+        assert!(SchemaCompatibility::None
+            .check(&previous, &required)
+            .is_empty());
+
+        // Widening a type is backward compatible only
+        let mut narrowed = previous.clone();
+        narrowed
+            .properties
+            .insert("amount".to_string(), schema(json!({ "type": "integer" })));
+        assert!(SchemaCompatibility::Forward
+            .check(&previous, &narrowed)
+            .is_empty());
+        assert_eq!(
+            SchemaCompatibility::Backward.check(&previous, &narrowed),
+            vec!["/amount: type changed from number to integer".to_string()]
+        );
+    }
+}
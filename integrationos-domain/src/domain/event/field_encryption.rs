@@ -0,0 +1,158 @@
+use super::Event;
+use crate::{CryptoExt, IntegrationOSError, InternalError};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Key of the object replacing an encrypted field, holding the ciphertext of the field
+const ENCRYPTED_KEY: &str = "$encrypted";
+
+/// Encrypts fields of event bodies at rest. Fields are JSON pointers into the body, e.g.
+/// `/customer/email`, and are replaced by `{"$encrypted": <ciphertext>}`. Bodies that are not
+/// JSON, and fields missing from a body, are left as they are.
+#[derive(Clone)]
+pub struct FieldEncryption {
+    paths: Vec<String>,
+    crypto: Arc<dyn CryptoExt + Sync + Send>,
+}
+
+impl FieldEncryption {
+    pub fn new(paths: Vec<String>, crypto: Arc<dyn CryptoExt + Sync + Send>) -> Self {
+        Self { paths, crypto }
+    }
+
+    /// Parses comma separated JSON pointers, as found in configurations
+    pub fn parse_paths(paths: &str) -> Vec<String> {
+        paths
+            .split(',')
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(str::to_owned)
+            .collect()
+    }
+
+    pub async fn encrypt_event(&self, mut event: Event) -> Result<Event, IntegrationOSError> {
+        event.body = self.encrypt(&event.body).await?;
+        Ok(event)
+    }
+
+    pub async fn encrypt(&self, body: &str) -> Result<String, IntegrationOSError> {
+        let Ok(mut body_value) = serde_json::from_str::<Value>(body) else {
+            return Ok(body.to_owned());
+        };
+
+        let mut encrypted = false;
+        for path in &self.paths {
+            let Some(field) = body_value.pointer_mut(path) else {
+                continue;
+            };
+            if ciphertext(field).is_some() {
+                continue;
+            }
+
+            let plaintext = serde_json::to_string(field)
+                .map_err(|e| InternalError::serialize_error(&e.to_string(), None))?;
+            *field = json!({ ENCRYPTED_KEY: self.crypto.encrypt(plaintext).await? });
+            encrypted = true;
+        }
+
+        if !encrypted {
+            return Ok(body.to_owned());
+        }
+        serde_json::to_string(&body_value)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), None))
+    }
+
+    pub async fn decrypt(&self, body: &str) -> Result<String, IntegrationOSError> {
+        let Ok(mut body_value) = serde_json::from_str::<Value>(body) else {
+            return Ok(body.to_owned());
+        };
+
+        let mut decrypted = false;
+        for path in &self.paths {
+            let Some(field) = body_value.pointer_mut(path) else {
+                continue;
+            };
+            let Some(ciphertext) = ciphertext(field) else {
+                continue;
+            };
+
+            let plaintext = self.crypto.decrypt(ciphertext.to_owned(), None).await?;
+            *field = serde_json::from_str(&plaintext)
+                .map_err(|e| InternalError::deserialize_error(&e.to_string(), None))?;
+            decrypted = true;
+        }
+
+        if !decrypted {
+            return Ok(body.to_owned());
+        }
+        serde_json::to_string(&body_value)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), None))
+    }
+}
+
+fn ciphertext(field: &Value) -> Option<&str> {
+    match field {
+        Value::Object(object) if object.len() == 1 => object.get(ENCRYPTED_KEY)?.as_str(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{secrets::SecretsConfig, IOSCrypto};
+
+    fn encryption() -> FieldEncryption {
+        let crypto =
+            IOSCrypto::new(SecretsConfig::default()).expect("Failed to create IOSCrypto client");
+        FieldEncryption::new(
+            FieldEncryption::parse_paths("/customer/email, /card,"),
+            Arc::new(crypto),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_configured_fields_are_encrypted_and_decrypted() {
+        let encryption = encryption();
+        let body = json!({
+            "customer": { "email": "jane@example.com", "name": "Jane" },
+            "card": { "number": "4242424242424242" },
+            "amount": 100
+        })
+        .to_string();
+
+        let encrypted = encryption.encrypt(&body).await.expect("Failed to encrypt");
+        let stored: Value = serde_json::from_str(&encrypted).unwrap();
+        assert!(!encrypted.contains("jane@example.com"));
+        assert!(!encrypted.contains("4242424242424242"));
+        assert!(stored["customer"]["email"][ENCRYPTED_KEY].is_string());
+        assert!(stored["card"][ENCRYPTED_KEY].is_string());
+        assert_eq!(stored["customer"]["name"], json!("Jane"));
+        assert_eq!(stored["amount"], json!(100));
+
+        // Encrypting again does not encrypt the ciphertext
+        let reencrypted = encryption.encrypt(&encrypted).await.unwrap();
+        assert_eq!(reencrypted, encrypted);
+
+        let decrypted = encryption
+            .decrypt(&encrypted)
+            .await
+            .expect("Failed to decrypt");
+        assert_eq!(
+            serde_json::from_str::<Value>(&decrypted).unwrap(),
+            serde_json::from_str::<Value>(&body).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bodies_without_configured_fields_are_untouched() {
+        let encryption = encryption();
+
+        let body = "not json";
+        assert_eq!(encryption.encrypt(body).await.unwrap(), body);
+
+        let body = r#"{"customer":{"name":"Jane"}}"#;
+        assert_eq!(encryption.encrypt(body).await.unwrap(), body);
+        assert_eq!(encryption.decrypt(body).await.unwrap(), body);
+    }
+}
@@ -0,0 +1,158 @@
+use super::Event;
+use bson::{Bson, Document};
+use chrono::{DateTime, SecondsFormat};
+
+/// Version of the shape of newly created events. Bump it together with a new step in
+/// [`migrate_event_document`] whenever the shape of stored events changes.
+pub const CURRENT_EVENT_VERSION: u32 = 2;
+
+/// Events stored before versioning was introduced have no `schemaVersion` field
+pub(crate) fn legacy_event_version() -> u32 {
+    1
+}
+
+/// Upgrades a stored event document to [`CURRENT_EVENT_VERSION`] by applying every migration
+/// step between its version and the current one, so that readers only ever deal with the
+/// current shape
+pub fn migrate_event_document(mut document: Document) -> Document {
+    let mut version = match document.get("schemaVersion") {
+        Some(Bson::Int32(version)) => *version as u32,
+        Some(Bson::Int64(version)) => *version as u32,
+        _ => legacy_event_version(),
+    };
+
+    while version < CURRENT_EVENT_VERSION {
+        if version == 1 {
+            v1_to_v2(&mut document);
+        }
+        version += 1;
+    }
+
+    document.insert("schemaVersion", version as i64);
+    document
+}
+
+/// v1 events did not store their arrival date separately nor the length of their payload
+fn v1_to_v2(document: &mut Document) {
+    if !document.contains_key("arrivedDate") {
+        let arrived_date = document
+            .get_i64("arrivedAt")
+            .ok()
+            .and_then(DateTime::from_timestamp_millis);
+        if let Some(arrived_date) = arrived_date {
+            document.insert(
+                "arrivedDate",
+                arrived_date.to_rfc3339_opts(SecondsFormat::Millis, true),
+            );
+        }
+    }
+
+    if !document.contains_key("payloadByteLength") {
+        let length = document.get_str("body").map(str::len).unwrap_or_default();
+        document.insert("payloadByteLength", length as i64);
+    }
+}
+
+/// Migrates a stored event document, as [`migrate_event_document`] does, and fills the
+/// top-level fields of `defaults` missing from it. Returns the fields to set on the stored
+/// document for it to match, empty if it is already up to date.
+pub fn backfill_event_document(document: &Document, defaults: &Document) -> Document {
+    let mut backfilled = migrate_event_document(document.clone());
+    for (field, value) in defaults {
+        if !backfilled.contains_key(field) {
+            backfilled.insert(field, value.clone());
+        }
+    }
+
+    backfilled
+        .into_iter()
+        .filter(|(field, value)| document.get(field) != Some(value))
+        .collect()
+}
+
+impl Event {
+    /// Deserializes a stored event, migrating it to the current shape first
+    pub fn from_document(document: Document) -> Result<Self, bson::de::Error> {
+        bson::from_document(migrate_event_document(document))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::id::{prefix::IdPrefix, Id};
+    use bson::doc;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_v1_event_is_migrated_on_read() {
+        let arrived_at = Utc.timestamp_millis_opt(1_700_000_000_123).unwrap();
+        let v1 = doc! {
+            "_id": Id::new(IdPrefix::Event, arrived_at).to_string(),
+            "key": Id::new(IdPrefix::EventKey, arrived_at).to_string(),
+            "name": "event.received",
+            "type": "id",
+            "group": "group",
+            "accessKey": "id_test_1_foo",
+            "topic": "topic",
+            "environment": "test",
+            "body": "hello world",
+            "headers": {},
+            "arrivedAt": arrived_at.timestamp_millis(),
+            "state": "pending",
+            "ownership": { "buildableId": "foo", "clientId": "foo" },
+            "hashes": [
+                { "type": "body", "hash": "a" },
+                { "type": "event", "hash": "b" },
+                { "type": "model::body", "hash": "c" },
+            ],
+            "createdAt": arrived_at.timestamp_millis(),
+            "updatedAt": arrived_at.timestamp_millis(),
+            "deleted": false,
+            "changeLog": {},
+            "tags": [],
+            "active": true,
+        };
+
+        let event = Event::from_document(v1).expect("v1 event should be migrated");
+
+        assert_eq!(event.schema_version, CURRENT_EVENT_VERSION);
+        assert_eq!(event.arrived_date, arrived_at);
+        assert_eq!(event.payload_byte_length, 11);
+    }
+
+    #[test]
+    fn test_current_event_is_left_unchanged() {
+        let document = doc! { "schemaVersion": CURRENT_EVENT_VERSION as i64, "body": "hello" };
+
+        assert_eq!(migrate_event_document(document.clone()), document);
+    }
+
+    #[test]
+    fn test_backfill_populates_missing_fields_of_old_events() {
+        let arrived_at = Utc.timestamp_millis_opt(1_700_000_000_123).unwrap();
+        let v1 = doc! {
+            "_id": Id::new(IdPrefix::Event, arrived_at).to_string(),
+            "body": "hello world",
+            "arrivedAt": arrived_at.timestamp_millis(),
+            "region": "eu",
+        };
+        let defaults = doc! { "region": "us", "priority": 0 };
+
+        let fields = backfill_event_document(&v1, &defaults);
+
+        assert_eq!(
+            fields,
+            doc! {
+                "arrivedDate": "2023-11-14T22:13:20.123Z",
+                "payloadByteLength": 11_i64,
+                "schemaVersion": CURRENT_EVENT_VERSION as i64,
+                "priority": 0,
+            }
+        );
+
+        let mut backfilled = v1.clone();
+        backfilled.extend(fields);
+        assert!(backfill_event_document(&backfilled, &defaults).is_empty());
+    }
+}
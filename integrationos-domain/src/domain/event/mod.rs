@@ -1,9 +1,13 @@
 pub mod duplicates;
 pub mod event_access;
 pub mod event_response;
+pub mod event_schema;
 pub mod event_state;
 pub mod event_with_context;
+pub mod field_encryption;
 pub mod hashes;
+pub mod migration;
+pub mod partition;
 
 use chrono::{DateTime, SubsecRound, Utc};
 use http::HeaderMap;
@@ -15,11 +19,14 @@ use self::{
     duplicates::Duplicates,
     event_state::EventState,
     hashes::{HashValue, Hashes},
+    migration::{legacy_event_version, CURRENT_EVENT_VERSION},
+    partition::EventPartitionKey,
 };
 
 use super::{
     access_key::{encrypted_access_key::EncryptedAccessKey, AccessKey},
     configuration::environment::Environment,
+    connection::ConnectionMode,
     shared::{ownership::Ownership, record_metadata::RecordMetadata},
 };
 
@@ -42,12 +49,29 @@ pub struct Event {
     #[serde(with = "chrono::serde::ts_milliseconds")]
     pub arrived_at: DateTime<Utc>,
     pub arrived_date: DateTime<Utc>,
+    /// When the event happened according to its sender, read through the timestamp path of
+    /// its access key and bounded by the ingestion policy of the gateway
+    #[serde(
+        with = "chrono::serde::ts_milliseconds_option",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub occurred_at: Option<DateTime<Utc>>,
     pub state: EventState,
     pub ownership: Ownership,
     pub hashes: [HashValue; 3],
     pub payload_byte_length: usize,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub duplicates: Option<Duplicates>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub partition_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub connection_mode: Option<ConnectionMode>,
+    /// Id shared by the event and the metrics of the request that caused it
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub correlation_id: Option<String>,
+    #[serde(default = "legacy_event_version")]
+    pub schema_version: u32,
     #[serde(flatten, default)]
     pub record_metadata: RecordMetadata,
 }
@@ -70,12 +94,27 @@ pub struct PublicEvent {
     #[serde(with = "chrono::serde::ts_milliseconds")]
     pub arrived_at: DateTime<Utc>,
     pub arrived_date: DateTime<Utc>,
+    /// When the event happened according to its sender, read through the timestamp path of
+    /// its access key and bounded by the ingestion policy of the gateway
+    #[serde(
+        with = "chrono::serde::ts_milliseconds_option",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub occurred_at: Option<DateTime<Utc>>,
     pub state: EventState,
     pub ownership: Ownership,
     pub hashes: [HashValue; 3],
     pub payload_byte_length: usize,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub duplicates: Option<Duplicates>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub connection_mode: Option<ConnectionMode>,
+    /// Id shared by the event and the metrics of the request that caused it
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub correlation_id: Option<String>,
+    #[serde(default = "legacy_event_version")]
+    pub schema_version: u32,
     #[serde(flatten, default)]
     pub record_metadata: RecordMetadata,
 }
@@ -120,6 +159,26 @@ impl Event {
         self
     }
 
+    pub fn with_connection_mode(mut self, connection_mode: ConnectionMode) -> Self {
+        self.connection_mode = Some(connection_mode);
+        self
+    }
+
+    pub fn with_occurred_at(mut self, occurred_at: DateTime<Utc>) -> Self {
+        self.occurred_at = Some(occurred_at);
+        self
+    }
+
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    pub fn with_partition_key(mut self, partition_key: EventPartitionKey) -> Self {
+        self.partition_key = partition_key.derive(&self);
+        self
+    }
+
     fn new_with_timestamp_and_ids(fields: IntermediateEventFields<'_>) -> Self {
         let topic = fields.access_key.get_topic(fields.event_name);
         let access_key_data = &fields.access_key.data;
@@ -156,11 +215,16 @@ impl Event {
             headers: fields.headers,
             arrived_at: fields.timestamp,
             arrived_date: fields.timestamp,
+            occurred_at: None,
             state,
             ownership,
             hashes,
             payload_byte_length,
             duplicates: None,
+            partition_key: None,
+            connection_mode: None,
+            correlation_id: None,
+            schema_version: CURRENT_EVENT_VERSION,
             record_metadata: Default::default(),
         }
     }
@@ -178,11 +242,15 @@ impl Event {
             headers: self.headers.clone(),
             arrived_at: self.arrived_at,
             arrived_date: self.arrived_date,
+            occurred_at: self.occurred_at,
             state: self.state,
             ownership: self.ownership.clone(),
             hashes: self.hashes,
             payload_byte_length: self.payload_byte_length,
             duplicates: self.duplicates.clone(),
+            connection_mode: self.connection_mode,
+            correlation_id: self.correlation_id.clone(),
+            schema_version: self.schema_version,
             record_metadata: self.record_metadata.clone(),
         }
     }
@@ -224,6 +292,7 @@ mod test {
             event_object_id_path: None,
             timestamp_path: None,
             parent_access_key: None,
+            scopes: vec![],
         },
     });
 
@@ -295,4 +364,66 @@ mod test {
         let deserialized: Event = serde_json::from_str(&json).unwrap();
         assert_eq!(deserialized, event);
     }
+
+    #[test]
+    fn test_event_partition_key() {
+        let timestamp = Utc.timestamp_opt(0, 0).single().unwrap();
+        let event = Event::new_with_timestamp_and_ids(IntermediateEventFields {
+            access_key: &ACCESS_KEY,
+            encrypted_access_key: &EncryptedAccessKey::parse("id_live_1_foo").unwrap(),
+            event_name: "event.received",
+            headers: HEADERS.clone(),
+            body: "hello world".to_owned(),
+            timestamp,
+            id: Id::new_with_uuid(IdPrefix::Event, timestamp, Uuid::nil()),
+            key: Id::new_with_uuid(IdPrefix::EventKey, timestamp, Uuid::nil()),
+        });
+
+        let document = bson::to_document(&event.clone()).unwrap();
+        assert!(!document.contains_key("partitionKey"));
+
+        let document = bson::to_document(
+            &event
+                .clone()
+                .with_partition_key(EventPartitionKey::ClientId),
+        )
+        .unwrap();
+        assert_eq!(document.get_str("partitionKey").unwrap(), "foo");
+
+        let document =
+            bson::to_document(&event.clone().with_partition_key(EventPartitionKey::Date)).unwrap();
+        assert_eq!(document.get_str("partitionKey").unwrap(), "1970-01-01");
+
+        let document =
+            bson::to_document(&event.with_partition_key(EventPartitionKey::ClientIdAndDate))
+                .unwrap();
+        assert_eq!(document.get_str("partitionKey").unwrap(), "foo::1970-01-01");
+
+        assert_eq!(
+            "clientIdAndDate".parse::<EventPartitionKey>().unwrap(),
+            EventPartitionKey::ClientIdAndDate
+        );
+    }
+
+    #[test]
+    fn test_event_connection_mode() {
+        let event = Event::new(
+            &ACCESS_KEY,
+            &EncryptedAccessKey::parse("id_live_1_foo").unwrap(),
+            "event.received",
+            HEADERS.clone(),
+            "hello world".to_owned(),
+        );
+
+        let document = bson::to_document(&event).unwrap();
+        assert!(!document.contains_key("connectionMode"));
+
+        let event = event.with_connection_mode(ConnectionMode::Sandbox);
+        let document = bson::to_document(&event).unwrap();
+        assert_eq!(document.get_str("connectionMode").unwrap(), "sandbox");
+        assert_eq!(
+            event.to_public().connection_mode,
+            Some(ConnectionMode::Sandbox)
+        );
+    }
 }
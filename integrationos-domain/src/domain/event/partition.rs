@@ -0,0 +1,34 @@
+use super::Event;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+/// How the partition key of an event is derived. The key is stored in the `partitionKey`
+/// field of the event, which is the field a sharded events collection should be sharded on
+/// so that inserts are spread across shards.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Display, EnumString,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum EventPartitionKey {
+    #[default]
+    None,
+    ClientId,
+    Date,
+    ClientIdAndDate,
+}
+
+impl EventPartitionKey {
+    pub fn derive(&self, event: &Event) -> Option<String> {
+        let date = || event.arrived_date.format("%Y-%m-%d").to_string();
+
+        match self {
+            EventPartitionKey::None => None,
+            EventPartitionKey::ClientId => Some(event.ownership.client_id.clone()),
+            EventPartitionKey::Date => Some(date()),
+            EventPartitionKey::ClientIdAndDate => {
+                Some(format!("{}::{}", event.ownership.client_id, date()))
+            }
+        }
+    }
+}
@@ -19,6 +19,7 @@ pub enum IdPrefix {
     EventAccess,
     EventDependency,
     EventKey,
+    EventSchema,
     Job,
     JobStage,
     LLMMessage,
@@ -26,6 +27,7 @@ pub enum IdPrefix {
     LinkToken,
     Log,
     LogTracking,
+    Operation,
     Pipeline,
     Platform,
     PlatformPage,
@@ -53,6 +55,7 @@ impl Display for IdPrefix {
             IdPrefix::EventAccess => write!(f, "evt_ac"),
             IdPrefix::EventDependency => write!(f, "evt_dep"),
             IdPrefix::EventKey => write!(f, "evt_k"),
+            IdPrefix::EventSchema => write!(f, "evt_sch"),
             IdPrefix::Job => write!(f, "job"),
             IdPrefix::JobStage => write!(f, "job_stg"),
             IdPrefix::LLMMessage => write!(f, "llm_msg"),
@@ -60,6 +63,7 @@ impl Display for IdPrefix {
             IdPrefix::LinkToken => write!(f, "ln_tk"),
             IdPrefix::Log => write!(f, "log"),
             IdPrefix::LogTracking => write!(f, "log_trk"),
+            IdPrefix::Operation => write!(f, "op"),
             IdPrefix::Pipeline => write!(f, "pipe"),
             IdPrefix::Platform => write!(f, "plf"),
             IdPrefix::PlatformPage => write!(f, "plf_pg"),
@@ -91,6 +95,7 @@ impl TryFrom<&str> for IdPrefix {
             "evt_ac" => Ok(IdPrefix::EventAccess),
             "evt_dep" => Ok(IdPrefix::EventDependency),
             "evt_k" => Ok(IdPrefix::EventKey),
+            "evt_sch" => Ok(IdPrefix::EventSchema),
             "job" => Ok(IdPrefix::Job),
             "job_stg" => Ok(IdPrefix::JobStage),
             "llm_msg" => Ok(IdPrefix::LLMMessage),
@@ -98,6 +103,7 @@ impl TryFrom<&str> for IdPrefix {
             "ln_tk" => Ok(IdPrefix::LinkToken),
             "log" => Ok(IdPrefix::Log),
             "log_trk" => Ok(IdPrefix::LogTracking),
+            "op" => Ok(IdPrefix::Operation),
             "pipe" => Ok(IdPrefix::Pipeline),
             "plf" => Ok(IdPrefix::Platform),
             "plf_pg" => Ok(IdPrefix::PlatformPage),
@@ -131,6 +137,7 @@ impl From<IdPrefix> for String {
             IdPrefix::EventAccess => "evt_ac".to_string(),
             IdPrefix::EventDependency => "evt_dep".to_string(),
             IdPrefix::EventKey => "evt_k".to_string(),
+            IdPrefix::EventSchema => "evt_sch".to_string(),
             IdPrefix::Job => "job".to_string(),
             IdPrefix::JobStage => "job_stg".to_string(),
             IdPrefix::LLMMessage => "llm_msg".to_string(),
@@ -138,6 +145,7 @@ impl From<IdPrefix> for String {
             IdPrefix::LinkToken => "ln_tk".to_string(),
             IdPrefix::Log => "log".to_string(),
             IdPrefix::LogTracking => "log_trk".to_string(),
+            IdPrefix::Operation => "op".to_string(),
             IdPrefix::Pipeline => "pipe".to_string(),
             IdPrefix::Platform => "plf".to_string(),
             IdPrefix::PlatformPage => "plf_pg".to_string(),
@@ -200,12 +208,17 @@ mod test {
         assert_eq!(IdPrefix::try_from("arch").unwrap(), IdPrefix::Archive);
         assert_eq!(IdPrefix::try_from("evt_ac").unwrap(), IdPrefix::EventAccess);
         assert_eq!(IdPrefix::try_from("evt_k").unwrap(), IdPrefix::EventKey);
+        assert_eq!(
+            IdPrefix::try_from("evt_sch").unwrap(),
+            IdPrefix::EventSchema
+        );
         assert_eq!(IdPrefix::try_from("job").unwrap(), IdPrefix::Job);
         assert_eq!(IdPrefix::try_from("job_stg").unwrap(), IdPrefix::JobStage);
         assert_eq!(IdPrefix::try_from("llm_msg").unwrap(), IdPrefix::LLMMessage);
         assert_eq!(IdPrefix::try_from("ln").unwrap(), IdPrefix::Link);
         assert_eq!(IdPrefix::try_from("ln_tk").unwrap(), IdPrefix::LinkToken);
         assert_eq!(IdPrefix::try_from("log").unwrap(), IdPrefix::Log);
+        assert_eq!(IdPrefix::try_from("op").unwrap(), IdPrefix::Operation);
         assert_eq!(IdPrefix::try_from("pipe").unwrap(), IdPrefix::Pipeline);
         assert_eq!(IdPrefix::try_from("plf").unwrap(), IdPrefix::Platform);
         assert_eq!(IdPrefix::try_from("q").unwrap(), IdPrefix::Queue);
@@ -240,6 +253,7 @@ mod test {
         assert_eq!(format!("{}", IdPrefix::EventAccess), "evt_ac");
         assert_eq!(format!("{}", IdPrefix::EventDependency), "evt_dep");
         assert_eq!(format!("{}", IdPrefix::EventKey), "evt_k");
+        assert_eq!(format!("{}", IdPrefix::EventSchema), "evt_sch");
         assert_eq!(format!("{}", IdPrefix::Job), "job");
         assert_eq!(format!("{}", IdPrefix::JobStage), "job_stg");
         assert_eq!(format!("{}", IdPrefix::LLMMessage), "llm_msg");
@@ -247,6 +261,7 @@ mod test {
         assert_eq!(format!("{}", IdPrefix::LinkToken), "ln_tk");
         assert_eq!(format!("{}", IdPrefix::Log), "log");
         assert_eq!(format!("{}", IdPrefix::LogTracking), "log_trk");
+        assert_eq!(format!("{}", IdPrefix::Operation), "op");
         assert_eq!(format!("{}", IdPrefix::Pipeline), "pipe");
         assert_eq!(format!("{}", IdPrefix::Platform), "plf");
         assert_eq!(format!("{}", IdPrefix::PlatformPage), "plf_pg");
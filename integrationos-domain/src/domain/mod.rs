@@ -9,6 +9,7 @@ pub mod http;
 pub mod id;
 pub mod jobs;
 pub mod microservice;
+pub mod operation;
 pub mod pipeline;
 pub mod platform;
 pub mod schema;
@@ -29,6 +30,7 @@ pub use http::*;
 pub use id::*;
 pub use jobs::*;
 pub use microservice::*;
+pub use operation::*;
 pub use pipeline::*;
 pub use platform::*;
 pub use schema::*;
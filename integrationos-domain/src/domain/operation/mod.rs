@@ -0,0 +1,166 @@
+use crate::{
+    algebra::MongoStore,
+    id::{prefix::IdPrefix, Id},
+    prelude::shared::record_metadata::RecordMetadata,
+    IntegrationOSError,
+};
+use bson::doc;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+use strum::{AsRefStr, Display};
+use tracing::error;
+
+/// A long-running task started through the API whose state can be polled by clients
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Operation {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub kind: OperationKind,
+    pub state: OperationState,
+    /// Fraction of the work done, between 0 and 1
+    pub progress: f64,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+    #[serde(flatten, default)]
+    pub record_metadata: RecordMetadata,
+}
+
+impl Operation {
+    pub fn new(kind: OperationKind) -> Self {
+        Self {
+            id: Id::now(IdPrefix::Operation),
+            kind,
+            state: OperationState::Pending,
+            progress: 0.0,
+            result: None,
+            error: None,
+            record_metadata: RecordMetadata::default(),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.state,
+            OperationState::Succeeded | OperationState::Failed
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Display, AsRefStr)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum OperationKind {
+    OpenApiGeneration,
+    BulkConnectionValidation,
+    BulkCredentialRotation,
+    EventBackfill,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Display, AsRefStr)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum OperationState {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// Handle given to a running operation to report its progress
+#[derive(Debug, Clone)]
+pub struct OperationProgress {
+    store: MongoStore<Operation>,
+    id: Id,
+}
+
+impl OperationProgress {
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    pub async fn report(&self, progress: f64) -> Result<(), IntegrationOSError> {
+        self.update(doc! { "progress": progress.clamp(0.0, 1.0) })
+            .await
+    }
+
+    async fn update(&self, mut fields: bson::Document) -> Result<(), IntegrationOSError> {
+        fields.insert("updatedAt", Utc::now().timestamp_millis());
+        self.store
+            .update_one(&self.id.to_string(), doc! { "$set": fields })
+            .await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OperationTracker {
+    store: MongoStore<Operation>,
+}
+
+impl OperationTracker {
+    pub fn new(store: MongoStore<Operation>) -> Self {
+        Self { store }
+    }
+
+    /// Records a pending operation and runs `task` in the background, storing its result
+    /// or error once it finishes. The returned operation can be polled by its id.
+    pub async fn start<F, Fut>(
+        &self,
+        kind: OperationKind,
+        task: F,
+    ) -> Result<Operation, IntegrationOSError>
+    where
+        F: FnOnce(OperationProgress) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Value, IntegrationOSError>> + Send + 'static,
+    {
+        let operation = Operation::new(kind);
+        self.store.create_one(&operation).await?;
+
+        let progress = OperationProgress {
+            store: self.store.clone(),
+            id: operation.id,
+        };
+
+        tokio::spawn(async move {
+            if let Err(e) = progress
+                .update(doc! { "state": OperationState::Running.as_ref() })
+                .await
+            {
+                error!("Could not mark operation {} as running: {e}", progress.id);
+            }
+
+            let fields = match task(progress.clone()).await {
+                Ok(result) => bson::to_bson(&result)
+                    .map(|result| {
+                        doc! {
+                            "state": OperationState::Succeeded.as_ref(),
+                            "progress": 1.0,
+                            "result": result,
+                        }
+                    })
+                    .unwrap_or_else(|e| {
+                        doc! {
+                            "state": OperationState::Failed.as_ref(),
+                            "error": format!("Could not serialize operation result: {e}"),
+                        }
+                    }),
+                Err(e) => doc! {
+                    "state": OperationState::Failed.as_ref(),
+                    "error": e.to_string(),
+                },
+            };
+
+            if let Err(e) = progress.update(fields).await {
+                error!("Could not record outcome of operation {}: {e}", progress.id);
+            }
+        });
+
+        Ok(operation)
+    }
+
+    pub async fn get(&self, id: &Id) -> Result<Option<Operation>, IntegrationOSError> {
+        self.store.get_one_by_id(&id.to_string()).await
+    }
+}
@@ -0,0 +1,196 @@
+use super::{extractor::HttpExtractor, middleware::Middleware};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Bounds on the pipelines accepted by the execution engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineLimits {
+    /// Maximum number of middleware stages
+    pub max_stages: usize,
+    /// Maximum length of a chain of extractors depending on each other
+    pub max_depth: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PipelineComplexityError {
+    #[error("Pipeline has {stages} stages, more than the maximum of {max}")]
+    TooManyStages { stages: usize, max: usize },
+    #[error("Pipeline has extractors nested {depth} deep, more than the maximum of {max}")]
+    TooDeep { depth: usize, max: usize },
+    #[error("Extractor {extractor} depends on unknown extractor {dependency}")]
+    UnknownDependency {
+        extractor: String,
+        dependency: String,
+    },
+    #[error("Extractors depend on each other in a cycle: {}", cycle.join(" -> "))]
+    Cycle { cycle: Vec<String> },
+}
+
+#[derive(Clone, Copy)]
+enum Visit {
+    InProgress,
+    Done { depth: usize },
+}
+
+impl PipelineLimits {
+    pub fn check(&self, middleware: &[Middleware]) -> Result<(), PipelineComplexityError> {
+        if middleware.len() > self.max_stages {
+            return Err(PipelineComplexityError::TooManyStages {
+                stages: middleware.len(),
+                max: self.max_stages,
+            });
+        }
+
+        let extractors: HashMap<&str, &HttpExtractor> = middleware
+            .iter()
+            .filter_map(|stage| match stage {
+                Middleware::HttpExtractor(e) => Some((e.key.as_str(), e)),
+                Middleware::Transformer { .. } => None,
+            })
+            .collect();
+
+        let mut visits = HashMap::new();
+        let mut path = vec![];
+        let mut depth = 0;
+        for key in extractors.keys() {
+            depth = depth.max(visit(key, &extractors, &mut visits, &mut path)?);
+        }
+
+        if depth > self.max_depth {
+            return Err(PipelineComplexityError::TooDeep {
+                depth,
+                max: self.max_depth,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Depth of the dependency chain starting at `key`, failing on cycles and unknown dependencies
+fn visit<'a>(
+    key: &'a str,
+    extractors: &HashMap<&'a str, &'a HttpExtractor>,
+    visits: &mut HashMap<&'a str, Visit>,
+    path: &mut Vec<&'a str>,
+) -> Result<usize, PipelineComplexityError> {
+    match visits.get(key) {
+        Some(Visit::Done { depth }) => return Ok(*depth),
+        Some(Visit::InProgress) => {
+            let start = path.iter().position(|k| *k == key).unwrap_or_default();
+            let mut cycle: Vec<String> = path[start..].iter().map(|k| k.to_string()).collect();
+            cycle.push(key.to_owned());
+            return Err(PipelineComplexityError::Cycle { cycle });
+        }
+        None => {}
+    }
+
+    visits.insert(key, Visit::InProgress);
+    path.push(key);
+
+    let mut depth = 0;
+    for dependency in &extractors[key].depends_on {
+        let Some((dependency, _)) = extractors.get_key_value(dependency.as_str()) else {
+            return Err(PipelineComplexityError::UnknownDependency {
+                extractor: key.to_owned(),
+                dependency: dependency.clone(),
+            });
+        };
+        depth = depth.max(visit(dependency, extractors, visits, path)?);
+    }
+
+    path.pop();
+    let depth = depth + 1;
+    visits.insert(key, Visit::Done { depth });
+    Ok(depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::policies::{Policies, RetryPolicy};
+
+    fn extractor(key: &str, depends_on: &[&str]) -> Middleware {
+        Middleware::HttpExtractor(HttpExtractor {
+            key: key.to_owned(),
+            url: "https://example.com".to_owned(),
+            method: http::Method::GET,
+            headers: "{}".to_owned(),
+            data: "{}".to_owned(),
+            policies: Policies {
+                retry: RetryPolicy {
+                    maximum_attempts: 1,
+                    initial_interval: "1 second".to_owned(),
+                },
+            },
+            start_to_close_timeout: "30s".to_owned(),
+            depends_on: depends_on.iter().map(|k| k.to_string()).collect(),
+            context: None,
+            auth_token: None,
+        })
+    }
+
+    const LIMITS: PipelineLimits = PipelineLimits {
+        max_stages: 4,
+        max_depth: 2,
+    };
+
+    #[test]
+    fn test_pipeline_within_limits_is_accepted() {
+        let middleware = vec![
+            extractor("a", &[]),
+            extractor("b", &["a"]),
+            extractor("c", &["a"]),
+            Middleware::Transformer {
+                language: "javascript".to_owned(),
+                code: "function transform() {}".to_owned(),
+            },
+        ];
+        assert_eq!(LIMITS.check(&middleware), Ok(()));
+    }
+
+    #[test]
+    fn test_over_complex_pipelines_are_rejected() {
+        let middleware = (0..5)
+            .map(|i| extractor(&i.to_string(), &[]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            LIMITS.check(&middleware),
+            Err(PipelineComplexityError::TooManyStages { stages: 5, max: 4 })
+        );
+
+        let middleware = vec![
+            extractor("a", &[]),
+            extractor("b", &["a"]),
+            extractor("c", &["b"]),
+        ];
+        assert_eq!(
+            LIMITS.check(&middleware),
+            Err(PipelineComplexityError::TooDeep { depth: 3, max: 2 })
+        );
+
+        let middleware = vec![extractor("a", &["missing"])];
+        assert_eq!(
+            LIMITS.check(&middleware),
+            Err(PipelineComplexityError::UnknownDependency {
+                extractor: "a".to_owned(),
+                dependency: "missing".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_cyclic_pipeline_is_rejected() {
+        let middleware = vec![
+            extractor("a", &["c"]),
+            extractor("b", &["a"]),
+            extractor("c", &["b"]),
+        ];
+
+        let Err(PipelineComplexityError::Cycle { cycle }) = LIMITS.check(&middleware) else {
+            panic!("Cycle should be detected");
+        };
+        assert_eq!(cycle.len(), 4);
+        assert_eq!(cycle.first(), cycle.last());
+    }
+}
@@ -15,6 +15,10 @@ pub struct HttpExtractor {
     pub data: String,
     pub policies: Policies,
     pub start_to_close_timeout: String,
+    /// Keys of the extractors of the same pipeline whose results this extractor needs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub depends_on: Vec<String>,
     #[serde(skip)]
     #[cfg_attr(feature = "dummy", dummy(default))]
     pub context: Option<Value>,
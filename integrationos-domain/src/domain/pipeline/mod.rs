@@ -1,3 +1,4 @@
+pub mod complexity;
 pub mod destination;
 pub mod extractor;
 pub mod middleware;
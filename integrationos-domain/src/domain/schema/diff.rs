@@ -0,0 +1,213 @@
+use super::json_schema::{JsonSchema, Property};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use strum::{AsRefStr, Display};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, AsRefStr)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum SchemaChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaChange {
+    /// JSON path of the changed field, e.g. `$.address.city`
+    pub path: String,
+    pub kind: SchemaChangeKind,
+    /// Whether data valid for the stored schema may be invalid for the proposed one
+    pub breaking: bool,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaDiff {
+    pub breaking: bool,
+    pub changes: Vec<SchemaChange>,
+}
+
+impl SchemaDiff {
+    /// Compares the `stored` schema against the `proposed` one. Removing a required field,
+    /// adding a required field, making a field required and changing the type of a field
+    /// are breaking changes. Only top level fields carry required information, nested
+    /// fields are treated as optional.
+    pub fn new(stored: &JsonSchema, proposed: &JsonSchema) -> Self {
+        let required = |schema: &JsonSchema| -> BTreeSet<String> {
+            schema.required.iter().flatten().cloned().collect()
+        };
+
+        let mut changes = vec![];
+        diff_properties(
+            "$",
+            &stored.properties,
+            &proposed.properties,
+            (&required(stored), &required(proposed)),
+            &mut changes,
+        );
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Self {
+            breaking: changes.iter().any(|change| change.breaking),
+            changes,
+        }
+    }
+}
+
+fn diff_properties(
+    path: &str,
+    stored: &HashMap<String, Property>,
+    proposed: &HashMap<String, Property>,
+    (stored_required, proposed_required): (&BTreeSet<String>, &BTreeSet<String>),
+    changes: &mut Vec<SchemaChange>,
+) {
+    for (name, property) in stored {
+        let field = format!("{path}.{name}");
+        let was_required = stored_required.contains(name);
+        let is_required = proposed_required.contains(name);
+
+        let Some(other) = proposed.get(name) else {
+            changes.push(SchemaChange {
+                path: field,
+                kind: SchemaChangeKind::Removed,
+                breaking: was_required,
+                description: format!(
+                    "Removed {} field",
+                    if was_required { "required" } else { "optional" }
+                ),
+            });
+            continue;
+        };
+
+        if property.r#type != other.r#type {
+            changes.push(SchemaChange {
+                path: field.clone(),
+                kind: SchemaChangeKind::Modified,
+                breaking: true,
+                description: format!("Changed type from {} to {}", property.r#type, other.r#type),
+            });
+        }
+
+        if was_required != is_required {
+            changes.push(SchemaChange {
+                path: field.clone(),
+                kind: SchemaChangeKind::Modified,
+                breaking: is_required,
+                description: if is_required {
+                    "Made field required".to_string()
+                } else {
+                    "Made field optional".to_string()
+                },
+            });
+        }
+
+        let nested = |property: &Property| property.properties.clone().unwrap_or_default();
+        let none = BTreeSet::new();
+        diff_properties(
+            &field,
+            &nested(property),
+            &nested(other),
+            (&none, &none),
+            changes,
+        );
+        if let (Some(items), Some(other_items)) = (&property.items, &other.items) {
+            diff_properties(
+                &format!("{field}[]"),
+                &nested(items),
+                &nested(other_items),
+                (&none, &none),
+                changes,
+            );
+        }
+    }
+
+    for name in proposed.keys().filter(|name| !stored.contains_key(*name)) {
+        let is_required = proposed_required.contains(name);
+
+        changes.push(SchemaChange {
+            path: format!("{path}.{name}"),
+            kind: SchemaChangeKind::Added,
+            breaking: is_required,
+            description: format!(
+                "Added {} field",
+                if is_required { "required" } else { "optional" }
+            ),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_removed_required_field_is_breaking() {
+        let stored = JsonSchema::from_value(json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "email": { "type": "string" },
+                "address": {
+                    "type": "object",
+                    "properties": { "city": { "type": "string" } }
+                }
+            },
+            "required": ["id", "email"]
+        }))
+        .expect("Failed to parse stored schema");
+        let proposed = JsonSchema::from_value(json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "nickname": { "type": "string" },
+                "address": {
+                    "type": "object",
+                    "properties": { "city": { "type": "number" } }
+                }
+            },
+            "required": ["id"]
+        }))
+        .expect("Failed to parse proposed schema");
+
+        let diff = SchemaDiff::new(&stored, &proposed);
+
+        assert!(diff.breaking);
+        assert_eq!(
+            diff.changes
+                .iter()
+                .map(|change| (change.path.as_str(), change.kind, change.breaking))
+                .collect::<Vec<_>>(),
+            vec![
+                ("$.address.city", SchemaChangeKind::Modified, true),
+                ("$.email", SchemaChangeKind::Removed, true),
+                ("$.nickname", SchemaChangeKind::Added, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_added_optional_field_is_not_breaking() {
+        let stored = JsonSchema::from_value(json!({
+            "type": "object",
+            "properties": { "id": { "type": "string" } },
+            "required": ["id"]
+        }))
+        .expect("Failed to parse stored schema");
+        let mut proposed = stored.clone();
+        proposed.insert(
+            "nickname".to_string(),
+            "string".to_string(),
+            "$.nickname".to_string(),
+        );
+
+        let diff = SchemaDiff::new(&stored, &proposed);
+
+        assert!(!diff.breaking);
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].kind, SchemaChangeKind::Added);
+    }
+}
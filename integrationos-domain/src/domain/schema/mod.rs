@@ -1,3 +1,4 @@
 pub mod common_model;
+pub mod diff;
 pub mod json_mapper;
 pub mod json_schema;
@@ -1,4 +1,5 @@
 pub mod cursor;
+pub mod number_precision;
 
 use bson::doc;
 use serde::{Deserialize, Serialize};
@@ -45,6 +46,10 @@ generate_stores!(
     "microservices",
     Events,
     "external-events",
+    DeadLetterEvents,
+    "dead-letter-events",
+    EventSchemas,
+    "event-schemas",
     EventAccess,
     "event-access",
     IntegrationDefinitions,
@@ -61,6 +66,8 @@ generate_stores!(
     "messages",
     Metrics,
     "system-stats",
+    MetricRecords,
+    "system-stat-records",
     CommonModels,
     "common-models",
     CommonEnums,
@@ -98,5 +105,11 @@ generate_stores!(
     Transactions,
     "event-transactions",
     Clients,
-    "clients"
+    "clients",
+    ShadowDiffs,
+    "shadow-diffs",
+    Operations,
+    "operations",
+    PkceVerifiers,
+    "pkce-verifiers"
 );
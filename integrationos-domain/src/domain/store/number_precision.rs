@@ -0,0 +1,314 @@
+use crate::{ApplicationError, IntegrationOSError};
+use bson::Decimal128;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use strum::{AsRefStr, Display, EnumString};
+
+/// How numbers that can't be represented exactly by a BSON `Int64` or `Double` are stored.
+/// Parsing JSON into `serde_json::Value` silently rounds such numbers, which corrupts
+/// amounts and identifiers coming from platforms.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    EnumString,
+    Display,
+    AsRefStr,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum NumberPrecision {
+    /// Stores the number as a string holding its exact JSON representation
+    #[default]
+    String,
+    /// Stores the number as a `Decimal128`, which holds up to 34 significant digits
+    Decimal128,
+    /// Fails the parsing
+    Reject,
+}
+
+impl NumberPrecision {
+    /// Parses a JSON payload, applying the policy to every number that would lose precision.
+    /// Numbers are read from the raw text as they would already be rounded once parsed into a
+    /// `serde_json::Value`. Decimals are kept as extended JSON, which the BSON serializer
+    /// stores as a `Decimal128`.
+    pub fn parse(&self, json: &str) -> Result<Value, IntegrationOSError> {
+        let mut parser = Parser {
+            input: json,
+            pos: 0,
+            policy: *self,
+        };
+
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos < json.len() {
+            return Err(parser.error("Trailing characters"));
+        }
+
+        Ok(value)
+    }
+
+    fn number(&self, token: &str) -> Result<Value, IntegrationOSError> {
+        let is_integer = !token.contains(['.', 'e', 'E']);
+
+        if is_integer {
+            if let Ok(integer) = token.parse::<i64>() {
+                return Ok(Value::from(integer));
+            }
+        } else if let Ok(float) = token.parse::<f64>() {
+            if float.is_finite() && normalize(&float.to_string()) == normalize(token) {
+                return Ok(Value::from(float));
+            }
+        }
+
+        match self {
+            NumberPrecision::String => Ok(Value::String(token.to_string())),
+            NumberPrecision::Decimal128 => token
+                .parse::<Decimal128>()
+                .map(|decimal| json!({ "$numberDecimal": decimal.to_string() }))
+                .map_err(|_| {
+                    ApplicationError::bad_request(
+                        &format!("Number {token} does not fit in a Decimal128"),
+                        None,
+                    )
+                }),
+            NumberPrecision::Reject => Err(ApplicationError::bad_request(
+                &format!("Number {token} can't be stored without losing precision"),
+                None,
+            )),
+        }
+    }
+}
+
+/// Significant digits and exponent of a decimal number, so that `1.50`, `15e-1` and `1.5`
+/// compare equal
+fn normalize(number: &str) -> (bool, String, i64) {
+    let (negative, number) = match number.strip_prefix('-') {
+        Some(number) => (true, number),
+        None => (false, number.strip_prefix('+').unwrap_or(number)),
+    };
+    let (mantissa, exponent) = match number.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().unwrap_or_default()),
+        None => (number, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    let digits = format!("{integer}{fraction}");
+    let digits = digits.trim_start_matches('0');
+    let significant = digits.trim_end_matches('0');
+    if significant.is_empty() {
+        return (false, String::new(), 0);
+    }
+
+    let exponent = exponent - fraction.len() as i64 + (digits.len() - significant.len()) as i64;
+    (negative, significant.to_string(), exponent)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    policy: NumberPrecision,
+}
+
+impl<'a> Parser<'a> {
+    fn value(&mut self) -> Result<Value, IntegrationOSError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(b'-' | b'0'..=b'9') => {
+                let start = self.pos;
+                while matches!(
+                    self.peek(),
+                    Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+                ) {
+                    self.pos += 1;
+                }
+                let token = &self.input[start..self.pos];
+                serde_json::from_str::<serde_json::Number>(token)
+                    .map_err(|_| self.error("Invalid number"))?;
+
+                self.policy.number(token)
+            }
+            _ => Err(self.error("Unexpected character")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, IntegrationOSError> {
+        let mut object = Map::new();
+        self.pos += 1;
+
+        self.skip_whitespace();
+        if self.eat(b'}') {
+            return Ok(Value::Object(object));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            if !self.eat(b':') {
+                return Err(self.error("Expected ':'"));
+            }
+            object.insert(key, self.value()?);
+
+            self.skip_whitespace();
+            if self.eat(b'}') {
+                return Ok(Value::Object(object));
+            }
+            if !self.eat(b',') {
+                return Err(self.error("Expected ',' or '}'"));
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, IntegrationOSError> {
+        let mut array = vec![];
+        self.pos += 1;
+
+        self.skip_whitespace();
+        if self.eat(b']') {
+            return Ok(Value::Array(array));
+        }
+
+        loop {
+            array.push(self.value()?);
+
+            self.skip_whitespace();
+            if self.eat(b']') {
+                return Ok(Value::Array(array));
+            }
+            if !self.eat(b',') {
+                return Err(self.error("Expected ',' or ']'"));
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, IntegrationOSError> {
+        if self.peek() != Some(b'"') {
+            return Err(self.error("Expected a string"));
+        }
+
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.peek() {
+                Some(b'"') => break,
+                Some(b'\\') => self.pos += 2,
+                Some(_) => self.pos += 1,
+                None => return Err(self.error("Unterminated string")),
+            }
+        }
+        self.pos += 1;
+
+        serde_json::from_str::<String>(&self.input[start..self.pos])
+            .map_err(|_| self.error("Invalid string"))
+    }
+
+    fn literal(&mut self, literal: &str, value: Value) -> Result<Value, IntegrationOSError> {
+        if self.input[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error("Unexpected literal"))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn error(&self, message: &str) -> IntegrationOSError {
+        ApplicationError::bad_request(
+            &format!("Invalid JSON payload: {message} at position {}", self.pos),
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bson::{doc, Bson, Document};
+
+    const PAYLOAD: &str =
+        r#"{"id": 12345678901234567890, "amount": 1234567890123456.789, "rate": 0.25, "count": 3}"#;
+
+    /// Stores the payload the way the context store does and reads it back
+    fn round_trip(value: &Value) -> Document {
+        let document = bson::to_document(&json!({ "data": value })).expect("Failed to serialize");
+        let bytes = bson::to_vec(&document).expect("Failed to encode");
+        let document: Document = bson::from_slice(&bytes).expect("Failed to decode");
+        document.get_document("data").expect("Missing data").clone()
+    }
+
+    #[test]
+    fn test_number_beyond_f64_precision_is_preserved() {
+        let lossy = serde_json::from_str::<Value>("1234567890123456.789").unwrap();
+        assert_ne!(lossy.to_string(), "1234567890123456.789");
+
+        let value = NumberPrecision::String
+            .parse(PAYLOAD)
+            .expect("Failed to parse payload");
+        assert_eq!(
+            round_trip(&value),
+            doc! {
+                "id": "12345678901234567890",
+                "amount": "1234567890123456.789",
+                "rate": 0.25,
+                "count": 3_i64,
+            }
+        );
+
+        let value = NumberPrecision::Decimal128
+            .parse(PAYLOAD)
+            .expect("Failed to parse payload");
+        let document = round_trip(&value);
+        assert!(matches!(document.get("amount"), Some(Bson::Decimal128(_))));
+        assert_eq!(
+            document.get("amount").map(ToString::to_string),
+            Some("1234567890123456.789".to_string())
+        );
+        assert_eq!(
+            document.get("id").map(ToString::to_string),
+            Some("12345678901234567890".to_string())
+        );
+        assert_eq!(document.get("rate"), Some(&Bson::Double(0.25)));
+
+        let err = NumberPrecision::Reject
+            .parse(PAYLOAD)
+            .expect_err("Imprecise numbers should be rejected");
+        assert_eq!(http::StatusCode::from(&err), http::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            NumberPrecision::Reject
+                .parse(r#"{"rate": 0.25, "nested": [1, -2.5e3, "a\"b"]}"#)
+                .ok(),
+            Some(json!({ "rate": 0.25, "nested": [1, -2500.0, "a\"b"] }))
+        );
+    }
+}
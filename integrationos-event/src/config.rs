@@ -20,12 +20,20 @@ pub struct EventCoreConfig {
     pub db_config: DatabaseConfig,
     #[envconfig(from = "CONNECTION_CACHE_TTL_SECS", default = "86400")]
     pub connection_cache_ttl_secs: u64,
+    #[envconfig(from = "CONNECTION_DEFINITION_CACHE_TTL_SECS", default = "86400")]
+    pub connection_definition_cache_ttl_secs: u64,
     #[envconfig(from = "CONNECTION_MODEL_SCHEMA_TTL_SECS", default = "86400")]
     pub connection_model_schema_cache_ttl_secs: u64,
     #[envconfig(from = "CONNECTION_MODEL_DEFINITION_CACHE_TTL_SECS", default = "86400")]
     pub connection_model_definition_cache_ttl_secs: u64,
-    #[envconfig(from = "SECRET_CACHE_TTL_SECS", default = "300")]
+    /// TTL of decrypted connection secrets, which must be shorter than the connection cache TTL
+    #[envconfig(from = "SECRET_CACHE_TTL_SECS", default = "60")]
     pub secret_cache_ttl_secs: u64,
+    /// Limits the calls of each connection to its throughput per refill window
+    #[envconfig(from = "RATE_LIMIT_ENABLED", default = "true")]
+    pub rate_limit_enabled: bool,
+    #[envconfig(from = "CONNECTION_RATE_LIMIT_REFILL_SECS", default = "60")]
+    pub connection_rate_limit_refill_secs: u64,
 }
 
 impl Display for EventCoreConfig {
@@ -3,9 +3,9 @@ use dotenvy::dotenv;
 use envconfig::Envconfig;
 use integrationos_domain::{
     secret::Secret,
-    secrets::SecretServiceProvider,
+    secrets_client,
     telemetry::{get_subscriber, init_subscriber},
-    GoogleKms, IOSKms, MongoStore, SecretExt, Store,
+    MongoStore, Store,
 };
 use integrationos_event::{
     config::EventCoreConfig,
@@ -51,14 +51,7 @@ async fn main() -> Result<()> {
     let database = client.database(&config.db_config.event_db_name);
     let secrets_store = MongoStore::<Secret>::new(&database, &Store::Secrets).await?;
 
-    let secrets_client: Arc<dyn SecretExt + Sync + Send> = match config.secrets_config.provider {
-        SecretServiceProvider::GoogleKms => {
-            Arc::new(GoogleKms::new(&config.secrets_config, secrets_store).await?)
-        }
-        SecretServiceProvider::IosKms => {
-            Arc::new(IOSKms::new(&config.secrets_config, secrets_store).await?)
-        }
-    };
+    let secrets_client = secrets_client(&config.secrets_config, secrets_store).await?;
 
     let control_store = Arc::new(
         MongoControlDataStore::new(&config, secrets_client)
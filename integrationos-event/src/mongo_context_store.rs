@@ -1,7 +1,7 @@
 use crate::{config::EventCoreConfig, store::ContextStore};
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use bson::doc;
+use bson::{doc, Document};
 use integrationos_domain::{algebra::PipelineExt, id::Id};
 use mongodb::{Client, Database};
 use serde::{Deserialize, Serialize};
@@ -39,7 +39,10 @@ impl ContextStore for MongoContextStore {
 
     async fn set<T: PipelineExt + Clone + Serialize>(&self, context: T) -> Result<()> {
         let instant = Instant::now();
-        let coll = self.db.collection(&self.collection_name);
+        let coll = self.db.collection::<Document>(&self.collection_name);
+        // Serialized through the BSON serializer so that the decimals kept as extended JSON
+        // by the number precision policy are stored as `Decimal128`
+        let context = bson::to_document(&context)?;
         if let Err(e) = coll.insert_one(context, None).await {
             error!("PipelineExt insertion error {e}");
         }
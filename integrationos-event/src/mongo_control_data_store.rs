@@ -17,9 +17,13 @@ use integrationos_domain::{
     extractor::HttpExtractor,
     id::Id,
     middleware::Middleware,
+    number_precision::NumberPrecision,
     Connection, Event, Pipeline, SecretExt, Store,
 };
-use integrationos_unified::unified::{UnifiedCacheTTLs, UnifiedDestination};
+use integrationos_unified::{
+    rate_limit::RateLimitConfig,
+    unified::{UnifiedCacheTTLs, UnifiedDestination},
+};
 use moka::future::Cache;
 use mongodb::{options::ClientOptions, Client};
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
@@ -40,6 +44,8 @@ pub struct MongoControlDataStore {
     pub pipeline_cache: Cache<String, Pipeline>,
     pub token_fetcher: Option<GoogleTokenFetcher>,
     pub http_client: reqwest::Client,
+    /// Policy for the numbers of extractor responses that would lose precision once stored
+    pub number_precision: NumberPrecision,
     destination_caller: UnifiedDestination,
 }
 
@@ -108,18 +114,25 @@ impl MongoControlDataStore {
                 None
             },
             http_client: reqwest::Client::new(),
+            number_precision: config.db_config.json_number_precision,
             destination_caller: UnifiedDestination::new(
                 config.db_config.clone(),
                 config.cache_size,
                 secrets_client,
                 UnifiedCacheTTLs {
                     connection_cache_ttl_secs: config.connection_cache_ttl_secs,
+                    connection_definition_cache_ttl_secs: config
+                        .connection_definition_cache_ttl_secs,
                     connection_model_definition_cache_ttl_secs: config
                         .connection_model_definition_cache_ttl_secs,
                     connection_model_schema_cache_ttl_secs: config
                         .connection_model_schema_cache_ttl_secs,
                     secret_cache_ttl_secs: config.secret_cache_ttl_secs,
                 },
+                RateLimitConfig {
+                    enabled: config.rate_limit_enabled,
+                    window: Duration::from_secs(config.connection_rate_limit_refill_secs),
+                },
             )
             .await?,
         })
@@ -333,7 +346,7 @@ impl ControlDataStore for MongoControlDataStore {
                 let v = String::from_utf8(v.as_bytes().to_vec())?;
                 headers.insert(k, v);
             }
-            let response_body = response.json::<Value>().await?;
+            let response_body = self.number_precision.parse(&response.text().await?)?;
             return Ok(json!({
                 "headers": headers,
                 "data": response_body
@@ -82,6 +82,11 @@ pub async fn seed_db(config: &EventCoreConfig, base_url: String) -> Id {
         is_default_crud_mapping: None,
         mapping: None,
         supported: true,
+        shadow: None,
+        streaming: false,
+        timeout: None,
+        error_format: None,
+        pagination_limits: None,
     };
 
     db.collection("connection-model-definitions")
@@ -103,6 +108,7 @@ pub async fn seed_db(config: &EventCoreConfig, base_url: String) -> Id {
         group: "group".to_string(),
         platform: "platform".to_string().into(),
         environment: Environment::Live,
+        mode: Default::default(),
         secrets_service_id: "secrets_service_id".to_string(),
         event_access_id,
         access_key: "accessKey".to_string(),
@@ -113,6 +119,10 @@ pub async fn seed_db(config: &EventCoreConfig, base_url: String) -> Id {
         },
         ownership: Ownership::default(),
         oauth: None,
+        health: Default::default(),
+        webhook_secret: None,
+        credential_expiry: Default::default(),
+        credentials_rotated_at: None,
         record_metadata: RecordMetadata::default(),
     };
 
@@ -193,6 +203,10 @@ async fn test_send_to_destination() {
                 None,
             ))
         }
+
+        async fn ping(&self) -> Result<(), IntegrationOSError> {
+            Ok(())
+        }
     }
 
     let store = get_control_store(&config, Arc::new(SecretsClient)).await;
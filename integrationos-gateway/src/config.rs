@@ -1,11 +1,16 @@
+#[cfg(feature = "kafka")]
+use crate::kafka::KafkaConfig;
+use crate::timestamp::{TimestampBounds, TimestampPolicy};
 use envconfig::Envconfig;
 use integrationos_domain::{
     cache::CacheConfig,
+    secrets::SecretsConfig,
     {database::DatabaseConfig, environment::Environment},
 };
 use std::{
     fmt::{Display, Formatter},
     net::SocketAddr,
+    time::Duration,
 };
 
 #[derive(Envconfig, Clone)] // Intentionally no Debug so secret is not printed
@@ -22,12 +27,43 @@ pub struct Config {
     pub redis: CacheConfig,
     #[envconfig(nested = true)]
     pub db: DatabaseConfig,
+    /// Comma separated JSON pointers of the event body fields encrypted before the event is
+    /// stored, e.g. `/customer/email,/card`
+    #[envconfig(from = "ENCRYPTED_EVENT_FIELDS")]
+    pub encrypted_event_fields: Option<String>,
+    #[envconfig(nested = true)]
+    pub secrets_config: SecretsConfig,
+    /// Oldest an event may be, according to the timestamp read through the timestamp path of
+    /// its access key, unbounded when unset
+    #[envconfig(from = "MAX_EVENT_AGE_SECS")]
+    pub max_event_age_secs: Option<u64>,
+    /// Furthest in the future the timestamp of an event may be, unbounded when unset
+    #[envconfig(from = "MAX_EVENT_FUTURE_SKEW_SECS")]
+    pub max_event_future_skew_secs: Option<u64>,
+    /// Whether the events with an out of range timestamp are rejected or have it clamped
+    #[envconfig(from = "EVENT_TIMESTAMP_POLICY", default = "reject")]
+    pub event_timestamp_policy: TimestampPolicy,
+    /// TTL of the latest event schemas events are validated against, schemas registered
+    /// through the API apply to the events ingested here once it expires
+    #[envconfig(from = "EVENT_SCHEMA_CACHE_TTL_SECS", default = "60")]
+    pub event_schema_cache_ttl_secs: u64,
+    #[cfg(feature = "kafka")]
+    #[envconfig(nested = true)]
+    pub kafka: KafkaConfig,
 }
 
 impl Config {
     pub fn new() -> Self {
         Self::default()
     }
+
+    pub fn timestamp_bounds(&self) -> TimestampBounds {
+        TimestampBounds {
+            max_age: self.max_event_age_secs.map(Duration::from_secs),
+            max_future_skew: self.max_event_future_skew_secs.map(Duration::from_secs),
+            policy: self.event_timestamp_policy,
+        }
+    }
 }
 
 impl Display for Config {
@@ -37,7 +73,28 @@ impl Display for Config {
         writeln!(f, "SECRET: ****")?;
         writeln!(f, "ENVIRONMENT: {}", self.environment)?;
         writeln!(f, "{}", self.redis)?;
-        writeln!(f, "{}", self.db)
+        writeln!(f, "{}", self.db)?;
+        writeln!(
+            f,
+            "ENCRYPTED_EVENT_FIELDS: {:?}",
+            self.encrypted_event_fields
+        )?;
+        writeln!(f, "{}", self.secrets_config)?;
+        writeln!(f, "MAX_EVENT_AGE_SECS: {:?}", self.max_event_age_secs)?;
+        writeln!(
+            f,
+            "MAX_EVENT_FUTURE_SKEW_SECS: {:?}",
+            self.max_event_future_skew_secs
+        )?;
+        writeln!(f, "EVENT_TIMESTAMP_POLICY: {}", self.event_timestamp_policy)?;
+        write!(
+            f,
+            "EVENT_SCHEMA_CACHE_TTL_SECS: {}",
+            self.event_schema_cache_ttl_secs
+        )?;
+        #[cfg(feature = "kafka")]
+        write!(f, "\n{}", self.kafka)?;
+        Ok(())
     }
 }
 
@@ -50,6 +107,14 @@ impl Default for Config {
             environment: Environment::Test,
             redis: CacheConfig::default(),
             db: DatabaseConfig::default(),
+            encrypted_event_fields: None,
+            secrets_config: SecretsConfig::default(),
+            max_event_age_secs: None,
+            max_event_future_skew_secs: None,
+            event_timestamp_policy: TimestampPolicy::default(),
+            event_schema_cache_ttl_secs: 60,
+            #[cfg(feature = "kafka")]
+            kafka: KafkaConfig::default(),
         }
     }
 }
@@ -92,6 +157,17 @@ ENVIRONMENT: test
         display += "\n";
         display += &config.db.to_string();
         display += "\n";
+        display += "ENCRYPTED_EVENT_FIELDS: None\n";
+        display += &config.secrets_config.to_string();
+        display += "\nMAX_EVENT_AGE_SECS: None\n";
+        display += "MAX_EVENT_FUTURE_SKEW_SECS: None\n";
+        display += "EVENT_TIMESTAMP_POLICY: reject\n";
+        display += "EVENT_SCHEMA_CACHE_TTL_SECS: 60";
+        #[cfg(feature = "kafka")]
+        {
+            display += "\n";
+            display += &config.kafka.to_string();
+        }
 
         assert_eq!(config.to_string(), display);
     }
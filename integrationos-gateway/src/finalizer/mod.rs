@@ -7,7 +7,8 @@ use async_trait::async_trait;
 use integrationos_cache::remote::RedisCache;
 use integrationos_domain::{
     algebra::MongoStore, encrypted_access_key::EncryptedAccessKey,
-    event_with_context::EventWithContext, Event, RootContext, Store,
+    event_with_context::EventWithContext, field_encryption::FieldEncryption,
+    partition::EventPartitionKey, Event, IOSCrypto, RootContext, Store,
 };
 use mongodb::Collection;
 use redis::AsyncCommands;
@@ -19,6 +20,8 @@ pub struct Finalizer {
     redis: Arc<Mutex<RedisCache>>,
     context_collection: Collection<RootContext>,
     event_store: MongoStore<Event>,
+    event_partition_key: EventPartitionKey,
+    field_encryption: Option<FieldEncryption>,
     queue_name: String,
 }
 
@@ -44,10 +47,25 @@ impl Finalizer {
                     config.db.event_db_name
                 )
             })?;
+
+        let field_encryption = match &config.encrypted_event_fields {
+            Some(paths) => {
+                let crypto = IOSCrypto::new(config.secrets_config.clone())
+                    .with_context(|| "Could not create crypto client for event fields")?;
+                Some(FieldEncryption::new(
+                    FieldEncryption::parse_paths(paths),
+                    Arc::new(crypto),
+                ))
+            }
+            None => None,
+        };
+
         Ok(Self {
             redis: Arc::new(Mutex::new(redis)),
             context_collection,
             event_store,
+            event_partition_key: config.db.event_partition_key,
+            field_encryption,
             queue_name: config.redis.queue_name,
         })
     }
@@ -61,6 +79,12 @@ impl FinalizeEvent for Finalizer {
         _event_name: &str,
         _access_key: &EncryptedAccessKey,
     ) -> Result<String, anyhow::Error> {
+        let mut event = event.clone().with_partition_key(self.event_partition_key);
+        // Fields are encrypted before the event is either stored or queued
+        if let Some(field_encryption) = &self.field_encryption {
+            event = field_encryption.encrypt_event(event).await?;
+        }
+        let event = &event;
         match self.event_store.create_one(event).await {
             Err(e) => {
                 error!("Failed to save event: {e}");
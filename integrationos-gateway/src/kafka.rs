@@ -0,0 +1,409 @@
+use crate::server::{AppState, Server, HEADER_STR};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use axum::{body::Bytes, http::HeaderMap};
+use envconfig::Envconfig;
+use http::{HeaderName, HeaderValue};
+use integrationos_domain::encrypted_access_key::EncryptedAccessKey;
+use rdkafka::{
+    config::ClientConfig,
+    consumer::{CommitMode, Consumer, StreamConsumer},
+    message::{Headers, Message},
+    Offset, TopicPartitionList,
+};
+use std::{
+    fmt::{Display, Formatter},
+    sync::Arc,
+    time::Duration,
+};
+use tracing::{error, info, warn};
+
+#[derive(Envconfig, Debug, Clone)]
+pub struct KafkaConfig {
+    /// Comma separated list of the brokers, the consumer is disabled when unset
+    #[envconfig(from = "KAFKA_BROKERS")]
+    pub brokers: Option<String>,
+    #[envconfig(from = "KAFKA_TOPIC", default = "events")]
+    pub topic: String,
+    #[envconfig(from = "KAFKA_GROUP_ID", default = "integrationos-gateway")]
+    pub group_id: String,
+    /// Wait before retrying a message, or reading the topic, for the first time. It doubles on
+    /// every failure up to `KAFKA_RETRY_MAX_DELAY_MILLIS`.
+    #[envconfig(from = "KAFKA_RETRY_BASE_DELAY_MILLIS", default = "500")]
+    pub retry_base_delay_millis: u64,
+    #[envconfig(from = "KAFKA_RETRY_MAX_DELAY_MILLIS", default = "30000")]
+    pub retry_max_delay_millis: u64,
+}
+
+impl Default for KafkaConfig {
+    fn default() -> Self {
+        Self {
+            brokers: None,
+            topic: "events".to_owned(),
+            group_id: "integrationos-gateway".to_owned(),
+            retry_base_delay_millis: 500,
+            retry_max_delay_millis: 30_000,
+        }
+    }
+}
+
+impl Display for KafkaConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "KAFKA_BROKERS: {:?}", self.brokers)?;
+        writeln!(f, "KAFKA_TOPIC: {}", self.topic)?;
+        writeln!(f, "KAFKA_GROUP_ID: {}", self.group_id)?;
+        writeln!(
+            f,
+            "KAFKA_RETRY_BASE_DELAY_MILLIS: {}",
+            self.retry_base_delay_millis
+        )?;
+        write!(
+            f,
+            "KAFKA_RETRY_MAX_DELAY_MILLIS: {}",
+            self.retry_max_delay_millis
+        )
+    }
+}
+
+/// Message read from the events topic. The access key is taken from the `x-buildable-secret`
+/// header of the message, or from its key when the header is missing.
+#[derive(Debug, Clone)]
+pub struct KafkaMessage {
+    pub partition: i32,
+    pub offset: i64,
+    pub key: Option<Bytes>,
+    pub headers: HeaderMap,
+    pub payload: Bytes,
+}
+
+/// Reads the messages of the configured topic, as a consumer of the configured group
+#[async_trait]
+pub trait MessageSource {
+    /// Next message of the topic, `None` once the source is closed
+    async fn next_message(&mut self) -> Result<Option<KafkaMessage>>;
+
+    /// Commits the offset of `message`, so that it is not delivered to the group again
+    async fn commit(&mut self, message: &KafkaMessage) -> Result<()>;
+}
+
+/// [`MessageSource`] of a topic of a Kafka cluster, committing the offsets of the group
+/// explicitly instead of periodically so that a message is only committed once ingested
+pub struct KafkaSource {
+    consumer: StreamConsumer,
+    topic: String,
+}
+
+impl KafkaSource {
+    pub fn new(brokers: &str, config: &KafkaConfig) -> Result<Self> {
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("group.id", &config.group_id)
+            .set("enable.auto.commit", "false")
+            .create()
+            .map_err(|e| anyhow!("Could not create Kafka consumer: {e}"))?;
+        consumer
+            .subscribe(&[&config.topic])
+            .map_err(|e| anyhow!("Could not subscribe to topic {}: {e}", config.topic))?;
+
+        Ok(Self {
+            consumer,
+            topic: config.topic.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl MessageSource for KafkaSource {
+    async fn next_message(&mut self) -> Result<Option<KafkaMessage>> {
+        let message = self.consumer.recv().await?;
+
+        let mut headers = HeaderMap::new();
+        for header in message.headers().iter().flat_map(|headers| headers.iter()) {
+            let name = HeaderName::from_bytes(header.key.as_bytes());
+            let value = HeaderValue::from_bytes(header.value.unwrap_or_default());
+            if let (Ok(name), Ok(value)) = (name, value) {
+                headers.append(name, value);
+            }
+        }
+
+        Ok(Some(KafkaMessage {
+            partition: message.partition(),
+            offset: message.offset(),
+            key: message.key().map(Bytes::copy_from_slice),
+            headers,
+            payload: Bytes::copy_from_slice(message.payload().unwrap_or_default()),
+        }))
+    }
+
+    async fn commit(&mut self, message: &KafkaMessage) -> Result<()> {
+        // The committed offset is the one of the next message to read
+        let mut offsets = TopicPartitionList::new();
+        offsets.add_partition_offset(
+            &self.topic,
+            message.partition,
+            Offset::Offset(message.offset + 1),
+        )?;
+        self.consumer.commit(&offsets, CommitMode::Async)?;
+        Ok(())
+    }
+}
+
+/// Feeds the messages of a [`MessageSource`] into the same ingestion path as the events
+/// posted over HTTP
+pub struct KafkaConsumer<S> {
+    source: S,
+    state: Arc<AppState>,
+    retry_base_delay: Duration,
+    retry_max_delay: Duration,
+}
+
+impl<S: MessageSource + Send> KafkaConsumer<S> {
+    pub fn new(source: S, state: Arc<AppState>) -> Self {
+        let config = KafkaConfig::default();
+        Self {
+            source,
+            state,
+            retry_base_delay: Duration::from_millis(config.retry_base_delay_millis),
+            retry_max_delay: Duration::from_millis(config.retry_max_delay_millis),
+        }
+    }
+
+    pub fn with_retry_delays(mut self, base: Duration, max: Duration) -> Self {
+        self.retry_base_delay = base;
+        self.retry_max_delay = max;
+        self
+    }
+
+    /// Wait before the `attempt`-th retry, counted from 1
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.retry_base_delay
+            .saturating_mul(factor)
+            .min(self.retry_max_delay)
+    }
+
+    /// Consumes messages until the source is closed. The offset of a message is committed
+    /// once its event is finalized, or when the message can never be ingested. A message whose
+    /// event can't be finalized, e.g. while the buffer is unavailable, is retried with a
+    /// growing delay until it is, so that no message after it is committed before it.
+    pub async fn run(mut self) {
+        let mut attempt = 0;
+        loop {
+            let message = match self.source.next_message().await {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                Err(e) => {
+                    attempt += 1;
+                    let delay = self.retry_delay(attempt);
+                    error!("Could not read the next message: {e}, retrying in {delay:?}");
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+            };
+            attempt = 0;
+
+            while self.ingest(&message).await.is_err() {
+                attempt += 1;
+                let delay = self.retry_delay(attempt);
+                warn!(
+                    "Retrying message {}:{} in {delay:?}",
+                    message.partition, message.offset
+                );
+                tokio::time::sleep(delay).await;
+            }
+            attempt = 0;
+
+            // Offsets are committed up to a message, the next commit covers a failed one
+            if let Err(e) = self.source.commit(&message).await {
+                warn!(
+                    "Could not commit message {}:{}: {e}",
+                    message.partition, message.offset
+                );
+            }
+        }
+        info!("Kafka source closed, stopping the consumer");
+    }
+
+    async fn ingest(&self, message: &KafkaMessage) -> Result<()> {
+        let identifier = message
+            .headers
+            .get(HEADER_STR)
+            .map(|identifier| identifier.as_bytes())
+            .or(message.key.as_deref());
+        let Some(encrypted_access_key) = identifier
+            .and_then(|identifier| std::str::from_utf8(identifier).ok())
+            .and_then(|identifier| EncryptedAccessKey::parse(identifier).ok())
+        else {
+            warn!(
+                "Skipping message {}:{} without a valid access key",
+                message.partition, message.offset
+            );
+            return Ok(());
+        };
+
+        match Server::handle_event(
+            encrypted_access_key,
+            message.payload.clone(),
+            None,
+            message.headers.clone(),
+            self.state.clone(),
+        )
+        .await
+        {
+            Ok(_) => Ok(()),
+            Err((status, reason)) if status.is_client_error() => {
+                warn!(
+                    "Skipping message {}:{} that can't be ingested: {reason}",
+                    message.partition, message.offset
+                );
+                Ok(())
+            }
+            Err((_, reason)) => {
+                error!(
+                    "Failed to ingest message {}:{}: {reason}",
+                    message.partition, message.offset
+                );
+                Err(anyhow!(reason))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, finalizer::event::FinalizeEvent};
+    use integrationos_domain::Event;
+    use std::{
+        collections::VecDeque,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+    };
+
+    const VALID_SK_KEY: &str = "sk_test_1_Q71YUIZydcgSwJQNOUCHhaTMqmIvslIafF5LluORJfJKydMGELHtYe_ydtBIrVuomEnOZ4jfZQgtkqWxtG-s7vhbyir4kNjLyHKyDyh1SDubBMlhSI7Mq-M5RVtwnwFqZiOeUkIgHJFgcGQn0Plb1AkAAAAAAAAAAAAAAAAAAAAAAMwWY_9_oDOV75noniBViOVmVPUQqzcW8G3P8nuUD6Q";
+
+    /// In memory broker serving a single partition
+    #[derive(Default)]
+    struct MockBroker {
+        messages: VecDeque<KafkaMessage>,
+        committed: Arc<Mutex<Vec<i64>>>,
+    }
+
+    impl MockBroker {
+        fn publish(&mut self, key: &str, payload: &str) {
+            let offset = self.messages.len() as i64;
+            self.messages.push_back(KafkaMessage {
+                partition: 0,
+                offset,
+                key: Some(Bytes::copy_from_slice(key.as_bytes())),
+                headers: HeaderMap::new(),
+                payload: Bytes::copy_from_slice(payload.as_bytes()),
+            });
+        }
+    }
+
+    #[async_trait]
+    impl MessageSource for MockBroker {
+        async fn next_message(&mut self) -> Result<Option<KafkaMessage>> {
+            Ok(self.messages.pop_front())
+        }
+
+        async fn commit(&mut self, message: &KafkaMessage) -> Result<()> {
+            self.committed.lock().unwrap().push(message.offset);
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingFinalizer {
+        events: Arc<Mutex<Vec<String>>>,
+        /// Number of attempts failing before the events are finalized
+        failures: AtomicUsize,
+        attempts: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl FinalizeEvent for RecordingFinalizer {
+        async fn finalize_event(
+            &self,
+            _event: &Event,
+            event_name: &str,
+            _access_key: &EncryptedAccessKey,
+        ) -> Result<String> {
+            self.attempts.fetch_add(1, Ordering::SeqCst);
+            let failing =
+                self.failures
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |failures| {
+                        failures.checked_sub(1)
+                    });
+            if failing.is_ok() {
+                return Err(anyhow!("Buffer is unavailable"));
+            }
+            self.events.lock().unwrap().push(event_name.to_owned());
+            Ok("sent".to_owned())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_consumed_messages_are_ingested() {
+        let mut broker = MockBroker::default();
+        broker.publish(VALID_SK_KEY, r#"{"event": "first", "payload": {}}"#);
+        broker.publish(
+            "not-an-access-key",
+            r#"{"event": "invalid", "payload": {}}"#,
+        );
+        broker.publish(VALID_SK_KEY, r#"{"event": "second", "payload": {}}"#);
+        let committed = broker.committed.clone();
+
+        let finalizer = RecordingFinalizer::default();
+        let events = finalizer.events.clone();
+        let state = Arc::new(AppState::new(Config::default(), Arc::new(finalizer)));
+
+        KafkaConsumer::new(broker, state).run().await;
+
+        assert_eq!(*events.lock().unwrap(), ["first", "second"]);
+        assert_eq!(*committed.lock().unwrap(), [0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_message_is_retried_until_ingested_before_being_committed() {
+        let mut broker = MockBroker::default();
+        broker.publish(VALID_SK_KEY, r#"{"event": "first", "payload": {}}"#);
+        broker.publish(VALID_SK_KEY, r#"{"event": "second", "payload": {}}"#);
+        let committed = broker.committed.clone();
+
+        let finalizer = RecordingFinalizer {
+            failures: AtomicUsize::new(3),
+            ..Default::default()
+        };
+        let events = finalizer.events.clone();
+        let attempts = finalizer.attempts.clone();
+        let state = Arc::new(AppState::new(Config::default(), Arc::new(finalizer)));
+
+        KafkaConsumer::new(broker, state)
+            .with_retry_delays(Duration::from_millis(1), Duration::from_millis(2))
+            .run()
+            .await;
+
+        assert_eq!(attempts.load(Ordering::SeqCst), 5);
+        assert_eq!(*events.lock().unwrap(), ["first", "second"]);
+        assert_eq!(*committed.lock().unwrap(), [0, 1]);
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_the_maximum() {
+        let state = Arc::new(AppState::new(
+            Config::default(),
+            Arc::new(RecordingFinalizer::default()),
+        ));
+        let consumer = KafkaConsumer::new(MockBroker::default(), state)
+            .with_retry_delays(Duration::from_millis(100), Duration::from_millis(300));
+
+        assert_eq!(consumer.retry_delay(1), Duration::from_millis(100));
+        assert_eq!(consumer.retry_delay(2), Duration::from_millis(200));
+        assert_eq!(consumer.retry_delay(3), Duration::from_millis(300));
+        assert_eq!(consumer.retry_delay(40), Duration::from_millis(300));
+    }
+}
@@ -1,5 +1,8 @@
 pub mod config;
 pub mod finalizer;
+#[cfg(feature = "kafka")]
+pub mod kafka;
 pub mod mock;
 pub mod server;
+pub mod timestamp;
 pub mod util;
@@ -1,10 +1,15 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use dotenvy::dotenv;
 use envconfig::Envconfig;
+use integrationos_cache::local::event_schema_cache::EventSchemaCache;
 use integrationos_domain::encrypted_data::PASSWORD_LENGTH;
 use integrationos_domain::telemetry::{get_subscriber, init_subscriber};
+use integrationos_domain::{MongoStore, Store};
 use integrationos_gateway::finalizer::Finalizer;
-use integrationos_gateway::{config::Config, server::Server};
+use integrationos_gateway::{
+    config::Config,
+    server::{EventSchemas, Server},
+};
 use tracing::info;
 
 #[tokio::main]
@@ -30,7 +35,36 @@ async fn main() -> Result<()> {
 
     let finalizer = Finalizer::new(config.clone()).await?;
 
-    let server = Server::new(config, finalizer);
+    let control_db = mongodb::Client::with_uri_str(&config.db.control_db_url)
+        .await
+        .with_context(|| "Could not connect to control mongodb")?
+        .database(&config.db.control_db_name);
+    let event_schemas = EventSchemas {
+        store: MongoStore::new(&control_db, &Store::EventSchemas).await?,
+        cache: EventSchemaCache::new(config.cache_size, config.event_schema_cache_ttl_secs),
+    };
+
+    let server = Server::new(config.clone(), finalizer).with_event_schemas(event_schemas);
+
+    #[cfg(feature = "kafka")]
+    if let Some(brokers) = &config.kafka.brokers {
+        use integrationos_gateway::kafka::{KafkaConsumer, KafkaSource};
+        use std::time::Duration;
+
+        info!(
+            "Consuming events of topic {} from {brokers}",
+            config.kafka.topic
+        );
+        let consumer = KafkaConsumer::new(
+            KafkaSource::new(brokers, &config.kafka)?,
+            server.app_state(),
+        )
+        .with_retry_delays(
+            Duration::from_millis(config.kafka.retry_base_delay_millis),
+            Duration::from_millis(config.kafka.retry_max_delay_millis),
+        );
+        tokio::spawn(consumer.run());
+    }
 
     server.run().await?;
 
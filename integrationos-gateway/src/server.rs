@@ -1,6 +1,6 @@
 use crate::{
     config::Config, finalizer::event::FinalizeEvent, mock::finalizer::MockFinalizer,
-    util::get_value_from_path,
+    timestamp::parse_timestamp, util::get_value_from_path,
 };
 use anyhow::{anyhow, Result};
 use axum::{
@@ -12,9 +12,11 @@ use axum::{
     Json, Router,
 };
 use axum_prometheus::PrometheusMetricLayer;
+use integrationos_cache::local::event_schema_cache::EventSchemaCache;
 use integrationos_domain::{
     encrypted_access_key::EncryptedAccessKey, encrypted_data::PASSWORD_LENGTH,
-    event_response::EventResponse, event_type::EventType, AccessKey, Event,
+    event_access::EventAccessScope, event_response::EventResponse, event_schema::EventSchema,
+    event_type::EventType, AccessKey, Event, MongoStore,
 };
 use moka::future::Cache;
 use serde::{Deserialize, Serialize};
@@ -28,16 +30,42 @@ use tower_http::{
 };
 use tracing::{error, info, warn};
 
-const HEADER_STR: &str = "x-buildable-secret";
+pub(crate) const HEADER_STR: &str = "x-buildable-secret";
 const INVALID_ACCESS_KEY_ERROR: (StatusCode, &str) =
     (StatusCode::BAD_REQUEST, "Invalid access key");
 const MISSING_HEADER_ERROR: (StatusCode, &str) =
     (StatusCode::BAD_REQUEST, "Missing x-buildable-secret header");
+const MISSING_INGEST_SCOPE_ERROR: (StatusCode, &str) = (
+    StatusCode::FORBIDDEN,
+    "Access key is not allowed to ingest events",
+);
+const OUT_OF_RANGE_TIMESTAMP_ERROR: (StatusCode, &str) = (
+    StatusCode::BAD_REQUEST,
+    "Event timestamp is out of the accepted range",
+);
+const INVALID_PAYLOAD_ERROR: (StatusCode, &str) = (
+    StatusCode::UNPROCESSABLE_ENTITY,
+    "Payload does not satisfy the schema of the event",
+);
+const SCHEMA_UNAVAILABLE_ERROR: (StatusCode, &str) = (
+    StatusCode::INTERNAL_SERVER_ERROR,
+    "Failed to read the schema of the event",
+);
+
+/// Schemas registered for the event types, the latest of which the payloads of the events
+/// are validated against
+#[derive(Clone)]
+pub struct EventSchemas {
+    pub store: MongoStore<EventSchema>,
+    pub cache: EventSchemaCache,
+}
 
 pub struct AppState {
     pub config: Config,
     pub cache: Cache<EncryptedAccessKey<'static>, AccessKey>,
     pub finalizer: Arc<dyn FinalizeEvent + Sync + Send>,
+    /// Events are not validated when unset
+    pub event_schemas: Option<EventSchemas>,
 }
 
 impl AppState {
@@ -47,9 +75,15 @@ impl AppState {
             config,
             cache,
             finalizer,
+            event_schemas: None,
         }
     }
 
+    pub fn with_event_schemas(mut self, event_schemas: Option<EventSchemas>) -> Self {
+        self.event_schemas = event_schemas;
+        self
+    }
+
     pub fn get_secret_key(&self) -> [u8; PASSWORD_LENGTH] {
         // We validate that the config must have 32 byte secret key in main.rs
         // So this is safe to unwrap
@@ -67,6 +101,7 @@ pub struct EventRequest {
 pub struct Server {
     config: Config,
     finalizer: Arc<dyn FinalizeEvent + Sync + Send>,
+    event_schemas: Option<EventSchemas>,
 }
 
 impl Default for Server {
@@ -74,6 +109,7 @@ impl Default for Server {
         Self {
             config: Config::default(),
             finalizer: Arc::new(MockFinalizer),
+            event_schemas: None,
         }
     }
 }
@@ -83,9 +119,16 @@ impl Server {
         Self {
             config,
             finalizer: Arc::new(finalizer),
+            event_schemas: None,
         }
     }
 
+    /// Validates the events against the latest schemas registered for their types
+    pub fn with_event_schemas(mut self, event_schemas: EventSchemas) -> Self {
+        self.event_schemas = Some(event_schemas);
+        self
+    }
+
     pub async fn run(&self) -> Result<()> {
         let app = self.get_router();
         info!("Gateway server listening on {}", self.config.address);
@@ -99,8 +142,16 @@ impl Server {
             .map_err(|e| anyhow!("Server error: {}", e))
     }
 
+    /// State of the ingestion of the events, shared by the sources of the events of the server
+    pub fn app_state(&self) -> Arc<AppState> {
+        Arc::new(
+            AppState::new(self.config.clone(), self.finalizer.clone())
+                .with_event_schemas(self.event_schemas.clone()),
+        )
+    }
+
     fn get_router(&self) -> Router {
-        let state = Arc::new(AppState::new(self.config.clone(), self.finalizer.clone()));
+        let state = self.app_state();
         let mut router = Router::new()
             .route("/emit", post(post_event_sk))
             .route("/emit/:id", post(post_event_id))
@@ -149,6 +200,20 @@ impl Server {
             return Err(INVALID_ACCESS_KEY_ERROR);
         };
 
+        if !access_key.data.has_scope(EventAccessScope::Ingest) {
+            warn!("Identifier lacks the ingest scope");
+            return Err(MISSING_INGEST_SCOPE_ERROR);
+        }
+
+        let occurred_at = access_key
+            .data
+            .timestamp_path
+            .as_ref()
+            .and_then(|path| {
+                get_value_from_path(&mut path.clone(), &headers, &payload, &query).ok()
+            })
+            .and_then(|timestamp| parse_timestamp(&timestamp));
+
         let (name, payload) = if access_key.prefix.event_type == EventType::SecretKey {
             let payload = match serde_json::from_slice::<EventRequest>(&payload) {
                 Ok(payload) => payload,
@@ -176,7 +241,35 @@ impl Server {
             (name, payload)
         };
 
-        let event = Event::new(&access_key, &encrypted_access_key, &name, headers, payload);
+        let mut event = Event::new(&access_key, &encrypted_access_key, &name, headers, payload);
+        if let Some(occurred_at) = occurred_at {
+            match state
+                .config
+                .timestamp_bounds()
+                .bound(occurred_at, event.arrived_at)
+            {
+                Some(occurred_at) => event = event.with_occurred_at(occurred_at),
+                None => {
+                    warn!("Event timestamp {occurred_at} is out of the accepted range");
+                    return Err(OUT_OF_RANGE_TIMESTAMP_ERROR);
+                }
+            }
+        }
+
+        if let Some(event_schemas) = &state.event_schemas {
+            let schema = event_schemas
+                .cache
+                .get_or_insert_latest(&event_schemas.store, &event.ownership.id, &event.name)
+                .await
+                .map_err(|e| {
+                    error!("Could not read the schema of {}: {e}", event.name);
+                    SCHEMA_UNAVAILABLE_ERROR
+                })?;
+            if let Some(Err(e)) = schema.map(|schema| schema.validate_event(&event)) {
+                warn!("{e}");
+                return Err(INVALID_PAYLOAD_ERROR);
+            }
+        }
 
         match state
             .finalizer
@@ -262,12 +355,16 @@ mod tests {
     };
     use http_body_util::BodyExt;
     use integrationos_domain::{
+        access_key_data::AccessKeyData,
+        access_key_prefix::AccessKeyPrefix,
+        encrypted_data::IV_LENGTH,
         event_state::EventState,
         hashes::{HashType, HashValue},
     };
     use tower::ServiceExt;
 
     use super::*;
+    use crate::timestamp::TimestampPolicy;
 
     const VALID_ID_KEY: &str = "id_test_1_Q71YUIZydcgSwJQNOUCHhaTMqmIvslIafF5LluORJfJKydMGELHtYe_ydtBIrVuomEnOZ4jfZQgtkqWxtG-s7vhbyir4kNjLyHKyDyh1SDubBMlhSI7Mq-M5RVtwnwFqZiOeUkIgHJFgcGQn0Plb1AkAAAAAAAAAAAAAAAAAAAAAAMwWY_9_oDOV75noniBViOVmVPUQqzcW8G3P8nuUD6Q";
     const VALID_SK_KEY: &str = "sk_test_1_Q71YUIZydcgSwJQNOUCHhaTMqmIvslIafF5LluORJfJKydMGELHtYe_ydtBIrVuomEnOZ4jfZQgtkqWxtG-s7vhbyir4kNjLyHKyDyh1SDubBMlhSI7Mq-M5RVtwnwFqZiOeUkIgHJFgcGQn0Plb1AkAAAAAAAAAAAAAAAAAAAAAAMwWY_9_oDOV75noniBViOVmVPUQqzcW8G3P8nuUD6Q";
@@ -394,6 +491,146 @@ mod tests {
         assert_eq!(body, "Invalid access key");
     }
 
+    #[tokio::test]
+    async fn test_emit_requires_ingest_scope() {
+        let server = Server::default();
+
+        let emit = |scopes: &[EventAccessScope]| {
+            let access_key = AccessKey {
+                prefix: AccessKeyPrefix {
+                    environment: server.config.environment,
+                    event_type: EventType::SecretKey,
+                    version: 1,
+                },
+                data: AccessKeyData {
+                    id: "build-2e76c839f5fd419db6b34682f4cdff1e".to_owned(),
+                    namespace: "default".to_owned(),
+                    event_type: "custom".to_owned(),
+                    group: "group".to_owned(),
+                    event_path: "$.body.event".to_owned(),
+                    scopes: scopes.iter().map(ToString::to_string).collect(),
+                    ..Default::default()
+                },
+            };
+            let password = server.config.secret_key.as_bytes().try_into().unwrap();
+            let key = access_key.encode(password, &[0u8; IV_LENGTH]).unwrap();
+
+            server.get_router().oneshot(
+                Request::builder()
+                    .uri("/emit")
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(HEADER_STR, key.to_string())
+                    .method(Method::POST)
+                    .body(Body::from("{\"event\": \"foo\", \"payload\": \"bar\"}"))
+                    .unwrap(),
+            )
+        };
+
+        let (status, message) = MISSING_INGEST_SCOPE_ERROR;
+        let response = emit(&[EventAccessScope::Read]).await.unwrap();
+        assert_eq!(response.status(), status);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, message);
+
+        let response = emit(&[EventAccessScope::Ingest]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Keys predating scopes hold none and keep ingesting
+        let response = emit(&[]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    /// Keeps the events it finalizes
+    #[derive(Clone, Default)]
+    struct RecordingFinalizer(Arc<std::sync::Mutex<Vec<Event>>>);
+
+    #[async_trait::async_trait]
+    impl FinalizeEvent for RecordingFinalizer {
+        async fn finalize_event(
+            &self,
+            event: &Event,
+            _event_name: &str,
+            _access_key: &EncryptedAccessKey,
+        ) -> Result<String, anyhow::Error> {
+            self.0.lock().unwrap().push(event.clone());
+            Ok("sent".to_owned())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_out_of_range_timestamps_follow_configured_policy() {
+        let emit = |policy: TimestampPolicy, sent_at: i64| async move {
+            let config = Config {
+                max_event_age_secs: Some(3600),
+                max_event_future_skew_secs: Some(60),
+                event_timestamp_policy: policy,
+                ..Default::default()
+            };
+            let access_key = AccessKey {
+                prefix: AccessKeyPrefix {
+                    environment: config.environment,
+                    event_type: EventType::SecretKey,
+                    version: 1,
+                },
+                data: AccessKeyData {
+                    id: "build-2e76c839f5fd419db6b34682f4cdff1e".to_owned(),
+                    namespace: "default".to_owned(),
+                    event_type: "custom".to_owned(),
+                    group: "group".to_owned(),
+                    event_path: "$.body.event".to_owned(),
+                    timestamp_path: Some("_.body.sentAt".to_owned()),
+                    ..Default::default()
+                },
+            };
+            let password = config.secret_key.as_bytes().try_into().unwrap();
+            let key = access_key.encode(password, &[0u8; IV_LENGTH]).unwrap();
+
+            let finalizer = RecordingFinalizer::default();
+            let response = Server::new(config, finalizer.clone())
+                .get_router()
+                .oneshot(
+                    Request::builder()
+                        .uri("/emit")
+                        .header(CONTENT_TYPE, "application/json")
+                        .header(HEADER_STR, key.to_string())
+                        .method(Method::POST)
+                        .body(Body::from(format!(
+                            "{{\"event\": \"foo\", \"payload\": \"bar\", \"sentAt\": {sent_at}}}"
+                        )))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            let event = finalizer.0.lock().unwrap().pop();
+            (response, event)
+        };
+        let now = chrono::Utc::now();
+        let too_old = (now - chrono::Duration::days(2)).timestamp_millis();
+
+        let (response, event) = emit(TimestampPolicy::Reject, too_old).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "Event timestamp is out of the accepted range");
+        assert!(event.is_none());
+
+        let (response, event) = emit(TimestampPolicy::Clamp, too_old).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let event = event.unwrap();
+        assert_eq!(
+            event.occurred_at,
+            Some(event.arrived_at - chrono::Duration::hours(1))
+        );
+
+        let in_range = (now - chrono::Duration::minutes(5)).timestamp();
+        let (response, event) = emit(TimestampPolicy::Reject, in_range).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            event.unwrap().occurred_at.map(|at| at.timestamp()),
+            Some(in_range)
+        );
+    }
+
     #[tokio::test]
     async fn test_root_returns_ok() {
         let router = Server::default().get_router();
@@ -0,0 +1,121 @@
+use chrono::{DateTime, TimeZone, Utc};
+use std::time::Duration;
+use strum::{AsRefStr, Display, EnumString};
+
+/// Timestamps above this are read as milliseconds rather than seconds since the epoch
+const MILLIS_THRESHOLD: i64 = 100_000_000_000;
+
+/// What is done with the events whose timestamp is out of the accepted range
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, EnumString, AsRefStr, Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum TimestampPolicy {
+    /// The event is refused with a bad request
+    #[default]
+    Reject,
+    /// The event is accepted with its timestamp moved to the nearest bound
+    Clamp,
+}
+
+/// Range of the timestamps accepted at ingestion, relative to the time the event arrives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimestampBounds {
+    pub max_age: Option<Duration>,
+    pub max_future_skew: Option<Duration>,
+    pub policy: TimestampPolicy,
+}
+
+impl TimestampBounds {
+    /// Timestamp stored for an event sent at `timestamp` arriving at `now`, none when the
+    /// policy rejects it
+    pub fn bound(&self, timestamp: DateTime<Utc>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let oldest = self
+            .max_age
+            .and_then(|max_age| chrono::Duration::from_std(max_age).ok())
+            .map(|max_age| now - max_age);
+        let latest = self
+            .max_future_skew
+            .and_then(|skew| chrono::Duration::from_std(skew).ok())
+            .map(|skew| now + skew);
+
+        let bounded = match (oldest, latest) {
+            (Some(oldest), _) if timestamp < oldest => oldest,
+            (_, Some(latest)) if timestamp > latest => latest,
+            _ => return Some(timestamp),
+        };
+
+        match self.policy {
+            TimestampPolicy::Reject => None,
+            TimestampPolicy::Clamp => Some(bounded),
+        }
+    }
+}
+
+/// Reads a timestamp sent by a client, either an RFC 3339 date or a number of seconds or
+/// milliseconds since the epoch
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim().trim_matches('"');
+
+    if let Ok(number) = value.parse::<i64>() {
+        return if number.abs() >= MILLIS_THRESHOLD {
+            Utc.timestamp_millis_opt(number).single()
+        } else {
+            Utc.timestamp_opt(number, 0).single()
+        };
+    }
+
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamps_are_parsed_from_dates_and_epochs() {
+        let expected = Utc.with_ymd_and_hms(2023, 7, 18, 18, 12, 47).unwrap();
+
+        assert_eq!(parse_timestamp("1689703967"), Some(expected));
+        assert_eq!(parse_timestamp("1689703967000"), Some(expected));
+        assert_eq!(
+            parse_timestamp("\"2023-07-18T20:12:47+02:00\""),
+            Some(expected)
+        );
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn test_out_of_range_timestamps_follow_policy() {
+        let now = Utc::now();
+        let mut bounds = TimestampBounds {
+            max_age: Some(Duration::from_secs(3600)),
+            max_future_skew: Some(Duration::from_secs(60)),
+            policy: TimestampPolicy::Reject,
+        };
+        let in_range = now - chrono::Duration::minutes(30);
+        let too_old = now - chrono::Duration::days(2);
+        let too_new = now + chrono::Duration::minutes(5);
+
+        assert_eq!(bounds.bound(in_range, now), Some(in_range));
+        assert_eq!(bounds.bound(too_old, now), None);
+        assert_eq!(bounds.bound(too_new, now), None);
+
+        bounds.policy = TimestampPolicy::Clamp;
+        assert_eq!(bounds.bound(in_range, now), Some(in_range));
+        assert_eq!(
+            bounds.bound(too_old, now),
+            Some(now - chrono::Duration::hours(1))
+        );
+        assert_eq!(
+            bounds.bound(too_new, now),
+            Some(now + chrono::Duration::minutes(1))
+        );
+
+        // Unset bounds accept any timestamp
+        bounds.max_age = None;
+        bounds.policy = TimestampPolicy::Reject;
+        assert_eq!(bounds.bound(too_old, now), Some(too_old));
+        assert_eq!(bounds.bound(too_new, now), None);
+    }
+}
@@ -7,15 +7,19 @@ use integrationos_domain::{
     AuthorizationType, IntegrationOSError, InternalError, Nonce, OAuthData, SignableRequest,
     SignatureMethod, SigningKey,
 };
-use reqwest::{Client, Response, Url};
+use reqwest::{Body, Client, Response, Url};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
+
+/// Subtype of the errors of the requests cancelled by the timeout of their client
+pub const CALL_TIMEOUT: &str = "call_timeout";
 
 #[derive(Debug, Clone)]
 pub struct CallerClient<'a> {
     config: &'a ApiModelConfig,
     action: http::Method,
     client: &'a Client,
+    timeout: Option<Duration>,
 }
 
 impl<'a> CallerClient<'a> {
@@ -24,12 +28,20 @@ impl<'a> CallerClient<'a> {
             config,
             action,
             client,
+            timeout: None,
         }
     }
 
+    /// Cancels the request, including the read of its response, once it takes longer than
+    /// `timeout`
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
     pub async fn make_request(
         &self,
-        payload: Option<Vec<u8>>,
+        payload: Option<Body>,
         secret: Option<&Value>,
         headers: Option<HeaderMap>,
         query_params: Option<&HashMap<String, String>>,
@@ -68,6 +80,10 @@ impl<'a> CallerClient<'a> {
             request_builder = request_builder.body(payload);
         }
 
+        if let Some(timeout) = self.timeout {
+            request_builder = request_builder.timeout(timeout);
+        }
+
         request_builder = match &self.config.auth_method {
             AuthMethod::BearerToken { value } => request_builder.bearer_auth(value),
             AuthMethod::ApiKey { key, value } => request_builder.header(key, value),
@@ -155,6 +171,12 @@ impl<'a> CallerClient<'a> {
         };
 
         let res = request_builder.send().await.map_err(|e| {
+            if e.is_timeout() {
+                return InternalError::timeout(
+                    &format!("Request to {endpoint} timed out: {e}"),
+                    Some(CALL_TIMEOUT),
+                );
+            }
             InternalError::io_err(
                 &format!("Failed to send request: {}", e),
                 Some("reqwest::Error"),
@@ -236,6 +258,11 @@ mod tests {
             is_default_crud_mapping: None,
             mapping: None,
             supported: true,
+            shadow: None,
+            streaming: false,
+            timeout: None,
+            error_format: None,
+            pagination_limits: None,
         };
 
         let client = Client::new();
@@ -311,6 +338,11 @@ mod tests {
             is_default_crud_mapping: None,
             mapping: None,
             supported: true,
+            shadow: None,
+            streaming: false,
+            timeout: None,
+            error_format: None,
+            pagination_limits: None,
         };
 
         let client = Client::new();
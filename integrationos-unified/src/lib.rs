@@ -1,4 +1,8 @@
 pub mod client;
+pub mod oauth_refresh;
+pub mod pagination;
+pub mod rate_limit;
 pub mod request;
+pub mod shadow;
 pub mod unified;
 pub mod utility;
@@ -0,0 +1,546 @@
+use async_trait::async_trait;
+use bson::doc;
+use chrono::{DateTime, Duration, Utc};
+use http::{HeaderMap, HeaderName, HeaderValue};
+use integrationos_domain::{
+    algebra::{DefaultTemplate, TemplateExt},
+    api_model_config::ContentType,
+    connection_oauth_definition::{Computation, ConnectionOAuthDefinition, OAuthResponse},
+    oauth_secret::OAuthSecret,
+    ApplicationError, Connection, ErrorMeta, IntegrationOSError, InternalError, MongoStore, OAuth,
+    SecretExt,
+};
+use moka::future::Cache;
+use serde_json::Value;
+use std::{collections::BTreeMap, str::FromStr, sync::Arc};
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+/// Time before the expiry of an access token from which it is refreshed before a call
+pub const DEFAULT_REFRESH_SKEW_SECS: i64 = 60;
+
+/// Margin the expiry of a refreshed access token is recorded with, the same one as the
+/// connections authorized through OAuth
+const EXPIRY_MARGIN_SECS: i64 = 120;
+
+/// Field of a secret holding the access token of an OAuth connection
+const ACCESS_TOKEN_FIELD: &str = "OAUTH_ACCESS_TOKEN";
+
+/// Secret of a connection holding a new access token
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefreshedToken {
+    pub secret: Value,
+    /// Unix timestamp, in seconds, from which the access token must no longer be used
+    pub expires_at: Option<i64>,
+}
+
+impl RefreshedToken {
+    /// Whether the token differs from the one of `secret`, which callers may have extended with
+    /// other fields before the call
+    fn replaces(&self, secret: &Value) -> bool {
+        self.secret.get(ACCESS_TOKEN_FIELD) != secret.get(ACCESS_TOKEN_FIELD)
+    }
+
+    fn is_fresh(&self, now: DateTime<Utc>, skew: Duration) -> bool {
+        !expires_within(self.expires_at, now, skew)
+    }
+}
+
+fn expires_within(expires_at: Option<i64>, now: DateTime<Utc>, skew: Duration) -> bool {
+    expires_at.is_some_and(|expires_at| expires_at <= (now + skew).timestamp())
+}
+
+/// Obtains a new access token for a connection and persists it, so that the following calls
+/// are made with it
+#[async_trait]
+pub trait TokenRefreshExt: Send + Sync {
+    async fn refresh(
+        &self,
+        connection: &Connection,
+        secret: &Value,
+    ) -> Result<RefreshedToken, IntegrationOSError>;
+}
+
+/// Refreshes access tokens through the refresh settings of the [`ConnectionOAuthDefinition`]
+/// of the connection, storing them in a new secret the connection is pointed at
+pub struct OAuthDefinitionRefresh {
+    pub oauth_definitions_store: MongoStore<ConnectionOAuthDefinition>,
+    pub connections_store: MongoStore<Connection>,
+    pub secrets_client: Arc<dyn SecretExt + Sync + Send>,
+    pub http_client: reqwest::Client,
+    pub template: DefaultTemplate,
+}
+
+#[async_trait]
+impl TokenRefreshExt for OAuthDefinitionRefresh {
+    async fn refresh(
+        &self,
+        connection: &Connection,
+        secret: &Value,
+    ) -> Result<RefreshedToken, IntegrationOSError> {
+        let Some(OAuth::Enabled {
+            connection_oauth_definition_id,
+            ..
+        }) = &connection.oauth
+        else {
+            return Err(ApplicationError::bad_request(
+                "Connection is not authorized through OAuth",
+                None,
+            ));
+        };
+
+        let definition = self
+            .oauth_definitions_store
+            .get_one_by_id(&connection_oauth_definition_id.to_string())
+            .await?
+            .ok_or_else(|| ApplicationError::not_found("Connection OAuth definition", None))?;
+        let current = serde_json::from_value::<OAuthSecret>(secret.clone()).map_err(|e| {
+            InternalError::deserialize_error(&format!("Invalid OAuth secret: {e}"), None)
+        })?;
+
+        let request = self.request(&definition, &current.as_json())?;
+        let response =
+            self.http_client.execute(request).await.map_err(|e| {
+                InternalError::io_err(&format!("Failed to refresh token: {e}"), None)
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ApplicationError::failed_dependency(
+                &format!("Token refresh endpoint answered with {status}"),
+                None,
+            ));
+        }
+        let response = response.json::<Value>().await.map_err(|e| {
+            InternalError::deserialize_error(&format!("Invalid refresh response: {e}"), None)
+        })?;
+        let decoded: OAuthResponse = definition
+            .compute
+            .refresh
+            .response
+            .compute(&response)
+            .map_err(|e| InternalError::script_error(e.message().as_ref(), None))?;
+
+        let refreshed = current.from_refresh(decoded, None, None, response);
+        let created = self
+            .secrets_client
+            .create(&refreshed.as_json(), &connection.ownership.id)
+            .await?;
+
+        let now = Utc::now();
+        let expires_at = (now + Duration::seconds(refreshed.expires_in as i64)
+            - Duration::seconds(EXPIRY_MARGIN_SECS))
+        .max(now)
+        .timestamp();
+        let mut record_metadata = connection.record_metadata.clone();
+        record_metadata.mark_updated("system");
+        let mut update = bson::to_document(&record_metadata)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), None))?;
+        update.insert("secretsServiceId", created.id());
+        update.insert("oauth.enabled.expires_in", refreshed.expires_in);
+        update.insert("oauth.enabled.expires_at", expires_at);
+        self.connections_store
+            .update_one(&connection.id.to_string(), doc! { "$set": update })
+            .await?;
+        info!("Refreshed the access token of connection {}", connection.id);
+
+        Ok(RefreshedToken {
+            secret: refreshed.as_json(),
+            expires_at: Some(expires_at),
+        })
+    }
+}
+
+impl OAuthDefinitionRefresh {
+    /// Request to the refresh endpoint of the definition. The body is the one computed by the
+    /// definition, the current secret if it computes none.
+    fn request(
+        &self,
+        definition: &ConnectionOAuthDefinition,
+        payload: &Value,
+    ) -> Result<reqwest::Request, IntegrationOSError> {
+        let config = &definition.configuration.refresh;
+        let computation = definition
+            .compute
+            .refresh
+            .computation
+            .as_ref()
+            .map(|computation| computation.compute::<Computation>(payload))
+            .transpose()
+            .map_err(|e| InternalError::script_error(e.message().as_ref(), None))?;
+        let computation = computation.as_ref();
+
+        let headers =
+            config.headers.iter().flatten().filter_map(|(key, value)| {
+                Some((key.to_string(), value.to_str().ok()?.to_string()))
+            });
+        let headers = self
+            .render(headers, computation.and_then(|c| c.headers.as_ref()))?
+            .into_iter()
+            .try_fold(HeaderMap::new(), |mut headers, (key, value)| {
+                let key = HeaderName::from_str(&key)
+                    .map_err(|e| InternalError::invalid_argument(&e.to_string(), None))?;
+                let value = HeaderValue::from_str(&value)
+                    .map_err(|e| InternalError::invalid_argument(&e.to_string(), None))?;
+                headers.insert(key, value);
+                Ok::<_, IntegrationOSError>(headers)
+            })?;
+        let query = self.render(
+            config.query_params.clone().into_iter().flatten(),
+            computation.and_then(|c| c.query_params.as_ref()),
+        )?;
+        let body = computation.and_then(|c| c.body.as_ref()).unwrap_or(payload);
+
+        let request = self
+            .http_client
+            .post(config.uri())
+            .headers(headers)
+            .query(&query);
+        let request = match config.content {
+            Some(ContentType::Form) => request.form(body),
+            _ => request.json(body),
+        };
+
+        request
+            .build()
+            .map_err(|e| InternalError::unknown(&e.to_string(), None))
+    }
+
+    fn render(
+        &self,
+        entries: impl Iterator<Item = (String, String)>,
+        data: Option<&Value>,
+    ) -> Result<BTreeMap<String, String>, IntegrationOSError> {
+        entries
+            .map(|(key, value)| Ok((key, self.template.render(&value, data)?)))
+            .collect()
+    }
+}
+
+/// Refreshes the access tokens of OAuth connections before they expire. Concurrent refreshes of
+/// a connection are coalesced: the first caller refreshes the token while the others wait for
+/// it and reuse the new one.
+#[derive(Clone)]
+pub struct OAuthTokenRefresher {
+    refresh: Arc<dyn TokenRefreshExt>,
+    /// Tokens expiring within the skew are refreshed before a call
+    pub skew: Duration,
+    /// Guards of the refreshes in flight, keyed by connection key like the connections cache
+    in_flight: Cache<Arc<str>, Arc<Mutex<()>>>,
+    /// Latest token refreshed for each connection, more recent than the cached connections
+    refreshed: Cache<Arc<str>, RefreshedToken>,
+}
+
+impl OAuthTokenRefresher {
+    pub fn new(refresh: Arc<dyn TokenRefreshExt>, skew: Duration, cache_size: u64) -> Self {
+        Self {
+            refresh,
+            skew,
+            in_flight: Cache::new(cache_size),
+            refreshed: Cache::new(cache_size),
+        }
+    }
+
+    /// Secret to call the platform of `connection` with: `secret` if the connection isn't
+    /// authorized through OAuth or its token is fresh, one with a refreshed token otherwise.
+    /// A token that didn't expire yet is still used if it can't be refreshed.
+    pub async fn fresh_secret(
+        &self,
+        connection: &Connection,
+        secret: Value,
+        now: DateTime<Utc>,
+    ) -> Result<Value, IntegrationOSError> {
+        let Some(OAuth::Enabled { expires_at, .. }) = &connection.oauth else {
+            return Ok(secret);
+        };
+
+        if let Some(refreshed) = self.refreshed.get(&connection.key).await {
+            if refreshed.is_fresh(now, self.skew) {
+                return Ok(refreshed.secret);
+            }
+        } else if !expires_within(*expires_at, now, self.skew) {
+            return Ok(secret);
+        }
+
+        match self.refresh(connection, &secret, now).await {
+            Ok(refreshed) => Ok(refreshed),
+            Err(e) if !expires_within(*expires_at, now, Duration::zero()) => {
+                warn!(
+                    "Could not refresh the access token of connection {} before its expiry: {e}",
+                    connection.id
+                );
+                Ok(secret)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Secret with a new token for `connection`, whose platform rejected `secret`
+    pub async fn refresh_rejected(
+        &self,
+        connection: &Connection,
+        secret: &Value,
+        now: DateTime<Utc>,
+    ) -> Result<Value, IntegrationOSError> {
+        self.refresh(connection, secret, now).await
+    }
+
+    async fn refresh(
+        &self,
+        connection: &Connection,
+        stale: &Value,
+        now: DateTime<Utc>,
+    ) -> Result<Value, IntegrationOSError> {
+        let guard = self
+            .in_flight
+            .get_with(connection.key.clone(), async { Arc::new(Mutex::new(())) })
+            .await;
+        let _guard = guard.lock().await;
+
+        // Refreshed by another call while this one was waiting
+        if let Some(refreshed) = self.refreshed.get(&connection.key).await {
+            if refreshed.replaces(stale) && refreshed.is_fresh(now, self.skew) {
+                return Ok(refreshed.secret);
+            }
+        }
+
+        let refreshed = self.refresh.refresh(connection, stale).await.map_err(|e| {
+            error!(
+                "Could not refresh the access token of connection {}: {e}",
+                connection.id
+            );
+            e
+        })?;
+        self.refreshed
+            .insert(connection.key.clone(), refreshed.clone())
+            .await;
+
+        Ok(refreshed.secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integrationos_domain::{
+        environment::Environment,
+        id::{prefix::IdPrefix, Id},
+        ownership::Ownership,
+        ConnectionType, Throughput,
+    };
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Hands out the tokens `token-1`, `token-2`, ... taking `delay` to answer
+    struct CountingRefresh {
+        refreshes: AtomicUsize,
+        delay: std::time::Duration,
+        now: DateTime<Utc>,
+    }
+
+    impl CountingRefresh {
+        fn new(now: DateTime<Utc>, delay: std::time::Duration) -> Arc<Self> {
+            Arc::new(Self {
+                refreshes: AtomicUsize::new(0),
+                delay,
+                now,
+            })
+        }
+
+        fn refreshes(&self) -> usize {
+            self.refreshes.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl TokenRefreshExt for CountingRefresh {
+        async fn refresh(
+            &self,
+            _connection: &Connection,
+            _secret: &Value,
+        ) -> Result<RefreshedToken, IntegrationOSError> {
+            tokio::time::sleep(self.delay).await;
+            let refresh = self.refreshes.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(RefreshedToken {
+                secret: json!({ "OAUTH_ACCESS_TOKEN": format!("token-{refresh}") }),
+                expires_at: Some((self.now + Duration::hours(1)).timestamp()),
+            })
+        }
+    }
+
+    fn connection(expires_at: DateTime<Utc>) -> Connection {
+        Connection {
+            id: Id::now(IdPrefix::Connection),
+            platform_version: "v1".to_string(),
+            connection_definition_id: Id::now(IdPrefix::ConnectionDefinition),
+            r#type: ConnectionType::Api {},
+            name: "connection".to_string(),
+            key: "live::hubspot::group".into(),
+            group: "group".to_string(),
+            environment: Environment::Live,
+            mode: Default::default(),
+            platform: "hubspot".into(),
+            secrets_service_id: "secret".to_string(),
+            event_access_id: Id::now(IdPrefix::EventAccess),
+            access_key: "access-key".to_string(),
+            settings: Default::default(),
+            throughput: Throughput {
+                key: "throughput".to_string(),
+                limit: 100,
+            },
+            ownership: Ownership::new("owner".to_string()),
+            oauth: Some(OAuth::Enabled {
+                connection_oauth_definition_id: Id::now(IdPrefix::ConnectionOAuthDefinition),
+                expires_in: Some(3600),
+                expires_at: Some(expires_at.timestamp()),
+                scopes: None,
+            }),
+            health: Default::default(),
+            webhook_secret: None,
+            credential_expiry: Default::default(),
+            credentials_rotated_at: None,
+            record_metadata: Default::default(),
+        }
+    }
+
+    fn stored() -> Value {
+        json!({ "OAUTH_ACCESS_TOKEN": "stored" })
+    }
+
+    #[tokio::test]
+    async fn test_token_near_expiry_is_refreshed_once() {
+        let now = Utc::now();
+        let refresh = CountingRefresh::new(now, std::time::Duration::ZERO);
+        let refresher = OAuthTokenRefresher::new(
+            refresh.clone(),
+            Duration::seconds(DEFAULT_REFRESH_SKEW_SECS),
+            10,
+        );
+
+        let fresh = connection(now + Duration::minutes(10));
+        let secret = refresher.fresh_secret(&fresh, stored(), now).await.unwrap();
+        assert_eq!(secret, stored());
+        assert_eq!(refresh.refreshes(), 0);
+
+        let expiring = connection(now + Duration::seconds(30));
+        let secret = refresher
+            .fresh_secret(&expiring, stored(), now)
+            .await
+            .unwrap();
+        assert_eq!(secret["OAUTH_ACCESS_TOKEN"], "token-1");
+
+        // The cached connection still has the previous expiry, the refreshed token is reused
+        let secret = refresher
+            .fresh_secret(&expiring, stored(), now + Duration::seconds(5))
+            .await
+            .unwrap();
+        assert_eq!(secret["OAUTH_ACCESS_TOKEN"], "token-1");
+        assert_eq!(refresh.refreshes(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_refreshed_and_failures_are_returned() {
+        struct FailingRefresh;
+
+        #[async_trait]
+        impl TokenRefreshExt for FailingRefresh {
+            async fn refresh(
+                &self,
+                _connection: &Connection,
+                _secret: &Value,
+            ) -> Result<RefreshedToken, IntegrationOSError> {
+                Err(ApplicationError::failed_dependency("Refresh failed", None))
+            }
+        }
+
+        let now = Utc::now();
+        let refresh = CountingRefresh::new(now, std::time::Duration::ZERO);
+        let refresher = OAuthTokenRefresher::new(refresh.clone(), Duration::seconds(60), 10);
+        let expired = connection(now - Duration::minutes(5));
+        let secret = refresher
+            .fresh_secret(&expired, stored(), now)
+            .await
+            .unwrap();
+        assert_eq!(secret["OAUTH_ACCESS_TOKEN"], "token-1");
+
+        // An expired token can't be used anymore, a token near expiry still can
+        let refresher =
+            OAuthTokenRefresher::new(Arc::new(FailingRefresh), Duration::seconds(60), 10);
+        assert!(refresher
+            .fresh_secret(&expired, stored(), now)
+            .await
+            .is_err());
+        let expiring = connection(now + Duration::seconds(30));
+        let secret = refresher
+            .fresh_secret(&expiring, stored(), now)
+            .await
+            .unwrap();
+        assert_eq!(secret, stored());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_refreshes_of_a_connection_are_coalesced() {
+        let now = Utc::now();
+        let refresh = CountingRefresh::new(now, std::time::Duration::from_millis(50));
+        let refresher = OAuthTokenRefresher::new(refresh.clone(), Duration::seconds(60), 10);
+        let expiring = connection(now + Duration::seconds(30));
+
+        let secrets = futures::future::join_all(
+            (0..10).map(|_| refresher.fresh_secret(&expiring, stored(), now)),
+        )
+        .await;
+        for secret in secrets {
+            assert_eq!(secret.unwrap()["OAUTH_ACCESS_TOKEN"], "token-1");
+        }
+        assert_eq!(refresh.refreshes(), 1);
+
+        // Calls rejected with the stale token reuse the token refreshed meanwhile, a call
+        // rejected with the refreshed one refreshes it again
+        let secret = refresher
+            .refresh_rejected(&expiring, &stored(), now)
+            .await
+            .unwrap();
+        assert_eq!(secret["OAUTH_ACCESS_TOKEN"], "token-1");
+        let secret = refresher
+            .refresh_rejected(&expiring, &secret, now)
+            .await
+            .unwrap();
+        assert_eq!(secret["OAUTH_ACCESS_TOKEN"], "token-2");
+        assert_eq!(refresh.refreshes(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_secret_with_extra_fields_reuses_concurrent_refresh() {
+        let now = Utc::now();
+        let refresh = CountingRefresh::new(now, std::time::Duration::ZERO);
+        let refresher = OAuthTokenRefresher::new(refresh.clone(), Duration::seconds(60), 10);
+        let expiring = connection(now + Duration::seconds(30));
+
+        let secret = refresher
+            .fresh_secret(&expiring, stored(), now)
+            .await
+            .unwrap();
+        assert_eq!(secret["OAUTH_ACCESS_TOKEN"], "token-1");
+
+        // A call made with the stale token and the fields merged into the secret for the
+        // request gets a 401 after the refresh above
+        let mut rejected = stored();
+        rejected["id"] = json!("contact-1");
+        rejected["portalId"] = json!("portal");
+        let secret = refresher
+            .refresh_rejected(&expiring, &rejected, now)
+            .await
+            .unwrap();
+        assert_eq!(secret["OAUTH_ACCESS_TOKEN"], "token-1");
+        assert_eq!(refresh.refreshes(), 1);
+
+        // The same fields merged into the refreshed secret still refresh a rejected token
+        let mut rejected = secret;
+        rejected["id"] = json!("contact-1");
+        let secret = refresher
+            .refresh_rejected(&expiring, &rejected, now)
+            .await
+            .unwrap();
+        assert_eq!(secret["OAUTH_ACCESS_TOKEN"], "token-2");
+        assert_eq!(refresh.refreshes(), 2);
+    }
+}
@@ -0,0 +1,184 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use integrationos_domain::connection_model_definition::PaginationLimits;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+const CURSOR: &str = "cursor";
+const LIMIT: &str = "limit";
+const NEXT_CURSOR: &str = "nextCursor";
+
+/// Cursor handed to the clients of a read capped by `maxRecords`: the cursor of the platform,
+/// with the records read from the first page up to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct CappedCursor {
+    cursor: Value,
+    fetched: u64,
+}
+
+impl CappedCursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    /// None for cursors that weren't issued for a capped read
+    fn decode(cursor: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+}
+
+/// Read of a list of records capped by the [`PaginationLimits`] of its definition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CappedRead {
+    limits: PaginationLimits,
+    /// Records read by the previous pages
+    fetched: u64,
+}
+
+impl CappedRead {
+    /// Starts a read from the query params of the client, replacing the cursor they sent with
+    /// the one of the platform and clamping the page size they asked for
+    pub fn start(limits: PaginationLimits, query_params: &mut HashMap<String, String>) -> Self {
+        let mut read = Self { limits, fetched: 0 };
+
+        if limits.max_records.is_some() {
+            if let Some(capped) = query_params
+                .get(CURSOR)
+                .and_then(|cursor| CappedCursor::decode(cursor))
+            {
+                read.fetched = capped.fetched;
+                match capped.cursor {
+                    Value::Null => query_params.remove(CURSOR),
+                    Value::String(cursor) => query_params.insert(CURSOR.to_owned(), cursor),
+                    cursor => query_params.insert(CURSOR.to_owned(), cursor.to_string()),
+                };
+            }
+        }
+
+        if let Some(max_page_size) = read.max_page_size() {
+            let requested = query_params
+                .get(LIMIT)
+                .and_then(|limit| limit.parse::<u64>().ok());
+            if matches!(requested, Some(requested) if requested > max_page_size) {
+                query_params.insert(LIMIT.to_owned(), max_page_size.to_string());
+            }
+        }
+
+        read
+    }
+
+    /// Largest page the platform may be asked for, at most the records left to read
+    fn max_page_size(&self) -> Option<u64> {
+        let remaining = self
+            .limits
+            .max_records
+            .map(|max_records| max_records.saturating_sub(self.fetched).max(1));
+
+        match (self.limits.max_page_size, remaining) {
+            (Some(max_page_size), Some(remaining)) => Some(max_page_size.min(remaining)),
+            (max_page_size, remaining) => max_page_size.or(remaining),
+        }
+    }
+
+    /// Caps the pagination of a page of `page_size` records. The next cursor is dropped once
+    /// `maxRecords` records were read, and carries the records read so far until then.
+    pub fn finish(&self, pagination: &mut Map<String, Value>, page_size: u64) {
+        let Some(max_records) = self.limits.max_records else {
+            return;
+        };
+        let fetched = self.fetched + page_size;
+
+        match pagination.get(NEXT_CURSOR) {
+            None | Some(Value::Null) => {}
+            Some(_) if fetched >= max_records => {
+                pagination.insert(NEXT_CURSOR.to_owned(), Value::Null);
+            }
+            Some(cursor) => {
+                let cursor = CappedCursor {
+                    cursor: cursor.clone(),
+                    fetched,
+                };
+                pagination.insert(NEXT_CURSOR.to_owned(), Value::String(cursor.encode()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn limits(max_page_size: Option<u64>, max_records: Option<u64>) -> PaginationLimits {
+        PaginationLimits {
+            max_page_size,
+            max_records,
+        }
+    }
+
+    #[test]
+    fn test_over_large_page_size_is_clamped() {
+        let mut query_params = HashMap::from([(LIMIT.to_owned(), "100000".to_owned())]);
+        CappedRead::start(limits(Some(100), None), &mut query_params);
+        assert_eq!(query_params[LIMIT], "100");
+
+        let mut query_params = HashMap::from([(LIMIT.to_owned(), "20".to_owned())]);
+        CappedRead::start(limits(Some(100), None), &mut query_params);
+        assert_eq!(query_params[LIMIT], "20");
+    }
+
+    #[test]
+    fn test_total_records_cap_halts_pagination() {
+        let limits = limits(Some(100), Some(250));
+        let mut query_params = HashMap::from([(LIMIT.to_owned(), "1000".to_owned())]);
+        let mut pages = 0;
+
+        loop {
+            let read = CappedRead::start(limits, &mut query_params);
+            if pages > 0 {
+                // The platform is sent back its own cursor
+                assert_eq!(query_params[CURSOR], format!("platform_cursor_{pages}"));
+                assert_eq!(read.fetched, pages * 100);
+            }
+            pages += 1;
+
+            let page_size = query_params[LIMIT].parse::<u64>().unwrap();
+            let mut pagination = json!({ NEXT_CURSOR: format!("platform_cursor_{pages}") })
+                .as_object()
+                .unwrap()
+                .clone();
+            read.finish(&mut pagination, page_size);
+
+            match &pagination[NEXT_CURSOR] {
+                Value::String(next) => {
+                    query_params.insert(CURSOR.to_owned(), next.clone());
+                    query_params.insert(LIMIT.to_owned(), "1000".to_owned());
+                }
+                Value::Null => {
+                    assert_eq!(page_size, 50, "The last page reads the records left");
+                    break;
+                }
+                next => panic!("Unexpected next cursor {next}"),
+            }
+        }
+
+        assert_eq!(pages, 3);
+    }
+
+    #[test]
+    fn test_cursors_of_uncapped_reads_are_passed_through() {
+        let mut query_params = HashMap::from([(CURSOR.to_owned(), "platform_cursor".to_owned())]);
+        let read = CappedRead::start(limits(Some(100), Some(250)), &mut query_params);
+        assert_eq!(query_params[CURSOR], "platform_cursor");
+
+        let mut pagination = json!({ NEXT_CURSOR: "next" }).as_object().unwrap().clone();
+        CappedRead::start(limits(Some(100), None), &mut HashMap::new())
+            .finish(&mut pagination, 100);
+        assert_eq!(pagination[NEXT_CURSOR], "next");
+
+        read.finish(&mut pagination, 10);
+        assert_ne!(pagination[NEXT_CURSOR], "next");
+    }
+}
@@ -0,0 +1,276 @@
+use chrono::{DateTime, Utc};
+use integrationos_domain::{ApplicationError, Clock, Connection, IntegrationOSError, SystemClock};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Per-connection rate limit of the unified calls
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    /// Calls beyond the throughput limit of their connection are rejected when set
+    pub enabled: bool,
+    /// Window in which a connection may make as many calls as its throughput limit
+    pub window: Duration,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Snapshot of the token bucket of a connection
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitState {
+    pub limit: u64,
+    pub available: u64,
+    /// Unix timestamp in milliseconds at which the bucket is full again
+    pub refill_at: i64,
+    pub refill_interval_secs: u64,
+}
+
+/// Bucket holding up to `capacity` tokens, refilled continuously so that it goes from empty
+/// to full in `refill_interval`. The capacity is passed on every call so that changes to the
+/// throughput of a connection apply right away.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    tokens: f64,
+    refill_interval: Duration,
+    last_refill: DateTime<Utc>,
+    /// No token is handed out until then, whatever the tokens left
+    paused_until: Option<DateTime<Utc>>,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u64, refill_interval: Duration, now: DateTime<Utc>) -> Self {
+        Self {
+            tokens: capacity as f64,
+            refill_interval,
+            last_refill: now,
+            paused_until: None,
+        }
+    }
+
+    /// How long the bucket is still paused for
+    fn paused_for(&mut self, now: DateTime<Utc>) -> Duration {
+        match self.paused_until {
+            Some(paused_until) if paused_until > now => {
+                (paused_until - now).to_std().unwrap_or_default()
+            }
+            _ => {
+                self.paused_until = None;
+                Duration::ZERO
+            }
+        }
+    }
+
+    /// Holds off the tokens until `until`. A shorter pause doesn't cut the current one.
+    pub fn pause(&mut self, until: DateTime<Utc>) {
+        if self.paused_until.map_or(true, |paused| paused < until) {
+            self.paused_until = Some(until);
+        }
+    }
+
+    fn refill(&mut self, capacity: u64, now: DateTime<Utc>) {
+        let elapsed = (now - self.last_refill).to_std().unwrap_or_default();
+        let per_sec = capacity as f64 / self.refill_interval.as_secs_f64().max(f64::EPSILON);
+
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * per_sec).min(capacity as f64);
+        self.last_refill = now;
+    }
+
+    /// Takes a token out of the bucket, returning `false` if none is available or the bucket
+    /// is paused
+    pub fn try_acquire(&mut self, capacity: u64, now: DateTime<Utc>) -> bool {
+        self.refill(capacity, now);
+
+        if self.paused_for(now) > Duration::ZERO {
+            false
+        } else if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// How long until a token is available again
+    pub fn retry_after(&mut self, capacity: u64, now: DateTime<Utc>) -> Duration {
+        self.refill(capacity, now);
+
+        let paused_for = self.paused_for(now);
+        if self.tokens >= 1.0 || capacity == 0 {
+            return paused_for;
+        }
+        self.refill_interval
+            .mul_f64((1.0 - self.tokens) / capacity as f64)
+            .max(paused_for)
+    }
+
+    pub fn state(&mut self, capacity: u64, now: DateTime<Utc>) -> RateLimitState {
+        self.refill(capacity, now);
+
+        let paused_for = self.paused_for(now);
+        let missing = (capacity as f64 - self.tokens).max(0.0);
+        let until_full = if capacity == 0 {
+            Duration::ZERO
+        } else {
+            self.refill_interval.mul_f64(missing / capacity as f64)
+        }
+        .max(paused_for);
+
+        RateLimitState {
+            limit: capacity,
+            available: if paused_for > Duration::ZERO {
+                0
+            } else {
+                self.tokens.floor() as u64
+            },
+            refill_at: now.timestamp_millis() + until_full.as_millis() as i64,
+            refill_interval_secs: self.refill_interval.as_secs(),
+        }
+    }
+}
+
+/// Token buckets of all connections, keyed by connection id. The capacity of a bucket is
+/// the throughput limit of its connection.
+#[derive(Clone)]
+pub struct ConnectionRateLimiter {
+    buckets: Arc<Mutex<HashMap<String, TokenBucket>>>,
+    config: RateLimitConfig,
+    clock: Arc<dyn Clock>,
+}
+
+impl ConnectionRateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self::with_clock(config, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(config: RateLimitConfig, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+            config,
+            clock,
+        }
+    }
+
+    fn with_bucket<T>(
+        &self,
+        connection: &Connection,
+        f: impl FnOnce(&mut TokenBucket, u64, DateTime<Utc>) -> T,
+    ) -> T {
+        let now = self.clock.now();
+        let capacity = connection.throughput.limit;
+        let mut buckets = self
+            .buckets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let bucket = buckets
+            .entry(connection.id.to_string())
+            .or_insert_with(|| TokenBucket::new(capacity, self.config.window, now));
+
+        f(bucket, capacity, now)
+    }
+
+    /// Takes a call of the connection out of its bucket, failing when the connection made as
+    /// many calls as its throughput limit in the window
+    pub fn acquire(&self, connection: &Connection) -> Result<(), IntegrationOSError> {
+        if !self.config.enabled || self.with_bucket(connection, TokenBucket::try_acquire) {
+            Ok(())
+        } else {
+            Err(ApplicationError::too_many_requests(
+                &format!("Rate limit exceeded for connection {}", connection.key),
+                None,
+            ))
+        }
+    }
+
+    pub fn state(&self, connection: &Connection) -> RateLimitState {
+        self.with_bucket(connection, TokenBucket::state)
+    }
+
+    /// Holds off the calls of the connection for `wait`
+    pub fn pause(&self, connection: &Connection, wait: Duration) {
+        if !self.config.enabled {
+            return;
+        }
+        self.with_bucket(connection, |bucket, _, now| {
+            if let Some(until) = chrono::Duration::from_std(wait)
+                .ok()
+                .and_then(|wait| now.checked_add_signed(wait))
+            {
+                bucket.pause(until);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_available_tokens_decrease_when_consumed() {
+        let start = Utc::now();
+        let mut bucket = TokenBucket::new(10, Duration::from_secs(60), start);
+
+        assert_eq!(bucket.state(10, start).available, 10);
+
+        for _ in 0..4 {
+            assert!(bucket.try_acquire(10, start));
+        }
+
+        let state = bucket.state(10, start);
+        assert_eq!(state.limit, 10);
+        assert_eq!(state.available, 6);
+        assert!(state.refill_at > start.timestamp_millis());
+
+        for _ in 0..6 {
+            assert!(bucket.try_acquire(10, start));
+        }
+        assert!(!bucket.try_acquire(10, start));
+        assert_eq!(bucket.state(10, start).available, 0);
+
+        // Tokens are refilled at a rate of limit / interval
+        let later = start + chrono::Duration::seconds(9);
+        assert_eq!(bucket.state(10, later).available, 1);
+        assert_eq!(
+            bucket
+                .state(10, start + chrono::Duration::seconds(600))
+                .available,
+            10
+        );
+    }
+
+    #[test]
+    fn test_paused_bucket_hands_out_no_token_until_the_pause_ends() {
+        let start = Utc::now();
+        let mut bucket = TokenBucket::new(10, Duration::from_secs(60), start);
+
+        bucket.pause(start + chrono::Duration::seconds(30));
+        assert!(!bucket.try_acquire(10, start));
+        assert_eq!(bucket.retry_after(10, start), Duration::from_secs(30));
+        let state = bucket.state(10, start);
+        assert_eq!(state.available, 0);
+        assert_eq!(state.refill_at, start.timestamp_millis() + 30_000);
+
+        // A shorter pause doesn't cut the current one
+        bucket.pause(start + chrono::Duration::seconds(10));
+        assert!(!bucket.try_acquire(10, start + chrono::Duration::seconds(20)));
+
+        assert!(bucket.try_acquire(10, start + chrono::Duration::seconds(30)));
+        assert_eq!(
+            bucket
+                .state(10, start + chrono::Duration::seconds(30))
+                .available,
+            9
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use chrono::Utc;
+use integrationos_domain::id::{prefix::IdPrefix, Id};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Outcome of comparing the response of a definition against the response of its shadow
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowDiff {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub connection_model_definition_id: Id,
+    pub shadow_connection_model_definition_id: Id,
+    pub primary_status: u16,
+    pub shadow_status: Option<u16>,
+    pub shadow_error: Option<String>,
+    /// JSON paths at which the shadow response differs from the primary one
+    pub differences: Vec<String>,
+    pub created_at: i64,
+}
+
+impl ShadowDiff {
+    pub fn new(
+        connection_model_definition_id: Id,
+        shadow_connection_model_definition_id: Id,
+        primary: (u16, &Value),
+        shadow: Result<(u16, &Value), &str>,
+    ) -> Self {
+        let (primary_status, primary_body) = primary;
+        let (shadow_status, shadow_error, differences) = match shadow {
+            Ok((status, body)) => {
+                let mut differences = diff_values(primary_body, body);
+                if status != primary_status {
+                    differences.insert(0, "status".to_string());
+                }
+                (Some(status), None, differences)
+            }
+            Err(e) => (None, Some(e.to_string()), vec![]),
+        };
+
+        Self {
+            id: Id::now(IdPrefix::Log),
+            connection_model_definition_id,
+            shadow_connection_model_definition_id,
+            primary_status,
+            shadow_status,
+            shadow_error,
+            differences,
+            created_at: Utc::now().timestamp_millis(),
+        }
+    }
+
+    pub fn is_match(&self) -> bool {
+        self.shadow_error.is_none() && self.differences.is_empty()
+    }
+}
+
+/// Returns the JSON paths at which both values differ. Objects and arrays are walked
+/// recursively, any other value is compared as a whole.
+pub fn diff_values(primary: &Value, shadow: &Value) -> Vec<String> {
+    let mut differences = vec![];
+    diff_at("$", primary, shadow, &mut differences);
+    differences
+}
+
+fn diff_at(path: &str, primary: &Value, shadow: &Value, differences: &mut Vec<String>) {
+    match (primary, shadow) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, value) in a {
+                let path = format!("{path}.{key}");
+                match b.get(key) {
+                    Some(other) => diff_at(&path, value, other, differences),
+                    None => differences.push(path),
+                }
+            }
+            differences.extend(
+                b.keys()
+                    .filter(|key| !a.contains_key(*key))
+                    .map(|key| format!("{path}.{key}")),
+            );
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for (index, value) in a.iter().enumerate() {
+                let path = format!("{path}[{index}]");
+                match b.get(index) {
+                    Some(other) => diff_at(&path, value, other, differences),
+                    None => differences.push(path),
+                }
+            }
+            differences.extend((a.len()..b.len()).map(|index| format!("{path}[{index}]")));
+        }
+        (a, b) if a != b => differences.push(path.to_string()),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_values() {
+        let primary = json!({"id": 1, "name": "a", "tags": ["x", "y"], "nested": {"a": 1}});
+        let shadow = json!({"id": 1, "name": "b", "tags": ["x"], "nested": {"a": 1, "b": 2}});
+
+        let mut differences = diff_values(&primary, &shadow);
+        differences.sort();
+
+        assert_eq!(differences, vec!["$.name", "$.nested.b", "$.tags[1]"]);
+        assert!(diff_values(&primary, &primary).is_empty());
+    }
+
+    #[test]
+    fn test_shadow_diff_status_mismatch() {
+        let id = Id::now(IdPrefix::ConnectionModelDefinition);
+        let body = json!({"id": 1});
+
+        let diff = ShadowDiff::new(id, id, (200, &body), Ok((404, &body)));
+        assert_eq!(diff.differences, vec!["status"]);
+        assert!(!diff.is_match());
+
+        let diff = ShadowDiff::new(id, id, (200, &body), Err("timeout"));
+        assert_eq!(diff.shadow_error.as_deref(), Some("timeout"));
+        assert!(!diff.is_match());
+    }
+}
@@ -1,25 +1,38 @@
 use crate::{
-    client::CallerClient,
+    client::{CallerClient, CALL_TIMEOUT},
+    oauth_refresh::{
+        OAuthDefinitionRefresh, OAuthTokenRefresher, TokenRefreshExt, DEFAULT_REFRESH_SKEW_SECS,
+    },
+    pagination::CappedRead,
+    rate_limit::{ConnectionRateLimiter, RateLimitConfig},
     request::{
         PathParams, RequestCrud, RequestCrudBorrowed, ResponseCrud, ResponseCrudToMap,
         ResponseCrudToMapRequest,
     },
-    utility::{match_route, remove_nulls, template_route},
+    shadow::ShadowDiff,
+    utility::{match_route, remove_nulls, retry_after, template_route},
 };
 use bson::doc;
+use bytes::{Bytes, BytesMut};
 use chrono::Utc;
-use futures::{future::join_all, join, FutureExt};
+use futures::{future::join_all, join, FutureExt, TryStream, TryStreamExt};
 use handlebars::Handlebars;
 use http::{HeaderMap, HeaderName, HeaderValue, Response, StatusCode};
 use integrationos_cache::local::{
     connection_cache::ConnectionCacheArcStrKey,
-    connection_model_definition_cache::ConnectionModelDefinitionDestinationKey,
-    connection_model_schema_cache::ConnectionModelSchemaCache, secrets_cache::SecretCache,
+    connection_definition_cache::ConnectionDefinitionCache,
+    connection_model_definition_cache::{
+        ConnectionModelDefinitionCacheForKey, ConnectionModelDefinitionCacheIdKey,
+        ConnectionModelDefinitionDestinationKey,
+    },
+    connection_model_schema_cache::ConnectionModelSchemaCache,
+    secrets_cache::SecretCache,
 };
 use integrationos_domain::{
     api_model_config::{ModelPaths, RequestModelPaths, ResponseModelPaths},
+    connection_definition::{ConnectionDefinition, DownstreamRetryPolicy},
     connection_model_definition::{
-        ConnectionModelDefinition, CrudAction, CrudMapping, PlatformInfo,
+        ConnectionModelDefinition, CrudAction, CrudMapping, PlatformInfo, TimeoutFallback,
     },
     connection_model_schema::ConnectionModelSchema,
     database::DatabaseConfig,
@@ -28,17 +41,32 @@ use integrationos_domain::{
     error::InternalError,
     hashed_secret::HashedSecret,
     id::{prefix::IdPrefix, Id},
-    prelude::{MongoStore, TimedExt},
-    ApplicationError, Connection, ErrorMeta, IntegrationOSError, SecretExt, Store,
+    prelude::{DefaultTemplate, MongoStore, TimedExt},
+    ApplicationError, Connection, ErrorMeta, IntegrationOSError, OAuth, SecretExt, Store,
 };
 use js_sandbox_ios::Script;
+use moka::future::Cache;
 use mongodb::{
     options::{Collation, CollationStrength, FindOneOptions},
     Client,
 };
-use serde_json::{json, Number, Value};
-use std::{cell::RefCell, collections::HashMap, str::FromStr, sync::Arc};
-use tracing::{debug, error};
+use serde_json::{json, Map, Number, Value};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::ControlFlow,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
+
+/// Header through which callers explicitly forward custom headers to the platform
+const PASSTHROUGH_HEADERS: &str = "x-integrationos-passthrough-forward";
+
+/// Longest wait asked by the `Retry-After` of a platform that is honoured by default
+pub const DEFAULT_MAX_RETRY_AFTER_SECS: u64 = 300;
 
 thread_local! {
     static JS_RUNTIME: RefCell<Script> = RefCell::new(Script::new());
@@ -53,17 +81,134 @@ pub struct UnifiedResponse {
 pub struct UnifiedDestination {
     pub connections_cache: ConnectionCacheArcStrKey,
     pub connections_store: MongoStore<Connection>,
+    pub connection_definitions_cache: ConnectionDefinitionCache,
+    pub connection_definitions_store: MongoStore<ConnectionDefinition>,
     pub connection_model_definitions_cache: ConnectionModelDefinitionDestinationKey,
     pub connection_model_definitions_store: MongoStore<ConnectionModelDefinition>,
+    /// Shadow definitions, keyed by their id
+    pub shadow_definitions_cache: ConnectionModelDefinitionCacheForKey<Id>,
     pub connection_model_schemas_cache: ConnectionModelSchemaCache,
     pub connection_model_schemas_store: MongoStore<ConnectionModelSchema>,
     pub secrets_client: Arc<dyn SecretExt + Sync + Send>,
     pub secrets_cache: SecretCache,
+    pub shadow_diffs_store: MongoStore<ShadowDiff>,
+    /// Client shared by every unified call, so that connections to a platform host are pooled
+    /// and reused whichever connection the call is made for. Connections don't carry TLS or
+    /// client identity settings of their own, so a single pool per host is enough.
     pub http_client: reqwest::Client,
+    /// Largest downstream response body, in bytes, that is read before the call is aborted
+    pub max_response_size: Option<usize>,
+    /// Largest request body, in bytes, buffered before being sent to the platform
+    pub max_request_size: Option<usize>,
+    /// Inbound headers forwarded to the platform on unified calls, all of them when unset
+    pub forwarded_headers: Option<HashSet<HeaderName>>,
+    /// Retry policy of the unified calls to platforms whose definition has none of its own
+    pub retry_policy: DownstreamRetryPolicy,
+    /// Refreshes the OAuth tokens of the connections called before they expire
+    pub oauth_refresher: OAuthTokenRefresher,
+    /// Longest a call to a platform may take, reading its response included, unbounded when
+    /// unset
+    pub call_timeout: Option<Duration>,
+    /// Limits the calls of each connection to its throughput, and holds them off while its
+    /// platform asks to
+    pub rate_limiter: ConnectionRateLimiter,
+    /// Longest a platform may hold off the calls of a connection through `Retry-After`
+    pub max_retry_after: Duration,
+    /// Last successful responses of the definitions falling back to them on timeout
+    fallback_responses: Cache<CachedResponseKey, (StatusCode, HeaderMap, Value)>,
+}
+
+/// Key of a cached response, scoped by connection and common model so that the writes through a
+/// connection can drop the responses they make stale
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CachedResponseKey {
+    connection_id: Id,
+    common_model: Arc<str>,
+    /// Definition, record id and query params of the call
+    request: String,
+}
+
+/// Common model mapping through which the successful responses of a definition and of its
+/// shadow are compared
+struct ShadowMapping {
+    schema: ConnectionModelSchema,
+    /// Namespace of the mapping script in the JS runtime
+    namespace: String,
+}
+
+impl ShadowMapping {
+    /// Mapping of the responses through `schema`, none if it doesn't map to a common model
+    fn for_schema(schema: &ConnectionModelSchema, namespace: &str) -> Option<Self> {
+        schema.mapping.is_some().then(|| Self {
+            schema: schema.clone(),
+            namespace: namespace.to_string() + "_mapToCommonModel",
+        })
+    }
+
+    /// Common model output of a response of `definition`, selected at the response path of the
+    /// definition and mapped the way unified calls return it
+    fn map(
+        &self,
+        definition: &ConnectionModelDefinition,
+        body: Value,
+    ) -> Result<Value, IntegrationOSError> {
+        let PlatformInfo::Api(api_config) = &definition.platform_info;
+        let body = match &api_config.paths {
+            Some(ModelPaths {
+                response:
+                    Some(ResponseModelPaths {
+                        object: Some(path), ..
+                    }),
+                ..
+            }) => {
+                let wrapped_body = json!({ "body": body });
+                let mut bodies = jsonpath_lib::select(&wrapped_body, path)
+                    .map_err(|e| ApplicationError::bad_request(&e.to_string(), None))?;
+                if bodies.len() == 1 {
+                    bodies.remove(0).clone()
+                } else {
+                    Value::Null
+                }
+            }
+            _ => body,
+        };
+        let Some(mapping) = &self.schema.mapping else {
+            return Ok(body);
+        };
+
+        let map = |body: Value| {
+            let body = self.schema.normalize(body)?;
+            JS_RUNTIME.with_borrow_mut(|script| {
+                script
+                    .add_script(
+                        &self.namespace,
+                        "mapToCommonModel",
+                        &mapping.to_common_model,
+                    )
+                    .and_then(|_| script.call_namespace::<_, Value>(&self.namespace, body))
+                    .map_err(|e| {
+                        ApplicationError::bad_request(
+                            &format!("Failed while running response schema mapping script: {e}"),
+                            None,
+                        )
+                    })
+            })
+        };
+        match body {
+            Value::Array(items) => items
+                .into_iter()
+                .map(map)
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            Value::Null => Ok(Value::Null),
+            body => map(body),
+        }
+    }
 }
 
 pub struct UnifiedCacheTTLs {
     pub connection_cache_ttl_secs: u64,
+    pub connection_definition_cache_ttl_secs: u64,
     pub connection_model_definition_cache_ttl_secs: u64,
     pub connection_model_schema_cache_ttl_secs: u64,
     pub secret_cache_ttl_secs: u64,
@@ -75,14 +220,34 @@ impl UnifiedDestination {
         cache_size: u64,
         secrets_client: Arc<dyn SecretExt + Sync + Send>,
         cache_ttls: UnifiedCacheTTLs,
+        rate_limit: RateLimitConfig,
     ) -> Result<Self, IntegrationOSError> {
+        // Secrets are rotated more often than connections change, so they must not outlive them
+        if cache_ttls.secret_cache_ttl_secs >= cache_ttls.connection_cache_ttl_secs {
+            return Err(InternalError::invalid_argument(
+                &format!(
+                    "The secret cache TTL ({}s) must be shorter than the connection cache TTL ({}s)",
+                    cache_ttls.secret_cache_ttl_secs, cache_ttls.connection_cache_ttl_secs
+                ),
+                None,
+            ));
+        }
+
         let http_client = reqwest::Client::new();
         let connections_cache =
             ConnectionCacheArcStrKey::new(cache_size, cache_ttls.connection_cache_ttl_secs);
+        let connection_definitions_cache = ConnectionDefinitionCache::new(
+            cache_size,
+            cache_ttls.connection_definition_cache_ttl_secs,
+        );
         let connection_model_definitions_cache = ConnectionModelDefinitionDestinationKey::create(
             cache_size,
             cache_ttls.connection_model_definition_cache_ttl_secs,
         );
+        let shadow_definitions_cache = ConnectionModelDefinitionCacheIdKey::create(
+            cache_size,
+            cache_ttls.connection_model_definition_cache_ttl_secs,
+        );
         let connection_model_schemas_cache = ConnectionModelSchemaCache::new(
             cache_size,
             cache_ttls.connection_model_schema_cache_ttl_secs,
@@ -101,24 +266,231 @@ impl UnifiedDestination {
         let db = client.database(&db_config.control_db_name);
 
         let connections_store = MongoStore::new(&db, &Store::Connections).await?;
+        let connection_definitions_store =
+            MongoStore::new(&db, &Store::ConnectionDefinitions).await?;
         let connection_model_definitions_store =
             MongoStore::new(&db, &Store::ConnectionModelDefinitions).await?;
         let connection_model_schemas_store =
             MongoStore::new(&db, &Store::ConnectionModelSchemas).await?;
+        let shadow_diffs_store = MongoStore::new(&db, &Store::ShadowDiffs).await?;
+        let oauth_refresher = OAuthTokenRefresher::new(
+            Arc::new(OAuthDefinitionRefresh {
+                oauth_definitions_store: MongoStore::new(&db, &Store::ConnectionOAuthDefinitions)
+                    .await?,
+                connections_store: connections_store.clone(),
+                secrets_client: secrets_client.clone(),
+                http_client: http_client.clone(),
+                template: DefaultTemplate::default(),
+            }),
+            chrono::Duration::seconds(DEFAULT_REFRESH_SKEW_SECS),
+            cache_size,
+        );
 
         Ok(Self {
             connections_cache,
             connections_store,
+            connection_definitions_cache,
+            connection_definitions_store,
             connection_model_definitions_cache,
             connection_model_definitions_store,
+            shadow_definitions_cache,
             connection_model_schemas_cache,
             connection_model_schemas_store,
             secrets_client,
             secrets_cache,
+            shadow_diffs_store,
             http_client,
+            max_response_size: None,
+            max_request_size: None,
+            forwarded_headers: None,
+            retry_policy: DownstreamRetryPolicy::default(),
+            oauth_refresher,
+            call_timeout: None,
+            rate_limiter: ConnectionRateLimiter::new(rate_limit),
+            max_retry_after: Duration::from_secs(DEFAULT_MAX_RETRY_AFTER_SECS),
+            fallback_responses: Cache::builder()
+                .max_capacity(cache_size)
+                .support_invalidation_closures()
+                .build(),
+        })
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: DownstreamRetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Retry policy of the unified calls to the platform of `definition`
+    pub fn retry_policy_for<'a>(
+        &'a self,
+        definition: &'a ConnectionDefinition,
+    ) -> &'a DownstreamRetryPolicy {
+        definition
+            .retry_policy
+            .as_ref()
+            .unwrap_or(&self.retry_policy)
+    }
+
+    /// Refreshes the OAuth tokens expiring within `skew` before calling the platform
+    pub fn with_oauth_refresh_skew(mut self, skew: Duration) -> Self {
+        self.oauth_refresher.skew =
+            chrono::Duration::from_std(skew).unwrap_or(self.oauth_refresher.skew);
+        self
+    }
+
+    /// Obtains the refreshed OAuth tokens through `refresh`
+    pub fn with_token_refresh(mut self, refresh: Arc<dyn TokenRefreshExt>) -> Self {
+        self.oauth_refresher = OAuthTokenRefresher::new(
+            refresh,
+            self.oauth_refresher.skew,
+            self.secrets_cache.max_capacity(),
+        );
+        self
+    }
+
+    /// Secret of `connection` with a token that doesn't expire within the refresh skew. A
+    /// refreshed secret replaces the cached one.
+    async fn fresh_secret(
+        &self,
+        connection: &Connection,
+        secret: Value,
+    ) -> Result<Value, IntegrationOSError> {
+        let fresh = self
+            .oauth_refresher
+            .fresh_secret(connection, secret.clone(), Utc::now())
+            .await?;
+        if fresh != secret {
+            self.secrets_cache.set(&connection.id, &fresh).await?;
+        }
+        Ok(fresh)
+    }
+
+    pub fn with_max_response_size(mut self, max_response_size: usize) -> Self {
+        self.max_response_size = Some(max_response_size);
+        self
+    }
+
+    pub fn with_max_request_size(mut self, max_request_size: usize) -> Self {
+        self.max_request_size = Some(max_request_size);
+        self
+    }
+
+    /// Tunes how long idle downstream connections are kept, and how many of them are kept per
+    /// host, in the pool shared by the unified calls
+    pub fn with_connection_pool(
+        mut self,
+        idle_timeout: Duration,
+        max_idle_per_host: usize,
+    ) -> Result<Self, IntegrationOSError> {
+        self.http_client = reqwest::Client::builder()
+            .pool_idle_timeout(idle_timeout)
+            .pool_max_idle_per_host(max_idle_per_host)
+            .build()
+            .map_err(|e| {
+                InternalError::configuration_error(
+                    &format!("Failed to create downstream http client: {e}"),
+                    None,
+                )
+            })?;
+        Ok(self)
+    }
+
+    /// Cancels the calls to platforms taking longer than `call_timeout`. Cancelled calls are
+    /// retried as if the platform answered 504, see [`Self::execute_model_definition_with_retries`].
+    pub fn with_call_timeout(mut self, call_timeout: Duration) -> Self {
+        self.call_timeout = Some(call_timeout);
+        self
+    }
+
+    /// Caps the wait asked by the `Retry-After` of platforms, see
+    /// [`Self::execute_model_definition_with_retries`]
+    pub fn with_max_retry_after(mut self, max_retry_after: Duration) -> Self {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
+    /// Longest wait asked by the `Retry-After` of a platform that a retry waits for, neither
+    /// longer than the maximum nor than a call may take
+    fn retry_after_limit(&self) -> Duration {
+        self.call_timeout.map_or(self.max_retry_after, |timeout| {
+            timeout.min(self.max_retry_after)
         })
     }
 
+    /// Holds off the calls of the connection when its platform answered 429, for as long as
+    /// the `Retry-After` of the answer asks but no longer than `max_retry_after`
+    fn on_rate_limited(&self, connection: &Connection, res: &reqwest::Response) {
+        if res.status() != StatusCode::TOO_MANY_REQUESTS {
+            return;
+        }
+        if let Some(wait) = retry_after(res.headers(), Utc::now()) {
+            self.rate_limiter
+                .pause(connection, wait.min(self.max_retry_after));
+        }
+    }
+
+    pub fn with_forwarded_headers(
+        mut self,
+        forwarded_headers: impl IntoIterator<Item = HeaderName>,
+    ) -> Self {
+        self.forwarded_headers = Some(forwarded_headers.into_iter().collect());
+        self
+    }
+
+    /// Drops the inbound headers that aren't allowed to reach the platform. Headers explicitly
+    /// forwarded through the passthrough header are kept.
+    pub fn filter_forwarded_headers(&self, headers: &mut HeaderMap) {
+        let Some(allowed) = &self.forwarded_headers else {
+            return;
+        };
+
+        let dropped = headers
+            .keys()
+            .filter(|name| !allowed.contains(*name) && name.as_str() != PASSTHROUGH_HEADERS)
+            .cloned()
+            .collect::<Vec<_>>();
+        for name in dropped {
+            headers.remove(name);
+        }
+    }
+
+    /// Reads the body of a downstream response, aborting as soon as it grows past
+    /// `max_response_size`
+    pub async fn read_response(
+        &self,
+        mut res: reqwest::Response,
+    ) -> Result<Bytes, IntegrationOSError> {
+        let read_error = |e: reqwest::Error| {
+            InternalError::io_err(&format!("Failed to read downstream response: {e}"), None)
+        };
+
+        let Some(max) = self.max_response_size else {
+            return res.bytes().await.map_err(read_error);
+        };
+
+        let too_large = || {
+            warn!("Downstream response exceeded the maximum size of {max} bytes");
+            ApplicationError::failed_dependency(
+                &format!("Downstream response exceeded the maximum size of {max} bytes"),
+                None,
+            )
+        };
+
+        if res.content_length().is_some_and(|len| len > max as u64) {
+            return Err(too_large());
+        }
+
+        let mut body = BytesMut::new();
+        while let Some(chunk) = res.chunk().await.map_err(read_error)? {
+            if body.len() + chunk.len() > max {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(body.freeze())
+    }
+
     pub async fn get_connection_model_definition(
         &self,
         destination: &Destination,
@@ -201,6 +573,24 @@ impl UnifiedDestination {
         query_params: &HashMap<String, String>,
         secret: &Value,
         context: Option<Vec<u8>>,
+    ) -> Result<reqwest::Response, IntegrationOSError> {
+        self.execute_model_definition_with_body(
+            config,
+            headers,
+            query_params,
+            secret,
+            context.map(reqwest::Body::from),
+        )
+        .await
+    }
+
+    pub async fn execute_model_definition_with_body(
+        &self,
+        config: &ConnectionModelDefinition,
+        headers: HeaderMap,
+        query_params: &HashMap<String, String>,
+        secret: &Value,
+        body: Option<reqwest::Body>,
     ) -> Result<reqwest::Response, IntegrationOSError> {
         let renderer = Handlebars::new();
 
@@ -216,10 +606,11 @@ impl UnifiedDestination {
 
         match config.platform_info {
             PlatformInfo::Api(ref c) => {
-                let api_caller = CallerClient::new(c, config.action, &self.http_client);
+                let api_caller = CallerClient::new(c, config.action, &self.http_client)
+                    .with_timeout(self.call_timeout);
 
                 let response = api_caller
-                    .make_request(context, Some(secret), Some(headers), Some(query_params))
+                    .make_request(body, Some(secret), Some(headers), Some(query_params))
                     .await?;
 
                 Ok(response)
@@ -227,133 +618,452 @@ impl UnifiedDestination {
         }
     }
 
-    // FIXME: This function is way too long. It should be broken down into smaller more manageable
-    // pieces.
+    async fn get_connection_definition(
+        &self,
+        connection: &Connection,
+    ) -> Result<ConnectionDefinition, IntegrationOSError> {
+        self.connection_definitions_cache
+            .get_or_insert_with_filter(
+                &connection.connection_definition_id,
+                self.connection_definitions_store.clone(),
+                doc! { "_id": connection.connection_definition_id.to_string() },
+            )
+            .await
+    }
+
+    /// Adds the default query parameters of the connection definition of the connection to the
+    /// given query parameters
+    pub async fn apply_default_query_params(
+        &self,
+        connection: &Connection,
+        config: &ConnectionModelDefinition,
+        query_params: &mut HashMap<String, String>,
+        secret: &Value,
+    ) -> Result<(), IntegrationOSError> {
+        let connection_definition = self.get_connection_definition(connection).await?;
+
+        let PlatformInfo::Api(api_config) = &config.platform_info;
+
+        merge_default_query_params(
+            &connection_definition.default_query_params,
+            api_config.query_params.as_ref(),
+            query_params,
+            secret,
+        )
+    }
+
+    /// Executes the model definition and, when the id of a shadow definition is given, the
+    /// shadow definition in the background once the primary response is read, see
+    /// [`Self::spawn_shadow`]. The primary response is returned untouched without waiting for
+    /// the shadow one.
     #[allow(clippy::too_many_arguments)]
-    pub async fn send_to_destination_unified(
+    pub async fn execute_model_definition_with_shadow(
         &self,
-        connection: Arc<Connection>,
-        action: Action,
-        include_passthrough: bool,
-        environment: Environment,
-        mut headers: HeaderMap,
-        mut query_params: HashMap<String, String>,
-        mut body: Option<Value>,
-    ) -> Result<UnifiedResponse, IntegrationOSError> {
-        let key = Destination {
-            platform: connection.platform.clone(),
-            action: action.clone(),
-            connection_key: connection.key.clone(),
+        config: &ConnectionModelDefinition,
+        shadow: Option<Id>,
+        headers: HeaderMap,
+        query_params: &HashMap<String, String>,
+        secret: &Value,
+        context: Option<Vec<u8>>,
+    ) -> Result<(reqwest::Response, Option<JoinHandle<Option<ShadowDiff>>>), IntegrationOSError>
+    {
+        let primary = self
+            .execute_model_definition(
+                config,
+                headers.clone(),
+                query_params,
+                secret,
+                context.clone(),
+            )
+            .await?;
+        let Some(shadow) = shadow else {
+            return Ok((primary, None));
         };
 
-        let config_fut = self
-            .connection_model_definitions_cache
-            .get_or_insert_with_fn(key.clone(), || async {
-                match self.get_connection_model_definition(&key).await {
-                    Ok(Some(c)) => Ok(c),
-                    Ok(None) => Err(InternalError::key_not_found("model definition", None)),
-                    Err(e) => Err(InternalError::connection_error(
-                        format!(
-                            "Failed to get connection model definition: {}",
-                            e.message().as_ref()
-                        )
-                        .as_str(),
-                        None,
-                    )),
-                }
-            });
+        self.spawn_shadow(
+            config,
+            shadow,
+            None,
+            primary,
+            headers,
+            query_params,
+            secret,
+            context,
+        )
+        .await
+        .map(|(res, diff)| (res, Some(diff)))
+    }
 
-        let secret_fut =
-            self.secrets_cache
-                .get_or_insert_with_fn(connection.as_ref().clone(), || async {
-                    match self
-                        .secrets_client
-                        .get(&connection.secrets_service_id, &connection.ownership.id)
-                        .map(|v| Some(v).transpose())
-                        .await
-                    {
-                        Ok(Some(c)) => Ok(c.as_value()?),
-                        Ok(None) => Err(InternalError::key_not_found("secret", None)),
-                        Err(e) => Err(InternalError::connection_error(
-                            format!("Failed to get secret: {}", e.message().as_ref()).as_str(),
-                            None,
-                        )),
+    /// Sends the request answered by `primary` to the shadow definition in a task of its own,
+    /// which resolves the shadow definition, records the differences between both responses
+    /// and resolves to them. Successful responses are compared through `mapping`, the way the
+    /// caller receives them, when given. Returns the primary response, its body read so that
+    /// it can be compared, right away: the shadow call neither delays it nor is bound by its
+    /// timeout and retries.
+    #[allow(clippy::too_many_arguments)]
+    async fn spawn_shadow(
+        &self,
+        config: &ConnectionModelDefinition,
+        shadow: Id,
+        mapping: Option<ShadowMapping>,
+        primary: reqwest::Response,
+        headers: HeaderMap,
+        query_params: &HashMap<String, String>,
+        secret: &Value,
+        context: Option<Vec<u8>>,
+    ) -> Result<(reqwest::Response, JoinHandle<Option<ShadowDiff>>), IntegrationOSError> {
+        let status = primary.status();
+        let version = primary.version();
+        let primary_headers = primary.headers().clone();
+        let bytes = self.read_response(primary).await?;
+
+        let diff = tokio::spawn({
+            let destination = self.clone();
+            let config = config.clone();
+            let primary_body = body_to_value(&bytes);
+            let query_params = query_params.clone();
+            let secret = secret.clone();
+            async move {
+                let shadow = destination.get_shadow_definition(&config, shadow).await?;
+                let shadowed = match destination
+                    .execute_model_definition(&shadow, headers, &query_params, &secret, context)
+                    .await
+                {
+                    Ok(res) => {
+                        let shadow_status = res.status();
+                        destination
+                            .read_response(res)
+                            .await
+                            .map(|bytes| (shadow_status, body_to_value(&bytes)))
+                            .map_err(|e| e.to_string())
+                    }
+                    Err(e) => Err(e.to_string()),
+                };
+
+                let map = |definition: &ConnectionModelDefinition, status: StatusCode, body| {
+                    match &mapping {
+                        Some(mapping) if status.is_success() => mapping.map(definition, body),
+                        _ => Ok(body),
+                    }
+                };
+                let primary_body = match map(&config, status, primary_body) {
+                    Ok(body) => body,
+                    Err(e) => {
+                        warn!(
+                            "Could not map the response of {} to compare it with its shadow: {e}",
+                            config.id
+                        );
+                        return None;
                     }
+                };
+                let shadowed = shadowed.and_then(|(status, body)| {
+                    map(&shadow, status, body)
+                        .map(|body| (status.as_u16(), body))
+                        .map_err(|e| e.to_string())
                 });
 
-        let Action::Unified {
-            action: _,
-            id,
-            name,
-        } = &action
-        else {
-            return Err(InternalError::invalid_argument(
-                "Sent a passthrough to the unified send to destination",
-                None,
-            ));
-        };
+                let diff = ShadowDiff::new(
+                    config.id,
+                    shadow.id,
+                    (status.as_u16(), &primary_body),
+                    shadowed
+                        .as_ref()
+                        .map(|(status, body)| (*status, body))
+                        .map_err(String::as_str),
+                );
+                destination.record_shadow_diff(diff.clone());
+                Some(diff)
+            }
+        });
 
-        let schema_key = (connection.platform.clone(), name.clone());
+        let mut response = http::Response::new(bytes);
+        *response.status_mut() = status;
+        *response.version_mut() = version;
+        *response.headers_mut() = primary_headers;
 
-        let schema_fut = self
-            .connection_model_schemas_cache
-            .get_or_insert_with_filter(
-                &schema_key,
-                self.connection_model_schemas_store.clone(),
-                doc! {
-                    "connectionPlatform": connection.platform.as_ref(),
-                    "mapping.commonModelName": name.as_ref(),
-                },
-                Some(
-                    FindOneOptions::builder()
-                        .collation(Some(
-                            Collation::builder()
-                                .strength(CollationStrength::Secondary)
-                                .locale("en")
-                                .build(),
-                        ))
-                        .build(),
-                ),
-            );
+        Ok((reqwest::Response::from(response), diff))
+    }
 
-        tracing::debug!("Joining futures for {schema_key:?}");
+    /// Executes the model definition, giving up when the platform doesn't answer within the
+    /// timeout of the definition
+    pub async fn execute_model_definition_with_timeout(
+        &self,
+        config: &ConnectionModelDefinition,
+        headers: HeaderMap,
+        query_params: &HashMap<String, String>,
+        secret: &Value,
+        context: Option<Vec<u8>>,
+    ) -> Result<Option<reqwest::Response>, IntegrationOSError> {
+        let call = self.execute_model_definition(config, headers, query_params, secret, context);
 
-        let join_result = join!(config_fut, secret_fut, schema_fut);
+        let Some(timeout) = config.timeout else {
+            return call.await.map(Some);
+        };
 
-        let config = join_result.0.map_err(|e| {
-            error!("Could not find connection model definition for destination with cache key {:?}: {:?}", key, e);
+        match tokio::time::timeout(Duration::from_millis(timeout.timeout_millis), call).await {
+            Ok(res) => res.map(Some),
+            Err(_) => {
+                warn!(
+                    "Connection model definition {} timed out after {}ms",
+                    config.id, timeout.timeout_millis
+                );
+                Ok(None)
+            }
+        }
+    }
 
-            InternalError::key_not_found("model definition", None)
-        })?;
-        tracing::debug!(
-            "Connection model definition found for destination with cache key {:?}",
-            key
-        );
+    /// Executes the model definition like [`Self::execute_model_definition_with_timeout`],
+    /// making another attempt for as long as the platform answers with a status the retry
+    /// policy retries. Calls cancelled by the call timeout are retried like 504 answers, unless
+    /// they write, as the platform may have applied the write before the call was cancelled.
+    /// Retries never come sooner than the `Retry-After` of the answer asks. Answers asking to
+    /// wait longer than `max_retry_after`, or than a call may take, are returned right away
+    /// instead.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn execute_model_definition_with_retries(
+        &self,
+        config: &ConnectionModelDefinition,
+        headers: HeaderMap,
+        query_params: &HashMap<String, String>,
+        secret: &Value,
+        context: Option<Vec<u8>>,
+        retry_policy: &DownstreamRetryPolicy,
+    ) -> Result<Option<reqwest::Response>, IntegrationOSError> {
+        let mut attempt = 1;
+        loop {
+            let executed = self
+                .execute_model_definition_with_timeout(
+                    config,
+                    headers.clone(),
+                    query_params,
+                    secret,
+                    context.clone(),
+                )
+                .await;
 
-        let mut secret = join_result.1.map_err(|e| {
-            error!(
-                "Error getting secret for destination with cache key {:?}: {e}",
-                key
-            );
-            InternalError::key_not_found(e.to_string().as_str(), None)
-        })?;
+            let (status, retry_wait) = match &executed {
+                Ok(Some(res)) => (res.status(), retry_after(res.headers(), Utc::now())),
+                Err(e) if is_call_timeout(e) && !is_write(config) => {
+                    (StatusCode::GATEWAY_TIMEOUT, None)
+                }
+                Ok(None) | Err(_) => return executed,
+            };
+            if !retry_policy.should_retry(attempt, status.as_u16()) {
+                return executed;
+            }
 
-        tracing::debug!("Secret found for destination with cache key {:?}", key);
+            let backoff = match retry_wait {
+                Some(wait) if wait > self.retry_after_limit() => {
+                    warn!(
+                        "Connection model definition {} answered {status} asking to retry in {wait:?}, giving up",
+                        config.id
+                    );
+                    return executed;
+                }
+                Some(wait) => retry_policy.backoff(attempt).max(wait),
+                None => retry_policy.backoff(attempt),
+            };
+            warn!(
+                "Connection model definition {} answered {status} on attempt {attempt}, retrying in {backoff:?}",
+                config.id
+            );
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
 
-        let cms = join_result.2.map_err(|e| {
-            InternalError::key_not_found(&format!("model schema {name} for destination: {e}"), None)
-        })?;
+    /// Answers a unified call whose platform didn't respond in time with the fallback of the
+    /// definition
+    async fn timeout_fallback(
+        &self,
+        config: &ConnectionModelDefinition,
+        key: &CachedResponseKey,
+        mut metadata: Value,
+    ) -> Result<UnifiedResponse, IntegrationOSError> {
+        let fallback = config.timeout.map(|t| t.fallback).unwrap_or_default();
+        if let Some(meta) = metadata.as_object_mut() {
+            meta.insert("fallback".to_string(), json!(fallback));
+        }
 
-        tracing::debug!(
-            "Connection model schema found for destination with cache key {:?}",
-            key
-        );
+        let timed_out = |metadata: &Value| {
+            InternalError::timeout(
+                &format!("Platform did not respond in time. ID: {}", config.id),
+                None,
+            )
+            .set_meta(metadata)
+        };
 
-        let ConnectionModelSchema {
-            id: schema_id,
-            mapping,
-            ..
-        } = cms;
+        let (status, headers, mut body) = match fallback {
+            TimeoutFallback::Error => return Err(timed_out(&metadata)),
+            TimeoutFallback::Empty => {
+                let unified = if config.action_name == CrudAction::GetMany {
+                    Value::Array(Default::default())
+                } else {
+                    Value::Object(Default::default())
+                };
+                (
+                    StatusCode::OK,
+                    HeaderMap::new(),
+                    json!({ "unified": unified }),
+                )
+            }
+            TimeoutFallback::LastCached => match self.fallback_responses.get(key).await {
+                Some(cached) => cached,
+                None => return Err(timed_out(&metadata)),
+            },
+        };
+
+        if let Value::Object(ref mut body) = body {
+            body.insert("meta".to_string(), metadata.clone());
+        }
+
+        let mut response = Response::new(body);
+        *response.status_mut() = status;
+        *response.headers_mut() = headers;
+
+        Ok(UnifiedResponse { response, metadata })
+    }
+
+    /// Hook run on the response of every unified call. A successful write drops the cached
+    /// responses of the model it was made for through the same connection, before the response
+    /// itself is cached.
+    async fn on_unified_response(
+        &self,
+        config: &ConnectionModelDefinition,
+        key: CachedResponseKey,
+        response: &Response<Value>,
+    ) {
+        if is_write(config) && response.status().is_success() {
+            self.invalidate_cached_responses(key.connection_id, &key.common_model);
+        }
+        self.cache_fallback_response(config, key, response).await;
+    }
+
+    /// Drops the cached responses of `common_model` for the connection, so that its next read
+    /// is answered by the platform
+    pub fn invalidate_cached_responses(&self, connection_id: Id, common_model: &str) {
+        let common_model = Arc::<str>::from(common_model);
+        let invalidated = self
+            .fallback_responses
+            .invalidate_entries_if(move |key, _| {
+                key.connection_id == connection_id && key.common_model == common_model
+            });
+        if let Err(e) = invalidated {
+            error!("Failed to invalidate the cached responses of connection {connection_id}: {e}");
+        }
+    }
+
+    /// Keeps the response for the definitions falling back to the last cached response
+    async fn cache_fallback_response(
+        &self,
+        config: &ConnectionModelDefinition,
+        key: CachedResponseKey,
+        response: &Response<Value>,
+    ) {
+        if let Some(timeout) = config.timeout {
+            if timeout.fallback == TimeoutFallback::LastCached {
+                self.fallback_responses
+                    .insert(
+                        key,
+                        (
+                            response.status(),
+                            response.headers().clone(),
+                            response.body().clone(),
+                        ),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    /// Id of the shadow definition of the given definition if this request is sampled. Writes
+    /// are never shadowed, the platform would be written to twice.
+    fn sampled_shadow(&self, config: &ConnectionModelDefinition) -> Option<Id> {
+        if is_write(config) {
+            return None;
+        }
+        config
+            .shadow
+            .as_ref()
+            .filter(|s| s.should_sample())
+            .map(|s| s.connection_model_definition_id)
+    }
+
+    /// Shadow definition `shadow` of the given definition, through the shadow definitions cache
+    async fn get_shadow_definition(
+        &self,
+        config: &ConnectionModelDefinition,
+        shadow: Id,
+    ) -> Option<ConnectionModelDefinition> {
+        match self
+            .shadow_definitions_cache
+            .get_or_insert_with_filter(
+                shadow,
+                self.connection_model_definitions_store.clone(),
+                doc! { "_id": shadow.to_string() },
+            )
+            .await
+        {
+            Ok(shadow) => Some(shadow),
+            Err(e) => {
+                error!(
+                    "Failed to get shadow connection model definition for {}: {e}",
+                    config.id
+                );
+                None
+            }
+        }
+    }
+
+    fn record_shadow_diff(&self, diff: ShadowDiff) {
+        if !diff.is_match() {
+            warn!(
+                "Shadow connection model definition {} diverged from {}: {:?} {:?}",
+                diff.shadow_connection_model_definition_id,
+                diff.connection_model_definition_id,
+                diff.differences,
+                diff.shadow_error
+            );
+        }
+
+        let store = self.shadow_diffs_store.clone();
+        tokio::spawn(async move {
+            if let Err(e) = store.create_one(&diff).await {
+                error!("Failed to save shadow diff: {e}");
+            }
+        });
+    }
+
+    /// Sends a unified call through `connection`. The request is mapped from the common model to
+    /// the platform, sent with retries, a refresh of a rejected token and a shadow call when
+    /// sampled, and its response is mapped back to the common model.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_to_destination_unified(
+        &self,
+        connection: Arc<Connection>,
+        action: Action,
+        include_passthrough: bool,
+        environment: Environment,
+        mut headers: HeaderMap,
+        mut query_params: HashMap<String, String>,
+        body: Option<Value>,
+    ) -> Result<UnifiedResponse, IntegrationOSError> {
+        self.filter_forwarded_headers(&mut headers);
+        self.rate_limiter.acquire(&connection)?;
+
+        let Action::Unified {
+            action: _,
+            id,
+            name,
+        } = &action
+        else {
+            return Err(InternalError::invalid_argument(
+                "Sent a passthrough to the unified send to destination",
+                None,
+            ));
+        };
+
+        let (config, mut secret, cms) = self.resolve_unified(&connection, &action, name).await?;
 
         if let Some(id) = id {
             let secret = &mut secret;
@@ -371,7 +1081,7 @@ impl UnifiedDestination {
         let schema_script_namespace = if self.secrets_cache.max_capacity() == 0 {
             "$".to_string() + &uuid::Uuid::new_v4().simple().to_string()
         } else {
-            schema_id.to_string().replace([':', '-'], "_")
+            cms.id.to_string().replace([':', '-'], "_")
         };
 
         let mut metadata = json!({
@@ -393,222 +1103,107 @@ impl UnifiedDestination {
             "connectionKey": connection.key,
         });
 
-        body = if let Some(body) = body {
-            if let Some(js) = mapping.as_ref().map(|m| m.from_common_model.as_str()) {
-                debug!(
-                    "Mapping request body {}\nUsing js {js}",
-                    serde_json::to_string_pretty(&body)
-                        .map_err(|e| {
-                            error!("Failed to convert body to pretty string for connection model. ID: {}, Body: {}, Error: {}", config.id, body, e);
-                        })
-                        .unwrap_or_default(),
-                );
-
-                let ns: String = schema_script_namespace.clone() + "_mapFromCommonModel";
-                JS_RUNTIME
-                    .with_borrow_mut(|script| script.add_script(&ns, "mapFromCommonModel", js))
-                    .map_err(|e| {
-                        error!("Failed to create request schema mapping script for connection model. ID: {}, Error: {}", config.id, e);
-
-                        ApplicationError::bad_request(
-                            &format!("Failed while creating request schema mapping script: {e}"),
-                            None,
-                        )
-                        .set_meta(&metadata)
-                    })?;
-                let body = JS_RUNTIME
-                    .with_borrow_mut(|script| script.call_namespace(&ns, body))
-                    .map_err(|e| {
-                        error!("Failed to run request schema mapping script for connection model. ID: {}, Error: {}", config.id, e);
-
-                        ApplicationError::bad_request(
-                            &format!("Failed while running request schema mapping script: {e}"),
-                            None,
-                        )
-                        .set_meta(&metadata)
-                    })?;
-
-                tokio::task::yield_now().await;
-
-                let body = remove_nulls(&body);
-
-                debug!(
-                    "Mapped body to {}",
-                    serde_json::to_string_pretty(&body)
-                        .map_err(|e| {
-                            error!("Failed to convert mapped body to pretty string. ID: {}, Body: {}, Error: {}", config.id, body, e);
-                        })
-                        .unwrap_or_default(),
-                );
-
-                Some(body)
-            } else {
-                debug!(
-                    "No js for schema mapping to common model {name} for {}",
-                    connection.platform
-                );
-                Some(body)
+        let mut body = map_from_common_model(
+            &config,
+            name,
+            &cms,
+            &schema_script_namespace,
+            body,
+            &metadata,
+        )
+        .await?;
+
+        let capped_read = match (&config.action_name, config.pagination_limits) {
+            (CrudAction::GetMany, Some(limits)) => {
+                Some(CappedRead::start(limits, &mut query_params))
             }
-        } else {
-            debug!("No body to map");
-            None
+            _ => None,
         };
 
-        if let Some(CrudMapping {
-            from_common_model: Some(js),
-            ..
-        }) = &config.mapping
-        {
-            if !js.is_empty() {
-                let ns: String = crud_script_namespace.clone() + "_mapFromCrudRequest";
-                JS_RUNTIME
-                    .with_borrow_mut(|script| script.add_script(&ns, "mapCrudRequest", js.as_str()))
-                    .map_err(|e| {
-                        error!("Failed to create request crud mapping script for connection model. ID: {}, JS: {}, Error: {}", config.id, js, e);
-                        ApplicationError::bad_request(&e.to_string(), None).set_meta(&metadata)
-                    })?;
-
-                const PASSTHROUGH_PARAMS: &str = "passthroughForward";
-                const PASSHTROUGH_HEADERS: &str = "x-integrationos-passthrough-forward";
-
-                if let Some(custom_params) = query_params.remove(PASSTHROUGH_PARAMS) {
-                    let pairs = custom_params.split('&').filter_map(|pair| {
-                        pair.split_once('=')
-                            .map(|(a, b)| (a.to_owned(), b.to_owned()))
-                    });
-                    query_params.extend(pairs);
-                }
-
-                if let Some(custom_headers) = headers.remove(PASSHTROUGH_HEADERS) {
-                    let pairs = custom_headers
-                        .to_str()
-                        .map_err(|e| {
-                            error!(
-                                "Failed to convert custom headers to string. ID {:?}, Error: {:?}",
-                                config.id, e
-                            );
-                            InternalError::invalid_argument(&e.to_string(), None)
-                                .set_meta(&metadata)
-                        })?
-                        .split(';')
-                        .filter_map(|pair| pair.split_once('='))
-                        .filter_map(|(a, b)| {
-                            match (HeaderName::from_str(a).ok(), HeaderValue::try_from(b).ok()) {
-                                (Some(a), Some(b)) => Some((Some(a), b)),
-                                _ => None,
-                            }
-                        });
-                    headers.extend(pairs);
-                }
-
-                let request = RequestCrudBorrowed {
-                    query_params: &query_params,
-                    headers: &headers,
-                    path_params: id.as_ref().map(|id| PathParams { id }),
-                };
-
-                debug!(
-                    "Mapping request crud {}\nUsing js {js}",
-                    serde_json::to_string_pretty(&request)
-                        .map_err(|e| {
-                            error!("Failed to convert request crud to pretty string. ID: {}, Request: {:?}, Error: {}", config.id, request, e);
-                        })
-                        .unwrap_or_default(),
-                );
-
-                let res: RequestCrud = JS_RUNTIME
-                    .with_borrow_mut(|script| script.call_namespace(&ns, request))
-                    .map_err(|e| {
-                        error!("Failed to run request crud mapping script for connection model. ID: {}, Error: {}", config.id, e);
-
-                        ApplicationError::bad_request(
-                            &format!("Failed while running request crud mapping script: {e}"),
-                            None,
-                        )
-                        .set_meta(&metadata)
-                    })?;
-
-                debug!(
-                    "Mapped request crud to {}",
-                    serde_json::to_string_pretty(&res)
-                        .map_err(|e| {
-                            error!("Failed to convert crud to pretty string. ID: {}, Res: {:?}, Error: {}", config.id, res, e);
-                        })
-                        .unwrap_or_default(),
-                );
-
-                headers = res.headers;
-
-                query_params = res.query_params.unwrap_or_default();
-
-                let secret = &mut secret;
-                if let Value::Object(ref mut sec) = secret {
-                    if let Some(path_params) = res.path_params {
-                        sec.extend(path_params.into_iter().map(|(a, b)| (a, Value::String(b))));
-                    }
-                }
-
-                match (&mut body, res.body) {
-                    (Some(Value::Object(a)), Some(Value::Object(b))) => {
-                        a.extend(b);
-                    }
-                    (body @ None, Some(mapped_body)) => {
-                        body.replace(mapped_body);
-                    }
-                    _ => {}
-                }
-            }
-        }
-
-        let PlatformInfo::Api(api_config) = &config.platform_info;
+        map_crud_request(
+            &config,
+            &crud_script_namespace,
+            id.as_deref(),
+            &mut body,
+            &mut headers,
+            &mut query_params,
+            &mut secret,
+            &metadata,
+        )?;
 
-        if let Some(ModelPaths {
-            request: Some(RequestModelPaths { object: Some(path) }),
-            ..
-        }) = &api_config.paths
-        {
-            if let Some(path) = path.strip_prefix("$.body.") {
-                body = body.map(|body| json!({path: body}));
-                debug!(
-                    "Mapped request body to {path}: {}",
-                    serde_json::to_string_pretty(&body)
-                        .map_err(|e| {
-                            error!("Failed to convert mapped body to pretty string. ID: {}, Body: {:?}, Error: {}", config.id, body, e);
-                        })
-                        .unwrap_or_default(),
-                );
-            }
-        }
+        let context = request_context(&config, body, &metadata)?;
 
         debug!("Executing model definition with config {config:#?}, headers {headers:#?}, query params {query_params:#?}");
 
-        let context = match body {
-            None | Some(Value::Null) => None,
-            _ => Some(serde_json::to_vec(&body).map_err(|e| {
-                error!(
-                    "Failed to convert body to vec. ID: {}, Error: {}",
-                    config.id, e
-                );
-
-                ApplicationError::bad_request(&e.to_string(), None).set_meta(&metadata)
-            })?),
-        };
-
-        let mut latency = 0i64;
-        let mut res = self
-            .execute_model_definition(&config, headers, &query_params, &secret, context)
-            .timed(|_, duration| {
-                latency = duration.as_millis() as i64;
-            })
+        self.apply_default_query_params(&connection, &config, &mut query_params, &secret)
             .await
             .map_err(|e| {
                 error!(
-                    "Failed to execute connection model definition. ID: {}, Error: {:?}",
+                    "Failed to apply default query params. ID: {}, Error: {:?}",
                     config.id, e
                 );
                 e.set_meta(&metadata)
             })?;
 
+        let connection_definition = self
+            .get_connection_definition(&connection)
+            .await
+            .map_err(|e| e.set_meta(&metadata))?;
+        let retry_policy = self.retry_policy_for(&connection_definition);
+
+        let shadow = self.sampled_shadow(&config);
+
+        let fallback_key = CachedResponseKey {
+            connection_id: connection.id,
+            common_model: name.clone(),
+            request: format!(
+                "{}::{}::{:?}",
+                config.id,
+                id.as_deref().unwrap_or_default(),
+                query_params.iter().collect::<BTreeMap<_, _>>()
+            ),
+        };
+
+        let (executed, latency) = self
+            .execute_with_token_refresh(
+                &connection,
+                &config,
+                &headers,
+                &query_params,
+                &mut secret,
+                &context,
+                retry_policy,
+                &metadata,
+            )
+            .await?;
+
+        if let Some(res) = &executed {
+            self.on_rate_limited(&connection, res);
+        }
+
+        let Some(mut res) = executed else {
+            return self
+                .timeout_fallback(&config, &fallback_key, metadata)
+                .await;
+        };
+
+        if let Some(shadow) = shadow {
+            let mapping = ShadowMapping::for_schema(&cms, &schema_script_namespace);
+            (res, _) = self
+                .spawn_shadow(
+                    &config,
+                    shadow,
+                    mapping,
+                    res,
+                    headers,
+                    &query_params,
+                    &secret,
+                    context,
+                )
+                .await
+                .map_err(|e| e.set_meta(&metadata))?;
+        }
+
         debug!(
             "Executed model definition with status code {}, headers: {:#?}",
             res.status(),
@@ -618,32 +1213,18 @@ impl UnifiedDestination {
         let headers = std::mem::take(res.headers_mut());
 
         if !res.status().is_success() {
-            let status = res.status();
-
-            let mut res = Response::builder()
-                .status(status)
-                .body(res.json().await.map_err(|e| {
-                    error!("Failed to get json body from unsuccessful response. ID: {}, Error: {}", config.id, e);
-
-                    IntegrationOSError::from_err_code(status, &e.to_string(), None)
-                        .set_meta(&metadata)
-                })?)
-                .map_err(|e| {
-                    error!("Failed to create response from builder for unsuccessful response. ID: {}, Error: {}", config.id, e);
-
-                    IntegrationOSError::from_err_code(status, &e.to_string(), None)
-                        .set_meta(&metadata)
-                })?;
-            *res.headers_mut() = headers;
-            return Ok(UnifiedResponse {
-                metadata: metadata.clone(),
-                response: res,
-            });
+            return self
+                .downstream_error_response(&config, res, headers, metadata)
+                .await;
         }
 
         let status = res.status();
 
-        let mut body: Option<Value> = res.json().await.ok();
+        let bytes = self
+            .read_response(res)
+            .await
+            .map_err(|e| e.set_meta(&metadata))?;
+        let body: Option<Value> = serde_json::from_slice(&bytes).ok();
 
         let passthrough = if include_passthrough {
             body.clone()
@@ -663,397 +1244,273 @@ impl UnifiedDestination {
                 .unwrap_or_default(),
         );
 
-        let pagination = if config.action_name == CrudAction::GetMany {
-            if let Some(CrudMapping {
-                to_common_model: Some(js),
-                ..
-            }) = &config.mapping
-            {
-                if !js.is_empty() {
-                    let ns: String = crud_script_namespace + "_mapToCrudRequest";
-                    JS_RUNTIME
-                        .with_borrow_mut(|script| {
-                            script.add_script(&ns, "mapCrudRequest", js.as_str())
-                        })
-                        .map_err(|e| {
-                            error!("Failed to create response crud mapping script for connection model. ID: {}, JS: {}, Error: {}", config.id, js, e);
-
-                            ApplicationError::bad_request(&e.to_string(), None).set_meta(&metadata)
-                        })?;
-
-                    let pagination = if let (
-                        Some(ModelPaths {
-                            response:
-                                Some(ResponseModelPaths {
-                                    cursor: Some(path), ..
-                                }),
-                            ..
-                        }),
-                        Some(body),
-                    ) = (&api_config.paths, &body)
-                    {
-                        let wrapped_body = json!({"body":body});
-                        let mut bodies =
-                            jsonpath_lib::select(&wrapped_body, path).map_err(|e| {
-                                error!("Failed to select cursor at response path. ID: {}, Path: {}, Error: {}", config.id, path, e);
-
-                                ApplicationError::bad_request(&e.to_string(), None)
-                                    .set_meta(&metadata)
-                            })?;
-                        if bodies.len() != 1 {
-                            Some(Value::Null)
-                        } else {
-                            Some(bodies.remove(0).clone())
-                        }
-                    } else {
-                        None
-                    };
-
-                    let res_to_map = ResponseCrudToMap {
-                        headers: &headers,
-                        pagination,
-                        request: ResponseCrudToMapRequest {
-                            query_params: &query_params,
-                        },
-                    };
-
-                    debug!(
-                        "Mapping response crud {}\nUsing js {js}",
-                        serde_json::to_string_pretty(&res_to_map).map_err(|e| {
-                            error!("Failed to convert response crud to pretty string. ID: {}, Response to Map: {:?}, Error: {}", config.id, res_to_map, e);
-                        })
-                        .unwrap_or_default(),
-                    );
-
-                    let res: ResponseCrud = JS_RUNTIME
-                        .with_borrow_mut(|script| script.call_namespace(&ns, &res_to_map))
-                        .map_err(|e| {
-                            ApplicationError::bad_request(
-                                &format!("Failed while running response crud mapping script. ID: {}, Error: {}", config.id, e),
-                                None,
-                            )
-                            .set_meta(&metadata)
-                        })?;
-
-                    tokio::task::yield_now().await;
-
-                    debug!(
-                        "Mapped response crud to {}",
-                        serde_json::to_string_pretty(&res).map_err(|e| {
-                            error!("Failed to convert response crud to pretty string. ID: {}, Response: {:?}, Error: {}", config.id, res, e);
-
-                            InternalError::invalid_argument(&e.to_string(), None)
-                                .set_meta(&metadata)
-                        })?
-                    );
-
-                    res.pagination
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        } else {
-            None
+        let pagination = map_response_pagination(
+            &config,
+            &crud_script_namespace,
+            &body,
+            &headers,
+            &query_params,
+            &metadata,
+        )
+        .await?;
+
+        let body = match select_response_body(&config, environment, body, &headers, &metadata)? {
+            ControlFlow::Continue(body) => body,
+            ControlFlow::Break(response) => return Ok(response),
         };
 
-        if let Some(ModelPaths {
-            response:
-                Some(ResponseModelPaths {
-                    object: Some(path), ..
-                }),
-            ..
-        }) = &api_config.paths
-        {
-            body = if let Some(body) = body {
-                let wrapped_body = json!({"body":body});
-                let mut bodies = jsonpath_lib::select(&wrapped_body, path).map_err(|e| {
-                    error!(
-                        "Failed to select body at response path. ID {}, Path {}, Error {}",
-                        config.id, path, e
-                    );
-
-                    ApplicationError::bad_request(&e.to_string(), None).set_meta(&metadata)
-                })?;
+        let body = map_to_common_model(
+            &config,
+            name,
+            &cms,
+            &schema_script_namespace,
+            body,
+            &metadata,
+        )
+        .await?;
+
+        let response = unified_body(
+            &config,
+            body,
+            passthrough,
+            pagination,
+            &query_params,
+            capped_read.as_ref(),
+            latency,
+            &mut metadata,
+        )?;
+
+        let res = success_response(&config, status, headers, response, &metadata)?;
+
+        self.on_unified_response(&config, fallback_key, &res).await;
 
-                let is_returning_error = !environment.is_production()
-                    && matches!(config.action_name, CrudAction::GetMany | CrudAction::GetOne);
-                let is_parseable_body = !bodies.is_empty() && bodies.len() == 1;
+        Ok(UnifiedResponse {
+            metadata: metadata.clone(),
+            response: res,
+        })
+    }
 
-                if bodies.is_empty() && is_returning_error {
-                    let error_string = format!(
-                        "Could not map unified model. 3rd party Connection returned an invalid response. Expected model at path {path} but found none.",
-                    );
-                    let mut res = Response::builder()
-                        .status(StatusCode::UNPROCESSABLE_ENTITY)
-                        .body(json!({
-                            "message": error_string,
-                            "passthrough": wrapped_body
-                        }))
-                        .map_err(|e| {
-                            error!("Failed to create response from builder for missing body. ID: {}, Error: {}", config.id, e);
-
-                            IntegrationOSError::from_err_code(
-                                StatusCode::UNPROCESSABLE_ENTITY,
-                                &e.to_string(),
-                                None,
-                            )
-                            .set_meta(&metadata)
-                        })?;
-                    *res.headers_mut() = headers;
-                    return Ok(UnifiedResponse {
-                        metadata: metadata.clone(),
-                        response: res,
-                    });
-                }
+    /// Model definition of the unified action, secret of the connection, its token refreshed
+    /// if about to expire, and schema of the common model, read through their caches
+    async fn resolve_unified(
+        &self,
+        connection: &Connection,
+        action: &Action,
+        name: &Arc<str>,
+    ) -> Result<(ConnectionModelDefinition, Value, ConnectionModelSchema), IntegrationOSError> {
+        let key = Destination {
+            platform: connection.platform.clone(),
+            action: action.clone(),
+            connection_key: connection.key.clone(),
+        };
 
-                if bodies.len() != 1 && is_returning_error {
-                    return Err(InternalError::invalid_argument(
-                        &format!(
-                            "Invalid number of selected bodies ({}) at response path {} for CMD with ID: {}",
-                            bodies.len(),
-                            path,
-                            config.id
-                        ),
+        let config_fut = self
+            .connection_model_definitions_cache
+            .get_or_insert_with_fn(key.clone(), || async {
+                match self.get_connection_model_definition(&key).await {
+                    Ok(Some(c)) => Ok(c),
+                    Ok(None) => Err(InternalError::key_not_found("model definition", None)),
+                    Err(e) => Err(InternalError::connection_error(
+                        format!(
+                            "Failed to get connection model definition: {}",
+                            e.message().as_ref()
+                        )
+                        .as_str(),
                         None,
-                    )
-                    .set_meta(&metadata));
+                    )),
                 }
+            });
 
-                if is_parseable_body {
-                    Some(bodies.remove(0).clone())
-                } else {
-                    None
+        let secret_fut = self
+            .secrets_cache
+            .get_or_insert_with_fn(connection.id, || async {
+                match self
+                    .secrets_client
+                    .get(&connection.secrets_service_id, &connection.ownership.id)
+                    .map(|v| Some(v).transpose())
+                    .await
+                {
+                    Ok(Some(c)) => Ok(c.as_value()?),
+                    Ok(None) => Err(InternalError::key_not_found("secret", None)),
+                    Err(e) => Err(InternalError::connection_error(
+                        format!("Failed to get secret: {}", e.message().as_ref()).as_str(),
+                        None,
+                    )),
                 }
-            } else {
-                None
-            };
-            debug!(
-                "Mapped response body to {path}: {}",
-                serde_json::to_string_pretty(&body)
-                    .map_err(|e| {
-                        error!("Could not convert mapped body to pretty string {body:?}: {e}");
-                    })
-                    .unwrap_or_default(),
-            );
-        }
-
-        if matches!(
-            config.action_name,
-            CrudAction::GetMany | CrudAction::GetOne | CrudAction::Create | CrudAction::Upsert
-        ) {
-            let Some(js) = mapping.as_ref().map(|m| &m.to_common_model) else {
-                return Err(InternalError::invalid_argument(
-                    &format!(
-                        "No js for schema mapping to common model {name} for {}. ID: {}",
-                        connection.platform, config.id
-                    ),
-                    None,
-                )
-                .set_meta(&metadata));
-            };
-            let ns: String = schema_script_namespace + "_mapToCommonModel";
-            JS_RUNTIME
-                .with_borrow_mut(|script| script.add_script(&ns, "mapToCommonModel", js))
-                .map_err(|e| {
-                    error!("Failed to create response schema mapping script for connection model. ID: {}, JS: {}, Error: {}", config.id, js, e);
+            });
 
-                    ApplicationError::bad_request(&e.to_string(), None).set_meta(&metadata)
-                })?;
+        let schema_key = (connection.platform.clone(), name.clone());
 
-            debug!(
-                "Mapping response body {}\nUsing js {js}",
-                serde_json::to_string_pretty(&body)
-                    .map_err(|e| {
-                        error!("Could not convert body to pretty string {body:?}: {e}");
-                    })
-                    .unwrap_or_default(),
+        let schema_fut = self
+            .connection_model_schemas_cache
+            .get_or_insert_with_filter(
+                &schema_key,
+                self.connection_model_schemas_store.clone(),
+                doc! {
+                    "connectionPlatform": connection.platform.as_ref(),
+                    "mapping.commonModelName": name.as_ref(),
+                },
+                Some(
+                    FindOneOptions::builder()
+                        .collation(Some(
+                            Collation::builder()
+                                .strength(CollationStrength::Secondary)
+                                .locale("en")
+                                .build(),
+                        ))
+                        .build(),
+                ),
             );
 
-            const ID_KEY: &str = "id";
-            const MODIFY_TOKEN_KEY: &str = "modifyToken";
-
-            let mapped_body: Value = if let Some(Value::Array(arr)) = body {
-                let mut futs = Vec::with_capacity(arr.len());
-                for body in arr {
-                    futs.push(async {
-                        let res =
-                            JS_RUNTIME.with_borrow_mut(|script| {
-                                script
-                            .add_script(&ns, "mapToCommonModel", js)
-                            .and_then(|_| script.call_namespace(&ns, body))
-                            .map_err(|e| {
-                                ApplicationError::bad_request(
-                                    &format!("Failed while running response schema mapping script: {}. ID: {}", e, config.id),
-                                    None,
-                                )
-                                .set_meta(&metadata)
-                            })
-                            });
-                        tokio::task::yield_now().await;
-                        res.map(|mut body| {
-                            if let Value::Object(map) = &mut body {
-                                if !map.contains_key(MODIFY_TOKEN_KEY) {
-                                    let v = map.get(ID_KEY).cloned().unwrap_or(json!(""));
-                                    map.insert(MODIFY_TOKEN_KEY.to_owned(), v);
-                                }
-                            }
-                            body
-                        })
-                    });
-                }
-                let values = join_all(futs)
-                    .await
-                    .into_iter()
-                    .collect::<Result<Vec<Value>, _>>()?;
-                Value::Array(values)
-            } else if let Some(body) = &body {
-                JS_RUNTIME
-                    .with_borrow_mut(|script| script.call_namespace(&ns, body))
-                    .map(|mut body| {
-                        if let Value::Object(map) = &mut body {
-                            if !map.contains_key(MODIFY_TOKEN_KEY) {
-                                let v = map.get(ID_KEY).cloned().unwrap_or(json!(""));
-                                map.insert(MODIFY_TOKEN_KEY.to_owned(), v);
-                            }
-                        }
-                        body
-                    })
-                    .map_err(|e| {
-                        ApplicationError::bad_request(
-                            &format!("Failed while running response schema mapping script. ID: {}, Error: {}", config.id, e),
-                            None,
-                        )
-                        .set_meta(&metadata)
-                    })?
-            } else if matches!(config.action_name, CrudAction::GetMany) {
-                Value::Array(Default::default())
-            } else {
-                Value::Object(Default::default())
-            };
+        tracing::debug!("Joining futures for {schema_key:?}");
 
-            let mapped_body = remove_nulls(&mapped_body);
+        let join_result = join!(config_fut, secret_fut, schema_fut);
 
-            body = Some(mapped_body);
-        } else if matches!(config.action_name, CrudAction::Update | CrudAction::Delete) {
-            body = None;
-        }
+        let config = join_result.0.map_err(|e| {
+            error!("Could not find connection model definition for destination with cache key {:?}: {:?}", key, e);
 
-        debug!(
-            "Mapped response body to {}",
-            serde_json::to_string_pretty(&body)
-                .map_err(|e| {
-                    error!("Could not convert body to pretty string {body:?}: {e}");
-                })
-                .unwrap_or_default(),
+            InternalError::key_not_found("model definition", None)
+        })?;
+        tracing::debug!(
+            "Connection model definition found for destination with cache key {:?}",
+            key
         );
 
-        let mut response = json!({});
+        let secret = join_result.1.map_err(|e| {
+            error!(
+                "Error getting secret for destination with cache key {:?}: {e}",
+                key
+            );
+            InternalError::key_not_found(e.to_string().as_str(), None)
+        })?;
+        let secret = self.fresh_secret(connection, secret).await?;
 
-        let response_len = if let Some(Value::Array(arr)) = &body {
-            arr.len()
-        } else {
-            0
-        };
+        tracing::debug!("Secret found for destination with cache key {:?}", key);
 
-        let hash = HashedSecret::try_from(json!({
-            "response": &body,
-            "action": config.action_name,
-            "commonModel": config.mapping.as_ref().map(|m| &m.common_model_name),
-        }))
-        .map_err(|e| e.set_meta(&metadata))?;
+        let cms = join_result.2.map_err(|e| {
+            InternalError::key_not_found(&format!("model schema {name} for destination: {e}"), None)
+        })?;
 
-        match body {
-            Some(body) => {
-                const UNIFIED: &str = "unified";
-                const COUNT: &str = "count";
-
-                match response {
-                    Value::Object(ref mut response) => {
-                        if config.action_name == CrudAction::GetCount {
-                            response.insert(UNIFIED.to_string(), json!({ COUNT: body }));
-                        } else {
-                            response.insert(UNIFIED.to_string(), body);
-                        }
-                    }
-                    Value::Number(ref mut count) => {
-                        if config.action_name == CrudAction::GetCount {
-                            response = json!({ UNIFIED: { COUNT: count } });
-                        }
-                    }
-                    _ => {}
-                }
-            }
-            None => tracing::info!(
-                "There was no response body to map for this action. ID: {}",
-                config.id
-            ),
-        };
+        tracing::debug!(
+            "Connection model schema found for destination with cache key {:?}",
+            key
+        );
 
-        if let (true, Some(passthrough), Value::Object(ref mut response)) =
-            (include_passthrough, passthrough, &mut response)
-        {
-            const PASSTHROUGH: &str = "passthrough";
-            response.insert(PASSTHROUGH.to_string(), passthrough);
-        }
+        Ok((config, secret, cms))
+    }
 
-        if let (Some(Value::Object(mut pagination)), Value::Object(ref mut response)) =
-            (pagination, &mut response)
-        {
-            const LIMIT: &str = "limit";
-            if let Some(Ok(limit)) = query_params.get(LIMIT).map(|s| s.parse::<u32>()) {
-                pagination.insert(LIMIT.to_string(), Value::Number(Number::from(limit)));
-            }
-            const PAGE_SIZE: &str = "pageSize";
-            pagination.insert(
-                PAGE_SIZE.to_string(),
-                Value::Number(Number::from(response_len)),
-            );
-            const PAGINATION: &str = "pagination";
-            response.insert(PAGINATION.to_string(), Value::Object(pagination));
-        }
+    /// Executes the model definition with retries, see
+    /// [`Self::execute_model_definition_with_retries`]. An OAuth token the platform rejects, as
+    /// it was revoked or lapsed before its recorded expiry, is refreshed into `secret` and the
+    /// call made once more. Returns the response, none if the platform didn't answer in time,
+    /// along with the time spent on the calls in milliseconds.
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_with_token_refresh(
+        &self,
+        connection: &Connection,
+        config: &ConnectionModelDefinition,
+        headers: &HeaderMap,
+        query_params: &HashMap<String, String>,
+        secret: &mut Value,
+        context: &Option<Vec<u8>>,
+        retry_policy: &DownstreamRetryPolicy,
+        metadata: &Value,
+    ) -> Result<(Option<reqwest::Response>, i64), IntegrationOSError> {
+        let mut latency = 0i64;
+        let mut executed = self
+            .execute_model_definition_with_retries(
+                config,
+                headers.clone(),
+                query_params,
+                secret,
+                context.clone(),
+                retry_policy,
+            )
+            .timed(|_, duration| {
+                latency = duration.as_millis() as i64;
+            })
+            .await
+            .map_err(|e| {
+                error!(
+                    "Failed to execute connection model definition. ID: {}, Error: {:?}",
+                    config.id, e
+                );
+                e.set_meta(metadata)
+            })?;
 
-        if let Value::Object(ref mut response) = &mut response {
-            if let Some(meta) = metadata.as_object_mut() {
-                meta.insert("latency".to_string(), Value::Number(Number::from(latency)));
-                meta.insert("hash".to_string(), Value::String(hash.inner().into()));
+        let rejected = matches!(&executed, Some(res) if res.status() == StatusCode::UNAUTHORIZED);
+        if rejected && matches!(connection.oauth, Some(OAuth::Enabled { .. })) {
+            let refreshed = self
+                .oauth_refresher
+                .refresh_rejected(connection, secret, Utc::now())
+                .await
+                .map_err(|e| e.set_meta(metadata))?;
+            self.secrets_cache.set(&connection.id, &refreshed).await?;
+            if let (Value::Object(secret), Value::Object(refreshed)) = (&mut *secret, refreshed) {
+                secret.extend(refreshed);
             }
 
-            const META: &str = "meta";
-            response.insert(META.to_string(), metadata.clone());
+            warn!(
+                "Platform rejected the access token of connection {}, retrying with a refreshed one",
+                connection.id
+            );
+            executed = self
+                .execute_model_definition_with_retries(
+                    config,
+                    headers.clone(),
+                    query_params,
+                    secret,
+                    context.clone(),
+                    retry_policy,
+                )
+                .timed(|_, duration| {
+                    latency += duration.as_millis() as i64;
+                })
+                .await
+                .map_err(|e| {
+                    error!(
+                        "Failed to execute connection model definition. ID: {}, Error: {:?}",
+                        config.id, e
+                    );
+                    e.set_meta(metadata)
+                })?;
         }
 
-        let mut builder = Response::builder();
+        Ok((executed, latency))
+    }
 
-        if status.is_success() {
-            const STATUS_HEADER: &str = "response-status";
-            builder = builder
-                .header::<&'static str, HeaderValue>(STATUS_HEADER, status.as_u16().into())
-                .status(StatusCode::OK);
-        } else {
-            builder = builder.status(status);
-        }
-        if let Some(builder_headers) = builder.headers_mut() {
-            builder_headers.extend(headers.into_iter());
-        } else {
-            return Err(IntegrationOSError::from_err_code(
-                status,
-                "Could not get headers from builder",
-                None,
-            )
-            .set_meta(&metadata));
-        };
-        let res = builder.body(response).map_err(|e| {
-            error!(
-                "Failed to create response from builder for successful response. ID: {}, Error: {}",
-                config.id, e
-            );
-            IntegrationOSError::from_err_code(status, &e.to_string(), None).set_meta(&metadata)
-        })?;
+    /// Answers an unsuccessful response of the platform with its status and headers, and its
+    /// body normalized to the common error shape, see [`downstream_error_body`]
+    async fn downstream_error_response(
+        &self,
+        config: &ConnectionModelDefinition,
+        res: reqwest::Response,
+        headers: HeaderMap,
+        metadata: Value,
+    ) -> Result<UnifiedResponse, IntegrationOSError> {
+        let status = res.status();
+
+        let bytes = self
+            .read_response(res)
+            .await
+            .map_err(|e| e.set_meta(&metadata))?;
+        let mut res = Response::builder()
+            .status(status)
+            .body(downstream_error_body(config, status, &bytes).map_err(|e| {
+                error!("Failed to get json body from unsuccessful response. ID: {}, Error: {}", config.id, e);
+
+                IntegrationOSError::from_err_code(status, &e.to_string(), None)
+                    .set_meta(&metadata)
+            })?)
+            .map_err(|e| {
+                error!("Failed to create response from builder for unsuccessful response. ID: {}, Error: {}", config.id, e);
 
+                IntegrationOSError::from_err_code(status, &e.to_string(), None)
+                    .set_meta(&metadata)
+            })?;
+        *res.headers_mut() = headers;
         Ok(UnifiedResponse {
-            metadata: metadata.clone(),
+            metadata,
             response: res,
         })
     }
@@ -1063,9 +1520,91 @@ impl UnifiedDestination {
         connection: Option<Arc<Connection>>,
         destination: &Destination,
         headers: HeaderMap,
-        query_params: HashMap<String, String>,
+        mut query_params: HashMap<String, String>,
         context: Option<Vec<u8>>,
     ) -> Result<reqwest::Response, IntegrationOSError> {
+        let (connection, config, secret) =
+            self.resolve_destination(connection, destination).await?;
+        self.rate_limiter.acquire(&connection)?;
+
+        self.apply_default_query_params(&connection, &config, &mut query_params, &secret)
+            .await?;
+
+        let res = self
+            .execute_model_definition(&config, headers, &query_params, &secret, context)
+            .await?;
+        self.on_rate_limited(&connection, &res);
+
+        Ok(res)
+    }
+
+    /// Same as `send_to_destination`, but streams the body straight to the destination when
+    /// the model definition is marked as streaming instead of buffering it in memory first.
+    /// Buffered bodies larger than `max_request_size` are rejected.
+    pub async fn send_to_destination_stream<S>(
+        &self,
+        connection: Option<Arc<Connection>>,
+        destination: &Destination,
+        headers: HeaderMap,
+        mut query_params: HashMap<String, String>,
+        body: S,
+    ) -> Result<reqwest::Response, IntegrationOSError>
+    where
+        S: TryStream + Send + 'static,
+        S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+        Bytes: From<S::Ok>,
+    {
+        let (connection, config, secret) =
+            self.resolve_destination(connection, destination).await?;
+        self.rate_limiter.acquire(&connection)?;
+
+        self.apply_default_query_params(&connection, &config, &mut query_params, &secret)
+            .await?;
+
+        let body = if config.streaming {
+            reqwest::Body::wrap_stream(body)
+        } else {
+            let bytes = body
+                .map_ok(Bytes::from)
+                .map_err(|e| {
+                    let e: Box<dyn std::error::Error + Send + Sync> = e.into();
+                    InternalError::io_err(&format!("Failed to read request body: {e}"), None)
+                })
+                .try_fold(Vec::new(), |mut acc, chunk| async move {
+                    if let Some(max) = self.max_request_size {
+                        if acc.len() + chunk.len() > max {
+                            return Err(ApplicationError::payload_too_large(
+                                &format!("Request body exceeded the maximum size of {max} bytes"),
+                                None,
+                            ));
+                        }
+                    }
+                    acc.extend_from_slice(&chunk);
+                    Ok(acc)
+                })
+                .await?;
+            reqwest::Body::from(bytes)
+        };
+
+        let res = self
+            .execute_model_definition_with_body(
+                &config,
+                headers,
+                &query_params,
+                &secret,
+                Some(body),
+            )
+            .await?;
+        self.on_rate_limited(&connection, &res);
+
+        Ok(res)
+    }
+
+    async fn resolve_destination(
+        &self,
+        connection: Option<Arc<Connection>>,
+        destination: &Destination,
+    ) -> Result<(Arc<Connection>, Arc<ConnectionModelDefinition>, Value), IntegrationOSError> {
         let connection = if let Some(connection) = connection {
             connection
         } else {
@@ -1105,7 +1644,7 @@ impl UnifiedDestination {
 
         let secret = self
             .secrets_cache
-            .get_or_insert_with_fn(connection.as_ref().clone(), || async {
+            .get_or_insert_with_fn(connection.id, || async {
                 match self
                     .secrets_client
                     .get(&connection.secrets_service_id, &connection.ownership.id)
@@ -1121,6 +1660,7 @@ impl UnifiedDestination {
                 }
             })
             .await?;
+        let secret = self.fresh_secret(&connection, secret).await?;
 
         // Template the route for passthrough actions
         let templated_config = match &destination.action {
@@ -1135,7 +1675,1842 @@ impl UnifiedDestination {
             _ => config.clone(),
         };
 
-        self.execute_model_definition(&templated_config, headers, &query_params, &secret, context)
-            .await
+        Ok((connection, templated_config, secret))
+    }
+}
+
+/// Request body mapped from the common model to the platform by the schema mapping
+async fn map_from_common_model(
+    config: &ConnectionModelDefinition,
+    name: &str,
+    cms: &ConnectionModelSchema,
+    namespace: &str,
+    body: Option<Value>,
+    metadata: &Value,
+) -> Result<Option<Value>, IntegrationOSError> {
+    let Some(body) = body else {
+        debug!("No body to map");
+        return Ok(None);
+    };
+    let Some(js) = cms.mapping.as_ref().map(|m| m.from_common_model.as_str()) else {
+        debug!(
+            "No js for schema mapping to common model {name} for {}",
+            cms.connection_platform
+        );
+        return Ok(Some(body));
+    };
+
+    debug!(
+        "Mapping request body {}\nUsing js {js}",
+        serde_json::to_string_pretty(&body)
+            .map_err(|e| {
+                error!("Failed to convert body to pretty string for connection model. ID: {}, Body: {}, Error: {}", config.id, body, e);
+            })
+            .unwrap_or_default(),
+    );
+
+    let ns: String = namespace.to_string() + "_mapFromCommonModel";
+    JS_RUNTIME
+        .with_borrow_mut(|script| script.add_script(&ns, "mapFromCommonModel", js))
+        .map_err(|e| {
+            error!("Failed to create request schema mapping script for connection model. ID: {}, Error: {}", config.id, e);
+
+            ApplicationError::bad_request(
+                &format!("Failed while creating request schema mapping script: {e}"),
+                None,
+            )
+            .set_meta(metadata)
+        })?;
+    let body = JS_RUNTIME
+        .with_borrow_mut(|script| script.call_namespace(&ns, body))
+        .map_err(|e| {
+            error!("Failed to run request schema mapping script for connection model. ID: {}, Error: {}", config.id, e);
+
+            ApplicationError::bad_request(
+                &format!("Failed while running request schema mapping script: {e}"),
+                None,
+            )
+            .set_meta(metadata)
+        })?;
+
+    tokio::task::yield_now().await;
+
+    let body = remove_nulls(&body);
+
+    debug!(
+        "Mapped body to {}",
+        serde_json::to_string_pretty(&body)
+            .map_err(|e| {
+                error!(
+                    "Failed to convert mapped body to pretty string. ID: {}, Body: {}, Error: {}",
+                    config.id, body, e
+                );
+            })
+            .unwrap_or_default(),
+    );
+
+    Ok(Some(body))
+}
+
+/// Maps the query params, headers, path params and body of the request to the platform through
+/// the CRUD mapping of the definition. Params and headers forwarded explicitly by the caller are
+/// handed to the mapping, the path params are added to `secret` for the path to be templated.
+#[allow(clippy::too_many_arguments)]
+fn map_crud_request(
+    config: &ConnectionModelDefinition,
+    namespace: &str,
+    id: Option<&str>,
+    body: &mut Option<Value>,
+    headers: &mut HeaderMap,
+    query_params: &mut HashMap<String, String>,
+    secret: &mut Value,
+    metadata: &Value,
+) -> Result<(), IntegrationOSError> {
+    let Some(CrudMapping {
+        from_common_model: Some(js),
+        ..
+    }) = &config.mapping
+    else {
+        return Ok(());
+    };
+    if js.is_empty() {
+        return Ok(());
+    }
+
+    let ns: String = namespace.to_string() + "_mapFromCrudRequest";
+    JS_RUNTIME
+        .with_borrow_mut(|script| script.add_script(&ns, "mapCrudRequest", js.as_str()))
+        .map_err(|e| {
+            error!("Failed to create request crud mapping script for connection model. ID: {}, JS: {}, Error: {}", config.id, js, e);
+            ApplicationError::bad_request(&e.to_string(), None).set_meta(metadata)
+        })?;
+
+    const PASSTHROUGH_PARAMS: &str = "passthroughForward";
+
+    if let Some(custom_params) = query_params.remove(PASSTHROUGH_PARAMS) {
+        let pairs = custom_params.split('&').filter_map(|pair| {
+            pair.split_once('=')
+                .map(|(a, b)| (a.to_owned(), b.to_owned()))
+        });
+        query_params.extend(pairs);
+    }
+
+    if let Some(custom_headers) = headers.remove(PASSTHROUGH_HEADERS) {
+        let pairs = custom_headers
+            .to_str()
+            .map_err(|e| {
+                error!(
+                    "Failed to convert custom headers to string. ID {:?}, Error: {:?}",
+                    config.id, e
+                );
+                InternalError::invalid_argument(&e.to_string(), None).set_meta(metadata)
+            })?
+            .split(';')
+            .filter_map(|pair| pair.split_once('='))
+            .filter_map(|(a, b)| {
+                match (HeaderName::from_str(a).ok(), HeaderValue::try_from(b).ok()) {
+                    (Some(a), Some(b)) => Some((Some(a), b)),
+                    _ => None,
+                }
+            });
+        headers.extend(pairs);
+    }
+
+    let request = RequestCrudBorrowed {
+        query_params: &*query_params,
+        headers: &*headers,
+        path_params: id.map(|id| PathParams { id }),
+    };
+
+    debug!(
+        "Mapping request crud {}\nUsing js {js}",
+        serde_json::to_string_pretty(&request)
+            .map_err(|e| {
+                error!("Failed to convert request crud to pretty string. ID: {}, Request: {:?}, Error: {}", config.id, request, e);
+            })
+            .unwrap_or_default(),
+    );
+
+    let res: RequestCrud = JS_RUNTIME
+        .with_borrow_mut(|script| script.call_namespace(&ns, request))
+        .map_err(|e| {
+            error!(
+                "Failed to run request crud mapping script for connection model. ID: {}, Error: {}",
+                config.id, e
+            );
+
+            ApplicationError::bad_request(
+                &format!("Failed while running request crud mapping script: {e}"),
+                None,
+            )
+            .set_meta(metadata)
+        })?;
+
+    debug!(
+        "Mapped request crud to {}",
+        serde_json::to_string_pretty(&res)
+            .map_err(|e| {
+                error!(
+                    "Failed to convert crud to pretty string. ID: {}, Res: {:?}, Error: {}",
+                    config.id, res, e
+                );
+            })
+            .unwrap_or_default(),
+    );
+
+    *headers = res.headers;
+
+    *query_params = res.query_params.unwrap_or_default();
+
+    if let Value::Object(ref mut sec) = secret {
+        if let Some(path_params) = res.path_params {
+            sec.extend(path_params.into_iter().map(|(a, b)| (a, Value::String(b))));
+        }
+    }
+
+    match (body, res.body) {
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            a.extend(b);
+        }
+        (body @ None, Some(mapped_body)) => {
+            body.replace(mapped_body);
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Body sent to the platform, nested at the request path of the definition if it has one
+fn request_context(
+    config: &ConnectionModelDefinition,
+    mut body: Option<Value>,
+    metadata: &Value,
+) -> Result<Option<Vec<u8>>, IntegrationOSError> {
+    let PlatformInfo::Api(api_config) = &config.platform_info;
+
+    if let Some(ModelPaths {
+        request: Some(RequestModelPaths { object: Some(path) }),
+        ..
+    }) = &api_config.paths
+    {
+        if let Some(path) = path.strip_prefix("$.body.") {
+            body = body.map(|body| json!({path: body}));
+            debug!(
+                "Mapped request body to {path}: {}",
+                serde_json::to_string_pretty(&body)
+                    .map_err(|e| {
+                        error!("Failed to convert mapped body to pretty string. ID: {}, Body: {:?}, Error: {}", config.id, body, e);
+                    })
+                    .unwrap_or_default(),
+            );
+        }
+    }
+
+    match body {
+        None | Some(Value::Null) => Ok(None),
+        _ => Ok(Some(serde_json::to_vec(&body).map_err(|e| {
+            error!(
+                "Failed to convert body to vec. ID: {}, Error: {}",
+                config.id, e
+            );
+
+            ApplicationError::bad_request(&e.to_string(), None).set_meta(metadata)
+        })?)),
+    }
+}
+
+/// Pagination of a list response, read from its cursor by the CRUD mapping of the definition
+async fn map_response_pagination(
+    config: &ConnectionModelDefinition,
+    namespace: &str,
+    body: &Option<Value>,
+    headers: &HeaderMap,
+    query_params: &HashMap<String, String>,
+    metadata: &Value,
+) -> Result<Option<Value>, IntegrationOSError> {
+    if config.action_name != CrudAction::GetMany {
+        return Ok(None);
+    }
+    let Some(CrudMapping {
+        to_common_model: Some(js),
+        ..
+    }) = &config.mapping
+    else {
+        return Ok(None);
+    };
+    if js.is_empty() {
+        return Ok(None);
+    }
+
+    let ns: String = namespace.to_string() + "_mapToCrudRequest";
+    JS_RUNTIME
+        .with_borrow_mut(|script| script.add_script(&ns, "mapCrudRequest", js.as_str()))
+        .map_err(|e| {
+            error!("Failed to create response crud mapping script for connection model. ID: {}, JS: {}, Error: {}", config.id, js, e);
+
+            ApplicationError::bad_request(&e.to_string(), None).set_meta(metadata)
+        })?;
+
+    let PlatformInfo::Api(api_config) = &config.platform_info;
+    let pagination = if let (
+        Some(ModelPaths {
+            response:
+                Some(ResponseModelPaths {
+                    cursor: Some(path), ..
+                }),
+            ..
+        }),
+        Some(body),
+    ) = (&api_config.paths, body)
+    {
+        let wrapped_body = json!({"body":body});
+        let mut bodies = jsonpath_lib::select(&wrapped_body, path).map_err(|e| {
+            error!(
+                "Failed to select cursor at response path. ID: {}, Path: {}, Error: {}",
+                config.id, path, e
+            );
+
+            ApplicationError::bad_request(&e.to_string(), None).set_meta(metadata)
+        })?;
+        if bodies.len() != 1 {
+            Some(Value::Null)
+        } else {
+            Some(bodies.remove(0).clone())
+        }
+    } else {
+        None
+    };
+
+    let res_to_map = ResponseCrudToMap {
+        headers,
+        pagination,
+        request: ResponseCrudToMapRequest { query_params },
+    };
+
+    debug!(
+        "Mapping response crud {}\nUsing js {js}",
+        serde_json::to_string_pretty(&res_to_map).map_err(|e| {
+            error!("Failed to convert response crud to pretty string. ID: {}, Response to Map: {:?}, Error: {}", config.id, res_to_map, e);
+        })
+        .unwrap_or_default(),
+    );
+
+    let res: ResponseCrud = JS_RUNTIME
+        .with_borrow_mut(|script| script.call_namespace(&ns, &res_to_map))
+        .map_err(|e| {
+            ApplicationError::bad_request(
+                &format!(
+                    "Failed while running response crud mapping script. ID: {}, Error: {}",
+                    config.id, e
+                ),
+                None,
+            )
+            .set_meta(metadata)
+        })?;
+
+    tokio::task::yield_now().await;
+
+    debug!(
+        "Mapped response crud to {}",
+        serde_json::to_string_pretty(&res).map_err(|e| {
+            error!("Failed to convert response crud to pretty string. ID: {}, Response: {:?}, Error: {}", config.id, res, e);
+
+            InternalError::invalid_argument(&e.to_string(), None).set_meta(metadata)
+        })?
+    );
+
+    Ok(res.pagination)
+}
+
+/// Model selected at the response path of the definition, the body as it is if it has none.
+/// Outside of production, reads whose response lacks the model are answered with a 422 holding
+/// the response of the platform, which breaks the flow.
+fn select_response_body(
+    config: &ConnectionModelDefinition,
+    environment: Environment,
+    body: Option<Value>,
+    headers: &HeaderMap,
+    metadata: &Value,
+) -> Result<ControlFlow<UnifiedResponse, Option<Value>>, IntegrationOSError> {
+    let PlatformInfo::Api(api_config) = &config.platform_info;
+    let Some(ModelPaths {
+        response: Some(ResponseModelPaths {
+            object: Some(path), ..
+        }),
+        ..
+    }) = &api_config.paths
+    else {
+        return Ok(ControlFlow::Continue(body));
+    };
+
+    let body = if let Some(body) = body {
+        let wrapped_body = json!({"body":body});
+        let mut bodies = jsonpath_lib::select(&wrapped_body, path).map_err(|e| {
+            error!(
+                "Failed to select body at response path. ID {}, Path {}, Error {}",
+                config.id, path, e
+            );
+
+            ApplicationError::bad_request(&e.to_string(), None).set_meta(metadata)
+        })?;
+
+        let is_returning_error = !environment.is_production()
+            && matches!(config.action_name, CrudAction::GetMany | CrudAction::GetOne);
+        let is_parseable_body = !bodies.is_empty() && bodies.len() == 1;
+
+        if bodies.is_empty() && is_returning_error {
+            let error_string = format!(
+                "Could not map unified model. 3rd party Connection returned an invalid response. Expected model at path {path} but found none.",
+            );
+            let mut res = Response::builder()
+                .status(StatusCode::UNPROCESSABLE_ENTITY)
+                .body(json!({
+                    "message": error_string,
+                    "passthrough": wrapped_body
+                }))
+                .map_err(|e| {
+                    error!(
+                        "Failed to create response from builder for missing body. ID: {}, Error: {}",
+                        config.id, e
+                    );
+
+                    IntegrationOSError::from_err_code(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        &e.to_string(),
+                        None,
+                    )
+                    .set_meta(metadata)
+                })?;
+            *res.headers_mut() = headers.clone();
+            return Ok(ControlFlow::Break(UnifiedResponse {
+                metadata: metadata.clone(),
+                response: res,
+            }));
+        }
+
+        if bodies.len() != 1 && is_returning_error {
+            return Err(InternalError::invalid_argument(
+                &format!(
+                    "Invalid number of selected bodies ({}) at response path {} for CMD with ID: {}",
+                    bodies.len(),
+                    path,
+                    config.id
+                ),
+                None,
+            )
+            .set_meta(metadata));
+        }
+
+        if is_parseable_body {
+            Some(bodies.remove(0).clone())
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+    debug!(
+        "Mapped response body to {path}: {}",
+        serde_json::to_string_pretty(&body)
+            .map_err(|e| {
+                error!("Could not convert mapped body to pretty string {body:?}: {e}");
+            })
+            .unwrap_or_default(),
+    );
+
+    Ok(ControlFlow::Continue(body))
+}
+
+/// Response body mapped to the common model by the schema mapping. Responses drifting from the
+/// schema are normalized as per its drift policy first, see [`ConnectionModelSchema::normalize`].
+/// Writes other than creations answer no body.
+async fn map_to_common_model(
+    config: &ConnectionModelDefinition,
+    name: &str,
+    cms: &ConnectionModelSchema,
+    namespace: &str,
+    body: Option<Value>,
+    metadata: &Value,
+) -> Result<Option<Value>, IntegrationOSError> {
+    let body = if matches!(
+        config.action_name,
+        CrudAction::GetMany | CrudAction::GetOne | CrudAction::Create | CrudAction::Upsert
+    ) {
+        let Some(js) = cms.mapping.as_ref().map(|m| &m.to_common_model) else {
+            return Err(InternalError::invalid_argument(
+                &format!(
+                    "No js for schema mapping to common model {name} for {}. ID: {}",
+                    cms.connection_platform, config.id
+                ),
+                None,
+            )
+            .set_meta(metadata));
+        };
+        let ns: String = namespace.to_string() + "_mapToCommonModel";
+        JS_RUNTIME
+            .with_borrow_mut(|script| script.add_script(&ns, "mapToCommonModel", js))
+            .map_err(|e| {
+                error!("Failed to create response schema mapping script for connection model. ID: {}, JS: {}, Error: {}", config.id, js, e);
+
+                ApplicationError::bad_request(&e.to_string(), None).set_meta(metadata)
+            })?;
+
+        debug!(
+            "Mapping response body {}\nUsing js {js}",
+            serde_json::to_string_pretty(&body)
+                .map_err(|e| {
+                    error!("Could not convert body to pretty string {body:?}: {e}");
+                })
+                .unwrap_or_default(),
+        );
+
+        const ID_KEY: &str = "id";
+        const MODIFY_TOKEN_KEY: &str = "modifyToken";
+
+        let mapped_body: Value = if let Some(Value::Array(arr)) = body {
+            let mut futs = Vec::with_capacity(arr.len());
+            for body in arr {
+                futs.push(async {
+                    let body = cms.normalize(body).map_err(|e| e.set_meta(metadata))?;
+                    let res = JS_RUNTIME.with_borrow_mut(|script| {
+                        script
+                            .add_script(&ns, "mapToCommonModel", js)
+                            .and_then(|_| script.call_namespace(&ns, body))
+                            .map_err(|e| {
+                                ApplicationError::bad_request(
+                                    &format!(
+                                        "Failed while running response schema mapping script: {}. ID: {}",
+                                        e, config.id
+                                    ),
+                                    None,
+                                )
+                                .set_meta(metadata)
+                            })
+                    });
+                    tokio::task::yield_now().await;
+                    res.map(|mut body| {
+                        if let Value::Object(map) = &mut body {
+                            if !map.contains_key(MODIFY_TOKEN_KEY) {
+                                let v = map.get(ID_KEY).cloned().unwrap_or(json!(""));
+                                map.insert(MODIFY_TOKEN_KEY.to_owned(), v);
+                            }
+                        }
+                        body
+                    })
+                });
+            }
+            let values = join_all(futs)
+                .await
+                .into_iter()
+                .collect::<Result<Vec<Value>, _>>()?;
+            Value::Array(values)
+        } else if let Some(body) = body {
+            let body = cms.normalize(body).map_err(|e| e.set_meta(metadata))?;
+            JS_RUNTIME
+                .with_borrow_mut(|script| script.call_namespace(&ns, &body))
+                .map(|mut body| {
+                    if let Value::Object(map) = &mut body {
+                        if !map.contains_key(MODIFY_TOKEN_KEY) {
+                            let v = map.get(ID_KEY).cloned().unwrap_or(json!(""));
+                            map.insert(MODIFY_TOKEN_KEY.to_owned(), v);
+                        }
+                    }
+                    body
+                })
+                .map_err(|e| {
+                    ApplicationError::bad_request(
+                        &format!(
+                            "Failed while running response schema mapping script. ID: {}, Error: {}",
+                            config.id, e
+                        ),
+                        None,
+                    )
+                    .set_meta(metadata)
+                })?
+        } else if matches!(config.action_name, CrudAction::GetMany) {
+            Value::Array(Default::default())
+        } else {
+            Value::Object(Default::default())
+        };
+
+        Some(remove_nulls(&mapped_body))
+    } else if matches!(config.action_name, CrudAction::Update | CrudAction::Delete) {
+        None
+    } else {
+        body
+    };
+
+    debug!(
+        "Mapped response body to {}",
+        serde_json::to_string_pretty(&body)
+            .map_err(|e| {
+                error!("Could not convert body to pretty string {body:?}: {e}");
+            })
+            .unwrap_or_default(),
+    );
+
+    Ok(body)
+}
+
+/// Body of a unified response: the common model output, the response of the platform if asked
+/// for, the pagination and the metadata of the call, into which its latency and the hash of the
+/// output are added
+#[allow(clippy::too_many_arguments)]
+fn unified_body(
+    config: &ConnectionModelDefinition,
+    body: Option<Value>,
+    passthrough: Option<Value>,
+    pagination: Option<Value>,
+    query_params: &HashMap<String, String>,
+    capped_read: Option<&CappedRead>,
+    latency: i64,
+    metadata: &mut Value,
+) -> Result<Value, IntegrationOSError> {
+    let mut response = json!({});
+
+    let response_len = if let Some(Value::Array(arr)) = &body {
+        arr.len()
+    } else {
+        0
+    };
+
+    let hash = HashedSecret::try_from(json!({
+        "response": &body,
+        "action": config.action_name,
+        "commonModel": config.mapping.as_ref().map(|m| &m.common_model_name),
+    }))
+    .map_err(|e| e.set_meta(metadata))?;
+
+    match body {
+        Some(body) => {
+            const UNIFIED: &str = "unified";
+            const COUNT: &str = "count";
+
+            match response {
+                Value::Object(ref mut response) => {
+                    if config.action_name == CrudAction::GetCount {
+                        response.insert(UNIFIED.to_string(), json!({ COUNT: body }));
+                    } else {
+                        response.insert(UNIFIED.to_string(), body);
+                    }
+                }
+                Value::Number(ref mut count) => {
+                    if config.action_name == CrudAction::GetCount {
+                        response = json!({ UNIFIED: { COUNT: count } });
+                    }
+                }
+                _ => {}
+            }
+        }
+        None => tracing::info!(
+            "There was no response body to map for this action. ID: {}",
+            config.id
+        ),
+    };
+
+    if let (Some(passthrough), Value::Object(ref mut response)) = (passthrough, &mut response) {
+        const PASSTHROUGH: &str = "passthrough";
+        response.insert(PASSTHROUGH.to_string(), passthrough);
+    }
+
+    if let (Some(Value::Object(mut pagination)), Value::Object(ref mut response)) =
+        (pagination, &mut response)
+    {
+        paginate(&mut pagination, query_params, response_len, capped_read);
+        const PAGINATION: &str = "pagination";
+        response.insert(PAGINATION.to_string(), Value::Object(pagination));
+    }
+
+    if let Value::Object(ref mut response) = &mut response {
+        if let Some(meta) = metadata.as_object_mut() {
+            meta.insert("latency".to_string(), Value::Number(Number::from(latency)));
+            meta.insert("hash".to_string(), Value::String(hash.inner().into()));
+        }
+
+        const META: &str = "meta";
+        response.insert(META.to_string(), metadata.clone());
+    }
+
+    Ok(response)
+}
+
+/// Completes the pagination of a list response with its limit and page size, and caps it as per
+/// the pagination limits of the definition
+fn paginate(
+    pagination: &mut Map<String, Value>,
+    query_params: &HashMap<String, String>,
+    response_len: usize,
+    capped_read: Option<&CappedRead>,
+) {
+    const LIMIT: &str = "limit";
+    if let Some(Ok(limit)) = query_params.get(LIMIT).map(|s| s.parse::<u32>()) {
+        pagination.insert(LIMIT.to_string(), Value::Number(Number::from(limit)));
+    }
+    const PAGE_SIZE: &str = "pageSize";
+    pagination.insert(
+        PAGE_SIZE.to_string(),
+        Value::Number(Number::from(response_len)),
+    );
+    if let Some(capped_read) = capped_read {
+        capped_read.finish(pagination, response_len as u64);
+    }
+}
+
+/// Successful unified response, answered with a 200 and the status of the platform in the
+/// `response-status` header
+fn success_response(
+    config: &ConnectionModelDefinition,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Value,
+    metadata: &Value,
+) -> Result<Response<Value>, IntegrationOSError> {
+    let mut builder = Response::builder();
+
+    if status.is_success() {
+        const STATUS_HEADER: &str = "response-status";
+        builder = builder
+            .header::<&'static str, HeaderValue>(STATUS_HEADER, status.as_u16().into())
+            .status(StatusCode::OK);
+    } else {
+        builder = builder.status(status);
+    }
+    if let Some(builder_headers) = builder.headers_mut() {
+        builder_headers.extend(headers.into_iter());
+    } else {
+        return Err(IntegrationOSError::from_err_code(
+            status,
+            "Could not get headers from builder",
+            None,
+        )
+        .set_meta(metadata));
+    };
+    builder.body(body).map_err(|e| {
+        error!(
+            "Failed to create response from builder for successful response. ID: {}, Error: {}",
+            config.id, e
+        );
+        IntegrationOSError::from_err_code(status, &e.to_string(), None).set_meta(metadata)
+    })
+}
+
+/// Whether the call to the platform was cancelled by the call timeout
+fn is_call_timeout(e: &IntegrationOSError) -> bool {
+    matches!(
+        e,
+        IntegrationOSError::Internal(InternalError::Timeout { subtype: Some(subtype), .. })
+            if subtype == CALL_TIMEOUT
+    )
+}
+
+/// Whether a call of the definition changes the records of its model. Custom actions are told
+/// apart by their HTTP method.
+fn is_write(config: &ConnectionModelDefinition) -> bool {
+    match config.action_name {
+        CrudAction::GetOne | CrudAction::GetMany | CrudAction::GetCount => false,
+        CrudAction::Custom => !config.action.is_safe(),
+        CrudAction::Upsert | CrudAction::Update | CrudAction::Create | CrudAction::Delete => true,
+    }
+}
+
+/// Body answered for an unsuccessful platform response. Platforms describing their error format
+/// have their errors normalized to a
+/// [`DownstreamError`](integrationos_domain::connection_model_definition::DownstreamError),
+/// others have them passed through.
+pub fn downstream_error_body(
+    config: &ConnectionModelDefinition,
+    status: StatusCode,
+    bytes: &[u8],
+) -> Result<Value, serde_json::Error> {
+    match &config.error_format {
+        Some(format) => {
+            let error = format.normalize(status.as_u16(), body_to_value(bytes));
+            serde_json::to_value(error)
+        }
+        None => serde_json::from_slice(bytes),
+    }
+}
+
+fn body_to_value(bytes: &[u8]) -> Value {
+    serde_json::from_slice(bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(bytes).into_owned()))
+}
+
+/// Inserts the templated default query parameters that are neither set on the request nor on
+/// the model definition itself, so explicit parameters always take precedence.
+pub fn merge_default_query_params(
+    defaults: &BTreeMap<String, String>,
+    model_query_params: Option<&BTreeMap<String, String>>,
+    query_params: &mut HashMap<String, String>,
+    secret: &Value,
+) -> Result<(), IntegrationOSError> {
+    let renderer = Handlebars::new();
+
+    for (key, value) in defaults {
+        if query_params.contains_key(key)
+            || model_query_params.is_some_and(|params| params.contains_key(key))
+        {
+            continue;
+        }
+
+        let value = renderer
+            .render_template(value, secret)
+            .map_err(|e| InternalError::invalid_argument(&e.to_string(), None))?;
+        query_params.insert(key.clone(), value);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use integrationos_domain::{
+        api_model_config::{ApiModelConfig, AuthMethod, SamplesInput, SchemasInput},
+        connection_model_definition::{ErrorFormat, ShadowConfig, TestConnection, TimeoutConfig},
+        connection_model_schema::{ConnectionModelSchemaBuilder, Mappings},
+        secret::Secret,
+    };
+    use mockito::{Matcher, Server};
+
+    struct MockSecretsClient;
+
+    #[async_trait]
+    impl SecretExt for MockSecretsClient {
+        async fn get(&self, _id: &str, _buildable_id: &str) -> Result<Secret, IntegrationOSError> {
+            Err(InternalError::key_not_found("Secret", None))
+        }
+
+        async fn create(
+            &self,
+            _secret: &Value,
+            _buildable_id: &str,
+        ) -> Result<Secret, IntegrationOSError> {
+            Err(InternalError::key_not_found("Secret", None))
+        }
+
+        async fn ping(&self) -> Result<(), IntegrationOSError> {
+            Ok(())
+        }
+    }
+
+    async fn destination() -> UnifiedDestination {
+        UnifiedDestination::new(
+            DatabaseConfig::default(),
+            100,
+            Arc::new(MockSecretsClient),
+            UnifiedCacheTTLs {
+                connection_cache_ttl_secs: 60,
+                connection_definition_cache_ttl_secs: 60,
+                connection_model_definition_cache_ttl_secs: 60,
+                connection_model_schema_cache_ttl_secs: 60,
+                secret_cache_ttl_secs: 30,
+            },
+            RateLimitConfig::default(),
+        )
+        .await
+        .expect("Failed to create unified destination")
+    }
+
+    fn definition(base_url: String, path: &str) -> ConnectionModelDefinition {
+        ConnectionModelDefinition {
+            id: Id::now(IdPrefix::ConnectionModelDefinition),
+            platform_version: "2023-08-16".to_string(),
+            connection_platform: "stripe".to_string(),
+            connection_definition_id: Id::now(IdPrefix::ConnectionDefinition),
+            title: "Get Customer".to_string(),
+            name: "Get Customer".to_string(),
+            key: "api::stripe::v1::customer::getOne::get_customer".to_string(),
+            model_name: "Customer".to_string(),
+            platform_info: PlatformInfo::Api(ApiModelConfig {
+                base_url,
+                path: path.to_string(),
+                auth_method: AuthMethod::None,
+                headers: None,
+                content: None,
+                query_params: None,
+                schemas: SchemasInput {
+                    headers: None,
+                    query_params: None,
+                    path_params: None,
+                    body: None,
+                },
+                samples: SamplesInput {
+                    headers: None,
+                    query_params: None,
+                    path_params: None,
+                    body: None,
+                },
+                responses: vec![],
+                paths: None,
+            }),
+            action: http::Method::GET,
+            action_name: CrudAction::GetOne,
+            extractor_config: None,
+            test_connection_status: TestConnection::default(),
+            test_connection_payload: None,
+            record_metadata: Default::default(),
+            is_default_crud_mapping: None,
+            mapping: None,
+            supported: true,
+            shadow: None,
+            streaming: false,
+            timeout: None,
+            error_format: None,
+            pagination_limits: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shadow_does_not_alter_primary_response() {
+        let mut server = Server::new_async().await;
+
+        let primary_mock = server
+            .mock("GET", "/v1/customers")
+            .with_status(200)
+            .with_body(r#"{"id": "cus_1", "name": "Jane"}"#)
+            .create_async()
+            .await;
+        let shadow_mock = server
+            .mock("GET", "/v2/customers")
+            .with_status(200)
+            .with_body(r#"{"id": "cus_1", "name": "John"}"#)
+            .create_async()
+            .await;
+
+        let destination = destination().await;
+        let primary = definition(server.url(), "/v1/customers");
+        let shadow = definition(server.url(), "/v2/customers");
+        destination
+            .shadow_definitions_cache
+            .set(shadow.id, &shadow)
+            .await
+            .expect("Failed to cache shadow definition");
+
+        let (res, diff) = destination
+            .execute_model_definition_with_shadow(
+                &primary,
+                Some(shadow.id),
+                HeaderMap::new(),
+                &HashMap::new(),
+                &json!({}),
+                None,
+            )
+            .await
+            .expect("Failed to execute model definition");
+
+        primary_mock.assert_async().await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.json::<Value>().await.expect("Failed to parse response"),
+            json!({"id": "cus_1", "name": "Jane"})
+        );
+
+        let diff = diff
+            .expect("Shadow was not run")
+            .await
+            .expect("Shadow task panicked")
+            .expect("Shadow definition was not resolved");
+        shadow_mock.assert_async().await;
+        assert_eq!(diff.connection_model_definition_id, primary.id);
+        assert_eq!(diff.shadow_connection_model_definition_id, shadow.id);
+        assert_eq!(diff.shadow_status, Some(200));
+        assert_eq!(diff.differences, vec!["$.name"]);
+    }
+
+    #[tokio::test]
+    async fn test_primary_response_does_not_wait_for_the_shadow() {
+        let mut server = Server::new_async().await;
+        let primary_mock = server
+            .mock("GET", "/v1/customers")
+            .with_status(200)
+            .with_body(r#"{"id": "cus_1"}"#)
+            .create_async()
+            .await;
+        // Accepts connections without ever answering them
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind listener");
+
+        let destination = destination().await;
+        let primary = definition(server.url(), "/v1/customers");
+        let shadow = definition(
+            format!("http://{}", listener.local_addr().unwrap()),
+            "/v2/customers",
+        );
+        destination
+            .shadow_definitions_cache
+            .set(shadow.id, &shadow)
+            .await
+            .expect("Failed to cache shadow definition");
+
+        let (res, diff) = tokio::time::timeout(
+            Duration::from_secs(1),
+            destination.execute_model_definition_with_shadow(
+                &primary,
+                Some(shadow.id),
+                HeaderMap::new(),
+                &HashMap::new(),
+                &json!({}),
+                None,
+            ),
+        )
+        .await
+        .expect("The primary response waited for the shadow one")
+        .expect("Failed to execute model definition");
+
+        primary_mock.assert_async().await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let diff = diff.expect("Shadow was not run");
+        assert!(!diff.is_finished());
+        diff.abort();
+    }
+
+    #[tokio::test]
+    async fn test_writes_are_not_shadowed() {
+        let destination = destination().await;
+        let mut config = definition("http://localhost".to_string(), "/v1/customers");
+        config.action_name = CrudAction::Create;
+        config.action = http::Method::POST;
+        config.shadow = Some(ShadowConfig {
+            connection_model_definition_id: Id::now(IdPrefix::ConnectionModelDefinition),
+            sample_rate: 1.0,
+        });
+
+        assert!(destination.sampled_shadow(&config).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_shadow_is_compared_on_the_common_model_output() {
+        let mut server = Server::new_async().await;
+        let primary_mock = server
+            .mock("GET", "/v1/customers")
+            .with_status(200)
+            .with_body(r#"{"data": {"id": "cus_1", "name": "Jane"}}"#)
+            .create_async()
+            .await;
+        let shadow_mock = server
+            .mock("GET", "/v2/customers")
+            .with_status(200)
+            .with_body(r#"{"id": "cus_1", "name": "Jane", "created": 1700000000}"#)
+            .create_async()
+            .await;
+
+        let destination = destination().await;
+        let mut primary = definition(server.url(), "/v1/customers");
+        let PlatformInfo::Api(api_config) = &mut primary.platform_info;
+        api_config.paths = Some(ModelPaths {
+            request: None,
+            response: Some(ResponseModelPaths {
+                object: Some("$.body.data".to_string()),
+                id: None,
+                cursor: None,
+            }),
+        });
+        let shadow = definition(server.url(), "/v2/customers");
+        destination
+            .shadow_definitions_cache
+            .set(shadow.id, &shadow)
+            .await
+            .expect("Failed to cache shadow definition");
+
+        let mapping = ShadowMapping {
+            schema: ConnectionModelSchema {
+                mapping: Some(Mappings {
+                    from_common_model: "function mapFromCommonModel(data) { return data; }"
+                        .to_string(),
+                    to_common_model:
+                        "function mapToCommonModel(data) { return { id: data.id, name: data.name }; }"
+                            .to_string(),
+                    common_model_name: "Customers".to_string(),
+                    common_model_id: Id::now(IdPrefix::CommonModel),
+                    unmapped_fields: Default::default(),
+                }),
+                ..ConnectionModelSchema::new(ConnectionModelSchemaBuilder {
+                    platform_id: Id::now(IdPrefix::Platform),
+                    platform_page_id: Id::now(IdPrefix::PlatformPage),
+                    connection_platform: "stripe".to_string(),
+                    connection_definition_id: primary.connection_definition_id,
+                    platform_version: "2023-08-16".to_string(),
+                    model_name: "Customer".to_string(),
+                    sample: json!({}),
+                    schema: Default::default(),
+                    paths: None,
+                    mapping: None,
+                    drift_policy: Default::default(),
+                })
+            },
+            namespace: "shadow_test_mapToCommonModel".to_string(),
+        };
+
+        let primary_res = destination
+            .execute_model_definition(
+                &primary,
+                HeaderMap::new(),
+                &HashMap::new(),
+                &json!({}),
+                None,
+            )
+            .await
+            .expect("Failed to execute model definition");
+        let (_, diff) = destination
+            .spawn_shadow(
+                &primary,
+                shadow.id,
+                Some(mapping),
+                primary_res,
+                HeaderMap::new(),
+                &HashMap::new(),
+                &json!({}),
+                None,
+            )
+            .await
+            .expect("Failed to spawn shadow");
+
+        let diff = diff
+            .await
+            .expect("Shadow task panicked")
+            .expect("Shadow definition was not resolved");
+        primary_mock.assert_async().await;
+        shadow_mock.assert_async().await;
+        // The raw bodies differ in shape and fields, the mapped ones don't
+        assert!(diff.is_match(), "{:?}", diff.differences);
+    }
+
+    #[tokio::test]
+    async fn test_default_query_params() {
+        let mut server = Server::new_async().await;
+
+        let defaults_mock = server
+            .mock("GET", "/v1/customers")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("api_version".into(), "2023-10".into()),
+                Matcher::UrlEncoded("account".into(), "acct_1".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"id": "cus_1"}"#)
+            .create_async()
+            .await;
+        let override_mock = server
+            .mock("GET", "/v1/customers")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("api_version".into(), "2024-01".into()),
+                Matcher::UrlEncoded("account".into(), "acct_1".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"id": "cus_2"}"#)
+            .create_async()
+            .await;
+
+        let destination = destination().await;
+        let config = definition(server.url(), "/v1/customers");
+        let secret = json!({ "accountId": "acct_1" });
+        let defaults = BTreeMap::from([
+            ("api_version".to_string(), "2023-10".to_string()),
+            ("account".to_string(), "{{accountId}}".to_string()),
+        ]);
+
+        let mut query_params = HashMap::new();
+        merge_default_query_params(&defaults, None, &mut query_params, &secret)
+            .expect("Failed to merge default query params");
+        let res = destination
+            .execute_model_definition(&config, HeaderMap::new(), &query_params, &secret, None)
+            .await
+            .expect("Failed to execute model definition");
+        assert_eq!(res.status(), StatusCode::OK);
+        defaults_mock.assert_async().await;
+
+        let mut query_params = HashMap::from([("api_version".to_string(), "2024-01".to_string())]);
+        merge_default_query_params(&defaults, None, &mut query_params, &secret)
+            .expect("Failed to merge default query params");
+        let res = destination
+            .execute_model_definition(&config, HeaderMap::new(), &query_params, &secret, None)
+            .await
+            .expect("Failed to execute model definition");
+        assert_eq!(res.status(), StatusCode::OK);
+        override_mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_default_query_params_do_not_override_model_params() {
+        let defaults = BTreeMap::from([("api_version".to_string(), "2023-10".to_string())]);
+        let model_params = BTreeMap::from([("api_version".to_string(), "2022-01".to_string())]);
+
+        let mut query_params = HashMap::new();
+        merge_default_query_params(
+            &defaults,
+            Some(&model_params),
+            &mut query_params,
+            &json!({}),
+        )
+        .expect("Failed to merge default query params");
+
+        assert!(query_params.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_streaming_body_arrives_intact() {
+        const CHUNK_SIZE: usize = 64 * 1024;
+        const CHUNKS: usize = 64;
+
+        let chunk = |i: usize| vec![b'a' + (i % 26) as u8; CHUNK_SIZE];
+        let expected = (0..CHUNKS).flat_map(chunk).collect::<Vec<u8>>();
+
+        let mut server = Server::new_async().await;
+        let upload_mock = server
+            .mock("POST", "/v1/files")
+            .match_header("transfer-encoding", "chunked")
+            .match_body(expected)
+            .with_status(201)
+            .create_async()
+            .await;
+
+        let destination = destination().await;
+        let mut config = definition(server.url(), "/v1/files");
+        config.action = http::Method::POST;
+        config.streaming = true;
+
+        let stream = futures::stream::iter(
+            (0..CHUNKS).map(move |i| Ok::<_, std::io::Error>(Bytes::from(chunk(i)))),
+        );
+
+        let res = destination
+            .execute_model_definition_with_body(
+                &config,
+                HeaderMap::new(),
+                &HashMap::new(),
+                &json!({}),
+                Some(reqwest::Body::wrap_stream(stream)),
+            )
+            .await
+            .expect("Failed to execute model definition");
+
+        assert_eq!(res.status(), StatusCode::CREATED);
+        upload_mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_response_over_size_limit_is_aborted() {
+        let mut server = Server::new_async().await;
+
+        let sized_mock = server
+            .mock("GET", "/v1/customers")
+            .with_status(200)
+            .with_body(vec![b'a'; 4096])
+            .create_async()
+            .await;
+        let chunked_mock = server
+            .mock("GET", "/v1/files")
+            .with_status(200)
+            .with_chunked_body(|w| {
+                for _ in 0..4 {
+                    w.write_all(&[b'a'; 1024])?;
+                }
+                Ok(())
+            })
+            .create_async()
+            .await;
+
+        let destination = destination().await.with_max_response_size(1024);
+
+        for path in ["/v1/customers", "/v1/files"] {
+            let config = definition(server.url(), path);
+            let res = destination
+                .execute_model_definition(
+                    &config,
+                    HeaderMap::new(),
+                    &HashMap::new(),
+                    &json!({}),
+                    None,
+                )
+                .await
+                .expect("Failed to execute model definition");
+
+            let err = destination
+                .read_response(res)
+                .await
+                .expect_err("Over-limit response should be aborted");
+            assert_eq!(StatusCode::from(&err), StatusCode::FAILED_DEPENDENCY);
+            assert!(err
+                .to_string()
+                .contains("exceeded the maximum size of 1024 bytes"));
+        }
+
+        sized_mock.assert_async().await;
+        chunked_mock.assert_async().await;
+
+        let config = definition(server.url(), "/v1/customers");
+        let destination = destination.with_max_response_size(4096);
+        let res = destination
+            .execute_model_definition(&config, HeaderMap::new(), &HashMap::new(), &json!({}), None)
+            .await
+            .expect("Failed to execute model definition");
+        assert_eq!(
+            destination
+                .read_response(res)
+                .await
+                .expect("Response within the limit should be read")
+                .len(),
+            4096
+        );
+    }
+
+    #[tokio::test]
+    async fn test_without_shadow_no_diff_is_recorded() {
+        let mut server = Server::new_async().await;
+
+        let primary_mock = server
+            .mock("GET", "/v1/customers")
+            .with_status(200)
+            .with_body(r#"{"id": "cus_1"}"#)
+            .create_async()
+            .await;
+
+        let destination = destination().await;
+        let primary = definition(server.url(), "/v1/customers");
+
+        let (res, diff) = destination
+            .execute_model_definition_with_shadow(
+                &primary,
+                None,
+                HeaderMap::new(),
+                &HashMap::new(),
+                &json!({}),
+                None,
+            )
+            .await
+            .expect("Failed to execute model definition");
+
+        primary_mock.assert_async().await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(diff.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_fallback_is_returned_when_downstream_times_out() {
+        // Accepts connections without ever answering them
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind listener");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        let destination = destination().await;
+        let mut config = definition(base_url, "/v1/customers");
+        config.action_name = CrudAction::GetMany;
+        config.timeout = Some(TimeoutConfig {
+            timeout_millis: 100,
+            fallback: TimeoutFallback::Empty,
+        });
+
+        let executed = destination
+            .execute_model_definition_with_timeout(
+                &config,
+                HeaderMap::new(),
+                &HashMap::new(),
+                &json!({}),
+                None,
+            )
+            .await
+            .expect("Timing out should not fail the execution");
+        assert!(executed.is_none());
+
+        let key = cached_response_key(Id::now(IdPrefix::Connection), "customers");
+        let res = destination
+            .timeout_fallback(&config, &key, json!({}))
+            .await
+            .expect("Empty fallback should be returned");
+        assert_eq!(res.response.status(), StatusCode::OK);
+        assert_eq!(res.response.body()["unified"], json!([]));
+        assert_eq!(res.response.body()["meta"]["fallback"], json!("empty"));
+
+        config.timeout = Some(TimeoutConfig {
+            timeout_millis: 100,
+            fallback: TimeoutFallback::LastCached,
+        });
+        let err = destination
+            .timeout_fallback(&config, &key, json!({}))
+            .await
+            .err()
+            .expect("Nothing was cached yet");
+        assert_eq!(StatusCode::from(&err), StatusCode::GATEWAY_TIMEOUT);
+
+        let cached = Response::new(json!({ "unified": [{ "id": "cus_1" }], "meta": {} }));
+        destination
+            .cache_fallback_response(&config, key.clone(), &cached)
+            .await;
+        let res = destination
+            .timeout_fallback(&config, &key, json!({}))
+            .await
+            .expect("Cached fallback should be returned");
+        assert_eq!(res.response.body()["unified"], json!([{ "id": "cus_1" }]));
+        assert_eq!(
+            res.response.body()["meta"]["fallback"],
+            json!("last-cached")
+        );
+
+        config.timeout = Some(TimeoutConfig {
+            timeout_millis: 100,
+            fallback: TimeoutFallback::Error,
+        });
+        let err = destination
+            .timeout_fallback(&config, &key, json!({}))
+            .await
+            .err()
+            .expect("Error fallback should fail the call");
+        assert_eq!(StatusCode::from(&err), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    fn cached_response_key(connection_id: Id, common_model: &str) -> CachedResponseKey {
+        CachedResponseKey {
+            connection_id,
+            common_model: common_model.into(),
+            request: "request".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_successful_write_invalidates_cached_reads_of_its_model() {
+        let destination = destination().await;
+        let connection_id = Id::now(IdPrefix::Connection);
+        let timeout = Some(TimeoutConfig {
+            timeout_millis: 100,
+            fallback: TimeoutFallback::LastCached,
+        });
+
+        let mut read = definition("http://localhost".to_string(), "/v1/customers");
+        read.action_name = CrudAction::GetMany;
+        read.timeout = timeout;
+        let mut write = read.clone();
+        write.id = Id::now(IdPrefix::ConnectionModelDefinition);
+        write.action = http::Method::POST;
+        write.action_name = CrudAction::Create;
+
+        let customers = cached_response_key(connection_id, "customers");
+        let invoices = cached_response_key(connection_id, "invoices");
+        let other_connection = cached_response_key(Id::now(IdPrefix::Connection), "customers");
+        let cached = Response::new(json!({ "unified": [{ "id": "cus_1" }] }));
+        for key in [&customers, &invoices, &other_connection] {
+            destination
+                .on_unified_response(&read, key.clone(), &cached)
+                .await;
+            assert!(destination
+                .timeout_fallback(&read, key, json!({}))
+                .await
+                .is_ok());
+        }
+
+        // A failed write changes nothing, the cached read is still answered
+        let mut failed = Response::new(json!({}));
+        *failed.status_mut() = StatusCode::BAD_REQUEST;
+        let write_key = CachedResponseKey {
+            request: "write".to_string(),
+            ..customers.clone()
+        };
+        destination
+            .on_unified_response(&write, write_key.clone(), &failed)
+            .await;
+        assert!(destination
+            .timeout_fallback(&read, &customers, json!({}))
+            .await
+            .is_ok());
+
+        destination
+            .on_unified_response(&write, write_key, &Response::new(json!({})))
+            .await;
+        let err = destination
+            .timeout_fallback(&read, &customers, json!({}))
+            .await
+            .err()
+            .expect("The cached read should have been invalidated by the write");
+        assert_eq!(StatusCode::from(&err), StatusCode::GATEWAY_TIMEOUT);
+
+        // Reads of other models and of other connections are still cached
+        for key in [&invoices, &other_connection] {
+            assert!(destination
+                .timeout_fallback(&read, key, json!({}))
+                .await
+                .is_ok());
+        }
+
+        // The next read is cached again
+        destination
+            .on_unified_response(&read, customers.clone(), &cached)
+            .await;
+        assert!(destination
+            .timeout_fallback(&read, &customers, json!({}))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_provider_error_is_normalized_to_common_shape() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/v1/customers")
+            .with_status(402)
+            .with_body(
+                r#"{"error": {"type": "card_error", "code": "card_declined", "message": "Your card was declined."}}"#,
+            )
+            .expect(2)
+            .create_async()
+            .await;
+
+        let destination = destination().await;
+        let mut config = definition(server.url(), "/v1/customers");
+        config.error_format = Some(ErrorFormat {
+            code_path: Some("$.body.error.code".to_string()),
+            message_path: Some("$.body.error.message".to_string()),
+        });
+
+        let res = destination
+            .execute_model_definition(&config, HeaderMap::new(), &HashMap::new(), &json!({}), None)
+            .await
+            .expect("Failed to execute model definition");
+        let status = res.status();
+        let bytes = res.bytes().await.expect("Failed to read body");
+
+        let body = downstream_error_body(&config, status, &bytes).expect("Failed to normalize");
+        assert_eq!(
+            body,
+            json!({
+                "status": 402,
+                "code": "card_declined",
+                "message": "Your card was declined.",
+                "raw": {
+                    "error": {
+                        "type": "card_error",
+                        "code": "card_declined",
+                        "message": "Your card was declined."
+                    }
+                }
+            })
+        );
+
+        // Without an error format the body of the platform is passed through
+        config.error_format = None;
+        let res = destination
+            .execute_model_definition(&config, HeaderMap::new(), &HashMap::new(), &json!({}), None)
+            .await
+            .expect("Failed to execute model definition");
+        let status = res.status();
+        let bytes = res.bytes().await.expect("Failed to read body");
+        let body = downstream_error_body(&config, status, &bytes).expect("Failed to read error");
+        assert_eq!(body["error"]["code"], json!("card_declined"));
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_definition_retry_policy_overrides_default() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/v1/customers")
+            .with_status(503)
+            .expect(1 + 3)
+            .create_async()
+            .await;
+
+        let destination = destination()
+            .await
+            .with_retry_policy(DownstreamRetryPolicy {
+                max_attempts: 1,
+                ..Default::default()
+            });
+        let config = definition(server.url(), "/v1/customers");
+        let mut connection_definition = ConnectionDefinition::new(
+            "Stripe".to_string(),
+            "Stripe".to_string(),
+            "stripe".to_string(),
+            "1.0.0".to_string(),
+            "Payments".to_string(),
+            "https://stripe.com/logo.png".to_string(),
+            vec![],
+        );
+        let execute = |retry_policy: DownstreamRetryPolicy| {
+            let destination = &destination;
+            let config = &config;
+            async move {
+                destination
+                    .execute_model_definition_with_retries(
+                        config,
+                        HeaderMap::new(),
+                        &HashMap::new(),
+                        &json!({}),
+                        None,
+                        &retry_policy,
+                    )
+                    .await
+                    .expect("Failed to execute model definition")
+                    .expect("Execution should not time out")
+            }
+        };
+
+        // The default policy makes a single attempt
+        let res = execute(destination.retry_policy_for(&connection_definition).clone()).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        connection_definition.retry_policy = Some(DownstreamRetryPolicy {
+            max_attempts: 3,
+            initial_backoff_millis: 1,
+            backoff_multiplier: 2,
+            retryable_status_codes: vec![503],
+        });
+        let res = execute(destination.retry_policy_for(&connection_definition).clone()).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_retry_waits_as_long_as_retry_after_asks() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/v1/customers")
+            .with_status(429)
+            .with_header("retry-after", "1")
+            .expect(2)
+            .create_async()
+            .await;
+
+        let config = definition(server.url(), "/v1/customers");
+        let started = std::time::Instant::now();
+        let res = destination()
+            .await
+            .execute_model_definition_with_retries(
+                &config,
+                HeaderMap::new(),
+                &HashMap::new(),
+                &json!({}),
+                None,
+                &DownstreamRetryPolicy {
+                    max_attempts: 2,
+                    initial_backoff_millis: 1,
+                    backoff_multiplier: 1,
+                    retryable_status_codes: vec![429],
+                },
+            )
+            .await
+            .expect("Failed to execute model definition")
+            .expect("Execution should not time out");
+
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_longer_than_allowed_is_answered_right_away() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/v1/customers")
+            .with_status(429)
+            .with_header("retry-after", "3600")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = definition(server.url(), "/v1/customers");
+        let started = std::time::Instant::now();
+        let res = destination()
+            .await
+            .with_max_retry_after(Duration::from_secs(10))
+            .execute_model_definition_with_retries(
+                &config,
+                HeaderMap::new(),
+                &HashMap::new(),
+                &json!({}),
+                None,
+                &DownstreamRetryPolicy {
+                    max_attempts: 3,
+                    initial_backoff_millis: 1,
+                    backoff_multiplier: 1,
+                    retryable_status_codes: vec![429],
+                },
+            )
+            .await
+            .expect("Failed to execute model definition")
+            .expect("Execution should not time out");
+
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_only_allowlisted_headers_are_forwarded() {
+        let mut headers = HeaderMap::new();
+        headers.insert("accept-language", HeaderValue::from_static("fr-CA"));
+        headers.insert("cookie", HeaderValue::from_static("session=secret"));
+        headers.insert("x-forwarded-for", HeaderValue::from_static("10.0.0.1"));
+        headers.insert(
+            PASSTHROUGH_HEADERS,
+            HeaderValue::from_static("x-custom=value"),
+        );
+
+        let destination = destination().await;
+        let mut unfiltered = headers.clone();
+        destination.filter_forwarded_headers(&mut unfiltered);
+        assert_eq!(unfiltered, headers);
+
+        let destination =
+            destination.with_forwarded_headers([HeaderName::from_static("accept-language")]);
+        destination.filter_forwarded_headers(&mut headers);
+
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers.get("accept-language").unwrap(), "fr-CA");
+        assert!(headers.contains_key(PASSTHROUGH_HEADERS));
+        assert!(!headers.contains_key("cookie"));
+        assert!(!headers.contains_key("x-forwarded-for"));
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/v1/customers")
+            .match_header("accept-language", "fr-CA")
+            .match_header("cookie", Matcher::Missing)
+            .with_status(200)
+            .with_body(r#"{"id": "cus_1"}"#)
+            .create_async()
+            .await;
+
+        let res = destination
+            .execute_model_definition(
+                &definition(server.url(), "/v1/customers"),
+                headers,
+                &HashMap::new(),
+                &json!({}),
+                None,
+            )
+            .await
+            .expect("Failed to execute model definition");
+
+        mock.assert_async().await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_calls_to_same_host_reuse_pooled_connection() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind listener");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let accepted = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        // Answers every request of a connection while keeping it open
+        let counter = accepted.clone();
+        tokio::spawn(async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+            while let Ok((mut socket, _)) = listener.accept().await {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = [0; 4096];
+                    while let Ok(read) = socket.read(&mut buf).await {
+                        if read == 0 {
+                            break;
+                        }
+                        let response = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n{}";
+                        if socket.write_all(response.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        let destination = destination()
+            .await
+            .with_connection_pool(Duration::from_secs(60), 8)
+            .expect("Failed to configure connection pool");
+        let config = definition(base_url, "/v1/customers");
+
+        for _ in 0..3 {
+            let res = destination
+                .execute_model_definition(
+                    &config,
+                    HeaderMap::new(),
+                    &HashMap::new(),
+                    &json!({}),
+                    None,
+                )
+                .await
+                .expect("Failed to execute model definition");
+            assert_eq!(res.status(), StatusCode::OK);
+            // Reading the body to its end hands the connection back to the pool
+            res.bytes().await.expect("Failed to read body");
+        }
+
+        assert_eq!(accepted.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    /// Base URL of a server accepting every connection without ever answering, along with the
+    /// count of the connections it accepted
+    async fn stalled_server() -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let accepted = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let counter = accepted.clone();
+        tokio::spawn(async move {
+            let mut sockets = vec![];
+            while let Ok((socket, _)) = listener.accept().await {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                sockets.push(socket);
+            }
+        });
+
+        (base_url, accepted)
+    }
+
+    async fn execute_stalled(
+        config: &ConnectionModelDefinition,
+    ) -> Result<Option<reqwest::Response>, IntegrationOSError> {
+        destination()
+            .await
+            .with_call_timeout(Duration::from_millis(100))
+            .execute_model_definition_with_retries(
+                config,
+                HeaderMap::new(),
+                &HashMap::new(),
+                &json!({}),
+                None,
+                &DownstreamRetryPolicy {
+                    max_attempts: 2,
+                    initial_backoff_millis: 1,
+                    backoff_multiplier: 1,
+                    retryable_status_codes: vec![504],
+                },
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_stalled_call_is_cancelled_and_retried() {
+        let (base_url, accepted) = stalled_server().await;
+        let config = definition(base_url, "/v1/customers");
+
+        let err = execute_stalled(&config)
+            .await
+            .expect_err("The stalled call should time out");
+
+        assert!(is_call_timeout(&err));
+        assert_eq!(StatusCode::from(err), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(accepted.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stalled_write_is_cancelled_but_not_retried() {
+        let (base_url, accepted) = stalled_server().await;
+        let mut config = definition(base_url, "/v1/customers");
+        config.action_name = CrudAction::Create;
+        config.action = http::Method::POST;
+
+        let err = execute_stalled(&config)
+            .await
+            .expect_err("The stalled call should time out");
+
+        assert!(is_call_timeout(&err));
+        assert_eq!(accepted.load(std::sync::atomic::Ordering::SeqCst), 1);
     }
 }